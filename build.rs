@@ -0,0 +1,13 @@
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    #[cfg(feature = "grpc")]
+    {
+        // A vendored protoc means `--features grpc` builds without a system install
+        let protoc = protoc_bin_vendored::protoc_bin_path()?;
+        // SAFETY: build scripts are single-threaded
+        unsafe { std::env::set_var("PROTOC", protoc) };
+        tonic_prost_build::configure()
+            .build_client(true)
+            .compile_protos(&["proto/pulseprint.proto"], &["proto"])?;
+    }
+    Ok(())
+}
@@ -0,0 +1,433 @@
+use crate::config::{AlertSettings, HmsAction, HmsSeverity};
+use crate::filament;
+use crate::jobs::{self, FilamentChange, InspectionResult};
+use crate::messages::{MergedState, PrintState};
+use std::collections::{BTreeMap, BTreeSet};
+use std::mem::Discriminant;
+use std::path::Path;
+use std::time::Instant;
+
+#[cfg(test)]
+mod tests;
+
+/// One condition that crossed its configured threshold
+#[derive(Debug, Clone, PartialEq)]
+pub struct Alert {
+    /// Stable identifier of the rule, e.g. `ams_humidity`
+    pub kind: &'static str,
+    pub message: String,
+}
+
+impl Alert {
+    /// `high` for alerts that call for someone to check on the printer right away
+    pub fn priority(&self) -> &'static str {
+        match self.kind {
+            "spaghetti" | "first_layer_defect" | "hms_serious" | "hms_fatal" => "high",
+            _ => "normal",
+        }
+    }
+}
+
+/// Evaluates the alert rules against the merged state. Each alert fires once when its
+/// condition starts to hold and is re-armed once the condition clears. After firing, a rule
+/// stays quiet for its quiet period, so a flapping condition alerts once.
+#[derive(Debug)]
+pub struct AlertTracker {
+    settings: AlertSettings,
+    /// AMS units currently above the humidity threshold
+    humid_units: BTreeSet<String>,
+    door_open: bool,
+    sdcard_problem: bool,
+    /// The WiFi signal was below the threshold at the previous update
+    wifi_weak: bool,
+    /// Label of the active tray while it is below the filament threshold
+    low_tray: Option<String>,
+    /// Kind of filament change at the previous update
+    filament_change: Option<Discriminant<FilamentChange>>,
+    /// Summary of the firmware update offered at the previous update
+    firmware_update: Option<String>,
+    /// Materials of the running job, when known from its project or the command line
+    job_materials: Vec<String>,
+    /// No status update has arrived since the connection last failed
+    disconnected: bool,
+    /// Remaining minutes of the running print at the previous update
+    remaining_minutes: Option<u32>,
+    /// Camera codes at the previous update
+    camera_codes: Vec<String>,
+    /// The previous update was part of the first-layer inspection
+    inspecting: bool,
+    /// HMS codes at the previous update
+    hms_codes: BTreeSet<String>,
+    /// When each rule last fired, per subject such as the AMS unit
+    last_fired: BTreeMap<(&'static str, String), Instant>,
+}
+
+impl AlertTracker {
+    pub fn new(settings: AlertSettings) -> Self {
+        Self {
+            settings,
+            humid_units: BTreeSet::new(),
+            door_open: false,
+            sdcard_problem: false,
+            wifi_weak: false,
+            low_tray: None,
+            filament_change: None,
+            firmware_update: None,
+            job_materials: Vec::new(),
+            disconnected: false,
+            remaining_minutes: None,
+            camera_codes: Vec::new(),
+            inspecting: false,
+            hms_codes: BTreeSet::new(),
+            last_fired: BTreeMap::new(),
+        }
+    }
+
+    pub fn settings(&self) -> &AlertSettings {
+        &self.settings
+    }
+
+    /// Materials the running job prints with, for the door alert; without them the
+    /// material of the active tray is used
+    pub fn set_job_materials(&mut self, materials: Vec<String>) {
+        self.job_materials = materials;
+    }
+
+    pub fn observe(&mut self, state: &MergedState) -> Vec<Alert> {
+        self.observe_at(state, Instant::now())
+    }
+
+    fn observe_at(&mut self, state: &MergedState, now: Instant) -> Vec<Alert> {
+        let mut alerts = Vec::new();
+        self.disconnected = false;
+
+        if let Some(limit) = self.settings.ams_humidity_above
+            && let Some(units) = state.print.ams.as_ref().and_then(|ams| ams.units.as_ref())
+        {
+            for unit in units {
+                let Some(humidity) = unit.humidity_percent else {
+                    continue;
+                };
+                let label = unit.label();
+                if humidity <= limit {
+                    self.humid_units.remove(&label);
+                } else if self.humid_units.insert(label.clone())
+                    && self.unmuted("ams_humidity", &label, now)
+                {
+                    alerts.push(Alert {
+                        kind: "ams_humidity",
+                        message: format!("{label} humidity is {humidity}%, above {limit}%"),
+                    });
+                }
+            }
+        }
+
+        let status = state.status();
+        if let Some(flags) = state.print.home_flags() {
+            let opened = rising(&mut self.door_open, flags.door_open);
+            if self.settings.door_open && opened && self.unmuted("door_open", "", now) {
+                alerts.push(Alert {
+                    kind: "door_open",
+                    message: "Enclosure door opened".to_string(),
+                });
+            }
+            if opened
+                && status.state == PrintState::Printing
+                && let Some(material) = self.enclosed_material(state)
+                && self.unmuted("door_open_during_print", "", now)
+            {
+                alerts.push(Alert {
+                    kind: "door_open_during_print",
+                    message: format!("Enclosure door opened while printing {material}"),
+                });
+            }
+            let problem = flags.sdcard_problem();
+            if self.settings.sdcard_missing
+                && rising(&mut self.sdcard_problem, problem.is_some())
+                && self.unmuted("sdcard", "", now)
+            {
+                alerts.push(Alert {
+                    kind: "sdcard",
+                    message: format!("SD card is {}", problem.unwrap_or_default()),
+                });
+            }
+        }
+
+        // Only a drop across the threshold counts, so joining a print in its last minutes
+        // does not alert
+        let remaining = state
+            .print
+            .mc_remaining_time
+            .filter(|_| status.state == PrintState::Printing);
+        let previous = std::mem::replace(&mut self.remaining_minutes, remaining);
+        if let Some(limit) = self.settings.almost_done_minutes
+            && let (Some(previous), Some(remaining)) = (previous, remaining)
+            && previous >= limit
+            && remaining < limit
+            && self.unmuted("almost_done", "", now)
+        {
+            let name = state.print.subtask_name.as_deref().unwrap_or("Print");
+            alerts.push(Alert {
+                kind: "almost_done",
+                message: format!("{name} finishing in {remaining} minutes"),
+            });
+        }
+
+        if let Some(limit) = self.settings.wifi_signal_below
+            && let Some(dbm) = state.print.wifi_dbm()
+            && rising(&mut self.wifi_weak, dbm < limit)
+            && self.unmuted("wifi_weak", "", now)
+        {
+            alerts.push(Alert {
+                kind: "wifi_weak",
+                message: format!("WiFi signal is {dbm} dBm, below {limit} dBm"),
+            });
+        }
+
+        let low_tray = self
+            .settings
+            .filament_low_percent
+            .and_then(|limit| low_active_tray(state, limit));
+        if let Some((label, remain)) = &low_tray
+            && self.low_tray.as_ref() != Some(label)
+            && self.unmuted("filament_low", label, now)
+        {
+            let limit = self.settings.filament_low_percent.unwrap_or_default();
+            let spool = match label.as_str() {
+                filament::EXTERNAL_TRAY => "External spool".to_string(),
+                label => format!("Tray {label}"),
+            };
+            alerts.push(Alert {
+                kind: "filament_low",
+                message: format!("{spool} has {remain}% filament left, below {limit}%"),
+            });
+        }
+        self.low_tray = low_tray.map(|(label, _)| label);
+
+        // A runout pause right after an AMS switch is a new change, the slots moving on
+        // during one is not
+        let change = jobs::filament_change(state);
+        let kind = change.as_ref().map(std::mem::discriminant);
+        let previous = std::mem::replace(&mut self.filament_change, kind);
+        if self.settings.filament_change
+            && let Some(change) = change
+            && kind != previous
+            && self.unmuted("filament_change", "", now)
+        {
+            alerts.push(Alert {
+                kind: "filament_change",
+                message: change.describe(),
+            });
+        }
+
+        // Reported once per offered version
+        let update = state.print.firmware_update().map(|update| update.summary());
+        if self.settings.firmware_update
+            && let Some(summary) = &update
+            && self.firmware_update.as_ref() != Some(summary)
+            && self.unmuted("firmware_update", summary, now)
+        {
+            alerts.push(Alert {
+                kind: "firmware_update",
+                message: format!("Firmware update available: {summary}"),
+            });
+        }
+        self.firmware_update = update;
+
+        // Codes raised by the first-layer inspection are reported with its result instead
+        let codes = jobs::camera_codes(state);
+        let inspecting = jobs::print_stage(state) == Some(jobs::STAGE_FIRST_LAYER_INSPECTION);
+        let was_inspecting = std::mem::replace(&mut self.inspecting, inspecting);
+        let previous = std::mem::replace(&mut self.camera_codes, codes.clone());
+        let new_codes: Vec<String> = codes
+            .into_iter()
+            .filter(|code| !previous.contains(code))
+            .collect();
+        if self.settings.spaghetti
+            && !new_codes.is_empty()
+            && !inspecting
+            && !was_inspecting
+            // The printer may pause itself in the same update that reports the failure
+            && matches!(status.state, PrintState::Printing | PrintState::Paused)
+            && self.unmuted("spaghetti", "", now)
+        {
+            alerts.push(Alert {
+                kind: "spaghetti",
+                message: format!(
+                    "Camera detected a possible print failure such as spaghetti ({})",
+                    new_codes.join(", ")
+                ),
+            });
+        }
+
+        alerts
+    }
+
+    /// The first material of the running job listed in `door_open_materials`
+    fn enclosed_material(&self, state: &MergedState) -> Option<String> {
+        let active_tray = state
+            .print
+            .active_tray()
+            .and_then(|tray| tray.tray_type.clone());
+        let materials = if self.job_materials.is_empty() {
+            active_tray.into_iter().collect()
+        } else {
+            self.job_materials.clone()
+        };
+        materials.into_iter().find(|material| {
+            // Variants such as `ABS-GF` or `ASA Aero` need the enclosure as much as the base
+            let base = material.split(['-', ' ']).next().unwrap_or_default();
+            self.settings.door_open_materials.iter().any(|listed| {
+                listed.eq_ignore_ascii_case(material) || listed.eq_ignore_ascii_case(base)
+            })
+        })
+    }
+
+    /// Reports a failed or dropped connection; failures before the next status update
+    /// count as the same outage
+    pub fn connection_lost(&mut self, error: &str) -> Option<Alert> {
+        self.connection_lost_at(error, Instant::now())
+    }
+
+    fn connection_lost_at(&mut self, error: &str, now: Instant) -> Option<Alert> {
+        if !self.settings.connection_lost || !rising(&mut self.disconnected, true) {
+            return None;
+        }
+        self.unmuted("connection_lost", "", now).then(|| Alert {
+            kind: "connection_lost",
+            message: format!("Connection lost: {error}"),
+        })
+    }
+
+    /// HMS errors reported since the previous update, with the action configured for their
+    /// severity
+    pub fn hms(&mut self, state: &MergedState) -> Vec<(HmsAction, Alert)> {
+        self.hms_at(state, Instant::now())
+    }
+
+    fn hms_at(&mut self, state: &MergedState, now: Instant) -> Vec<(HmsAction, Alert)> {
+        let entries = jobs::hms_entries(state.print.extra.get("hms"));
+        let codes = entries.iter().map(|entry| entry.code.clone()).collect();
+        let previous = std::mem::replace(&mut self.hms_codes, codes);
+
+        let mut raised = Vec::new();
+        for entry in entries {
+            if previous.contains(&entry.code) {
+                continue;
+            }
+            let Some(severity) = entry.severity else {
+                continue;
+            };
+            let Some(action) = self.settings.hms.action(severity) else {
+                continue;
+            };
+            let kind = match severity {
+                HmsSeverity::Info => "hms_info",
+                HmsSeverity::Common => "hms_common",
+                HmsSeverity::Serious => "hms_serious",
+                HmsSeverity::Fatal => "hms_fatal",
+            };
+            if self.unmuted(kind, &entry.code, now) {
+                let message = format!("HMS {} ({})", entry.code, severity.name());
+                raised.push((action, Alert { kind, message }));
+            }
+        }
+        raised
+    }
+
+    /// Raises the first-layer defect alert for an inspection that found defects
+    pub fn inspection(&mut self, result: &InspectionResult) -> Option<Alert> {
+        self.inspection_at(result, Instant::now())
+    }
+
+    fn inspection_at(&mut self, result: &InspectionResult, now: Instant) -> Option<Alert> {
+        let InspectionResult::DefectDetected { codes } = result else {
+            return None;
+        };
+        if !self.settings.first_layer_defect {
+            return None;
+        }
+        self.unmuted("first_layer_defect", "", now).then(|| Alert {
+            kind: "first_layer_defect",
+            message: format!(
+                "First layer inspection detected defects ({})",
+                codes.join(", ")
+            ),
+        })
+    }
+
+    /// Whether `rule` may fire for `subject` now, outside the quiet period of its last alert
+    fn unmuted(&mut self, rule: &'static str, subject: &str, now: Instant) -> bool {
+        let key = (rule, subject.to_string());
+        if let Some(last) = self.last_fired.get(&key)
+            && now.duration_since(*last) < self.settings.quiet_period(rule)
+        {
+            return false;
+        }
+        self.last_fired.insert(key, now);
+        true
+    }
+}
+
+/// Stores `now` in `active` and reports whether the condition just started
+fn rising(active: &mut bool, now: bool) -> bool {
+    let started = now && !*active;
+    *active = now;
+    started
+}
+
+/// Label and remaining percentage of the tray feeding the running print, when below `limit`
+fn low_active_tray(state: &MergedState, limit: u32) -> Option<(String, u32)> {
+    if !matches!(
+        state.status().state,
+        PrintState::Printing | PrintState::Paused
+    ) {
+        return None;
+    }
+    // Spools without an RFID tag report -1
+    let remain = u32::try_from(state.print.active_tray()?.remain?).ok()?;
+    if remain >= limit {
+        return None;
+    }
+    Some((filament::active_tray_label(&state.print)?, remain))
+}
+
+/// Runs the configured alert command in the background; its exit status is not checked.
+/// `details` are extra environment variables describing the printer.
+pub fn run_command(
+    command: &str,
+    printer: &str,
+    details: &[(String, String)],
+    alert: &Alert,
+    snapshot: Option<&Path>,
+) -> std::io::Result<()> {
+    let mut process = shell_command(command);
+    process
+        .env("PULSEPRINT_ALERT", alert.kind)
+        .env("PULSEPRINT_PRINTER", printer)
+        .env("PULSEPRINT_MESSAGE", &alert.message)
+        .env("PULSEPRINT_PRIORITY", alert.priority())
+        .envs(details.iter().map(|(key, value)| (key, value)))
+        .stdin(std::process::Stdio::null());
+    if let Some(snapshot) = snapshot {
+        process.env("PULSEPRINT_SNAPSHOT", snapshot);
+    }
+    let mut child = process.spawn()?;
+    tokio::spawn(async move {
+        let _ = child.wait().await;
+    });
+    Ok(())
+}
+
+/// `command` run through the platform shell
+pub fn shell_command(command: &str) -> tokio::process::Command {
+    if cfg!(windows) {
+        let mut process = tokio::process::Command::new("cmd");
+        process.arg("/C").arg(command);
+        process
+    } else {
+        let mut process = tokio::process::Command::new("sh");
+        process.arg("-c").arg(command);
+        process
+    }
+}
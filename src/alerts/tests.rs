@@ -0,0 +1,550 @@
+use super::*;
+use crate::messages::DeviceMessage;
+
+fn state_with_humidity(humidity: &[(&str, u32)]) -> MergedState {
+    let units: Vec<String> = humidity
+        .iter()
+        .map(|(id, percent)| {
+            format!(r#"{{"id": "{id}", "humidity_raw": "{percent}", "tray": []}}"#)
+        })
+        .collect();
+    let payload = format!(
+        r#"{{"print": {{"ams": {{"ams": [{}]}}}}}}"#,
+        units.join(",")
+    );
+    let mut state = MergedState::default();
+    state.update(&DeviceMessage::parse(&payload).unwrap());
+    state
+}
+
+fn humidity_tracker(limit: u32) -> AlertTracker {
+    AlertTracker::new(AlertSettings {
+        ams_humidity_above: Some(limit),
+        quiet_period_secs: 0,
+        ..Default::default()
+    })
+}
+
+#[test]
+fn test_humidity_alert_fires_once_until_cleared() {
+    let mut tracker = humidity_tracker(40);
+
+    assert!(
+        tracker
+            .observe(&state_with_humidity(&[("0", 35)]))
+            .is_empty()
+    );
+
+    let alerts = tracker.observe(&state_with_humidity(&[("0", 45), ("1", 20)]));
+    assert_eq!(
+        alerts,
+        vec![Alert {
+            kind: "ams_humidity",
+            message: "AMS A humidity is 45%, above 40%".to_string(),
+        }]
+    );
+    // Still humid: no repeat
+    assert!(
+        tracker
+            .observe(&state_with_humidity(&[("0", 50)]))
+            .is_empty()
+    );
+
+    // Dried out, then humid again
+    assert!(
+        tracker
+            .observe(&state_with_humidity(&[("0", 40)]))
+            .is_empty()
+    );
+    assert_eq!(tracker.observe(&state_with_humidity(&[("0", 41)])).len(), 1);
+}
+
+#[test]
+fn test_no_alerts_without_threshold_or_readings() {
+    let mut tracker = AlertTracker::new(AlertSettings::default());
+    assert!(
+        tracker
+            .observe(&state_with_humidity(&[("0", 90)]))
+            .is_empty()
+    );
+
+    let mut tracker = humidity_tracker(40);
+    assert!(tracker.observe(&MergedState::default()).is_empty());
+}
+
+fn state_with_home_flag(flag: u32) -> MergedState {
+    let mut state = MergedState::default();
+    let payload = format!(r#"{{"print": {{"home_flag": {flag}}}}}"#);
+    state.update(&DeviceMessage::parse(&payload).unwrap());
+    state
+}
+
+#[test]
+fn test_door_and_sdcard_alerts() {
+    const SDCARD: u32 = 0x100;
+    const DOOR: u32 = 0x0080_0000;
+
+    let mut tracker = AlertTracker::new(AlertSettings {
+        door_open: true,
+        sdcard_missing: true,
+        ..Default::default()
+    });
+    assert!(tracker.observe(&state_with_home_flag(SDCARD)).is_empty());
+
+    let alerts = tracker.observe(&state_with_home_flag(SDCARD | DOOR));
+    assert_eq!(alerts.len(), 1);
+    assert_eq!(alerts[0].kind, "door_open");
+    assert!(
+        tracker
+            .observe(&state_with_home_flag(SDCARD | DOOR))
+            .is_empty()
+    );
+
+    let alerts = tracker.observe(&state_with_home_flag(0));
+    assert_eq!(
+        alerts,
+        vec![Alert {
+            kind: "sdcard",
+            message: "SD card is missing".to_string(),
+        }]
+    );
+
+    // Rules that are not enabled stay quiet
+    let mut tracker = humidity_tracker(40);
+    assert!(tracker.observe(&state_with_home_flag(DOOR)).is_empty());
+}
+
+#[test]
+fn test_quiet_period_per_rule_and_subject() {
+    let start = Instant::now();
+    let minutes = |n: u64| start + std::time::Duration::from_secs(n * 60);
+    let mut tracker = AlertTracker::new(AlertSettings {
+        ams_humidity_above: Some(40),
+        quiet_period_secs: 600,
+        quiet_periods: [("connection_lost".to_string(), 60)].into(),
+        ..Default::default()
+    });
+
+    assert_eq!(
+        tracker
+            .observe_at(&state_with_humidity(&[("0", 45)]), minutes(0))
+            .len(),
+        1
+    );
+    tracker.observe_at(&state_with_humidity(&[("0", 35)]), minutes(1));
+    // Humid again within the quiet period: only the other unit alerts
+    let alerts = tracker.observe_at(&state_with_humidity(&[("0", 45), ("1", 45)]), minutes(2));
+    assert_eq!(alerts.len(), 1);
+    assert!(alerts[0].message.starts_with("AMS B"));
+
+    tracker.observe_at(&state_with_humidity(&[("0", 35)]), minutes(3));
+    assert_eq!(
+        tracker
+            .observe_at(&state_with_humidity(&[("0", 45)]), minutes(10))
+            .len(),
+        1
+    );
+}
+
+#[test]
+fn test_connection_lost_fires_once_per_outage() {
+    let start = Instant::now();
+    let seconds = |n: u64| start + std::time::Duration::from_secs(n);
+    let mut tracker = AlertTracker::new(AlertSettings {
+        connection_lost: true,
+        quiet_periods: [("connection_lost".to_string(), 60)].into(),
+        ..Default::default()
+    });
+    let connected = MergedState::default();
+
+    let alert = tracker.connection_lost_at("timed out", seconds(0)).unwrap();
+    assert_eq!(alert.kind, "connection_lost");
+    assert_eq!(alert.message, "Connection lost: timed out");
+    // Retries while still disconnected belong to the same outage
+    assert!(
+        tracker
+            .connection_lost_at("timed out", seconds(5))
+            .is_none()
+    );
+
+    // A bouncing connection stays quiet until the quiet period is over
+    tracker.observe_at(&connected, seconds(10));
+    assert!(tracker.connection_lost_at("reset", seconds(20)).is_none());
+    tracker.observe_at(&connected, seconds(30));
+    assert!(tracker.connection_lost_at("reset", seconds(70)).is_some());
+
+    // Disabled unless configured
+    let mut tracker = humidity_tracker(40);
+    assert!(tracker.connection_lost("timed out").is_none());
+}
+
+#[test]
+fn test_almost_done_fires_when_remaining_time_crosses_threshold() {
+    let mut tracker = AlertTracker::new(AlertSettings {
+        almost_done_minutes: Some(10),
+        quiet_period_secs: 0,
+        ..Default::default()
+    });
+    let mut state = MergedState::default();
+    let mut step = |payload: &str| {
+        state.update(&DeviceMessage::parse(payload).unwrap());
+        tracker.observe(&state)
+    };
+
+    // Joining a print that is already nearly done stays quiet
+    assert!(step(r#"{"print": {"gcode_state": "RUNNING", "subtask_name": "benchy", "mc_remaining_time": 8}}"#).is_empty());
+    assert!(step(r#"{"print": {"gcode_state": "FINISH", "mc_remaining_time": 0}}"#).is_empty());
+
+    assert!(step(r#"{"print": {"gcode_state": "RUNNING", "mc_remaining_time": 42}}"#).is_empty());
+    assert!(step(r#"{"print": {"mc_remaining_time": 10}}"#).is_empty());
+    assert_eq!(
+        step(r#"{"print": {"mc_remaining_time": 9}}"#),
+        vec![Alert {
+            kind: "almost_done",
+            message: "benchy finishing in 9 minutes".to_string(),
+        }]
+    );
+    assert!(step(r#"{"print": {"mc_remaining_time": 5}}"#).is_empty());
+
+    // Paused prints do not count down
+    assert!(step(r#"{"print": {"gcode_state": "PAUSE", "mc_remaining_time": 12}}"#).is_empty());
+    assert!(step(r#"{"print": {"gcode_state": "RUNNING", "mc_remaining_time": 12}}"#).is_empty());
+    assert_eq!(step(r#"{"print": {"mc_remaining_time": 7}}"#).len(), 1);
+}
+
+#[test]
+fn test_first_layer_defect_alert() {
+    let defect = InspectionResult::DefectDetected {
+        codes: vec!["0C00_8001".to_string()],
+    };
+    let mut tracker = AlertTracker::new(AlertSettings {
+        first_layer_defect: true,
+        ..Default::default()
+    });
+    assert!(tracker.inspection(&InspectionResult::Passed).is_none());
+    assert_eq!(
+        tracker.inspection(&defect),
+        Some(Alert {
+            kind: "first_layer_defect",
+            message: "First layer inspection detected defects (0C00_8001)".to_string(),
+        })
+    );
+
+    let mut tracker = humidity_tracker(40);
+    assert!(tracker.inspection(&defect).is_none());
+}
+
+#[test]
+fn test_spaghetti_alert_skips_first_layer_inspection() {
+    let mut tracker = AlertTracker::new(AlertSettings {
+        spaghetti: true,
+        quiet_period_secs: 0,
+        ..Default::default()
+    });
+    let mut state = MergedState::default();
+    let mut step = |payload: &str| {
+        state.update(&DeviceMessage::parse(payload).unwrap());
+        tracker.observe(&state)
+    };
+
+    assert!(step(r#"{"print": {"gcode_state": "RUNNING", "stg_cur": 10}}"#).is_empty());
+    // The first-layer defect pauses the print; it is not reported as spaghetti
+    assert!(
+        step(r#"{"print": {"gcode_state": "PAUSE", "stg_cur": 16, "print_error": 201375747}}"#)
+            .is_empty()
+    );
+    assert!(
+        step(r#"{"print": {"gcode_state": "RUNNING", "stg_cur": 0, "print_error": 0}}"#).is_empty()
+    );
+
+    // Codes from other modules are not camera detections
+    assert!(step(r#"{"print": {"hms": [{"attr": 50331904, "code": 65543}]}}"#).is_empty());
+    let alerts = step(r#"{"print": {"gcode_state": "PAUSE", "print_error": 201375748}}"#);
+    assert_eq!(
+        alerts,
+        vec![Alert {
+            kind: "spaghetti",
+            message: "Camera detected a possible print failure such as spaghetti (0C00_C004)"
+                .to_string(),
+        }]
+    );
+    assert_eq!(alerts[0].priority(), "high");
+    // Reported once while the code stays raised
+    assert!(step(r#"{"print": {"mc_remaining_time": 30}}"#).is_empty());
+}
+
+#[test]
+fn test_door_open_during_print_of_listed_material() {
+    const DOOR: u32 = 0x0080_0000;
+    let mut tracker = AlertTracker::new(AlertSettings {
+        door_open_materials: vec!["ABS".to_string(), "asa".to_string()],
+        quiet_period_secs: 0,
+        ..Default::default()
+    });
+    let mut state = MergedState::default();
+    let mut step = |tracker: &mut AlertTracker, payload: &str| {
+        state.update(&DeviceMessage::parse(payload).unwrap());
+        tracker.observe(&state)
+    };
+
+    // The active tray holds PLA
+    step(
+        &mut tracker,
+        r#"{"print": {"gcode_state": "RUNNING", "home_flag": 0, "ams": {"tray_now": "0", "ams": [{"id": "0", "tray": [{"id": "0", "tray_type": "PLA"}, {"id": "1", "tray_type": "ABS-GF"}]}]}}}"#,
+    );
+    assert!(
+        step(
+            &mut tracker,
+            &format!(r#"{{"print": {{"home_flag": {DOOR}}}}}"#)
+        )
+        .is_empty()
+    );
+    step(&mut tracker, r#"{"print": {"home_flag": 0}}"#);
+
+    // Variants of a listed material count
+    step(&mut tracker, r#"{"print": {"ams": {"tray_now": "1"}}}"#);
+    assert_eq!(
+        step(
+            &mut tracker,
+            &format!(r#"{{"print": {{"home_flag": {DOOR}}}}}"#)
+        ),
+        vec![Alert {
+            kind: "door_open_during_print",
+            message: "Enclosure door opened while printing ABS-GF".to_string(),
+        }]
+    );
+    step(&mut tracker, r#"{"print": {"home_flag": 0}}"#);
+
+    // Materials of the job take precedence over the active tray
+    tracker.set_job_materials(vec!["PETG".to_string()]);
+    assert!(
+        step(
+            &mut tracker,
+            &format!(r#"{{"print": {{"home_flag": {DOOR}}}}}"#)
+        )
+        .is_empty()
+    );
+    step(&mut tracker, r#"{"print": {"home_flag": 0}}"#);
+    tracker.set_job_materials(vec!["PLA".to_string(), "ASA".to_string()]);
+    assert_eq!(
+        step(
+            &mut tracker,
+            &format!(r#"{{"print": {{"home_flag": {DOOR}}}}}"#)
+        )
+        .len(),
+        1
+    );
+
+    // Not while idle
+    step(
+        &mut tracker,
+        r#"{"print": {"gcode_state": "FINISH", "home_flag": 0}}"#,
+    );
+    assert!(
+        step(
+            &mut tracker,
+            &format!(r#"{{"print": {{"home_flag": {DOOR}}}}}"#)
+        )
+        .is_empty()
+    );
+}
+
+#[test]
+fn test_hms_actions_by_severity() {
+    let mut tracker = AlertTracker::new(AlertSettings {
+        hms: crate::config::HmsPolicy {
+            info: Some(HmsAction::Log),
+            fatal: Some(HmsAction::Pause),
+            ..Default::default()
+        },
+        ..Default::default()
+    });
+    let mut state = MergedState::default();
+    let mut step = |tracker: &mut AlertTracker, payload: &str| {
+        state.update(&DeviceMessage::parse(payload).unwrap());
+        tracker.hms(&state)
+    };
+
+    // Info and fatal entries are handled, the common one is not configured
+    let raised = step(
+        &mut tracker,
+        r#"{"print": {"hms": [
+            {"attr": 201326592, "code": 262145},
+            {"attr": 50331904, "code": 196609},
+            {"attr": 50331904, "code": 65543}
+        ]}}"#,
+    );
+    assert_eq!(
+        raised,
+        vec![
+            (
+                HmsAction::Log,
+                Alert {
+                    kind: "hms_info",
+                    message: "HMS 0C00_0000_0004_0001 (info)".to_string(),
+                }
+            ),
+            (
+                HmsAction::Pause,
+                Alert {
+                    kind: "hms_fatal",
+                    message: "HMS 0300_0100_0001_0007 (fatal)".to_string(),
+                }
+            ),
+        ]
+    );
+    assert_eq!(raised[1].1.priority(), "high");
+
+    // Codes still reported are not raised again; new ones are
+    assert!(step(&mut tracker, r#"{"print": {"percent": 5}}"#).is_empty());
+    let raised = step(
+        &mut tracker,
+        r#"{"print": {"hms": [{"attr": 50331904, "code": 65543}, {"attr": 50331904, "code": 65544}]}}"#,
+    );
+    assert_eq!(raised.len(), 1);
+    assert_eq!(raised[0].1.message, "HMS 0300_0100_0001_0008 (fatal)");
+}
+
+#[test]
+fn test_filament_low_alert_for_active_tray() {
+    let mut tracker = AlertTracker::new(AlertSettings {
+        filament_low_percent: Some(10),
+        quiet_period_secs: 0,
+        ..Default::default()
+    });
+    let mut state = MergedState::default();
+    let mut step = |payload: &str| {
+        state.update(&DeviceMessage::parse(payload).unwrap());
+        tracker.observe(&state)
+    };
+
+    // A low tray that is not feeding the print does not count
+    assert!(
+        step(
+            r#"{"print": {"gcode_state": "RUNNING", "ams": {"tray_now": "0", "ams": [
+                {"id": "0", "tray": [{"id": "0", "remain": 40}, {"id": "1", "remain": 5}]}
+            ]}}}"#
+        )
+        .is_empty()
+    );
+    assert_eq!(
+        step(
+            r#"{"print": {"ams": {"tray_now": "0", "ams": [
+                {"id": "0", "tray": [{"id": "0", "remain": 8}, {"id": "1", "remain": 5}]}
+            ]}}}"#
+        ),
+        vec![Alert {
+            kind: "filament_low",
+            message: "Tray A1 has 8% filament left, below 10%".to_string(),
+        }]
+    );
+    assert!(
+        step(
+            r#"{"print": {"ams": {"tray_now": "0", "ams": [
+                {"id": "0", "tray": [{"id": "0", "remain": 7}, {"id": "1", "remain": 5}]}
+            ]}}}"#
+        )
+        .is_empty()
+    );
+    // Switching to another low tray warns about that one
+    assert_eq!(
+        step(r#"{"print": {"ams": {"tray_now": "1"}}}"#)[0].message,
+        "Tray A2 has 5% filament left, below 10%"
+    );
+    assert!(step(r#"{"print": {"gcode_state": "FINISH"}}"#).is_empty());
+}
+
+#[test]
+fn test_filament_change_alert() {
+    let mut tracker = AlertTracker::new(AlertSettings {
+        filament_change: true,
+        quiet_period_secs: 0,
+        ..Default::default()
+    });
+    let mut state = MergedState::default();
+    let mut step = |payload: &str| {
+        state.update(&DeviceMessage::parse(payload).unwrap());
+        tracker.observe(&state)
+    };
+
+    assert!(
+        step(
+            r#"{"print": {"gcode_state": "RUNNING", "stg_cur": 0, "ams": {"tray_now": "2", "ams": [
+                {"id": "0", "tray": [{"id": "0"}, {"id": "2"}]}
+            ]}}}"#
+        )
+        .is_empty()
+    );
+    assert_eq!(
+        step(r#"{"print": {"stg_cur": 22, "ams": {"tray_tar": "0"}}}"#),
+        vec![Alert {
+            kind: "filament_change",
+            message: "Changing filament, A3 → A1".to_string(),
+        }]
+    );
+    // Unloading, changing and loading are one change
+    assert!(step(r#"{"print": {"stg_cur": 4, "ams": {"tray_now": "255"}}}"#).is_empty());
+    assert!(step(r#"{"print": {"stg_cur": 24}}"#).is_empty());
+    assert_eq!(
+        step(r#"{"print": {"gcode_state": "PAUSE", "stg_cur": 6, "ams": {"tray_now": "0"}}}"#)[0]
+            .message,
+        "Paused, filament ran out in A1"
+    );
+    assert!(step(r#"{"print": {"gcode_state": "RUNNING", "stg_cur": 0}}"#).is_empty());
+}
+
+#[test]
+fn test_firmware_update_alert_once_per_version() {
+    let mut tracker = AlertTracker::new(AlertSettings {
+        firmware_update: true,
+        quiet_period_secs: 0,
+        ..Default::default()
+    });
+    let mut state = MergedState::default();
+    let mut step = |new_ver: &str| {
+        let payload = format!(
+            r#"{{"print": {{"upgrade_state": {{"new_version_state": 1, "new_ver_list": [
+                {{"name": "ota", "cur_ver": "01.07.00.00", "new_ver": "{new_ver}"}}
+            ]}}}}}}"#
+        );
+        state.update(&DeviceMessage::parse(&payload).unwrap());
+        tracker.observe(&state)
+    };
+
+    assert_eq!(
+        step("01.08.00.00"),
+        vec![Alert {
+            kind: "firmware_update",
+            message: "Firmware update available: ota 01.07.00.00 → 01.08.00.00".to_string(),
+        }]
+    );
+    assert!(step("01.08.00.00").is_empty());
+    assert_eq!(step("01.09.00.00").len(), 1);
+}
+
+#[test]
+fn test_wifi_weak_alert() {
+    let mut tracker = AlertTracker::new(AlertSettings {
+        wifi_signal_below: Some(-70),
+        quiet_period_secs: 0,
+        ..Default::default()
+    });
+    let mut state = MergedState::default();
+    let mut step = |signal: &str| {
+        let payload = format!(r#"{{"print": {{"wifi_signal": "{signal}"}}}}"#);
+        state.update(&DeviceMessage::parse(&payload).unwrap());
+        tracker.observe(&state)
+    };
+
+    assert!(step("-60dBm").is_empty());
+    assert_eq!(
+        step("-74dBm"),
+        vec![Alert {
+            kind: "wifi_weak",
+            message: "WiFi signal is -74 dBm, below -70 dBm".to_string(),
+        }]
+    );
+    assert!(step("-78dBm").is_empty());
+    assert!(step("-70dBm").is_empty());
+    assert_eq!(step("-71dBm").len(), 1);
+}
@@ -0,0 +1,198 @@
+use crate::calendar;
+use crate::daemon::{HealthReport, PrinterHealth, SharedPrinters, health_report};
+use crate::display;
+use crate::graphql::{self, PrinterSchema};
+use crate::telemetry::TelemetrySample;
+use async_graphql::http::GraphiQLSource;
+use axum::extract::{Path, Query, State};
+use axum::http::{StatusCode, header};
+use axum::response::Html;
+use axum::routing::get;
+use axum::{Json, Router};
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tokio::net::TcpListener;
+use utoipa::{IntoParams, OpenApi, ToSchema};
+
+#[cfg(test)]
+mod tests;
+
+/// Routes of the daemon's HTTP API; `/openapi.json` is generated from these
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "pulseprint-cli",
+        description = "HTTP API of the pulseprint-cli daemon"
+    ),
+    paths(healthz, telemetry, calendar_ics, openapi, graphql_request, graphiql),
+    components(schemas(HealthReport, PrinterHealth, TelemetryReport, TelemetrySample))
+)]
+pub struct ApiDoc;
+
+#[derive(Clone)]
+struct ApiState {
+    printers: SharedPrinters,
+    stale_after: Duration,
+    schema: PrinterSchema,
+}
+
+pub fn router(printers: SharedPrinters, stale_after: Duration) -> Router {
+    Router::new()
+        .route("/healthz", get(healthz))
+        .route("/printers/{name}/telemetry", get(telemetry))
+        .route("/calendar.ics", get(calendar_ics))
+        .route("/openapi.json", get(openapi))
+        .route("/graphql", get(graphiql).post(graphql_request))
+        .fallback(not_found)
+        .with_state(ApiState {
+            schema: graphql::schema(printers.clone()),
+            printers,
+            stale_after,
+        })
+}
+
+/// Serves the API until the task is aborted
+pub async fn serve(
+    listener: TcpListener,
+    printers: SharedPrinters,
+    stale_after: Duration,
+) -> std::io::Result<()> {
+    axum::serve(listener, router(printers, stale_after)).await
+}
+
+/// Connection health of every monitored printer
+#[utoipa::path(
+    get,
+    path = "/healthz",
+    responses(
+        (status = 200, description = "Every printer is connected and recently heard from", body = HealthReport),
+        (status = 503, description = "A printer is disconnected or stale", body = HealthReport)
+    )
+)]
+async fn healthz(State(state): State<ApiState>) -> (StatusCode, Json<HealthReport>) {
+    let report = health_report(&state.printers, Local::now(), state.stale_after);
+    let status = if report.healthy {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (status, Json(report))
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+struct TelemetryQuery {
+    /// How far back to go, e.g. `90s`, `10m` or `1h`; everything kept when left out
+    window: Option<String>,
+}
+
+/// Recent samples of one printer, oldest first
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct TelemetryReport {
+    pub printer: String,
+    pub window_secs: u64,
+    pub samples: Vec<TelemetrySample>,
+}
+
+/// Temperatures and progress of one printer over the last minutes, kept in memory by the
+/// daemon
+#[utoipa::path(
+    get,
+    path = "/printers/{name}/telemetry",
+    params(("name" = String, Path, description = "Printer name from the config"), TelemetryQuery),
+    responses(
+        (status = 200, description = "Samples within the window", body = TelemetryReport),
+        (status = 400, description = "The window is not a duration"),
+        (status = 404, description = "No printer has this name")
+    )
+)]
+async fn telemetry(
+    State(state): State<ApiState>,
+    Path(name): Path<String>,
+    Query(query): Query<TelemetryQuery>,
+) -> Result<Json<TelemetryReport>, (StatusCode, Json<serde_json::Value>)> {
+    let window = query
+        .window
+        .as_deref()
+        .map(display::parse_duration)
+        .transpose()
+        .map_err(|e| error(StatusCode::BAD_REQUEST, &e))?;
+
+    let printers = state.printers.lock().unwrap();
+    let entry = printers
+        .get(&name)
+        .ok_or_else(|| error(StatusCode::NOT_FOUND, "unknown printer"))?;
+    let window = window.unwrap_or(entry.telemetry.retention());
+    Ok(Json(TelemetryReport {
+        samples: entry.telemetry.window(window, Local::now()),
+        printer: name,
+        window_secs: window.as_secs(),
+    }))
+}
+
+/// iCalendar feed with an event for every running print, ending at its expected finish
+#[utoipa::path(
+    get,
+    path = "/calendar.ics",
+    responses((status = 200, description = "iCalendar document", content_type = "text/calendar"))
+)]
+async fn calendar_ics(
+    State(state): State<ApiState>,
+) -> ([(header::HeaderName, &'static str); 1], String) {
+    let snapshots: Vec<_> = state
+        .printers
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(name, entry)| entry.snapshot(name))
+        .collect();
+    (
+        [(header::CONTENT_TYPE, "text/calendar; charset=utf-8")],
+        calendar::render(&snapshots, Local::now()),
+    )
+}
+
+/// OpenAPI 3 description of this API
+#[utoipa::path(
+    get,
+    path = "/openapi.json",
+    responses((status = 200, description = "OpenAPI document", content_type = "application/json"))
+)]
+async fn openapi() -> Json<utoipa::openapi::OpenApi> {
+    Json(ApiDoc::openapi())
+}
+
+/// Runs a GraphQL query or mutation against the printers, their status and job history
+#[utoipa::path(
+    post,
+    path = "/graphql",
+    request_body(
+        content = Object,
+        description = "GraphQL request with `query`, and optionally `variables` and `operationName`"
+    ),
+    responses((status = 200, description = "GraphQL response with `data` and `errors`", content_type = "application/json"))
+)]
+async fn graphql_request(
+    State(state): State<ApiState>,
+    Json(request): Json<async_graphql::Request>,
+) -> Json<async_graphql::Response> {
+    Json(state.schema.execute(request).await)
+}
+
+/// GraphiQL page for exploring the GraphQL schema in a browser
+#[utoipa::path(
+    get,
+    path = "/graphql",
+    responses((status = 200, description = "GraphiQL page", content_type = "text/html"))
+)]
+async fn graphiql() -> Html<String> {
+    Html(GraphiQLSource::build().endpoint("/graphql").finish())
+}
+
+async fn not_found() -> (StatusCode, Json<serde_json::Value>) {
+    error(StatusCode::NOT_FOUND, "not found")
+}
+
+fn error(status: StatusCode, message: &str) -> (StatusCode, Json<serde_json::Value>) {
+    (status, Json(serde_json::json!({ "error": message })))
+}
@@ -0,0 +1,169 @@
+use super::*;
+use crate::daemon::test_support::shared;
+use crate::messages::DeviceMessage;
+use std::sync::Arc;
+
+/// Serves the API on an ephemeral port and returns its base URL
+async fn spawn_api(printers: &SharedPrinters) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    tokio::spawn(serve(
+        listener,
+        Arc::clone(printers),
+        Duration::from_secs(120),
+    ));
+    format!("http://{address}")
+}
+
+#[tokio::test]
+async fn test_healthz_http_responses() {
+    let printers = shared(&["x1c"]);
+    let base = spawn_api(&printers).await;
+    let client = reqwest::Client::new();
+
+    let response = client.get(format!("{base}/healthz")).send().await.unwrap();
+    assert_eq!(response.status(), 503);
+    assert_eq!(
+        response.text().await.unwrap(),
+        r#"{"healthy":false,"printers":[{"name":"x1c","connected":false,"last_message_age_secs":null,"healthy":false}]}"#
+    );
+
+    {
+        let mut printers = printers.lock().unwrap();
+        let entry = printers.get_mut("x1c").unwrap();
+        entry.connected = true;
+        entry.last_message_at = Some(Local::now());
+    }
+    let response = client.head(format!("{base}/healthz")).send().await.unwrap();
+    assert_eq!(response.status(), 200);
+
+    let response = client.get(format!("{base}/")).send().await.unwrap();
+    assert_eq!(response.status(), 404);
+
+    let response = client.post(format!("{base}/healthz")).send().await.unwrap();
+    assert_eq!(response.status(), 405);
+}
+
+#[tokio::test]
+async fn test_telemetry_window() {
+    let printers = shared(&["x1c"]);
+    {
+        let mut printers = printers.lock().unwrap();
+        let entry = printers.get_mut("x1c").unwrap();
+        entry.state.update(
+            &DeviceMessage::parse(r#"{"print": {"nozzle_temper": 220, "percent": 40}}"#).unwrap(),
+        );
+        let now = Local::now();
+        entry
+            .telemetry
+            .record(&entry.state, now - chrono::Duration::minutes(20));
+        entry.telemetry.record(&entry.state, now);
+    }
+    let base = spawn_api(&printers).await;
+
+    let report: serde_json::Value =
+        reqwest::get(format!("{base}/printers/x1c/telemetry?window=10m"))
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+    assert_eq!(report["printer"], "x1c");
+    assert_eq!(report["window_secs"], 600);
+    let samples = report["samples"].as_array().unwrap();
+    assert_eq!(samples.len(), 1);
+    assert_eq!(samples[0]["nozzle"], 220.0);
+    assert_eq!(samples[0]["progress"], 40);
+    assert!(samples[0]["bed"].is_null());
+
+    let report: serde_json::Value = reqwest::get(format!("{base}/printers/x1c/telemetry"))
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(report["window_secs"], 3600);
+    assert_eq!(report["samples"].as_array().unwrap().len(), 2);
+
+    let response = reqwest::get(format!("{base}/printers/x1c/telemetry?window=soon"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 400);
+    let response = reqwest::get(format!("{base}/printers/p1s/telemetry"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 404);
+}
+
+#[tokio::test]
+async fn test_openapi_document_covers_routes() {
+    let base = spawn_api(&shared(&[])).await;
+    let document: serde_json::Value = reqwest::get(format!("{base}/openapi.json"))
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+
+    assert!(document["openapi"].as_str().unwrap().starts_with("3."));
+    assert!(document["paths"]["/openapi.json"]["get"].is_object());
+    assert_eq!(
+        document["paths"]["/healthz"]["get"]["responses"]["503"]["content"]["application/json"]["schema"]
+            ["$ref"],
+        "#/components/schemas/HealthReport"
+    );
+    assert_eq!(
+        document["paths"]["/printers/{name}/telemetry"]["get"]["responses"]["200"]["content"]["application/json"]
+            ["schema"]["$ref"],
+        "#/components/schemas/TelemetryReport"
+    );
+    assert!(
+        document["components"]["schemas"]["PrinterHealth"]["properties"]["last_message_age_secs"]
+            .is_object()
+    );
+}
+
+#[tokio::test]
+async fn test_graphql_endpoint() {
+    let base = spawn_api(&shared(&["x1c"])).await;
+    let client = reqwest::Client::new();
+
+    let response: serde_json::Value = client
+        .post(format!("{base}/graphql"))
+        .json(&serde_json::json!({ "query": "{ printers { name connected } }" }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(
+        response,
+        serde_json::json!({"data": {"printers": [{"name": "x1c", "connected": false}]}})
+    );
+
+    let page = client.get(format!("{base}/graphql")).send().await.unwrap();
+    assert_eq!(page.status(), 200);
+    assert!(page.text().await.unwrap().contains("graphiql"));
+}
+
+#[tokio::test]
+async fn test_calendar_feed() {
+    let printers = shared(&["x1c"]);
+    printers.lock().unwrap().get_mut("x1c").unwrap().state.update(
+        &DeviceMessage::parse(
+            r#"{"print": {"gcode_state": "RUNNING", "subtask_name": "benchy", "mc_remaining_time": 600}}"#,
+        )
+        .unwrap(),
+    );
+    let base = spawn_api(&printers).await;
+
+    let response = reqwest::get(format!("{base}/calendar.ics")).await.unwrap();
+    assert_eq!(response.status(), 200);
+    assert_eq!(
+        response.headers()["content-type"],
+        "text/calendar; charset=utf-8"
+    );
+    let calendar = response.text().await.unwrap();
+    assert!(calendar.contains("SUMMARY:benchy on x1c\r\n"));
+}
@@ -0,0 +1,120 @@
+use crate::daemon::PrinterSnapshot;
+use crate::messages::PrintState;
+use chrono::{DateTime, Local, Utc};
+
+#[cfg(test)]
+mod tests;
+
+/// Calendar timestamps are written in UTC, so no time zone definitions are needed
+const TIME_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+/// Longest content line in octets; longer lines are folded
+const MAX_LINE: usize = 75;
+
+/// iCalendar document with an event for every running print with a known finish time. An
+/// event spans the print, from its start (or `now` when the daemon did not see it start)
+/// to its expected finish. Event UIDs stay the same while a job runs, so calendar apps
+/// that subscribe to the document move the event as the estimate changes.
+pub fn render(printers: &[PrinterSnapshot], now: DateTime<Local>) -> String {
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        format!(
+            "PRODID:-//pulseprint-cli//{}//EN",
+            env!("CARGO_PKG_VERSION")
+        ),
+        "CALSCALE:GREGORIAN".to_string(),
+        "X-WR-CALNAME:Print jobs".to_string(),
+    ];
+    for printer in printers {
+        let status = printer.state.status();
+        let Some(finish_at) = status.finish_at else {
+            continue;
+        };
+        if status.state != PrintState::Printing {
+            continue;
+        }
+        let start = status.started_at.unwrap_or(now).min(finish_at);
+        let job = printer
+            .state
+            .print
+            .subtask_name
+            .as_deref()
+            .filter(|name| !name.is_empty())
+            .unwrap_or("Print");
+        let uid = match status.started_at {
+            Some(started_at) => format!(
+                "{}-{}@pulseprint-cli",
+                printer.name,
+                started_at.with_timezone(&Utc).format(TIME_FORMAT)
+            ),
+            None => format!("{}@pulseprint-cli", printer.name),
+        };
+        let mut description = format!(
+            "Expected to finish at {}",
+            finish_at.format("%Y-%m-%d %H:%M")
+        );
+        if let Some(progress) = status.progress {
+            description.push_str(&format!(", {progress}% done"));
+        }
+
+        lines.extend([
+            "BEGIN:VEVENT".to_string(),
+            format!("UID:{}", escape_text(&uid)),
+            format!("DTSTAMP:{}", utc(now)),
+            format!("DTSTART:{}", utc(start)),
+            format!("DTEND:{}", utc(finish_at)),
+            format!(
+                "SUMMARY:{}",
+                escape_text(&format!("{job} on {}", printer.name))
+            ),
+            format!("DESCRIPTION:{}", escape_text(&description)),
+            "TRANSP:TRANSPARENT".to_string(),
+            "END:VEVENT".to_string(),
+        ]);
+    }
+    lines.push("END:VCALENDAR".to_string());
+
+    let mut out = String::new();
+    for line in lines {
+        out.push_str(&fold(&line));
+        out.push_str("\r\n");
+    }
+    out
+}
+
+fn utc(at: DateTime<Local>) -> String {
+    at.with_timezone(&Utc).format(TIME_FORMAT).to_string()
+}
+
+/// Escapes a TEXT value (RFC 5545 section 3.3.11)
+fn escape_text(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' | ';' | ',' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '\n' => escaped.push_str("\\n"),
+            '\r' => {}
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Splits a content line into lines of at most [`MAX_LINE`] octets, continuation lines
+/// starting with a space, without breaking a UTF-8 character
+fn fold(line: &str) -> String {
+    let mut folded = String::with_capacity(line.len());
+    let mut length = 0;
+    for c in line.chars() {
+        if length + c.len_utf8() > MAX_LINE {
+            folded.push_str("\r\n ");
+            length = 1;
+        }
+        folded.push(c);
+        length += c.len_utf8();
+    }
+    folded
+}
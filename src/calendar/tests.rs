@@ -0,0 +1,67 @@
+use super::*;
+use crate::messages::{DeviceMessage, MergedState};
+use chrono::TimeZone;
+
+fn now() -> DateTime<Local> {
+    Local.with_ymd_and_hms(2025, 8, 1, 9, 30, 0).unwrap()
+}
+
+fn snapshot(name: &str, payload: &str, started_at: Option<DateTime<Local>>) -> PrinterSnapshot {
+    let mut state = MergedState::default();
+    state.update_at(&DeviceMessage::parse(payload).unwrap(), now());
+    state.started_at = started_at;
+    PrinterSnapshot {
+        name: name.to_string(),
+        connected: true,
+        last_message_at: Some(now()),
+        state,
+    }
+}
+
+#[test]
+fn test_events_for_running_prints() {
+    let started_at = now() - chrono::Duration::minutes(30);
+    let printers = [
+        snapshot(
+            "x1c",
+            r#"{"print": {"gcode_state": "RUNNING", "subtask_name": "benchy, v2", "percent": 40, "mc_remaining_time": 2700}}"#,
+            Some(started_at),
+        ),
+        snapshot("p1s", r#"{"print": {"gcode_state": "IDLE"}}"#, None),
+        snapshot(
+            "a1",
+            r#"{"print": {"gcode_state": "RUNNING", "mc_remaining_time": 600}}"#,
+            None,
+        ),
+    ];
+
+    let calendar = render(&printers, now());
+    assert!(calendar.starts_with("BEGIN:VCALENDAR\r\nVERSION:2.0\r\n"));
+    assert!(calendar.ends_with("END:VCALENDAR\r\n"));
+    assert_eq!(calendar.matches("BEGIN:VEVENT").count(), 2);
+
+    let utc = |at: DateTime<Local>| at.with_timezone(&Utc).format(TIME_FORMAT).to_string();
+    assert!(calendar.contains(&format!("UID:x1c-{}@pulseprint-cli\r\n", utc(started_at))));
+    assert!(calendar.contains(&format!("DTSTART:{}\r\n", utc(started_at))));
+    assert!(calendar.contains(&format!(
+        "DTEND:{}\r\n",
+        utc(now() + chrono::Duration::minutes(45))
+    )));
+    assert!(calendar.contains("SUMMARY:benchy\\, v2 on x1c\r\n"));
+    assert!(calendar.contains("40% done"));
+
+    // Without a tracked start the event starts now and falls back to a generic name
+    assert!(calendar.contains("UID:a1@pulseprint-cli\r\n"));
+    assert!(calendar.contains(&format!("DTSTART:{}\r\n", utc(now()))));
+    assert!(calendar.contains("SUMMARY:Print on a1\r\n"));
+}
+
+#[test]
+fn test_fold_long_lines() {
+    let line = format!("SUMMARY:{}", "é".repeat(60));
+    let folded = fold(&line);
+    let parts: Vec<&str> = folded.split("\r\n").collect();
+    assert_eq!(parts.len(), 2);
+    assert!(parts.iter().all(|part| part.len() <= MAX_LINE));
+    assert_eq!(parts.concat().replacen(" ", "", 1), line);
+}
@@ -0,0 +1,186 @@
+use crate::config::PrinterConfig;
+use crate::mqtt;
+use rustls::pki_types::ServerName;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use thiserror::Error;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio_rustls::TlsConnector;
+
+#[cfg(test)]
+mod tests;
+
+/// Port of the JPEG stream on P1 and A1 series printers
+pub const CHAMBER_IMAGE_PORT: u16 = 6000;
+/// Port of the RTSPS live view on X1 series printers
+pub const RTSP_PORT: u16 = 322;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+const FFMPEG_TIMEOUT: Duration = Duration::from_secs(30);
+/// Frames larger than this are treated as a corrupt header
+const MAX_FRAME_SIZE: usize = 16 * 1024 * 1024;
+
+#[derive(Debug, Error)]
+pub enum CameraError {
+    #[error("IO error: {0}")]
+    IoError(#[from] std::io::Error),
+
+    #[error("Invalid printer address: {0}")]
+    InvalidAddress(String),
+
+    #[error("Timed out talking to the camera")]
+    Timeout,
+
+    #[error("Camera sent an invalid frame: {0}")]
+    InvalidFrame(String),
+
+    #[error("ffmpeg is needed for X1 snapshots but was not found in PATH")]
+    FfmpegMissing,
+
+    #[error("ffmpeg could not grab a frame: {0}")]
+    FfmpegFailed(String),
+}
+
+/// How the printer serves its camera
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum CameraKind {
+    /// JPEG frames over TLS on port 6000 (P1 and A1 series)
+    Chamber,
+    /// RTSPS live view on port 322 (X1 series), grabbed with ffmpeg
+    Rtsp,
+}
+
+impl CameraKind {
+    /// Picks the protocol from the configured model, or from the serial number prefix
+    pub fn detect(printer: &PrinterConfig) -> Self {
+        if let Some(model) = &printer.model {
+            return if model.to_ascii_uppercase().contains("X1") {
+                CameraKind::Rtsp
+            } else {
+                CameraKind::Chamber
+            };
+        }
+        // X1 Carbon, X1 and X1E serial numbers
+        if ["00M", "00W", "03W"]
+            .iter()
+            .any(|prefix| printer.device_id.starts_with(prefix))
+        {
+            CameraKind::Rtsp
+        } else {
+            CameraKind::Chamber
+        }
+    }
+}
+
+/// Grabs one JPEG still from the printer camera
+pub async fn snapshot(printer: &PrinterConfig, kind: CameraKind) -> Result<Vec<u8>, CameraError> {
+    match kind {
+        CameraKind::Chamber => chamber_snapshot(&printer.ip, &printer.access_code).await,
+        CameraKind::Rtsp => rtsp_snapshot(&printer.ip, &printer.access_code).await,
+    }
+}
+
+/// Login packet of the chamber image protocol: a 16-byte header, then the user name and
+/// access code, each NUL-padded to 32 bytes
+pub fn auth_packet(access_code: &str) -> [u8; 80] {
+    let mut packet = [0u8; 80];
+    packet[0..4].copy_from_slice(&0x40u32.to_le_bytes());
+    packet[4..8].copy_from_slice(&0x3000u32.to_le_bytes());
+    write_padded(&mut packet[16..48], "bblp");
+    write_padded(&mut packet[48..80], access_code);
+    packet
+}
+
+fn write_padded(field: &mut [u8], value: &str) {
+    let bytes = value.as_bytes();
+    let len = bytes.len().min(field.len());
+    field[..len].copy_from_slice(&bytes[..len]);
+}
+
+/// Reads one frame: a 16-byte header starting with the little-endian payload size, then
+/// the JPEG itself
+pub async fn read_frame<R: AsyncRead + Unpin>(reader: &mut R) -> Result<Vec<u8>, CameraError> {
+    let mut header = [0u8; 16];
+    reader.read_exact(&mut header).await?;
+    let size = u32::from_le_bytes([header[0], header[1], header[2], header[3]]) as usize;
+    if size == 0 || size > MAX_FRAME_SIZE {
+        return Err(CameraError::InvalidFrame(format!("payload size {size}")));
+    }
+
+    let mut frame = vec![0u8; size];
+    reader.read_exact(&mut frame).await?;
+    if !frame.starts_with(&[0xFF, 0xD8]) || !frame.ends_with(&[0xFF, 0xD9]) {
+        return Err(CameraError::InvalidFrame("not a JPEG image".to_string()));
+    }
+    Ok(frame)
+}
+
+async fn chamber_snapshot(ip: &str, access_code: &str) -> Result<Vec<u8>, CameraError> {
+    let server_name = ServerName::try_from(ip.to_string())
+        .map_err(|_| CameraError::InvalidAddress(ip.to_string()))?;
+    let connector = TlsConnector::from(Arc::new(mqtt::printer_tls_config()));
+
+    tokio::time::timeout(CONNECT_TIMEOUT, async {
+        let stream = TcpStream::connect((ip, CHAMBER_IMAGE_PORT)).await?;
+        let mut stream = connector.connect(server_name, stream).await?;
+        stream.write_all(&auth_packet(access_code)).await?;
+        stream.flush().await?;
+        read_frame(&mut stream).await
+    })
+    .await
+    .map_err(|_| CameraError::Timeout)?
+}
+
+pub fn rtsp_url(ip: &str, access_code: &str) -> String {
+    format!("rtsps://bblp:{access_code}@{ip}:{RTSP_PORT}/streaming/live/1")
+}
+
+/// `message` with the access code of an RTSPS URL masked, for errors that may quote the URL
+fn redact_rtsp_url(message: &str, access_code: &str) -> String {
+    message.replace(&format!("bblp:{access_code}@"), "bblp:***@")
+}
+
+/// Decodes one frame of the RTSPS stream with ffmpeg, which handles the H.264 decoding.
+///
+/// ffmpeg only takes the stream URL on its command line, so while it runs the access code
+/// is visible to other local users in the process list.
+async fn rtsp_snapshot(ip: &str, access_code: &str) -> Result<Vec<u8>, CameraError> {
+    let child = tokio::process::Command::new("ffmpeg")
+        .args(["-loglevel", "error", "-rtsp_transport", "tcp", "-i"])
+        .arg(rtsp_url(ip, access_code))
+        .args(["-frames:v", "1", "-f", "image2", "-c:v", "mjpeg", "-"])
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => CameraError::FfmpegMissing,
+            _ => CameraError::IoError(e),
+        })?;
+
+    let output = tokio::time::timeout(FFMPEG_TIMEOUT, child.wait_with_output())
+        .await
+        .map_err(|_| CameraError::Timeout)??;
+    if !output.status.success() || output.stdout.is_empty() {
+        let message = redact_rtsp_url(String::from_utf8_lossy(&output.stderr).trim(), access_code);
+        return Err(CameraError::FfmpegFailed(if message.is_empty() {
+            output.status.to_string()
+        } else {
+            message
+        }));
+    }
+    Ok(output.stdout)
+}
+
+/// Writes the image, creating parent directories as needed
+pub fn save(image: &[u8], out: &Path) -> std::io::Result<()> {
+    if let Some(parent) = out.parent()
+        && !parent.as_os_str().is_empty()
+    {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(out, image)
+}
@@ -0,0 +1,207 @@
+use crate::telemetry::TelemetrySample;
+use plotters::coord::Shift;
+use plotters::prelude::*;
+use plotters::style::{FontStyle, register_font};
+use std::path::Path;
+use std::sync::OnceLock;
+use thiserror::Error;
+
+#[cfg(test)]
+mod tests;
+
+const CHART_SIZE: (u32, u32) = (1200, 800);
+
+/// Used for all chart text, so PNG output does not depend on fonts installed on the system
+const FONT: &[u8] = include_bytes!("../../assets/fonts/DejaVuSans.ttf");
+
+#[derive(Debug, Error)]
+pub enum ChartError {
+    #[error("Job has no temperature or progress samples")]
+    NoData,
+
+    #[error("Unsupported chart format '{0}' (use .svg or .png)")]
+    UnsupportedFormat(String),
+
+    #[error("Failed to draw chart: {0}")]
+    DrawError(String),
+
+    #[error("Bundled chart font could not be loaded")]
+    InvalidFont,
+}
+
+/// Telemetry of one job, keyed by seconds since its first sample
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct JobSeries {
+    pub nozzle: Vec<(f64, f64)>,
+    pub bed: Vec<(f64, f64)>,
+    pub chamber: Vec<(f64, f64)>,
+    pub progress: Vec<(f64, f64)>,
+}
+
+impl JobSeries {
+    pub fn from_samples(samples: &[TelemetrySample]) -> Self {
+        let mut series = JobSeries::default();
+        let Some(start) = samples.first().map(|sample| sample.at) else {
+            return series;
+        };
+
+        for sample in samples {
+            let t = (sample.at - start).num_milliseconds().max(0) as f64 / 1000.0;
+            if let Some(nozzle) = sample.nozzle {
+                series.nozzle.push((t, nozzle));
+            }
+            if let Some(bed) = sample.bed {
+                series.bed.push((t, bed));
+            }
+            if let Some(chamber) = sample.chamber {
+                series.chamber.push((t, chamber));
+            }
+            if let Some(percent) = sample.progress {
+                series.progress.push((t, f64::from(percent)));
+            }
+        }
+
+        series
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nozzle.is_empty()
+            && self.bed.is_empty()
+            && self.chamber.is_empty()
+            && self.progress.is_empty()
+    }
+
+    fn duration_secs(&self) -> f64 {
+        [&self.nozzle, &self.bed, &self.chamber, &self.progress]
+            .iter()
+            .filter_map(|points| points.last().map(|(t, _)| *t))
+            .fold(0.0, f64::max)
+    }
+
+    fn max_temperature(&self) -> f64 {
+        [&self.nozzle, &self.bed, &self.chamber]
+            .iter()
+            .flat_map(|points| points.iter().map(|(_, temp)| *temp))
+            .fold(0.0, f64::max)
+    }
+}
+
+/// Renders temperatures (top) and progress (bottom) over time; format follows the extension
+pub fn render(series: &JobSeries, title: &str, out: &Path) -> Result<(), ChartError> {
+    if series.is_empty() {
+        return Err(ChartError::NoData);
+    }
+    load_font()?;
+
+    let extension = out
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+
+    match extension.as_str() {
+        "svg" => draw(
+            SVGBackend::new(out, CHART_SIZE).into_drawing_area(),
+            series,
+            title,
+        ),
+        "png" => draw(
+            BitMapBackend::new(out, CHART_SIZE).into_drawing_area(),
+            series,
+            title,
+        ),
+        other => Err(ChartError::UnsupportedFormat(other.to_string())),
+    }
+}
+
+/// Registers the bundled font as plotters' sans-serif family, once per process
+fn load_font() -> Result<(), ChartError> {
+    static LOADED: OnceLock<bool> = OnceLock::new();
+    let loaded =
+        *LOADED.get_or_init(|| register_font("sans-serif", FontStyle::Normal, FONT).is_ok());
+    if loaded {
+        Ok(())
+    } else {
+        Err(ChartError::InvalidFont)
+    }
+}
+
+fn draw<DB: DrawingBackend>(
+    root: DrawingArea<DB, Shift>,
+    series: &JobSeries,
+    title: &str,
+) -> Result<(), ChartError> {
+    let draw_error = |e: DrawingAreaErrorKind<DB::ErrorType>| ChartError::DrawError(e.to_string());
+
+    root.fill(&WHITE).map_err(draw_error)?;
+    let root = root.titled(title, ("sans-serif", 28)).map_err(draw_error)?;
+
+    let (upper, lower) = root.split_vertically(CHART_SIZE.1 * 3 / 5);
+    let duration = series.duration_secs().max(1.0);
+
+    let mut temperatures = ChartBuilder::on(&upper)
+        .margin(16)
+        .x_label_area_size(32)
+        .y_label_area_size(56)
+        .build_cartesian_2d(
+            0.0..duration,
+            0.0..(series.max_temperature() * 1.1).max(50.0),
+        )
+        .map_err(draw_error)?;
+
+    temperatures
+        .configure_mesh()
+        .y_desc("Temperature (°C)")
+        .draw()
+        .map_err(draw_error)?;
+
+    for (label, points, color) in [
+        ("Nozzle", &series.nozzle, RED),
+        ("Bed", &series.bed, BLUE),
+        ("Chamber", &series.chamber, GREEN),
+    ] {
+        if points.is_empty() {
+            continue;
+        }
+        temperatures
+            .draw_series(LineSeries::new(
+                points.iter().copied(),
+                color.stroke_width(2),
+            ))
+            .map_err(draw_error)?
+            .label(label)
+            .legend(move |(x, y)| {
+                PathElement::new(vec![(x, y), (x + 20, y)], color.stroke_width(2))
+            });
+    }
+
+    temperatures
+        .configure_series_labels()
+        .background_style(WHITE.mix(0.8))
+        .border_style(BLACK)
+        .draw()
+        .map_err(draw_error)?;
+
+    let mut progress = ChartBuilder::on(&lower)
+        .margin(16)
+        .x_label_area_size(32)
+        .y_label_area_size(56)
+        .build_cartesian_2d(0.0..duration, 0.0..100.0)
+        .map_err(draw_error)?;
+
+    progress
+        .configure_mesh()
+        .x_desc("Elapsed (s)")
+        .y_desc("Progress (%)")
+        .draw()
+        .map_err(draw_error)?;
+
+    progress
+        .draw_series(LineSeries::new(
+            series.progress.iter().copied(),
+            BLACK.stroke_width(2),
+        ))
+        .map_err(draw_error)?;
+
+    root.present().map_err(draw_error)
+}
@@ -0,0 +1,90 @@
+use super::*;
+use chrono::{DateTime, Duration, Local, TimeZone};
+use tempfile::tempdir;
+
+fn sample(seconds: i64, nozzle: Option<f64>, bed: Option<f64>, progress: u32) -> TelemetrySample {
+    let start: DateTime<Local> = Local.with_ymd_and_hms(2025, 8, 1, 9, 0, 0).unwrap();
+    TelemetrySample {
+        at: start + Duration::seconds(seconds),
+        nozzle,
+        bed,
+        chamber: None,
+        progress: Some(progress),
+        layer: None,
+    }
+}
+
+fn samples() -> Vec<TelemetrySample> {
+    vec![
+        sample(0, Some(25.0), Some(24.0), 0),
+        sample(60, Some(220.0), Some(24.0), 0),
+        sample(120, None, Some(60.0), 40),
+    ]
+}
+
+#[test]
+fn test_series_from_samples() {
+    let series = JobSeries::from_samples(&samples());
+
+    assert_eq!(series.nozzle, vec![(0.0, 25.0), (60.0, 220.0)]);
+    assert_eq!(series.bed, vec![(0.0, 24.0), (60.0, 24.0), (120.0, 60.0)]);
+    assert_eq!(
+        series.progress,
+        vec![(0.0, 0.0), (60.0, 0.0), (120.0, 40.0)]
+    );
+    assert!(series.chamber.is_empty());
+    assert!(JobSeries::from_samples(&[]).is_empty());
+}
+
+#[test]
+fn test_render_svg_and_png() {
+    let temp_dir = tempdir().unwrap();
+    let series = JobSeries::from_samples(&samples());
+
+    let svg = temp_dir.path().join("chart.svg");
+    render(&series, "Test job", &svg).unwrap();
+    let contents = std::fs::read_to_string(&svg).unwrap();
+    assert!(contents.contains("<svg"));
+    assert!(contents.contains("Test job"));
+
+    let png = temp_dir.path().join("chart.png");
+    render(&series, "Test job", &png).unwrap();
+    assert_eq!(&std::fs::read(&png).unwrap()[1..4], b"PNG");
+}
+
+#[test]
+fn test_png_draws_text_with_bundled_font() {
+    let temp_dir = tempdir().unwrap();
+    let series = JobSeries::from_samples(&samples());
+
+    let first = temp_dir.path().join("first.png");
+    let second = temp_dir.path().join("second.png");
+    render(&series, "Benchy", &first).unwrap();
+    render(&series, "Calibration cube", &second).unwrap();
+
+    // Only the title differs, so the images differ only if text is rendered
+    assert_ne!(
+        std::fs::read(&first).unwrap(),
+        std::fs::read(&second).unwrap()
+    );
+}
+
+#[test]
+fn test_render_rejects_empty_and_unknown_format() {
+    let temp_dir = tempdir().unwrap();
+
+    assert!(matches!(
+        render(
+            &JobSeries::default(),
+            "empty",
+            &temp_dir.path().join("a.svg")
+        ),
+        Err(ChartError::NoData)
+    ));
+
+    let series = JobSeries::from_samples(&samples());
+    assert!(matches!(
+        render(&series, "job", &temp_dir.path().join("a.gif")),
+        Err(ChartError::UnsupportedFormat(_))
+    ));
+}
@@ -0,0 +1,151 @@
+use crate::jobs;
+use crate::messages::{MergedState, PrintState};
+use std::fmt;
+
+#[cfg(test)]
+mod tests;
+
+/// Plugin result, following the Nagios plugin exit code conventions
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum CheckStatus {
+    Ok,
+    Warning,
+    Critical,
+    Unknown,
+}
+
+impl CheckStatus {
+    pub fn exit_code(self) -> i32 {
+        match self {
+            CheckStatus::Ok => 0,
+            CheckStatus::Warning => 1,
+            CheckStatus::Critical => 2,
+            CheckStatus::Unknown => 3,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            CheckStatus::Ok => "OK",
+            CheckStatus::Warning => "WARNING",
+            CheckStatus::Critical => "CRITICAL",
+            CheckStatus::Unknown => "UNKNOWN",
+        }
+    }
+}
+
+/// Nozzle temperature thresholds; a reading at or above either one raises the status
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Thresholds {
+    pub warn_temp: f64,
+    pub crit_temp: f64,
+}
+
+/// A single line of plugin output: `PULSEPRINT <STATUS> - <summary> | <perfdata>`
+#[derive(Debug, Clone, PartialEq)]
+pub struct CheckResult {
+    pub status: CheckStatus,
+    pub summary: String,
+    pub perfdata: Vec<String>,
+}
+
+impl CheckResult {
+    pub fn unknown(summary: impl Into<String>) -> Self {
+        Self {
+            status: CheckStatus::Unknown,
+            summary: summary.into(),
+            perfdata: Vec::new(),
+        }
+    }
+}
+
+impl fmt::Display for CheckResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "PULSEPRINT {} - {}", self.status.label(), self.summary)?;
+        if !self.perfdata.is_empty() {
+            write!(f, " | {}", self.perfdata.join(" "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Maps the merged printer state onto a plugin result
+///
+/// Failed prints are critical and paused prints are a warning; nozzle temperatures escalate
+/// independently and the worst status wins.
+pub fn evaluate(printer: &str, state: &MergedState, thresholds: &Thresholds) -> CheckResult {
+    if state.message_count == 0 {
+        return CheckResult::unknown(format!("{printer}: no status received"));
+    }
+
+    let status = state.status();
+    let mut check_status = match status.state {
+        PrintState::Idle | PrintState::Printing | PrintState::Finished => CheckStatus::Ok,
+        PrintState::Paused => CheckStatus::Warning,
+        PrintState::Failed => CheckStatus::Critical,
+        PrintState::Unknown(_) => CheckStatus::Unknown,
+    };
+
+    let mut summary = format!("{printer} {}", state_label(&status.state));
+    if let Some(progress) = status.progress {
+        summary.push_str(&format!(" {progress}%"));
+    }
+    if let Some(reason) = &status.fail_reason {
+        summary.push_str(&format!(" ({reason})"));
+    } else if status.state == PrintState::Paused
+        && let Some(reason) = jobs::pause_reason(&state.print)
+    {
+        summary.push_str(&format!(" ({})", reason.describe()));
+    }
+
+    let mut perfdata = Vec::new();
+    if let Some(progress) = status.progress {
+        perfdata.push(format!("progress={progress}%;;;0;100"));
+    }
+
+    if let Some(nozzle) = state.print.nozzle_temper {
+        let temp_status = if nozzle >= thresholds.crit_temp {
+            CheckStatus::Critical
+        } else if nozzle >= thresholds.warn_temp {
+            CheckStatus::Warning
+        } else {
+            CheckStatus::Ok
+        };
+        if temp_status != CheckStatus::Ok {
+            summary.push_str(&format!(", nozzle {nozzle:.1}C"));
+        }
+        check_status = check_status.max(temp_status);
+
+        perfdata.push(format!(
+            "nozzle_temp={nozzle:.1};{};{}",
+            thresholds.warn_temp, thresholds.crit_temp
+        ));
+    }
+
+    if let Some(bed) = state.print.bed_temper {
+        perfdata.push(format!("bed_temp={bed:.1}"));
+    }
+    if let Some(remaining) = status.remaining_time {
+        perfdata.push(format!("remaining={remaining}s"));
+    }
+    if let Some(dbm) = state.print.wifi_dbm() {
+        perfdata.push(format!("wifi_signal={dbm}"));
+    }
+
+    CheckResult {
+        status: check_status,
+        summary,
+        perfdata,
+    }
+}
+
+fn state_label(state: &PrintState) -> String {
+    match state {
+        PrintState::Idle => "idle".to_string(),
+        PrintState::Printing => "printing".to_string(),
+        PrintState::Paused => "paused".to_string(),
+        PrintState::Failed => "failed".to_string(),
+        PrintState::Finished => "finished".to_string(),
+        PrintState::Unknown(raw) => format!("in unknown state '{raw}'"),
+    }
+}
@@ -0,0 +1,88 @@
+use super::*;
+use crate::messages::DeviceMessage;
+
+const THRESHOLDS: Thresholds = Thresholds {
+    warn_temp: 260.0,
+    crit_temp: 300.0,
+};
+
+fn state_from(payload: &str) -> MergedState {
+    let mut state = MergedState::default();
+    state.update(&DeviceMessage::parse(payload).unwrap());
+    state
+}
+
+#[test]
+fn test_no_messages_is_unknown() {
+    let result = evaluate("x1c", &MergedState::default(), &THRESHOLDS);
+
+    assert_eq!(result.status, CheckStatus::Unknown);
+    assert_eq!(result.status.exit_code(), 3);
+    assert_eq!(
+        result.to_string(),
+        "PULSEPRINT UNKNOWN - x1c: no status received"
+    );
+}
+
+#[test]
+fn test_printing_is_ok_with_perfdata() {
+    let state = state_from(
+        r#"{"print": {"command": "push_status", "gcode_state": "RUNNING", "percent": 45, "nozzle_temper": 220.0, "bed_temper": 60.0, "mc_remaining_time": 600, "wifi_signal": "-45dBm"}}"#,
+    );
+    let result = evaluate("x1c", &state, &THRESHOLDS);
+
+    assert_eq!(result.status, CheckStatus::Ok);
+    assert_eq!(
+        result.to_string(),
+        "PULSEPRINT OK - x1c printing 45% | progress=45%;;;0;100 nozzle_temp=220.0;260;300 bed_temp=60.0 remaining=600s wifi_signal=-45"
+    );
+}
+
+#[test]
+fn test_print_state_maps_to_status() {
+    let paused = state_from(r#"{"print": {"command": "push_status", "gcode_state": "PAUSE"}}"#);
+    assert_eq!(
+        evaluate("x1c", &paused, &THRESHOLDS).status,
+        CheckStatus::Warning
+    );
+    let runout = state_from(
+        r#"{"print": {"command": "push_status", "gcode_state": "PAUSE", "stg_cur": 6, "percent": 40}}"#,
+    );
+    assert_eq!(
+        evaluate("x1c", &runout, &THRESHOLDS).summary,
+        "x1c paused 40% (filament ran out)"
+    );
+
+    let failed = state_from(
+        r#"{"print": {"command": "push_status", "gcode_state": "FAILED", "fail_reason": "spaghetti"}}"#,
+    );
+    let result = evaluate("x1c", &failed, &THRESHOLDS);
+    assert_eq!(result.status, CheckStatus::Critical);
+    assert_eq!(result.status.exit_code(), 2);
+    assert!(result.summary.contains("(spaghetti)"));
+}
+
+#[test]
+fn test_nozzle_temperature_thresholds() {
+    let warm = state_from(r#"{"print": {"command": "push_status", "nozzle_temper": 265.0}}"#);
+    let result = evaluate("x1c", &warm, &THRESHOLDS);
+    assert_eq!(result.status, CheckStatus::Warning);
+    assert!(result.summary.contains("nozzle 265.0C"));
+
+    let hot = state_from(r#"{"print": {"command": "push_status", "nozzle_temper": 305.0}}"#);
+    assert_eq!(
+        evaluate("x1c", &hot, &THRESHOLDS).status,
+        CheckStatus::Critical
+    );
+}
+
+#[test]
+fn test_worst_status_wins() {
+    let state = state_from(
+        r#"{"print": {"command": "push_status", "gcode_state": "FAILED", "nozzle_temper": 265.0}}"#,
+    );
+    assert_eq!(
+        evaluate("x1c", &state, &THRESHOLDS).status,
+        CheckStatus::Critical
+    );
+}
@@ -15,7 +15,7 @@ fn test_printer_config_creation() {
     assert_eq!(config.device_id, "device123");
     assert_eq!(config.access_code, "access123");
     assert_eq!(config.port, 8883);
-    assert_eq!(config.use_tls, true);
+    assert!(config.use_tls);
     assert_eq!(config.model, None);
     assert_eq!(config.firmware_version, None);
 }
@@ -31,6 +31,48 @@ fn test_printer_config_topics() {
 
     assert_eq!(config.report_topic(), "device/device123/report");
     assert_eq!(config.request_topic(), "device/device123/request");
+    assert_eq!(config.subscribe_topics(), vec!["device/device123/report"]);
+
+    let config = PrinterConfig {
+        extra_topics: vec![
+            "bridge/test/report".to_string(),
+            "device/device123/report".to_string(),
+        ],
+        ..config
+    };
+    assert_eq!(
+        config.subscribe_topics(),
+        vec!["device/device123/report", "bridge/test/report"]
+    );
+}
+
+#[test]
+fn test_mqtt_version_setting() {
+    let mut config = PrinterConfig::new(
+        "test".to_string(),
+        "192.168.1.100".to_string(),
+        "device123".to_string(),
+        "access123".to_string(),
+    );
+    assert_eq!(config.mqtt_version, MqttVersion::V311);
+    assert!(!toml::to_string(&config).unwrap().contains("mqtt_version"));
+
+    config.mqtt_version = MqttVersion::V5;
+    let saved = toml::to_string(&config).unwrap();
+    assert!(saved.contains(r#"mqtt_version = "5""#));
+    let loaded: PrinterConfig = toml::from_str(&saved).unwrap();
+    assert_eq!(loaded.mqtt_version, MqttVersion::V5);
+    assert_eq!(MqttVersion::V5.to_string(), "MQTT 5");
+}
+
+#[test]
+fn test_validate_topic_filter() {
+    for topic in ["bridge/x1c/report", "bridge/+/report", "bridge/#", "#"] {
+        assert!(validate_topic_filter(topic).is_ok(), "{topic}");
+    }
+    for topic in ["", "bridge/#/report", "bridge/x1c+/report", "bridge/a#"] {
+        assert!(validate_topic_filter(topic).is_err(), "{topic}");
+    }
 }
 
 #[test]
@@ -58,6 +100,7 @@ fn test_app_config_default() {
     assert_eq!(config.default_printer, None);
     assert_eq!(config.mqtt_settings.keep_alive_secs, 30);
     assert_eq!(config.mqtt_settings.retry_attempts, 5);
+    assert_eq!(config.mqtt_settings.retry_backoff, RetryBackoff::Fixed);
 }
 
 #[test]
@@ -147,6 +190,42 @@ fn test_get_printer() {
     assert!(app_config.get_printer("nonexistent").is_err());
 }
 
+#[test]
+fn test_update_nozzle() {
+    let mut app_config = AppConfig::default();
+    app_config
+        .add_printer(
+            "x1c".to_string(),
+            PrinterConfig::new(
+                "x1c".to_string(),
+                "192.168.1.100".to_string(),
+                "device123".to_string(),
+                "access123".to_string(),
+            ),
+        )
+        .unwrap();
+
+    assert!(
+        app_config
+            .update_nozzle("x1c", Some("hardened_steel"), Some(0.4))
+            .unwrap()
+    );
+    // Same values, or nothing reported, leave the config untouched
+    assert!(
+        !app_config
+            .update_nozzle("x1c", Some("hardened_steel"), Some(0.4))
+            .unwrap()
+    );
+    assert!(!app_config.update_nozzle("x1c", None, None).unwrap());
+    assert!(app_config.update_nozzle("x1c", None, Some(0.6)).unwrap());
+
+    let printer = app_config.get_printer("x1c").unwrap();
+    assert_eq!(printer.nozzle_type.as_deref(), Some("hardened_steel"));
+    assert_eq!(printer.nozzle_diameter, Some(0.6));
+
+    assert!(app_config.update_nozzle("other", None, Some(0.4)).is_err());
+}
+
 #[test]
 fn test_default_printer_operations() {
     let mut app_config = AppConfig::default();
@@ -242,6 +321,35 @@ fn test_config_path_formats() {
     assert!(json_path.to_string_lossy().ends_with("config.json"));
 }
 
+#[test]
+fn test_profile_config_paths() {
+    let dir = Path::new("/etc/pulseprint-cli");
+    assert_eq!(
+        config_path_in(Some(dir), None, "toml"),
+        dir.join("config.toml")
+    );
+    assert_eq!(
+        config_path_in(Some(dir), Some("office"), "json"),
+        dir.join("profiles").join("office").join("config.json")
+    );
+    assert_eq!(
+        config_path_in(None, None, "toml"),
+        PathBuf::from(".pulseprint-config.toml")
+    );
+}
+
+#[test]
+fn test_validate_profile_name() {
+    assert!(validate_profile_name("home").is_ok());
+    assert!(validate_profile_name("lab-2_b").is_ok());
+    for name in ["", "../etc", "a b", "home/office"] {
+        assert!(matches!(
+            validate_profile_name(name),
+            Err(ConfigError::InvalidProfile(_))
+        ));
+    }
+}
+
 #[test]
 fn test_load_nonexistent_config() {
     let temp_dir = tempdir().unwrap();
@@ -322,6 +430,69 @@ fn test_toml_format_support() {
     );
 }
 
+#[test]
+fn test_minimal_hand_written_toml() {
+    let temp_dir = tempdir().unwrap();
+    let toml_path = temp_dir.path().join("config.toml");
+    fs::write(
+        &toml_path,
+        r#"
+default_printer = "workshop"
+
+[printers.workshop]
+ip = "192.168.1.100"
+device_id = "01S00A000000000"
+access_code = "12345678"
+"#,
+    )
+    .unwrap();
+
+    let config = AppConfig::load_from_file(&toml_path).unwrap();
+    let printer = config.get_default_printer().unwrap();
+    assert_eq!(printer.name, "workshop");
+    assert_eq!(printer.port, 8883);
+    assert!(printer.use_tls);
+    assert_eq!(config.mqtt_settings.keep_alive_secs, 30);
+}
+
+#[test]
+fn test_nested_settings_round_trip_in_both_formats() {
+    let temp_dir = tempdir().unwrap();
+
+    let mut printer = PrinterConfig::new(
+        "x1c".to_string(),
+        "192.168.1.100".to_string(),
+        "01S00A000000000".to_string(),
+        "12345678".to_string(),
+    );
+    printer.zabbix = Some(toml::from_str(r#"server = "zabbix.lan""#).unwrap());
+    printer.spoolman = Some(
+        toml::from_str(
+            r#"
+url = "http://spoolman.local:7912"
+spools = { A1 = 12 }
+"#,
+        )
+        .unwrap(),
+    );
+    let mut config = AppConfig::default();
+    config.add_printer("x1c".to_string(), printer).unwrap();
+    config.alerts.ams_humidity_above = Some(40);
+    config.costs.currency = Some("EUR".to_string());
+
+    for file_name in ["config.toml", "config.json"] {
+        let path = temp_dir.path().join(file_name);
+        config.save_to_file(&path).unwrap();
+
+        let loaded = AppConfig::load_from_file(&path).unwrap();
+        let printer = loaded.get_printer("x1c").unwrap();
+        assert_eq!(printer.zabbix.as_ref().unwrap().server, "zabbix.lan");
+        assert_eq!(printer.spoolman.as_ref().unwrap().spools["A1"], 12);
+        assert_eq!(loaded.alerts.ams_humidity_above, Some(40));
+        assert_eq!(loaded.costs.currency.as_deref(), Some("EUR"));
+    }
+}
+
 #[test]
 fn test_json_format_support() {
     let temp_dir = tempdir().unwrap();
@@ -407,3 +578,963 @@ fn test_existing_config_file_discovery() {
     assert_eq!(loaded_config.printers.len(), 1);
     assert!(loaded_config.printers.contains_key("json_printer"));
 }
+
+#[test]
+fn test_display_settings_default_when_missing() {
+    let temp_dir = tempdir().unwrap();
+    let config_path = temp_dir.path().join("config.toml");
+
+    let toml_content = r#"
+[printers]
+
+[mqtt_settings]
+keep_alive_secs = 30
+connection_timeout_secs = 10
+retry_attempts = 5
+retry_delay_secs = 5
+queue_size = 10
+"#;
+    fs::write(&config_path, toml_content).unwrap();
+
+    let config = AppConfig::load_from_file(&config_path).unwrap();
+    assert!(!config.display.no_emoji);
+}
+
+#[test]
+fn test_retry_backoff_parsing() {
+    let temp_dir = tempdir().unwrap();
+    let config_path = temp_dir.path().join("config.toml");
+
+    let toml_content = r#"
+[mqtt_settings]
+keep_alive_secs = 30
+connection_timeout_secs = 10
+retry_attempts = 8
+retry_delay_secs = 2
+retry_backoff = "exponential"
+max_retry_delay_secs = 120
+queue_size = 10
+"#;
+    fs::write(&config_path, toml_content).unwrap();
+
+    let config = AppConfig::load_from_file(&config_path).unwrap();
+    assert_eq!(
+        config.mqtt_settings.retry_backoff,
+        RetryBackoff::Exponential
+    );
+    assert_eq!(config.mqtt_settings.max_retry_delay_secs, 120);
+}
+
+#[test]
+fn test_display_settings_round_trip() {
+    let temp_dir = tempdir().unwrap();
+    let config_path = temp_dir.path().join("config.toml");
+
+    let mut config = AppConfig::default();
+    config.display.no_emoji = true;
+    config.save_to_file(&config_path).unwrap();
+
+    let loaded = AppConfig::load_from_file(&config_path).unwrap();
+    assert!(loaded.display.no_emoji);
+}
+
+#[test]
+fn test_display_time_format_parsing() {
+    let temp_dir = tempdir().unwrap();
+    let config_path = temp_dir.path().join("config.toml");
+
+    let toml_content = r#"
+[printers]
+
+[mqtt_settings]
+keep_alive_secs = 30
+connection_timeout_secs = 10
+retry_attempts = 5
+retry_delay_secs = 5
+queue_size = 10
+
+[display]
+time_format = "12h"
+"#;
+    fs::write(&config_path, toml_content).unwrap();
+
+    let config = AppConfig::load_from_file(&config_path).unwrap();
+    assert_eq!(config.display.time_format, TimeFormat::TwelveHour);
+    assert_eq!(
+        AppConfig::default().display.time_format,
+        TimeFormat::TwentyFourHour
+    );
+}
+
+#[test]
+fn test_zabbix_settings_defaults() {
+    let temp_dir = tempdir().unwrap();
+    let config_path = temp_dir.path().join("config.toml");
+
+    let toml_content = r#"
+[printers.farm1]
+name = "farm1"
+ip = "192.168.1.50"
+device_id = "01S00A000000001"
+access_code = "12345678"
+port = 8883
+use_tls = true
+
+[printers.farm1.zabbix]
+server = "zabbix.local"
+
+[mqtt_settings]
+keep_alive_secs = 30
+connection_timeout_secs = 10
+retry_attempts = 5
+retry_delay_secs = 5
+queue_size = 10
+"#;
+    fs::write(&config_path, toml_content).unwrap();
+
+    let config = AppConfig::load_from_file(&config_path).unwrap();
+    let zabbix = config.printers["farm1"].zabbix.as_ref().unwrap();
+    assert_eq!(zabbix.server, "zabbix.local");
+    assert_eq!(zabbix.port, 10051);
+    assert_eq!(zabbix.host, None);
+    assert_eq!(zabbix.key_prefix, "pulseprint");
+    assert_eq!(zabbix.interval_secs, 60);
+}
+
+#[test]
+fn test_spoolman_settings_parsing() {
+    let temp_dir = tempdir().unwrap();
+    let config_path = temp_dir.path().join("config.toml");
+
+    let toml_content = r#"
+[printers.farm1]
+name = "farm1"
+ip = "192.168.1.50"
+device_id = "01S00A000000001"
+access_code = "12345678"
+port = 8883
+use_tls = true
+
+[printers.farm1.spoolman]
+url = "http://spoolman.local:7912"
+spools = { A1 = 12, B3 = 4 }
+
+[mqtt_settings]
+keep_alive_secs = 30
+connection_timeout_secs = 10
+retry_attempts = 5
+retry_delay_secs = 5
+queue_size = 10
+"#;
+    fs::write(&config_path, toml_content).unwrap();
+
+    let config = AppConfig::load_from_file(&config_path).unwrap();
+    let spoolman = config.printers["farm1"].spoolman.as_ref().unwrap();
+    assert_eq!(spoolman.url, "http://spoolman.local:7912");
+    assert_eq!(spoolman.spools["A1"], 12);
+    assert_eq!(spoolman.spools["B3"], 4);
+}
+
+#[test]
+fn test_smart_plug_settings_parsing() {
+    let temp_dir = tempdir().unwrap();
+    let config_path = temp_dir.path().join("config.toml");
+
+    let toml_content = r#"
+[printers.farm1]
+name = "farm1"
+ip = "192.168.1.50"
+device_id = "01S00A000000001"
+access_code = "12345678"
+port = 8883
+use_tls = true
+
+[printers.farm1.smart_plug]
+kind = "shelly"
+broker = "mqtt.local"
+topic = "shellies/plug-1"
+
+[mqtt_settings]
+keep_alive_secs = 30
+connection_timeout_secs = 10
+retry_attempts = 5
+retry_delay_secs = 5
+queue_size = 10
+"#;
+    fs::write(&config_path, toml_content).unwrap();
+
+    let config = AppConfig::load_from_file(&config_path).unwrap();
+    let plug = config.printers["farm1"].smart_plug.as_ref().unwrap();
+    assert_eq!(plug.kind, PlugKind::Shelly);
+    assert_eq!(plug.broker, "mqtt.local");
+    assert_eq!(plug.port, 1883);
+    assert_eq!(plug.username, None);
+    assert_eq!(plug.topic, "shellies/plug-1");
+}
+
+#[test]
+fn test_cost_settings_parsing() {
+    let temp_dir = tempdir().unwrap();
+    let config_path = temp_dir.path().join("config.toml");
+
+    let toml_content = r#"
+[printers]
+
+[mqtt_settings]
+keep_alive_secs = 30
+connection_timeout_secs = 10
+retry_attempts = 5
+retry_delay_secs = 5
+queue_size = 10
+
+[costs]
+filament_per_kg = 22.5
+electricity_per_kwh = 0.31
+currency = "EUR"
+"#;
+    fs::write(&config_path, toml_content).unwrap();
+
+    let config = AppConfig::load_from_file(&config_path).unwrap();
+    assert!(config.costs.is_configured());
+    assert_eq!(config.costs.filament_per_kg, Some(22.5));
+    assert_eq!(config.costs.electricity_per_kwh, Some(0.31));
+    assert_eq!(config.costs.printer_watts, 120.0);
+    assert_eq!(config.costs.currency.as_deref(), Some("EUR"));
+
+    assert!(!CostSettings::default().is_configured());
+}
+
+#[test]
+fn test_alert_settings_parsing() {
+    let temp_dir = tempdir().unwrap();
+    let config_path = temp_dir.path().join("config.toml");
+
+    let toml_content = r#"
+[printers]
+
+[mqtt_settings]
+keep_alive_secs = 30
+connection_timeout_secs = 10
+retry_attempts = 5
+retry_delay_secs = 5
+queue_size = 10
+
+[alerts]
+ams_humidity_above = 40
+door_open = true
+command = "notify-send PulsePrint \"$PULSEPRINT_MESSAGE\""
+
+[alerts.quiet_periods]
+connection_lost = 900
+
+[alerts.hms]
+serious = "notify"
+fatal = "pause"
+"#;
+    fs::write(&config_path, toml_content).unwrap();
+
+    let config = AppConfig::load_from_file(&config_path).unwrap();
+    assert!(config.alerts.is_configured());
+    assert_eq!(config.alerts.ams_humidity_above, Some(40));
+    assert!(config.alerts.door_open);
+    assert!(!config.alerts.sdcard_missing);
+    assert_eq!(
+        config.alerts.quiet_period("connection_lost"),
+        std::time::Duration::from_secs(900)
+    );
+    assert_eq!(
+        config.alerts.quiet_period("door_open"),
+        std::time::Duration::from_secs(300)
+    );
+    assert_eq!(
+        config.alerts.hms.action(HmsSeverity::Fatal),
+        Some(HmsAction::Pause)
+    );
+    assert_eq!(config.alerts.hms.action(HmsSeverity::Info), None);
+    assert_eq!(
+        config.alerts.command.as_deref(),
+        Some("notify-send PulsePrint \"$PULSEPRINT_MESSAGE\"")
+    );
+
+    assert!(!AlertSettings::default().is_configured());
+    let sdcard_only = AlertSettings {
+        sdcard_missing: true,
+        ..Default::default()
+    };
+    assert!(sdcard_only.is_configured());
+}
+
+#[test]
+fn test_reports_settings_parsing() {
+    let temp_dir = tempdir().unwrap();
+    let config_path = temp_dir.path().join("config.toml");
+
+    let toml_content = r#"
+[printers]
+
+[mqtt_settings]
+keep_alive_secs = 30
+connection_timeout_secs = 10
+retry_attempts = 5
+retry_delay_secs = 5
+queue_size = 10
+
+[reports]
+enabled = true
+format = "html"
+directory = "/srv/print-reports"
+"#;
+    fs::write(&config_path, toml_content).unwrap();
+
+    let config = AppConfig::load_from_file(&config_path).unwrap();
+    assert!(config.reports.enabled);
+    assert_eq!(config.reports.format, ReportFormat::Html);
+    assert_eq!(
+        config.reports.directory,
+        Some(PathBuf::from("/srv/print-reports"))
+    );
+    assert!(!AppConfig::default().reports.enabled);
+}
+
+#[test]
+fn test_validate_reports_problems() {
+    let temp_dir = tempdir().unwrap();
+    let printer = |name: &str, ip: &str, device_id: &str, access_code: &str| {
+        PrinterConfig::new(
+            name.to_string(),
+            ip.to_string(),
+            device_id.to_string(),
+            access_code.to_string(),
+        )
+    };
+
+    let mut app_config = AppConfig::default();
+    assert_eq!(
+        app_config.validate(),
+        vec![ConfigIssue::warning("printers", "No printers configured")]
+    );
+
+    app_config.printers.insert(
+        "a1".to_string(),
+        printer("a1", "192.168.1.10", "03919A000000000", "12345678"),
+    );
+    app_config.printers.insert(
+        "p1s".to_string(),
+        printer("printer", "192.168.1", "03919A000000000", "1234"),
+    );
+    app_config.default_printer = Some("x1c".to_string());
+    app_config.alerts.command = Some(format!(
+        "{} --loud",
+        temp_dir.path().join("notify.sh").display()
+    ));
+    let report_file = temp_dir.path().join("reports");
+    fs::write(&report_file, "").unwrap();
+    app_config.reports.directory = Some(report_file);
+
+    let issues = app_config.validate();
+    let locations: Vec<(&str, bool)> = issues
+        .iter()
+        .map(|issue| (issue.location.as_str(), issue.is_error))
+        .collect();
+    assert_eq!(
+        locations,
+        vec![
+            ("printers.p1s.name", false),
+            ("printers.p1s.ip", true),
+            ("printers.p1s.access_code", true),
+            ("printers.p1s.device_id", true),
+            ("default_printer", true),
+            ("reports.directory", true),
+            ("alerts.command", true),
+        ]
+    );
+    assert_eq!(issues[1].message, "Invalid IP address: 192.168.1");
+    assert!(issues[3].message.contains("also used by printer 'a1'"));
+
+    // Bare program names are resolved through PATH and not checked
+    let mut app_config = AppConfig::default();
+    app_config.printers.insert(
+        "a1".to_string(),
+        printer("a1", "192.168.1.10", "03919A000000000", "12345678"),
+    );
+    app_config.alerts.command = Some("notify-send pulseprint".to_string());
+    assert!(app_config.validate().is_empty());
+
+    app_config.printers.get_mut("a1").unwrap().extra_topics = vec![
+        "bridge/a1/report".to_string(),
+        "bridge/#/report".to_string(),
+    ];
+    let issues = app_config.validate();
+    assert_eq!(issues.len(), 1);
+    assert_eq!(issues[0].location, "printers.a1.extra_topics[1]");
+    app_config
+        .printers
+        .get_mut("a1")
+        .unwrap()
+        .extra_topics
+        .clear();
+
+    app_config
+        .alerts
+        .quiet_periods
+        .insert("door".to_string(), 60);
+    let issues = app_config.validate();
+    assert_eq!(issues.len(), 1);
+    assert_eq!(issues[0].location, "alerts.quiet_periods.door");
+    assert!(issues[0].message.contains("'door_open'"));
+}
+
+#[test]
+fn test_message_log_settings() {
+    let config: AppConfig = toml::from_str(
+        r#"
+log_messages = true
+
+[message_log]
+content = "parsed"
+rotate = "daily"
+max_age_days = 7
+"#,
+    )
+    .unwrap();
+    assert!(config.log_messages);
+    assert_eq!(config.message_log.content, MessageLogContent::Parsed);
+    assert_eq!(config.message_log.rotate, LogRotation::Daily);
+    assert_eq!(config.message_log.max_size_mb, 10);
+    assert_eq!(config.message_log.max_files, 5);
+    assert_eq!(config.message_log.max_age_days, Some(7));
+    assert!(!AppConfig::default().log_messages);
+
+    let mut config = config;
+    config.message_log.max_size_mb = 0;
+    let errors: Vec<_> = config
+        .validate()
+        .into_iter()
+        .filter(|issue| issue.is_error)
+        .map(|issue| issue.location)
+        .collect();
+    assert_eq!(errors, vec!["message_log.max_size_mb"]);
+}
+
+#[test]
+fn test_history_settings() {
+    assert_eq!(AppConfig::default().history.backend, HistoryBackend::Memory);
+
+    let config: AppConfig = toml::from_str(
+        r#"
+[history]
+backend = "file"
+path = "/srv/farm/history.jsonl"
+"#,
+    )
+    .unwrap();
+    assert_eq!(config.history.backend, HistoryBackend::File);
+    assert_eq!(
+        config.history.path,
+        Some(PathBuf::from("/srv/farm/history.jsonl"))
+    );
+    assert!(toml::from_str::<AppConfig>("[history]\nbackend = \"sqlite\"").is_err());
+}
+
+#[test]
+fn test_postgres_history_needs_url() {
+    let mut config: AppConfig = toml::from_str("[history]\nbackend = \"postgres\"").unwrap();
+    assert_eq!(config.history.backend, HistoryBackend::Postgres);
+    let errors = |config: &AppConfig| -> Vec<String> {
+        config
+            .validate()
+            .into_iter()
+            .filter(|issue| issue.location.starts_with("history"))
+            .map(|issue| issue.message)
+            .collect()
+    };
+    assert_eq!(
+        errors(&config),
+        vec!["The postgres backend needs a connection URL"]
+    );
+
+    config.history.url = Some("mysql://db/farm".to_string());
+    assert_eq!(errors(&config).len(), 1);
+
+    config.history.url = Some("postgres://pulseprint@db.example.org/farm".to_string());
+    assert!(errors(&config).is_empty());
+}
+
+#[test]
+fn test_email_settings() {
+    let mut config: AppConfig = toml::from_str(
+        "[email]\nsmtp_host = \"smtp.example.org\"\nfrom = \"farm@example.org\"\nto = []\nusername = \"farm\"",
+    )
+    .unwrap();
+    let email = config.email.as_ref().unwrap();
+    assert_eq!(email.security, EmailSecurity::Starttls);
+    assert_eq!(email.port(), 587);
+
+    let errors = |config: &AppConfig| -> Vec<String> {
+        config
+            .validate()
+            .into_iter()
+            .filter(|issue| issue.location.starts_with("email"))
+            .map(|issue| issue.message)
+            .collect()
+    };
+    assert_eq!(
+        errors(&config),
+        vec![
+            "Reports need at least one recipient",
+            "Set both username and password, or neither"
+        ]
+    );
+
+    let email = config.email.as_mut().unwrap();
+    email.to = vec!["me@example.org".to_string()];
+    email.password = Some("secret".to_string());
+    email.security = EmailSecurity::Tls;
+    assert_eq!(email.port(), 465);
+    assert!(errors(&config).is_empty());
+}
+
+#[test]
+fn test_json_schema_describes_config() {
+    let schema = serde_json::to_value(AppConfig::json_schema()).unwrap();
+    assert_eq!(schema["title"], "AppConfig");
+    assert!(schema["properties"]["printers"].is_object());
+    assert!(schema["properties"]["alerts"].is_object());
+
+    let printer = &schema["$defs"]["PrinterConfig"];
+    let required = printer["required"].as_array().unwrap();
+    assert!(required.contains(&"ip".into()));
+    assert!(!required.contains(&"name".into()));
+    assert_eq!(printer["properties"]["port"]["default"], 8883);
+    assert_eq!(
+        schema["$defs"]["ReportFormat"]["enum"],
+        serde_json::json!(["markdown", "html"])
+    );
+}
+
+#[test]
+fn test_env_overlay() {
+    let env = |vars: &'static [(&'static str, &'static str)]| {
+        move |key: &str| {
+            vars.iter()
+                .find(|(name, _)| *name == key)
+                .map(|(_, value)| value.to_string())
+        }
+    };
+
+    // No config file: the environment defines the whole printer
+    let mut app_config = AppConfig::default();
+    app_config
+        .apply_env_overlay(env(&[
+            (PRINTER_IP_ENV, "192.168.1.70"),
+            (DEVICE_ID_ENV, "01P00A000000000"),
+            (ACCESS_CODE_ENV, "12345678"),
+            (PRINTER_PORT_ENV, "8884"),
+        ]))
+        .unwrap();
+    let printer = app_config.get_default_printer().unwrap();
+    assert_eq!(printer.name, "default");
+    assert_eq!(printer.ip, "192.168.1.70");
+    assert_eq!(printer.port, 8884);
+
+    // Fields of an existing printer are overridden, the default one unless named
+    let mut app_config = AppConfig::default();
+    for name in ["x1c", "p1s"] {
+        app_config
+            .add_printer(
+                name.to_string(),
+                PrinterConfig::new(
+                    name.to_string(),
+                    "192.168.1.100".to_string(),
+                    "01S00A000000000".to_string(),
+                    "12345678".to_string(),
+                ),
+            )
+            .unwrap();
+    }
+    let mut overlaid = app_config.clone();
+    overlaid
+        .apply_env_overlay(env(&[(ACCESS_CODE_ENV, "87654321"), (PRINTER_IP_ENV, "")]))
+        .unwrap();
+    assert_eq!(overlaid.get_printer("x1c").unwrap().access_code, "87654321");
+    assert_eq!(overlaid.get_printer("x1c").unwrap().ip, "192.168.1.100");
+    assert_eq!(overlaid.get_printer("p1s").unwrap().access_code, "12345678");
+
+    let mut overlaid = app_config.clone();
+    overlaid
+        .apply_env_overlay(env(&[
+            (PRINTER_NAME_ENV, "p1s"),
+            (PRINTER_MODEL_ENV, "P1S"),
+        ]))
+        .unwrap();
+    assert_eq!(
+        overlaid.get_printer("p1s").unwrap().model.as_deref(),
+        Some("P1S")
+    );
+
+    // A new printer needs all connection details, and ports must parse
+    let mut overlaid = app_config.clone();
+    assert!(
+        overlaid
+            .apply_env_overlay(env(&[
+                (PRINTER_NAME_ENV, "a1"),
+                (PRINTER_IP_ENV, "192.168.1.80")
+            ]))
+            .is_err()
+    );
+    assert!(
+        overlaid
+            .apply_env_overlay(env(&[(PRINTER_PORT_ENV, "mqtt")]))
+            .is_err()
+    );
+
+    // Nothing set leaves the config alone
+    let mut overlaid = app_config.clone();
+    overlaid
+        .apply_env_overlay(env(&[(PRINTER_NAME_ENV, "a1")]))
+        .unwrap();
+    assert_eq!(overlaid.printers.len(), 2);
+}
+
+#[test]
+fn test_resolve_env_references() {
+    let env = |key: &str| match key {
+        "OFFICE_P1S_CODE" => Some("87654321".to_string()),
+        "SPOOLMAN_HOST" => Some("spoolman.local".to_string()),
+        _ => None,
+    };
+
+    assert_eq!(
+        resolve_references("${env:OFFICE_P1S_CODE}", "code", env).unwrap(),
+        "87654321"
+    );
+    assert_eq!(
+        resolve_references("http://${env:SPOOLMAN_HOST}:7912", "url", env).unwrap(),
+        "http://spoolman.local:7912"
+    );
+    assert_eq!(
+        resolve_references("plain $HOME {env:X}", "text", env).unwrap(),
+        "plain $HOME {env:X}"
+    );
+    assert!(resolve_references("${env:OFFICE_P1S_CODE", "code", env).is_err());
+
+    let temp_dir = tempdir().unwrap();
+    let config_path = temp_dir.path().join("config.toml");
+    fs::write(
+        &config_path,
+        r#"
+[printers.p1s]
+ip = "192.168.1.60"
+device_id = "01P00A000000000"
+access_code = "${env:OFFICE_P1S_CODE}"
+
+[printers.p1s.spoolman]
+url = "http://${env:SPOOLMAN_HOST}:7912"
+"#,
+    )
+    .unwrap();
+
+    let mut app_config = AppConfig::load_from_file(&config_path).unwrap();
+    app_config.resolve_env_references(env).unwrap();
+    let printer = app_config.get_printer("p1s").unwrap();
+    assert_eq!(printer.access_code, "87654321");
+    assert_eq!(
+        printer.spoolman.as_ref().unwrap().url,
+        "http://spoolman.local:7912"
+    );
+
+    // The file keeps the reference, and a missing variable names the setting
+    let mut app_config = AppConfig::load_from_file(&config_path).unwrap();
+    assert_eq!(
+        app_config.get_printer("p1s").unwrap().access_code,
+        "${env:OFFICE_P1S_CODE}"
+    );
+    let error = app_config.resolve_env_references(|_| None).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "printers.p1s.access_code refers to environment variable OFFICE_P1S_CODE, which is not set"
+    );
+}
+
+#[test]
+fn test_display_defaults_parsing() {
+    let temp_dir = tempdir().unwrap();
+    let config_path = temp_dir.path().join("config.toml");
+
+    fs::write(
+        &config_path,
+        r#"
+[display]
+temperature_unit = "fahrenheit"
+refresh_secs = 5
+fields = ["state", "progress", "nozzle", "ams"]
+"#,
+    )
+    .unwrap();
+
+    let config = AppConfig::load_from_file(&config_path).unwrap();
+    assert_eq!(config.display.temperature_unit, TemperatureUnit::Fahrenheit);
+    assert_eq!(config.display.refresh_secs, Some(5));
+    assert_eq!(
+        config.display.fields,
+        vec![Field::State, Field::Progress, Field::Nozzle, Field::Ams]
+    );
+
+    let defaults = DisplaySettings::default();
+    assert_eq!(defaults.temperature_unit, TemperatureUnit::Celsius);
+    assert_eq!(defaults.refresh_secs, None);
+    assert!(defaults.fields.is_empty());
+
+    let mut config = AppConfig::default();
+    config.display.refresh_secs = Some(0);
+    assert!(
+        config
+            .validate()
+            .iter()
+            .any(|issue| issue.location == "display.refresh_secs" && issue.is_error)
+    );
+}
+
+#[test]
+fn test_printer_name_suggestions_and_prefixes() {
+    let mut app_config = AppConfig::default();
+    for name in ["voron", "x1c-office", "x1c-home", "a1-mini"] {
+        app_config
+            .add_printer(
+                name.to_string(),
+                PrinterConfig::new(
+                    name.to_string(),
+                    "192.168.1.100".to_string(),
+                    "01S00A000000000".to_string(),
+                    "12345678".to_string(),
+                ),
+            )
+            .unwrap();
+    }
+
+    // Unambiguous prefixes select the printer
+    assert_eq!(app_config.get_printer("a1").unwrap().name, "a1-mini");
+    assert_eq!(
+        app_config.resolve_printer_name("x1c-h").unwrap(),
+        "x1c-home"
+    );
+    assert_eq!(
+        app_config.get_printer("x1c").unwrap_err().to_string(),
+        "Printer not found: Printer 'x1c' is ambiguous; it could be 'x1c-home' or 'x1c-office'"
+    );
+
+    assert_eq!(app_config.similar_printer_names("Voroon"), vec!["voron"]);
+    assert_eq!(
+        app_config.get_printer("voroon").unwrap_err().to_string(),
+        "Printer not found: Printer 'voroon' not found; did you mean 'voron'?"
+    );
+    assert_eq!(
+        app_config.similar_printer_names("x1c-hom"),
+        vec!["x1c-home"]
+    );
+    assert_eq!(
+        app_config
+            .set_default_printer("x1c-offce")
+            .unwrap_err()
+            .to_string(),
+        "Printer not found: Printer 'x1c-offce' not found; did you mean 'x1c-office'?"
+    );
+    // Removing never guesses
+    assert!(app_config.remove_printer("voro").is_err());
+    assert!(app_config.similar_printer_names("bambu").is_empty());
+
+    assert_eq!(edit_distance("kitten", "sitting"), 3);
+    assert_eq!(quoted_list(&["a", "b", "c"]), "'a', 'b' or 'c'");
+}
+
+#[test]
+fn test_clone_as_keeps_shared_settings() {
+    let mut source = PrinterConfig::new(
+        "p1s-1".to_string(),
+        "192.168.1.61".to_string(),
+        "01P00A000000001".to_string(),
+        "12345678".to_string(),
+    );
+    source.port = 8884;
+    source.use_tls = false;
+    source.model = Some("P1S".to_string());
+    source.firmware_version = Some("01.06.00.00".to_string());
+    source.nozzle_diameter = Some(0.4);
+    source.zabbix = Some(ZabbixSettings {
+        server: "zabbix.local".to_string(),
+        port: 10051,
+        host: Some("farm-p1s-1".to_string()),
+        key_prefix: "farm".to_string(),
+        interval_secs: 30,
+    });
+    source.spoolman = Some(SpoolmanSettings {
+        url: "http://spoolman.local:7912".to_string(),
+        spools: HashMap::from([("A1".to_string(), 7)]),
+    });
+    source.smart_plug = Some(SmartPlugSettings {
+        kind: PlugKind::Tasmota,
+        broker: "192.168.1.5".to_string(),
+        port: 1883,
+        username: None,
+        password: None,
+        topic: "plug_p1s_1".to_string(),
+    });
+
+    let copy = source.clone_as(
+        "p1s-2".to_string(),
+        "192.168.1.62".to_string(),
+        "01P00A000000002".to_string(),
+    );
+    assert_eq!(copy.name, "p1s-2");
+    assert_eq!(copy.ip, "192.168.1.62");
+    assert_eq!(copy.device_id, "01P00A000000002");
+    assert_eq!(copy.access_code, "12345678");
+    assert_eq!(copy.port, 8884);
+    assert!(!copy.use_tls);
+    assert_eq!(copy.model.as_deref(), Some("P1S"));
+
+    assert_eq!(copy.firmware_version, None);
+    assert_eq!(copy.nozzle_diameter, None);
+    let zabbix = copy.zabbix.unwrap();
+    assert_eq!(zabbix.host, None);
+    assert_eq!(zabbix.key_prefix, "farm");
+    let spoolman = copy.spoolman.unwrap();
+    assert_eq!(spoolman.url, "http://spoolman.local:7912");
+    assert!(spoolman.spools.is_empty());
+    assert!(copy.smart_plug.is_none());
+}
+
+#[test]
+fn test_printer_details() {
+    let temp_dir = tempdir().unwrap();
+    let config_path = temp_dir.path().join("config.toml");
+
+    let toml_content = r#"
+[printers.x1c]
+ip = "192.168.1.100"
+device_id = "01S00A000000000"
+access_code = "12345678"
+location = "rack-B/slot-3"
+notes = "Hardened steel nozzle"
+
+[printers.x1c.metadata]
+owner = "lab"
+"asset-tag" = "A-1042"
+"#;
+    fs::write(&config_path, toml_content).unwrap();
+
+    let config = AppConfig::load_from_file(&config_path).unwrap();
+    let printer = &config.printers["x1c"];
+
+    assert_eq!(printer.label(), "x1c (rack-B/slot-3)");
+    assert_eq!(
+        printer.details(),
+        vec![
+            ("Location".to_string(), "rack-B/slot-3".to_string()),
+            ("Notes".to_string(), "Hardened steel nozzle".to_string()),
+            ("asset-tag".to_string(), "A-1042".to_string()),
+            ("owner".to_string(), "lab".to_string()),
+        ]
+    );
+    assert!(printer.detail_environment().contains(&(
+        "PULSEPRINT_META_ASSET_TAG".to_string(),
+        "A-1042".to_string()
+    )));
+
+    // Printers without details serialize without the empty fields
+    let plain = PrinterConfig::new(
+        "a1".to_string(),
+        "192.168.1.101".to_string(),
+        "03919A000000000".to_string(),
+        "12345678".to_string(),
+    );
+    assert_eq!(plain.label(), "a1");
+    let saved = toml::to_string(&plain).unwrap();
+    assert!(!saved.contains("metadata"));
+    assert!(!saved.contains("location"));
+}
+
+#[test]
+fn test_sinks_parsing_and_validation() {
+    let temp_dir = tempdir().unwrap();
+    let config_path = temp_dir.path().join("config.toml");
+
+    let toml_content = r#"
+[[sinks]]
+type = "json_file"
+path = "/var/log/pulseprint/events.jsonl"
+
+[[sinks]]
+type = "webhook"
+url = "hooks.local/print"
+events = ["alert", "job_finished"]
+alerts = ["hms_fatal", "hms_urgent"]
+
+[[sinks]]
+type = "command"
+command = " "
+
+[[sinks]]
+type = "matrix"
+homeserver = "https://matrix.example.org"
+access_token = "syt_token"
+room_id = "!shop:example.org"
+
+[[sinks]]
+type = "matrix"
+homeserver = "matrix.example.org"
+access_token = ""
+room_id = "shop:example.org"
+"#;
+    fs::write(&config_path, toml_content).unwrap();
+
+    let config = AppConfig::load_from_file(&config_path).unwrap();
+    assert_eq!(
+        config.sinks[0].target,
+        SinkTarget::JsonFile {
+            path: PathBuf::from("/var/log/pulseprint/events.jsonl")
+        }
+    );
+    assert!(config.sinks[0].accepts(EventType::Status));
+    assert!(config.sinks[1].accepts(EventType::JobFinished));
+    assert!(!config.sinks[1].accepts(EventType::Status));
+    assert!(config.sinks[0].accepts_alert("hms_info"));
+    assert!(config.sinks[1].accepts_alert("hms_fatal"));
+    assert!(!config.sinks[1].accepts_alert("hms_info"));
+    assert_eq!(
+        config.sinks[3].target,
+        SinkTarget::Matrix {
+            homeserver: "https://matrix.example.org".to_string(),
+            access_token: "syt_token".to_string(),
+            room_id: "!shop:example.org".to_string(),
+        }
+    );
+
+    let locations: Vec<String> = config
+        .validate()
+        .into_iter()
+        .filter(|issue| issue.is_error)
+        .map(|issue| issue.location)
+        .collect();
+    assert_eq!(
+        locations,
+        vec![
+            "sinks[1].alerts",
+            "sinks[1].url",
+            "sinks[2].command",
+            "sinks[4].homeserver",
+            "sinks[4].access_token",
+            "sinks[4].room_id"
+        ]
+    );
+
+    // Saved configs keep the sinks and leave the table out when there are none
+    let saved = toml::to_string(&config).unwrap();
+    assert!(saved.contains("[[sinks]]"));
+    assert!(saved.contains(r#"events = ["alert", "job_finished"]"#));
+    assert!(
+        !toml::to_string(&AppConfig::default())
+            .unwrap()
+            .contains("sinks")
+    );
+}
@@ -0,0 +1,118 @@
+use crate::messages::DeviceMessage;
+use crate::recording::RecordedMessage;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[cfg(test)]
+mod tests;
+
+/// A single captured payload along with where it came from (file, or file:line)
+#[derive(Debug, Clone)]
+pub struct CorpusEntry {
+    pub source: String,
+    pub payload: String,
+}
+
+/// Aggregate parse results across a corpus of captured payloads
+#[derive(Debug, Default)]
+pub struct CorpusReport {
+    pub total: usize,
+    pub parsed: usize,
+    pub failures: Vec<(String, String)>,
+    pub message_types: BTreeMap<String, usize>,
+    pub unknown_fields: BTreeMap<String, usize>,
+}
+
+impl CorpusReport {
+    pub fn check(&mut self, entry: &CorpusEntry) {
+        self.total += 1;
+
+        match DeviceMessage::parse(&entry.payload) {
+            Ok(message) => {
+                self.parsed += 1;
+                *self
+                    .message_types
+                    .entry(format!("{:?}", message.get_message_type()))
+                    .or_default() += 1;
+                for field in message.unknown_fields() {
+                    *self.unknown_fields.entry(field).or_default() += 1;
+                }
+            }
+            Err(e) => {
+                self.failures.push((entry.source.clone(), e.to_string()));
+            }
+        }
+    }
+
+    /// Unknown fields ordered by how often they appeared, most frequent first
+    pub fn unknown_fields_by_frequency(&self) -> Vec<(&str, usize)> {
+        let mut fields: Vec<(&str, usize)> = self
+            .unknown_fields
+            .iter()
+            .map(|(field, count)| (field.as_str(), *count))
+            .collect();
+        fields.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        fields
+    }
+}
+
+/// Loads payloads from a file or directory. NDJSON files (`.ndjson`/`.jsonl`) yield one
+/// payload per line, accepting either `record` session lines or bare JSON payloads; any
+/// other file is treated as a single payload.
+pub fn load_entries(path: &Path) -> Result<Vec<CorpusEntry>, std::io::Error> {
+    let mut files = Vec::new();
+    collect_files(path, &mut files)?;
+    files.sort();
+
+    let mut entries = Vec::new();
+    for file in files {
+        let contents = fs::read_to_string(&file)?;
+        let display = file.display().to_string();
+
+        if is_ndjson(&file) {
+            for (index, line) in contents.lines().enumerate() {
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let payload = match serde_json::from_str::<RecordedMessage>(line) {
+                    // Binary payloads are kept as far as they go, so they show up as failures
+                    Ok(recorded) => match recorded.payload_bytes() {
+                        Ok(bytes) => String::from_utf8_lossy(&bytes).into_owned(),
+                        Err(_) => recorded.payload,
+                    },
+                    Err(_) => line.to_string(),
+                };
+                entries.push(CorpusEntry {
+                    source: format!("{display}:{}", index + 1),
+                    payload,
+                });
+            }
+        } else {
+            entries.push(CorpusEntry {
+                source: display,
+                payload: contents,
+            });
+        }
+    }
+
+    Ok(entries)
+}
+
+fn collect_files(path: &Path, files: &mut Vec<PathBuf>) -> Result<(), std::io::Error> {
+    if path.is_dir() {
+        for entry in fs::read_dir(path)? {
+            collect_files(&entry?.path(), files)?;
+        }
+    } else {
+        files.push(path.to_path_buf());
+    }
+    Ok(())
+}
+
+fn is_ndjson(path: &Path) -> bool {
+    matches!(
+        path.extension().and_then(|ext| ext.to_str()),
+        Some("ndjson") | Some("jsonl")
+    )
+}
@@ -0,0 +1,65 @@
+use super::*;
+use tempfile::tempdir;
+
+fn entry(source: &str, payload: &str) -> CorpusEntry {
+    CorpusEntry {
+        source: source.to_string(),
+        payload: payload.to_string(),
+    }
+}
+
+#[test]
+fn test_report_counts_parsed_and_failed() {
+    let mut report = CorpusReport::default();
+    report.check(&entry(
+        "a",
+        r#"{"print": {"command": "push_status", "lights_report": []}}"#,
+    ));
+    report.check(&entry(
+        "b",
+        r#"{"print": {"command": "push_status", "lights_report": [], "hms": []}}"#,
+    ));
+    report.check(&entry("c", "not json"));
+
+    assert_eq!(report.total, 3);
+    assert_eq!(report.parsed, 2);
+    assert_eq!(report.failures.len(), 1);
+    assert_eq!(report.failures[0].0, "c");
+    assert_eq!(report.message_types.get("PrintPushStatus"), Some(&2));
+    assert_eq!(
+        report.unknown_fields_by_frequency(),
+        vec![("print.lights_report", 2), ("print.hms", 1)]
+    );
+}
+
+#[test]
+fn test_load_entries_from_directory() {
+    let temp_dir = tempdir().unwrap();
+    fs::write(temp_dir.path().join("one.json"), r#"{"print": {}}"#).unwrap();
+
+    let nested = temp_dir.path().join("nested");
+    fs::create_dir(&nested).unwrap();
+    fs::write(
+        nested.join("session.ndjson"),
+        concat!(
+            r#"{"timestamp_ms":1,"topic":"t","payload":"{\"pushing\":{}}"}"#,
+            "\n\n",
+            r#"{"system": {}}"#,
+            "\n"
+        ),
+    )
+    .unwrap();
+
+    let entries = load_entries(temp_dir.path()).unwrap();
+    assert_eq!(entries.len(), 3);
+
+    let payloads: Vec<&str> = entries.iter().map(|e| e.payload.as_str()).collect();
+    assert!(payloads.contains(&r#"{"print": {}}"#));
+    assert!(payloads.contains(&r#"{"pushing":{}}"#));
+    assert!(payloads.contains(&r#"{"system": {}}"#));
+    assert!(
+        entries
+            .iter()
+            .any(|e| e.source.ends_with("session.ndjson:3"))
+    );
+}
@@ -0,0 +1,854 @@
+use crate::api;
+use crate::config::{MessageLogSettings, PrinterConfig};
+use crate::history::HistoryStore;
+use crate::jobs::{JobRecord, JobTracker};
+use crate::logging::{LoggedSource, MessageLog};
+use crate::messages::{DeviceMessage, MergedState};
+use crate::moonraker;
+use crate::mqtt::{self, MessageSource, PrintAction, PrinterControl};
+use crate::requests::{self, CommandResponse};
+use crate::schedule::{self, ScheduledPrint};
+use crate::service;
+use crate::telemetry::TelemetryBuffer;
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{File, OpenOptions, TryLockError};
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use thiserror::Error;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::TcpListener;
+use tokio::sync::{Notify, watch};
+use tokio::task::LocalSet;
+use utoipa::ToSchema;
+
+#[cfg(test)]
+mod tests;
+
+/// Printer tables for the tests of the daemon and the APIs it serves
+#[cfg(test)]
+pub(crate) mod test_support {
+    use super::{PrinterEntry, SharedPrinters};
+    use crate::messages::DeviceMessage;
+    use chrono::Local;
+    use std::sync::{Arc, Mutex};
+
+    /// Disconnected printers with these names
+    pub(crate) fn shared(names: &[&str]) -> SharedPrinters {
+        Arc::new(Mutex::new(
+            names
+                .iter()
+                .map(|name| (name.to_string(), PrinterEntry::default()))
+                .collect(),
+        ))
+    }
+
+    /// Merges a report into the printer the way its connection does, recording a job it ends
+    pub(crate) fn apply(printers: &SharedPrinters, name: &str, payload: &str) {
+        let mut printers = printers.lock().unwrap();
+        let entry = printers.get_mut(name).unwrap();
+        entry.connected = true;
+        entry.state.update(&DeviceMessage::parse(payload).unwrap());
+        if let Some(job) = entry.jobs.observe(&entry.state, Local::now()) {
+            entry.record_job(job);
+        }
+        entry.touch();
+    }
+}
+
+/// Pipe the daemon listens on under Windows; other platforms use a socket file
+pub const PIPE_NAME: &str = r"\\.\pipe\pulseprint-cli";
+
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+/// Completed jobs kept per printer
+pub const MAX_HISTORY: usize = 100;
+
+#[derive(Debug, Clone)]
+pub struct DaemonOptions {
+    /// Address for the HTTP API (see [`crate::api`]), if enabled
+    pub http: Option<SocketAddr>,
+    /// A connected printer is unhealthy when its last message is older than this
+    pub stale_after: Duration,
+    /// Address for the gRPC API, if enabled; needs the `grpc` feature
+    pub grpc: Option<SocketAddr>,
+    /// Address for the Moonraker emulation of `moonraker_printer`, if enabled
+    pub moonraker: Option<SocketAddr>,
+    pub moonraker_printer: Option<String>,
+    /// Printer connections opened at the same time; 0 means no limit
+    pub max_concurrent_connections: usize,
+    /// Log every message received, to the configured directory or the one given here
+    pub message_log: Option<(MessageLogSettings, PathBuf)>,
+    /// Where finished jobs are saved, besides memory; the latest are reloaded on start
+    pub history: Option<Arc<dyn HistoryStore>>,
+}
+
+impl Default for DaemonOptions {
+    fn default() -> Self {
+        Self {
+            http: None,
+            stale_after: Duration::from_secs(120),
+            grpc: None,
+            moonraker: None,
+            moonraker_printer: None,
+            max_concurrent_connections: 8,
+            message_log: None,
+            history: None,
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum DaemonError {
+    #[error("IO error: {0}")]
+    IoError(#[from] std::io::Error),
+
+    #[error("Invalid daemon message: {0}")]
+    JsonError(#[from] serde_json::Error),
+
+    #[error("Daemon is not running")]
+    NotRunning,
+
+    #[error("Another daemon is already listening on {0}")]
+    AlreadyRunning(String),
+
+    #[error("Daemon closed the connection without answering")]
+    NoResponse,
+
+    #[error("Another daemon is already running{}", pid_suffix(*.pid))]
+    Locked { pid: Option<u32> },
+
+    #[error("Daemon is not monitoring a printer named '{0}'")]
+    UnknownPrinter(String),
+
+    #[cfg(not(feature = "grpc"))]
+    #[error("This build has no gRPC support; rebuild with `--features grpc`")]
+    GrpcUnavailable,
+}
+
+fn pid_suffix(pid: Option<u32>) -> String {
+    pid.map(|pid| format!(" (PID {pid})")).unwrap_or_default()
+}
+
+const LOCK_FILE: &str = "daemon.lock";
+const PID_FILE: &str = "daemon.pid";
+
+/// Exclusive lock held for the daemon's lifetime, with the daemon's PID written next to it
+///
+/// The OS releases the lock when the process exits, even after a crash, so a leftover lock
+/// file never blocks the next start.
+#[derive(Debug)]
+pub struct InstanceLock {
+    _file: File,
+    pid_path: PathBuf,
+}
+
+impl InstanceLock {
+    pub fn acquire(dir: &Path) -> Result<Self, DaemonError> {
+        std::fs::create_dir_all(dir)?;
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(dir.join(LOCK_FILE))?;
+
+        match file.try_lock() {
+            Ok(()) => {}
+            Err(TryLockError::WouldBlock) => {
+                return Err(DaemonError::Locked { pid: read_pid(dir) });
+            }
+            Err(TryLockError::Error(e)) => return Err(e.into()),
+        }
+
+        let pid_path = dir.join(PID_FILE);
+        std::fs::write(&pid_path, format!("{}\n", std::process::id()))?;
+        Ok(Self {
+            _file: file,
+            pid_path,
+        })
+    }
+}
+
+impl Drop for InstanceLock {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.pid_path);
+    }
+}
+
+/// Whether a daemon holds the lock in `dir`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InstanceState {
+    NotRunning,
+    Running { pid: Option<u32> },
+}
+
+pub fn instance_state(dir: &Path) -> std::io::Result<InstanceState> {
+    let file = match File::open(dir.join(LOCK_FILE)) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            return Ok(InstanceState::NotRunning);
+        }
+        Err(e) => return Err(e),
+    };
+
+    match file.try_lock_shared() {
+        Ok(()) => Ok(InstanceState::NotRunning),
+        Err(TryLockError::WouldBlock) => Ok(InstanceState::Running { pid: read_pid(dir) }),
+        Err(TryLockError::Error(e)) => Err(e),
+    }
+}
+
+fn read_pid(dir: &Path) -> Option<u32> {
+    std::fs::read_to_string(dir.join(PID_FILE))
+        .ok()?
+        .trim()
+        .parse()
+        .ok()
+}
+
+/// One request per line from a CLI invocation to the daemon
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "request", rename_all = "snake_case")]
+pub enum Request {
+    /// State of one printer, or of every printer when `printer` is `None`
+    Status {
+        printer: Option<String>,
+    },
+    Control {
+        printer: String,
+        action: PrintAction,
+    },
+    /// Start a print later, after checking that the printer is ready for it
+    Schedule {
+        print: ScheduledPrint,
+    },
+    Shutdown,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "response", rename_all = "snake_case")]
+pub enum Response {
+    Printers { printers: Vec<PrinterSnapshot> },
+    Ok,
+    Error { message: String },
+}
+
+/// What the daemon knows about one printer
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PrinterSnapshot {
+    pub name: String,
+    pub connected: bool,
+    pub last_message_at: Option<DateTime<Local>>,
+    /// Sent as `print`, `message_count` and `energy_kwh` next to the fields above
+    #[serde(flatten)]
+    pub state: MergedState,
+}
+
+/// Live state of one printer connection inside the daemon
+#[derive(Debug, Default)]
+pub struct PrinterEntry {
+    pub state: MergedState,
+    pub connected: bool,
+    pub last_message_at: Option<DateTime<Local>>,
+    pub control: Option<PrinterControl>,
+    /// Bumped whenever the state or connection changes
+    pub changes: watch::Sender<u64>,
+    pub jobs: JobTracker,
+    /// Jobs finished or failed since the daemon started, oldest first
+    pub history: Vec<JobRecord>,
+    /// Recent temperatures and progress, served on `/printers/{name}/telemetry`
+    pub telemetry: TelemetryBuffer,
+}
+
+impl PrinterEntry {
+    /// Keeps the job in the history, dropping the oldest beyond [`MAX_HISTORY`]
+    pub fn record_job(&mut self, job: JobRecord) {
+        self.history.push(job);
+        if self.history.len() > MAX_HISTORY {
+            self.history.remove(0);
+        }
+    }
+
+    /// Marks the entry as changed, waking everyone watching it
+    pub fn touch(&self) {
+        self.changes.send_modify(|version| *version += 1);
+    }
+
+    pub fn snapshot(&self, name: &str) -> PrinterSnapshot {
+        PrinterSnapshot {
+            name: name.to_string(),
+            connected: self.connected,
+            last_message_at: self.last_message_at,
+            state: self.state.clone(),
+        }
+    }
+}
+
+pub type SharedPrinters = Arc<Mutex<BTreeMap<String, PrinterEntry>>>;
+
+/// Answers one request against the shared printer table
+pub async fn handle_request(printers: &SharedPrinters, request: Request) -> Response {
+    match request {
+        Request::Status { printer } => {
+            let printers = printers.lock().unwrap();
+            let snapshots = match printer {
+                Some(name) => match printers.get(&name) {
+                    Some(entry) => vec![entry.snapshot(&name)],
+                    None => return unknown_printer(&name),
+                },
+                None => printers
+                    .iter()
+                    .map(|(name, entry)| entry.snapshot(name))
+                    .collect(),
+            };
+            Response::Printers {
+                printers: snapshots,
+            }
+        }
+        Request::Control { printer, action } => {
+            let control = {
+                let printers = printers.lock().unwrap();
+                let Some(entry) = printers.get(&printer) else {
+                    return unknown_printer(&printer);
+                };
+                entry.control.clone()
+            };
+            let Some(control) = control else {
+                return Response::Error {
+                    message: format!("Printer '{printer}' is not connected"),
+                };
+            };
+            match control.execute(action).await {
+                Ok(_) => Response::Ok,
+                Err(e) => Response::Error {
+                    message: e.to_string(),
+                },
+            }
+        }
+        Request::Schedule { print } => {
+            if !printers.lock().unwrap().contains_key(&print.printer) {
+                return unknown_printer(&print.printer);
+            }
+            if print.at <= Local::now() {
+                return Response::Error {
+                    message: format!("Start time {} has already passed", print.at),
+                };
+            }
+            println!(
+                "Scheduled '{}' on '{}' for {}",
+                print.job.path,
+                print.printer,
+                print.at.format("%Y-%m-%d %H:%M")
+            );
+            tokio::spawn(run_scheduled(Arc::clone(printers), print));
+            Response::Ok
+        }
+        // The server loop stops after answering
+        Request::Shutdown => Response::Ok,
+    }
+}
+
+/// Waits for the start time, then starts the print if the printer passes the pre-flight checks
+async fn run_scheduled(printers: SharedPrinters, print: ScheduledPrint) {
+    let delay = (print.at - Local::now()).to_std().unwrap_or_default();
+    tokio::time::sleep(delay).await;
+
+    let control = {
+        let printers = printers.lock().unwrap();
+        let Some(entry) = printers.get(&print.printer) else {
+            return;
+        };
+        let ready = match &entry.control {
+            Some(control) if entry.connected => {
+                schedule::preflight(&entry.state).map(|()| control.clone())
+            }
+            _ => Err(schedule::PreflightError::NotConnected),
+        };
+        match ready {
+            Ok(control) => control,
+            Err(e) => {
+                eprintln!(
+                    "Scheduled print of '{}' on '{}' not started: {e}",
+                    print.job.path, print.printer
+                );
+                return;
+            }
+        }
+    };
+
+    let started = control
+        .request(print.job.payload(), requests::RESPONSE_TIMEOUT)
+        .await
+        .and_then(CommandResponse::into_result);
+    match started {
+        Ok(_) => println!(
+            "Started scheduled print of '{}' on '{}'",
+            print.job.path, print.printer
+        ),
+        Err(e) => eprintln!(
+            "Failed to start scheduled print of '{}' on '{}': {e}",
+            print.job.path, print.printer
+        ),
+    }
+}
+
+fn unknown_printer(name: &str) -> Response {
+    Response::Error {
+        message: format!("Daemon is not monitoring a printer named '{name}'"),
+    }
+}
+
+/// Connects to every printer, serves requests on `socket` and returns on shutdown, Ctrl+C or
+/// SIGTERM; reports readiness and watchdog pings to systemd when started as a notify service
+pub async fn run(
+    configs: Vec<PrinterConfig>,
+    socket: &Path,
+    options: &DaemonOptions,
+) -> Result<(), DaemonError> {
+    #[cfg(not(feature = "grpc"))]
+    if options.grpc.is_some() {
+        return Err(DaemonError::GrpcUnavailable);
+    }
+
+    if let Some(printer) = &options.moonraker_printer
+        && !configs.iter().any(|config| &config.name == printer)
+    {
+        return Err(DaemonError::UnknownPrinter(printer.clone()));
+    }
+
+    let mut listener = transport::listen(socket).await?;
+
+    // A store may answer over the network, so the histories load off the runtime
+    let mut histories = match &options.history {
+        Some(store) => {
+            let store = Arc::clone(store);
+            let names: Vec<String> = configs.iter().map(|config| config.name.clone()).collect();
+            tokio::task::spawn_blocking(move || load_histories(store.as_ref(), names))
+                .await
+                .unwrap_or_default()
+        }
+        None => BTreeMap::new(),
+    };
+    let printers: SharedPrinters = Arc::new(Mutex::new(
+        configs
+            .iter()
+            .map(|config| {
+                let entry = PrinterEntry {
+                    history: histories.remove(&config.name).unwrap_or_default(),
+                    ..PrinterEntry::default()
+                };
+                (config.name.clone(), entry)
+            })
+            .collect(),
+    ));
+
+    let http = match options.http {
+        Some(address) => {
+            let http_listener = TcpListener::bind(address).await?;
+            Some(tokio::spawn(api::serve(
+                http_listener,
+                Arc::clone(&printers),
+                options.stale_after,
+            )))
+        }
+        None => None,
+    };
+    let moonraker = match (options.moonraker, &options.moonraker_printer) {
+        (Some(address), Some(printer)) => {
+            let moonraker_listener = TcpListener::bind(address).await?;
+            Some(tokio::spawn(moonraker::serve(
+                moonraker_listener,
+                Arc::clone(&printers),
+                printer.clone(),
+            )))
+        }
+        _ => None,
+    };
+    #[cfg(feature = "grpc")]
+    let grpc = match options.grpc {
+        Some(address) => {
+            let grpc_listener = TcpListener::bind(address).await?;
+            Some(tokio::spawn(crate::grpc::serve(
+                grpc_listener,
+                Arc::clone(&printers),
+            )))
+        }
+        None => None,
+    };
+
+    // The MQTT client is not `Sync`, so printer connections run on a local task set; they
+    // are cancelled when it is dropped
+    let connections = LocalSet::new();
+    let pool = mqtt::ConnectionPool::new(options.max_concurrent_connections);
+    for config in configs {
+        connections.spawn_local(follow_printer(
+            Arc::new(config),
+            Arc::clone(&printers),
+            pool.clone(),
+            options.message_log.clone(),
+            options.history.clone(),
+        ));
+    }
+
+    if let Err(e) = service::notify(&format!(
+        "READY=1\nSTATUS=Monitoring {} printer(s)",
+        printers.lock().unwrap().len()
+    )) {
+        eprintln!("Failed to notify systemd: {e}");
+    }
+    let mut watchdog = service::watchdog_interval().map(tokio::time::interval);
+
+    let shutdown = Arc::new(Notify::new());
+    let result = connections
+        .run_until(async {
+            loop {
+                tokio::select! {
+                    _ = async {
+                        match watchdog.as_mut() {
+                            Some(ticker) => ticker.tick().await,
+                            None => std::future::pending().await,
+                        }
+                    } => {
+                        let _ = service::notify("WATCHDOG=1");
+                    }
+                    accepted = listener.accept() => match accepted {
+                        Ok(stream) => {
+                            tokio::spawn(serve_connection(
+                                stream,
+                                Arc::clone(&printers),
+                                Arc::clone(&shutdown),
+                            ));
+                        }
+                        Err(e) => break Err(e.into()),
+                    },
+                    _ = shutdown.notified() => break Ok(()),
+                    _ = stop_signal() => break Ok(()),
+                }
+            }
+        })
+        .await;
+    drop(connections);
+    if let Some(http) = http {
+        http.abort();
+    }
+    if let Some(moonraker) = moonraker {
+        moonraker.abort();
+    }
+    #[cfg(feature = "grpc")]
+    if let Some(grpc) = grpc {
+        grpc.abort();
+    }
+    let _ = service::notify("STOPPING=1");
+
+    transport::cleanup(socket);
+    result
+}
+
+/// The latest jobs of each printer in `store`; printers whose history cannot be read start
+/// with none
+fn load_histories(
+    store: &dyn HistoryStore,
+    names: Vec<String>,
+) -> BTreeMap<String, Vec<JobRecord>> {
+    names
+        .into_iter()
+        .filter_map(|name| match store.recent(&name, MAX_HISTORY) {
+            Ok(jobs) => Some((name, jobs)),
+            Err(e) => {
+                eprintln!("Printer '{name}': cannot load the job history: {e}");
+                None
+            }
+        })
+        .collect()
+}
+
+/// Ctrl+C, or SIGTERM as sent by `systemctl stop`
+async fn stop_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{SignalKind, signal};
+        if let Ok(mut terminate) = signal(SignalKind::terminate()) {
+            tokio::select! {
+                _ = tokio::signal::ctrl_c() => {}
+                _ = terminate.recv() => {}
+            }
+            return;
+        }
+    }
+    let _ = tokio::signal::ctrl_c().await;
+}
+
+/// Reads requests line by line until the client hangs up
+pub async fn serve_connection<S>(stream: S, printers: SharedPrinters, shutdown: Arc<Notify>)
+where
+    S: AsyncRead + AsyncWrite,
+{
+    let (reader, mut writer) = tokio::io::split(stream);
+    let mut lines = BufReader::new(reader).lines();
+
+    while let Ok(Some(line)) = lines.next_line().await {
+        let request = serde_json::from_str::<Request>(&line);
+        let stop = matches!(request, Ok(Request::Shutdown));
+        let response = match request {
+            Ok(request) => handle_request(&printers, request).await,
+            Err(e) => Response::Error {
+                message: format!("Invalid request: {e}"),
+            },
+        };
+
+        let Ok(mut encoded) = serde_json::to_string(&response) else {
+            break;
+        };
+        encoded.push('\n');
+        if writer.write_all(encoded.as_bytes()).await.is_err() {
+            break;
+        }
+        if stop {
+            shutdown.notify_one();
+            break;
+        }
+    }
+}
+
+/// Keeps one printer connected, reconnecting after every failure
+async fn follow_printer(
+    config: Arc<PrinterConfig>,
+    printers: SharedPrinters,
+    pool: mqtt::ConnectionPool,
+    message_log: Option<(MessageLogSettings, PathBuf)>,
+    history: Option<Arc<dyn HistoryStore>>,
+) {
+    let mut attempt = 0;
+    loop {
+        let log = message_log
+            .as_ref()
+            .map(|(settings, default_dir)| MessageLog::new(settings, default_dir, &config.name));
+        if let Err(e) = follow_once(&config, &printers, &pool, log, history.as_deref()).await {
+            eprintln!("Printer '{}': {e}", config.name);
+        }
+        if let Some(entry) = printers.lock().unwrap().get_mut(&config.name) {
+            entry.connected = false;
+            entry.control = None;
+            entry.touch();
+        }
+        attempt += 1;
+        tokio::time::sleep(mqtt::reconnect_delay(
+            &config.name,
+            attempt,
+            RECONNECT_DELAY,
+        ))
+        .await;
+    }
+}
+
+async fn follow_once(
+    config: &Arc<PrinterConfig>,
+    printers: &SharedPrinters,
+    pool: &mqtt::ConnectionPool,
+    log: Option<MessageLog>,
+    history: Option<&dyn HistoryStore>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut client = pool.open(Arc::clone(config)).await?;
+    client.connect().await?;
+    client.request_pushall().await?;
+    let control = client.control();
+    let requests = client.requests();
+    let mut eventloop = LoggedSource::new(client.get_eventloop(), log);
+
+    while let Some(publish) = eventloop.next_publish().await? {
+        requests.resolve(&publish.payload);
+        let Ok(message) = DeviceMessage::parse_from(&publish.topic, &publish.payload) else {
+            continue;
+        };
+
+        let finished = {
+            let mut printers = printers.lock().unwrap();
+            let Some(entry) = printers.get_mut(&config.name) else {
+                break;
+            };
+            if !entry.connected {
+                println!("Printer '{}' connected", config.name);
+            }
+            entry.connected = true;
+            entry.control = Some(control.clone());
+            let now = Local::now();
+            entry.last_message_at = Some(now);
+            entry.state.update(&message);
+            entry.telemetry.record(&entry.state, now);
+            let finished = entry.jobs.observe(&entry.state, now);
+            if let Some(job) = &finished {
+                entry.record_job(job.clone());
+            }
+            entry.state.started_at = entry.jobs.start().map(|start| start.started_at);
+            entry.touch();
+            finished
+        };
+
+        // Saved outside the lock, so a slow store does not hold up the APIs
+        if let (Some(store), Some(job)) = (history, finished)
+            && let Err(e) = store.record(&config.name, &job)
+        {
+            eprintln!(
+                "Printer '{}': cannot save the finished job: {e}",
+                config.name
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Health of one printer connection as reported on `/healthz`
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct PrinterHealth {
+    pub name: String,
+    pub connected: bool,
+    pub last_message_age_secs: Option<i64>,
+    pub healthy: bool,
+}
+
+/// Overall health is good only when every printer is connected and recently heard from
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct HealthReport {
+    pub healthy: bool,
+    pub printers: Vec<PrinterHealth>,
+}
+
+pub fn health_report(
+    printers: &SharedPrinters,
+    now: DateTime<Local>,
+    stale_after: Duration,
+) -> HealthReport {
+    let printers: Vec<_> = printers
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(name, entry)| {
+            let age = entry
+                .last_message_at
+                .map(|last| (now - last).num_seconds().max(0));
+            let fresh = age.is_some_and(|age| age as u64 <= stale_after.as_secs());
+            PrinterHealth {
+                name: name.clone(),
+                connected: entry.connected,
+                last_message_age_secs: age,
+                healthy: entry.connected && fresh,
+            }
+        })
+        .collect();
+
+    HealthReport {
+        healthy: printers.iter().all(|printer| printer.healthy),
+        printers,
+    }
+}
+
+/// Sends one request to a running daemon and waits for the answer
+pub async fn request(socket: &Path, request: &Request) -> Result<Response, DaemonError> {
+    let stream = transport::connect(socket)
+        .await
+        .map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound | std::io::ErrorKind::ConnectionRefused => {
+                DaemonError::NotRunning
+            }
+            _ => DaemonError::IoError(e),
+        })?;
+    exchange(stream, request).await
+}
+
+async fn exchange<S>(stream: S, request: &Request) -> Result<Response, DaemonError>
+where
+    S: AsyncRead + AsyncWrite,
+{
+    let (reader, mut writer) = tokio::io::split(stream);
+    let mut encoded = serde_json::to_string(request)?;
+    encoded.push('\n');
+    writer.write_all(encoded.as_bytes()).await?;
+
+    let line = BufReader::new(reader)
+        .lines()
+        .next_line()
+        .await?
+        .ok_or(DaemonError::NoResponse)?;
+    Ok(serde_json::from_str(&line)?)
+}
+
+#[cfg(unix)]
+mod transport {
+    use super::DaemonError;
+    use std::path::Path;
+    use tokio::net::{UnixListener, UnixStream};
+
+    pub struct Listener(UnixListener);
+
+    impl Listener {
+        pub async fn accept(&mut self) -> std::io::Result<UnixStream> {
+            Ok(self.0.accept().await?.0)
+        }
+    }
+
+    /// Binds the socket, replacing a stale file left behind by a daemon that did not exit cleanly
+    pub async fn listen(path: &Path) -> Result<Listener, DaemonError> {
+        if path.exists() {
+            if UnixStream::connect(path).await.is_ok() {
+                return Err(DaemonError::AlreadyRunning(path.display().to_string()));
+            }
+            std::fs::remove_file(path)?;
+        }
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        Ok(Listener(UnixListener::bind(path)?))
+    }
+
+    pub async fn connect(path: &Path) -> std::io::Result<UnixStream> {
+        UnixStream::connect(path).await
+    }
+
+    pub fn cleanup(path: &Path) {
+        let _ = std::fs::remove_file(path);
+    }
+}
+
+#[cfg(windows)]
+mod transport {
+    use super::DaemonError;
+    use std::ffi::OsString;
+    use std::path::Path;
+    use tokio::net::windows::named_pipe::{
+        ClientOptions, NamedPipeClient, NamedPipeServer, ServerOptions,
+    };
+
+    /// Named pipes serve one client per instance, so a fresh instance is created per accept
+    pub struct Listener {
+        name: OsString,
+        next: NamedPipeServer,
+    }
+
+    impl Listener {
+        pub async fn accept(&mut self) -> std::io::Result<NamedPipeServer> {
+            self.next.connect().await?;
+            let next = ServerOptions::new().create(&self.name)?;
+            Ok(std::mem::replace(&mut self.next, next))
+        }
+    }
+
+    pub async fn listen(path: &Path) -> Result<Listener, DaemonError> {
+        let next = ServerOptions::new()
+            .first_pipe_instance(true)
+            .create(path)
+            .map_err(|_| DaemonError::AlreadyRunning(path.display().to_string()))?;
+        Ok(Listener {
+            name: path.as_os_str().to_owned(),
+            next,
+        })
+    }
+
+    pub async fn connect(path: &Path) -> std::io::Result<NamedPipeClient> {
+        ClientOptions::new().open(path)
+    }
+
+    pub fn cleanup(_path: &Path) {}
+}
@@ -0,0 +1,261 @@
+use super::*;
+use crate::daemon::test_support::shared;
+use tempfile::tempdir;
+
+#[test]
+fn test_request_wire_format() {
+    let request = Request::Control {
+        printer: "x1c".to_string(),
+        action: PrintAction::Pause,
+    };
+    let encoded = serde_json::to_string(&request).unwrap();
+    assert_eq!(
+        encoded,
+        r#"{"request":"control","printer":"x1c","action":"pause"}"#
+    );
+    assert_eq!(serde_json::from_str::<Request>(&encoded).unwrap(), request);
+
+    assert_eq!(
+        serde_json::from_str::<Request>(r#"{"request":"status","printer":null}"#).unwrap(),
+        Request::Status { printer: None }
+    );
+}
+
+#[tokio::test]
+async fn test_status_reports_known_printers() {
+    let printers = shared(&["b", "a"]);
+    {
+        let mut printers = printers.lock().unwrap();
+        let entry = printers.get_mut("a").unwrap();
+        entry.connected = true;
+        entry.state.update(
+            &DeviceMessage::parse(
+                r#"{"print": {"command": "push_status", "gcode_state": "RUNNING", "percent": 40}}"#,
+            )
+            .unwrap(),
+        );
+    }
+
+    let Response::Printers { printers: all } =
+        handle_request(&printers, Request::Status { printer: None }).await
+    else {
+        panic!("expected printers");
+    };
+    let names: Vec<_> = all.iter().map(|printer| printer.name.as_str()).collect();
+    assert_eq!(names, vec!["a", "b"]);
+
+    let state = &all[0].state;
+    assert!(all[0].connected);
+    assert_eq!(state.message_count, 1);
+    assert_eq!(state.status().progress, Some(40));
+
+    let response = handle_request(
+        &printers,
+        Request::Status {
+            printer: Some("missing".to_string()),
+        },
+    )
+    .await;
+    assert!(matches!(response, Response::Error { message } if message.contains("'missing'")));
+}
+
+#[tokio::test]
+async fn test_control_requires_connection() {
+    let printers = shared(&["x1c"]);
+    let response = handle_request(
+        &printers,
+        Request::Control {
+            printer: "x1c".to_string(),
+            action: PrintAction::Resume,
+        },
+    )
+    .await;
+    assert!(matches!(response, Response::Error { message } if message.contains("not connected")));
+}
+
+#[tokio::test]
+async fn test_schedule_validates_printer_and_time() {
+    let printers = shared(&["x1c"]);
+    let print = |printer: &str, at: DateTime<Local>| Request::Schedule {
+        print: ScheduledPrint {
+            printer: printer.to_string(),
+            at,
+            job: mqtt::ProjectFile {
+                path: "benchy.gcode.3mf".to_string(),
+                plate: 1,
+                bed_leveling: true,
+                flow_calibration: false,
+                timelapse: false,
+                ams_mapping: Vec::new(),
+            },
+        },
+    };
+    let later = Local::now() + chrono::Duration::hours(1);
+
+    let response = handle_request(&printers, print("missing", later)).await;
+    assert!(matches!(response, Response::Error { message } if message.contains("'missing'")));
+
+    let earlier = Local::now() - chrono::Duration::minutes(1);
+    let response = handle_request(&printers, print("x1c", earlier)).await;
+    assert!(matches!(response, Response::Error { message } if message.contains("passed")));
+
+    let response = handle_request(&printers, print("x1c", later)).await;
+    assert!(matches!(response, Response::Ok));
+
+    // Survives a round trip over the control socket
+    let encoded = serde_json::to_string(&print("x1c", later)).unwrap();
+    assert_eq!(
+        serde_json::from_str::<Request>(&encoded).unwrap(),
+        print("x1c", later)
+    );
+}
+
+#[tokio::test]
+async fn test_request_without_daemon() {
+    let temp_dir = tempdir().unwrap();
+    let result = request(
+        &temp_dir.path().join("daemon.sock"),
+        &Request::Status { printer: None },
+    )
+    .await;
+    assert!(matches!(result, Err(DaemonError::NotRunning)));
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_run_serves_requests_until_shutdown() {
+    let temp_dir = tempdir().unwrap();
+    let socket = temp_dir.path().join("daemon.sock");
+    let mut config = PrinterConfig::new(
+        "x1c".to_string(),
+        "127.0.0.1".to_string(),
+        "01S00A000000000".to_string(),
+        "12345678".to_string(),
+    );
+    // Nothing listens here, so the printer stays disconnected
+    config.port = 1;
+
+    let client = async {
+        while !socket.exists() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        let Response::Printers { printers } = request(&socket, &Request::Status { printer: None })
+            .await
+            .unwrap()
+        else {
+            panic!("expected printers");
+        };
+        assert_eq!(printers.len(), 1);
+        assert!(!printers[0].connected);
+
+        // A second daemon on the same socket is refused
+        let second = run(Vec::new(), &socket, &DaemonOptions::default()).await;
+        assert!(matches!(second, Err(DaemonError::AlreadyRunning(_))));
+
+        assert!(matches!(
+            request(&socket, &Request::Shutdown).await.unwrap(),
+            Response::Ok
+        ));
+    };
+
+    let options = DaemonOptions::default();
+    let (result, ()) = tokio::join!(run(vec![config], &socket, &options), client);
+    result.unwrap();
+    assert!(!socket.exists());
+}
+
+#[test]
+fn test_health_report_flags_stale_and_disconnected_printers() {
+    let printers = shared(&["fresh", "silent", "stale"]);
+    let now = Local::now();
+    {
+        let mut printers = printers.lock().unwrap();
+        let fresh = printers.get_mut("fresh").unwrap();
+        fresh.connected = true;
+        fresh.last_message_at = Some(now - chrono::Duration::seconds(5));
+        let stale = printers.get_mut("stale").unwrap();
+        stale.connected = true;
+        stale.last_message_at = Some(now - chrono::Duration::seconds(300));
+    }
+
+    let report = health_report(&printers, now, Duration::from_secs(120));
+    assert!(!report.healthy);
+    assert_eq!(
+        report.printers,
+        vec![
+            PrinterHealth {
+                name: "fresh".to_string(),
+                connected: true,
+                last_message_age_secs: Some(5),
+                healthy: true,
+            },
+            PrinterHealth {
+                name: "silent".to_string(),
+                connected: false,
+                last_message_age_secs: None,
+                healthy: false,
+            },
+            PrinterHealth {
+                name: "stale".to_string(),
+                connected: true,
+                last_message_age_secs: Some(300),
+                healthy: false,
+            },
+        ]
+    );
+
+    let report = health_report(&shared(&[]), now, Duration::from_secs(120));
+    assert!(report.healthy);
+}
+
+#[test]
+fn test_instance_lock_is_exclusive_and_records_pid() {
+    let temp_dir = tempdir().unwrap();
+    assert_eq!(
+        instance_state(temp_dir.path()).unwrap(),
+        InstanceState::NotRunning
+    );
+
+    let lock = InstanceLock::acquire(temp_dir.path()).unwrap();
+    let pid = Some(std::process::id());
+    assert_eq!(
+        instance_state(temp_dir.path()).unwrap(),
+        InstanceState::Running { pid }
+    );
+
+    let second = InstanceLock::acquire(temp_dir.path()).unwrap_err();
+    assert!(matches!(second, DaemonError::Locked { pid: found } if found == pid));
+    assert_eq!(
+        second.to_string(),
+        format!(
+            "Another daemon is already running (PID {})",
+            std::process::id()
+        )
+    );
+
+    drop(lock);
+    assert!(!temp_dir.path().join("daemon.pid").exists());
+    assert_eq!(
+        instance_state(temp_dir.path()).unwrap(),
+        InstanceState::NotRunning
+    );
+    InstanceLock::acquire(temp_dir.path()).unwrap();
+}
+
+#[test]
+fn test_load_histories() {
+    let temp_dir = tempdir().unwrap();
+    let path = temp_dir.path().join("history.jsonl");
+    let store = crate::history::FileHistory::new(&path);
+    let names = vec!["x1c".to_string(), "p1s".to_string()];
+
+    // Nothing saved yet
+    let histories = load_histories(&store, names.clone());
+    assert_eq!(histories.len(), 2);
+    assert!(histories["x1c"].is_empty());
+
+    // An unreadable history leaves the printers without jobs
+    std::fs::write(&path, "not json\n").unwrap();
+    assert!(load_histories(&store, names).is_empty());
+}
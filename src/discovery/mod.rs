@@ -0,0 +1,115 @@
+use std::collections::BTreeMap;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::time::Duration;
+use tokio::net::UdpSocket;
+
+#[cfg(test)]
+mod tests;
+
+/// Printers announce themselves with SSDP `NOTIFY` messages sent to this port
+pub const ANNOUNCE_PORT: u16 = 2021;
+/// Printers answer `M-SEARCH` requests sent to the SSDP group on this port
+const SEARCH_PORT: u16 = 1990;
+const SSDP_GROUP: Ipv4Addr = Ipv4Addr::new(239, 255, 255, 250);
+const SEARCH_TARGET: &str = "urn:bambulab-com:device:3dprinter:1";
+
+/// Printer found on the local network
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiscoveredPrinter {
+    pub ip: String,
+    /// Serial number, used as the MQTT device ID
+    pub device_id: String,
+    /// Name given to the printer in Bambu Studio or Handy
+    pub name: Option<String>,
+    pub model: Option<String>,
+}
+
+/// Parses an SSDP `NOTIFY` or search response from a Bambu printer; other SSDP traffic on
+/// the network yields `None`. `source` is used when the message carries no address.
+pub fn parse_announcement(message: &str, source: IpAddr) -> Option<DiscoveredPrinter> {
+    let headers: BTreeMap<String, &str> = message
+        .lines()
+        .filter_map(|line| line.split_once(':'))
+        .map(|(key, value)| (key.trim().to_ascii_lowercase(), value.trim()))
+        .collect();
+
+    let target = headers.get("nt").or_else(|| headers.get("st"))?;
+    if !target.starts_with("urn:bambulab-com:device:3dprinter") {
+        return None;
+    }
+    let device_id = headers.get("usn").filter(|usn| !usn.is_empty())?;
+    let ip = headers
+        .get("location")
+        .filter(|location| location.parse::<Ipv4Addr>().is_ok())
+        .map(|location| location.to_string())
+        .unwrap_or_else(|| source.to_string());
+
+    Some(DiscoveredPrinter {
+        ip,
+        device_id: device_id.to_string(),
+        name: headers
+            .get("devname.bambu.com")
+            .filter(|name| !name.is_empty())
+            .map(|name| name.to_string()),
+        model: headers
+            .get("devmodel.bambu.com")
+            .map(|model| model_name(model).to_string()),
+    })
+}
+
+/// Marketing name for the model code a printer announces, e.g. `C12` for the P1S
+pub fn model_name(code: &str) -> &str {
+    match code {
+        "3DPrinter-X1-Carbon" | "BL-P001" => "X1 Carbon",
+        "3DPrinter-X1" | "BL-P002" => "X1",
+        "C13" => "X1E",
+        "C11" => "P1P",
+        "C12" => "P1S",
+        "N1" => "A1 mini",
+        "N2S" => "A1",
+        other => other,
+    }
+}
+
+fn search_request() -> String {
+    format!(
+        "M-SEARCH * HTTP/1.1\r\nHOST: {SSDP_GROUP}:{SEARCH_PORT}\r\nMAN: \"ssdp:discover\"\r\n\
+         MX: 1\r\nST: {SEARCH_TARGET}\r\n\r\n"
+    )
+}
+
+/// Listens for printer announcements for `timeout`, asking printers to answer right away.
+/// Printers are returned once each, ordered by IP address.
+pub async fn discover(timeout: Duration) -> std::io::Result<Vec<DiscoveredPrinter>> {
+    // Another slicer may already hold the announcement port; search replies still arrive
+    // on any port
+    let socket = match UdpSocket::bind((Ipv4Addr::UNSPECIFIED, ANNOUNCE_PORT)).await {
+        Ok(socket) => socket,
+        Err(_) => UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).await?,
+    };
+    socket.set_broadcast(true)?;
+    let _ = socket.join_multicast_v4(SSDP_GROUP, Ipv4Addr::UNSPECIFIED);
+    let search = SocketAddr::from((SSDP_GROUP, SEARCH_PORT));
+    let _ = socket.send_to(search_request().as_bytes(), search).await;
+
+    let mut found: BTreeMap<String, DiscoveredPrinter> = BTreeMap::new();
+    let mut buffer = [0u8; 2048];
+    let deadline = tokio::time::Instant::now() + timeout;
+    while let Ok(received) = tokio::time::timeout_at(deadline, socket.recv_from(&mut buffer)).await
+    {
+        let (len, source) = received?;
+        let message = String::from_utf8_lossy(&buffer[..len]);
+        if let Some(printer) = parse_announcement(&message, source.ip()) {
+            found.insert(printer.device_id.clone(), printer);
+        }
+    }
+
+    let mut printers: Vec<DiscoveredPrinter> = found.into_values().collect();
+    printers.sort_by_key(|printer| {
+        printer
+            .ip
+            .parse::<Ipv4Addr>()
+            .unwrap_or(Ipv4Addr::UNSPECIFIED)
+    });
+    Ok(printers)
+}
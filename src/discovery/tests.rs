@@ -0,0 +1,61 @@
+use super::*;
+
+const NOTIFY: &str = "NOTIFY * HTTP/1.1\r\n\
+HOST: 239.255.255.250:1990\r\n\
+Server: UPnP/1.0\r\n\
+Location: 192.168.1.100\r\n\
+NT: urn:bambulab-com:device:3dprinter:1\r\n\
+USN: 01P00A000000000\r\n\
+Cache-Control: max-age=1800\r\n\
+DevModel.bambu.com: C12\r\n\
+DevName.bambu.com: Workshop P1S\r\n\
+DevSignal.bambu.com: -44\r\n\
+DevConnect.bambu.com: lan\r\n\
+DevBind.bambu.com: free\r\n\r\n";
+
+fn source() -> IpAddr {
+    IpAddr::from([192, 168, 1, 100])
+}
+
+#[test]
+fn test_parse_announcement() {
+    assert_eq!(
+        parse_announcement(NOTIFY, source()),
+        Some(DiscoveredPrinter {
+            ip: "192.168.1.100".to_string(),
+            device_id: "01P00A000000000".to_string(),
+            name: Some("Workshop P1S".to_string()),
+            model: Some("P1S".to_string()),
+        })
+    );
+}
+
+#[test]
+fn test_parse_search_response_without_location() {
+    let response = "HTTP/1.1 200 OK\r\nST: urn:bambulab-com:device:3dprinter:1\r\n\
+                    USN: 00M09A000000000\r\nDevModel.bambu.com: 3DPrinter-X1-Carbon\r\n\r\n";
+    let printer = parse_announcement(response, IpAddr::from([10, 0, 0, 7])).unwrap();
+    assert_eq!(printer.ip, "10.0.0.7");
+    assert_eq!(printer.model.as_deref(), Some("X1 Carbon"));
+    assert_eq!(printer.name, None);
+}
+
+#[test]
+fn test_other_ssdp_traffic_is_ignored() {
+    let router = "NOTIFY * HTTP/1.1\r\nNT: upnp:rootdevice\r\nUSN: uuid:1234\r\n\r\n";
+    assert_eq!(parse_announcement(router, source()), None);
+
+    let no_serial = "NOTIFY * HTTP/1.1\r\nNT: urn:bambulab-com:device:3dprinter:1\r\n\r\n";
+    assert_eq!(parse_announcement(no_serial, source()), None);
+}
+
+#[test]
+fn test_model_name_and_search_request() {
+    assert_eq!(model_name("N2S"), "A1");
+    assert_eq!(model_name("O1D"), "O1D");
+
+    let request = search_request();
+    assert!(request.starts_with("M-SEARCH * HTTP/1.1\r\n"));
+    assert!(request.contains("ST: urn:bambulab-com:device:3dprinter:1\r\n"));
+    assert!(request.ends_with("\r\n\r\n"));
+}
@@ -0,0 +1,662 @@
+pub use crate::config::{Field, TemperatureUnit, TimeFormat};
+use crate::messages::{PrintInfo, WifiQuality};
+use crate::monitor::PrinterEvent;
+use chrono::{DateTime, Local, TimeZone, Timelike};
+use std::fmt;
+use std::io::Write;
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+#[cfg(test)]
+mod tests;
+
+const PROGRESS_BAR_WIDTH: usize = 20;
+
+static ASCII_MODE: AtomicBool = AtomicBool::new(false);
+static COLOR_ENABLED: AtomicBool = AtomicBool::new(false);
+static TRUECOLOR: AtomicBool = AtomicBool::new(false);
+static TWELVE_HOUR_CLOCK: AtomicBool = AtomicBool::new(false);
+static FAHRENHEIT: AtomicBool = AtomicBool::new(false);
+static SELECTED_FIELDS: OnceLock<Vec<Field>> = OnceLock::new();
+static FILAMENT_LOW_PERCENT: OnceLock<u32> = OnceLock::new();
+
+/// Nozzle temperatures at or above these thresholds are highlighted
+pub const NOZZLE_WARN_TEMP: f64 = 260.0;
+pub const NOZZLE_CRITICAL_TEMP: f64 = 300.0;
+/// Bed temperatures at or above these thresholds are highlighted
+const BED_WARN_TEMP: f64 = 100.0;
+const BED_CRITICAL_TEMP: f64 = 115.0;
+
+/// Switches all icons and symbols to plain ASCII for the rest of the process
+pub fn set_ascii_mode(enabled: bool) {
+    ASCII_MODE.store(enabled, Ordering::Relaxed);
+}
+
+pub fn ascii_mode() -> bool {
+    ASCII_MODE.load(Ordering::Relaxed)
+}
+
+/// Restricts monitor output to the given fields; may only be set once per process
+pub fn set_fields(fields: Vec<Field>) {
+    let _ = SELECTED_FIELDS.set(fields);
+}
+
+/// Every field is shown unless a selection was made
+pub fn field_enabled(field: Field) -> bool {
+    SELECTED_FIELDS
+        .get()
+        .is_none_or(|fields| fields.contains(&field))
+}
+
+/// Highlights spools below this remaining percentage; may only be set once per process
+pub fn set_filament_low_percent(percent: u32) {
+    let _ = FILAMENT_LOW_PERCENT.set(percent);
+}
+
+pub fn filament_low_percent() -> Option<u32> {
+    FILAMENT_LOW_PERCENT.get().copied()
+}
+
+pub fn set_color_enabled(enabled: bool) {
+    COLOR_ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+pub fn color_enabled() -> bool {
+    COLOR_ENABLED.load(Ordering::Relaxed)
+}
+
+/// Colors are used only when nothing opts out (flag, config, `NO_COLOR`) and stdout is a
+/// terminal, so piped output stays free of escape codes
+pub fn should_use_color(
+    no_color_flag: bool,
+    no_color_config: bool,
+    no_color_env: Option<&std::ffi::OsStr>,
+    is_terminal: bool,
+) -> bool {
+    let env_opt_out = no_color_env.is_some_and(|value| !value.is_empty());
+    !no_color_flag && !no_color_config && !env_opt_out && is_terminal
+}
+
+pub fn set_truecolor(enabled: bool) {
+    TRUECOLOR.store(enabled, Ordering::Relaxed);
+}
+
+pub fn truecolor() -> bool {
+    TRUECOLOR.load(Ordering::Relaxed)
+}
+
+/// Terminals announce 24-bit color support through `COLORTERM`; the others get the nearest
+/// of the 256 standard colors
+pub fn supports_truecolor(colorterm: Option<&std::ffi::OsStr>) -> bool {
+    colorterm.is_some_and(|value| value == "truecolor" || value == "24bit")
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Color {
+    Red,
+    Green,
+    Yellow,
+    Cyan,
+}
+
+impl Color {
+    fn ansi_code(self) -> &'static str {
+        match self {
+            Color::Red => "31",
+            Color::Green => "32",
+            Color::Yellow => "33",
+            Color::Cyan => "36",
+        }
+    }
+}
+
+/// A value rendered in a color when color output is enabled
+pub struct Painted<T> {
+    value: T,
+    color: Option<Color>,
+}
+
+pub fn paint<T>(value: T, color: Option<Color>) -> Painted<T> {
+    Painted { value, color }
+}
+
+impl<T> Painted<T> {
+    fn write_with(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+        write_value: impl FnOnce(&T, &mut fmt::Formatter<'_>) -> fmt::Result,
+    ) -> fmt::Result {
+        match self.color {
+            Some(color) if color_enabled() => {
+                write!(f, "\x1b[{}m", color.ansi_code())?;
+                write_value(&self.value, f)?;
+                f.write_str("\x1b[0m")
+            }
+            _ => write_value(&self.value, f),
+        }
+    }
+}
+
+// Both impls forward the formatter so width/precision specs like `{:.1}` still apply
+impl<T: fmt::Display> fmt::Display for Painted<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write_with(f, |value, f| fmt::Display::fmt(value, f))
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Painted<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write_with(f, |value, f| fmt::Debug::fmt(value, f))
+    }
+}
+
+/// Block in the color of a printer-reported hex color (`RRGGBB`, optionally followed by
+/// alpha), or `None` when colors are off or the value does not parse
+pub fn swatch(hex: &str) -> Option<String> {
+    if !color_enabled() {
+        return None;
+    }
+    let (r, g, b) = parse_rgb(hex)?;
+    let block = if ascii_mode() { "##" } else { "██" };
+    Some(if truecolor() {
+        format!("\x1b[38;2;{r};{g};{b}m{block}\x1b[0m")
+    } else {
+        format!("\x1b[38;5;{}m{block}\x1b[0m", ansi256(r, g, b))
+    })
+}
+
+pub fn parse_rgb(hex: &str) -> Option<(u8, u8, u8)> {
+    let rgb = hex
+        .get(..6)
+        .filter(|rgb| rgb.chars().all(|c| c.is_ascii_hexdigit()))?;
+    let channel = |i: usize| u8::from_str_radix(&rgb[i..i + 2], 16).ok();
+    Some((channel(0)?, channel(2)?, channel(4)?))
+}
+
+/// Nearest entry of the xterm 256-color palette: the gray ramp for grays, otherwise the
+/// 6x6x6 color cube
+pub fn ansi256(r: u8, g: u8, b: u8) -> u8 {
+    if r == g && g == b {
+        return match r {
+            0..8 => 16,
+            249.. => 231,
+            gray => 232 + ((gray - 8) / 10).min(23),
+        };
+    }
+    // Cube levels are 0, 95, 135, 175, 215 and 255
+    let level = |value: u8| match value {
+        0..48 => 0,
+        48..115 => 1,
+        value => (value - 35) / 40,
+    };
+    16 + 36 * level(r) + 6 * level(g) + level(b)
+}
+
+/// Remaining filament of a spool, e.g. `45%`, or `8% (low)` in red below `low_percent`
+pub fn format_filament_remain(remain: u32, low_percent: Option<u32>) -> String {
+    if low_percent.is_some_and(|limit| remain < limit) {
+        paint(format!("{remain}% (low)"), Some(Color::Red)).to_string()
+    } else {
+        format!("{remain}%")
+    }
+}
+
+pub fn nozzle_temp_color(temp: f64) -> Option<Color> {
+    temperature_color(temp, NOZZLE_WARN_TEMP, NOZZLE_CRITICAL_TEMP)
+}
+
+pub fn bed_temp_color(temp: f64) -> Option<Color> {
+    temperature_color(temp, BED_WARN_TEMP, BED_CRITICAL_TEMP)
+}
+
+fn temperature_color(temp: f64, warn: f64, critical: f64) -> Option<Color> {
+    if temp >= critical {
+        Some(Color::Red)
+    } else if temp >= warn {
+        Some(Color::Yellow)
+    } else {
+        None
+    }
+}
+
+pub fn wifi_color(quality: WifiQuality) -> Option<Color> {
+    Some(match quality {
+        WifiQuality::Excellent => Color::Green,
+        WifiQuality::Good => Color::Yellow,
+        WifiQuality::Weak => Color::Red,
+    })
+}
+
+/// WiFi signal with its quality, e.g. `-45dBm (excellent)`; as reported when it is not a
+/// dBm value
+pub fn format_wifi(print: &PrintInfo) -> Option<String> {
+    let Some(dbm) = print.wifi_dbm() else {
+        return print.wifi_signal.clone();
+    };
+    let quality = WifiQuality::from_dbm(dbm);
+    let label = format!("{dbm}dBm ({})", quality.name());
+    Some(paint(label, wifi_color(quality)).to_string())
+}
+
+pub fn set_time_format(format: TimeFormat) {
+    TWELVE_HOUR_CLOCK.store(format == TimeFormat::TwelveHour, Ordering::Relaxed);
+}
+
+pub fn time_format() -> TimeFormat {
+    if TWELVE_HOUR_CLOCK.load(Ordering::Relaxed) {
+        TimeFormat::TwelveHour
+    } else {
+        TimeFormat::TwentyFourHour
+    }
+}
+
+/// Formats a wall-clock time as `18:42` or `6:42 PM`
+pub fn format_clock_time<Tz: TimeZone>(time: &DateTime<Tz>, format: TimeFormat) -> String {
+    match format {
+        TimeFormat::TwentyFourHour => format!("{:02}:{:02}", time.hour(), time.minute()),
+        TimeFormat::TwelveHour => {
+            let (is_pm, hour) = time.hour12();
+            let suffix = if is_pm { "PM" } else { "AM" };
+            format!("{hour}:{:02} {suffix}", time.minute())
+        }
+    }
+}
+
+/// Estimated finish time in the configured clock style, e.g. `18:42`
+pub fn format_finish_time(finish_at: &DateTime<Local>) -> String {
+    format_clock_time(finish_at, time_format())
+}
+
+pub fn set_temperature_unit(unit: TemperatureUnit) {
+    FAHRENHEIT.store(unit == TemperatureUnit::Fahrenheit, Ordering::Relaxed);
+}
+
+pub fn temperature_unit() -> TemperatureUnit {
+    if FAHRENHEIT.load(Ordering::Relaxed) {
+        TemperatureUnit::Fahrenheit
+    } else {
+        TemperatureUnit::Celsius
+    }
+}
+
+/// A reading from the printer, which always reports Celsius, in the configured unit
+pub fn temperature(celsius: f64) -> f64 {
+    match temperature_unit() {
+        TemperatureUnit::Celsius => celsius,
+        TemperatureUnit::Fahrenheit => celsius * 9.0 / 5.0 + 32.0,
+    }
+}
+
+/// Temperature unit suffix
+pub fn degrees() -> &'static str {
+    match (temperature_unit(), ascii_mode()) {
+        (TemperatureUnit::Celsius, false) => "°C",
+        (TemperatureUnit::Celsius, true) => "C",
+        (TemperatureUnit::Fahrenheit, false) => "°F",
+        (TemperatureUnit::Fahrenheit, true) => "F",
+    }
+}
+
+/// Status markers printed in front of output lines
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Icon {
+    Success,
+    Default,
+    Printer,
+    Removed,
+    Hint,
+    Info,
+    Unknown,
+    Failure,
+    Dedupe,
+    Stop,
+    Recording,
+    Play,
+    Simulate,
+    Publish,
+    Pushall,
+    System,
+    Monitoring,
+    Idle,
+    Printing,
+    Paused,
+    Finished,
+    Temperature,
+    Bed,
+    Layer,
+    Timer,
+    Wifi,
+    Fan,
+    Filament,
+    Humidity,
+    Alert,
+    Serial,
+    Firmware,
+    Online,
+    Rejected,
+    Offline,
+    ListItem,
+    Inspection,
+}
+
+impl Icon {
+    pub fn emoji(self) -> &'static str {
+        // Emoji with a variation selector render narrow in many terminals, so a few carry
+        // an extra trailing space to keep the text after them aligned
+        match self {
+            Icon::Success => "✅",
+            Icon::Default => "🎯",
+            Icon::Printer => "🖨️",
+            Icon::Removed => "🗑️ ",
+            Icon::Hint => "💡",
+            Icon::Info => "📨",
+            Icon::Unknown => "❓",
+            Icon::Failure => "❌",
+            Icon::Dedupe => "🔁",
+            Icon::Stop => "⏹️ ",
+            Icon::Recording => "📼",
+            Icon::Play => "▶️ ",
+            Icon::Simulate => "🧪",
+            Icon::Publish => "📤",
+            Icon::Pushall => "📊",
+            Icon::System => "🔧",
+            Icon::Monitoring => "📡",
+            Icon::Idle => "💤",
+            Icon::Printing => "🖨️",
+            Icon::Paused => "⏸️",
+            Icon::Finished => "✅",
+            Icon::Temperature => "🌡️",
+            Icon::Bed => "🛏️",
+            Icon::Layer => "📄",
+            Icon::Timer => "⏱️",
+            Icon::Wifi => "📶",
+            Icon::Fan => "🌀",
+            Icon::Filament => "🧵",
+            Icon::Humidity => "💧",
+            Icon::Alert => "🚨",
+            Icon::Serial => "🏷️",
+            Icon::Firmware => "📦",
+            Icon::Online => "🟢",
+            Icon::Rejected => "🟡",
+            Icon::Offline => "🔴",
+            Icon::ListItem => "📄",
+            Icon::Inspection => "🔍",
+        }
+    }
+
+    pub fn ascii(self) -> &'static str {
+        match self {
+            Icon::Success => "[OK]",
+            Icon::Default => "[DEFAULT]",
+            Icon::Printer => "[PRINTER]",
+            Icon::Removed => "[REMOVED]",
+            Icon::Hint => "[HINT]",
+            Icon::Info => "[INFO]",
+            Icon::Unknown => "[?]",
+            Icon::Failure => "[FAIL]",
+            Icon::Dedupe => "[DEDUPE]",
+            Icon::Stop => "[STOP]",
+            Icon::Recording => "[REC]",
+            Icon::Play => "[PLAY]",
+            Icon::Simulate => "[SIM]",
+            Icon::Publish => "[PUB]",
+            Icon::Pushall => "[STATUS]",
+            Icon::System => "[SYSTEM]",
+            Icon::Monitoring => "[LIVE]",
+            Icon::Idle => "[IDLE]",
+            Icon::Printing => "[PRINTING]",
+            Icon::Paused => "[PAUSED]",
+            Icon::Finished => "[DONE]",
+            Icon::Temperature => "[TEMP]",
+            Icon::Bed => "[BED]",
+            Icon::Layer => "[LAYER]",
+            Icon::Timer => "[TIME]",
+            Icon::Wifi => "[WIFI]",
+            Icon::Fan => "[FAN]",
+            Icon::Filament => "[FILAMENT]",
+            Icon::Humidity => "[AMS]",
+            Icon::Alert => "[ALERT]",
+            Icon::Serial => "[SN]",
+            Icon::Firmware => "[FW]",
+            Icon::Online => "[ONLINE]",
+            Icon::Rejected => "[DENIED]",
+            Icon::Offline => "[OFFLINE]",
+            Icon::ListItem => "-",
+            Icon::Inspection => "[INSPECT]",
+        }
+    }
+}
+
+impl fmt::Display for Icon {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if ascii_mode() {
+            f.write_str(self.ascii())
+        } else {
+            f.write_str(self.emoji())
+        }
+    }
+}
+
+/// Tracks terminal output state so a progress line can be redrawn in place
+pub struct StatusDisplay {
+    in_place: bool,
+    line_active: bool,
+}
+
+impl StatusDisplay {
+    pub fn new(in_place: bool) -> Self {
+        Self {
+            in_place,
+            line_active: false,
+        }
+    }
+
+    pub fn in_place(&self) -> bool {
+        self.in_place
+    }
+
+    /// Redraws the current progress line without emitting a newline
+    pub fn update_progress(&mut self, line: &str) {
+        let mut stdout = std::io::stdout().lock();
+        let _ = write!(stdout, "\r\x1b[2K{line}");
+        let _ = stdout.flush();
+        self.line_active = true;
+    }
+
+    /// Ends an in-place progress line so regular output starts on a fresh line
+    pub fn finish_line(&mut self) {
+        if self.line_active {
+            println!();
+            self.line_active = false;
+        }
+    }
+
+    /// Clears the terminal and draws `panel` from the top-left corner
+    pub fn redraw_screen(&mut self, panel: &str) {
+        let mut stdout = std::io::stdout().lock();
+        let _ = write!(stdout, "\x1b[2J\x1b[H{panel}");
+        let _ = stdout.flush();
+        self.line_active = false;
+    }
+}
+
+/// Draws values as a one-line bar chart scaled between their minimum and maximum
+pub fn render_sparkline<'a>(values: impl IntoIterator<Item = &'a f64>) -> String {
+    const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+    const ASCII_BARS: [char; 8] = ['_', '.', ',', '-', '=', '+', '*', '#'];
+
+    let values: Vec<f64> = values.into_iter().copied().collect();
+    let bars = if ascii_mode() { ASCII_BARS } else { BARS };
+    let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+    let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    let span = max - min;
+
+    values
+        .iter()
+        .map(|value| {
+            if span <= f64::EPSILON {
+                return bars[0];
+            }
+            let level = ((value - min) / span * (bars.len() - 1) as f64).round() as usize;
+            bars[level.min(bars.len() - 1)]
+        })
+        .collect()
+}
+
+/// Renders a title over label/value rows with the values aligned in one column
+pub fn render_panel(title: &str, rows: &[(&str, String)]) -> String {
+    let rule = if ascii_mode() { "=" } else { "─" };
+    let label_width = rows
+        .iter()
+        .map(|(label, _)| label.chars().count())
+        .max()
+        .unwrap_or(0);
+
+    let mut panel = format!("{title}\n{}\n", rule.repeat(title.chars().count()));
+    for (label, value) in rows {
+        panel.push_str(&format!("{label:<label_width$}  {value}\n"));
+    }
+    panel
+}
+
+/// Progress details shown on the in-place progress line
+#[derive(Debug, Clone, Default)]
+pub struct ProgressInfo {
+    pub percent: u32,
+    pub layer: Option<u32>,
+    pub total_layers: Option<u32>,
+    pub remaining_secs: Option<u32>,
+    pub finish_at: Option<String>,
+}
+
+pub fn render_progress_bar(info: &ProgressInfo) -> String {
+    let percent = info.percent.min(100);
+    let filled = percent as usize * PROGRESS_BAR_WIDTH / 100;
+
+    let (full, empty) = if ascii_mode() {
+        ("#", "-")
+    } else {
+        ("█", "░")
+    };
+
+    let mut line = format!(
+        "[{}{}] {percent:>3}%",
+        full.repeat(filled),
+        empty.repeat(PROGRESS_BAR_WIDTH - filled)
+    );
+
+    match (info.layer, info.total_layers) {
+        (Some(layer), Some(total)) if total > 0 => {
+            line.push_str(&format!(" | Layer {layer}/{total}"));
+        }
+        (Some(layer), _) => line.push_str(&format!(" | Layer {layer}")),
+        _ => {}
+    }
+
+    if let Some(remaining) = info.remaining_secs {
+        line.push_str(&format!(" | {} left", format_duration_short(remaining)));
+    }
+
+    if let Some(finish_at) = &info.finish_at {
+        line.push_str(&format!(" | ETA {finish_at}"));
+    }
+
+    line
+}
+
+/// One console line for an event of a library [`PrinterMonitor`](crate::monitor::PrinterMonitor)
+/// or [`EventDetector`](crate::monitor::EventDetector); `None` for status updates, which a
+/// status line already shows
+pub fn render_event(event: &PrinterEvent) -> Option<String> {
+    let line = match event {
+        PrinterEvent::ConnectionChanged {
+            connected: true, ..
+        } => format!("{} Connected", Icon::Online),
+        PrinterEvent::ConnectionChanged { error, .. } => match error {
+            Some(error) => format!("{} Disconnected: {error}", Icon::Offline),
+            None => format!("{} Disconnected", Icon::Offline),
+        },
+        PrinterEvent::StatusUpdated(_) => return None,
+        PrinterEvent::TemperatureChanged(temperatures) => {
+            let readings: Vec<String> = [
+                ("Nozzle", temperatures.nozzle),
+                ("Bed", temperatures.bed),
+                ("Chamber", temperatures.chamber),
+            ]
+            .into_iter()
+            .filter_map(|(label, reading)| {
+                Some(format!("{label} {:.0}{}", temperature(reading?), degrees()))
+            })
+            .collect();
+            format!("{} {}", Icon::Temperature, readings.join(" | "))
+        }
+        PrinterEvent::JobStarted { name } => match name {
+            Some(name) => format!("{} Started {name}", Icon::Printing),
+            None => format!("{} Started a print", Icon::Printing),
+        },
+        PrinterEvent::HmsRaised(entry) => match entry.severity {
+            Some(severity) => format!("{} HMS {} ({})", Icon::Alert, entry.code, severity.name()),
+            None => format!("{} HMS {}", Icon::Alert, entry.code),
+        },
+        PrinterEvent::AmsChanged(ams) => {
+            let units: Vec<String> = ams
+                .units
+                .iter()
+                .flatten()
+                .map(|unit| {
+                    let trays: Vec<&str> = unit
+                        .trays
+                        .iter()
+                        .map(|tray| {
+                            tray.tray_type
+                                .as_deref()
+                                .filter(|t| !t.is_empty())
+                                .unwrap_or("-")
+                        })
+                        .collect();
+                    format!("{}: {}", unit.label(), trays.join("/"))
+                })
+                .collect();
+            if units.is_empty() {
+                format!("{} AMS changed", Icon::Printer)
+            } else {
+                format!("{} {}", Icon::Printer, units.join(", "))
+            }
+        }
+    };
+    Some(line)
+}
+
+/// Parses a duration such as `90`, `45s`, `10m` or `2h` (bare numbers are seconds)
+pub fn parse_duration(value: &str) -> Result<std::time::Duration, String> {
+    let trimmed = value.trim();
+    let (number, multiplier) = match trimmed.char_indices().last() {
+        Some((index, 's')) => (&trimmed[..index], 1),
+        Some((index, 'm')) => (&trimmed[..index], 60),
+        Some((index, 'h')) => (&trimmed[..index], 3600),
+        Some((index, 'd')) => (&trimmed[..index], 86400),
+        _ => (trimmed, 1),
+    };
+
+    let invalid =
+        || format!("Invalid duration '{value}' (expected e.g. '90s', '10m', '2h', '30d')");
+    let number: u64 = number.trim().parse().map_err(|_| invalid())?;
+
+    if number == 0 {
+        return Err(format!("Duration must be greater than zero, got '{value}'"));
+    }
+
+    let secs = number.checked_mul(multiplier).ok_or_else(invalid)?;
+    Ok(std::time::Duration::from_secs(secs))
+}
+
+/// Formats seconds as `1h 5m` or `16m`, dropping seconds
+pub fn format_duration_short(secs: u32) -> String {
+    let hours = secs / 3600;
+    let minutes = (secs % 3600) / 60;
+    if hours > 0 {
+        format!("{hours}h {minutes}m")
+    } else {
+        format!("{minutes}m")
+    }
+}
@@ -0,0 +1,270 @@
+use super::*;
+
+#[test]
+fn test_render_progress_bar_full_details() {
+    let info = ProgressInfo {
+        percent: 45,
+        layer: Some(12),
+        total_layers: Some(120),
+        remaining_secs: Some(3900),
+        finish_at: Some("18:42".to_string()),
+    };
+
+    assert_eq!(
+        render_progress_bar(&info),
+        "[█████████░░░░░░░░░░░]  45% | Layer 12/120 | 1h 5m left | ETA 18:42"
+    );
+}
+
+#[test]
+fn test_render_progress_bar_minimal() {
+    let info = ProgressInfo {
+        percent: 0,
+        ..Default::default()
+    };
+    assert_eq!(render_progress_bar(&info), "[░░░░░░░░░░░░░░░░░░░░]   0%");
+
+    let info = ProgressInfo {
+        percent: 250,
+        layer: Some(7),
+        ..Default::default()
+    };
+    assert_eq!(
+        render_progress_bar(&info),
+        "[████████████████████] 100% | Layer 7"
+    );
+}
+
+#[test]
+fn test_format_duration_short() {
+    assert_eq!(format_duration_short(0), "0m");
+    assert_eq!(format_duration_short(1015), "16m");
+    assert_eq!(format_duration_short(7260), "2h 1m");
+}
+
+#[test]
+fn test_status_display_in_place_flag() {
+    assert!(StatusDisplay::new(true).in_place());
+    assert!(!StatusDisplay::new(false).in_place());
+}
+
+#[test]
+fn test_icon_ascii_labels_are_ascii() {
+    for icon in [
+        Icon::Success,
+        Icon::Removed,
+        Icon::Stop,
+        Icon::Printing,
+        Icon::Temperature,
+        Icon::ListItem,
+    ] {
+        assert!(icon.ascii().is_ascii(), "{icon:?} has a non-ASCII label");
+        assert!(!icon.emoji().is_ascii(), "{icon:?} has no emoji");
+    }
+}
+
+#[test]
+fn test_should_use_color() {
+    use std::ffi::OsStr;
+
+    assert!(should_use_color(false, false, None, true));
+    // Piped output never gets colors
+    assert!(!should_use_color(false, false, None, false));
+    assert!(!should_use_color(true, false, None, true));
+    assert!(!should_use_color(false, true, None, true));
+    assert!(!should_use_color(false, false, Some(OsStr::new("1")), true));
+    // An empty NO_COLOR does not opt out
+    assert!(should_use_color(false, false, Some(OsStr::new("")), true));
+}
+
+#[test]
+fn test_supports_truecolor() {
+    use std::ffi::OsStr;
+
+    assert!(supports_truecolor(Some(OsStr::new("truecolor"))));
+    assert!(supports_truecolor(Some(OsStr::new("24bit"))));
+    assert!(!supports_truecolor(Some(OsStr::new("yes"))));
+    assert!(!supports_truecolor(None));
+}
+
+#[test]
+fn test_swatch_colors() {
+    assert_eq!(parse_rgb("FF6A13FF"), Some((255, 106, 19)));
+    assert_eq!(parse_rgb("00ae42"), Some((0, 174, 66)));
+    assert_eq!(parse_rgb("FFF"), None);
+    assert_eq!(parse_rgb("+F0000"), None);
+
+    assert_eq!(ansi256(0, 0, 0), 16);
+    assert_eq!(ansi256(255, 255, 255), 231);
+    assert_eq!(ansi256(128, 128, 128), 244);
+    assert_eq!(ansi256(245, 245, 245), 255);
+    assert_eq!(ansi256(255, 0, 0), 196);
+    assert_eq!(ansi256(0, 174, 66), 35);
+}
+
+#[test]
+fn test_filament_remain() {
+    assert_eq!(format_filament_remain(45, None), "45%");
+    assert_eq!(format_filament_remain(45, Some(10)), "45%");
+    assert_eq!(format_filament_remain(8, Some(10)), "8% (low)");
+}
+
+#[test]
+fn test_temperature_colors() {
+    assert_eq!(nozzle_temp_color(220.0), None);
+    assert_eq!(nozzle_temp_color(265.0), Some(Color::Yellow));
+    assert_eq!(nozzle_temp_color(305.0), Some(Color::Red));
+    assert_eq!(bed_temp_color(60.0), None);
+    assert_eq!(bed_temp_color(100.0), Some(Color::Yellow));
+    assert_eq!(bed_temp_color(120.0), Some(Color::Red));
+}
+
+#[test]
+fn test_wifi_display() {
+    let print = |signal: &str| PrintInfo {
+        wifi_signal: Some(signal.to_string()),
+        ..Default::default()
+    };
+    assert_eq!(format_wifi(&print("-45dBm")).unwrap(), "-45dBm (excellent)");
+    assert_eq!(format_wifi(&print("-78dBm")).unwrap(), "-78dBm (weak)");
+    assert_eq!(format_wifi(&print("n/a")).unwrap(), "n/a");
+    assert_eq!(format_wifi(&PrintInfo::default()), None);
+
+    assert_eq!(wifi_color(WifiQuality::Excellent), Some(Color::Green));
+    assert_eq!(wifi_color(WifiQuality::Good), Some(Color::Yellow));
+    assert_eq!(wifi_color(WifiQuality::Weak), Some(Color::Red));
+}
+
+#[test]
+fn test_painted_forwards_format_spec_without_color() {
+    // Color output is disabled unless explicitly enabled at startup
+    assert_eq!(format!("{:.1}", paint(219.84, Some(Color::Red))), "219.8");
+    assert_eq!(format!("{:?}", paint("x", None)), "\"x\"");
+}
+
+#[test]
+fn test_format_clock_time() {
+    use chrono::{FixedOffset, TimeZone};
+
+    let offset = FixedOffset::east_opt(0).unwrap();
+    let evening = offset.with_ymd_and_hms(2025, 8, 1, 18, 42, 5).unwrap();
+    let morning = offset.with_ymd_and_hms(2025, 8, 1, 0, 7, 0).unwrap();
+
+    assert_eq!(
+        format_clock_time(&evening, TimeFormat::TwentyFourHour),
+        "18:42"
+    );
+    assert_eq!(
+        format_clock_time(&evening, TimeFormat::TwelveHour),
+        "6:42 PM"
+    );
+    assert_eq!(
+        format_clock_time(&morning, TimeFormat::TwentyFourHour),
+        "00:07"
+    );
+    assert_eq!(
+        format_clock_time(&morning, TimeFormat::TwelveHour),
+        "12:07 AM"
+    );
+}
+
+#[test]
+fn test_parse_duration() {
+    use std::time::Duration;
+
+    assert_eq!(parse_duration("90").unwrap(), Duration::from_secs(90));
+    assert_eq!(parse_duration("45s").unwrap(), Duration::from_secs(45));
+    assert_eq!(parse_duration("10m").unwrap(), Duration::from_secs(600));
+    assert_eq!(parse_duration(" 2h ").unwrap(), Duration::from_secs(7200));
+    assert_eq!(
+        parse_duration("30d").unwrap(),
+        Duration::from_secs(30 * 86400)
+    );
+    assert!(parse_duration("0").is_err());
+    assert!(parse_duration("m").is_err());
+    assert!(parse_duration("soon").is_err());
+}
+
+#[test]
+fn test_parse_duration_overflow() {
+    assert_eq!(
+        parse_duration(&format!("{}s", u64::MAX)).unwrap(),
+        std::time::Duration::from_secs(u64::MAX)
+    );
+    let error = parse_duration("9999999999999999d").unwrap_err();
+    assert!(error.starts_with("Invalid duration"), "{error}");
+    assert!(parse_duration(&format!("{}m", u64::MAX / 60 + 1)).is_err());
+}
+
+#[test]
+fn test_render_panel_aligns_values() {
+    let rows = [
+        ("State", "Printing".to_string()),
+        ("Progress", "45%".to_string()),
+    ];
+
+    assert_eq!(
+        render_panel("x1c", &rows),
+        "x1c\n───\nState     Printing\nProgress  45%\n"
+    );
+}
+
+#[test]
+fn test_render_sparkline_scales_to_range() {
+    assert_eq!(render_sparkline(&[20.0, 60.0, 100.0, 240.0]), "▁▂▄█");
+    assert_eq!(render_sparkline(&[55.0, 55.0, 55.0]), "▁▁▁");
+    assert_eq!(render_sparkline(&[]), "");
+}
+
+#[test]
+fn test_temperature_conversion() {
+    set_temperature_unit(TemperatureUnit::Fahrenheit);
+    assert_eq!(temperature(0.0), 32.0);
+    assert_eq!(temperature(220.0), 428.0);
+    set_temperature_unit(TemperatureUnit::Celsius);
+    assert_eq!(temperature(220.0), 220.0);
+}
+
+#[test]
+fn test_render_event() {
+    use crate::config::HmsSeverity;
+    use crate::jobs::HmsEntry;
+    use crate::monitor::Temperatures;
+
+    let disconnected = PrinterEvent::ConnectionChanged {
+        connected: false,
+        error: Some("connection refused".to_string()),
+    };
+    assert_eq!(
+        render_event(&disconnected).unwrap(),
+        format!("{} Disconnected: connection refused", Icon::Offline)
+    );
+    let status = PrinterEvent::StatusUpdated(Box::default());
+    assert_eq!(render_event(&status), None);
+
+    let started = PrinterEvent::JobStarted {
+        name: Some("benchy".to_string()),
+    };
+    assert_eq!(
+        render_event(&started).unwrap(),
+        format!("{} Started benchy", Icon::Printing)
+    );
+
+    let hms = PrinterEvent::HmsRaised(HmsEntry {
+        code: "0300_0200_0001_0001".to_string(),
+        severity: Some(HmsSeverity::Serious),
+    });
+    assert_eq!(
+        render_event(&hms).unwrap(),
+        format!("{} HMS 0300_0200_0001_0001 (serious)", Icon::Alert)
+    );
+
+    let temperatures = PrinterEvent::TemperatureChanged(Temperatures {
+        nozzle: Some(215.0),
+        bed: Some(60.0),
+        chamber: None,
+    });
+    let line = render_event(&temperatures).unwrap();
+    assert!(line.contains("Nozzle ") && line.contains(" | Bed "));
+    assert!(!line.contains("Chamber"));
+}
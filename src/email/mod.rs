@@ -0,0 +1,85 @@
+use crate::config::{EmailSecurity, EmailSettings, ReportFormat};
+use lettre::message::Mailbox;
+use lettre::message::header::ContentType;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{Message, SmtpTransport, Transport};
+use std::time::Duration;
+use thiserror::Error;
+
+#[cfg(test)]
+mod tests;
+
+const SEND_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Error)]
+pub enum EmailError {
+    #[error("Invalid email address: {0}")]
+    Address(#[from] lettre::address::AddressError),
+
+    #[error("Failed to build email: {0}")]
+    Message(#[from] lettre::error::Error),
+
+    #[error("SMTP error: {0}")]
+    Smtp(#[from] lettre::transport::smtp::Error),
+}
+
+/// Sends documents such as the monthly report to the recipients in `[email]`
+pub struct EmailNotifier {
+    transport: SmtpTransport,
+    from: Mailbox,
+    to: Vec<Mailbox>,
+}
+
+impl EmailNotifier {
+    pub fn new(settings: &EmailSettings) -> Result<Self, EmailError> {
+        let builder = match settings.security {
+            EmailSecurity::Starttls => SmtpTransport::starttls_relay(&settings.smtp_host)?,
+            EmailSecurity::Tls => SmtpTransport::relay(&settings.smtp_host)?,
+            EmailSecurity::Plain => SmtpTransport::builder_dangerous(&settings.smtp_host),
+        };
+        let mut builder = builder.port(settings.port()).timeout(Some(SEND_TIMEOUT));
+        if let (Some(username), Some(password)) = (&settings.username, &settings.password) {
+            builder = builder.credentials(Credentials::new(username.clone(), password.clone()));
+        }
+
+        Ok(Self {
+            transport: builder.build(),
+            from: settings.from.parse()?,
+            to: settings
+                .to
+                .iter()
+                .map(|address| address.parse())
+                .collect::<Result<_, _>>()?,
+        })
+    }
+
+    /// Email with `body` inline, as HTML or as plain text for Markdown
+    pub fn message(
+        &self,
+        subject: &str,
+        body: String,
+        format: ReportFormat,
+    ) -> Result<Message, EmailError> {
+        let mut builder = Message::builder().from(self.from.clone()).subject(subject);
+        for to in &self.to {
+            builder = builder.to(to.clone());
+        }
+        let content_type = match format {
+            ReportFormat::Markdown => ContentType::TEXT_PLAIN,
+            ReportFormat::Html => ContentType::TEXT_HTML,
+        };
+        Ok(builder.header(content_type).body(body)?)
+    }
+
+    /// Blocks until the SMTP server accepted the email
+    pub fn send(
+        &self,
+        subject: &str,
+        body: String,
+        format: ReportFormat,
+    ) -> Result<(), EmailError> {
+        let message = self.message(subject, body, format)?;
+        self.transport.send(&message)?;
+        Ok(())
+    }
+}
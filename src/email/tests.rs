@@ -0,0 +1,95 @@
+use super::*;
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+
+fn settings(port: u16) -> EmailSettings {
+    EmailSettings {
+        smtp_host: "127.0.0.1".to_string(),
+        port: Some(port),
+        security: EmailSecurity::Plain,
+        username: None,
+        password: None,
+        from: "PulsePrint <farm@example.org>".to_string(),
+        to: vec!["me@example.org".to_string(), "you@example.org".to_string()],
+    }
+}
+
+/// Accepts one SMTP session and returns the DATA it received
+fn fake_smtp_server() -> (u16, std::thread::JoinHandle<String>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let server = std::thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut writer = stream;
+        writer.write_all(b"220 test ESMTP\r\n").unwrap();
+
+        let mut data = String::new();
+        let mut in_data = false;
+        let mut line = String::new();
+        while reader.read_line(&mut line).unwrap() > 0 {
+            if in_data {
+                if line == ".\r\n" {
+                    in_data = false;
+                    writer.write_all(b"250 queued\r\n").unwrap();
+                } else {
+                    data.push_str(&line);
+                }
+            } else if line.starts_with("DATA") {
+                in_data = true;
+                writer.write_all(b"354 go ahead\r\n").unwrap();
+            } else if line.starts_with("QUIT") {
+                writer.write_all(b"221 bye\r\n").unwrap();
+                break;
+            } else {
+                writer.write_all(b"250 ok\r\n").unwrap();
+            }
+            line.clear();
+        }
+        data
+    });
+    (port, server)
+}
+
+#[test]
+fn test_message_content_type_follows_format() {
+    let notifier = EmailNotifier::new(&settings(25)).unwrap();
+
+    let html = notifier
+        .message("July 2024", "<h1>July</h1>".to_string(), ReportFormat::Html)
+        .unwrap();
+    let html = String::from_utf8(html.formatted()).unwrap();
+    assert!(html.contains("Subject: July 2024"));
+    assert!(html.contains("To: me@example.org, you@example.org"));
+    assert!(html.contains("Content-Type: text/html"));
+
+    let text = notifier
+        .message("July 2024", "# July".to_string(), ReportFormat::Markdown)
+        .unwrap();
+    let text = String::from_utf8(text.formatted()).unwrap();
+    assert!(text.contains("Content-Type: text/plain"));
+}
+
+#[test]
+fn test_invalid_address_is_rejected() {
+    let mut settings = settings(25);
+    settings.to.push("not an address".to_string());
+    assert!(matches!(
+        EmailNotifier::new(&settings),
+        Err(EmailError::Address(_))
+    ));
+}
+
+#[test]
+fn test_send_delivers_to_smtp_server() {
+    let (port, server) = fake_smtp_server();
+    let notifier = EmailNotifier::new(&settings(port)).unwrap();
+
+    notifier
+        .send("July 2024", "# July".to_string(), ReportFormat::Markdown)
+        .unwrap();
+
+    let data = server.join().unwrap();
+    assert!(data.contains("Subject: July 2024"));
+    assert!(data.contains("# July"));
+}
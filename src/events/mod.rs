@@ -0,0 +1,505 @@
+use crate::alerts::{self, Alert};
+use crate::config::{EventType, SinkSettings, SinkTarget};
+use crate::display::format_duration_short;
+use crate::filament;
+use crate::jobs::{InspectionResult, JobRecord, Transition};
+use crate::messages::{MergedState, PrintInfo, PrintState, PrinterStatus};
+use chrono::{DateTime, Local};
+use serde::Serialize;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use thiserror::Error;
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+
+#[cfg(test)]
+mod tests;
+
+/// Events a slow sink may fall behind by before it starts missing some
+const BUS_CAPACITY: usize = 256;
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+/// How long [`EventBus::finish`] waits for the sinks to deliver what is left
+const FLUSH_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Error)]
+pub enum EventError {
+    #[error("{0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Cannot encode event: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("Webhook request failed: {0}")]
+    Http(#[from] reqwest::Error),
+
+    #[error("Invalid homeserver URL '{0}'")]
+    Homeserver(String),
+
+    #[error("Command exited with {0}")]
+    Command(std::process::ExitStatus),
+}
+
+/// Something that happened to one printer, as delivered to the sinks
+#[derive(Debug, Clone, Serialize)]
+pub struct Event {
+    pub printer: String,
+    pub at: DateTime<Local>,
+    #[serde(flatten)]
+    pub kind: EventKind,
+}
+
+impl Event {
+    pub fn new(printer: &str, kind: EventKind) -> Self {
+        Self {
+            printer: printer.to_string(),
+            at: Local::now(),
+            kind,
+        }
+    }
+
+    pub fn status(printer: &str, state: &MergedState) -> Self {
+        Self::new(
+            printer,
+            EventKind::Status {
+                message_count: state.message_count,
+                print: Box::new(state.print.clone()),
+            },
+        )
+    }
+
+    pub fn alert(printer: &str, alert: &Alert, snapshot: Option<&Path>) -> Self {
+        Self::new(
+            printer,
+            EventKind::Alert {
+                rule: alert.kind.to_string(),
+                priority: alert.priority(),
+                message: alert.message.clone(),
+                snapshot: snapshot.map(Path::to_path_buf),
+            },
+        )
+    }
+
+    pub fn job_finished(printer: &str, job: &JobRecord) -> Self {
+        Self::new(
+            printer,
+            EventKind::JobFinished {
+                job: JobSummary::from(job),
+            },
+        )
+    }
+
+    pub fn transition(printer: &str, transition: &Transition) -> Self {
+        Self::new(
+            printer,
+            match transition {
+                Transition::Started => EventKind::PrintStarted,
+                Transition::Paused => EventKind::PrintPaused,
+                Transition::Resumed => EventKind::PrintResumed,
+                Transition::Completed => EventKind::PrintCompleted,
+                Transition::Failed { reason } => EventKind::PrintFailed {
+                    reason: reason.clone(),
+                },
+                Transition::LayerChanged { layer, total } => EventKind::LayerChanged {
+                    layer: *layer,
+                    total_layers: *total,
+                },
+            },
+        )
+    }
+
+    pub fn inspection(printer: &str, result: &InspectionResult) -> Self {
+        let (result, codes) = match result {
+            InspectionResult::Passed => ("passed", Vec::new()),
+            InspectionResult::DefectDetected { codes } => ("defect_detected", codes.clone()),
+        };
+        Self::new(printer, EventKind::FirstLayerInspection { result, codes })
+    }
+
+    pub fn event_type(&self) -> EventType {
+        match self.kind {
+            EventKind::Status { .. } => EventType::Status,
+            EventKind::Alert { .. } => EventType::Alert,
+            EventKind::JobFinished { .. } => EventType::JobFinished,
+            EventKind::PrintStarted => EventType::PrintStarted,
+            EventKind::PrintPaused => EventType::PrintPaused,
+            EventKind::PrintResumed => EventType::PrintResumed,
+            EventKind::PrintCompleted => EventType::PrintCompleted,
+            EventKind::PrintFailed { .. } => EventType::PrintFailed,
+            EventKind::LayerChanged { .. } => EventType::LayerChanged,
+            EventKind::FirstLayerInspection { .. } => EventType::FirstLayerInspection,
+        }
+    }
+
+    /// Rule of an alert event
+    pub fn alert_rule(&self) -> Option<&str> {
+        match &self.kind {
+            EventKind::Alert { rule, .. } => Some(rule),
+            _ => None,
+        }
+    }
+
+    /// One line describing the event, for chat sinks
+    pub fn text(&self) -> String {
+        let printer = &self.printer;
+        match &self.kind {
+            EventKind::Status { print, .. } => {
+                let status = PrinterStatus::from_print_info(print);
+                let state = match &status.state {
+                    PrintState::Unknown(state) => state.clone(),
+                    state => format!("{state:?}").to_lowercase(),
+                };
+                match status.progress {
+                    Some(progress) => format!("{printer}: {state}, {progress}%"),
+                    None => format!("{printer}: {state}"),
+                }
+            }
+            EventKind::Alert {
+                priority, message, ..
+            } if *priority == "high" => format!("{printer}: ALERT {message}"),
+            EventKind::Alert { message, .. } => format!("{printer}: {message}"),
+            EventKind::JobFinished { job } => {
+                let mut text = format!(
+                    "{printer}: {} {} after {}",
+                    job.name.as_deref().unwrap_or("Print"),
+                    job.result,
+                    format_duration_short(job.duration_secs)
+                );
+                if let Some(reason) = &job.fail_reason {
+                    text.push_str(&format!(" ({reason})"));
+                }
+                text
+            }
+            EventKind::PrintStarted => format!("{printer}: print started"),
+            EventKind::PrintPaused => format!("{printer}: print paused"),
+            EventKind::PrintResumed => format!("{printer}: print resumed"),
+            EventKind::PrintCompleted => format!("{printer}: print completed"),
+            EventKind::PrintFailed {
+                reason: Some(reason),
+            } => {
+                format!("{printer}: print failed ({reason})")
+            }
+            EventKind::PrintFailed { reason: None } => format!("{printer}: print failed"),
+            EventKind::LayerChanged {
+                layer,
+                total_layers: Some(total),
+            } => format!("{printer}: layer {layer}/{total}"),
+            EventKind::LayerChanged { layer, .. } => format!("{printer}: layer {layer}"),
+            EventKind::FirstLayerInspection { codes, .. } if !codes.is_empty() => format!(
+                "{printer}: first layer inspection found a defect ({})",
+                codes.join(", ")
+            ),
+            EventKind::FirstLayerInspection { result, .. } => {
+                format!("{printer}: first layer inspection {result}")
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum EventKind {
+    Status {
+        message_count: u64,
+        /// Merged printer fields, as reported by the printer
+        print: Box<PrintInfo>,
+    },
+    Alert {
+        /// Alert rule, e.g. `ams_humidity`
+        rule: String,
+        /// `high` or `normal`
+        priority: &'static str,
+        message: String,
+        /// Camera still saved when the alert fired
+        #[serde(skip_serializing_if = "Option::is_none")]
+        snapshot: Option<PathBuf>,
+    },
+    JobFinished {
+        job: JobSummary,
+    },
+    PrintStarted,
+    PrintPaused,
+    PrintResumed,
+    PrintCompleted,
+    PrintFailed {
+        reason: Option<String>,
+    },
+    LayerChanged {
+        layer: u32,
+        total_layers: Option<u32>,
+    },
+    FirstLayerInspection {
+        /// `passed` or `defect_detected`
+        result: &'static str,
+        /// Camera HMS codes and print errors behind a detected defect
+        codes: Vec<String>,
+    },
+}
+
+/// The parts of a completed job that sinks receive
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct JobSummary {
+    pub name: Option<String>,
+    pub started_at: DateTime<Local>,
+    pub ended_at: DateTime<Local>,
+    /// `finished`, `failed`, `interrupted` or `unknown`
+    pub result: &'static str,
+    pub final_progress: Option<u32>,
+    pub duration_secs: u32,
+    pub fail_reason: Option<String>,
+    pub pauses: u32,
+    pub hms_codes: Vec<String>,
+    /// Estimated from AMS remaining percentages
+    pub filament_grams: Option<f64>,
+    pub energy_kwh: Option<f64>,
+    pub cost: Option<f64>,
+    pub currency: Option<String>,
+}
+
+impl From<&JobRecord> for JobSummary {
+    fn from(job: &JobRecord) -> Self {
+        let usage = job.filament_usage();
+        Self {
+            name: job.name.clone(),
+            started_at: job.started_at,
+            ended_at: job.ended_at,
+            result: match job.final_state {
+                PrintState::Finished => "finished",
+                PrintState::Failed => "failed",
+                PrintState::Printing | PrintState::Paused => "interrupted",
+                PrintState::Idle | PrintState::Unknown(_) => "unknown",
+            },
+            final_progress: job.final_progress,
+            duration_secs: job.elapsed_secs(),
+            fail_reason: job.fail_reason.clone(),
+            pauses: job.pauses,
+            hms_codes: job.hms_codes.clone(),
+            filament_grams: (!usage.is_empty()).then(|| filament::total_grams(&usage)),
+            energy_kwh: job.energy_kwh(),
+            cost: job.cost.as_ref().map(|cost| cost.total()),
+            currency: job.cost.as_ref().and_then(|cost| cost.currency.clone()),
+        }
+    }
+}
+
+/// Fans events out to the configured sinks. Every sink runs as its own task, so a slow
+/// webhook holds up neither the monitor nor the other sinks. Clones publish to the same
+/// sinks.
+#[derive(Debug, Clone)]
+pub struct EventBus {
+    /// `None` tells the sinks to stop once they have handled everything before it
+    sender: broadcast::Sender<Option<Arc<Event>>>,
+    wanted: Vec<EventType>,
+    sinks: Arc<Mutex<Vec<JoinHandle<()>>>>,
+}
+
+impl EventBus {
+    /// Starts one task per configured sink
+    pub fn start(settings: &[SinkSettings]) -> Self {
+        let (sender, _) = broadcast::channel(BUS_CAPACITY);
+        let mut wanted = Vec::new();
+        for event_type in EventType::ALL {
+            if settings.iter().any(|sink| sink.accepts(event_type)) {
+                wanted.push(event_type);
+            }
+        }
+        let sinks = settings
+            .iter()
+            .map(|settings| tokio::spawn(run_sink(settings.clone(), sender.subscribe())))
+            .collect();
+
+        Self {
+            sender,
+            wanted,
+            sinks: Arc::new(Mutex::new(sinks)),
+        }
+    }
+
+    /// Whether any sink takes events of this type, to skip building ones nobody receives
+    pub fn wants(&self, event_type: EventType) -> bool {
+        self.wanted.contains(&event_type)
+    }
+
+    pub fn publish(&self, event: Event) {
+        if self.wants(event.event_type()) {
+            // Fails only when every sink has stopped already
+            let _ = self.sender.send(Some(Arc::new(event)));
+        }
+    }
+
+    /// Lets the sinks deliver the events published so far, then stops them
+    pub async fn finish(&self) {
+        let _ = self.sender.send(None);
+        let sinks: Vec<_> = self.sinks.lock().unwrap().drain(..).collect();
+        let _ = tokio::time::timeout(FLUSH_TIMEOUT, async {
+            for sink in sinks {
+                let _ = sink.await;
+            }
+        })
+        .await;
+    }
+}
+
+async fn run_sink(settings: SinkSettings, mut events: broadcast::Receiver<Option<Arc<Event>>>) {
+    let label = describe(&settings.target);
+    let mut sink = match Sink::open(&settings.target) {
+        Ok(sink) => sink,
+        Err(e) => {
+            eprintln!("Event sink {label} disabled: {e}");
+            return;
+        }
+    };
+
+    loop {
+        match events.recv().await {
+            Ok(Some(event)) => {
+                if settings.accepts(event.event_type())
+                    && event
+                        .alert_rule()
+                        .is_none_or(|rule| settings.accepts_alert(rule))
+                    && let Err(e) = sink.deliver(&event).await
+                {
+                    eprintln!("Event sink {label} failed: {e}");
+                }
+            }
+            Ok(None) | Err(broadcast::error::RecvError::Closed) => break,
+            Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                eprintln!("Event sink {label} fell behind and skipped {skipped} events");
+            }
+        }
+    }
+}
+
+/// Short description of a sink for error messages, e.g. `webhook https://...`
+pub fn describe(target: &SinkTarget) -> String {
+    match target {
+        SinkTarget::JsonFile { path } => format!("json_file {}", path.display()),
+        SinkTarget::Webhook { url } => format!("webhook {url}"),
+        SinkTarget::Command { command } => format!("command '{command}'"),
+        SinkTarget::Matrix { room_id, .. } => format!("matrix {room_id}"),
+    }
+}
+
+enum Sink {
+    JsonFile(File),
+    Webhook {
+        client: reqwest::Client,
+        url: String,
+    },
+    Command(String),
+    Matrix {
+        client: reqwest::Client,
+        /// Send endpoint of the room, without the transaction ID
+        url: reqwest::Url,
+        access_token: String,
+        /// Keeps transaction IDs unique; the homeserver drops repeated ones as retries
+        sent: u64,
+    },
+}
+
+impl Sink {
+    fn open(target: &SinkTarget) -> Result<Self, EventError> {
+        Ok(match target {
+            SinkTarget::JsonFile { path } => {
+                if let Some(parent) = path
+                    .parent()
+                    .filter(|parent| !parent.as_os_str().is_empty())
+                {
+                    std::fs::create_dir_all(parent)?;
+                }
+                Sink::JsonFile(OpenOptions::new().create(true).append(true).open(path)?)
+            }
+            SinkTarget::Webhook { url } => Sink::Webhook {
+                client: reqwest::Client::builder()
+                    .timeout(WEBHOOK_TIMEOUT)
+                    .build()?,
+                url: url.clone(),
+            },
+            SinkTarget::Command { command } => Sink::Command(command.clone()),
+            SinkTarget::Matrix {
+                homeserver,
+                access_token,
+                room_id,
+            } => {
+                let invalid = || EventError::Homeserver(homeserver.clone());
+                let mut url = reqwest::Url::parse(homeserver).map_err(|_| invalid())?;
+                url.path_segments_mut()
+                    .map_err(|()| invalid())?
+                    .pop_if_empty()
+                    .extend([
+                        "_matrix",
+                        "client",
+                        "v3",
+                        "rooms",
+                        room_id,
+                        "send",
+                        "m.room.message",
+                    ]);
+                Sink::Matrix {
+                    client: reqwest::Client::builder()
+                        .timeout(WEBHOOK_TIMEOUT)
+                        .build()?,
+                    url,
+                    access_token: access_token.clone(),
+                    sent: 0,
+                }
+            }
+        })
+    }
+
+    async fn deliver(&mut self, event: &Event) -> Result<(), EventError> {
+        match self {
+            Sink::JsonFile(file) => {
+                let mut line = serde_json::to_vec(event)?;
+                line.push(b'\n');
+                file.write_all(&line)?;
+            }
+            Sink::Webhook { client, url } => {
+                client
+                    .post(url.as_str())
+                    .json(event)
+                    .send()
+                    .await?
+                    .error_for_status()?;
+            }
+            Sink::Command(command) => {
+                let event_type = serde_json::to_value(event.event_type())?;
+                let status = alerts::shell_command(command)
+                    .env("PULSEPRINT_EVENT", event_type.as_str().unwrap_or_default())
+                    .env("PULSEPRINT_PRINTER", &event.printer)
+                    .env("PULSEPRINT_EVENT_JSON", serde_json::to_string(event)?)
+                    .stdin(std::process::Stdio::null())
+                    .status()
+                    .await?;
+                if !status.success() {
+                    return Err(EventError::Command(status));
+                }
+            }
+            Sink::Matrix {
+                client,
+                url,
+                access_token,
+                sent,
+            } => {
+                *sent += 1;
+                let mut url = url.clone();
+                if let Ok(mut segments) = url.path_segments_mut() {
+                    segments.push(&format!(
+                        "pulseprint-{}-{sent}",
+                        event.at.timestamp_millis()
+                    ));
+                }
+                client
+                    .put(url)
+                    .bearer_auth(access_token.as_str())
+                    .json(&serde_json::json!({ "msgtype": "m.text", "body": event.text() }))
+                    .send()
+                    .await?
+                    .error_for_status()?;
+            }
+        }
+        Ok(())
+    }
+}
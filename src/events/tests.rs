@@ -0,0 +1,286 @@
+use super::*;
+use crate::jobs::JobTracker;
+use crate::messages::DeviceMessage;
+use chrono::TimeZone;
+use tempfile::tempdir;
+
+fn failed_job() -> JobRecord {
+    let start = Local.with_ymd_and_hms(2025, 8, 1, 9, 0, 0).unwrap();
+    let mut tracker = JobTracker::default();
+    let mut state = MergedState::default();
+
+    for (minutes, payload) in [
+        (
+            0,
+            r#"{"print": {"command": "push_status", "gcode_state": "RUNNING", "subtask_name": "benchy", "percent": 0}}"#,
+        ),
+        (
+            45,
+            r#"{"print": {"command": "push_status", "gcode_state": "FAILED", "fail_reason": "spaghetti", "percent": 40}}"#,
+        ),
+    ] {
+        state.update(&DeviceMessage::parse(payload).unwrap());
+        if let Some(job) = tracker.observe(&state, start + chrono::Duration::minutes(minutes)) {
+            return job;
+        }
+    }
+    panic!("job did not complete");
+}
+
+fn sink(target: SinkTarget, events: Vec<EventType>) -> SinkSettings {
+    SinkSettings {
+        target,
+        events,
+        alerts: Vec::new(),
+    }
+}
+
+#[test]
+fn test_event_json() {
+    let alert = Alert {
+        kind: "door_open",
+        message: "Enclosure door opened".to_string(),
+    };
+    let value = serde_json::to_value(Event::alert("x1c", &alert, None)).unwrap();
+    assert_eq!(value["event"], "alert");
+    assert_eq!(value["printer"], "x1c");
+    assert_eq!(value["rule"], "door_open");
+    assert_eq!(value["priority"], "normal");
+    assert_eq!(value["message"], "Enclosure door opened");
+    assert!(value["at"].is_string());
+    assert!(value.get("snapshot").is_none());
+
+    let snapshot = Path::new("snapshots/x1c.jpg");
+    let value = serde_json::to_value(Event::alert("x1c", &alert, Some(snapshot))).unwrap();
+    assert_eq!(value["snapshot"], "snapshots/x1c.jpg");
+
+    let value = serde_json::to_value(Event::job_finished("x1c", &failed_job())).unwrap();
+    assert_eq!(value["event"], "job_finished");
+    assert_eq!(value["job"]["name"], "benchy");
+    assert_eq!(value["job"]["result"], "failed");
+    assert_eq!(value["job"]["duration_secs"], 45 * 60);
+    assert_eq!(value["job"]["fail_reason"], "spaghetti");
+
+    let failed = Transition::Failed {
+        reason: Some("spaghetti".to_string()),
+    };
+    let value = serde_json::to_value(Event::transition("x1c", &failed)).unwrap();
+    assert_eq!(value["event"], "print_failed");
+    assert_eq!(value["reason"], "spaghetti");
+
+    let layer = Transition::LayerChanged {
+        layer: 12,
+        total: Some(120),
+    };
+    let event = Event::transition("x1c", &layer);
+    assert_eq!(event.event_type(), EventType::LayerChanged);
+    let value = serde_json::to_value(event).unwrap();
+    assert_eq!(value["event"], "layer_changed");
+    assert_eq!(value["layer"], 12);
+    assert_eq!(value["total_layers"], 120);
+
+    let value = serde_json::to_value(Event::transition("x1c", &Transition::Paused)).unwrap();
+    assert_eq!(value["event"], "print_paused");
+
+    let defect = InspectionResult::DefectDetected {
+        codes: vec!["0C00_8001".to_string()],
+    };
+    let value = serde_json::to_value(Event::inspection("x1c", &defect)).unwrap();
+    assert_eq!(value["event"], "first_layer_inspection");
+    assert_eq!(value["result"], "defect_detected");
+    assert_eq!(value["codes"], serde_json::json!(["0C00_8001"]));
+}
+
+#[tokio::test]
+async fn test_json_file_sink_receives_selected_events() {
+    let temp_dir = tempdir().unwrap();
+    let all = temp_dir.path().join("all.jsonl");
+    let alerts_only = temp_dir.path().join("logs/alerts.jsonl");
+
+    let bus = EventBus::start(&[
+        sink(SinkTarget::JsonFile { path: all.clone() }, Vec::new()),
+        sink(
+            SinkTarget::JsonFile {
+                path: alerts_only.clone(),
+            },
+            vec![EventType::Alert],
+        ),
+    ]);
+    assert!(bus.wants(EventType::Status));
+
+    let mut state = MergedState::default();
+    state.update(&DeviceMessage::parse(r#"{"print": {"gcode_state": "IDLE"}}"#).unwrap());
+    bus.publish(Event::status("x1c", &state));
+    bus.publish(Event::alert(
+        "x1c",
+        &Alert {
+            kind: "sdcard",
+            message: "SD card is missing".to_string(),
+        },
+        None,
+    ));
+    bus.finish().await;
+
+    let lines: Vec<serde_json::Value> = std::fs::read_to_string(&all)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[0]["event"], "status");
+    assert_eq!(lines[0]["print"]["gcode_state"], "IDLE");
+    assert_eq!(lines[1]["event"], "alert");
+
+    let alerts = std::fs::read_to_string(&alerts_only).unwrap();
+    assert_eq!(alerts.lines().count(), 1);
+    assert!(alerts.contains("SD card is missing"));
+}
+
+#[tokio::test]
+async fn test_sinks_receive_selected_alert_rules() {
+    let temp_dir = tempdir().unwrap();
+    let urgent = temp_dir.path().join("urgent.jsonl");
+    let info = temp_dir.path().join("info.jsonl");
+    let all = temp_dir.path().join("all.jsonl");
+
+    let bus = EventBus::start(&[
+        SinkSettings {
+            alerts: vec!["hms_fatal".to_string(), "spaghetti".to_string()],
+            ..sink(
+                SinkTarget::JsonFile {
+                    path: urgent.clone(),
+                },
+                vec![EventType::Alert],
+            )
+        },
+        SinkSettings {
+            alerts: vec!["hms_info".to_string()],
+            ..sink(SinkTarget::JsonFile { path: info.clone() }, Vec::new())
+        },
+        sink(SinkTarget::JsonFile { path: all.clone() }, Vec::new()),
+    ]);
+
+    for kind in ["hms_fatal", "hms_info", "sdcard"] {
+        bus.publish(Event::alert(
+            "x1c",
+            &Alert {
+                kind,
+                message: format!("{kind} raised"),
+            },
+            None,
+        ));
+    }
+    // The rule filter only applies to alerts
+    bus.publish(Event::new("x1c", EventKind::PrintStarted));
+    bus.finish().await;
+
+    let rules = |path: &std::path::Path| -> Vec<String> {
+        std::fs::read_to_string(path)
+            .unwrap()
+            .lines()
+            .map(|line| {
+                let value: serde_json::Value = serde_json::from_str(line).unwrap();
+                value["rule"]
+                    .as_str()
+                    .unwrap_or(value["event"].as_str().unwrap())
+                    .to_string()
+            })
+            .collect()
+    };
+    assert_eq!(rules(&urgent), ["hms_fatal"]);
+    assert_eq!(rules(&info), ["hms_info", "print_started"]);
+    assert_eq!(
+        rules(&all),
+        ["hms_fatal", "hms_info", "sdcard", "print_started"]
+    );
+}
+
+#[tokio::test]
+async fn test_unwanted_events_are_not_published() {
+    let temp_dir = tempdir().unwrap();
+    let path = temp_dir.path().join("jobs.jsonl");
+    let bus = EventBus::start(&[sink(
+        SinkTarget::JsonFile { path: path.clone() },
+        vec![EventType::JobFinished],
+    )]);
+    assert!(!bus.wants(EventType::Status));
+    assert!(!bus.wants(EventType::Alert));
+
+    bus.publish(Event::status("x1c", &MergedState::default()));
+    bus.publish(Event::job_finished("x1c", &failed_job()));
+    bus.finish().await;
+
+    let contents = std::fs::read_to_string(&path).unwrap();
+    assert_eq!(contents.lines().count(), 1);
+    assert!(contents.contains("\"event\":\"job_finished\""));
+}
+
+#[test]
+fn test_event_text() {
+    assert_eq!(
+        Event::job_finished("x1c", &failed_job()).text(),
+        "x1c: benchy failed after 45m (spaghetti)"
+    );
+    let layer = Transition::LayerChanged {
+        layer: 12,
+        total: Some(120),
+    };
+    assert_eq!(Event::transition("x1c", &layer).text(), "x1c: layer 12/120");
+
+    let mut state = MergedState::default();
+    state.update(
+        &DeviceMessage::parse(r#"{"print": {"gcode_state": "RUNNING", "percent": 40}}"#).unwrap(),
+    );
+    assert_eq!(Event::status("x1c", &state).text(), "x1c: printing, 40%");
+}
+
+#[tokio::test]
+async fn test_matrix_sink_posts_to_room() {
+    use axum::extract::{Path as UrlPath, State};
+    use axum::http::HeaderMap;
+
+    type Received = Arc<Mutex<Vec<(String, String, String, serde_json::Value)>>>;
+    async fn send(
+        State(received): State<Received>,
+        UrlPath((room, txn)): UrlPath<(String, String)>,
+        headers: HeaderMap,
+        axum::Json(body): axum::Json<serde_json::Value>,
+    ) -> axum::Json<serde_json::Value> {
+        let auth = headers["authorization"].to_str().unwrap().to_string();
+        received.lock().unwrap().push((room, txn, auth, body));
+        axum::Json(serde_json::json!({ "event_id": "$sent" }))
+    }
+
+    let received = Received::default();
+    let app = axum::Router::new()
+        .route(
+            "/_matrix/client/v3/rooms/{room}/send/m.room.message/{txn}",
+            axum::routing::put(send),
+        )
+        .with_state(Arc::clone(&received));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await });
+
+    let bus = EventBus::start(&[sink(
+        SinkTarget::Matrix {
+            homeserver: format!("http://{address}/"),
+            access_token: "syt_token".to_string(),
+            room_id: "!shop:example.org".to_string(),
+        },
+        vec![EventType::PrintStarted, EventType::PrintCompleted],
+    )]);
+    bus.publish(Event::transition("x1c", &Transition::Started));
+    bus.publish(Event::transition("x1c", &Transition::Completed));
+    bus.finish().await;
+
+    let received = received.lock().unwrap();
+    assert_eq!(received.len(), 2);
+    let (room, txn, auth, body) = &received[0];
+    assert_eq!(room, "!shop:example.org");
+    assert_eq!(auth, "Bearer syt_token");
+    assert_eq!(body["msgtype"], "m.text");
+    assert_eq!(body["body"], "x1c: print started");
+    assert_ne!(txn, &received[1].1);
+    assert_eq!(received[1].3["body"], "x1c: print completed");
+}
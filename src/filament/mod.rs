@@ -0,0 +1,237 @@
+use crate::messages::{AmsTray, EXTERNAL_SLOT, PrintInfo};
+use crate::project::PlateInfo;
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::Path;
+use std::time::Duration;
+use thiserror::Error;
+
+#[cfg(test)]
+mod tests;
+
+const SPOOLMAN_TIMEOUT: Duration = Duration::from_secs(10);
+/// Label of the external spool holder, used like an AMS tray label
+pub const EXTERNAL_TRAY: &str = "External";
+
+#[derive(Debug, Error)]
+pub enum FilamentError {
+    #[error("Spoolman request failed: {0}")]
+    HttpError(#[from] reqwest::Error),
+
+    #[error("Spoolman rejected the update for spool {spool_id}: HTTP {status}")]
+    Rejected { spool_id: u32, status: u16 },
+}
+
+/// Filament left in one AMS tray, as reported by the printer
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TrayReading {
+    /// Remaining filament in percent
+    pub remain: u32,
+    /// Nominal spool weight in grams
+    pub weight: f64,
+    pub material: Option<String>,
+}
+
+/// Trays with a known remaining percentage, keyed by their label on the printer (`A1`..`D4`,
+/// or [`EXTERNAL_TRAY`])
+///
+/// Spools without an RFID tag report `-1` and are left out.
+pub fn tray_readings(print: &PrintInfo) -> BTreeMap<String, TrayReading> {
+    let mut readings = BTreeMap::new();
+    let units = print
+        .ams
+        .as_ref()
+        .and_then(|ams| ams.units.as_deref())
+        .unwrap_or_default();
+    for unit in units {
+        for tray in &unit.trays {
+            if let Some(label) = tray_label(unit.id.as_deref(), tray.id.as_deref())
+                && let Some(reading) = reading(tray)
+            {
+                readings.insert(label, reading);
+            }
+        }
+    }
+    if let Some(reading) = print.vt_tray.as_ref().and_then(reading) {
+        readings.insert(EXTERNAL_TRAY.to_string(), reading);
+    }
+    readings
+}
+
+fn reading(tray: &AmsTray) -> Option<TrayReading> {
+    Some(TrayReading {
+        remain: tray.remain.and_then(|remain| u32::try_from(remain).ok())?,
+        weight: tray.tray_weight.filter(|weight| *weight > 0.0)?,
+        material: tray.tray_type.clone().filter(|kind| !kind.is_empty()),
+    })
+}
+
+/// Label of the tray feeding the printer, e.g. `A2` or [`EXTERNAL_TRAY`]
+pub fn active_tray_label(print: &PrintInfo) -> Option<String> {
+    slot_label(print.active_slot()?)
+}
+
+/// Label of a slot as numbered by `tray_now`: `5` is `B2`, [`EXTERNAL_SLOT`] is
+/// [`EXTERNAL_TRAY`]
+pub fn slot_label(slot: u32) -> Option<String> {
+    match slot {
+        EXTERNAL_SLOT => Some(EXTERNAL_TRAY.to_string()),
+        slot => tray_label(Some(&(slot / 4).to_string()), Some(&(slot % 4).to_string())),
+    }
+}
+
+/// Formats AMS unit `0` tray `1` as `A2`, matching the printer screen
+pub fn tray_label(unit: Option<&str>, tray: Option<&str>) -> Option<String> {
+    let unit: u8 = unit?.parse().ok()?;
+    let tray: u8 = tray?.parse().ok()?;
+    (unit < 26).then(|| format!("{}{}", char::from(b'A' + unit), tray + 1))
+}
+
+/// Filament drawn from one tray over a job
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FilamentUsage {
+    pub tray: String,
+    pub material: Option<String>,
+    /// Share of the spool; 0 when the usage comes from the slicer's weights
+    pub percent: u32,
+    pub grams: f64,
+}
+
+/// Slicer filament weights of the printed plate, for jobs whose tray readings show no usage
+#[derive(Debug, Clone, Copy)]
+pub struct PlannedUsage<'a> {
+    pub plate: &'a PlateInfo,
+    /// Trays that fed the printer during the job, in order of first use
+    pub trays_fed: &'a [String],
+    /// Share of the plate that was printed, in percent
+    pub progress: u32,
+}
+
+impl PlannedUsage<'_> {
+    /// Weight of each project filament, scaled to the printed share
+    ///
+    /// The slicer does not know which tray each filament is loaded in, so the usage is only
+    /// put on a tray when the plate has a single filament and a single tray fed the printer.
+    /// Otherwise it is labeled with the project's filament slot, e.g. `Filament 2`.
+    fn usage(self) -> Vec<FilamentUsage> {
+        let share = f64::from(self.progress.min(100)) / 100.0;
+        let used: Vec<_> = self
+            .plate
+            .filaments
+            .iter()
+            .filter_map(|filament| {
+                let grams = filament.used_g.filter(|grams| *grams > 0.0)? * share;
+                (grams > 0.0).then_some((filament, grams))
+            })
+            .collect();
+        used.iter()
+            .map(|(filament, grams)| FilamentUsage {
+                tray: match (used.len(), self.trays_fed) {
+                    (1, [tray]) => tray.clone(),
+                    _ => format!("Filament {}", filament.id),
+                },
+                material: filament.material.clone(),
+                percent: 0,
+                grams: *grams,
+            })
+            .collect()
+    }
+}
+
+/// Compares tray readings from the start and end of a job, falling back to the slicer's
+/// weights in `planned` when no tray went down
+///
+/// Bambu firmware only reports whole percentages, so usage below 1% of a spool is not seen,
+/// and spools without an RFID tag have no readings at all. Trays that went up (spool swapped
+/// or refilled) are ignored.
+pub fn estimate_usage(
+    start: &BTreeMap<String, TrayReading>,
+    end: &BTreeMap<String, TrayReading>,
+    planned: Option<PlannedUsage>,
+) -> Vec<FilamentUsage> {
+    let measured: Vec<_> = start
+        .iter()
+        .filter_map(|(tray, before)| {
+            let after = end.get(tray)?;
+            let percent = before.remain.checked_sub(after.remain)?;
+            (percent > 0).then(|| FilamentUsage {
+                tray: tray.clone(),
+                material: after.material.clone().or_else(|| before.material.clone()),
+                percent,
+                grams: f64::from(percent) * before.weight / 100.0,
+            })
+        })
+        .collect();
+    if !measured.is_empty() {
+        return measured;
+    }
+    planned.map(PlannedUsage::usage).unwrap_or_default()
+}
+
+pub fn total_grams(usage: &[FilamentUsage]) -> f64 {
+    usage.iter().map(|entry| entry.grams).sum()
+}
+
+/// One line of the filament ledger
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LedgerEntry {
+    pub recorded_at: DateTime<Local>,
+    pub printer: String,
+    pub job: Option<String>,
+    #[serde(flatten)]
+    pub usage: FilamentUsage,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spool_id: Option<u32>,
+}
+
+/// Appends entries to the newline-delimited JSON ledger, creating it if needed
+pub fn append_ledger(path: &Path, entries: &[LedgerEntry]) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    for entry in entries {
+        writeln!(file, "{}", serde_json::to_string(entry)?)?;
+    }
+    Ok(())
+}
+
+/// Minimal client for the Spoolman REST API
+#[derive(Debug, Clone)]
+pub struct SpoolmanClient {
+    client: reqwest::Client,
+    base_url: String,
+}
+
+impl SpoolmanClient {
+    pub fn new(base_url: &str) -> Result<Self, FilamentError> {
+        Ok(Self {
+            client: reqwest::Client::builder()
+                .timeout(SPOOLMAN_TIMEOUT)
+                .build()?,
+            base_url: base_url.trim_end_matches('/').to_string(),
+        })
+    }
+
+    /// Deducts `grams` from the spool's remaining weight
+    pub async fn use_filament(&self, spool_id: u32, grams: f64) -> Result<(), FilamentError> {
+        let response = self
+            .client
+            .put(format!("{}/api/v1/spool/{spool_id}/use", self.base_url))
+            .json(&serde_json::json!({ "use_weight": grams }))
+            .send()
+            .await?;
+
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            Err(FilamentError::Rejected {
+                spool_id,
+                status: response.status().as_u16(),
+            })
+        }
+    }
+}
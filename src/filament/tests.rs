@@ -0,0 +1,280 @@
+use super::*;
+use crate::messages::{DeviceMessage, MergedState};
+use chrono::TimeZone;
+use tempfile::tempdir;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+fn print_with_ams(ams: &str) -> PrintInfo {
+    let mut state = MergedState::default();
+    state.update(
+        &DeviceMessage::parse(&format!(
+            r#"{{"print": {{"command": "push_status", "ams": {ams}}}}}"#
+        ))
+        .unwrap(),
+    );
+    state.print
+}
+
+fn reading(remain: u32) -> TrayReading {
+    TrayReading {
+        remain,
+        weight: 1000.0,
+        material: Some("PLA".to_string()),
+    }
+}
+
+#[test]
+fn test_tray_readings_skip_unknown_remain() {
+    let print = print_with_ams(
+        r#"{"ams": [
+            {"id": "0", "tray": [
+                {"id": "0", "remain": 80, "tray_type": "PLA", "tray_weight": "1000"},
+                {"id": "1", "remain": -1, "tray_type": "PETG", "tray_weight": "1000"},
+                {"id": "2"}
+            ]},
+            {"id": 1, "tray": [{"id": 3, "remain": "45", "tray_type": "", "tray_weight": 500}]}
+        ], "tray_now": "0"}"#,
+    );
+
+    let readings = tray_readings(&print);
+    assert_eq!(readings.keys().collect::<Vec<_>>(), vec!["A1", "B4"]);
+    assert_eq!(readings["A1"], reading(80));
+    assert_eq!(
+        readings["B4"],
+        TrayReading {
+            remain: 45,
+            weight: 500.0,
+            material: None,
+        }
+    );
+}
+
+#[test]
+fn test_external_spool_readings() {
+    let mut state = MergedState::default();
+    state.update(
+        &DeviceMessage::parse(
+            r#"{"print": {"vt_tray": {"id": "254", "remain": 80, "tray_type": "PLA", "tray_weight": "1000"}}}"#,
+        )
+        .unwrap(),
+    );
+
+    let readings = tray_readings(&state.print);
+    assert_eq!(readings.keys().collect::<Vec<_>>(), vec![EXTERNAL_TRAY]);
+    assert_eq!(readings[EXTERNAL_TRAY], reading(80));
+    assert_eq!(
+        active_tray_label(&state.print).as_deref(),
+        Some(EXTERNAL_TRAY)
+    );
+
+    let print = print_with_ams(r#"{"ams": [{"id": "1", "tray": []}], "tray_now": "5"}"#);
+    assert_eq!(active_tray_label(&print).as_deref(), Some("B2"));
+}
+
+#[test]
+fn test_partial_ams_update_keeps_units() {
+    let mut state = MergedState::default();
+    for payload in [
+        r#"{"print": {"ams": {"ams": [{"id": "0", "tray": [{"id": "0", "remain": 80, "tray_weight": "1000"}]}], "tray_now": "0"}}}"#,
+        r#"{"print": {"ams": {"tray_now": "255"}}}"#,
+    ] {
+        state.update(&DeviceMessage::parse(payload).unwrap());
+    }
+
+    let ams = state.print.ams.as_ref().unwrap();
+    assert_eq!(ams.tray_now.as_deref(), Some("255"));
+    assert_eq!(tray_readings(&state.print).len(), 1);
+}
+
+#[test]
+fn test_estimate_usage_from_remain_delta() {
+    let start = BTreeMap::from([
+        ("A1".to_string(), reading(80)),
+        ("A2".to_string(), reading(50)),
+        ("A3".to_string(), reading(10)),
+    ]);
+    // A2 unchanged, A3 swapped for a fuller spool, A4 only seen at the end
+    let end = BTreeMap::from([
+        ("A1".to_string(), reading(77)),
+        ("A2".to_string(), reading(50)),
+        ("A3".to_string(), reading(100)),
+        ("A4".to_string(), reading(60)),
+    ]);
+
+    let usage = estimate_usage(&start, &end, None);
+    assert_eq!(
+        usage,
+        vec![FilamentUsage {
+            tray: "A1".to_string(),
+            material: Some("PLA".to_string()),
+            percent: 3,
+            grams: 30.0,
+        }]
+    );
+    assert_eq!(total_grams(&usage), 30.0);
+}
+
+fn plate(grams: &[f64]) -> PlateInfo {
+    PlateInfo {
+        index: 1,
+        estimated_secs: None,
+        weight_g: Some(grams.iter().sum()),
+        filaments: grams
+            .iter()
+            .enumerate()
+            .map(|(index, grams)| crate::project::FilamentInfo {
+                id: index as u32 + 1,
+                material: Some("PLA".to_string()),
+                color: None,
+                used_m: None,
+                used_g: Some(*grams),
+            })
+            .collect(),
+        objects: Vec::new(),
+        nozzle_diameter: None,
+        thumbnail: None,
+    }
+}
+
+#[test]
+fn test_estimate_usage_falls_back_to_slicer_weights() {
+    let single = plate(&[6.5]);
+    let fed = ["B3".to_string()];
+    let planned = |plate, trays_fed, progress| {
+        Some(PlannedUsage {
+            plate,
+            trays_fed,
+            progress,
+        })
+    };
+
+    // Under 1% of the spool: the readings do not move
+    let unchanged = BTreeMap::from([("B3".to_string(), reading(80))]);
+    let usage = estimate_usage(&unchanged, &unchanged, planned(&single, &fed, 100));
+    assert_eq!(
+        usage,
+        vec![FilamentUsage {
+            tray: "B3".to_string(),
+            material: Some("PLA".to_string()),
+            percent: 0,
+            grams: 6.5,
+        }]
+    );
+
+    // Spools without RFID have no readings; a failed job used part of the plate
+    let multi = plate(&[40.0, 0.0, 10.0]);
+    let fed = ["A1".to_string(), "A2".to_string()];
+    let usage = estimate_usage(
+        &BTreeMap::new(),
+        &BTreeMap::new(),
+        planned(&multi, &fed, 50),
+    );
+    let usage: Vec<_> = usage
+        .iter()
+        .map(|usage| (usage.tray.as_str(), usage.grams))
+        .collect();
+    assert_eq!(usage, vec![("Filament 1", 20.0), ("Filament 3", 5.0)]);
+
+    // Measured usage wins over the slicer's weights
+    let end = BTreeMap::from([("B3".to_string(), reading(77))]);
+    let usage = estimate_usage(&unchanged, &end, planned(&single, &[], 100));
+    assert_eq!(total_grams(&usage), 30.0);
+}
+
+#[test]
+fn test_append_ledger_writes_one_line_per_entry() {
+    let temp_dir = tempdir().unwrap();
+    let path = temp_dir.path().join("nested").join("filament.jsonl");
+    let entry = LedgerEntry {
+        recorded_at: Local.with_ymd_and_hms(2025, 8, 1, 9, 0, 0).unwrap(),
+        printer: "x1c".to_string(),
+        job: Some("benchy".to_string()),
+        usage: FilamentUsage {
+            tray: "A1".to_string(),
+            material: Some("PLA".to_string()),
+            percent: 3,
+            grams: 30.0,
+        },
+        spool_id: Some(7),
+    };
+
+    append_ledger(&path, std::slice::from_ref(&entry)).unwrap();
+    append_ledger(&path, std::slice::from_ref(&entry)).unwrap();
+
+    let contents = std::fs::read_to_string(&path).unwrap();
+    let lines: Vec<&str> = contents.lines().collect();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].contains(r#""tray":"A1""#));
+    assert_eq!(
+        serde_json::from_str::<LedgerEntry>(lines[1]).unwrap(),
+        entry
+    );
+}
+
+/// Accepts one HTTP request, answers with `status` and returns the raw request text
+async fn serve_once(listener: TcpListener, status: &'static str) -> String {
+    let (mut socket, _) = listener.accept().await.unwrap();
+    let mut request = Vec::new();
+    let mut buffer = [0u8; 1024];
+    loop {
+        let read = socket.read(&mut buffer).await.unwrap();
+        request.extend_from_slice(&buffer[..read]);
+        let text = String::from_utf8_lossy(&request);
+        if let Some((head, body)) = text.split_once("\r\n\r\n") {
+            let length = head
+                .lines()
+                .find_map(|line| {
+                    let (name, value) = line.split_once(':')?;
+                    name.eq_ignore_ascii_case("content-length")
+                        .then(|| value.trim().parse::<usize>().ok())?
+                })
+                .unwrap_or(0);
+            if body.len() >= length {
+                break;
+            }
+        }
+        if read == 0 {
+            break;
+        }
+    }
+
+    socket
+        .write_all(format!("HTTP/1.1 {status}\r\ncontent-length: 2\r\n\r\n{{}}").as_bytes())
+        .await
+        .unwrap();
+    String::from_utf8(request).unwrap()
+}
+
+#[tokio::test]
+async fn test_spoolman_use_filament() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let server = tokio::spawn(serve_once(listener, "200 OK"));
+
+    let client = SpoolmanClient::new(&format!("http://127.0.0.1:{port}/")).unwrap();
+    client.use_filament(7, 12.5).await.unwrap();
+
+    let request = server.await.unwrap();
+    assert!(request.starts_with("PUT /api/v1/spool/7/use HTTP/1.1"));
+    assert!(request.ends_with(r#"{"use_weight":12.5}"#));
+}
+
+#[tokio::test]
+async fn test_spoolman_rejection_reports_status() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let server = tokio::spawn(serve_once(listener, "404 Not Found"));
+
+    let client = SpoolmanClient::new(&format!("http://127.0.0.1:{port}")).unwrap();
+    let error = client.use_filament(99, 1.0).await.unwrap_err();
+    server.await.unwrap();
+
+    assert!(matches!(
+        error,
+        FilamentError::Rejected {
+            spool_id: 99,
+            status: 404
+        }
+    ));
+}
@@ -0,0 +1,295 @@
+use crate::config::PrinterConfig;
+use crate::mqtt;
+use rustls::pki_types::ServerName;
+use std::sync::Arc;
+use std::time::Duration;
+use thiserror::Error;
+use tokio::io::{
+    AsyncBufRead, AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader,
+};
+use tokio::net::TcpStream;
+use tokio_rustls::TlsConnector;
+use tokio_rustls::client::TlsStream;
+
+#[cfg(test)]
+mod tests;
+
+/// Port of the printer's implicit-TLS FTP server
+pub const FTPS_PORT: u16 = 990;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Error)]
+pub enum FtpError {
+    #[error("IO error: {0}")]
+    IoError(#[from] std::io::Error),
+
+    #[error("Invalid printer address: {0}")]
+    InvalidAddress(String),
+
+    #[error("Timed out connecting to the printer")]
+    Timeout,
+
+    #[error("Unexpected reply from the FTP server: {0}")]
+    Protocol(String),
+
+    #[error("FTP server refused the request ({code}): {message}")]
+    Rejected { code: u16, message: String },
+}
+
+/// Numbered server reply; multi-line replies are joined with newlines
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reply {
+    pub code: u16,
+    pub message: String,
+}
+
+/// File or directory on the printer's storage
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemoteEntry {
+    pub name: String,
+    pub size: u64,
+    pub is_dir: bool,
+}
+
+/// FTPS session with one printer. The control and data connections both use TLS from the
+/// first byte; data connections resume the control session, which the printer requires.
+pub struct FtpsClient {
+    control: BufReader<TlsStream<TcpStream>>,
+    connector: TlsConnector,
+    server_name: ServerName<'static>,
+    ip: String,
+}
+
+impl FtpsClient {
+    /// Connects and logs in with the printer's LAN access code
+    pub async fn connect(printer: &PrinterConfig) -> Result<Self, FtpError> {
+        let server_name = ServerName::try_from(printer.ip.clone())
+            .map_err(|_| FtpError::InvalidAddress(printer.ip.clone()))?;
+        // One shared config so its session cache lets data connections resume the control session
+        let connector = TlsConnector::from(Arc::new(mqtt::printer_tls_config()));
+
+        let stream = connect_tcp(&printer.ip, FTPS_PORT).await?;
+        let stream = connector.connect(server_name.clone(), stream).await?;
+        let mut client = Self {
+            control: BufReader::new(stream),
+            connector,
+            server_name,
+            ip: printer.ip.clone(),
+        };
+
+        let greeting = read_reply(&mut client.control).await?;
+        expect(greeting, &[220])?;
+        let mut reply = client.command("USER bblp").await?;
+        if reply.code == 331 {
+            reply = client
+                .command(&format!("PASS {}", printer.access_code))
+                .await?;
+        }
+        expect(reply, &[230])?;
+        for command in ["TYPE I", "PBSZ 0", "PROT P"] {
+            let reply = client.command(command).await?;
+            expect(reply, &[200])?;
+        }
+        Ok(client)
+    }
+
+    pub async fn list(&mut self, path: &str) -> Result<Vec<RemoteEntry>, FtpError> {
+        let mut data = self.open_data(&format!("LIST {path}")).await?;
+        let mut listing = Vec::new();
+        copy_data(&mut data, &mut listing).await?;
+        drop(data);
+        self.finish_data().await?;
+
+        let mut entries: Vec<RemoteEntry> = String::from_utf8_lossy(&listing)
+            .lines()
+            .filter_map(parse_list_line)
+            .filter(|entry| entry.name != "." && entry.name != "..")
+            .collect();
+        entries.sort_by(|a, b| b.is_dir.cmp(&a.is_dir).then_with(|| a.name.cmp(&b.name)));
+        Ok(entries)
+    }
+
+    /// Streams a remote file into `writer`, returning the number of bytes received
+    pub async fn download<W: AsyncWrite + Unpin>(
+        &mut self,
+        path: &str,
+        writer: &mut W,
+    ) -> Result<u64, FtpError> {
+        let mut data = self.open_data(&format!("RETR {path}")).await?;
+        let received = copy_data(&mut data, writer).await?;
+        writer.flush().await?;
+        drop(data);
+        self.finish_data().await?;
+        Ok(received)
+    }
+
+    /// Stores everything read from `reader` at `path`, returning the number of bytes sent
+    pub async fn upload<R: AsyncRead + Unpin>(
+        &mut self,
+        path: &str,
+        reader: &mut R,
+    ) -> Result<u64, FtpError> {
+        let mut data = self.open_data(&format!("STOR {path}")).await?;
+        let sent = tokio::io::copy(reader, &mut data).await?;
+        // The server only completes the transfer once it sees the end of the TLS stream
+        data.shutdown().await?;
+        drop(data);
+        self.finish_data().await?;
+        Ok(sent)
+    }
+
+    pub async fn delete(&mut self, path: &str) -> Result<(), FtpError> {
+        let reply = self.command(&format!("DELE {path}")).await?;
+        expect(reply, &[250])
+    }
+
+    pub async fn quit(mut self) -> Result<(), FtpError> {
+        let reply = self.command("QUIT").await?;
+        expect(reply, &[221])
+    }
+
+    async fn command(&mut self, command: &str) -> Result<Reply, FtpError> {
+        let stream = self.control.get_mut();
+        stream.write_all(command.as_bytes()).await?;
+        stream.write_all(b"\r\n").await?;
+        stream.flush().await?;
+        read_reply(&mut self.control).await
+    }
+
+    /// Opens a passive data connection and issues `command` over it
+    async fn open_data(&mut self, command: &str) -> Result<TlsStream<TcpStream>, FtpError> {
+        let reply = self.command("PASV").await?;
+        let port = match reply.code {
+            227 => parse_pasv(&reply.message)
+                .ok_or_else(|| FtpError::Protocol(format!("bad PASV reply: {}", reply.message)))?,
+            _ => return Err(rejected(reply)),
+        };
+
+        // The address in the PASV reply is ignored; it is unreachable behind NAT
+        let stream = connect_tcp(&self.ip, port).await?;
+        let reply = self.command(command).await?;
+        expect(reply, &[125, 150])?;
+        Ok(self
+            .connector
+            .connect(self.server_name.clone(), stream)
+            .await?)
+    }
+
+    async fn finish_data(&mut self) -> Result<(), FtpError> {
+        let reply = read_reply(&mut self.control).await?;
+        expect(reply, &[226, 250])
+    }
+}
+
+async fn connect_tcp(ip: &str, port: u16) -> Result<TcpStream, FtpError> {
+    tokio::time::timeout(CONNECT_TIMEOUT, TcpStream::connect((ip, port)))
+        .await
+        .map_err(|_| FtpError::Timeout)?
+        .map_err(FtpError::from)
+}
+
+/// Copies a data connection to `writer`. The printer may close the connection without a TLS
+/// close_notify, which is treated as the end of the transfer.
+async fn copy_data<R: AsyncRead + Unpin, W: AsyncWrite + Unpin>(
+    reader: &mut R,
+    writer: &mut W,
+) -> Result<u64, FtpError> {
+    let mut buffer = vec![0u8; 64 * 1024];
+    let mut total = 0u64;
+    loop {
+        let read = match reader.read(&mut buffer).await {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(e.into()),
+        };
+        writer.write_all(&buffer[..read]).await?;
+        total += read as u64;
+    }
+    Ok(total)
+}
+
+fn expect(reply: Reply, codes: &[u16]) -> Result<(), FtpError> {
+    if codes.contains(&reply.code) {
+        Ok(())
+    } else {
+        Err(rejected(reply))
+    }
+}
+
+fn rejected(reply: Reply) -> FtpError {
+    FtpError::Rejected {
+        code: reply.code,
+        message: reply.message,
+    }
+}
+
+/// Reads one reply, following `123-` continuation lines up to the closing `123 ` line
+pub async fn read_reply<R: AsyncBufRead + Unpin>(reader: &mut R) -> Result<Reply, FtpError> {
+    let first = read_line(reader).await?;
+    let code = first
+        .get(..3)
+        .and_then(|code| code.parse::<u16>().ok())
+        .ok_or_else(|| FtpError::Protocol(first.clone()))?;
+    let mut message = first.get(4..).unwrap_or_default().to_string();
+
+    if first.as_bytes().get(3) == Some(&b'-') {
+        let terminator = format!("{code} ");
+        loop {
+            let line = read_line(reader).await?;
+            message.push('\n');
+            if let Some(last) = line.strip_prefix(&terminator) {
+                message.push_str(last);
+                break;
+            }
+            message.push_str(&line);
+        }
+    }
+    Ok(Reply { code, message })
+}
+
+async fn read_line<R: AsyncBufRead + Unpin>(reader: &mut R) -> Result<String, FtpError> {
+    let mut line = String::new();
+    if reader.read_line(&mut line).await? == 0 {
+        return Err(FtpError::Protocol(
+            "connection closed by the server".to_string(),
+        ));
+    }
+    Ok(line.trim_end_matches(['\r', '\n']).to_string())
+}
+
+/// Data port from a `227 Entering Passive Mode (h1,h2,h3,h4,p1,p2)` reply
+pub fn parse_pasv(message: &str) -> Option<u16> {
+    let start = message.find('(')?;
+    let end = start + message[start..].find(')')?;
+    let numbers: Vec<u16> = message[start + 1..end]
+        .split(',')
+        .map(|n| n.trim().parse().ok())
+        .collect::<Option<_>>()?;
+    match numbers[..] {
+        [_, _, _, _, high, low] if high < 256 && low < 256 => Some(high * 256 + low),
+        _ => None,
+    }
+}
+
+/// Parses one `ls -l` style line of a LIST reply
+pub fn parse_list_line(line: &str) -> Option<RemoteEntry> {
+    let mut rest = line.trim_start();
+    let mut fields = Vec::with_capacity(8);
+    // Permissions, links, owner, group, size and three date fields come before the name
+    for _ in 0..8 {
+        let end = rest.find(char::is_whitespace)?;
+        fields.push(&rest[..end]);
+        rest = rest[end..].trim_start();
+    }
+    if rest.is_empty() {
+        return None;
+    }
+
+    Some(RemoteEntry {
+        name: rest.to_string(),
+        size: fields[4].parse().ok()?,
+        is_dir: fields[0].starts_with('d'),
+    })
+}
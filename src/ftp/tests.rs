@@ -0,0 +1,84 @@
+use super::*;
+
+#[tokio::test]
+async fn test_read_reply_single_and_multi_line() {
+    let mut input = "220 Welcome\r\n".as_bytes();
+    assert_eq!(
+        read_reply(&mut input).await.unwrap(),
+        Reply {
+            code: 220,
+            message: "Welcome".to_string()
+        }
+    );
+
+    let mut input = "211-Features:\r\n PASV\r\n211 End\r\n226 Done\r\n".as_bytes();
+    let reply = read_reply(&mut input).await.unwrap();
+    assert_eq!(reply.code, 211);
+    assert_eq!(reply.message, "Features:\n PASV\nEnd");
+    // The following reply is left unread
+    assert_eq!(read_reply(&mut input).await.unwrap().code, 226);
+}
+
+#[tokio::test]
+async fn test_read_reply_errors() {
+    let mut input = "".as_bytes();
+    assert!(matches!(
+        read_reply(&mut input).await,
+        Err(FtpError::Protocol(_))
+    ));
+
+    let mut input = "hello\r\n".as_bytes();
+    assert!(matches!(
+        read_reply(&mut input).await,
+        Err(FtpError::Protocol(_))
+    ));
+}
+
+#[test]
+fn test_parse_pasv() {
+    assert_eq!(
+        parse_pasv("Entering Passive Mode (192,168,1,50,195,80)."),
+        Some(195 * 256 + 80)
+    );
+    assert_eq!(parse_pasv("Entering Passive Mode"), None);
+    assert_eq!(parse_pasv("(192,168,1,50,300,1)"), None);
+    assert_eq!(parse_pasv("(192,168,1,50,1)"), None);
+}
+
+#[test]
+fn test_parse_list_line() {
+    assert_eq!(
+        parse_list_line("-rw-r--r--    1 root     root      1048576 Mar 03 12:30 benchy.gcode.3mf"),
+        Some(RemoteEntry {
+            name: "benchy.gcode.3mf".to_string(),
+            size: 1_048_576,
+            is_dir: false,
+        })
+    );
+    assert_eq!(
+        parse_list_line("drwxr-xr-x 2 root root 4096 Jan 01 2024 timelapse"),
+        Some(RemoteEntry {
+            name: "timelapse".to_string(),
+            size: 4096,
+            is_dir: true,
+        })
+    );
+    // Names may contain spaces
+    assert_eq!(
+        parse_list_line("-rw-r--r-- 1 root root 10 Jan 01 00:00 my part.3mf")
+            .unwrap()
+            .name,
+        "my part.3mf"
+    );
+    assert_eq!(parse_list_line("total 8"), None);
+}
+
+#[tokio::test]
+async fn test_copy_data_counts_bytes() {
+    let mut output = Vec::new();
+    let copied = copy_data(&mut "0123456789".as_bytes(), &mut output)
+        .await
+        .unwrap();
+    assert_eq!(copied, 10);
+    assert_eq!(output, b"0123456789");
+}
@@ -0,0 +1,208 @@
+use crate::daemon::{self, PrinterEntry, SharedPrinters};
+use crate::filament;
+use crate::jobs::JobRecord;
+use crate::messages::PrintState;
+use crate::mqtt::PrintAction;
+use async_graphql::{Context, EmptySubscription, Enum, Object, Schema, SimpleObject};
+use chrono::{DateTime, Local};
+
+#[cfg(test)]
+mod tests;
+
+pub type PrinterSchema = Schema<Query, Mutation, EmptySubscription>;
+
+/// Schema served on `/graphql`, resolving against the daemon's printer table
+pub fn schema(printers: SharedPrinters) -> PrinterSchema {
+    Schema::build(Query, Mutation, EmptySubscription)
+        .data(printers)
+        .finish()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Enum)]
+pub enum JobState {
+    Idle,
+    Printing,
+    Paused,
+    Failed,
+    Finished,
+    Unknown,
+}
+
+impl From<&PrintState> for JobState {
+    fn from(state: &PrintState) -> Self {
+        match state {
+            PrintState::Idle => JobState::Idle,
+            PrintState::Printing => JobState::Printing,
+            PrintState::Paused => JobState::Paused,
+            PrintState::Failed => JobState::Failed,
+            PrintState::Finished => JobState::Finished,
+            PrintState::Unknown(_) => JobState::Unknown,
+        }
+    }
+}
+
+/// Live status of the current or last print
+#[derive(Debug, Clone, SimpleObject)]
+pub struct PrinterStatus {
+    pub state: JobState,
+    /// Raw printer state, e.g. RUNNING, PAUSE, FINISH
+    pub gcode_state: Option<String>,
+    pub job_name: Option<String>,
+    pub progress: Option<u32>,
+    pub remaining_minutes: Option<u32>,
+    /// Expected finish of the running print, computed when the remaining time was reported
+    pub finish_at: Option<DateTime<Local>>,
+    pub layer: Option<u32>,
+    pub total_layers: Option<u32>,
+    pub nozzle_temperature: Option<f64>,
+    pub bed_temperature: Option<f64>,
+    pub chamber_temperature: Option<f64>,
+    pub fail_reason: Option<String>,
+}
+
+#[derive(Debug, Clone, SimpleObject)]
+pub struct Printer {
+    pub name: String,
+    pub connected: bool,
+    pub last_message_at: Option<DateTime<Local>>,
+    pub message_count: u64,
+    pub status: PrinterStatus,
+}
+
+impl Printer {
+    fn from_entry(name: &str, entry: &PrinterEntry) -> Self {
+        let print = &entry.state.print;
+        let status = entry.state.status();
+        Self {
+            name: name.to_string(),
+            connected: entry.connected,
+            last_message_at: entry.last_message_at,
+            message_count: entry.state.message_count,
+            status: PrinterStatus {
+                state: JobState::from(&status.state),
+                gcode_state: print.gcode_state.clone(),
+                job_name: print.subtask_name.clone(),
+                progress: status.progress,
+                remaining_minutes: status.remaining_time,
+                finish_at: status.finish_at,
+                layer: print.layer_num,
+                total_layers: print.total_layer_num,
+                nozzle_temperature: print.nozzle_temper,
+                bed_temperature: print.bed_temper,
+                chamber_temperature: print.chamber_temper,
+                fail_reason: status.fail_reason,
+            },
+        }
+    }
+}
+
+/// A job the daemon saw finish or fail
+#[derive(Debug, Clone, SimpleObject)]
+pub struct Job {
+    pub printer: String,
+    pub name: Option<String>,
+    pub started_at: DateTime<Local>,
+    pub ended_at: DateTime<Local>,
+    pub state: JobState,
+    pub final_progress: Option<u32>,
+    pub duration_secs: u32,
+    pub fail_reason: Option<String>,
+    pub pauses: u32,
+    pub hms_codes: Vec<String>,
+    /// Estimated from AMS remaining percentages
+    pub filament_grams: Option<f64>,
+    /// Measured by a smart plug
+    pub energy_kwh: Option<f64>,
+}
+
+impl Job {
+    fn from_record(printer: &str, job: &JobRecord) -> Self {
+        let usage = job.filament_usage();
+        Self {
+            printer: printer.to_string(),
+            name: job.name.clone(),
+            started_at: job.started_at,
+            ended_at: job.ended_at,
+            state: JobState::from(&job.final_state),
+            final_progress: job.final_progress,
+            duration_secs: job.elapsed_secs(),
+            fail_reason: job.fail_reason.clone(),
+            pauses: job.pauses,
+            hms_codes: job.hms_codes.clone(),
+            filament_grams: (!usage.is_empty()).then(|| filament::total_grams(&usage)),
+            energy_kwh: job.energy_kwh(),
+        }
+    }
+}
+
+pub struct Query;
+
+#[Object]
+impl Query {
+    /// Every printer the daemon monitors
+    async fn printers(&self, ctx: &Context<'_>) -> Vec<Printer> {
+        ctx.data_unchecked::<SharedPrinters>()
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(name, entry)| Printer::from_entry(name, entry))
+            .collect()
+    }
+
+    async fn printer(&self, ctx: &Context<'_>, name: String) -> Option<Printer> {
+        ctx.data_unchecked::<SharedPrinters>()
+            .lock()
+            .unwrap()
+            .get(&name)
+            .map(|entry| Printer::from_entry(&name, entry))
+    }
+
+    /// Jobs finished or failed since the daemon started, newest first
+    async fn history(
+        &self,
+        ctx: &Context<'_>,
+        printer: Option<String>,
+        limit: Option<usize>,
+    ) -> Vec<Job> {
+        let printers = ctx.data_unchecked::<SharedPrinters>().lock().unwrap();
+        let mut jobs: Vec<Job> = printers
+            .iter()
+            .filter(|(name, _)| printer.as_ref().is_none_or(|printer| printer == *name))
+            .flat_map(|(name, entry)| entry.history.iter().map(|job| Job::from_record(name, job)))
+            .collect();
+        jobs.sort_by_key(|job| std::cmp::Reverse(job.ended_at));
+        jobs.truncate(limit.unwrap_or(usize::MAX));
+        jobs
+    }
+}
+
+pub struct Mutation;
+
+#[Object]
+impl Mutation {
+    async fn pause(&self, ctx: &Context<'_>, printer: String) -> async_graphql::Result<bool> {
+        control(ctx, printer, PrintAction::Pause).await
+    }
+
+    async fn resume(&self, ctx: &Context<'_>, printer: String) -> async_graphql::Result<bool> {
+        control(ctx, printer, PrintAction::Resume).await
+    }
+
+    /// Cancels the current print
+    async fn stop(&self, ctx: &Context<'_>, printer: String) -> async_graphql::Result<bool> {
+        control(ctx, printer, PrintAction::Stop).await
+    }
+}
+
+async fn control(
+    ctx: &Context<'_>,
+    printer: String,
+    action: PrintAction,
+) -> async_graphql::Result<bool> {
+    let printers = ctx.data_unchecked::<SharedPrinters>();
+    match daemon::handle_request(printers, daemon::Request::Control { printer, action }).await {
+        daemon::Response::Ok => Ok(true),
+        daemon::Response::Error { message } => Err(message.into()),
+        daemon::Response::Printers { .. } => Err("Unexpected daemon response".into()),
+    }
+}
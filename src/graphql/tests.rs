@@ -0,0 +1,88 @@
+use super::*;
+use crate::daemon::test_support::{apply, shared};
+use serde_json::json;
+use std::sync::Arc;
+
+async fn execute(printers: &SharedPrinters, query: &str) -> async_graphql::Response {
+    schema(Arc::clone(printers)).execute(query).await
+}
+
+#[tokio::test]
+async fn test_printers_query_selects_fields() {
+    let printers = shared(&["x1c", "a1"]);
+    apply(
+        &printers,
+        "x1c",
+        r#"{"print": {"gcode_state": "RUNNING", "percent": 42, "mc_remaining_time": 17, "subtask_name": "benchy"}}"#,
+    );
+
+    let response = execute(
+        &printers,
+        "{ printers { name connected status { state progress remainingMinutes jobName } } }",
+    )
+    .await;
+    assert!(response.errors.is_empty(), "{:?}", response.errors);
+    assert_eq!(
+        response.data.into_json().unwrap(),
+        json!({"printers": [
+            {"name": "a1", "connected": false, "status": {"state": "IDLE", "progress": null, "remainingMinutes": null, "jobName": null}},
+            {"name": "x1c", "connected": true, "status": {"state": "PRINTING", "progress": 42, "remainingMinutes": 17, "jobName": "benchy"}}
+        ]})
+    );
+
+    let response = execute(
+        &printers,
+        r#"{ x1c: printer(name: "x1c") { status { finishAt } } a1: printer(name: "a1") { status { finishAt } } }"#,
+    )
+    .await;
+    let data = response.data.into_json().unwrap();
+    assert!(data["x1c"]["status"]["finishAt"].is_string());
+    assert!(data["a1"]["status"]["finishAt"].is_null());
+
+    let response = execute(&printers, r#"{ printer(name: "p1s") { name } }"#).await;
+    assert_eq!(response.data.into_json().unwrap(), json!({"printer": null}));
+}
+
+#[tokio::test]
+async fn test_history_lists_completed_jobs() {
+    let printers = shared(&["x1c", "a1"]);
+    for payload in [
+        r#"{"print": {"gcode_state": "RUNNING", "percent": 0, "subtask_name": "benchy"}}"#,
+        r#"{"print": {"gcode_state": "FINISH", "percent": 100}}"#,
+    ] {
+        apply(&printers, "x1c", payload);
+    }
+    apply(
+        &printers,
+        "a1",
+        r#"{"print": {"gcode_state": "RUNNING", "percent": 5}}"#,
+    );
+
+    let response = execute(
+        &printers,
+        r#"{ history { printer state finalProgress } filtered: history(printer: "a1") { printer } }"#,
+    )
+    .await;
+    assert!(response.errors.is_empty(), "{:?}", response.errors);
+    assert_eq!(
+        response.data.into_json().unwrap(),
+        json!({
+            "history": [{"printer": "x1c", "state": "FINISHED", "finalProgress": 100}],
+            "filtered": []
+        })
+    );
+}
+
+#[tokio::test]
+async fn test_control_mutations_report_errors() {
+    let printers = shared(&["x1c"]);
+
+    let response = execute(&printers, r#"mutation { stop(printer: "x1c") }"#).await;
+    assert_eq!(response.errors[0].message, "Printer 'x1c' is not connected");
+
+    let response = execute(&printers, r#"mutation { pause(printer: "p1s") }"#).await;
+    assert_eq!(
+        response.errors[0].message,
+        "Daemon is not monitoring a printer named 'p1s'"
+    );
+}
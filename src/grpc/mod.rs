@@ -0,0 +1,161 @@
+use crate::daemon::{PrinterSnapshot, SharedPrinters};
+use crate::mqtt::PrintAction;
+use std::sync::Arc;
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::{ReceiverStream, TcpListenerStream};
+use tonic::{Request, Response, Status};
+
+#[cfg(test)]
+mod tests;
+
+/// Types and service stubs generated from `proto/pulseprint.proto`
+pub mod proto {
+    tonic::include_proto!("pulseprint.v1");
+}
+
+use proto::printers_server::{Printers, PrintersServer};
+use proto::{
+    ControlResponse, ListPrintersRequest, ListPrintersResponse, PrinterRequest, PrinterState,
+};
+
+/// Updates buffered per `WatchPrinter` stream before waiting on a slow client
+const WATCH_BUFFER: usize = 16;
+
+impl From<PrinterSnapshot> for PrinterState {
+    fn from(snapshot: PrinterSnapshot) -> Self {
+        let print = &snapshot.state.print;
+        Self {
+            print_json: serde_json::to_string(print).unwrap_or_default(),
+            name: snapshot.name.clone(),
+            connected: snapshot.connected,
+            last_message_at: snapshot.last_message_at.map(|at| at.timestamp()),
+            message_count: snapshot.state.message_count,
+            gcode_state: print.gcode_state.clone(),
+            job_name: print.subtask_name.clone(),
+            percent: snapshot.state.status().progress,
+            remaining_minutes: print.mc_remaining_time.or(print.remaining_time),
+            layer: print.layer_num,
+            total_layers: print.total_layer_num,
+            nozzle_temperature: print.nozzle_temper,
+            bed_temperature: print.bed_temper,
+            chamber_temperature: print.chamber_temper,
+            finish_at: snapshot.state.finish_at.map(|at| at.timestamp()),
+        }
+    }
+}
+
+/// gRPC view of the daemon's printer table
+pub struct PrinterService {
+    printers: SharedPrinters,
+}
+
+impl PrinterService {
+    pub fn new(printers: SharedPrinters) -> Self {
+        Self { printers }
+    }
+
+    fn snapshot(&self, name: &str) -> Result<PrinterSnapshot, Status> {
+        self.printers
+            .lock()
+            .unwrap()
+            .get(name)
+            .map(|entry| entry.snapshot(name))
+            .ok_or_else(|| unknown_printer(name))
+    }
+
+    async fn control(
+        &self,
+        request: Request<PrinterRequest>,
+        action: PrintAction,
+    ) -> Result<Response<ControlResponse>, Status> {
+        let name = request.into_inner().name;
+        let control = {
+            let printers = self.printers.lock().unwrap();
+            let entry = printers.get(&name).ok_or_else(|| unknown_printer(&name))?;
+            entry.control.clone()
+        };
+        let control = control
+            .ok_or_else(|| Status::unavailable(format!("Printer '{name}' is not connected")))?;
+        control
+            .execute(action)
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?;
+        Ok(Response::new(ControlResponse {}))
+    }
+}
+
+fn unknown_printer(name: &str) -> Status {
+    Status::not_found(format!("Daemon is not monitoring a printer named '{name}'"))
+}
+
+#[tonic::async_trait]
+impl Printers for PrinterService {
+    async fn list_printers(
+        &self,
+        _request: Request<ListPrintersRequest>,
+    ) -> Result<Response<ListPrintersResponse>, Status> {
+        let printers = self
+            .printers
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(name, entry)| entry.snapshot(name).into())
+            .collect();
+        Ok(Response::new(ListPrintersResponse { printers }))
+    }
+
+    type WatchPrinterStream = ReceiverStream<Result<PrinterState, Status>>;
+
+    async fn watch_printer(
+        &self,
+        request: Request<PrinterRequest>,
+    ) -> Result<Response<Self::WatchPrinterStream>, Status> {
+        let name = request.into_inner().name;
+        let mut changes = {
+            let printers = self.printers.lock().unwrap();
+            let entry = printers.get(&name).ok_or_else(|| unknown_printer(&name))?;
+            entry.changes.subscribe()
+        };
+
+        let (sender, receiver) = mpsc::channel(WATCH_BUFFER);
+        let service = Self::new(Arc::clone(&self.printers));
+        tokio::spawn(async move {
+            loop {
+                // Mark the current version seen before reading, so no change is missed
+                changes.borrow_and_update();
+                let update = service.snapshot(&name).map(PrinterState::from);
+                if sender.send(update).await.is_err() || changes.changed().await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        Ok(Response::new(ReceiverStream::new(receiver)))
+    }
+
+    async fn pause_print(
+        &self,
+        request: Request<PrinterRequest>,
+    ) -> Result<Response<ControlResponse>, Status> {
+        self.control(request, PrintAction::Pause).await
+    }
+
+    async fn resume_print(
+        &self,
+        request: Request<PrinterRequest>,
+    ) -> Result<Response<ControlResponse>, Status> {
+        self.control(request, PrintAction::Resume).await
+    }
+}
+
+/// Serves the gRPC API until the task is aborted
+pub async fn serve(
+    listener: TcpListener,
+    printers: SharedPrinters,
+) -> Result<(), tonic::transport::Error> {
+    tonic::transport::Server::builder()
+        .add_service(PrintersServer::new(PrinterService::new(printers)))
+        .serve_with_incoming(TcpListenerStream::new(listener))
+        .await
+}
@@ -0,0 +1,89 @@
+use super::proto::printers_client::PrintersClient;
+use super::*;
+use crate::daemon::test_support::{apply, shared};
+use std::time::Duration;
+use tokio_stream::StreamExt;
+use tonic::Code;
+use tonic::transport::Channel;
+
+/// Serves the API on an ephemeral port and connects a client to it
+async fn connect(printers: &SharedPrinters) -> PrintersClient<Channel> {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    tokio::spawn(serve(listener, Arc::clone(printers)));
+    PrintersClient::connect(format!("http://{address}"))
+        .await
+        .unwrap()
+}
+
+fn request(name: &str) -> PrinterRequest {
+    PrinterRequest {
+        name: name.to_string(),
+    }
+}
+
+#[tokio::test]
+async fn test_list_printers_maps_state() {
+    let printers = shared(&["x1c", "a1"]);
+    apply(
+        &printers,
+        "x1c",
+        r#"{"print": {"gcode_state": "RUNNING", "percent": 42, "mc_remaining_time": 17, "subtask_name": "benchy", "nozzle_temper": 220.0}}"#,
+    );
+    let mut client = connect(&printers).await;
+
+    let response = client
+        .list_printers(ListPrintersRequest {})
+        .await
+        .unwrap()
+        .into_inner();
+    let names: Vec<_> = response.printers.iter().map(|p| p.name.as_str()).collect();
+    assert_eq!(names, vec!["a1", "x1c"]);
+
+    let x1c = &response.printers[1];
+    assert!(x1c.connected);
+    assert_eq!(x1c.gcode_state.as_deref(), Some("RUNNING"));
+    assert_eq!(x1c.job_name.as_deref(), Some("benchy"));
+    assert_eq!(x1c.percent, Some(42));
+    assert_eq!(x1c.remaining_minutes, Some(17));
+    assert_eq!(x1c.nozzle_temperature, Some(220.0));
+    assert!(x1c.print_json.contains(r#""subtask_name":"benchy""#));
+    assert!(!response.printers[0].connected);
+}
+
+#[tokio::test]
+async fn test_watch_printer_streams_changes() {
+    let printers = shared(&["x1c"]);
+    let mut client = connect(&printers).await;
+
+    let mut stream = client
+        .watch_printer(request("x1c"))
+        .await
+        .unwrap()
+        .into_inner();
+    let first = stream.next().await.unwrap().unwrap();
+    assert_eq!(first.percent, None);
+
+    apply(&printers, "x1c", r#"{"print": {"percent": 10}}"#);
+    let update = tokio::time::timeout(Duration::from_secs(5), stream.next())
+        .await
+        .unwrap()
+        .unwrap()
+        .unwrap();
+    assert_eq!(update.percent, Some(10));
+    assert_eq!(update.message_count, 1);
+
+    let error = client.watch_printer(request("p1s")).await.unwrap_err();
+    assert_eq!(error.code(), Code::NotFound);
+}
+
+#[tokio::test]
+async fn test_control_requires_connection() {
+    let printers = shared(&["x1c"]);
+    let mut client = connect(&printers).await;
+
+    let error = client.pause_print(request("x1c")).await.unwrap_err();
+    assert_eq!(error.code(), Code::Unavailable);
+    let error = client.resume_print(request("p1s")).await.unwrap_err();
+    assert_eq!(error.code(), Code::NotFound);
+}
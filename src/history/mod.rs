@@ -0,0 +1,400 @@
+use crate::jobs::JobRecord;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt::Debug;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use thiserror::Error;
+
+#[cfg(test)]
+mod tests;
+
+#[derive(Debug, Error)]
+pub enum HistoryError {
+    #[error("IO error: {0}")]
+    IoError(#[from] std::io::Error),
+
+    #[error("Invalid history entry: {0}")]
+    JsonError(#[from] serde_json::Error),
+
+    #[cfg(feature = "postgres")]
+    #[error("Database error: {0}")]
+    Postgres(#[from] tokio_postgres::Error),
+
+    #[cfg(feature = "postgres")]
+    #[error("TLS error: {0}")]
+    Tls(#[from] tokio_native_tls::native_tls::Error),
+
+    #[error("The history database connection is closed")]
+    Closed,
+}
+
+/// Storage for the jobs the daemon saw finish or fail. The daemon keeps recent jobs of each
+/// printer in memory for its APIs; a store makes them outlive the daemon, and a store shared
+/// by several hosts collects the history of a whole farm.
+///
+/// Calls are made from the printer connections as jobs end, so implementations should
+/// return quickly.
+pub trait HistoryStore: Debug + Send + Sync {
+    /// Saves a finished or failed job of `printer`
+    fn record(&self, printer: &str, job: &JobRecord) -> Result<(), HistoryError>;
+
+    /// The latest `limit` jobs of `printer`, oldest first
+    fn recent(&self, printer: &str, limit: usize) -> Result<Vec<JobRecord>, HistoryError>;
+
+    /// Saved jobs with their printer, oldest first: all of them, or only those of `printer`
+    /// and those that ended at or after `since`
+    fn entries(
+        &self,
+        printer: Option<&str>,
+        since: Option<DateTime<Utc>>,
+    ) -> Result<Vec<(String, JobRecord)>, HistoryError>;
+}
+
+/// One line of a [`FileHistory`]
+#[derive(Debug, Serialize, Deserialize)]
+struct HistoryLine {
+    printer: String,
+    job: JobRecord,
+}
+
+/// Jobs of every printer appended to one NDJSON file
+#[derive(Debug)]
+pub struct FileHistory {
+    path: PathBuf,
+    /// Keeps lines from concurrent connections whole
+    lock: Mutex<()>,
+}
+
+impl FileHistory {
+    pub fn new(path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            lock: Mutex::new(()),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Every saved job with its printer, in the order they were saved
+    fn read(&self) -> Result<Vec<(String, JobRecord)>, HistoryError> {
+        let _guard = self.lock.lock().unwrap();
+        let file = match File::open(&self.path) {
+            Ok(file) => file,
+            // Nothing recorded yet
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut entries = Vec::new();
+        for line in BufReader::new(file).lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let entry: HistoryLine = serde_json::from_str(&line)?;
+            entries.push((entry.printer, entry.job));
+        }
+        Ok(entries)
+    }
+}
+
+impl HistoryStore for FileHistory {
+    fn record(&self, printer: &str, job: &JobRecord) -> Result<(), HistoryError> {
+        let mut line = serde_json::to_vec(&HistoryLine {
+            printer: printer.to_string(),
+            job: job.clone(),
+        })?;
+        line.push(b'\n');
+
+        let _guard = self.lock.lock().unwrap();
+        if let Some(parent) = self
+            .path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+        {
+            fs::create_dir_all(parent)?;
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        file.write_all(&line)?;
+        Ok(())
+    }
+
+    fn recent(&self, printer: &str, limit: usize) -> Result<Vec<JobRecord>, HistoryError> {
+        let mut jobs: Vec<JobRecord> = self
+            .read()?
+            .into_iter()
+            .filter(|(name, _)| name == printer)
+            .map(|(_, job)| job)
+            .collect();
+        let skip = jobs.len().saturating_sub(limit);
+        Ok(jobs.split_off(skip))
+    }
+
+    fn entries(
+        &self,
+        printer: Option<&str>,
+        since: Option<DateTime<Utc>>,
+    ) -> Result<Vec<(String, JobRecord)>, HistoryError> {
+        Ok(self
+            .read()?
+            .into_iter()
+            .filter(|(name, _)| printer.is_none_or(|printer| printer == name))
+            .filter(|(_, job)| since.is_none_or(|since| job.ended_at >= since))
+            .collect())
+    }
+}
+
+/// Table of the `postgres` history backend, created when the daemon connects
+pub const CREATE_TABLE_SQL: &str = "\
+CREATE TABLE IF NOT EXISTS print_jobs (
+    id BIGSERIAL PRIMARY KEY,
+    printer TEXT NOT NULL,
+    name TEXT,
+    started_at TIMESTAMPTZ NOT NULL,
+    ended_at TIMESTAMPTZ NOT NULL,
+    final_state TEXT NOT NULL,
+    job JSONB NOT NULL
+);
+CREATE INDEX IF NOT EXISTS print_jobs_printer_ended_at ON print_jobs (printer, ended_at)";
+
+pub const INSERT_JOB_SQL: &str = "\
+INSERT INTO print_jobs (printer, name, started_at, ended_at, final_state, job)
+VALUES ($1, $2, $3, $4, $5, $6)";
+
+/// Takes the printer and the row limit; the inner query picks the latest jobs, the outer one
+/// puts them oldest first
+pub const RECENT_JOBS_SQL: &str = "\
+SELECT job FROM (
+    SELECT id, ended_at, job FROM print_jobs
+    WHERE printer = $1
+    ORDER BY ended_at DESC, id DESC
+    LIMIT $2
+) AS latest
+ORDER BY ended_at, id";
+
+/// Takes an optional printer and an optional earliest end time, each ignored when `NULL`
+pub const ENTRIES_SQL: &str = "\
+SELECT printer, job FROM print_jobs
+WHERE ($1::TEXT IS NULL OR printer = $1)
+    AND ($2::TIMESTAMPTZ IS NULL OR ended_at >= $2)
+ORDER BY ended_at, id";
+
+/// One row of the `print_jobs` table. The whole record goes in the `job` column; the other
+/// columns copy the fields farm dashboards filter on.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JobRow {
+    pub printer: String,
+    pub name: Option<String>,
+    pub started_at: chrono::DateTime<chrono::Utc>,
+    pub ended_at: chrono::DateTime<chrono::Utc>,
+    pub final_state: String,
+    pub job: serde_json::Value,
+}
+
+impl JobRow {
+    pub fn new(printer: &str, job: &JobRecord) -> Result<Self, HistoryError> {
+        let record = serde_json::to_value(job)?;
+        Ok(Self {
+            printer: printer.to_string(),
+            name: job.name.clone(),
+            started_at: job.started_at.to_utc(),
+            ended_at: job.ended_at.to_utc(),
+            final_state: record["final_state"]
+                .as_str()
+                .unwrap_or_default()
+                .to_string(),
+            job: record,
+        })
+    }
+
+    /// The record stored in a `job` column
+    pub fn decode(job: serde_json::Value) -> Result<JobRecord, HistoryError> {
+        Ok(serde_json::from_value(job)?)
+    }
+
+    /// The printer and record read back from the `printer` and `job` columns
+    pub fn entry(
+        printer: String,
+        job: serde_json::Value,
+    ) -> Result<(String, JobRecord), HistoryError> {
+        Ok((printer, Self::decode(job)?))
+    }
+
+    /// Column values in the order [`INSERT_JOB_SQL`] binds them
+    #[cfg(feature = "postgres")]
+    fn params(&self) -> [&(dyn tokio_postgres::types::ToSql + Sync); 6] {
+        [
+            &self.printer,
+            &self.name,
+            &self.started_at,
+            &self.ended_at,
+            &self.final_state,
+            &self.job,
+        ]
+    }
+}
+
+#[cfg(feature = "postgres")]
+enum PostgresRequest {
+    Record(JobRow),
+    Recent {
+        printer: String,
+        limit: i64,
+        reply: std::sync::mpsc::Sender<Result<Vec<JobRecord>, HistoryError>>,
+    },
+    Entries {
+        printer: Option<String>,
+        since: Option<DateTime<Utc>>,
+        reply: std::sync::mpsc::Sender<Result<Vec<(String, JobRecord)>, HistoryError>>,
+    },
+}
+
+/// Jobs saved to a PostgreSQL table. The connection lives on a thread of its own, so saving a
+/// job only queues it and never holds up a printer connection.
+#[cfg(feature = "postgres")]
+#[derive(Debug)]
+pub struct PostgresHistory {
+    requests: Mutex<std::sync::mpsc::Sender<PostgresRequest>>,
+}
+
+#[cfg(feature = "postgres")]
+impl PostgresHistory {
+    /// Connects to `url` and creates the table when missing. TLS is used when the server
+    /// offers it, or required with `sslmode=require` in the URL.
+    pub fn connect(url: &str) -> Result<Self, HistoryError> {
+        let (requests, receiver) = std::sync::mpsc::channel();
+        let (ready, connected) = std::sync::mpsc::channel();
+        let url = url.to_string();
+        std::thread::Builder::new()
+            .name("history-postgres".to_string())
+            .spawn(move || {
+                let runtime = match tokio::runtime::Builder::new_current_thread()
+                    .enable_all()
+                    .build()
+                {
+                    Ok(runtime) => runtime,
+                    Err(e) => {
+                        let _ = ready.send(Err(HistoryError::from(e)));
+                        return;
+                    }
+                };
+                runtime.block_on(async move {
+                    let client = match Self::open(&url).await {
+                        Ok(client) => {
+                            let _ = ready.send(Ok(()));
+                            client
+                        }
+                        Err(e) => {
+                            let _ = ready.send(Err(e));
+                            return;
+                        }
+                    };
+                    // Ends when the store is dropped
+                    while let Ok(request) = receiver.recv() {
+                        Self::serve(&client, request).await;
+                    }
+                });
+            })?;
+
+        connected.recv().map_err(|_| HistoryError::Closed)??;
+        Ok(Self {
+            requests: Mutex::new(requests),
+        })
+    }
+
+    async fn open(url: &str) -> Result<tokio_postgres::Client, HistoryError> {
+        let tls = postgres_native_tls::MakeTlsConnector::new(
+            tokio_native_tls::native_tls::TlsConnector::new()?,
+        );
+        let (client, connection) = tokio_postgres::connect(url, tls).await?;
+        tokio::spawn(async move {
+            if let Err(e) = connection.await {
+                eprintln!("Job history: database connection lost: {e}");
+            }
+        });
+        client.batch_execute(CREATE_TABLE_SQL).await?;
+        Ok(client)
+    }
+
+    async fn serve(client: &tokio_postgres::Client, request: PostgresRequest) {
+        match request {
+            PostgresRequest::Record(row) => {
+                if let Err(e) = client.execute(INSERT_JOB_SQL, &row.params()).await {
+                    eprintln!("Printer '{}': cannot save the job: {e}", row.printer);
+                }
+            }
+            PostgresRequest::Recent {
+                printer,
+                limit,
+                reply,
+            } => {
+                let jobs = match client.query(RECENT_JOBS_SQL, &[&printer, &limit]).await {
+                    Ok(rows) => rows.iter().map(|row| JobRow::decode(row.get(0))).collect(),
+                    Err(e) => Err(e.into()),
+                };
+                let _ = reply.send(jobs);
+            }
+            PostgresRequest::Entries {
+                printer,
+                since,
+                reply,
+            } => {
+                let entries = match client.query(ENTRIES_SQL, &[&printer, &since]).await {
+                    Ok(rows) => rows
+                        .iter()
+                        .map(|row| JobRow::entry(row.get(0), row.get(1)))
+                        .collect(),
+                    Err(e) => Err(e.into()),
+                };
+                let _ = reply.send(entries);
+            }
+        }
+    }
+
+    fn send(&self, request: PostgresRequest) -> Result<(), HistoryError> {
+        self.requests
+            .lock()
+            .unwrap()
+            .send(request)
+            .map_err(|_| HistoryError::Closed)
+    }
+}
+
+#[cfg(feature = "postgres")]
+impl HistoryStore for PostgresHistory {
+    fn record(&self, printer: &str, job: &JobRecord) -> Result<(), HistoryError> {
+        self.send(PostgresRequest::Record(JobRow::new(printer, job)?))
+    }
+
+    fn recent(&self, printer: &str, limit: usize) -> Result<Vec<JobRecord>, HistoryError> {
+        let (reply, jobs) = std::sync::mpsc::channel();
+        self.send(PostgresRequest::Recent {
+            printer: printer.to_string(),
+            limit: i64::try_from(limit).unwrap_or(i64::MAX),
+            reply,
+        })?;
+        jobs.recv().map_err(|_| HistoryError::Closed)?
+    }
+
+    fn entries(
+        &self,
+        printer: Option<&str>,
+        since: Option<DateTime<Utc>>,
+    ) -> Result<Vec<(String, JobRecord)>, HistoryError> {
+        let (reply, entries) = std::sync::mpsc::channel();
+        self.send(PostgresRequest::Entries {
+            printer: printer.map(str::to_string),
+            since,
+            reply,
+        })?;
+        entries.recv().map_err(|_| HistoryError::Closed)?
+    }
+}
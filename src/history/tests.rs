@@ -0,0 +1,173 @@
+use super::*;
+use crate::jobs::JobTracker;
+use crate::messages::{DeviceMessage, MergedState};
+use chrono::{Local, TimeZone};
+use tempfile::tempdir;
+
+fn finished_job(name: &str, state: &str) -> JobRecord {
+    let start = Local.with_ymd_and_hms(2025, 8, 1, 9, 0, 0).unwrap();
+    let mut tracker = JobTracker::default();
+    let mut merged = MergedState::default();
+
+    for (minutes, payload) in [
+        (
+            0,
+            format!(
+                r#"{{"print": {{"gcode_state": "RUNNING", "subtask_name": "{name}", "percent": 0, "nozzle_temper": 220}}}}"#
+            ),
+        ),
+        (
+            30,
+            format!(r#"{{"print": {{"gcode_state": "{state}", "percent": 100}}}}"#),
+        ),
+    ] {
+        merged.update(&DeviceMessage::parse(&payload).unwrap());
+        if let Some(job) = tracker.observe(&merged, start + chrono::Duration::minutes(minutes)) {
+            return job;
+        }
+    }
+    panic!("job did not complete");
+}
+
+#[test]
+fn test_file_history_round_trip() {
+    let temp_dir = tempdir().unwrap();
+    let store = FileHistory::new(&temp_dir.path().join("farm/history.jsonl"));
+    assert!(store.recent("x1c", 10).unwrap().is_empty());
+
+    let benchy = finished_job("benchy", "FINISH");
+    store.record("x1c", &benchy).unwrap();
+    store
+        .record("p1s", &finished_job("vase", "FAILED"))
+        .unwrap();
+    store
+        .record("x1c", &finished_job("gears", "FINISH"))
+        .unwrap();
+
+    let jobs = store.recent("x1c", 10).unwrap();
+    assert_eq!(jobs.len(), 2);
+    assert_eq!(jobs[0], benchy);
+    assert_eq!(jobs[1].name.as_deref(), Some("gears"));
+
+    // The latest jobs, still oldest first
+    let jobs = store.recent("x1c", 1).unwrap();
+    assert_eq!(jobs.len(), 1);
+    assert_eq!(jobs[0].name.as_deref(), Some("gears"));
+    assert_eq!(store.recent("p1s", 10).unwrap().len(), 1);
+}
+
+#[test]
+fn test_file_history_entries() {
+    let temp_dir = tempdir().unwrap();
+    let store = FileHistory::new(&temp_dir.path().join("history.jsonl"));
+    assert!(store.entries(None, None).unwrap().is_empty());
+
+    let benchy = finished_job("benchy", "FINISH");
+    store.record("x1c", &benchy).unwrap();
+    store
+        .record("p1s", &finished_job("vase", "FAILED"))
+        .unwrap();
+
+    let entries = store.entries(None, None).unwrap();
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0], ("x1c".to_string(), benchy.clone()));
+    assert_eq!(entries[1].0, "p1s");
+
+    let entries = store.entries(Some("p1s"), None).unwrap();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].1.name.as_deref(), Some("vase"));
+
+    // Both jobs ended at 09:30
+    let ended = benchy.ended_at.to_utc();
+    assert_eq!(store.entries(None, Some(ended)).unwrap().len(), 2);
+    let later = ended + chrono::Duration::seconds(1);
+    assert!(store.entries(None, Some(later)).unwrap().is_empty());
+}
+
+#[test]
+fn test_file_history_rejects_corrupt_lines() {
+    let temp_dir = tempdir().unwrap();
+    let path = temp_dir.path().join("history.jsonl");
+    std::fs::write(&path, "not json\n").unwrap();
+
+    let store = FileHistory::new(&path);
+    assert!(matches!(
+        store.recent("x1c", 10),
+        Err(HistoryError::JsonError(_))
+    ));
+}
+
+#[test]
+fn test_job_row_round_trip() {
+    let benchy = finished_job("benchy", "FINISH");
+    let row = JobRow::new("x1c", &benchy).unwrap();
+    assert_eq!(row.printer, "x1c");
+    assert_eq!(row.name.as_deref(), Some("benchy"));
+    assert_eq!(row.final_state, "finished");
+    assert_eq!(row.started_at, benchy.started_at.to_utc());
+    assert_eq!(row.ended_at - row.started_at, chrono::Duration::minutes(30));
+    assert_eq!(JobRow::decode(row.job).unwrap(), benchy);
+
+    let failed = JobRow::new("x1c", &finished_job("bracket", "FAILED")).unwrap();
+    assert_eq!(failed.final_state, "failed");
+}
+
+#[test]
+fn test_job_row_entry_round_trip() {
+    for job in [
+        finished_job("benchy", "FINISH"),
+        finished_job("bracket", "FAILED"),
+    ] {
+        let row = JobRow::new("x1c", &job).unwrap();
+        let (printer, decoded) = JobRow::entry(row.printer.clone(), row.job.clone()).unwrap();
+        assert_eq!(printer, "x1c");
+        assert_eq!(decoded, job);
+
+        // The copied columns agree with the record read back
+        assert_eq!(JobRow::new(&printer, &decoded).unwrap(), row);
+        assert_eq!(row.name, decoded.name);
+        assert_eq!(row.started_at, decoded.started_at);
+        assert_eq!(row.ended_at, decoded.ended_at);
+    }
+
+    assert!(matches!(
+        JobRow::entry("x1c".to_string(), serde_json::json!({"name": "benchy"})),
+        Err(HistoryError::JsonError(_))
+    ));
+}
+
+/// Needs a scratch database: `DATABASE_URL=postgres://... cargo test --features postgres --
+/// --ignored`
+#[cfg(feature = "postgres")]
+#[test]
+#[ignore]
+fn test_postgres_history_round_trip() {
+    let url = std::env::var("DATABASE_URL").expect("DATABASE_URL is not set");
+    let store = PostgresHistory::connect(&url).unwrap();
+    // Keeps runs against the same database apart
+    let printer = format!(
+        "test-{}",
+        Local::now().timestamp_nanos_opt().unwrap_or_default()
+    );
+
+    let benchy = finished_job("benchy", "FINISH");
+    store.record(&printer, &benchy).unwrap();
+    store
+        .record(&printer, &finished_job("gears", "FAILED"))
+        .unwrap();
+
+    let jobs = store.recent(&printer, 1).unwrap();
+    assert_eq!(jobs.len(), 1);
+    assert_eq!(jobs[0].name.as_deref(), Some("gears"));
+
+    let entries = store.entries(Some(&printer), None).unwrap();
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0], (printer.clone(), benchy.clone()));
+    let later = benchy.ended_at.to_utc() + chrono::Duration::seconds(1);
+    assert!(
+        store
+            .entries(Some(&printer), Some(later))
+            .unwrap()
+            .is_empty()
+    );
+}
@@ -0,0 +1,707 @@
+use crate::config::{CostSettings, HmsSeverity};
+use crate::filament::{self, FilamentUsage, PlannedUsage, TrayReading};
+use crate::messages::{MergedState, PrintInfo, PrintState};
+use crate::project::PlateInfo;
+use crate::telemetry::TelemetrySample;
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+#[cfg(test)]
+mod tests;
+
+/// Minimum time between two telemetry samples kept with a job
+const TELEMETRY_INTERVAL_SECS: i64 = 60;
+
+/// Running peak and average of one temperature sensor over a job
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct TemperatureStats {
+    pub peak: f64,
+    sum: f64,
+    samples: u32,
+}
+
+impl TemperatureStats {
+    fn record(&mut self, reading: f64) {
+        self.peak = if self.samples == 0 {
+            reading
+        } else {
+            self.peak.max(reading)
+        };
+        self.sum += reading;
+        self.samples += 1;
+    }
+
+    pub fn average(&self) -> Option<f64> {
+        (self.samples > 0).then(|| self.sum / f64::from(self.samples))
+    }
+}
+
+/// Everything observed about one print job, from the first active state to finished/failed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JobRecord {
+    pub name: Option<String>,
+    pub started_at: DateTime<Local>,
+    pub ended_at: DateTime<Local>,
+    pub final_state: PrintState,
+    /// Progress when monitoring first saw the job; non-zero means the start was missed
+    pub start_progress: u32,
+    pub final_progress: Option<u32>,
+    pub total_layers: Option<u32>,
+    pub last_layer: Option<u32>,
+    pub nozzle: TemperatureStats,
+    pub bed: TemperatureStats,
+    pub fail_reason: Option<String>,
+    /// Times the job went from printing to paused
+    pub pauses: u32,
+    /// HMS error codes seen during the job, in order of first appearance
+    pub hms_codes: Vec<String>,
+    /// First and latest AMS reading of every tray seen during the job
+    pub trays_at_start: BTreeMap<String, TrayReading>,
+    pub trays_at_end: BTreeMap<String, TrayReading>,
+    /// Trays that fed the printer during the job, in order of first use
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub trays_fed: Vec<String>,
+    /// Smart plug energy counter at the first and latest observation of the job
+    pub energy_start_kwh: Option<f64>,
+    pub energy_end_kwh: Option<f64>,
+    /// Filled in from [`JobRecord::estimate_cost`] when cost rates are configured
+    pub cost: Option<JobCost>,
+    /// Slicer metadata of the printed plate, when the project was inspected or uploaded
+    pub plate: Option<PlateInfo>,
+    /// Temperatures and progress about once a minute, plus the final state; charted by
+    /// `history chart`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub telemetry: Vec<TelemetrySample>,
+}
+
+/// Estimated cost of one job, in the configured currency
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JobCost {
+    pub filament: Option<f64>,
+    pub electricity: Option<f64>,
+    pub currency: Option<String>,
+}
+
+impl JobCost {
+    pub fn total(&self) -> f64 {
+        self.filament.unwrap_or(0.0) + self.electricity.unwrap_or(0.0)
+    }
+
+    /// Two decimals followed by the currency, e.g. `1.37 EUR`
+    pub fn format_amount(&self, amount: f64) -> String {
+        match &self.currency {
+            Some(currency) => format!("{amount:.2} {currency}"),
+            None => format!("{amount:.2}"),
+        }
+    }
+}
+
+impl JobRecord {
+    pub fn elapsed_secs(&self) -> u32 {
+        (self.ended_at - self.started_at).num_seconds().max(0) as u32
+    }
+
+    /// Average progress in percent per hour over the observed part of the job
+    pub fn progress_per_hour(&self) -> Option<f64> {
+        let elapsed = self.elapsed_secs();
+        let gained = self.final_progress?.saturating_sub(self.start_progress);
+        (elapsed > 0).then(|| f64::from(gained) * 3600.0 / f64::from(elapsed))
+    }
+
+    /// Filament drawn from each AMS tray, estimated from its remaining percentage, or from
+    /// the plate's slicer weights when no tray reading went down
+    pub fn filament_usage(&self) -> Vec<FilamentUsage> {
+        let planned = self.plate.as_ref().map(|plate| PlannedUsage {
+            plate,
+            trays_fed: &self.trays_fed,
+            progress: match self.final_state {
+                PrintState::Finished => 100,
+                _ => self.final_progress.unwrap_or(0),
+            },
+        });
+        filament::estimate_usage(&self.trays_at_start, &self.trays_at_end, planned)
+    }
+
+    /// Energy measured by the smart plug over the observed part of the job
+    pub fn energy_kwh(&self) -> Option<f64> {
+        Some((self.energy_end_kwh? - self.energy_start_kwh?).max(0.0))
+    }
+
+    /// Filament from the AMS usage estimate, electricity from the smart plug measurement or,
+    /// without one, the average printer draw over the observed duration
+    pub fn estimate_cost(&self, settings: &CostSettings) -> Option<JobCost> {
+        let usage = self.filament_usage();
+        let filament = settings
+            .filament_per_kg
+            .filter(|_| !usage.is_empty())
+            .map(|per_kg| filament::total_grams(&usage) / 1000.0 * per_kg);
+        let electricity = settings.electricity_per_kwh.map(|per_kwh| {
+            let kwh = self.energy_kwh().unwrap_or_else(|| {
+                let hours = f64::from(self.elapsed_secs()) / 3600.0;
+                settings.printer_watts * hours / 1000.0
+            });
+            kwh * per_kwh
+        });
+
+        (filament.is_some() || electricity.is_some()).then(|| JobCost {
+            filament,
+            electricity,
+            currency: settings.currency.clone(),
+        })
+    }
+
+    /// Folds the latest merged state into the record
+    fn absorb(&mut self, state: &MergedState, now: DateTime<Local>) {
+        let status = state.status();
+        let print = &state.print;
+
+        self.ended_at = now;
+        self.final_state = status.state;
+        self.fail_reason = status.fail_reason.or(self.fail_reason.take());
+        if print.subtask_name.is_some() {
+            self.name = print.subtask_name.clone();
+        }
+        self.final_progress = status.progress.or(self.final_progress);
+        self.total_layers = print.total_layer_num.or(self.total_layers);
+        self.last_layer = print.layer_num.or(self.last_layer);
+        if let Some(nozzle) = print.nozzle_temper {
+            self.nozzle.record(nozzle);
+        }
+        if let Some(bed) = print.bed_temper {
+            self.bed.record(bed);
+        }
+        for code in hms_codes(print.extra.get("hms")) {
+            if !self.hms_codes.contains(&code) {
+                self.hms_codes.push(code);
+            }
+        }
+        if let Some(energy) = state.energy_kwh {
+            self.energy_start_kwh.get_or_insert(energy);
+            self.energy_end_kwh = Some(energy);
+        }
+        for (tray, reading) in filament::tray_readings(print) {
+            self.trays_at_start
+                .entry(tray.clone())
+                .or_insert_with(|| reading.clone());
+            self.trays_at_end.insert(tray, reading);
+        }
+        if let Some(tray) = filament::active_tray_label(print)
+            && !self.trays_fed.contains(&tray)
+        {
+            self.trays_fed.push(tray);
+        }
+        if self
+            .telemetry
+            .last()
+            .is_none_or(|last| (now - last.at).num_seconds() >= TELEMETRY_INTERVAL_SECS)
+        {
+            self.telemetry.push(TelemetrySample::of(state, now));
+        }
+    }
+
+    /// Folds the final state into the record, always keeping it as the last sample
+    fn finish(&mut self, state: &MergedState, now: DateTime<Local>) {
+        self.absorb(state, now);
+        if self.telemetry.last().is_none_or(|last| last.at != now) {
+            self.telemetry.push(TelemetrySample::of(state, now));
+        }
+    }
+}
+
+/// Change in a printer's print job between two merged states
+#[derive(Debug, Clone, PartialEq)]
+pub enum Transition {
+    Started,
+    Paused,
+    Resumed,
+    Completed,
+    Failed {
+        reason: Option<String>,
+    },
+    /// The printer moved on to another layer of the running job
+    LayerChanged {
+        layer: u32,
+        total: Option<u32>,
+    },
+}
+
+/// Follows the merged state of one printer and reports the transitions between updates.
+/// The first state seen is only the starting point, so connecting to a busy printer does
+/// not report a start.
+#[derive(Debug, Default)]
+pub struct PrintStateMachine {
+    state: Option<PrintState>,
+    layer: Option<u32>,
+}
+
+impl PrintStateMachine {
+    pub fn observe(&mut self, state: &MergedState) -> Vec<Transition> {
+        let status = state.status();
+        let layer = state.print.layer_num;
+        // A state the printer did not name tells nothing about the job
+        if let PrintState::Unknown(_) = status.state {
+            return Vec::new();
+        }
+        let Some(previous) = self.state.replace(status.state.clone()) else {
+            self.layer = layer;
+            return Vec::new();
+        };
+
+        let was_active = matches!(previous, PrintState::Printing | PrintState::Paused);
+        let mut transitions = Vec::new();
+        match status.state {
+            _ if status.state == previous => {}
+            PrintState::Printing | PrintState::Paused if !was_active => {
+                transitions.push(Transition::Started);
+                if status.state == PrintState::Paused {
+                    transitions.push(Transition::Paused);
+                }
+            }
+            PrintState::Paused => transitions.push(Transition::Paused),
+            PrintState::Printing => transitions.push(Transition::Resumed),
+            PrintState::Finished if was_active => transitions.push(Transition::Completed),
+            PrintState::Failed if was_active => transitions.push(Transition::Failed {
+                reason: status.fail_reason,
+            }),
+            _ => {}
+        }
+
+        if transitions.first() == Some(&Transition::Started) {
+            // The layer still shows the previous job until the new one reports its own
+            self.layer = layer;
+        } else if matches!(status.state, PrintState::Printing | PrintState::Paused)
+            && let Some(current) = layer
+            && self.layer.is_some_and(|seen| seen != current)
+        {
+            transitions.push(Transition::LayerChanged {
+                layer: current,
+                total: state.print.total_layer_num,
+            });
+        }
+        if layer.is_some() {
+            self.layer = layer;
+        }
+
+        transitions
+    }
+}
+
+/// When a job started, kept on disk so a job joined after a restart keeps its real start
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JobStart {
+    pub name: Option<String>,
+    pub started_at: DateTime<Local>,
+}
+
+impl JobStart {
+    /// Whether this may be the start of the job named `name`; unnamed starts match any job
+    pub fn matches(&self, name: Option<&str>) -> bool {
+        match (self.name.as_deref(), name) {
+            (Some(saved), Some(name)) => saved == name,
+            _ => true,
+        }
+    }
+}
+
+/// Saves `start` as the start of the running job on `printer`, replacing the previous one
+pub fn remember_start(path: &Path, printer: &str, start: &JobStart) -> std::io::Result<()> {
+    let mut starts = read_starts(path);
+    starts.insert(printer.to_string(), start.clone());
+    if let Some(parent) = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+    {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, serde_json::to_string_pretty(&starts)?)
+}
+
+/// Start of the last job [`remember_start`] saved for `printer`
+pub fn recall_start(path: &Path, printer: &str) -> Option<JobStart> {
+    read_starts(path).remove(printer)
+}
+
+fn read_starts(path: &Path) -> BTreeMap<String, JobStart> {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or_default()
+}
+
+/// Follows the merged state and emits a [`JobRecord`] when a print finishes or fails
+#[derive(Debug, Default)]
+pub struct JobTracker {
+    active: Option<JobRecord>,
+    /// The active job was seen to start, or its start was restored
+    start_known: bool,
+    machine: PrintStateMachine,
+    transitions: Vec<Transition>,
+}
+
+impl JobTracker {
+    /// The job in progress, if any
+    pub fn active(&self) -> Option<&JobRecord> {
+        self.active.as_ref()
+    }
+
+    /// Start of the active job, unless monitoring joined it while it was running
+    pub fn start(&self) -> Option<JobStart> {
+        let job = self.active.as_ref().filter(|_| self.start_known)?;
+        Some(JobStart {
+            name: job.name.clone(),
+            started_at: job.started_at,
+        })
+    }
+
+    /// Takes over the start time of a job that was already running when monitoring began.
+    /// Ignored when the start is known already or belongs to another job.
+    pub fn restore_start(&mut self, start: &JobStart) -> bool {
+        let Some(job) = self.active.as_mut().filter(|_| !self.start_known) else {
+            return false;
+        };
+        if !start.matches(job.name.as_deref()) || start.started_at > job.started_at {
+            return false;
+        }
+        job.started_at = start.started_at;
+        job.start_progress = 0;
+        self.start_known = true;
+        true
+    }
+
+    /// Transitions seen by the last call to [`JobTracker::observe`]
+    pub fn transitions(&self) -> &[Transition] {
+        &self.transitions
+    }
+
+    pub fn observe(&mut self, state: &MergedState, now: DateTime<Local>) -> Option<JobRecord> {
+        self.transitions = self.machine.observe(state);
+        let status = state.status();
+        match status.state {
+            PrintState::Printing | PrintState::Paused => {}
+            PrintState::Finished | PrintState::Failed => {
+                let ended = self.transitions.iter().any(|transition| {
+                    matches!(
+                        transition,
+                        Transition::Completed | Transition::Failed { .. }
+                    )
+                });
+                if !ended {
+                    return None;
+                }
+                let mut job = self.active.take()?;
+                job.finish(state, now);
+                return Some(job);
+            }
+            // Back to idle without finishing, e.g. the job was cancelled from the screen
+            PrintState::Idle | PrintState::Unknown(_) => {
+                self.active = None;
+                return None;
+            }
+        }
+
+        if self.active.is_none() {
+            self.start_known = self.transitions.first() == Some(&Transition::Started);
+        }
+        let job = self.active.get_or_insert_with(|| JobRecord {
+            name: None,
+            started_at: now,
+            ended_at: now,
+            final_state: status.state.clone(),
+            start_progress: status.progress.unwrap_or(0),
+            final_progress: None,
+            total_layers: None,
+            last_layer: None,
+            nozzle: TemperatureStats::default(),
+            bed: TemperatureStats::default(),
+            fail_reason: None,
+            pauses: 0,
+            hms_codes: Vec::new(),
+            trays_at_start: BTreeMap::new(),
+            trays_at_end: BTreeMap::new(),
+            trays_fed: Vec::new(),
+            energy_start_kwh: None,
+            energy_end_kwh: None,
+            cost: None,
+            plate: None,
+            telemetry: Vec::new(),
+        });
+        job.pauses += self
+            .transitions
+            .iter()
+            .filter(|transition| **transition == Transition::Paused)
+            .count() as u32;
+        job.absorb(state, now);
+
+        None
+    }
+}
+
+/// `stg_cur` value while the X1 inspects the first layer with its lidar
+pub const STAGE_FIRST_LAYER_INSPECTION: i64 = 10;
+/// `stg_cur` values while the AMS unloads, changes and loads filament
+pub const STAGE_CHANGING_FILAMENT: i64 = 4;
+pub const STAGE_FILAMENT_UNLOADING: i64 = 22;
+pub const STAGE_FILAMENT_LOADING: i64 = 24;
+/// `stg_cur` while paused by an `M400 U1` in the G-code, which slicers insert where a
+/// filament has to be swapped by hand
+pub const STAGE_M400_PAUSE: i64 = 5;
+pub const STAGE_FILAMENT_RUNOUT: i64 = 6;
+/// `stg_cur` values of the printer pausing itself
+pub const STAGE_USER_PAUSE: i64 = 16;
+pub const STAGE_FRONT_COVER_PAUSE: i64 = 17;
+pub const STAGE_NOZZLE_TEMPERATURE_PAUSE: i64 = 20;
+pub const STAGE_BED_TEMPERATURE_PAUSE: i64 = 21;
+/// HMS module of the camera and lidar, which report first-layer defects and failures such
+/// as spaghetti
+const CAMERA_MODULE: &str = "0C00";
+
+/// Current print stage (`stg_cur`), e.g. [`STAGE_FIRST_LAYER_INSPECTION`]
+pub fn print_stage(state: &MergedState) -> Option<i64> {
+    stage(&state.print)
+}
+
+fn stage(print: &PrintInfo) -> Option<i64> {
+    print.extra.get("stg_cur").and_then(Value::as_i64)
+}
+
+/// A filament change of the running job, from the print stage and AMS slots
+#[derive(Debug, Clone, PartialEq)]
+pub enum FilamentChange {
+    /// The AMS is swapping spools on its own
+    Switching { from: Option<u32>, to: Option<u32> },
+    /// The print is paused until someone swaps the spool in `slot`
+    WaitingForSwap { slot: Option<u32>, runout: bool },
+}
+
+impl FilamentChange {
+    /// `Changing filament, A3 → A1` or `Paused for filament change, External`
+    pub fn describe(&self) -> String {
+        let label = |slot: &Option<u32>| {
+            slot.and_then(filament::slot_label)
+                .unwrap_or_else(|| "?".to_string())
+        };
+        match self {
+            FilamentChange::Switching { from, to } => {
+                format!("Changing filament, {} → {}", label(from), label(to))
+            }
+            FilamentChange::WaitingForSwap {
+                slot,
+                runout: false,
+            } => format!("Paused for filament change, {}", label(slot)),
+            FilamentChange::WaitingForSwap { slot, runout: true } => {
+                format!("Paused, filament ran out in {}", label(slot))
+            }
+        }
+    }
+}
+
+/// The filament change the printer is in, if any
+pub fn filament_change(state: &MergedState) -> Option<FilamentChange> {
+    let status = state.status();
+    let from = state.print.active_slot();
+    match print_stage(state)? {
+        STAGE_CHANGING_FILAMENT | STAGE_FILAMENT_UNLOADING | STAGE_FILAMENT_LOADING
+            if matches!(status.state, PrintState::Printing | PrintState::Paused) =>
+        {
+            Some(FilamentChange::Switching {
+                from,
+                to: state.print.target_slot(),
+            })
+        }
+        stage @ (STAGE_M400_PAUSE | STAGE_FILAMENT_RUNOUT)
+            if status.state == PrintState::Paused =>
+        {
+            Some(FilamentChange::WaitingForSwap {
+                slot: from,
+                runout: stage == STAGE_FILAMENT_RUNOUT,
+            })
+        }
+        _ => None,
+    }
+}
+
+/// HMS codes and the print error reported by the camera and lidar
+pub fn camera_codes(state: &MergedState) -> Vec<String> {
+    print_camera_codes(&state.print)
+}
+
+fn print_camera_codes(print: &PrintInfo) -> Vec<String> {
+    let print_error = print_error_code(print.extra.get("print_error"));
+    hms_codes(print.extra.get("hms"))
+        .into_iter()
+        .chain(print_error)
+        .filter(|code| code.starts_with(CAMERA_MODULE))
+        .collect()
+}
+
+/// Why a print is paused, as far as the stage, errors and HMS codes tell
+#[derive(Debug, Clone, PartialEq)]
+pub enum PauseReason {
+    User,
+    /// An `M400` pause in the G-code, usually a manual filament change
+    Gcode,
+    FilamentRunout,
+    FirstLayerDefect,
+    /// The camera spotted a failure such as spaghetti
+    FailureDetected,
+    FrontCover,
+    NozzleTemperature,
+    BedTemperature,
+    /// A serious or fatal HMS error
+    Hms(String),
+}
+
+impl PauseReason {
+    pub fn describe(&self) -> String {
+        match self {
+            PauseReason::User => "paused by user".to_string(),
+            PauseReason::Gcode => "pause in G-code".to_string(),
+            PauseReason::FilamentRunout => "filament ran out".to_string(),
+            PauseReason::FirstLayerDefect => "first layer defect".to_string(),
+            PauseReason::FailureDetected => "camera detected a print failure".to_string(),
+            PauseReason::FrontCover => "front cover came off".to_string(),
+            PauseReason::NozzleTemperature => "nozzle temperature fault".to_string(),
+            PauseReason::BedTemperature => "bed temperature fault".to_string(),
+            PauseReason::Hms(code) => format!("HMS {code}"),
+        }
+    }
+}
+
+/// Cause of the pause of a paused print. Camera detections count as first-layer defects
+/// while inspecting or on the first layer. Only meaningful while the print is paused.
+pub fn pause_reason(print: &PrintInfo) -> Option<PauseReason> {
+    let stage = stage(print);
+    if !print_camera_codes(print).is_empty() {
+        let first_layer = stage == Some(STAGE_FIRST_LAYER_INSPECTION)
+            || print.layer_num.is_some_and(|layer| layer <= 1);
+        return Some(if first_layer {
+            PauseReason::FirstLayerDefect
+        } else {
+            PauseReason::FailureDetected
+        });
+    }
+    match stage {
+        Some(STAGE_FILAMENT_RUNOUT) => return Some(PauseReason::FilamentRunout),
+        Some(STAGE_FRONT_COVER_PAUSE) => return Some(PauseReason::FrontCover),
+        Some(STAGE_NOZZLE_TEMPERATURE_PAUSE) => return Some(PauseReason::NozzleTemperature),
+        Some(STAGE_BED_TEMPERATURE_PAUSE) => return Some(PauseReason::BedTemperature),
+        _ => {}
+    }
+    if let Some(entry) = hms_entries(print.extra.get("hms"))
+        .into_iter()
+        .find(|entry| {
+            matches!(
+                entry.severity,
+                Some(HmsSeverity::Serious | HmsSeverity::Fatal)
+            )
+        })
+    {
+        return Some(PauseReason::Hms(entry.code));
+    }
+    match stage? {
+        STAGE_USER_PAUSE => Some(PauseReason::User),
+        STAGE_M400_PAUSE => Some(PauseReason::Gcode),
+        _ => None,
+    }
+}
+
+/// Outcome of the X1's first-layer inspection
+#[derive(Debug, Clone, PartialEq)]
+pub enum InspectionResult {
+    Passed,
+    /// Camera HMS codes or print errors reported during the inspection
+    DefectDetected {
+        codes: Vec<String>,
+    },
+}
+
+/// Follows the print stage and reports the first-layer inspection result once the
+/// inspection is over. Camera HMS codes and print errors raised while inspecting, or as the
+/// stage ends, count as detected defects.
+#[derive(Debug, Default)]
+pub struct FirstLayerInspection {
+    inspecting: bool,
+    codes: Vec<String>,
+}
+
+impl FirstLayerInspection {
+    pub fn observe(&mut self, state: &MergedState) -> Option<InspectionResult> {
+        let stage = print_stage(state)?;
+        if !self.inspecting && stage != STAGE_FIRST_LAYER_INSPECTION {
+            return None;
+        }
+        self.inspecting = true;
+
+        for code in camera_codes(state) {
+            if !self.codes.contains(&code) {
+                self.codes.push(code);
+            }
+        }
+        if stage == STAGE_FIRST_LAYER_INSPECTION {
+            return None;
+        }
+
+        self.inspecting = false;
+        let codes = std::mem::take(&mut self.codes);
+        Some(if codes.is_empty() {
+            InspectionResult::Passed
+        } else {
+            InspectionResult::DefectDetected { codes }
+        })
+    }
+}
+
+/// Formats a non-zero `print_error` number as a `0C00_8001` style code
+pub fn print_error_code(print_error: Option<&Value>) -> Option<String> {
+    let code = print_error?.as_u64().filter(|code| *code != 0)?;
+    Some(format!(
+        "{:04X}_{:04X}",
+        (code >> 16) & 0xFFFF,
+        code & 0xFFFF
+    ))
+}
+
+/// One entry of the `hms` array
+#[derive(Debug, Clone, PartialEq)]
+pub struct HmsEntry {
+    /// `0300_0100_0001_0007` style code
+    pub code: String,
+    pub severity: Option<HmsSeverity>,
+}
+
+/// Formats the `hms` array (`[{"attr": .., "code": ..}]`) as `0300_0100_0001_0007` style codes
+pub fn hms_codes(hms: Option<&Value>) -> Vec<String> {
+    hms_entries(hms)
+        .into_iter()
+        .map(|entry| entry.code)
+        .collect()
+}
+
+/// Codes and severities of the `hms` array; malformed entries are skipped
+pub fn hms_entries(hms: Option<&Value>) -> Vec<HmsEntry> {
+    let Some(Value::Array(entries)) = hms else {
+        return Vec::new();
+    };
+
+    entries
+        .iter()
+        .filter_map(|entry| {
+            let attr = entry.get("attr")?.as_u64()?;
+            let code = entry.get("code")?.as_u64()?;
+            Some(HmsEntry {
+                code: format!(
+                    "{:04X}_{:04X}_{:04X}_{:04X}",
+                    (attr >> 16) & 0xFFFF,
+                    attr & 0xFFFF,
+                    (code >> 16) & 0xFFFF,
+                    code & 0xFFFF
+                ),
+                severity: HmsSeverity::from_level((code >> 16) & 0xFFFF),
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,607 @@
+use super::*;
+use crate::messages::DeviceMessage;
+use chrono::{Duration, TimeZone};
+
+fn at(minutes: i64) -> DateTime<Local> {
+    Local.with_ymd_and_hms(2025, 8, 1, 9, 0, 0).unwrap() + Duration::minutes(minutes)
+}
+
+fn update(state: &mut MergedState, payload: &str) {
+    state.update(&DeviceMessage::parse(payload).unwrap());
+}
+
+#[test]
+fn test_tracks_job_until_finished() {
+    let mut tracker = JobTracker::default();
+    let mut state = MergedState::default();
+
+    update(
+        &mut state,
+        r#"{"print": {"command": "push_status", "gcode_state": "IDLE", "nozzle_temper": 25.0}}"#,
+    );
+    assert!(tracker.observe(&state, at(0)).is_none());
+    assert!(tracker.active.is_none());
+
+    update(
+        &mut state,
+        r#"{"print": {"command": "push_status", "gcode_state": "RUNNING", "subtask_name": "benchy", "percent": 0, "nozzle_temper": 220.0, "bed_temper": 60.0, "total_layer_num": 120}}"#,
+    );
+    assert!(tracker.observe(&state, at(1)).is_none());
+
+    update(
+        &mut state,
+        r#"{"print": {"command": "push_status", "percent": 50, "nozzle_temper": 230.0, "layer_num": 60, "hms": [{"attr": 50331904, "code": 65543}]}}"#,
+    );
+    assert!(tracker.observe(&state, at(31)).is_none());
+
+    update(
+        &mut state,
+        r#"{"print": {"command": "push_status", "gcode_state": "FINISH", "percent": 100, "layer_num": 120}}"#,
+    );
+    let job = tracker.observe(&state, at(61)).unwrap();
+
+    assert_eq!(job.name.as_deref(), Some("benchy"));
+    assert_eq!(job.final_state, PrintState::Finished);
+    assert_eq!(job.elapsed_secs(), 3600);
+    assert_eq!(job.start_progress, 0);
+    assert_eq!(job.final_progress, Some(100));
+    assert_eq!(job.total_layers, Some(120));
+    assert_eq!(job.last_layer, Some(120));
+    assert_eq!(job.nozzle.peak, 230.0);
+    // The merged state is sampled once per message, so the last reading counts twice
+    let average = job.nozzle.average().unwrap();
+    assert!((average - 226.67).abs() < 0.01);
+    assert_eq!(job.bed.peak, 60.0);
+    assert_eq!(job.hms_codes, vec!["0300_0100_0001_0007"]);
+
+    // The finished state is reported once
+    assert!(tracker.observe(&state, at(62)).is_none());
+}
+
+#[test]
+fn test_telemetry_sampled_once_a_minute_and_at_the_end() {
+    let mut tracker = JobTracker::default();
+    let mut state = MergedState::default();
+
+    update(
+        &mut state,
+        r#"{"print": {"command": "push_status", "gcode_state": "RUNNING", "percent": 0, "nozzle_temper": 220.0}}"#,
+    );
+    tracker.observe(&state, at(0));
+    for (seconds, percent) in [(20, 1), (40, 2), (60, 3), (90, 4)] {
+        update(
+            &mut state,
+            &format!(r#"{{"print": {{"command": "push_status", "percent": {percent}}}}}"#),
+        );
+        tracker.observe(&state, at(0) + Duration::seconds(seconds));
+    }
+    update(
+        &mut state,
+        r#"{"print": {"command": "push_status", "gcode_state": "FINISH", "percent": 100}}"#,
+    );
+    let job = tracker
+        .observe(&state, at(0) + Duration::seconds(100))
+        .unwrap();
+
+    let samples: Vec<_> = job
+        .telemetry
+        .iter()
+        .map(|sample| ((sample.at - at(0)).num_seconds(), sample.progress))
+        .collect();
+    assert_eq!(samples, vec![(0, Some(0)), (60, Some(3)), (100, Some(100))]);
+    assert_eq!(job.telemetry[0].nozzle, Some(220.0));
+}
+
+#[test]
+fn test_failed_job_keeps_reason_and_cancel_drops_job() {
+    let mut tracker = JobTracker::default();
+    let mut state = MergedState::default();
+
+    update(
+        &mut state,
+        r#"{"print": {"command": "push_status", "gcode_state": "RUNNING", "percent": 30}}"#,
+    );
+    tracker.observe(&state, at(0));
+    update(
+        &mut state,
+        r#"{"print": {"command": "push_status", "gcode_state": "FAILED", "fail_reason": "spaghetti"}}"#,
+    );
+    let job = tracker.observe(&state, at(5)).unwrap();
+    assert_eq!(job.final_state, PrintState::Failed);
+    assert_eq!(job.fail_reason.as_deref(), Some("spaghetti"));
+    assert_eq!(job.start_progress, 30);
+
+    let mut state = MergedState::default();
+    update(
+        &mut state,
+        r#"{"print": {"command": "push_status", "gcode_state": "RUNNING"}}"#,
+    );
+    tracker.observe(&state, at(10));
+    update(
+        &mut state,
+        r#"{"print": {"command": "push_status", "gcode_state": "IDLE"}}"#,
+    );
+    assert!(tracker.observe(&state, at(11)).is_none());
+    assert!(tracker.active.is_none());
+}
+
+#[test]
+fn test_hms_codes_ignore_malformed_entries() {
+    let hms = serde_json::json!([{"attr": 0, "code": 1}, {"attr": "x"}, 5]);
+    assert_eq!(hms_codes(Some(&hms)), vec!["0000_0000_0000_0001"]);
+    assert!(hms_codes(None).is_empty());
+}
+
+#[test]
+fn test_hms_severity() {
+    let hms = serde_json::json!([
+        {"attr": 50331904, "code": 65543},
+        {"attr": 201326592, "code": 262145},
+        {"attr": 0, "code": 1}
+    ]);
+    let severities: Vec<_> = hms_entries(Some(&hms))
+        .into_iter()
+        .map(|entry| (entry.code, entry.severity))
+        .collect();
+    assert_eq!(
+        severities,
+        vec![
+            ("0300_0100_0001_0007".to_string(), Some(HmsSeverity::Fatal)),
+            ("0C00_0000_0004_0001".to_string(), Some(HmsSeverity::Info)),
+            ("0000_0000_0000_0001".to_string(), None),
+        ]
+    );
+}
+
+#[test]
+fn test_counts_pauses_and_progress_rate() {
+    let mut tracker = JobTracker::default();
+    let mut state = MergedState::default();
+
+    for (minute, payload) in [
+        (
+            0,
+            r#"{"print": {"command": "push_status", "gcode_state": "RUNNING", "percent": 10}}"#,
+        ),
+        (
+            10,
+            r#"{"print": {"command": "push_status", "gcode_state": "PAUSE"}}"#,
+        ),
+        (
+            11,
+            r#"{"print": {"command": "push_status", "gcode_state": "PAUSE"}}"#,
+        ),
+        (
+            20,
+            r#"{"print": {"command": "push_status", "gcode_state": "RUNNING", "percent": 40}}"#,
+        ),
+        (
+            25,
+            r#"{"print": {"command": "push_status", "gcode_state": "PAUSE"}}"#,
+        ),
+        (
+            30,
+            r#"{"print": {"command": "push_status", "gcode_state": "RUNNING", "percent": 60}}"#,
+        ),
+    ] {
+        update(&mut state, payload);
+        assert!(tracker.observe(&state, at(minute)).is_none());
+    }
+
+    update(
+        &mut state,
+        r#"{"print": {"command": "push_status", "gcode_state": "FINISH", "percent": 100}}"#,
+    );
+    let job = tracker.observe(&state, at(60)).unwrap();
+
+    assert_eq!(job.pauses, 2);
+    assert_eq!(job.progress_per_hour(), Some(90.0));
+}
+
+#[test]
+fn test_filament_usage_from_first_and_last_tray_reading() {
+    let mut tracker = JobTracker::default();
+    let mut state = MergedState::default();
+
+    update(
+        &mut state,
+        r#"{"print": {"command": "push_status", "gcode_state": "RUNNING", "percent": 0}}"#,
+    );
+    tracker.observe(&state, at(0));
+    // The AMS report often arrives a few messages after the job starts
+    update(
+        &mut state,
+        r#"{"print": {"ams": {"ams": [{"id": "0", "tray": [{"id": "1", "remain": 90, "tray_type": "PETG", "tray_weight": "1000"}]}]}}}"#,
+    );
+    tracker.observe(&state, at(1));
+    update(
+        &mut state,
+        r#"{"print": {"ams": {"ams": [{"id": "0", "tray": [{"id": "1", "remain": 86, "tray_type": "PETG", "tray_weight": "1000"}]}]}}}"#,
+    );
+    tracker.observe(&state, at(30));
+    update(
+        &mut state,
+        r#"{"print": {"command": "push_status", "gcode_state": "FINISH", "percent": 100}}"#,
+    );
+    let job = tracker.observe(&state, at(60)).unwrap();
+
+    let usage = job.filament_usage();
+    assert_eq!(usage.len(), 1);
+    assert_eq!(usage[0].tray, "A2");
+    assert_eq!(usage[0].material.as_deref(), Some("PETG"));
+    assert_eq!(usage[0].grams, 40.0);
+}
+
+#[test]
+fn test_filament_usage_from_plate_without_tray_readings() {
+    let mut tracker = JobTracker::default();
+    let mut state = MergedState::default();
+
+    // A spool without an RFID tag reports no remaining percentage
+    update(
+        &mut state,
+        r#"{"print": {"command": "push_status", "gcode_state": "RUNNING", "percent": 0, "ams": {"tray_now": "2", "ams": [{"id": "0", "tray": [{"id": "2", "remain": -1, "tray_type": "PLA"}]}]}}}"#,
+    );
+    tracker.observe(&state, at(0));
+    update(
+        &mut state,
+        r#"{"print": {"command": "push_status", "gcode_state": "FINISH", "percent": 100}}"#,
+    );
+    let mut job = tracker.observe(&state, at(60)).unwrap();
+    assert_eq!(job.trays_fed, vec!["A3"]);
+    assert!(job.filament_usage().is_empty());
+
+    job.plate = Some(PlateInfo {
+        index: 1,
+        estimated_secs: None,
+        weight_g: Some(12.5),
+        filaments: vec![crate::project::FilamentInfo {
+            id: 1,
+            material: Some("PLA".to_string()),
+            color: None,
+            used_m: None,
+            used_g: Some(12.5),
+        }],
+        objects: Vec::new(),
+        nozzle_diameter: None,
+        thumbnail: None,
+    });
+    let usage = job.filament_usage();
+    assert_eq!(usage.len(), 1);
+    assert_eq!(usage[0].tray, "A3");
+    assert_eq!(usage[0].grams, 12.5);
+}
+
+#[test]
+fn test_estimate_cost_from_filament_and_duration() {
+    let mut tracker = JobTracker::default();
+    let mut state = MergedState::default();
+
+    for (minute, payload) in [
+        (
+            0,
+            r#"{"print": {"gcode_state": "RUNNING", "ams": {"ams": [{"id": "0", "tray": [{"id": "0", "remain": 50, "tray_weight": "1000"}]}]}}}"#,
+        ),
+        (
+            120,
+            r#"{"print": {"gcode_state": "RUNNING", "ams": {"ams": [{"id": "0", "tray": [{"id": "0", "remain": 45, "tray_weight": "1000"}]}]}}}"#,
+        ),
+    ] {
+        update(&mut state, payload);
+        tracker.observe(&state, at(minute));
+    }
+    update(&mut state, r#"{"print": {"gcode_state": "FINISH"}}"#);
+    let job = tracker.observe(&state, at(120)).unwrap();
+
+    let settings = CostSettings {
+        filament_per_kg: Some(20.0),
+        electricity_per_kwh: Some(0.30),
+        printer_watts: 150.0,
+        currency: Some("EUR".to_string()),
+    };
+    let cost = job.estimate_cost(&settings).unwrap();
+    // 50 g at 20/kg, 0.3 kWh at 0.30/kWh
+    assert_eq!(cost.filament, Some(1.0));
+    assert!((cost.electricity.unwrap() - 0.09).abs() < 1e-9);
+    assert_eq!(cost.format_amount(cost.total()), "1.09 EUR");
+
+    // Without AMS data only the electricity part is known
+    let no_filament = JobRecord {
+        trays_at_end: BTreeMap::new(),
+        ..job.clone()
+    };
+    let cost = no_filament.estimate_cost(&settings).unwrap();
+    assert_eq!(cost.filament, None);
+
+    assert!(job.estimate_cost(&CostSettings::default()).is_none());
+}
+
+#[test]
+fn test_energy_from_smart_plug_replaces_power_estimate() {
+    let mut tracker = JobTracker::default();
+    let mut state = MergedState::default();
+
+    update(&mut state, r#"{"print": {"gcode_state": "RUNNING"}}"#);
+    state.energy_kwh = Some(1.5);
+    tracker.observe(&state, at(0));
+    state.energy_kwh = Some(2.0);
+    tracker.observe(&state, at(60));
+    update(&mut state, r#"{"print": {"gcode_state": "FINISH"}}"#);
+    state.energy_kwh = Some(2.25);
+    let job = tracker.observe(&state, at(120)).unwrap();
+
+    assert_eq!(job.energy_kwh(), Some(0.75));
+
+    let settings = CostSettings {
+        electricity_per_kwh: Some(0.4),
+        ..CostSettings::default()
+    };
+    let cost = job.estimate_cost(&settings).unwrap();
+    assert!((cost.electricity.unwrap() - 0.3).abs() < 1e-9);
+}
+
+#[test]
+fn test_state_machine_transitions() {
+    let mut machine = PrintStateMachine::default();
+    let mut state = MergedState::default();
+    let mut step = |payload: &str| {
+        update(&mut state, payload);
+        machine.observe(&state)
+    };
+
+    // Connecting mid-print is only the starting point
+    assert!(
+        step(r#"{"print": {"gcode_state": "RUNNING", "layer_num": 40, "total_layer_num": 120}}"#)
+            .is_empty()
+    );
+    assert_eq!(
+        step(r#"{"print": {"layer_num": 41}}"#),
+        vec![Transition::LayerChanged {
+            layer: 41,
+            total: Some(120)
+        }]
+    );
+    assert!(step(r#"{"print": {"percent": 50}}"#).is_empty());
+    assert_eq!(
+        step(r#"{"print": {"gcode_state": "PAUSE"}}"#),
+        vec![Transition::Paused]
+    );
+    assert_eq!(
+        step(r#"{"print": {"gcode_state": "RUNNING"}}"#),
+        vec![Transition::Resumed]
+    );
+    // Unnamed states are skipped rather than ending the job
+    assert!(step(r#"{"print": {"gcode_state": "OFFLINE"}}"#).is_empty());
+    assert_eq!(
+        step(r#"{"print": {"gcode_state": "FAILED", "fail_reason": "spaghetti"}}"#),
+        vec![Transition::Failed {
+            reason: Some("spaghetti".to_string())
+        }]
+    );
+    assert!(step(r#"{"print": {"gcode_state": "IDLE"}}"#).is_empty());
+
+    // A new job starts from its own first layer
+    assert_eq!(
+        step(r#"{"print": {"gcode_state": "RUNNING", "layer_num": 0}}"#),
+        vec![Transition::Started]
+    );
+    assert_eq!(
+        step(r#"{"print": {"gcode_state": "FINISH", "layer_num": 120}}"#),
+        vec![Transition::Completed]
+    );
+    assert!(step(r#"{"print": {"gcode_state": "FINISH"}}"#).is_empty());
+}
+
+#[test]
+fn test_tracker_exposes_transitions() {
+    let mut tracker = JobTracker::default();
+    let mut state = MergedState::default();
+
+    update(&mut state, r#"{"print": {"gcode_state": "IDLE"}}"#);
+    tracker.observe(&state, at(0));
+    assert!(tracker.transitions().is_empty());
+
+    update(&mut state, r#"{"print": {"gcode_state": "RUNNING"}}"#);
+    tracker.observe(&state, at(1));
+    assert_eq!(tracker.transitions(), [Transition::Started]);
+
+    update(&mut state, r#"{"print": {"percent": 5}}"#);
+    tracker.observe(&state, at(2));
+    assert!(tracker.transitions().is_empty());
+}
+
+#[test]
+fn test_job_start_survives_restart() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let path = temp_dir.path().join("job_starts.json");
+    assert_eq!(recall_start(&path, "x1c"), None);
+
+    let mut tracker = JobTracker::default();
+    let mut state = MergedState::default();
+    update(&mut state, r#"{"print": {"gcode_state": "IDLE"}}"#);
+    tracker.observe(&state, at(0));
+    update(
+        &mut state,
+        r#"{"print": {"gcode_state": "RUNNING", "subtask_name": "benchy", "percent": 0}}"#,
+    );
+    tracker.observe(&state, at(5));
+    let start = tracker.start().unwrap();
+    assert_eq!(start.started_at, at(5));
+    remember_start(&path, "x1c", &start).unwrap();
+    remember_start(
+        &path,
+        "p1s",
+        &JobStart {
+            name: None,
+            started_at: at(1),
+        },
+    )
+    .unwrap();
+
+    // A new tracker joins the running job; its own first sighting is not the start
+    let mut joined = JobTracker::default();
+    update(&mut state, r#"{"print": {"percent": 30}}"#);
+    joined.observe(&state, at(65));
+    assert_eq!(joined.start(), None);
+    assert_eq!(joined.active().unwrap().start_progress, 30);
+
+    let other = JobStart {
+        name: Some("cube".to_string()),
+        started_at: at(2),
+    };
+    assert!(!joined.restore_start(&other));
+    let saved = recall_start(&path, "x1c").unwrap();
+    assert_eq!(saved, start);
+    assert!(joined.restore_start(&saved));
+    assert!(!joined.restore_start(&other));
+    assert_eq!(joined.start(), Some(start));
+    assert_eq!(joined.active().unwrap().start_progress, 0);
+
+    update(
+        &mut state,
+        r#"{"print": {"gcode_state": "FINISH", "percent": 100}}"#,
+    );
+    assert_eq!(
+        joined.observe(&state, at(125)).unwrap().elapsed_secs(),
+        120 * 60
+    );
+}
+
+#[test]
+fn test_first_layer_inspection_results() {
+    let mut inspection = FirstLayerInspection::default();
+    let mut state = MergedState::default();
+    let mut step = |payload: &str| {
+        update(&mut state, payload);
+        inspection.observe(&state)
+    };
+
+    assert_eq!(
+        step(r#"{"print": {"gcode_state": "RUNNING", "stg_cur": 2}}"#),
+        None
+    );
+    assert_eq!(step(r#"{"print": {"stg_cur": 10}}"#), None);
+    assert_eq!(
+        step(r#"{"print": {"stg_cur": 0}}"#),
+        Some(InspectionResult::Passed)
+    );
+    assert_eq!(step(r#"{"print": {"layer_num": 2}}"#), None);
+
+    // Unrelated HMS codes are not defects; the camera's are, even when reported as the
+    // printer pauses
+    assert_eq!(
+        step(r#"{"print": {"stg_cur": 10, "hms": [{"attr": 50331904, "code": 65543}]}}"#),
+        None
+    );
+    assert_eq!(
+        step(r#"{"print": {"gcode_state": "PAUSE", "stg_cur": 16, "print_error": 201359361}}"#),
+        Some(InspectionResult::DefectDetected {
+            codes: vec!["0C00_8001".to_string()]
+        })
+    );
+}
+
+#[test]
+fn test_filament_change_stages() {
+    let mut state = MergedState::default();
+    update(
+        &mut state,
+        r#"{"print": {"gcode_state": "RUNNING", "stg_cur": 0, "ams": {"tray_now": "2", "tray_tar": "255", "ams": [
+            {"id": "0", "tray": [{"id": "0"}, {"id": "2"}]}
+        ]}}}"#,
+    );
+    assert_eq!(filament_change(&state), None);
+
+    update(
+        &mut state,
+        r#"{"print": {"stg_cur": 22, "ams": {"tray_tar": "0"}}}"#,
+    );
+    let change = filament_change(&state).unwrap();
+    assert_eq!(
+        change,
+        FilamentChange::Switching {
+            from: Some(2),
+            to: Some(0)
+        }
+    );
+    assert_eq!(change.describe(), "Changing filament, A3 → A1");
+
+    // Slicers pause with M400 for a manual swap on the external spool
+    let mut state = MergedState::default();
+    update(
+        &mut state,
+        r#"{"print": {"gcode_state": "PAUSE", "stg_cur": 5, "vt_tray": {"id": "254"}}}"#,
+    );
+    assert_eq!(
+        filament_change(&state).unwrap().describe(),
+        "Paused for filament change, External"
+    );
+    update(&mut state, r#"{"print": {"stg_cur": 6}}"#);
+    assert_eq!(
+        filament_change(&state).unwrap().describe(),
+        "Paused, filament ran out in External"
+    );
+    update(
+        &mut state,
+        r#"{"print": {"gcode_state": "RUNNING", "stg_cur": 0}}"#,
+    );
+    assert_eq!(filament_change(&state), None);
+}
+
+#[test]
+fn test_pause_reasons() {
+    let reason = |payload: &str| {
+        let mut state = MergedState::default();
+        update(&mut state, payload);
+        pause_reason(&state.print)
+    };
+
+    assert_eq!(reason(r#"{"print": {"gcode_state": "PAUSE"}}"#), None);
+    assert_eq!(
+        reason(r#"{"print": {"gcode_state": "PAUSE", "stg_cur": 16}}"#),
+        Some(PauseReason::User)
+    );
+    assert_eq!(
+        reason(r#"{"print": {"gcode_state": "PAUSE", "stg_cur": 5}}"#),
+        Some(PauseReason::Gcode)
+    );
+    assert_eq!(
+        reason(r#"{"print": {"gcode_state": "PAUSE", "stg_cur": 6}}"#),
+        Some(PauseReason::FilamentRunout)
+    );
+    // The camera's codes win over the stage the pause was reported with
+    assert_eq!(
+        reason(
+            r#"{"print": {"gcode_state": "PAUSE", "stg_cur": 16, "layer_num": 1, "print_error": 201359361}}"#
+        ),
+        Some(PauseReason::FirstLayerDefect)
+    );
+    assert_eq!(
+        reason(
+            r#"{"print": {"gcode_state": "PAUSE", "stg_cur": 16, "layer_num": 40, "print_error": 201375748}}"#
+        ),
+        Some(PauseReason::FailureDetected)
+    );
+    // Only serious and fatal HMS codes explain a pause
+    assert_eq!(
+        reason(
+            r#"{"print": {"gcode_state": "PAUSE", "stg_cur": 16, "hms": [{"attr": 50331904, "code": 262145}]}}"#
+        ),
+        Some(PauseReason::User)
+    );
+    let hms = reason(
+        r#"{"print": {"gcode_state": "PAUSE", "hms": [{"attr": 50331904, "code": 131073}]}}"#,
+    )
+    .unwrap();
+    assert_eq!(hms.describe(), "HMS 0300_0100_0002_0001");
+}
+
+#[test]
+fn test_print_error_code() {
+    assert_eq!(
+        print_error_code(Some(&serde_json::json!(50348044))),
+        Some("0300_400C".to_string())
+    );
+    assert_eq!(print_error_code(Some(&serde_json::json!(0))), None);
+    assert_eq!(print_error_code(None), None);
+}
@@ -0,0 +1,41 @@
+//! Monitoring and control of Bambu Lab printers over their LAN MQTT broker. The
+//! `pulseprint-cli` binary is built on these modules; other programs can use them directly.
+
+pub mod alerts;
+pub mod api;
+pub mod calendar;
+pub mod camera;
+pub mod chart;
+pub mod check;
+pub mod config;
+pub mod corpus;
+pub mod daemon;
+pub mod discovery;
+pub mod display;
+#[cfg(feature = "email")]
+pub mod email;
+pub mod events;
+pub mod filament;
+pub mod ftp;
+pub mod graphql;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+pub mod history;
+pub mod jobs;
+pub mod logging;
+pub mod messages;
+pub mod monitor;
+pub mod moonraker;
+pub mod mqtt;
+pub mod plug;
+pub mod project;
+pub mod recording;
+pub mod report;
+pub mod requests;
+pub mod schedule;
+pub mod service;
+pub mod setup;
+pub mod simulator;
+pub mod stats;
+pub mod telemetry;
+pub mod zabbix;
@@ -0,0 +1,226 @@
+use crate::config::{LogRotation, MessageLogContent, MessageLogSettings};
+use crate::mqtt::MessageSource;
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone};
+use rumqttc::Publish;
+use serde::Serialize;
+use serde_json::Value;
+use std::error::Error;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+#[cfg(test)]
+mod tests;
+
+const EXTENSION: &str = "ndjson";
+/// Timestamp in the names of rotated files, which sort in the order they were rotated
+const ROTATED_FORMAT: &str = "%Y%m%d-%H%M%S%.3f";
+
+#[derive(Debug, Error)]
+pub enum LogError {
+    #[error("IO error: {0}")]
+    IoError(#[from] std::io::Error),
+
+    #[error("Failed to serialize logged message: {0}")]
+    SerializeError(#[from] serde_json::Error),
+}
+
+/// One line of the message log
+#[derive(Debug, Serialize)]
+struct LogLine<'a> {
+    at: DateTime<Local>,
+    printer: &'a str,
+    topic: &'a str,
+    payload: Value,
+}
+
+/// Message log of one printer. Messages are appended to `<printer>.ndjson` in the log
+/// directory; rotated files are renamed to `<printer>.<timestamp>.ndjson` next to it and
+/// pruned to the configured retention.
+#[derive(Debug)]
+pub struct MessageLog {
+    settings: MessageLogSettings,
+    directory: PathBuf,
+    printer: String,
+    file: Option<BufWriter<File>>,
+    size: u64,
+    /// When the current file was started, for hourly and daily rotation
+    started_at: DateTime<Local>,
+}
+
+impl MessageLog {
+    /// Log of `printer` in the configured directory, or in `default_dir` when none is set.
+    /// Nothing is created until the first message.
+    pub fn new(settings: &MessageLogSettings, default_dir: &Path, printer: &str) -> Self {
+        Self {
+            settings: settings.clone(),
+            directory: settings
+                .directory
+                .clone()
+                .unwrap_or_else(|| default_dir.to_path_buf()),
+            printer: printer.to_string(),
+            file: None,
+            size: 0,
+            started_at: Local::now(),
+        }
+    }
+
+    /// File currently written to
+    pub fn path(&self) -> PathBuf {
+        self.directory.join(format!("{}.{EXTENSION}", self.printer))
+    }
+
+    /// Appends a message received on `topic`, rotating the file first when it is due
+    pub fn log(
+        &mut self,
+        topic: &str,
+        payload: &[u8],
+        at: DateTime<Local>,
+    ) -> Result<(), LogError> {
+        let raw = || Value::String(String::from_utf8_lossy(payload).into_owned());
+        let payload = match self.settings.content {
+            MessageLogContent::Raw => raw(),
+            MessageLogContent::Parsed => serde_json::from_slice(payload).unwrap_or_else(|_| raw()),
+        };
+        let mut line = serde_json::to_vec(&LogLine {
+            at,
+            printer: &self.printer,
+            topic,
+            payload,
+        })?;
+        line.push(b'\n');
+
+        if self.file.is_none() {
+            self.open()?;
+        }
+        if self.rotation_due(line.len() as u64, at) {
+            self.rotate(at)?;
+        }
+        if self.size == 0 {
+            self.started_at = at;
+        }
+        if let Some(file) = self.file.as_mut() {
+            file.write_all(&line)?;
+            // Flush every line so the log is current for anyone tailing it
+            file.flush()?;
+        }
+        self.size += line.len() as u64;
+        Ok(())
+    }
+
+    /// Opens the current file for appending, carrying on where an earlier run stopped
+    fn open(&mut self) -> Result<(), LogError> {
+        fs::create_dir_all(&self.directory)?;
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.path())?;
+        let metadata = file.metadata()?;
+        self.size = metadata.len();
+        if self.size > 0
+            && let Ok(modified) = metadata.modified()
+        {
+            // Last written in an earlier period means it is due for rotation
+            self.started_at = modified.into();
+        }
+        self.file = Some(BufWriter::new(file));
+        Ok(())
+    }
+
+    fn rotation_due(&self, next_line: u64, at: DateTime<Local>) -> bool {
+        if self.size == 0 {
+            return false;
+        }
+        if self.size + next_line > self.settings.max_size_mb * 1024 * 1024 {
+            return true;
+        }
+        match self.settings.rotate {
+            LogRotation::Size => false,
+            LogRotation::Hourly => {
+                self.started_at.format("%Y%m%d%H").to_string() != at.format("%Y%m%d%H").to_string()
+            }
+            LogRotation::Daily => self.started_at.date_naive() != at.date_naive(),
+        }
+    }
+
+    /// Moves the current file aside, starts a new one and prunes old files
+    fn rotate(&mut self, at: DateTime<Local>) -> Result<(), LogError> {
+        if let Some(mut file) = self.file.take() {
+            file.flush()?;
+        }
+        let rotated = self.directory.join(format!(
+            "{}.{}.{EXTENSION}",
+            self.printer,
+            at.format(ROTATED_FORMAT)
+        ));
+        fs::rename(self.path(), rotated)?;
+        self.open()?;
+        self.prune(at)
+    }
+
+    /// Rotated files of this printer, newest first
+    pub fn rotated_files(&self) -> Result<Vec<(PathBuf, DateTime<Local>)>, LogError> {
+        let prefix = format!("{}.", self.printer);
+        let suffix = format!(".{EXTENSION}");
+        let mut files = Vec::new();
+        for entry in fs::read_dir(&self.directory)? {
+            let entry = entry?;
+            let name = entry.file_name();
+            let Some(rotated_at) = name
+                .to_str()
+                .and_then(|name| name.strip_prefix(&prefix))
+                .and_then(|rest| rest.strip_suffix(&suffix))
+                .and_then(|stamp| NaiveDateTime::parse_from_str(stamp, ROTATED_FORMAT).ok())
+                .and_then(|stamp| Local.from_local_datetime(&stamp).earliest())
+            else {
+                continue;
+            };
+            files.push((entry.path(), rotated_at));
+        }
+        files.sort_by_key(|(_, rotated_at)| std::cmp::Reverse(*rotated_at));
+        Ok(files)
+    }
+
+    /// Deletes rotated files beyond `max_files` and those older than `max_age_days`
+    fn prune(&self, now: DateTime<Local>) -> Result<(), LogError> {
+        let cutoff = self
+            .settings
+            .max_age_days
+            .map(|days| now - chrono::Duration::days(days as i64));
+        for (index, (path, rotated_at)) in self.rotated_files()?.into_iter().enumerate() {
+            let expired = cutoff.is_some_and(|cutoff| rotated_at < cutoff);
+            if index >= self.settings.max_files || expired {
+                fs::remove_file(path)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Wraps a message source and logs every publish it yields. Logging stops with a warning
+/// when the log cannot be written, so a full disk does not end monitoring.
+pub struct LoggedSource<S> {
+    inner: S,
+    log: Option<MessageLog>,
+}
+
+impl<S: MessageSource> LoggedSource<S> {
+    /// Source logging to `log`; without one, publishes are passed on untouched
+    pub fn new(inner: S, log: Option<MessageLog>) -> Self {
+        Self { inner, log }
+    }
+}
+
+impl<S: MessageSource> MessageSource for LoggedSource<S> {
+    async fn next_publish(&mut self) -> Result<Option<Publish>, Box<dyn Error>> {
+        let publish = self.inner.next_publish().await?;
+        if let (Some(publish), Some(log)) = (&publish, self.log.as_mut())
+            && let Err(e) = log.log(&publish.topic, &publish.payload, Local::now())
+        {
+            eprintln!("Message log {} disabled: {e}", log.path().display());
+            self.log = None;
+        }
+        Ok(publish)
+    }
+}
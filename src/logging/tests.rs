@@ -0,0 +1,142 @@
+use super::*;
+use crate::mqtt::ChannelSource;
+use rumqttc::QoS;
+use tempfile::tempdir;
+
+fn at(day: u32, hour: u32) -> DateTime<Local> {
+    Local.with_ymd_and_hms(2025, 8, day, hour, 0, 0).unwrap()
+}
+
+fn settings(directory: &Path) -> MessageLogSettings {
+    MessageLogSettings {
+        directory: Some(directory.to_path_buf()),
+        ..MessageLogSettings::default()
+    }
+}
+
+fn lines(path: &Path) -> Vec<Value> {
+    fs::read_to_string(path)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect()
+}
+
+#[test]
+fn test_log_lines() {
+    let temp_dir = tempdir().unwrap();
+    let mut log = MessageLog::new(&settings(temp_dir.path()), Path::new("unused"), "x1c");
+    assert!(!log.path().exists());
+
+    log.log(
+        "device/01S/report",
+        br#"{"print": {"percent": 5}}"#,
+        at(1, 9),
+    )
+    .unwrap();
+    let logged = lines(&log.path());
+    assert_eq!(logged[0]["printer"], "x1c");
+    assert_eq!(logged[0]["topic"], "device/01S/report");
+    assert_eq!(logged[0]["payload"], r#"{"print": {"percent": 5}}"#);
+    assert!(
+        logged[0]["at"]
+            .as_str()
+            .unwrap()
+            .starts_with("2025-08-01T09:00:00")
+    );
+
+    let parsed = MessageLogSettings {
+        content: MessageLogContent::Parsed,
+        ..settings(temp_dir.path())
+    };
+    let mut log = MessageLog::new(&parsed, Path::new("unused"), "p1s");
+    log.log(
+        "device/01P/report",
+        br#"{"print": {"percent": 5}}"#,
+        at(1, 9),
+    )
+    .unwrap();
+    log.log("device/01P/report", b"not json", at(1, 9)).unwrap();
+    let logged = lines(&log.path());
+    assert_eq!(logged[0]["payload"]["print"]["percent"], 5);
+    assert_eq!(logged[1]["payload"], "not json");
+}
+
+#[test]
+fn test_size_rotation_keeps_max_files() {
+    let temp_dir = tempdir().unwrap();
+    let settings = MessageLogSettings {
+        max_size_mb: 1,
+        max_files: 2,
+        ..settings(temp_dir.path())
+    };
+    let mut log = MessageLog::new(&settings, Path::new("unused"), "x1c");
+    let payload = vec![b'x'; 400 * 1024];
+
+    // Two payloads fit in a megabyte, so every third starts a new file
+    for minute in 0..9 {
+        log.log(
+            "device/01S/report",
+            &payload,
+            at(1, 9) + chrono::Duration::minutes(minute),
+        )
+        .unwrap();
+    }
+    assert_eq!(lines(&log.path()).len(), 1);
+    let rotated = log.rotated_files().unwrap();
+    assert_eq!(rotated.len(), 2);
+    assert_eq!(rotated[0].1, at(1, 9) + chrono::Duration::minutes(8));
+    assert_eq!(rotated[1].1, at(1, 9) + chrono::Duration::minutes(6));
+    assert_eq!(lines(&rotated[0].0).len(), 2);
+}
+
+#[test]
+fn test_daily_rotation_drops_expired_files() {
+    let temp_dir = tempdir().unwrap();
+    let settings = MessageLogSettings {
+        rotate: LogRotation::Daily,
+        max_age_days: Some(2),
+        ..settings(temp_dir.path())
+    };
+    let mut log = MessageLog::new(&settings, Path::new("unused"), "x1c");
+    log.log("device/01S/report", b"{}", at(1, 9)).unwrap();
+    log.log("device/01S/report", b"{}", at(1, 23)).unwrap();
+    assert!(log.rotated_files().unwrap().is_empty());
+
+    for day in 2..=5 {
+        log.log("device/01S/report", b"{}", at(day, 9)).unwrap();
+    }
+    // Rotated on the 2nd to the 5th; those of the 2nd were older than two days by the 5th
+    let rotated: Vec<_> = log
+        .rotated_files()
+        .unwrap()
+        .into_iter()
+        .map(|(_, rotated_at)| rotated_at)
+        .collect();
+    assert_eq!(rotated, vec![at(5, 9), at(4, 9), at(3, 9)]);
+    assert_eq!(lines(&log.path()).len(), 1);
+}
+
+#[tokio::test]
+async fn test_logged_source_passes_publishes_on() {
+    let temp_dir = tempdir().unwrap();
+    let log = MessageLog::new(&settings(temp_dir.path()), Path::new("unused"), "x1c");
+    let path = log.path();
+    let (sender, source) = ChannelSource::new(4);
+    let mut source = LoggedSource::new(source, Some(log));
+
+    sender
+        .send(Ok(Publish::new(
+            "device/01S/report",
+            QoS::AtMostOnce,
+            "first",
+        )))
+        .await
+        .unwrap();
+    drop(sender);
+
+    let publish = source.next_publish().await.unwrap().unwrap();
+    assert_eq!(&publish.payload[..], b"first");
+    assert!(source.next_publish().await.unwrap().is_none());
+    assert_eq!(lines(&path)[0]["payload"], "first");
+}
@@ -1,8 +1,13 @@
-use clap::{Parser, Subcommand};
+use clap::{Args, CommandFactory, Parser, Subcommand};
+use display::Icon;
+use pulseprint_cli::{
+    alerts, calendar, camera, chart, check, config, corpus, daemon, discovery, display, events,
+    filament, ftp, history, jobs, logging, messages, mqtt, plug, project, recording, report,
+    requests, schedule, service, setup, simulator, stats, telemetry, zabbix,
+};
 
-mod config;
-mod messages;
-mod mqtt;
+#[cfg(test)]
+mod tests;
 
 /// PulsePrint-CLI: A tool for monitoring Bambu Labs printers via MQTT
 #[derive(Parser)]
@@ -10,12 +15,247 @@ mod mqtt;
 struct Cli {
     #[command(subcommand)]
     command: Option<Commands>,
+
+    /// Use plain ASCII labels instead of emoji
+    #[arg(long, global = true)]
+    no_emoji: bool,
+
+    /// Disable colored output (also honours NO_COLOR)
+    #[arg(long, global = true)]
+    no_color: bool,
+
+    /// Use the printers and settings of this profile (also PULSEPRINT_PROFILE)
+    #[arg(long, global = true, value_name = "NAME")]
+    profile: Option<String>,
+
+    /// Read and write this config file instead of the default one (also PULSEPRINT_CONFIG)
+    #[arg(long, global = true, value_name = "PATH", conflicts_with = "profile")]
+    config: Option<std::path::PathBuf>,
 }
 
 #[derive(Subcommand)]
 enum Commands {
     /// Monitor a Bambu Labs printer via MQTT
     Monitor {
+        /// Printer name from config (or use default if not specified); repeat or separate
+        /// with commas to watch several printers at once
+        #[arg(short, long, value_delimiter = ',')]
+        name: Vec<String>,
+
+        /// Printer IP address (overrides config)
+        #[arg(short = 'i', long)]
+        ip: Option<String>,
+
+        /// Device ID of the printer (overrides config)
+        #[arg(short, long)]
+        device_id: Option<String>,
+
+        /// LAN access code for the printer (overrides config)
+        #[arg(short, long)]
+        access_code: Option<String>,
+
+        /// Print one JSON object per status update, from every watched printer
+        #[arg(long)]
+        json: bool,
+
+        /// Drop payloads identical to the previous one before parsing
+        #[arg(long)]
+        dedupe: bool,
+
+        /// Print a new line per status update instead of an in-place progress bar
+        #[arg(long)]
+        no_progress: bool,
+
+        /// Print one complete status summary and exit
+        #[arg(long)]
+        once: bool,
+
+        /// Stop monitoring after this long (e.g. 90s, 10m, 2h)
+        #[arg(long, value_parser = display::parse_duration)]
+        duration: Option<std::time::Duration>,
+
+        /// Stop monitoring once the print finishes or fails
+        #[arg(long)]
+        until_done: bool,
+
+        /// Only show these data points (comma-separated)
+        #[arg(long, value_enum, value_delimiter = ',')]
+        fields: Vec<display::Field>,
+
+        /// Clear the screen and redraw a status panel every N seconds
+        #[arg(long, value_name = "SECS", conflicts_with_all = ["once", "json"], value_parser = clap::value_parser!(u64).range(1..))]
+        refresh: Option<u64>,
+
+        /// Materials of the job being watched (e.g. ABS), for the door alert when the
+        /// project is unknown
+        #[arg(long, value_delimiter = ',')]
+        material: Vec<String>,
+    },
+    /// Print a one-shot status summary; the exit code reflects the print state
+    Status {
+        /// Printer name from config (or use default if not specified)
+        #[arg(short, long)]
+        name: Option<String>,
+
+        /// Printer IP address (overrides config)
+        #[arg(short = 'i', long)]
+        ip: Option<String>,
+
+        /// Device ID of the printer (overrides config)
+        #[arg(short, long)]
+        device_id: Option<String>,
+
+        /// LAN access code for the printer (overrides config)
+        #[arg(short, long)]
+        access_code: Option<String>,
+    },
+    /// Pause the current print
+    Pause {
+        /// Printer name from config (or use default if not specified)
+        #[arg(short, long)]
+        name: Option<String>,
+    },
+    /// Resume a paused print
+    Resume {
+        /// Printer name from config (or use default if not specified)
+        #[arg(short, long)]
+        name: Option<String>,
+    },
+    /// Start and control print jobs
+    Print {
+        #[command(subcommand)]
+        command: PrintCommand,
+    },
+    /// Start a print on the SD card at a later time (needs a running daemon)
+    Schedule {
+        /// Printer name from config (or use default if not specified)
+        name: Option<String>,
+
+        /// Start time: HH:MM for the next time the clock shows it, or YYYY-MM-DD HH:MM
+        #[arg(long)]
+        at: String,
+
+        /// Path of the 3MF file on the SD card (e.g. benchy.gcode.3mf)
+        #[arg(long)]
+        file: String,
+
+        #[command(flatten)]
+        job: JobArgs,
+    },
+    /// Manage files on the printer's SD card over FTPS
+    Files {
+        #[command(subcommand)]
+        command: FilesCommand,
+    },
+    /// Show AMS and external spool details
+    Ams {
+        #[command(subcommand)]
+        command: AmsCommand,
+    },
+    /// Summarize a sliced 3MF project: print time, filament and plates
+    Inspect {
+        /// Project file to read
+        file: std::path::PathBuf,
+
+        /// Write each plate's preview image into this directory
+        #[arg(long, value_name = "DIR")]
+        thumbnails: Option<std::path::PathBuf>,
+    },
+    /// Stop printing some objects of a multi-object plate, or list the objects of the current job
+    SkipObjects {
+        /// Printer name from config (or use default if not specified)
+        name: Option<String>,
+
+        /// Identify IDs of the objects to skip, comma-separated (see --list)
+        #[arg(long, value_delimiter = ',', required_unless_present = "list")]
+        ids: Vec<u32>,
+
+        /// List the objects of the current job instead of skipping any
+        #[arg(long, conflicts_with = "ids")]
+        list: bool,
+    },
+    /// Save a still image from the printer camera
+    Snapshot {
+        /// Printer name from config (or use default if not specified)
+        name: Option<String>,
+
+        /// Where to write the JPEG image
+        #[arg(short, long)]
+        out: std::path::PathBuf,
+
+        /// Camera protocol (detected from the printer model when not specified)
+        #[arg(long, value_enum)]
+        camera: Option<camera::CameraKind>,
+    },
+    /// Turn timelapse recording on or off for upcoming prints
+    Timelapse {
+        /// New timelapse setting
+        #[arg(value_enum)]
+        setting: Toggle,
+
+        /// Printer name from config (or use default if not specified)
+        #[arg(short, long)]
+        name: Option<String>,
+    },
+    /// Send a raw JSON command to the printer's request topic, for commands not wrapped yet
+    Publish {
+        /// Name of the printer to send the command to
+        name: String,
+
+        /// Request payload, e.g. '{"print": {"command": "pause"}}'
+        #[arg(long, value_name = "PAYLOAD", conflicts_with = "file")]
+        json: Option<String>,
+
+        /// Read the payload from this file, or from stdin for "-" (the default without --json)
+        #[arg(long, value_name = "PATH")]
+        file: Option<std::path::PathBuf>,
+
+        /// Wait for the printer's response with the same sequence ID and print it
+        #[arg(short, long)]
+        wait: bool,
+
+        /// How long --wait waits for the response (e.g. 10s, 1m)
+        #[arg(short, long, default_value = "10s", value_parser = display::parse_duration)]
+        timeout: std::time::Duration,
+    },
+    /// Keep every configured printer connected in the background and serve other commands
+    Daemon {
+        #[command(subcommand)]
+        command: DaemonCommand,
+    },
+    /// Manage the systemd user service that runs the daemon
+    Service {
+        #[command(subcommand)]
+        command: ServiceCommand,
+    },
+    /// Show the serial, model, hardware revision and module firmware versions of a printer
+    Info {
+        /// Name of the printer to query
+        name: String,
+
+        /// Print a formatted block or one JSON object
+        #[arg(short, long, value_enum, default_value_t = OutputFormat::Text)]
+        output: OutputFormat,
+    },
+    /// Nagios/Icinga-compatible check with perfdata and plugin exit codes
+    Check {
+        /// Name of the printer to check
+        name: String,
+
+        /// Nozzle temperature that raises a WARNING
+        #[arg(long, default_value_t = display::NOZZLE_WARN_TEMP)]
+        warn_temp: f64,
+
+        /// Nozzle temperature that raises a CRITICAL
+        #[arg(long, alias = "crit", default_value_t = display::NOZZLE_CRITICAL_TEMP)]
+        crit_temp: f64,
+
+        /// Report UNKNOWN if no status arrives within this time (e.g. 10s, 1m)
+        #[arg(short, long, default_value = "10s", value_parser = display::parse_duration)]
+        timeout: std::time::Duration,
+    },
+    /// Record raw MQTT traffic from a printer to an NDJSON file
+    Record {
         /// Printer name from config (or use default if not specified)
         #[arg(short, long)]
         name: Option<String>,
@@ -31,7 +271,91 @@ enum Commands {
         /// LAN access code for the printer (overrides config)
         #[arg(short, long)]
         access_code: Option<String>,
+
+        /// Output file for the recorded session
+        #[arg(short, long)]
+        out: std::path::PathBuf,
+    },
+    /// Replay a recorded session through the normal parsing and display path
+    Replay {
+        /// Session file produced by the record command
+        file: std::path::PathBuf,
+
+        /// Playback speed multiplier (e.g. 10x)
+        #[arg(short, long, default_value = "1x", value_parser = recording::parse_speed)]
+        speed: f64,
+    },
+    /// Inspect saved and recorded print jobs
+    History {
+        #[command(subcommand)]
+        command: HistoryCommand,
+    },
+    /// Summarize the job history the daemon saved: success rate, failure reasons, filament,
+    /// durations and the busiest hours
+    Stats {
+        /// Only jobs of this printer
+        #[arg(short, long)]
+        printer: Option<String>,
+
+        /// Only jobs that ended within this long, e.g. 30d or 12h
+        #[arg(long, value_parser = display::parse_duration)]
+        since: Option<std::time::Duration>,
+    },
+    /// Compile a monthly report of utilization, failures, filament and energy per printer
+    /// from the saved job history
+    Report {
+        /// Month to report on, e.g. 2024-07
+        #[arg(long, value_parser = report::parse_month)]
+        month: chrono::NaiveDate,
+
+        /// File to write; HTML for a .html extension, Markdown otherwise
+        #[arg(short, long)]
+        out: std::path::PathBuf,
+
+        /// Also send the report to the recipients in [email] (needs the `email` feature)
+        #[arg(long)]
+        email: bool,
+    },
+    /// Write an iCalendar file with the expected finish of every running print, from the
+    /// daemon
+    Calendar {
+        /// Output file; stdout when left out
+        #[arg(short, long)]
+        out: Option<std::path::PathBuf>,
+    },
+    /// Simulate a Bambu Labs printer by publishing a scripted print job to an MQTT broker
+    Simulate {
+        /// Host of the MQTT broker to publish to
+        #[arg(long, default_value = "127.0.0.1")]
+        broker_host: String,
+
+        /// Port of the MQTT broker to publish to
+        #[arg(long, default_value_t = 1883)]
+        broker_port: u16,
+
+        /// Device ID the simulated printer reports as
+        #[arg(short, long, default_value = "SIMULATOR000001")]
+        device_id: String,
+
+        /// Delay between status messages in milliseconds
+        #[arg(long, default_value_t = 1000)]
+        interval_ms: u64,
+
+        /// Number of status updates while printing
+        #[arg(long, default_value_t = 50)]
+        steps: u32,
+    },
+    /// Parse a corpus of captured payloads and report unknown fields and failures
+    ParseCheck {
+        /// File or directory of captured JSON payloads or recorded sessions
+        path: std::path::PathBuf,
+
+        /// Show every failing payload instead of only the first few
+        #[arg(short, long)]
+        verbose: bool,
     },
+    /// Find a printer on the network, test the connection and save it, step by step
+    Setup,
     /// Add a new printer configuration
     Add {
         /// Printer name (unique identifier)
@@ -55,7 +379,42 @@ enum Commands {
         set_default: bool,
     },
     /// List all configured printers
-    List,
+    List {
+        /// Show each printer's current state as seen by the running daemon
+        #[arg(long)]
+        live: bool,
+
+        /// Check whether each printer accepts a connection right now
+        #[arg(long, conflicts_with = "live")]
+        online: bool,
+    },
+    /// List the configuration profiles (select one with --profile)
+    Profiles,
+    /// Check the configuration file
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
+    },
+    /// Add a printer with the settings of an existing one and its own address and serial
+    Clone {
+        /// Printer to copy the settings from
+        existing: String,
+
+        /// Name of the new printer
+        new_name: String,
+
+        /// IP address of the new printer
+        #[arg(short = 'i', long)]
+        ip: String,
+
+        /// Device ID of the new printer
+        #[arg(short, long)]
+        device_id: String,
+
+        /// LAN access code of the new printer (defaults to the existing printer's)
+        #[arg(short, long)]
+        access_code: Option<String>,
+    },
     /// Remove a printer configuration
     Remove {
         /// Name of the printer to remove
@@ -66,30 +425,725 @@ enum Commands {
         /// Name of the printer to set as default
         name: String,
     },
+    /// Print a shell completion script to stdout
+    Completions {
+        /// Shell to generate completions for
+        shell: clap_complete::Shell,
+    },
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum Toggle {
+    On,
+    Off,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum OutputFormat {
+    Text,
+    Json,
+}
+
+#[derive(Subcommand)]
+enum PrintCommand {
+    /// Start printing a project file that is already on the printer's SD card, then monitor it
+    Start {
+        /// Path of the 3MF file on the SD card (e.g. benchy.gcode.3mf or cache/benchy.gcode.3mf)
+        file: String,
+
+        /// Printer name from config (or use default if not specified)
+        #[arg(short, long)]
+        name: Option<String>,
+
+        #[command(flatten)]
+        job: JobArgs,
+
+        /// Exit once the job has been sent instead of monitoring it
+        #[arg(long)]
+        detach: bool,
+    },
+}
+
+/// Print options shared by `print start` and `schedule`
+#[derive(Args)]
+struct JobArgs {
+    /// Plate of the project to print
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+    plate: u32,
+
+    /// Skip automatic bed leveling before the print
+    #[arg(long)]
+    no_bed_leveling: bool,
+
+    /// Run flow dynamics calibration before the print
+    #[arg(long)]
+    flow_calibration: bool,
+
+    /// Record a timelapse of the print
+    #[arg(long)]
+    timelapse: bool,
+
+    /// AMS tray for each filament of the project, comma-separated (e.g. 0,2); prints from
+    /// the external spool when omitted
+    #[arg(long, value_delimiter = ',')]
+    ams_mapping: Vec<i32>,
+}
+
+impl JobArgs {
+    fn project_file(&self, path: &str) -> mqtt::ProjectFile {
+        mqtt::ProjectFile {
+            path: path.to_string(),
+            plate: self.plate,
+            bed_leveling: !self.no_bed_leveling,
+            flow_calibration: self.flow_calibration,
+            timelapse: self.timelapse,
+            ams_mapping: self.ams_mapping.clone(),
+        }
+    }
+}
+
+#[derive(Subcommand)]
+enum ConfigCommand {
+    /// Report invalid printer entries and missing files without connecting to anything
+    Validate,
+    /// Print a JSON Schema of the config file for editor completion and validation
+    Schema,
+}
+
+#[derive(Subcommand)]
+enum AmsCommand {
+    /// List the loaded trays with their material and flow calibration (K and n values)
+    Info {
+        /// Printer name from config (or use default if not specified)
+        #[arg(short, long)]
+        name: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum FilesCommand {
+    /// List a directory on the SD card
+    List {
+        /// Directory to list
+        #[arg(default_value = "/")]
+        path: String,
+
+        /// Printer name from config (or use default if not specified)
+        #[arg(short, long)]
+        name: Option<String>,
+    },
+    /// Copy a local file to the SD card
+    Upload {
+        /// Local file to upload
+        file: std::path::PathBuf,
+
+        /// Destination path on the SD card (defaults to the file name in the root directory)
+        #[arg(long)]
+        to: Option<String>,
+
+        /// Printer name from config (or use default if not specified)
+        #[arg(short, long)]
+        name: Option<String>,
+    },
+    /// Copy a file from the SD card
+    Download {
+        /// Path of the file on the SD card
+        path: String,
+
+        /// Local destination (defaults to the remote file name in the current directory)
+        #[arg(short, long)]
+        out: Option<std::path::PathBuf>,
+
+        /// Printer name from config (or use default if not specified)
+        #[arg(short, long)]
+        name: Option<String>,
+    },
+    /// Delete a file from the SD card
+    Delete {
+        /// Path of the file on the SD card
+        path: String,
+
+        /// Printer name from config (or use default if not specified)
+        #[arg(short, long)]
+        name: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum DaemonCommand {
+    /// Run the daemon in the foreground until stopped or interrupted
+    Start {
+        /// Serve the HTTP API on this address, e.g. 0.0.0.0:8080 (GET /healthz, GET /openapi.json)
+        #[arg(long, value_name = "ADDR", alias = "health")]
+        http: Option<std::net::SocketAddr>,
+
+        /// Report a printer as unhealthy when its last message is older than this
+        #[arg(long, default_value = "2m", value_parser = display::parse_duration)]
+        stale_after: std::time::Duration,
+
+        /// Serve the gRPC API on this address, e.g. 0.0.0.0:50051 (needs the `grpc` feature)
+        #[arg(long, value_name = "ADDR")]
+        grpc: Option<std::net::SocketAddr>,
+
+        /// Emulate the Moonraker API on this address for Klipper dashboards, e.g. 0.0.0.0:7125
+        #[arg(long, value_name = "ADDR")]
+        moonraker: Option<std::net::SocketAddr>,
+
+        /// Printer shown through the Moonraker API (defaults to the default printer)
+        #[arg(long, value_name = "NAME", requires = "moonraker")]
+        moonraker_printer: Option<String>,
+    },
+    /// Ask the running daemon to exit
+    Stop,
+    /// Report whether a daemon is running and its PID
+    Status,
+}
+
+#[derive(Subcommand)]
+enum ServiceCommand {
+    /// Write a systemd user unit that runs `daemon start` with the current settings
+    Install {
+        /// Overwrite an existing unit file
+        #[arg(long)]
+        force: bool,
+
+        /// Print the unit to stdout instead of writing it
+        #[arg(long)]
+        print: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum HistoryCommand {
+    /// List the jobs the daemon saved, with the ids used by `history chart`
+    List {
+        /// Only jobs of this printer
+        #[arg(short, long)]
+        printer: Option<String>,
+
+        /// Show only the most recent jobs
+        #[arg(short = 'n', long, default_value_t = 20)]
+        limit: usize,
+    },
+    /// Chart temperatures and progress over time for a saved job
+    Chart {
+        /// Job id from `history list`
+        id: usize,
+
+        /// Output image; the format follows the extension (.svg or .png)
+        #[arg(short, long)]
+        out: std::path::PathBuf,
+    },
+    /// Export the temperatures and progress of a recorded job as a table
+    Export {
+        /// Session file produced by the record command
+        job: std::path::PathBuf,
+
+        #[arg(short, long, value_enum, default_value = "csv")]
+        format: ExportFormat,
+
+        /// Output file; stdout when left out
+        #[arg(short, long)]
+        out: Option<std::path::PathBuf>,
+    },
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum ExportFormat {
+    Csv,
+    /// One JSON object per sample
+    Ndjson,
+    /// Columnar file for analysis tools; needs `--out`
+    #[cfg(feature = "parquet")]
+    Parquet,
 }
 
 #[tokio::main]
 async fn main() {
     let cli = Cli::parse();
 
+    // Every config lookup below depends on the config file and profile, so pick them first
+    config::select_config_file(cli.config.clone());
+    if let Err(e) = config::select_profile(cli.profile.clone()) {
+        eprintln!("Error: {e}");
+        std::process::exit(EXIT_CONFIG_ERROR);
+    }
+
+    // Display, report and cost defaults come from the config file; an unreadable config is reported
+    // by the commands that actually need it
+    let app_config = config::AppConfig::load(&config::AppConfig::get_config_path()).ok();
+    let display_settings = app_config
+        .as_ref()
+        .map(|app_config| app_config.display.clone())
+        .unwrap_or_default();
+    let report_settings = app_config
+        .as_ref()
+        .map(|app_config| app_config.reports.clone())
+        .filter(|reports| reports.enabled);
+    let cost_settings = app_config
+        .as_ref()
+        .map(|app_config| app_config.costs.clone())
+        .filter(config::CostSettings::is_configured);
+    let unconfigured = app_config
+        .as_ref()
+        .is_some_and(|app_config| app_config.printers.is_empty());
+    let mqtt_settings = app_config
+        .as_ref()
+        .map(|app_config| app_config.mqtt_settings.clone())
+        .unwrap_or_default();
+    let max_connections = mqtt_settings.max_concurrent_connections;
+    let reconnect = mqtt::reconnect_policy(&mqtt_settings);
+    let sink_settings = app_config
+        .as_ref()
+        .map(|app_config| app_config.sinks.clone())
+        .unwrap_or_default();
+    let message_log_settings = app_config
+        .as_ref()
+        .filter(|app_config| app_config.log_messages)
+        .map(|app_config| app_config.message_log.clone());
+    let alert_settings = app_config
+        .map(|app_config| app_config.alerts)
+        .filter(config::AlertSettings::is_configured);
+    if let Some(percent) = alert_settings
+        .as_ref()
+        .and_then(|alerts| alerts.filament_low_percent)
+    {
+        display::set_filament_low_percent(percent);
+    }
+    display::set_ascii_mode(cli.no_emoji || display_settings.no_emoji);
+    display::set_time_format(display_settings.time_format);
+    display::set_temperature_unit(display_settings.temperature_unit);
+    // `monitor --fields` replaces the configured selection
+    let fields = match &cli.command {
+        Some(Commands::Monitor { fields, .. }) if !fields.is_empty() => fields.clone(),
+        _ => display_settings.fields.clone(),
+    };
+    if !fields.is_empty() {
+        display::set_fields(fields);
+    }
+    display::set_color_enabled(display::should_use_color(
+        cli.no_color,
+        display_settings.no_color,
+        std::env::var_os("NO_COLOR").as_deref(),
+        std::io::IsTerminal::is_terminal(&std::io::stdout()),
+    ));
+    display::set_truecolor(display::supports_truecolor(
+        std::env::var_os("COLORTERM").as_deref(),
+    ));
+
+    if unconfigured
+        && cli.command.as_ref().is_some_and(needs_printer)
+        && std::io::IsTerminal::is_terminal(&std::io::stdin())
+        && std::io::IsTerminal::is_terminal(&std::io::stdout())
+    {
+        offer_setup().await;
+    }
+
     match &cli.command {
         Some(Commands::Monitor {
             name,
             ip,
             device_id,
             access_code,
+            json,
+            dedupe,
+            no_progress,
+            once,
+            duration,
+            until_done,
+            fields: _,
+            refresh,
+            material,
         }) => {
-            let printer_config = match load_printer_config(name, ip, device_id, access_code) {
+            let scripted = *once || duration.is_some() || *until_done;
+            if name.len() > 1 || *json {
+                let configs = if name.len() > 1 {
+                    if ip.is_some()
+                        || device_id.is_some()
+                        || access_code.is_some()
+                        || refresh.is_some()
+                    {
+                        eprintln!(
+                            "Error: --ip, --device-id, --access-code and --refresh apply to a single printer"
+                        );
+                        std::process::exit(EXIT_CONFIG_ERROR);
+                    }
+                    name.iter()
+                        .map(|name| load_printer_config(&Some(name.clone()), &None, &None, &None))
+                        .collect()
+                } else {
+                    load_printer_config(&name.first().cloned(), ip, device_id, access_code)
+                        .map(|config| vec![config])
+                };
+                let configs = match configs {
+                    Ok(configs) => configs,
+                    Err(e) => {
+                        eprintln!("Error loading printer configuration: {e}");
+                        std::process::exit(EXIT_CONFIG_ERROR);
+                    }
+                };
+
+                let options = MonitorOptions {
+                    dedupe: *dedupe,
+                    once: *once,
+                    duration: *duration,
+                    until_done: *until_done,
+                    reports: report_settings.clone(),
+                    costs: cost_settings.clone(),
+                    alerts: alert_settings.clone(),
+                    events: start_event_bus(&sink_settings),
+                    materials: material.clone(),
+                    reconnect: Some(reconnect),
+                    message_log: message_log_settings.clone(),
+                    ..Default::default()
+                };
+                let pool = mqtt::ConnectionPool::new(max_connections);
+                let exit_code = monitor_printers(configs, &options, *json, pool).await;
+                finish_event_bus(&options).await;
+                if scripted {
+                    std::process::exit(exit_code);
+                }
+                return;
+            }
+
+            let name = name.first().cloned();
+            let printer_config = match load_printer_config(&name, ip, device_id, access_code) {
                 Ok(config) => config,
                 Err(e) => {
                     eprintln!("Error loading printer configuration: {e}");
-                    std::process::exit(1);
+                    std::process::exit(if scripted { EXIT_CONFIG_ERROR } else { 1 });
                 }
             };
 
-            match monitor_printer(printer_config).await {
+            // A configured refresh only applies to interactive, open-ended monitoring
+            let refresh = refresh.or(display_settings
+                .refresh_secs
+                .filter(|secs| *secs > 0)
+                .filter(|_| !*once && std::io::IsTerminal::is_terminal(&std::io::stdout())));
+
+            let options = MonitorOptions {
+                dedupe: *dedupe,
+                progress_bar: !*no_progress,
+                once: *once,
+                duration: *duration,
+                until_done: *until_done,
+                refresh: refresh.map(std::time::Duration::from_secs),
+                reports: report_settings.clone(),
+                costs: cost_settings.clone(),
+                alerts: alert_settings.clone(),
+                events: start_event_bus(&sink_settings),
+                materials: material.clone(),
+                reconnect: Some(reconnect),
+                message_log: message_log_settings.clone(),
+                ..Default::default()
+            };
+
+            let result = monitor_printer(printer_config, &options).await;
+            finish_event_bus(&options).await;
+            match result {
+                Ok(state) if *once => std::process::exit(state_exit_code(&state)),
+                Ok(state) if scripted => {
+                    let exit_code = state_exit_code(&state);
+                    println!("Monitoring stopped (exit code {exit_code})");
+                    std::process::exit(exit_code);
+                }
                 Ok(_) => println!("Monitoring completed successfully"),
-                Err(e) => eprintln!("Error monitoring printer: {e}"),
+                Err(e) => {
+                    eprintln!("Error monitoring printer: {e}");
+                    if scripted {
+                        std::process::exit(EXIT_CONNECTION_ERROR);
+                    }
+                }
+            }
+        }
+        Some(Commands::Status {
+            name,
+            ip,
+            device_id,
+            access_code,
+        }) => {
+            // A running daemon already has the state, so skip opening a new session
+            if ip.is_none()
+                && device_id.is_none()
+                && access_code.is_none()
+                && let Some(code) = status_from_daemon(name).await
+            {
+                std::process::exit(code);
+            }
+
+            let printer_config = match load_printer_config(name, ip, device_id, access_code) {
+                Ok(config) => config,
+                Err(e) => {
+                    eprintln!("Error loading printer configuration: {e}");
+                    std::process::exit(EXIT_CONFIG_ERROR);
+                }
+            };
+
+            let options = MonitorOptions {
+                once: true,
+                ..Default::default()
+            };
+
+            match monitor_printer(printer_config, &options).await {
+                Ok(state) => std::process::exit(state_exit_code(&state)),
+                Err(e) => {
+                    eprintln!("Error querying printer status: {e}");
+                    std::process::exit(EXIT_CONNECTION_ERROR);
+                }
+            }
+        }
+        Some(Commands::Pause { name }) => {
+            std::process::exit(handle_print_action(name, mqtt::PrintAction::Pause).await);
+        }
+        Some(Commands::Resume { name }) => {
+            std::process::exit(handle_print_action(name, mqtt::PrintAction::Resume).await);
+        }
+        Some(Commands::Print {
+            command:
+                PrintCommand::Start {
+                    file,
+                    name,
+                    job,
+                    detach,
+                },
+        }) => {
+            let project = job.project_file(file);
+            let options = MonitorOptions {
+                progress_bar: true,
+                reports: report_settings.clone(),
+                costs: cost_settings.clone(),
+                alerts: alert_settings.clone(),
+                events: start_event_bus(&sink_settings),
+                reconnect: Some(reconnect),
+                message_log: message_log_settings.clone(),
+                ..Default::default()
+            };
+            let exit_code = handle_print_start(name, project, *detach, &options).await;
+            finish_event_bus(&options).await;
+            std::process::exit(exit_code);
+        }
+        Some(Commands::Schedule {
+            name,
+            at,
+            file,
+            job,
+        }) => {
+            std::process::exit(handle_schedule(name, at, job.project_file(file)).await);
+        }
+        Some(Commands::Files { command }) => {
+            std::process::exit(handle_files(command).await);
+        }
+        Some(Commands::Ams {
+            command: AmsCommand::Info { name },
+        }) => {
+            std::process::exit(handle_ams_info(name).await);
+        }
+        Some(Commands::Inspect { file, thumbnails }) => {
+            if let Err(e) = handle_inspect(file, thumbnails.as_deref()) {
+                eprintln!("Error inspecting {}: {e}", file.display());
+                std::process::exit(1);
+            }
+        }
+        Some(Commands::SkipObjects { name, ids, list }) => {
+            let exit_code = if *list {
+                handle_list_objects(name).await
+            } else {
+                handle_skip_objects(name, ids).await
+            };
+            std::process::exit(exit_code);
+        }
+        Some(Commands::Snapshot { name, out, camera }) => {
+            std::process::exit(handle_snapshot(name, out, *camera).await);
+        }
+        Some(Commands::Timelapse { setting, name }) => {
+            std::process::exit(handle_timelapse(name, *setting == Toggle::On).await);
+        }
+        Some(Commands::Publish {
+            name,
+            json,
+            file,
+            wait,
+            timeout,
+        }) => {
+            let timeout = wait.then_some(*timeout);
+            std::process::exit(
+                handle_publish(name, json.as_deref(), file.as_deref(), timeout).await,
+            );
+        }
+        Some(Commands::Daemon {
+            command:
+                DaemonCommand::Start {
+                    http,
+                    stale_after,
+                    grpc,
+                    moonraker,
+                    moonraker_printer,
+                },
+        }) => {
+            let options = daemon::DaemonOptions {
+                http: *http,
+                stale_after: *stale_after,
+                grpc: *grpc,
+                moonraker: *moonraker,
+                moonraker_printer: moonraker_printer.clone(),
+                ..Default::default()
+            };
+            if let Err(e) = handle_daemon_start(&options).await {
+                eprintln!("Error running daemon: {e}");
+                std::process::exit(1);
+            }
+        }
+        Some(Commands::Daemon {
+            command: DaemonCommand::Status,
+        }) => match daemon::instance_state(&data_dir()) {
+            Ok(daemon::InstanceState::Running { pid }) => {
+                let pid = pid.map(|pid| format!(" (PID {pid})")).unwrap_or_default();
+                println!(
+                    "{} Daemon is running{pid}, listening on {}",
+                    Icon::Success,
+                    daemon_socket_path().display()
+                );
+            }
+            Ok(daemon::InstanceState::NotRunning) => {
+                println!("{} Daemon is not running", Icon::Stop);
+                std::process::exit(1);
+            }
+            Err(e) => {
+                eprintln!("Error checking daemon: {e}");
+                std::process::exit(1);
+            }
+        },
+        Some(Commands::Daemon {
+            command: DaemonCommand::Stop,
+        }) => match daemon::request(&daemon_socket_path(), &daemon::Request::Shutdown).await {
+            Ok(_) => println!("{} Daemon stopped", Icon::Stop),
+            Err(e) => {
+                eprintln!("Error stopping daemon: {e}");
+                std::process::exit(1);
+            }
+        },
+        Some(Commands::Service {
+            command: ServiceCommand::Install { force, print },
+        }) => {
+            if let Err(e) = handle_service_install(&cli, *force, *print) {
+                eprintln!("Error installing service: {e}");
+                std::process::exit(1);
+            }
+        }
+        Some(Commands::Info { name, output }) => {
+            std::process::exit(handle_info(name, *output).await);
+        }
+        Some(Commands::Check {
+            name,
+            warn_temp,
+            crit_temp,
+            timeout,
+        }) => {
+            let thresholds = check::Thresholds {
+                warn_temp: *warn_temp,
+                crit_temp: *crit_temp,
+            };
+            let result = run_check(name, &thresholds, *timeout).await;
+            println!("{result}");
+            std::process::exit(result.status.exit_code());
+        }
+        Some(Commands::Record {
+            name,
+            ip,
+            device_id,
+            access_code,
+            out,
+        }) => {
+            let printer_config = match load_printer_config(name, ip, device_id, access_code) {
+                Ok(config) => config,
+                Err(e) => {
+                    eprintln!("Error loading printer configuration: {e}");
+                    std::process::exit(1);
+                }
+            };
+
+            if let Err(e) = record_printer(printer_config, out).await {
+                eprintln!("Error recording printer: {e}");
+                std::process::exit(1);
+            }
+        }
+        Some(Commands::Replay { file, speed }) => {
+            if let Err(e) = replay_session(file, *speed).await {
+                eprintln!("Error replaying session: {e}");
+                std::process::exit(1);
+            }
+        }
+        Some(Commands::History {
+            command: HistoryCommand::List { printer, limit },
+        }) => {
+            if let Err(e) = handle_history_list(printer.as_deref(), *limit).await {
+                eprintln!("Error listing jobs: {e}");
+                std::process::exit(1);
+            }
+        }
+        Some(Commands::History {
+            command: HistoryCommand::Chart { id, out },
+        }) => {
+            if let Err(e) = handle_history_chart(*id, out).await {
+                eprintln!("Error rendering chart: {e}");
+                std::process::exit(1);
+            }
+        }
+        Some(Commands::Stats { printer, since }) => {
+            if let Err(e) = handle_stats(printer.as_deref(), *since).await {
+                eprintln!("Error computing stats: {e}");
+                std::process::exit(1);
+            }
+        }
+        Some(Commands::Calendar { out }) => {
+            if let Err(e) = handle_calendar(out.as_deref()).await {
+                eprintln!("Error writing calendar: {e}");
+                std::process::exit(1);
+            }
+        }
+        Some(Commands::Report { month, out, email }) => {
+            if let Err(e) = handle_monthly_report(*month, out, *email).await {
+                eprintln!("Error writing report: {e}");
+                std::process::exit(1);
+            }
+        }
+        Some(Commands::History {
+            command: HistoryCommand::Export { job, format, out },
+        }) => {
+            if let Err(e) = handle_history_export(job, *format, out.as_deref()) {
+                eprintln!("Error exporting history: {e}");
+                std::process::exit(1);
+            }
+        }
+        Some(Commands::Simulate {
+            broker_host,
+            broker_port,
+            device_id,
+            interval_ms,
+            steps,
+        }) => {
+            let script = simulator::PrintJobScript {
+                printing_steps: *steps,
+                ..Default::default()
+            };
+
+            if let Err(e) =
+                run_simulator(broker_host, *broker_port, device_id, *interval_ms, script).await
+            {
+                eprintln!("Error running simulator: {e}");
+                std::process::exit(1);
+            }
+        }
+        Some(Commands::ParseCheck { path, verbose }) => {
+            if let Err(e) = handle_parse_check(path, *verbose) {
+                eprintln!("Error checking payloads: {e}");
+                std::process::exit(1);
+            }
+        }
+        Some(Commands::Setup) => {
+            if let Err(e) = run_setup_wizard().await {
+                eprintln!("Error during setup: {e}");
+                std::process::exit(EXIT_CONFIG_ERROR);
             }
         }
         Some(Commands::Add {
@@ -104,12 +1158,51 @@ async fn main() {
                 std::process::exit(1);
             }
         }
-        Some(Commands::List) => {
-            if let Err(e) = handle_list_printers() {
+        Some(Commands::List { live: true, .. }) => {
+            if let Err(e) = handle_list_live().await {
+                eprintln!("Error listing printers: {e}");
+                std::process::exit(1);
+            }
+        }
+        Some(Commands::Profiles) => handle_list_profiles(),
+        Some(Commands::Config {
+            command: ConfigCommand::Validate,
+        }) => {
+            std::process::exit(handle_config_validate());
+        }
+        Some(Commands::Config {
+            command: ConfigCommand::Schema,
+        }) => {
+            let schema = config::AppConfig::json_schema();
+            match serde_json::to_string_pretty(&schema) {
+                Ok(schema) => println!("{schema}"),
+                Err(e) => {
+                    eprintln!("Error writing schema: {e}");
+                    std::process::exit(1);
+                }
+            }
+        }
+        Some(Commands::List {
+            live: false,
+            online,
+        }) => {
+            if let Err(e) = handle_list_printers(*online).await {
                 eprintln!("Error listing printers: {e}");
                 std::process::exit(1);
             }
         }
+        Some(Commands::Clone {
+            existing,
+            new_name,
+            ip,
+            device_id,
+            access_code,
+        }) => {
+            if let Err(e) = handle_clone_printer(existing, new_name, ip, device_id, access_code) {
+                eprintln!("Error cloning printer: {e}");
+                std::process::exit(1);
+            }
+        }
         Some(Commands::Remove { name }) => {
             if let Err(e) = handle_remove_printer(name) {
                 eprintln!("Error removing printer: {e}");
@@ -122,12 +1215,213 @@ async fn main() {
                 std::process::exit(1);
             }
         }
+        Some(Commands::Completions { shell }) => {
+            print_completions(*shell);
+        }
         None => {
             println!("Welcome to PulsePrint-CLI! Use --help for usage.");
         }
     }
 }
 
+/// Subcommands whose `name` argument refers to a configured printer
+const PRINTER_NAME_SUBCOMMANDS: [&str; 12] = [
+    "monitor",
+    "status",
+    "pause",
+    "resume",
+    "schedule",
+    "snapshot",
+    "skip-objects",
+    "timelapse",
+    "check",
+    "record",
+    "remove",
+    "set-default",
+];
+
+fn print_completions(shell: clap_complete::Shell) {
+    // Completion scripts are static, so bake in the printer names known right now
+    let printer_names: Vec<String> = config::AppConfig::load(&config::AppConfig::get_config_path())
+        .map(|app_config| app_config.printers.into_keys().collect())
+        .unwrap_or_default();
+
+    let mut command = completion_command(&printer_names);
+    let bin_name = command.get_name().to_string();
+    clap_complete::generate(shell, &mut command, bin_name, &mut std::io::stdout());
+}
+
+fn completion_command(printer_names: &[String]) -> clap::Command {
+    let mut command = Cli::command();
+    if printer_names.is_empty() {
+        return command;
+    }
+
+    let mut printer_names = printer_names.to_vec();
+    printer_names.sort();
+
+    for subcommand in PRINTER_NAME_SUBCOMMANDS {
+        let names = printer_names.clone();
+        command = command.mut_subcommand(subcommand, |sub| {
+            sub.mut_arg("name", |arg| {
+                arg.value_parser(clap::builder::PossibleValuesParser::new(names))
+            })
+        });
+    }
+
+    command
+}
+
+/// Commands that talk to a configured printer, for which a first run offers the setup wizard
+fn needs_printer(command: &Commands) -> bool {
+    match command {
+        Commands::Monitor { ip, .. }
+        | Commands::Status { ip, .. }
+        | Commands::Record { ip, .. } => ip.is_none(),
+        Commands::Pause { .. }
+        | Commands::Resume { .. }
+        | Commands::Print { .. }
+        | Commands::Schedule { .. }
+        | Commands::Files { .. }
+        | Commands::Ams { .. }
+        | Commands::SkipObjects { .. }
+        | Commands::Snapshot { .. }
+        | Commands::Timelapse { .. } => true,
+        _ => false,
+    }
+}
+
+/// Runs the setup wizard before a command that would otherwise fail for lack of a printer
+async fn offer_setup() {
+    let stdin = std::io::stdin();
+    let mut prompter = setup::Prompter::new(stdin.lock(), std::io::stdout());
+    println!("{} No printers are configured yet.", Icon::Hint);
+    if !matches!(prompter.confirm("Set one up now?", true), Ok(true)) {
+        return;
+    }
+    drop(prompter);
+
+    if let Err(e) = run_setup_wizard().await {
+        eprintln!("Error during setup: {e}");
+        std::process::exit(EXIT_CONFIG_ERROR);
+    }
+    println!();
+}
+
+/// How long the wizard listens for printer announcements
+const DISCOVERY_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+/// How long the wizard waits for the first status report of the new printer
+const SETUP_CONNECT_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(15);
+
+/// Interactive setup: discover, pick a printer, enter the access code, test and save
+async fn run_setup_wizard() -> Result<(), Box<dyn std::error::Error>> {
+    let config_path = config::AppConfig::get_config_path();
+    let mut app_config = config::AppConfig::load_from_file(&config_path)?;
+    let taken: Vec<String> = app_config.printers.keys().cloned().collect();
+    let taken: Vec<&str> = taken.iter().map(String::as_str).collect();
+
+    println!(
+        "{} Looking for printers on the local network...",
+        Icon::Monitoring
+    );
+    let found = discovery::discover(DISCOVERY_TIMEOUT)
+        .await
+        .unwrap_or_else(|e| {
+            eprintln!("{} Network discovery failed: {e}", Icon::Failure);
+            Vec::new()
+        });
+
+    let stdin = std::io::stdin();
+    let mut prompter = setup::Prompter::new(stdin.lock(), std::io::stdout());
+    let chosen = if found.is_empty() {
+        println!(
+            "No printers answered. Make sure LAN mode is enabled and the printer is on this network."
+        );
+        None
+    } else {
+        println!("Found {} printer(s):", found.len());
+        let options: Vec<String> = found.iter().map(setup::describe).collect();
+        prompter.choose(&options)?.map(|index| found[index].clone())
+    };
+
+    let (ip, device_id, model, suggested) = match chosen {
+        Some(printer) => {
+            let suggested =
+                setup::suggest_name(printer.name.as_deref(), printer.model.as_deref(), &taken);
+            (printer.ip, printer.device_id, printer.model, suggested)
+        }
+        None => {
+            let ip = prompter.ask("IP address", None, config::validate_ip_address)?;
+            let device_id = prompter.ask(
+                "Serial number (device ID)",
+                None,
+                config::validate_device_id,
+            )?;
+            (ip, device_id, None, setup::suggest_name(None, None, &taken))
+        }
+    };
+    let name = prompter.ask("Name for this printer", Some(&suggested), |name| {
+        if taken.contains(&name) {
+            Err(config::ConfigError::Invalid(format!(
+                "Printer '{name}' already exists"
+            )))
+        } else {
+            Ok(())
+        }
+    })?;
+    println!("The access code is shown on the printer's screen under Settings > Network.");
+    let access_code = prompter.ask("LAN access code", None, config::validate_access_code)?;
+
+    let mut printer = config::PrinterConfig::new(name.clone(), ip, device_id, access_code);
+    printer.model = model;
+
+    println!("{} Testing the connection...", Icon::Wifi);
+    let options = MonitorOptions {
+        once: true,
+        quiet: true,
+        duration: Some(SETUP_CONNECT_TIMEOUT),
+        ..Default::default()
+    };
+    match monitor_printer(printer.clone(), &options).await {
+        Ok(state) if state.message_count > 0 => {
+            println!("{} Connected", Icon::Success);
+            print_state_summary(&state);
+        }
+        result => {
+            let reason = match result {
+                Err(e) => e.to_string(),
+                Ok(_) => format!(
+                    "no status report within {}s",
+                    SETUP_CONNECT_TIMEOUT.as_secs()
+                ),
+            };
+            println!("{} Connection test failed: {reason}", Icon::Failure);
+            println!("Check the access code and that LAN mode is enabled on the printer.");
+            if !prompter.confirm("Save the printer anyway?", false)? {
+                return Err("setup cancelled; nothing was saved".into());
+            }
+        }
+    }
+
+    let had_default = app_config.default_printer.is_some();
+    app_config.add_printer(name.clone(), printer)?;
+    if had_default && prompter.confirm("Make it the default printer?", true)? {
+        app_config.set_default_printer(&name)?;
+    }
+    app_config.save_to_file(&config_path)?;
+
+    println!(
+        "{} Printer '{name}' saved to {}",
+        Icon::Success,
+        config_path.display()
+    );
+    println!(
+        "{} Start monitoring with: pulseprint-cli monitor",
+        Icon::Hint
+    );
+    Ok(())
+}
+
 fn handle_add_printer(
     name: &str,
     ip: &str,
@@ -136,9 +1430,9 @@ fn handle_add_printer(
     set_default: bool,
 ) -> Result<(), Box<dyn std::error::Error>> {
     // Validate inputs
-    validate_ip_address(ip)?;
-    validate_device_id(device_id)?;
-    validate_access_code(access_code)?;
+    config::validate_ip_address(ip)?;
+    config::validate_device_id(device_id)?;
+    config::validate_access_code(access_code)?;
 
     // Load existing config
     let config_path = config::AppConfig::get_config_path();
@@ -163,23 +1457,94 @@ fn handle_add_printer(
     // Save config
     app_config.save_to_file(&config_path)?;
 
-    println!("✅ Printer '{name}' added successfully");
+    println!("{} Printer '{name}' added successfully", Icon::Success);
     if set_default {
-        println!("🎯 Set as default printer");
+        println!("{} Set as default printer", Icon::Default);
     }
 
     Ok(())
 }
 
-fn handle_list_printers() -> Result<(), Box<dyn std::error::Error>> {
+fn handle_config_validate() -> i32 {
+    let config_path = config::AppConfig::get_config_path();
+    let app_config = match config::AppConfig::load(&config_path) {
+        Ok(app_config) => app_config,
+        Err(e) => {
+            eprintln!("{} {}: {e}", Icon::Failure, config_path.display());
+            return EXIT_CONFIG_ERROR;
+        }
+    };
+
+    let issues = app_config.validate();
+    if issues.is_empty() {
+        println!(
+            "{} Configuration at {} is valid ({} printers)",
+            Icon::Success,
+            config_path.display(),
+            app_config.printers.len()
+        );
+        return 0;
+    }
+
+    println!("{}:", config_path.display());
+    for issue in &issues {
+        let icon = if issue.is_error {
+            Icon::Failure
+        } else {
+            Icon::Alert
+        };
+        println!("{icon} {}: {}", issue.location, issue.message);
+    }
+    let errors = issues.iter().filter(|issue| issue.is_error).count();
+    println!("{errors} errors, {} warnings", issues.len() - errors);
+    if errors > 0 { EXIT_CONFIG_ERROR } else { 0 }
+}
+
+fn handle_list_profiles() {
+    let profiles = config::AppConfig::list_profiles();
+    if profiles.is_empty() {
+        println!("No profiles yet. Run any command with --profile <NAME> to use one, e.g.");
+        println!("  pulseprint-cli --profile office add --name ...");
+        return;
+    }
+
+    let active = config::active_profile();
+    for profile in &profiles {
+        let marker = if Some(profile.as_str()) == active {
+            " (active)"
+        } else {
+            ""
+        };
+        println!("{} {profile}{marker}", Icon::ListItem);
+    }
+}
+
+/// How long `list --online` waits for each printer's broker to answer
+const PROBE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+async fn handle_list_printers(online: bool) -> Result<(), Box<dyn std::error::Error>> {
     let config_path = config::AppConfig::get_config_path();
-    let app_config = config::AppConfig::load_from_file(&config_path)?;
+    let app_config = config::AppConfig::load(&config_path)?;
 
     if app_config.printers.is_empty() {
         println!("No printers configured. Use 'add' command to add a printer.");
         return Ok(());
     }
 
+    // Printers are probed in parallel, up to the connection limit at a time
+    let mut reachability = std::collections::HashMap::new();
+    if online {
+        let pool = mqtt::ConnectionPool::new(app_config.mqtt_settings.max_concurrent_connections);
+        let mut probes = tokio::task::JoinSet::new();
+        for (name, printer) in &app_config.printers {
+            let (name, printer, pool) = (name.clone(), printer.clone(), pool.clone());
+            probes.spawn(async move { (name, pool.probe(&printer, PROBE_TIMEOUT).await) });
+        }
+        while let Some(Ok((name, result))) = probes.join_next().await {
+            reachability.insert(name, result);
+        }
+    }
+
     println!("Configured Printers:");
     println!("==================");
 
@@ -194,7 +1559,17 @@ fn handle_list_printers() -> Result<(), Box<dyn std::error::Error>> {
             ""
         };
 
-        println!("📄 {name}{default_marker}");
+        let state = match reachability.get(name) {
+            Some(mqtt::Reachability::Online) => format!(" {} online", Icon::Online),
+            Some(mqtt::Reachability::Rejected) => {
+                format!(" {} access code rejected", Icon::Rejected)
+            }
+            Some(mqtt::Reachability::Unreachable(reason)) => {
+                format!(" {} unreachable ({reason})", Icon::Offline)
+            }
+            None => String::new(),
+        };
+        println!("{} {name}{default_marker}{state}", Icon::ListItem);
         println!("   IP: {}", printer.ip);
         println!("   Device ID: {}", printer.device_id);
         println!(
@@ -205,346 +1580,3624 @@ fn handle_list_printers() -> Result<(), Box<dyn std::error::Error>> {
             } else {
                 "disabled"
             }
-        );
-        if let Some(model) = &printer.model {
-            println!("   Model: {model}");
+        );
+        if let Some(model) = &printer.model {
+            println!("   Model: {model}");
+        }
+        for (label, value) in printer.details() {
+            println!("   {label}: {value}");
+        }
+        println!();
+    }
+
+    Ok(())
+}
+
+/// Where `daemon start` listens and other commands look for it
+fn daemon_socket_path() -> std::path::PathBuf {
+    if cfg!(windows) {
+        std::path::PathBuf::from(daemon::PIPE_NAME)
+    } else {
+        data_dir().join("daemon.sock")
+    }
+}
+
+/// Printer name as given (or the printer it is a prefix of), or the configured default
+fn resolve_printer_name(name: &Option<String>) -> Result<String, Box<dyn std::error::Error>> {
+    let app_config = config::AppConfig::load(&config::AppConfig::get_config_path());
+    if let Some(name) = name {
+        // Prefixes resolve through the config; other names are left for the daemon to judge
+        let resolved = app_config
+            .ok()
+            .and_then(|app_config| Some(app_config.resolve_printer_name(name).ok()?.to_string()));
+        return Ok(resolved.unwrap_or_else(|| name.clone()));
+    }
+    Ok(app_config?.get_default_printer()?.name.clone())
+}
+
+/// Prints the printer state held by a running daemon and returns the exit code, or `None`
+/// when no daemon is running or it does not know the printer
+async fn status_from_daemon(name: &Option<String>) -> Option<i32> {
+    let printer = resolve_printer_name(name).ok()?;
+    let request = daemon::Request::Status {
+        printer: Some(printer),
+    };
+    let daemon::Response::Printers { printers } = daemon::request(&daemon_socket_path(), &request)
+        .await
+        .ok()?
+    else {
+        return None;
+    };
+
+    let snapshot = printers.first()?;
+    let state = &snapshot.state;
+    if state.message_count > 0 {
+        print_state_summary(state);
+    } else {
+        eprintln!(
+            "Printer '{}' has not reported any status to the daemon yet",
+            snapshot.name
+        );
+    }
+    Some(state_exit_code(state))
+}
+
+async fn handle_daemon_start(
+    options: &daemon::DaemonOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let app_config = config::AppConfig::load(&config::AppConfig::get_config_path())?;
+    if app_config.printers.is_empty() {
+        return Err("No printers configured. Use 'add' command to add a printer.".into());
+    }
+
+    // Moonraker describes a single printer; pick the default one unless told otherwise
+    let mut options = options.clone();
+    if options.moonraker.is_some() && options.moonraker_printer.is_none() {
+        let printer = match app_config.get_default_printer() {
+            Ok(printer) => printer,
+            Err(_) if app_config.printers.len() == 1 => {
+                app_config.printers.values().next().unwrap()
+            }
+            Err(_) => {
+                return Err(
+                    "Several printers are configured; choose one with --moonraker-printer".into(),
+                );
+            }
+        };
+        options.moonraker_printer = Some(printer.name.clone());
+    }
+    options.max_concurrent_connections = app_config.mqtt_settings.max_concurrent_connections;
+    if app_config.log_messages {
+        options.message_log = Some((app_config.message_log.clone(), data_dir().join("messages")));
+    }
+    options.history = history_store(&app_config).await?;
+
+    // Held until the daemon returns; a second daemon fails here with the first one's PID
+    let _lock = daemon::InstanceLock::acquire(&data_dir())?;
+
+    let mut printers: Vec<_> = app_config.printers.into_values().collect();
+    printers.sort_by(|a, b| a.name.cmp(&b.name));
+    let socket = daemon_socket_path();
+    println!(
+        "{} Daemon monitoring {} printer(s), listening on {}",
+        Icon::Monitoring,
+        printers.len(),
+        socket.display()
+    );
+
+    if let Some(address) = options.http {
+        println!("{} Health check at http://{address}/healthz", Icon::Info);
+        println!(
+            "{} API description at http://{address}/openapi.json",
+            Icon::Info
+        );
+    }
+    if let Some(address) = options.grpc
+        && cfg!(feature = "grpc")
+    {
+        println!("{} gRPC API on {address}", Icon::Info);
+    }
+    if let (Some(address), Some(printer)) = (options.moonraker, &options.moonraker_printer) {
+        println!(
+            "{} Moonraker API for '{printer}' at http://{address}",
+            Icon::Info
+        );
+    }
+
+    daemon::run(printers, &socket, &options).await?;
+    println!("{} Daemon stopped", Icon::Stop);
+    Ok(())
+}
+
+fn handle_service_install(
+    cli: &Cli,
+    force: bool,
+    print: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let exe = std::env::current_exe()?;
+
+    // Keep the display flags, profile and config location of this invocation
+    let mut args = Vec::new();
+    if cli.no_emoji {
+        args.push("--no-emoji");
+    }
+    if cli.no_color {
+        args.push("--no-color");
+    }
+    // The unit may run from another working directory
+    let config_file = config::explicit_config_file()
+        .map(std::path::absolute)
+        .transpose()?
+        .map(|path| path.display().to_string());
+    if let Some(path) = &config_file {
+        args.extend(["--config", path.as_str()]);
+    } else if let Some(profile) = config::active_profile() {
+        args.extend(["--profile", profile]);
+    }
+    args.extend(["daemon", "start"]);
+    let environment: Vec<_> = std::env::var("PULSEPRINT_TEST_CONFIG_DIR")
+        .ok()
+        .map(|dir| ("PULSEPRINT_TEST_CONFIG_DIR", dir))
+        .into_iter()
+        .collect();
+    let unit = service::unit_file(&exe, &args, &environment);
+
+    if print {
+        print!("{unit}");
+        return Ok(());
+    }
+    if !cfg!(target_os = "linux") {
+        return Err(
+            "systemd services are only available on Linux; use --print to view the unit".into(),
+        );
+    }
+
+    let path = service::user_unit_path().ok_or("Could not determine the user config directory")?;
+    if path.exists() && !force {
+        return Err(format!(
+            "{} already exists (use --force to overwrite)",
+            path.display()
+        )
+        .into());
+    }
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(&path, unit)?;
+
+    println!("{} Wrote {}", Icon::Success, path.display());
+    println!(
+        "{} Enable it with: systemctl --user daemon-reload && systemctl --user enable --now {}",
+        Icon::Hint,
+        service::UNIT_NAME
+    );
+    Ok(())
+}
+
+async fn handle_calendar(out: Option<&std::path::Path>) -> Result<(), Box<dyn std::error::Error>> {
+    let request = daemon::Request::Status { printer: None };
+    let response = match daemon::request(&daemon_socket_path(), &request).await {
+        Ok(response) => response,
+        Err(daemon::DaemonError::NotRunning) => {
+            return Err("Daemon is not running. Start it with 'daemon start'.".into());
+        }
+        Err(e) => return Err(e.into()),
+    };
+    let daemon::Response::Printers { printers } = response else {
+        return Err("Unexpected response from daemon".into());
+    };
+
+    let document = calendar::render(&printers, chrono::Local::now());
+    match out {
+        Some(path) => std::fs::write(path, document)?,
+        None => print!("{document}"),
+    }
+    Ok(())
+}
+
+async fn handle_list_live() -> Result<(), Box<dyn std::error::Error>> {
+    let request = daemon::Request::Status { printer: None };
+    let response = match daemon::request(&daemon_socket_path(), &request).await {
+        Ok(response) => response,
+        Err(daemon::DaemonError::NotRunning) => {
+            return Err("Daemon is not running. Start it with 'daemon start'.".into());
+        }
+        Err(e) => return Err(e.into()),
+    };
+    let daemon::Response::Printers { printers } = response else {
+        return Err("Unexpected response from daemon".into());
+    };
+
+    println!("Live Printers:");
+    println!("==============");
+    for printer in printers {
+        let state = &printer.state;
+        let status = state.status();
+        let connection = if printer.connected {
+            "connected"
+        } else {
+            "disconnected"
+        };
+        let mut line = format!("{} {} - {connection}", Icon::ListItem, printer.name);
+        if state.message_count > 0 {
+            line.push_str(&format!(", {:?}", status.state));
+            if let Some(progress) = status.progress {
+                line.push_str(&format!(" {progress}%"));
+            }
+        }
+        if let Some(last) = printer.last_message_at {
+            let age = (chrono::Local::now() - last).num_seconds().max(0) as u32;
+            line.push_str(&format!(
+                " (last update {} ago)",
+                display::format_duration_short(age)
+            ));
+        }
+        println!("{line}");
+    }
+
+    Ok(())
+}
+
+/// Sends pause/resume through the daemon when it runs, otherwise over a short-lived session
+async fn handle_print_action(name: &Option<String>, action: mqtt::PrintAction) -> i32 {
+    let verb = match action {
+        mqtt::PrintAction::Pause => "Pause",
+        mqtt::PrintAction::Resume => "Resume",
+        mqtt::PrintAction::Stop => "Stop",
+    };
+
+    if let Ok(printer) = resolve_printer_name(name) {
+        let request = daemon::Request::Control {
+            printer: printer.clone(),
+            action,
+        };
+        match daemon::request(&daemon_socket_path(), &request).await {
+            Ok(daemon::Response::Ok) => {
+                println!("{} {verb} sent to '{printer}'", Icon::Success);
+                return 0;
+            }
+            Ok(daemon::Response::Error { message }) => {
+                eprintln!("Error: {message}");
+                return EXIT_CONNECTION_ERROR;
+            }
+            // No daemon, or an unexpected answer: fall back to a direct connection
+            Ok(daemon::Response::Printers { .. }) | Err(_) => {}
+        }
+    }
+
+    let printer_config = match load_printer_config(name, &None, &None, &None) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("Error loading printer configuration: {e}");
+            return EXIT_CONFIG_ERROR;
+        }
+    };
+    let printer = printer_config.name.clone();
+
+    match confirm_command(printer_config, action.payload()).await {
+        Ok(_) => {
+            println!("{} {verb} accepted by '{printer}'", Icon::Success);
+            0
+        }
+        Err(e) => {
+            eprintln!("Error sending {} command: {e}", verb.to_lowercase());
+            EXIT_CONNECTION_ERROR
+        }
+    }
+}
+
+async fn handle_print_start(
+    name: &Option<String>,
+    project: mqtt::ProjectFile,
+    detach: bool,
+    options: &MonitorOptions,
+) -> i32 {
+    let printer_config = match load_printer_config(name, &None, &None, &None) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("Error loading printer configuration: {e}");
+            return EXIT_CONFIG_ERROR;
+        }
+    };
+    let printer = printer_config.name.clone();
+
+    if let Some(warning) = nozzle_mismatch(&printer_config, &project) {
+        eprintln!("{} {warning}", Icon::Alert);
+    }
+    if let Err(e) = confirm_command(printer_config.clone(), project.payload()).await {
+        eprintln!("Error starting print: {e}");
+        return EXIT_CONNECTION_ERROR;
+    }
+    println!(
+        "{} Started '{}' (plate {}) on '{printer}'",
+        Icon::Success,
+        project.path,
+        project.plate
+    );
+    if detach {
+        return 0;
+    }
+
+    match monitor_printer(printer_config, options).await {
+        Ok(_) => {
+            println!("Monitoring completed successfully");
+            0
+        }
+        Err(e) => {
+            eprintln!("Error monitoring printer: {e}");
+            EXIT_CONNECTION_ERROR
+        }
+    }
+}
+
+async fn handle_schedule(name: &Option<String>, at: &str, job: mqtt::ProjectFile) -> i32 {
+    let printer = match resolve_printer_name(name) {
+        Ok(printer) => printer,
+        Err(e) => {
+            eprintln!("Error loading printer configuration: {e}");
+            return EXIT_CONFIG_ERROR;
+        }
+    };
+    let at = match schedule::parse_start_time(at, chrono::Local::now()) {
+        Ok(at) => at,
+        Err(e) => {
+            eprintln!("Error: {e}");
+            return 2;
+        }
+    };
+    if let Ok(printer_config) = load_printer_config(&Some(printer.clone()), &None, &None, &None)
+        && let Some(warning) = nozzle_mismatch(&printer_config, &job)
+    {
+        eprintln!("{} {warning}", Icon::Alert);
+    }
+
+    let path = job.path.clone();
+    let request = daemon::Request::Schedule {
+        print: schedule::ScheduledPrint {
+            printer: printer.clone(),
+            at,
+            job,
+        },
+    };
+    match daemon::request(&daemon_socket_path(), &request).await {
+        Ok(daemon::Response::Ok) => {
+            println!(
+                "{} '{path}' will start on '{printer}' at {}",
+                Icon::Timer,
+                at.format("%Y-%m-%d %H:%M")
+            );
+            0
+        }
+        Ok(daemon::Response::Error { message }) => {
+            eprintln!("Error: {message}");
+            1
+        }
+        Ok(_) => {
+            eprintln!("Error: unexpected answer from the daemon");
+            1
+        }
+        Err(daemon::DaemonError::NotRunning) => {
+            eprintln!(
+                "Scheduled prints are started by the daemon; run `pulseprint-cli daemon start` first"
+            );
+            EXIT_CONNECTION_ERROR
+        }
+        Err(e) => {
+            eprintln!("Error contacting the daemon: {e}");
+            EXIT_CONNECTION_ERROR
+        }
+    }
+}
+
+/// Warning when the plate was sliced for another nozzle than the one the printer last reported
+fn nozzle_mismatch(printer: &config::PrinterConfig, file: &mqtt::ProjectFile) -> Option<String> {
+    let installed = printer.nozzle_diameter?;
+    let name = project::job_name(&file.path);
+    let project = project::lookup(&data_dir().join("projects"), name)?;
+    let sliced = project.plate(Some(file.plate))?.nozzle_diameter?;
+
+    ((sliced - installed).abs() > 0.001).then(|| {
+        format!(
+            "'{name}' was sliced for a {sliced} mm nozzle, but '{}' has a {installed} mm nozzle",
+            printer.name
+        )
+    })
+}
+
+async fn handle_files(command: &FilesCommand) -> i32 {
+    let name = match command {
+        FilesCommand::List { name, .. }
+        | FilesCommand::Upload { name, .. }
+        | FilesCommand::Download { name, .. }
+        | FilesCommand::Delete { name, .. } => name,
+    };
+    let printer_config = match load_printer_config(name, &None, &None, &None) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("Error loading printer configuration: {e}");
+            return EXIT_CONFIG_ERROR;
+        }
+    };
+
+    match run_files_command(command, &printer_config).await {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("Error: {e}");
+            EXIT_CONNECTION_ERROR
+        }
+    }
+}
+
+async fn run_files_command(
+    command: &FilesCommand,
+    config: &config::PrinterConfig,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut client = ftp::FtpsClient::connect(config).await?;
+
+    match command {
+        FilesCommand::List { path, .. } => {
+            for entry in client.list(path).await? {
+                if entry.is_dir {
+                    println!("{:>12}  {}/", "-", entry.name);
+                } else {
+                    println!("{:>12}  {}", entry.size, entry.name);
+                }
+            }
+        }
+        FilesCommand::Upload { file, to, .. } => {
+            let file_name = file
+                .file_name()
+                .ok_or_else(|| format!("{} is not a file", file.display()))?
+                .to_string_lossy();
+            let remote = to.clone().unwrap_or_else(|| format!("/{file_name}"));
+            let mut local = tokio::fs::File::open(file)
+                .await
+                .map_err(|e| format!("Cannot read {}: {e}", file.display()))?;
+            // Remember sliced projects so their jobs get the slicer metadata in reports
+            if let Ok(project) = project::ProjectInfo::read(file) {
+                let _ = project::remember(&data_dir().join("projects"), &project);
+            }
+            let sent = client.upload(&remote, &mut local).await?;
+            println!(
+                "{} Uploaded {} ({sent} bytes) to '{remote}' on '{}'",
+                Icon::Success,
+                file.display(),
+                config.name
+            );
+        }
+        FilesCommand::Download { path, out, .. } => {
+            let out = match out {
+                Some(out) => out.clone(),
+                None => std::path::PathBuf::from(
+                    path.rsplit('/')
+                        .find(|part| !part.is_empty())
+                        .ok_or_else(|| format!("'{path}' is not a file"))?,
+                ),
+            };
+            let mut local = tokio::fs::File::create(&out)
+                .await
+                .map_err(|e| format!("Cannot write {}: {e}", out.display()))?;
+            let received = client.download(path, &mut local).await?;
+            println!(
+                "{} Downloaded '{path}' ({received} bytes) to {}",
+                Icon::Success,
+                out.display()
+            );
+        }
+        FilesCommand::Delete { path, .. } => {
+            client.delete(path).await?;
+            println!("{} Deleted '{path}' on '{}'", Icon::Success, config.name);
+        }
+    }
+
+    client.quit().await?;
+    Ok(())
+}
+
+async fn handle_ams_info(name: &Option<String>) -> i32 {
+    let printer_config = match load_printer_config(name, &None, &None, &None) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("Error loading printer configuration: {e}");
+            return EXIT_CONFIG_ERROR;
+        }
+    };
+    let printer = printer_config.name.clone();
+    let options = MonitorOptions {
+        once: true,
+        quiet: true,
+        ..Default::default()
+    };
+    let state = match monitor_printer(printer_config, &options).await {
+        Ok(state) => state,
+        Err(e) => {
+            eprintln!("Error querying printer status: {e}");
+            return EXIT_CONNECTION_ERROR;
+        }
+    };
+
+    let mut lines: Vec<(String, String)> = Vec::new();
+    for unit in ams_units(&state.print) {
+        lines.push((String::new(), ams_unit_summary(unit)));
+        for tray in &unit.trays {
+            let label = filament::tray_label(unit.id.as_deref(), tray.id.as_deref())
+                .unwrap_or_else(|| "?".to_string());
+            lines.push((label, tray_summary(tray)));
+        }
+    }
+    if let Some(tray) = &state.print.vt_tray {
+        lines.push((filament::EXTERNAL_TRAY.to_string(), tray_summary(tray)));
+    }
+    if lines.is_empty() {
+        eprintln!("'{printer}' did not report an AMS or external spool");
+        return 1;
+    }
+
+    let rows: Vec<(&str, String)> = lines
+        .iter()
+        .map(|(label, value)| (label.as_str(), value.clone()))
+        .collect();
+    print!(
+        "{}",
+        display::render_panel(&format!("{printer} - filament"), &rows)
+    );
+    0
+}
+
+async fn handle_info(name: &str, output: OutputFormat) -> i32 {
+    let printer_config = match load_printer_config(&Some(name.to_string()), &None, &None, &None) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("Error loading printer configuration: {e}");
+            return EXIT_CONFIG_ERROR;
+        }
+    };
+    let printer = printer_config.name.clone();
+    let configured_model = printer_config.model.clone();
+    let mut report = match query_versions(printer_config).await {
+        Ok(report) => report,
+        Err(e) => {
+            eprintln!("Error querying device info: {e}");
+            return EXIT_CONNECTION_ERROR;
+        }
+    };
+    // Older firmware leaves out the product name
+    if report.model.is_none() {
+        report.model = configured_model;
+    }
+
+    match output {
+        OutputFormat::Json => match serde_json::to_string_pretty(&report) {
+            Ok(json) => println!("{json}"),
+            Err(e) => {
+                eprintln!("Error encoding device info: {e}");
+                return 1;
+            }
+        },
+        OutputFormat::Text => print!("{}", render_device_info(&printer, &report)),
+    }
+    0
+}
+
+/// Device details over the firmware of each module, e.g. `mc  00.00.30.12 (hardware MC07)`
+fn render_device_info(printer: &str, report: &messages::VersionReport) -> String {
+    let unknown = || "unknown".to_string();
+    let mut rows: Vec<(&str, String)> = vec![
+        ("Model", report.model.clone().unwrap_or_else(unknown)),
+        ("Serial", report.serial.clone().unwrap_or_else(unknown)),
+        (
+            "Hardware",
+            report.hardware_revision.clone().unwrap_or_else(unknown),
+        ),
+        ("Firmware", report.firmware.clone().unwrap_or_else(unknown)),
+    ];
+    for module in &report.modules {
+        let mut value = module.firmware.clone().unwrap_or_else(unknown);
+        let details: Vec<String> = [
+            module.hardware.as_ref().map(|hw| format!("hardware {hw}")),
+            module.serial.as_ref().map(|sn| format!("serial {sn}")),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        if !details.is_empty() {
+            value.push_str(&format!(" ({})", details.join(", ")));
+        }
+        rows.push((&module.name, value));
+    }
+    display::render_panel(&format!("{printer} - device info"), &rows)
+}
+
+/// Sends `get_version` and waits for the printer's answer on the report topic
+async fn query_versions(
+    config: config::PrinterConfig,
+) -> Result<messages::VersionReport, Box<dyn std::error::Error>> {
+    let response = confirm_command(config, mqtt::get_version_payload()).await?;
+    let message: messages::DeviceMessage = serde_json::from_value(response.report)?;
+    message
+        .info
+        .and_then(|info| info.version_report())
+        .ok_or_else(|| "The printer's answer holds no version details".into())
+}
+
+/// The spool feeding the printer, e.g. `External PETG 60%`
+fn active_filament(print: &messages::PrintInfo) -> Option<String> {
+    let tray = print.active_tray()?;
+    let mut parts = vec![filament::active_tray_label(print)?];
+    parts.extend(tray.tray_type.clone().filter(|kind| !kind.is_empty()));
+    // Spools without an RFID tag report -1
+    if let Some(remain) = tray.remain.and_then(|remain| u32::try_from(remain).ok()) {
+        parts.push(display::format_filament_remain(
+            remain,
+            display::filament_low_percent(),
+        ));
+    }
+    Some(parts.join(" "))
+}
+
+/// `PLA  #FFFFFF  80%  K 0.020  n 1.40 (default)`, or `empty`
+fn tray_summary(tray: &messages::AmsTray) -> String {
+    let Some(material) = tray.tray_type.as_deref().filter(|kind| !kind.is_empty()) else {
+        return "empty".to_string();
+    };
+
+    let mut parts = vec![material.to_string()];
+    // Colors are RGBA; the alpha channel is always opaque
+    if let Some(color) = tray.tray_color.as_deref().and_then(|color| color.get(..6)) {
+        match display::swatch(color) {
+            Some(swatch) => parts.push(format!("{swatch} #{color}")),
+            None => parts.push(format!("#{color}")),
+        }
+    }
+    // Spools without an RFID tag report -1
+    if let Some(remain) = tray.remain.and_then(|remain| u32::try_from(remain).ok()) {
+        parts.push(display::format_filament_remain(
+            remain,
+            display::filament_low_percent(),
+        ));
+    }
+    if let Some(k) = tray.k {
+        parts.push(format!("K {k:.3}"));
+    }
+    if let Some(n) = tray.n {
+        parts.push(format!("n {n:.2}"));
+    }
+    let mut summary = parts.join("  ");
+    match tray.cali_idx {
+        Some(-1) => summary.push_str(" (default)"),
+        Some(profile) => summary.push_str(&format!(" (calibration #{profile})")),
+        None => {}
+    }
+    summary
+}
+
+fn handle_inspect(
+    file: &std::path::Path,
+    thumbnails: Option<&std::path::Path>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let project = project::ProjectInfo::read(file)?;
+
+    for (position, plate) in project.plates.iter().enumerate() {
+        if position > 0 {
+            println!();
+        }
+        let mut rows = Vec::new();
+        if let Some(secs) = plate.estimated_secs {
+            rows.push(("Estimated time", display::format_duration_short(secs)));
+        }
+        if let Some(weight) = plate.total_weight_g() {
+            rows.push(("Filament", format!("{weight:.1} g")));
+        }
+        for filament in &plate.filaments {
+            let mut parts = vec![format!("#{}", filament.id)];
+            parts.extend(filament.material.clone());
+            parts.extend(filament.color.clone());
+            if let Some(meters) = filament.used_m {
+                parts.push(format!("{meters:.2} m"));
+            }
+            if let Some(grams) = filament.used_g {
+                parts.push(format!("{grams:.1} g"));
+            }
+            rows.push(("", parts.join("  ")));
+        }
+        if !plate.objects.is_empty() {
+            let ids: Vec<String> = plate.objects.iter().map(|o| o.id.to_string()).collect();
+            rows.push((
+                "Objects",
+                format!("{} (ids {})", plate.objects.len(), ids.join(", ")),
+            ));
+        }
+        if let Some(thumbnail) = &plate.thumbnail {
+            rows.push(("Thumbnail", thumbnail.clone()));
+        }
+        let title = format!("{} - plate {}", project.name, plate.index);
+        print!("{}", display::render_panel(&title, &rows));
+    }
+
+    if let Some(dir) = thumbnails {
+        std::fs::create_dir_all(dir)?;
+        for plate in project
+            .plates
+            .iter()
+            .filter(|plate| plate.thumbnail.is_some())
+        {
+            let out = dir.join(format!("{}_plate_{}.png", project.name, plate.index));
+            std::fs::write(&out, project::read_thumbnail(file, plate.index)?)?;
+            println!("{} Wrote {}", Icon::Success, out.display());
+        }
+    }
+
+    // Jobs printed from this project pick the metadata up by name
+    project::remember(&data_dir().join("projects"), &project)?;
+    Ok(())
+}
+
+async fn handle_skip_objects(name: &Option<String>, ids: &[u32]) -> i32 {
+    let printer_config = match load_printer_config(name, &None, &None, &None) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("Error loading printer configuration: {e}");
+            return EXIT_CONFIG_ERROR;
+        }
+    };
+    let printer = printer_config.name.clone();
+    let list: Vec<String> = ids.iter().map(u32::to_string).collect();
+
+    match confirm_command(printer_config, mqtt::skip_objects_payload(ids)).await {
+        Ok(_) => {
+            println!(
+                "{} Skipping object(s) {} on '{printer}'",
+                Icon::Success,
+                list.join(", ")
+            );
+            0
+        }
+        Err(e) => {
+            eprintln!("Error sending skip command: {e}");
+            EXIT_CONNECTION_ERROR
+        }
+    }
+}
+
+/// Lists the objects of the running job from the metadata remembered by `inspect`
+async fn handle_list_objects(name: &Option<String>) -> i32 {
+    let printer_config = match load_printer_config(name, &None, &None, &None) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("Error loading printer configuration: {e}");
+            return EXIT_CONFIG_ERROR;
+        }
+    };
+    let options = MonitorOptions {
+        once: true,
+        quiet: true,
+        ..Default::default()
+    };
+    let state = match monitor_printer(printer_config, &options).await {
+        Ok(state) => state,
+        Err(e) => {
+            eprintln!("Error querying printer status: {e}");
+            return EXIT_CONNECTION_ERROR;
+        }
+    };
+
+    let Some(job) = state.print.subtask_name.as_deref() else {
+        eprintln!("The printer did not report a current job");
+        return 1;
+    };
+    let Some(project) = project::lookup(&data_dir().join("projects"), job) else {
+        eprintln!("No metadata for '{job}'; run `inspect` on its project file first");
+        return 1;
+    };
+    let plate_index = state
+        .print
+        .extra
+        .get("gcode_file")
+        .and_then(serde_json::Value::as_str)
+        .and_then(project::plate_index);
+    let Some(plate) = project.plate(plate_index) else {
+        eprintln!("Could not tell which plate of '{job}' is printing");
+        return 1;
+    };
+
+    let skipped = state.print.skipped_objects();
+    let rows: Vec<(&str, String)> = plate
+        .objects
+        .iter()
+        .map(|object| {
+            let note = if skipped.contains(&object.id) {
+                " (skipped)"
+            } else {
+                ""
+            };
+            ("", format!("{:>6}  {}{note}", object.id, object.name))
+        })
+        .collect();
+    print!(
+        "{}",
+        display::render_panel(&format!("{job} - plate {}", plate.index), &rows)
+    );
+    0
+}
+
+async fn handle_snapshot(
+    name: &Option<String>,
+    out: &std::path::Path,
+    camera: Option<camera::CameraKind>,
+) -> i32 {
+    let printer_config = match load_printer_config(name, &None, &None, &None) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("Error loading printer configuration: {e}");
+            return EXIT_CONFIG_ERROR;
+        }
+    };
+    let kind = camera.unwrap_or_else(|| camera::CameraKind::detect(&printer_config));
+
+    let image = match camera::snapshot(&printer_config, kind).await {
+        Ok(image) => image,
+        Err(e) => {
+            eprintln!(
+                "Error grabbing a snapshot from '{}': {e}",
+                printer_config.name
+            );
+            return EXIT_CONNECTION_ERROR;
+        }
+    };
+    if let Err(e) = camera::save(&image, out) {
+        eprintln!("Error writing {}: {e}", out.display());
+        return 1;
+    }
+
+    println!(
+        "{} Saved snapshot of '{}' to {}",
+        Icon::Success,
+        printer_config.name,
+        out.display()
+    );
+    0
+}
+
+/// Sends a raw request payload, and with a `timeout` prints the printer's response to it
+async fn handle_publish(
+    name: &str,
+    json: Option<&str>,
+    file: Option<&std::path::Path>,
+    timeout: Option<std::time::Duration>,
+) -> i32 {
+    let text = match (json, file) {
+        (Some(json), _) => Ok(json.to_string()),
+        (None, Some(path)) if path != std::path::Path::new("-") => std::fs::read_to_string(path),
+        (None, _) => std::io::read_to_string(std::io::stdin()),
+    };
+    let payload: serde_json::Value = match text {
+        Ok(text) => match serde_json::from_str(&text) {
+            Ok(payload) => payload,
+            Err(e) => {
+                eprintln!("Invalid JSON payload: {e}");
+                return 1;
+            }
+        },
+        Err(e) => {
+            eprintln!("Error reading the payload: {e}");
+            return 1;
+        }
+    };
+    let (group, command) = match requests::request_command(&payload) {
+        Ok((group, command)) => (group.to_string(), command.to_string()),
+        Err(e) => {
+            eprintln!("Invalid request: {e}");
+            return 1;
+        }
+    };
+
+    let printer_config = match load_printer_config(&Some(name.to_string()), &None, &None, &None) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("Error loading printer configuration: {e}");
+            return EXIT_CONFIG_ERROR;
+        }
+    };
+    let printer = printer_config.name.clone();
+
+    let Some(timeout) = timeout else {
+        return match send_request(printer_config, payload).await {
+            Ok(()) => {
+                println!(
+                    "{} Sent {group} command '{command}' to '{printer}'",
+                    Icon::Success
+                );
+                0
+            }
+            Err(e) => {
+                eprintln!("Error sending the request: {e}");
+                EXIT_CONNECTION_ERROR
+            }
+        };
+    };
+
+    match send_command(printer_config, payload, timeout).await {
+        Ok(response) => {
+            let report = &response.report;
+            println!(
+                "{}",
+                serde_json::to_string_pretty(report).unwrap_or_else(|_| report.to_string())
+            );
+            match response.failure() {
+                Some(reason) => {
+                    eprintln!("The printer rejected '{command}': {reason}");
+                    1
+                }
+                None => 0,
+            }
+        }
+        Err(e) => {
+            eprintln!("Error waiting for the response: {e}");
+            EXIT_CONNECTION_ERROR
+        }
+    }
+}
+
+/// Sends a command on a short-lived connection and waits up to `timeout` for the printer's
+/// answer, which may still report that the command failed
+async fn send_command(
+    config: config::PrinterConfig,
+    payload: serde_json::Value,
+    timeout: std::time::Duration,
+) -> Result<requests::CommandResponse, Box<dyn std::error::Error>> {
+    use mqtt::MessageSource;
+
+    let mut mqtt_client = mqtt::MqttClient::new(std::sync::Arc::new(config)).await?;
+    mqtt_client.connect().await?;
+    let requests = mqtt_client.requests();
+    let control = mqtt_client.control();
+    let mut eventloop = mqtt_client.get_eventloop();
+
+    // The event loop has to run for the request to go out and its answer to come in
+    let reports = async {
+        while let Some(publish) = eventloop.next_publish().await? {
+            requests.resolve(&publish.payload);
+        }
+        Err::<(), Box<dyn std::error::Error>>("Connection closed by the printer".into())
+    };
+    tokio::select! {
+        response = control.request(payload, timeout) => Ok(response?),
+        Err(e) = reports => Err(e),
+    }
+}
+
+/// [`send_command`] for commands that must be confirmed: a refusal is an error
+async fn confirm_command(
+    config: config::PrinterConfig,
+    payload: serde_json::Value,
+) -> Result<requests::CommandResponse, Box<dyn std::error::Error>> {
+    let response = send_command(config, payload, requests::RESPONSE_TIMEOUT).await?;
+    Ok(response.into_result()?)
+}
+
+async fn handle_timelapse(name: &Option<String>, enabled: bool) -> i32 {
+    let printer_config = match load_printer_config(name, &None, &None, &None) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("Error loading printer configuration: {e}");
+            return EXIT_CONFIG_ERROR;
+        }
+    };
+    let printer = printer_config.name.clone();
+    let setting = if enabled { "enabled" } else { "disabled" };
+
+    match set_timelapse(printer_config, enabled).await {
+        Ok(()) => {
+            println!("{} Timelapse {setting} on '{printer}'", Icon::Success);
+            0
+        }
+        Err(e) => {
+            eprintln!("Error changing the timelapse setting: {e}");
+            EXIT_CONNECTION_ERROR
+        }
+    }
+}
+
+/// Sends the timelapse command and waits until the report stream shows the new setting
+async fn set_timelapse(
+    config: config::PrinterConfig,
+    enabled: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    use mqtt::MessageSource;
+    const CONFIRM_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(15);
+
+    let mut mqtt_client = mqtt::MqttClient::new(std::sync::Arc::new(config)).await?;
+    mqtt_client.connect().await?;
+    mqtt_client.control().set_timelapse(enabled).await?;
+    mqtt_client.request_pushall().await?;
+    let mut eventloop = mqtt_client.get_eventloop();
+
+    let mut state = messages::MergedState::default();
+    let confirmed = async {
+        while let Some(publish) = eventloop.next_publish().await? {
+            let Ok(message) = messages::DeviceMessage::parse_bytes(&publish.payload) else {
+                continue;
+            };
+            state.update(&message);
+            // A status push from before the command may still show the old setting
+            if state.print.timelapse_enabled() == Some(enabled) {
+                return Ok(());
+            }
+        }
+        Err::<(), Box<dyn std::error::Error>>("Connection closed by the printer".into())
+    };
+    match tokio::time::timeout(CONFIRM_TIMEOUT, confirmed).await {
+        Ok(result) => result,
+        Err(_) => Err("Timed out waiting for the printer to confirm the new setting".into()),
+    }
+}
+
+/// Publishes one command on a short-lived connection, waiting until it has been flushed
+async fn send_request(
+    config: config::PrinterConfig,
+    payload: serde_json::Value,
+) -> Result<(), Box<dyn std::error::Error>> {
+    const SEND_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+    let mqtt_client = mqtt::MqttClient::new(std::sync::Arc::new(config)).await?;
+    mqtt_client.control().publish(payload).await?;
+    let mut eventloop = mqtt_client.get_eventloop();
+
+    // The publish is only queued until the event loop has connected and flushed it
+    match tokio::time::timeout(SEND_TIMEOUT, eventloop.flush_publish()).await {
+        Ok(result) => result,
+        Err(_) => Err("Timed out waiting for the printer".into()),
+    }
+}
+
+fn handle_clone_printer(
+    existing: &str,
+    new_name: &str,
+    ip: &str,
+    device_id: &str,
+    access_code: &Option<String>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    config::validate_ip_address(ip)?;
+    config::validate_device_id(device_id)?;
+    if let Some(access_code) = access_code {
+        config::validate_access_code(access_code)?;
+    }
+
+    let config_path = config::AppConfig::get_config_path();
+    let mut app_config = config::AppConfig::load_from_file(&config_path)?;
+    let source = app_config.get_printer(existing)?;
+    let source_name = source.name.clone();
+    let mut printer = source.clone_as(new_name.to_string(), ip.to_string(), device_id.to_string());
+    if let Some(access_code) = access_code {
+        printer.access_code = access_code.clone();
+    }
+
+    app_config.add_printer(new_name.to_string(), printer)?;
+    app_config.save_to_file(&config_path)?;
+
+    println!(
+        "{} Printer '{new_name}' added with the settings of '{source_name}'",
+        Icon::Success
+    );
+    if access_code.is_none() {
+        println!(
+            "{} It uses the access code of '{source_name}'; pass --access-code if it differs",
+            Icon::Hint
+        );
+    }
+    Ok(())
+}
+
+fn handle_remove_printer(name: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let config_path = config::AppConfig::get_config_path();
+    let mut app_config = config::AppConfig::load_from_file(&config_path)?;
+
+    // Remove printer
+    let removed_printer = app_config.remove_printer(name)?;
+
+    // Save config
+    app_config.save_to_file(&config_path)?;
+
+    let removed_name = &removed_printer.name;
+    println!(
+        "{} Printer '{removed_name}' removed successfully",
+        Icon::Removed
+    );
+
+    // Show message if this was the default printer
+    if app_config.default_printer.is_none() && !app_config.printers.is_empty() {
+        let first_printer = app_config.printers.keys().next().unwrap();
+        println!(
+            "{} Consider setting a new default printer with: set-default {first_printer}",
+            Icon::Hint
+        );
+    }
+
+    Ok(())
+}
+
+fn handle_set_default_printer(name: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let config_path = config::AppConfig::get_config_path();
+    let mut app_config = config::AppConfig::load_from_file(&config_path)?;
+
+    // Set default printer
+    app_config.set_default_printer(name)?;
+
+    // Save config
+    app_config.save_to_file(&config_path)?;
+
+    println!("{} Printer '{name}' set as default", Icon::Default);
+
+    Ok(())
+}
+
+/// Jobs in the configured history store, oldest first, optionally only those of `printer`
+/// or those that ended since `since`. Listed without filters, a job's id is its 1-based
+/// position.
+async fn saved_jobs(
+    printer: Option<&str>,
+    since: Option<chrono::DateTime<chrono::Utc>>,
+) -> Result<Vec<(String, jobs::JobRecord)>, Box<dyn std::error::Error>> {
+    let app_config = config::AppConfig::load(&config::AppConfig::get_config_path())?;
+    let store = history_store(&app_config).await?.ok_or(
+        "The daemon keeps its job history in memory only. Set `backend = \"file\"` or `\"postgres\"` under [history] so it saves finished jobs.",
+    )?;
+    let printer = printer.map(str::to_string);
+    Ok(tokio::task::spawn_blocking(move || store.entries(printer.as_deref(), since)).await??)
+}
+
+async fn handle_history_list(
+    printer: Option<&str>,
+    limit: usize,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let entries = saved_jobs(None, None).await?;
+    let jobs: Vec<_> = entries
+        .iter()
+        .enumerate()
+        .filter(|(_, (name, _))| printer.is_none_or(|printer| printer == name))
+        .collect();
+    if jobs.is_empty() {
+        println!("No saved jobs");
+        return Ok(());
+    }
+
+    for (index, (name, job)) in &jobs[jobs.len().saturating_sub(limit)..] {
+        let outcome = match job.final_state {
+            messages::PrintState::Failed => "failed",
+            _ => "finished",
+        };
+        println!(
+            "{:>5}  {}  {name}  {outcome:<8}  {} ({})",
+            index + 1,
+            job.started_at.format("%Y-%m-%d %H:%M"),
+            job.name.as_deref().unwrap_or("Untitled job"),
+            display::format_duration_short(job.elapsed_secs())
+        );
+    }
+    Ok(())
+}
+
+async fn handle_history_chart(
+    id: usize,
+    out: &std::path::Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let entries = saved_jobs(None, None).await?;
+    let (printer, job) = id
+        .checked_sub(1)
+        .and_then(|index| entries.get(index))
+        .ok_or_else(|| format!("No job with id {id}; see `history list`"))?;
+    let series = chart::JobSeries::from_samples(&job.telemetry);
+
+    let title = format!(
+        "{printer}: {} ({})",
+        job.name.as_deref().unwrap_or("Untitled job"),
+        job.started_at.format("%Y-%m-%d %H:%M")
+    );
+    chart::render(&series, &title, out)?;
+
+    println!(
+        "{} Chart for {} samples written to {}",
+        Icon::Success,
+        job.telemetry.len(),
+        out.display()
+    );
+    Ok(())
+}
+
+fn handle_history_export(
+    job: &std::path::Path,
+    format: ExportFormat,
+    out: Option<&std::path::Path>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    use std::io::Write;
+
+    let samples = telemetry::session_samples(&recording::read_session(job)?);
+    let text_writer = || -> std::io::Result<Box<dyn Write>> {
+        Ok(match out {
+            Some(path) => Box::new(std::io::BufWriter::new(std::fs::File::create(path)?)),
+            None => Box::new(std::io::stdout().lock()),
+        })
+    };
+    match format {
+        ExportFormat::Csv => telemetry::write_csv(&samples, text_writer()?)?,
+        ExportFormat::Ndjson => {
+            let mut writer = text_writer()?;
+            for sample in &samples {
+                serde_json::to_writer(&mut writer, sample)?;
+                writeln!(writer)?;
+            }
+            writer.flush()?;
+        }
+        #[cfg(feature = "parquet")]
+        ExportFormat::Parquet => {
+            let path = out.ok_or("Parquet output needs --out")?;
+            telemetry::write_parquet(&samples, std::fs::File::create(path)?)?;
+        }
+    }
+
+    if let Some(path) = out {
+        println!(
+            "{} {} samples written to {}",
+            Icon::Success,
+            samples.len(),
+            path.display()
+        );
+    }
+    Ok(())
+}
+
+/// The daemon's job history store chosen under [history]
+async fn history_store(
+    app_config: &config::AppConfig,
+) -> Result<Option<std::sync::Arc<dyn history::HistoryStore>>, Box<dyn std::error::Error>> {
+    Ok(match app_config.history.backend {
+        config::HistoryBackend::Memory => None,
+        config::HistoryBackend::File => Some(std::sync::Arc::new(history::FileHistory::new(
+            &history_path(app_config),
+        ))),
+        #[cfg(feature = "postgres")]
+        config::HistoryBackend::Postgres => {
+            let url = app_config
+                .history
+                .url
+                .clone()
+                .ok_or("Set `url` under [history] to use the postgres backend")?;
+            let store =
+                tokio::task::spawn_blocking(move || history::PostgresHistory::connect(&url))
+                    .await??;
+            Some(std::sync::Arc::new(store))
+        }
+        #[cfg(not(feature = "postgres"))]
+        config::HistoryBackend::Postgres => {
+            return Err(
+                "This build has no PostgreSQL support; rebuild with `--features postgres`".into(),
+            );
+        }
+    })
+}
+
+/// File of the daemon's job history with the `file` backend
+fn history_path(app_config: &config::AppConfig) -> std::path::PathBuf {
+    app_config
+        .history
+        .path
+        .clone()
+        .unwrap_or_else(|| data_dir().join("history.jsonl"))
+}
+
+async fn handle_stats(
+    printer: Option<&str>,
+    since: Option<std::time::Duration>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    // Periods reaching before the earliest representable time cover every job
+    let cutoff = since
+        .and_then(|since| chrono::Duration::from_std(since).ok())
+        .and_then(|since| chrono::Local::now().checked_sub_signed(since));
+    let entries = saved_jobs(printer, cutoff.map(|cutoff| cutoff.to_utc())).await?;
+    let stats = stats::JobStats::compute(entries.iter().map(|(_, job)| job));
+
+    let mut title = printer.unwrap_or("All printers").to_string();
+    if let Some(cutoff) = cutoff {
+        title.push_str(&format!(" since {}", cutoff.format("%Y-%m-%d %H:%M")));
+    }
+    if stats.jobs == 0 {
+        println!("{title}: no jobs");
+        return Ok(());
+    }
+
+    let mut rows = vec![("Jobs", stats.jobs.to_string())];
+    if let Some(rate) = stats.success_rate() {
+        rows.push((
+            "Success rate",
+            format!(
+                "{rate:.1}% ({} finished, {} failed)",
+                stats.finished, stats.failed
+            ),
+        ));
+    }
+    if let Some(secs) = stats.average_duration_secs {
+        rows.push(("Average duration", display::format_duration_short(secs)));
+    }
+    rows.push(("Filament", format!("{:.1} g", stats.filament_g)));
+    print!("{}", display::render_panel(&title, &rows));
+
+    if !stats.failure_reasons.is_empty() {
+        println!();
+        println!("Most common failure reasons:");
+        for (reason, count) in &stats.failure_reasons {
+            println!("  {count:>4}  {reason}");
+        }
+    }
+    println!();
+    println!("Job starts by weekday and hour:");
+    print!("{}", stats.heatmap());
+    Ok(())
+}
+
+async fn handle_monthly_report(
+    month: chrono::NaiveDate,
+    out: &std::path::Path,
+    email: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let since = report::MonthlyReport::start(month).to_utc();
+    let entries = saved_jobs(None, Some(since)).await?;
+
+    let report = report::MonthlyReport::compile(month, &entries);
+    let format = match out.extension().and_then(|extension| extension.to_str()) {
+        Some(extension)
+            if extension.eq_ignore_ascii_case("html") || extension.eq_ignore_ascii_case("htm") =>
+        {
+            config::ReportFormat::Html
+        }
+        _ => config::ReportFormat::Markdown,
+    };
+    let document = report.render(format);
+    std::fs::write(out, &document)?;
+    println!(
+        "{} Report for {} written to {}",
+        Icon::Success,
+        report.title(),
+        out.display()
+    );
+
+    if email {
+        email_report(
+            &format!("Print report for {}", report.title()),
+            document,
+            format,
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(feature = "email")]
+fn email_report(
+    subject: &str,
+    document: String,
+    format: config::ReportFormat,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let app_config = config::AppConfig::load(&config::AppConfig::get_config_path())?;
+    let settings = app_config
+        .email
+        .ok_or("Set up [email] in the config file to send reports")?;
+    pulseprint_cli::email::EmailNotifier::new(&settings)?.send(subject, document, format)?;
+    println!(
+        "{} Report sent to {}",
+        Icon::Success,
+        settings.to.join(", ")
+    );
+    Ok(())
+}
+
+#[cfg(not(feature = "email"))]
+fn email_report(
+    _subject: &str,
+    _document: String,
+    _format: config::ReportFormat,
+) -> Result<(), Box<dyn std::error::Error>> {
+    Err("This build has no email support; rebuild with `--features email`".into())
+}
+
+fn handle_parse_check(
+    path: &std::path::Path,
+    verbose: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    const MAX_FAILURES_SHOWN: usize = 10;
+
+    let entries = corpus::load_entries(path)?;
+    if entries.is_empty() {
+        return Err(format!("No payloads found in {}", path.display()).into());
+    }
+
+    let mut report = corpus::CorpusReport::default();
+    for entry in &entries {
+        report.check(entry);
+    }
+
+    println!("Parse Check Results:");
+    println!("====================");
+    println!("   Payloads: {}", report.total);
+    println!("   Parsed: {}", report.parsed);
+    println!("   Failed: {}", report.failures.len());
+
+    if !report.message_types.is_empty() {
+        println!();
+        println!("{} Message types:", Icon::Info);
+        for (message_type, count) in &report.message_types {
+            println!("   {message_type}: {count}");
+        }
+    }
+
+    let unknown_fields = report.unknown_fields_by_frequency();
+    if !unknown_fields.is_empty() {
+        println!();
+        println!(
+            "{} Fields not covered by the parser (landed in extra):",
+            Icon::Unknown
+        );
+        for (field, count) in unknown_fields {
+            println!("   {field}: {count}");
+        }
+    }
+
+    if !report.failures.is_empty() {
+        println!();
+        println!("{} Failures:", Icon::Failure);
+        let shown = if verbose {
+            report.failures.len()
+        } else {
+            MAX_FAILURES_SHOWN
+        };
+        for (source, error) in report.failures.iter().take(shown) {
+            println!("   {source}: {error}");
+        }
+        if report.failures.len() > shown {
+            println!(
+                "   ... and {} more (use --verbose to show all)",
+                report.failures.len() - shown
+            );
+        }
+    }
+
+    Ok(())
+}
+
+fn load_printer_config(
+    name: &Option<String>,
+    ip: &Option<String>,
+    device_id: &Option<String>,
+    access_code: &Option<String>,
+) -> Result<config::PrinterConfig, Box<dyn std::error::Error>> {
+    // If all manual parameters are provided, use them directly
+    if let (Some(ip), Some(device_id), Some(access_code)) = (ip, device_id, access_code) {
+        config::validate_ip_address(ip)?;
+        config::validate_device_id(device_id)?;
+        config::validate_access_code(access_code)?;
+
+        return Ok(config::PrinterConfig::new(
+            name.clone().unwrap_or_else(|| "manual".to_string()),
+            ip.clone(),
+            device_id.clone(),
+            access_code.clone(),
+        ));
+    }
+
+    // Otherwise, load from config
+    let config_path = config::AppConfig::get_config_path();
+    let app_config = config::AppConfig::load(&config_path)?;
+
+    // Determine which printer to use
+    let printer_config = match name {
+        Some(printer_name) => {
+            // Use specified printer
+            app_config.get_printer(printer_name)?.clone()
+        }
+        None => {
+            // Use default printer
+            if app_config.printers.is_empty() {
+                return Err("No printers configured. Use 'add' command to add a printer.".into());
+            }
+            app_config.get_default_printer()?.clone()
+        }
+    };
+
+    // Apply any overrides
+    let mut final_config = printer_config;
+    if let Some(ip) = ip {
+        config::validate_ip_address(ip)?;
+        final_config.ip = ip.clone();
+    }
+    if let Some(device_id) = device_id {
+        config::validate_device_id(device_id)?;
+        final_config.device_id = device_id.clone();
+    }
+    if let Some(access_code) = access_code {
+        config::validate_access_code(access_code)?;
+        final_config.access_code = access_code.clone();
+    }
+
+    Ok(final_config)
+}
+
+/// Behaviour switches for the monitor pipeline
+#[derive(Debug, Clone, Default)]
+struct MonitorOptions {
+    dedupe: bool,
+    progress_bar: bool,
+    once: bool,
+    duration: Option<std::time::Duration>,
+    until_done: bool,
+    /// Print nothing to stdout; the caller reports the final state itself
+    quiet: bool,
+    /// Redraw a full-screen status panel at this interval instead of printing each message
+    refresh: Option<std::time::Duration>,
+    /// Write a report file when a print finishes or fails
+    reports: Option<config::ReportSettings>,
+    /// Attach an estimated cost to completed jobs
+    costs: Option<config::CostSettings>,
+    /// Raise alerts when the configured conditions are met
+    alerts: Option<config::AlertSettings>,
+    /// Publish status updates, alerts and finished jobs to the configured sinks
+    events: Option<events::EventBus>,
+    /// Materials of the watched job, for the door alert
+    materials: Vec<String>,
+    /// When to reconnect after a failure; `None` takes the `[mqtt_settings]` defaults
+    reconnect: Option<std::sync::Arc<dyn mqtt::ReconnectPolicy>>,
+    /// Log every message received, with `log_messages` on
+    message_log: Option<config::MessageLogSettings>,
+}
+
+impl MonitorOptions {
+    fn message_log(&self, config: &config::PrinterConfig) -> Option<logging::MessageLog> {
+        let settings = self.message_log.as_ref()?;
+        Some(logging::MessageLog::new(
+            settings,
+            &data_dir().join("messages"),
+            &config.name,
+        ))
+    }
+
+    fn reconnect_policy(&self) -> std::sync::Arc<dyn mqtt::ReconnectPolicy> {
+        self.reconnect
+            .clone()
+            .unwrap_or_else(|| mqtt::reconnect_policy(&config::MqttSettings::default()))
+    }
+}
+
+/// Event bus for the `[[sinks]]` of the config, if there are any
+fn start_event_bus(sinks: &[config::SinkSettings]) -> Option<events::EventBus> {
+    (!sinks.is_empty()).then(|| events::EventBus::start(sinks))
+}
+
+/// Gives the sinks a chance to deliver the last events before the process exits
+async fn finish_event_bus(options: &MonitorOptions) {
+    if let Some(bus) = &options.events {
+        bus.finish().await;
+    }
+}
+
+/// What the monitor pipeline hands its subscribers, in the order it observes it
+#[derive(Debug)]
+enum PipelineEvent<'a> {
+    /// The merged state after every status update
+    Status(&'a messages::MergedState),
+    /// A change in the print job seen by the job tracker
+    Transition(&'a jobs::Transition),
+    /// An alert was just raised; `pause` asks for the print to be paused because of it
+    Raised {
+        alert: &'a alerts::Alert,
+        pause: bool,
+    },
+    /// A raised alert ready to go out, with the camera still taken for it
+    Alert {
+        alert: &'a alerts::Alert,
+        snapshot: Option<&'a std::path::Path>,
+    },
+    /// An HMS code set to `log`, which only shows on the console
+    Notice(&'a alerts::Alert),
+    Inspection(&'a jobs::InspectionResult),
+    /// A job ended, with its plate and cost filled in
+    JobFinished(&'a jobs::JobRecord),
+}
+
+/// How the monitor pipeline uses stdout
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ConsoleOutput {
+    Plain,
+    /// Other printers print to the same console, so job summaries carry the printer name
+    Tagged,
+    /// Keeps job summaries, alerts and other notices off stdout, which carries JSON
+    Off,
+}
+
+/// Turns the merged state into [`PipelineEvent`]s and fans them out to its subscribers. Set up
+/// once per printer and kept across reconnects, so alerts and jobs are not reported again
+/// after each one.
+#[derive(Debug, Default)]
+struct MonitorPipeline {
+    printer_name: String,
+    jobs: jobs::JobTracker,
+    /// File of job start times; `None` when replaying recorded sessions
+    job_starts: Option<std::path::PathBuf>,
+    /// The saved start was already looked up for the job joined while running
+    start_recalled: bool,
+    costs: Option<config::CostSettings>,
+    /// Directory of inspected project metadata; `None` when replaying recorded sessions
+    projects: Option<std::path::PathBuf>,
+    /// Job materials given on the command line
+    materials: Vec<String>,
+    /// Materials of the last job looked up in `projects`, by job name
+    project_materials: Option<(String, Vec<String>)>,
+    plug: Option<plug::PlugMonitor>,
+    alerts: Option<alerts::AlertTracker>,
+    inspection: jobs::FirstLayerInspection,
+    /// Printer camera and directory for snapshots attached to spaghetti alerts; `None`
+    /// when replaying recorded sessions
+    snapshots: Option<(config::PrinterConfig, std::path::PathBuf)>,
+    /// Spaghetti alerts waiting for their camera still
+    deliveries: tokio::task::JoinSet<(alerts::Alert, Option<std::path::PathBuf>)>,
+    subscribers: Vec<Subscriber>,
+}
+
+impl MonitorPipeline {
+    fn new(
+        config: &config::PrinterConfig,
+        options: &MonitorOptions,
+        console: ConsoleOutput,
+    ) -> Self {
+        let quiet = console == ConsoleOutput::Off;
+        let mut subscribers = Vec::new();
+        // First in line, so a pause is not held up by the others
+        if options.alerts.is_some() {
+            subscribers.push(Subscriber::Pause(PauseSubscriber {
+                printer_label: config.label(),
+                control: None,
+                printing: false,
+                quiet,
+            }));
+        }
+        if !quiet {
+            subscribers.push(Subscriber::Console(ConsoleSubscriber {
+                printer_name: config.name.clone(),
+                printer_label: config.label(),
+                tagged: console == ConsoleOutput::Tagged,
+            }));
+        }
+        if let Some(settings) = config.zabbix.clone() {
+            subscribers.push(Subscriber::Zabbix(zabbix::ZabbixSender::new(
+                settings,
+                &config.name,
+            )));
+        }
+        subscribers.push(Subscriber::Nozzle(NozzleSubscriber {
+            printer_name: config.name.clone(),
+            nozzle: (config.nozzle_type.clone(), config.nozzle_diameter),
+        }));
+        if let Some(bus) = options.events.clone() {
+            subscribers.push(Subscriber::Sinks(SinkSubscriber {
+                printer_name: config.name.clone(),
+                bus,
+            }));
+        }
+        if let Some(command) = options
+            .alerts
+            .as_ref()
+            .and_then(|settings| settings.command.clone())
+        {
+            subscribers.push(Subscriber::AlertCommand(AlertCommandSubscriber {
+                printer_name: config.name.clone(),
+                printer_environment: config.detail_environment(),
+                command,
+            }));
+        }
+        if let Some(settings) = options.reports.clone() {
+            subscribers.push(Subscriber::Reports(ReportSubscriber {
+                printer_name: config.name.clone(),
+                printer_details: config.details(),
+                settings,
+                quiet,
+            }));
+        }
+        subscribers.push(Subscriber::Filament(FilamentSubscriber {
+            printer_name: config.name.clone(),
+            ledger: data_dir().join("filament.jsonl"),
+            spoolman: config.spoolman.clone().and_then(|settings| {
+                match filament::SpoolmanClient::new(&settings.url) {
+                    Ok(client) => Some((client, settings)),
+                    Err(e) => {
+                        eprintln!("{} Spoolman disabled: {e}", Icon::Failure);
+                        None
+                    }
+                }
+            }),
+        }));
+
+        Self {
+            printer_name: config.name.clone(),
+            jobs: jobs::JobTracker::default(),
+            job_starts: Some(data_dir().join(JOB_STARTS_FILE)),
+            start_recalled: false,
+            costs: options.costs.clone(),
+            projects: Some(data_dir().join("projects")),
+            materials: options.materials.clone(),
+            project_materials: None,
+            plug: config
+                .smart_plug
+                .as_ref()
+                .map(|settings| plug::PlugMonitor::start(settings, &config.name)),
+            alerts: options.alerts.clone().map(alerts::AlertTracker::new),
+            inspection: jobs::FirstLayerInspection::default(),
+            snapshots: Some((config.clone(), data_dir().join("snapshots"))),
+            deliveries: tokio::task::JoinSet::new(),
+            subscribers,
+        }
+    }
+
+    /// Hands the commands of a new connection to the subscribers that pause prints
+    fn connected(&mut self, control: mqtt::PrinterControl) {
+        for subscriber in &mut self.subscribers {
+            if let Subscriber::Pause(pause) = subscriber {
+                pause.control = Some(control.clone());
+            }
+        }
+    }
+
+    /// Latest smart plug energy total, for the merged state
+    fn energy_kwh(&self) -> Option<f64> {
+        self.plug.as_ref()?.energy_kwh()
+    }
+
+    /// Start of the running job, for the merged state
+    fn job_started_at(&self) -> Option<chrono::DateTime<chrono::Local>> {
+        self.jobs.start().map(|start| start.started_at)
+    }
+
+    /// Saved start of the job the printer is running, for a single status report
+    fn saved_job_start(
+        &self,
+        state: &messages::MergedState,
+    ) -> Option<chrono::DateTime<chrono::Local>> {
+        if !matches!(
+            state.status().state,
+            messages::PrintState::Printing | messages::PrintState::Paused
+        ) {
+            return None;
+        }
+        let start = jobs::recall_start(self.job_starts.as_ref()?, &self.printer_name)?;
+        start
+            .matches(state.print.subtask_name.as_deref())
+            .then_some(start.started_at)
+    }
+
+    /// Saves the start of a job seen to start, and restores it for a job joined while
+    /// running, so the elapsed time survives reconnects and restarts
+    fn track_job_start(&mut self) {
+        let Some(path) = &self.job_starts else {
+            return;
+        };
+        if self.jobs.active().is_none() {
+            self.start_recalled = false;
+        } else if self.jobs.transitions().contains(&jobs::Transition::Started) {
+            if let Some(start) = self.jobs.start()
+                && let Err(e) = jobs::remember_start(path, &self.printer_name, &start)
+            {
+                eprintln!("{} Failed to save the job start: {e}", Icon::Failure);
+            }
+        } else if !self.start_recalled {
+            self.start_recalled = true;
+            if let Some(start) = jobs::recall_start(path, &self.printer_name) {
+                self.jobs.restore_start(&start);
+            }
+        }
+    }
+
+    async fn observe(
+        &mut self,
+        state: &messages::MergedState,
+        display: &mut display::StatusDisplay,
+    ) {
+        self.deliver_snapshots(display).await;
+        self.publish(PipelineEvent::Status(state), display).await;
+
+        let finished = self.jobs.observe(state, chrono::Local::now());
+        self.track_job_start();
+        for transition in self.jobs.transitions().to_vec() {
+            self.publish(PipelineEvent::Transition(&transition), display)
+                .await;
+        }
+
+        if self
+            .alerts
+            .as_ref()
+            .is_some_and(|tracker| !tracker.settings().door_open_materials.is_empty())
+        {
+            let materials = self.job_materials(state);
+            if let Some(tracker) = self.alerts.as_mut() {
+                tracker.set_job_materials(materials);
+            }
+        }
+        let raised = self
+            .alerts
+            .as_mut()
+            .map(|tracker| tracker.observe(state))
+            .unwrap_or_default();
+        for alert in raised {
+            if alert.kind != "spaghetti" {
+                self.raise(&alert, false, display).await;
+                continue;
+            }
+            let pause = self
+                .alerts
+                .as_ref()
+                .is_some_and(|tracker| tracker.settings().pause_on_spaghetti);
+            self.raise_with_snapshot(alert, pause, display).await;
+        }
+
+        let hms = self
+            .alerts
+            .as_mut()
+            .map(|tracker| tracker.hms(state))
+            .unwrap_or_default();
+        for (action, alert) in hms {
+            match action {
+                config::HmsAction::Log => {
+                    self.publish(PipelineEvent::Notice(&alert), display).await;
+                }
+                config::HmsAction::Notify => self.raise(&alert, false, display).await,
+                config::HmsAction::Pause => self.raise(&alert, true, display).await,
+            }
+        }
+
+        if let Some(result) = self.inspection.observe(state) {
+            self.publish(PipelineEvent::Inspection(&result), display)
+                .await;
+            if let Some(alert) = self
+                .alerts
+                .as_mut()
+                .and_then(|tracker| tracker.inspection(&result))
+            {
+                self.raise(&alert, false, display).await;
+            }
+        }
+
+        let Some(mut job) = finished else {
+            return;
+        };
+        // The plate's slicer weights stand in for filament the AMS did not measure
+        if let Some(dir) = &self.projects
+            && let Some(name) = &job.name
+        {
+            job.plate = project_plate(dir, name, state);
+        }
+        if let Some(costs) = &self.costs {
+            job.cost = job.estimate_cost(costs);
+        }
+        self.publish(PipelineEvent::JobFinished(&job), display)
+            .await;
+    }
+
+    /// Hands `event` to every subscriber in turn
+    async fn publish(&mut self, event: PipelineEvent<'_>, display: &mut display::StatusDisplay) {
+        // Only status updates share the line the status display is drawing on
+        if !matches!(event, PipelineEvent::Status(_)) {
+            display.finish_line();
+        }
+        for subscriber in &mut self.subscribers {
+            subscriber.handle(&event, display).await;
+        }
+    }
+
+    /// Materials of the running job: those given with `--material`, or the filaments of the
+    /// plate in its remembered project
+    fn job_materials(&mut self, state: &messages::MergedState) -> Vec<String> {
+        if !self.materials.is_empty() {
+            return self.materials.clone();
+        }
+        let (Some(dir), Some(name)) = (&self.projects, &state.print.subtask_name) else {
+            return Vec::new();
+        };
+        if self
+            .project_materials
+            .as_ref()
+            .is_none_or(|(cached, _)| cached != name)
+        {
+            let materials = project_plate(dir, name, state)
+                .map(|plate| {
+                    plate
+                        .filaments
+                        .into_iter()
+                        .filter_map(|filament| filament.material)
+                        .collect()
+                })
+                .unwrap_or_default();
+            self.project_materials = Some((name.clone(), materials));
+        }
+        self.project_materials
+            .as_ref()
+            .map(|(_, materials)| materials.clone())
+            .unwrap_or_default()
+    }
+
+    /// Raises an alert for a failed or dropped connection, once per outage
+    async fn connection_lost(&mut self, error: &str) {
+        if let Some(alert) = self
+            .alerts
+            .as_mut()
+            .and_then(|tracker| tracker.connection_lost(error))
+        {
+            // No status line is drawn between connections
+            let mut display = display::StatusDisplay::new(false);
+            self.raise(&alert, false, &mut display).await;
+        }
+    }
+
+    async fn raise(
+        &mut self,
+        alert: &alerts::Alert,
+        pause: bool,
+        display: &mut display::StatusDisplay,
+    ) {
+        self.publish(PipelineEvent::Raised { alert, pause }, display)
+            .await;
+        self.publish(
+            PipelineEvent::Alert {
+                alert,
+                snapshot: None,
+            },
+            display,
+        )
+        .await;
+    }
+
+    /// Raises the alert right away and holds it back from the subscribers that deliver it
+    /// until a camera still is saved. The camera can take a while to answer, so the still is
+    /// grabbed in the background rather than holding up the messages that follow; it goes
+    /// out with the next message, or when monitoring ends.
+    async fn raise_with_snapshot(
+        &mut self,
+        alert: alerts::Alert,
+        pause: bool,
+        display: &mut display::StatusDisplay,
+    ) {
+        let Some((printer, dir)) = self.snapshots.clone() else {
+            self.raise(&alert, pause, display).await;
+            return;
+        };
+        // Every second counts while the nozzle keeps extruding, so the pause goes out
+        // before the camera is asked for a still
+        self.publish(
+            PipelineEvent::Raised {
+                alert: &alert,
+                pause,
+            },
+            display,
+        )
+        .await;
+        self.deliveries.spawn(async move {
+            let snapshot = save_snapshot(&printer, &dir).await;
+            (alert, snapshot)
+        });
+    }
+
+    /// Publishes the alerts whose camera still is done
+    async fn deliver_snapshots(&mut self, display: &mut display::StatusDisplay) {
+        while let Some(delivery) = self.deliveries.try_join_next() {
+            if let Ok((alert, snapshot)) = delivery {
+                self.publish(
+                    PipelineEvent::Alert {
+                        alert: &alert,
+                        snapshot: snapshot.as_deref(),
+                    },
+                    display,
+                )
+                .await;
+            }
+        }
+    }
+
+    /// Waits for the alerts still waiting on a camera still, so they reach the subscribers
+    /// before the process exits
+    async fn finish(&mut self) {
+        let mut display = display::StatusDisplay::new(false);
+        while let Some(delivery) = self.deliveries.join_next().await {
+            if let Ok((alert, snapshot)) = delivery {
+                self.publish(
+                    PipelineEvent::Alert {
+                        alert: &alert,
+                        snapshot: snapshot.as_deref(),
+                    },
+                    &mut display,
+                )
+                .await;
+            }
+        }
+    }
+}
+
+/// A consumer of [`PipelineEvent`]s, each keeping what it needs to handle them
+#[derive(Debug)]
+enum Subscriber {
+    /// Alert, HMS, inspection and job summary lines on stdout
+    Console(ConsoleSubscriber),
+    /// Pauses the print for alerts that ask for it
+    Pause(PauseSubscriber),
+    Zabbix(zabbix::ZabbixSender),
+    /// Saves a newly reported nozzle to the config
+    Nozzle(NozzleSubscriber),
+    /// The `[[sinks]]` of the config
+    Sinks(SinkSubscriber),
+    /// The `command` of the `[alerts]` settings
+    AlertCommand(AlertCommandSubscriber),
+    /// Job report files
+    Reports(ReportSubscriber),
+    /// Filament ledger and Spoolman
+    Filament(FilamentSubscriber),
+}
+
+impl Subscriber {
+    async fn handle(&mut self, event: &PipelineEvent<'_>, display: &mut display::StatusDisplay) {
+        match self {
+            Self::Console(console) => console.handle(event),
+            Self::Pause(pause) => pause.handle(event).await,
+            Self::Zabbix(sender) => {
+                if let PipelineEvent::Status(state) = event
+                    && let Some(Err(e)) = sender.maybe_send(state).await
+                {
+                    display.finish_line();
+                    eprintln!("{} Zabbix push failed: {e}", Icon::Failure);
+                }
+            }
+            Self::Nozzle(nozzle) => {
+                if let PipelineEvent::Status(state) = event {
+                    nozzle.remember(&state.print);
+                }
+            }
+            Self::Sinks(sinks) => sinks.handle(event),
+            Self::AlertCommand(command) => command.handle(event),
+            Self::Reports(reports) => {
+                if let PipelineEvent::JobFinished(job) = event {
+                    reports.write(job);
+                }
+            }
+            Self::Filament(filament) => {
+                if let PipelineEvent::JobFinished(job) = event {
+                    filament.record(job).await;
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+struct ConsoleSubscriber {
+    printer_name: String,
+    /// Name with the location, for alerts
+    printer_label: String,
+    /// Job summaries and inspection results carry the printer name
+    tagged: bool,
+}
+
+impl ConsoleSubscriber {
+    fn handle(&self, event: &PipelineEvent<'_>) {
+        match event {
+            PipelineEvent::Raised { alert, .. } => {
+                println!("{} {}: {}", Icon::Alert, self.printer_label, alert.message);
+            }
+            PipelineEvent::Alert {
+                snapshot: Some(path),
+                ..
+            } => println!("   Snapshot saved to {}", path.display()),
+            PipelineEvent::Notice(alert) => {
+                println!("{} {}: {}", Icon::Info, self.printer_label, alert.message);
+            }
+            PipelineEvent::Inspection(result) => {
+                let line = match result {
+                    jobs::InspectionResult::Passed => "First layer inspection passed".to_string(),
+                    jobs::InspectionResult::DefectDetected { codes } => format!(
+                        "First layer inspection detected defects ({})",
+                        codes.join(", ")
+                    ),
+                };
+                self.print(&format!("{} {line}", Icon::Inspection));
+            }
+            PipelineEvent::JobFinished(job) => self.print(&job_summary_line(job)),
+            _ => {}
+        }
+    }
+
+    fn print(&self, line: &str) {
+        if self.tagged {
+            println!("[{}] {line}", self.printer_name);
+        } else {
+            println!("{line}");
+        }
+    }
+}
+
+#[derive(Debug)]
+struct PauseSubscriber {
+    printer_label: String,
+    /// Commands for the current connection
+    control: Option<mqtt::PrinterControl>,
+    /// The last status update reported a running print
+    printing: bool,
+    quiet: bool,
+}
+
+impl PauseSubscriber {
+    async fn handle(&mut self, event: &PipelineEvent<'_>) {
+        match event {
+            PipelineEvent::Status(state) => {
+                self.printing = state.status().state == messages::PrintState::Printing;
+            }
+            // The printer may have paused itself already
+            PipelineEvent::Raised { alert, pause: true } if self.printing => {
+                let Some(control) = &self.control else {
+                    return;
+                };
+                match control.send(mqtt::PrintAction::Pause).await {
+                    Ok(()) if self.quiet => {}
+                    Ok(()) => println!(
+                        "{} {}: Paused the print after the {} alert",
+                        Icon::Paused,
+                        self.printer_label,
+                        alert.kind
+                    ),
+                    Err(e) => eprintln!("{} Failed to pause the print: {e}", Icon::Failure),
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[derive(Debug)]
+struct NozzleSubscriber {
+    printer_name: String,
+    /// Nozzle stored in the config, updated when the printer reports a different one
+    nozzle: (Option<String>, Option<f64>),
+}
+
+impl NozzleSubscriber {
+    /// Saves a newly reported nozzle to the printer's config entry
+    fn remember(&mut self, print: &messages::PrintInfo) {
+        let reported = (
+            print.nozzle_type.clone().or_else(|| self.nozzle.0.clone()),
+            print.nozzle_diameter.or(self.nozzle.1),
+        );
+        if reported == self.nozzle {
+            return;
+        }
+        self.nozzle = reported;
+
+        // Printers given on the command line have no entry to update
+        let config_path = config::AppConfig::get_config_path();
+        if let Ok(mut app_config) = config::AppConfig::load_from_file(&config_path)
+            && let Ok(true) = app_config.update_nozzle(
+                &self.printer_name,
+                self.nozzle.0.as_deref(),
+                self.nozzle.1,
+            )
+            && let Err(e) = app_config.save_to_file(&config_path)
+        {
+            eprintln!("{} Failed to save nozzle details: {e}", Icon::Failure);
+        }
+    }
+}
+
+#[derive(Debug)]
+struct SinkSubscriber {
+    printer_name: String,
+    bus: events::EventBus,
+}
+
+impl SinkSubscriber {
+    fn handle(&self, event: &PipelineEvent<'_>) {
+        let name = &self.printer_name;
+        let event = match event {
+            PipelineEvent::Status(state) if self.bus.wants(config::EventType::Status) => {
+                events::Event::status(name, state)
+            }
+            PipelineEvent::Transition(transition) => events::Event::transition(name, transition),
+            PipelineEvent::Alert { alert, snapshot } => {
+                events::Event::alert(name, alert, *snapshot)
+            }
+            PipelineEvent::Inspection(result) => events::Event::inspection(name, result),
+            PipelineEvent::JobFinished(job) => events::Event::job_finished(name, job),
+            _ => return,
+        };
+        self.bus.publish(event);
+    }
+}
+
+#[derive(Debug)]
+struct AlertCommandSubscriber {
+    printer_name: String,
+    /// Location, notes and metadata of the printer as environment variables
+    printer_environment: Vec<(String, String)>,
+    command: String,
+}
+
+impl AlertCommandSubscriber {
+    fn handle(&self, event: &PipelineEvent<'_>) {
+        if let PipelineEvent::Alert { alert, snapshot } = event
+            && let Err(e) = alerts::run_command(
+                &self.command,
+                &self.printer_name,
+                &self.printer_environment,
+                alert,
+                *snapshot,
+            )
+        {
+            eprintln!("{} Alert command failed: {e}", Icon::Failure);
+        }
+    }
+}
+
+#[derive(Debug)]
+struct ReportSubscriber {
+    printer_name: String,
+    /// Location, notes and metadata rows for the report
+    printer_details: Vec<(String, String)>,
+    settings: config::ReportSettings,
+    quiet: bool,
+}
+
+impl ReportSubscriber {
+    fn write(&self, job: &jobs::JobRecord) {
+        let default_dir = data_dir().join("reports");
+        match report::write_report(
+            &self.settings,
+            &default_dir,
+            &self.printer_name,
+            &self.printer_details,
+            job,
+        ) {
+            Ok(path) if !self.quiet => {
+                println!("{} Job report written to {}", Icon::Success, path.display())
+            }
+            Ok(_) => {}
+            Err(e) => eprintln!("{} Failed to write job report: {e}", Icon::Failure),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct FilamentSubscriber {
+    printer_name: String,
+    ledger: std::path::PathBuf,
+    spoolman: Option<(filament::SpoolmanClient, config::SpoolmanSettings)>,
+}
+
+impl FilamentSubscriber {
+    /// Appends the job's filament usage to the ledger and reports mapped trays to Spoolman
+    async fn record(&self, job: &jobs::JobRecord) {
+        let usage = job.filament_usage();
+        if usage.is_empty() {
+            return;
+        }
+
+        let mut entries = Vec::new();
+        for usage in usage {
+            let mut spool_id = None;
+            if let Some((client, settings)) = &self.spoolman
+                && let Some(&id) = settings.spools.get(&usage.tray)
+            {
+                match client.use_filament(id, usage.grams).await {
+                    Ok(()) => spool_id = Some(id),
+                    Err(e) => eprintln!("{} {e}", Icon::Failure),
+                }
+            }
+            entries.push(filament::LedgerEntry {
+                recorded_at: job.ended_at,
+                printer: self.printer_name.clone(),
+                job: job.name.clone(),
+                usage,
+                spool_id,
+            });
+        }
+
+        if let Err(e) = filament::append_ledger(&self.ledger, &entries) {
+            eprintln!("{} Failed to update filament ledger: {e}", Icon::Failure);
+        }
+    }
+}
+
+/// Grabs a camera still of `printer` into `dir`, or `None` when the camera cannot be reached
+async fn save_snapshot(
+    printer: &config::PrinterConfig,
+    dir: &std::path::Path,
+) -> Option<std::path::PathBuf> {
+    let kind = camera::CameraKind::detect(printer);
+    let image = match tokio::time::timeout(SNAPSHOT_TIMEOUT, camera::snapshot(printer, kind)).await
+    {
+        Ok(Ok(image)) => image,
+        Ok(Err(e)) => {
+            eprintln!("{} Could not grab a snapshot: {e}", Icon::Failure);
+            return None;
+        }
+        Err(_) => {
+            eprintln!("{} Could not grab a snapshot: timed out", Icon::Failure);
+            return None;
+        }
+    };
+    let path = dir.join(format!(
+        "{}-{}.jpg",
+        printer.name,
+        chrono::Local::now().format("%Y%m%d-%H%M%S")
+    ));
+    match camera::save(&image, &path) {
+        Ok(()) => Some(path),
+        Err(e) => {
+            eprintln!("{} Failed to write {}: {e}", Icon::Failure, path.display());
+            None
+        }
+    }
+}
+
+/// Start times of running jobs per printer, next to the config file
+const JOB_STARTS_FILE: &str = "job_starts.json";
+
+/// Directory holding the config file, also used for reports and the filament ledger
+fn data_dir() -> std::path::PathBuf {
+    config::AppConfig::get_config_path()
+        .parent()
+        .map(std::path::Path::to_path_buf)
+        .unwrap_or_default()
+}
+
+/// How long an alert waits for a camera snapshot before going out without one
+const SNAPSHOT_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(20);
+
+/// Exit code when the printer could not be reached or never reported status
+const EXIT_CONNECTION_ERROR: i32 = 10;
+/// Exit code when a scripted run cannot resolve a printer configuration
+const EXIT_CONFIG_ERROR: i32 = 11;
+
+/// Exit code reflecting the last known print state, for scripts chaining on `status` and `monitor`
+///
+/// `0` idle/finished, `1` printing, `2` paused, `3` failed, `4` unknown state, `>= 10` errors.
+fn state_exit_code(state: &messages::MergedState) -> i32 {
+    use messages::PrintState;
+
+    if state.message_count == 0 {
+        return EXIT_CONNECTION_ERROR;
+    }
+
+    match state.status().state {
+        PrintState::Idle | PrintState::Finished => 0,
+        PrintState::Printing => 1,
+        PrintState::Paused => 2,
+        PrintState::Failed => 3,
+        PrintState::Unknown(_) => 4,
+    }
+}
+
+async fn run_check(
+    name: &str,
+    thresholds: &check::Thresholds,
+    timeout: std::time::Duration,
+) -> check::CheckResult {
+    let printer_config = match load_printer_config(&Some(name.to_string()), &None, &None, &None) {
+        Ok(config) => config,
+        Err(e) => return check::CheckResult::unknown(format!("{name}: {e}")),
+    };
+
+    let options = MonitorOptions {
+        once: true,
+        quiet: true,
+        duration: Some(timeout),
+        ..Default::default()
+    };
+
+    match monitor_printer(printer_config, &options).await {
+        Ok(state) => check::evaluate(name, &state, thresholds),
+        Err(e) => check::CheckResult::unknown(format!("{name}: {e}")),
+    }
+}
+
+async fn monitor_printer(
+    config: config::PrinterConfig,
+    options: &MonitorOptions,
+) -> Result<messages::MergedState, Box<dyn std::error::Error>> {
+    let config = std::sync::Arc::new(config);
+    let mut state = messages::MergedState::default();
+    // Kept across reconnects so alerts and jobs are not reported again after each one
+    let mut pipeline = MonitorPipeline::new(&config, options, ConsoleOutput::Plain);
+
+    let monitoring = monitor_with_retries(&config, options, &mut state, &mut pipeline);
+    let result = match options.duration {
+        Some(duration) => match tokio::time::timeout(duration, monitoring).await {
+            Ok(result) => result,
+            Err(_) => {
+                if !options.quiet {
+                    println!();
+                    println!("{} Monitoring duration elapsed", Icon::Timer);
+                }
+                Ok(())
+            }
+        },
+        None => monitoring.await,
+    };
+    pipeline.finish().await;
+    result?;
+
+    Ok(state)
+}
+
+async fn monitor_with_retries(
+    config: &std::sync::Arc<config::PrinterConfig>,
+    options: &MonitorOptions,
+    state: &mut messages::MergedState,
+    pipeline: &mut MonitorPipeline,
+) -> Result<(), Box<dyn std::error::Error>> {
+    retry_connections(config, options, state, pipeline, async |state, pipeline| {
+        attempt_connection(config, options, state, pipeline).await
+    })
+    .await
+}
+
+/// Runs one connection after another through `connect` until one ends without an error or
+/// the reconnect policy gives up
+async fn retry_connections(
+    config: &config::PrinterConfig,
+    options: &MonitorOptions,
+    state: &mut messages::MergedState,
+    pipeline: &mut MonitorPipeline,
+    mut connect: impl AsyncFnMut(
+        &mut messages::MergedState,
+        &mut MonitorPipeline,
+    ) -> Result<(), Box<dyn std::error::Error>>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let policy = options.reconnect_policy();
+    let mut retry_count = 0;
+
+    loop {
+        if !options.once {
+            println!(
+                "Connecting to printer '{}' at {} with device ID {} (attempt {})",
+                config.name,
+                config.ip,
+                config.device_id,
+                retry_count + 1
+            );
+        }
+
+        match connect(state, pipeline).await {
+            Ok(_) => {
+                if !options.once {
+                    println!("Connection successful! Monitoring stopped.");
+                }
+                return Ok(());
+            }
+            Err(e) => {
+                eprintln!("Connection attempt failed: {e}");
+                pipeline.connection_lost(&e.to_string()).await;
+
+                retry_count += 1;
+                if !policy.should_retry(retry_count) {
+                    return Err(format!("Failed to connect after {retry_count} attempts").into());
+                }
+
+                let delay = policy.next_delay(&config.name, retry_count);
+                if !options.quiet {
+                    println!("Retrying in {} seconds...", delay.as_secs());
+                }
+                tokio::time::sleep(delay).await;
+            }
+        }
+    }
+}
+
+async fn attempt_connection(
+    config: &std::sync::Arc<config::PrinterConfig>,
+    options: &MonitorOptions,
+    state: &mut messages::MergedState,
+    pipeline: &mut MonitorPipeline,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut mqtt_client = mqtt::MqttClient::new(std::sync::Arc::clone(config)).await?;
+    mqtt_client.connect().await?;
+
+    if options.once {
+        // Ask for a full status push instead of waiting for deltas to fill in
+        mqtt_client.request_pushall().await?;
+    } else {
+        println!(
+            "Connected to printer '{}' at {} over {} and subscribed to {}",
+            config.name,
+            config.ip,
+            mqtt_client.protocol(),
+            config.report_topic()
+        );
+        if let Some(reason) = mqtt_client.v5_refusal() {
+            println!(
+                "{} The printer did not accept MQTT 5 ({reason}), so MQTT 3.1.1 is used",
+                Icon::Info
+            );
+        }
+        println!(
+            "{} Monitoring printer status - Press Ctrl+C to stop...",
+            Icon::Monitoring
+        );
+    }
+
+    use std::io::IsTerminal;
+    let mut display =
+        display::StatusDisplay::new(options.progress_bar && std::io::stdout().is_terminal());
+
+    pipeline.connected(mqtt_client.control());
+    let mut eventloop =
+        logging::LoggedSource::new(mqtt_client.get_eventloop(), options.message_log(config));
+    if options.dedupe {
+        let mut source = mqtt::DedupeSource::new(eventloop);
+        let result = process_messages(&mut source, &mut display, options, state, pipeline).await;
+        display.finish_line();
+        if source.skipped() > 0 && !options.once {
+            println!(
+                "{} Skipped {} duplicate payloads",
+                Icon::Dedupe,
+                source.skipped()
+            );
+        }
+        return result;
+    }
+
+    let result = process_messages(&mut eventloop, &mut display, options, state, pipeline).await;
+    display.finish_line();
+    result
+}
+
+async fn process_messages<S: mqtt::MessageSource>(
+    source: &mut S,
+    display: &mut display::StatusDisplay,
+    options: &MonitorOptions,
+    state: &mut messages::MergedState,
+    pipeline: &mut MonitorPipeline,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut refresh = options.refresh.map(tokio::time::interval);
+    let mut history = messages::TemperatureHistory::new(SPARKLINE_SAMPLES);
+
+    loop {
+        let next = match refresh.as_mut() {
+            Some(ticker) => tokio::select! {
+                publish = source.next_publish() => publish?,
+                _ = ticker.tick() => {
+                    // Sample on the refresh tick so the sparklines have an even time axis
+                    if state.message_count > 0 {
+                        history.record(&state.print);
+                    }
+                    display.redraw_screen(&status_panel(state, &history));
+                    continue;
+                }
+            },
+            None => source.next_publish().await?,
+        };
+        let Some(publish) = next else {
+            break;
+        };
+
+        let Some(message) = parse_mqtt_message(&publish, display) else {
+            continue;
+        };
+        state.update(&message);
+        if let Some(energy) = pipeline.energy_kwh() {
+            state.energy_kwh = Some(energy);
+        }
+
+        if options.once {
+            let is_pushall = matches!(
+                message.get_message_type(),
+                messages::MessageType::PushingPushAll
+            );
+            if state.is_complete() || is_pushall {
+                state.started_at = pipeline.saved_job_start(state);
+                if !options.quiet {
+                    print_state_summary(state);
+                }
+                return Ok(());
+            }
+            continue;
+        }
+
+        if refresh.is_none() {
+            display_message(&message, display);
+        }
+
+        pipeline.observe(state, display).await;
+        state.started_at = pipeline.job_started_at();
+
+        if options.until_done && state.status().state.is_terminal() {
+            if refresh.is_some() {
+                display.redraw_screen(&status_panel(state, &history));
+            }
+            display.finish_line();
+            return Ok(());
+        }
+    }
+
+    Ok(())
+}
+
+/// Watches several printers at once, each on its own connection. Output from all of them is
+/// interleaved: status lines are tagged `[name]` and printed when they change, or with `json`
+/// every update is printed as one object per line. Returns the highest exit code of any printer.
+async fn monitor_printers(
+    configs: Vec<config::PrinterConfig>,
+    options: &MonitorOptions,
+    json: bool,
+    pool: mqtt::ConnectionPool,
+) -> i32 {
+    // The MQTT client is not `Sync`, so the connections run on a local task set
+    let connections = tokio::task::LocalSet::new();
+    connections
+        .run_until(async {
+            let mut seen = std::collections::HashSet::new();
+            let mut watchers = tokio::task::JoinSet::new();
+            for config in configs {
+                if !seen.insert(config.name.clone()) {
+                    continue;
+                }
+                let options = options.clone();
+                let pool = pool.clone();
+                watchers.spawn_local(async move {
+                    let name = config.name.clone();
+                    (name, watch_printer(config, &options, json, &pool).await)
+                });
+            }
+
+            let mut exit_code = 0;
+            while let Some(Ok((name, result))) = watchers.join_next().await {
+                let code = match result {
+                    Ok(state) => state_exit_code(&state),
+                    Err(e) => {
+                        eprintln!("[{name}] Error monitoring printer: {e}");
+                        EXIT_CONNECTION_ERROR
+                    }
+                };
+                exit_code = exit_code.max(code);
+            }
+            exit_code
+        })
+        .await
+}
+
+/// One printer of [`monitor_printers`], reconnecting like [`monitor_with_retries`]
+async fn watch_printer(
+    config: config::PrinterConfig,
+    options: &MonitorOptions,
+    json: bool,
+    pool: &mqtt::ConnectionPool,
+) -> Result<messages::MergedState, String> {
+    let config = std::sync::Arc::new(config);
+    let mut state = messages::MergedState::default();
+
+    // Alerts, events and reports still go out with JSON output; only the console lines
+    // would break up the stream
+    let console = if json {
+        ConsoleOutput::Off
+    } else {
+        ConsoleOutput::Tagged
+    };
+    let mut pipeline = MonitorPipeline::new(&config, options, console);
+
+    let policy = options.reconnect_policy();
+    let watching = async {
+        let mut retry_count = 0;
+        loop {
+            let result = watch_connection(&config, options, json, pool, &mut state, &mut pipeline)
+                .await
+                .map_err(|e| e.to_string());
+            let Err(e) = result else {
+                return Ok(());
+            };
+            eprintln!("[{}] Connection attempt failed: {e}", config.name);
+            pipeline.connection_lost(&e).await;
+            retry_count += 1;
+            if !policy.should_retry(retry_count) {
+                return Err(format!("Failed to connect after {retry_count} attempts"));
+            }
+            // Spread on top of the policy, so a fleet does not reconnect all at once
+            let base = policy.next_delay(&config.name, retry_count);
+            tokio::time::sleep(mqtt::reconnect_delay(&config.name, retry_count, base)).await;
+        }
+    };
+    let result = match options.duration {
+        Some(duration) => tokio::time::timeout(duration, watching)
+            .await
+            .unwrap_or(Ok(())),
+        None => watching.await,
+    };
+    pipeline.finish().await;
+    result?;
+
+    Ok(state)
+}
+
+async fn watch_connection(
+    config: &std::sync::Arc<config::PrinterConfig>,
+    options: &MonitorOptions,
+    json: bool,
+    pool: &mqtt::ConnectionPool,
+    state: &mut messages::MergedState,
+    pipeline: &mut MonitorPipeline,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut mqtt_client = pool.open(std::sync::Arc::clone(config)).await?;
+    mqtt_client.connect().await?;
+    // A full status push gives every printer a complete first line
+    mqtt_client.request_pushall().await?;
+    pipeline.connected(mqtt_client.control());
+
+    let eventloop =
+        logging::LoggedSource::new(mqtt_client.get_eventloop(), options.message_log(config));
+    if options.dedupe {
+        let mut source = mqtt::DedupeSource::new(eventloop);
+        watch_messages(&mut source, &config.name, options, json, state, pipeline).await
+    } else {
+        let mut source = eventloop;
+        watch_messages(&mut source, &config.name, options, json, state, pipeline).await
+    }
+}
+
+async fn watch_messages<S: mqtt::MessageSource>(
+    source: &mut S,
+    name: &str,
+    options: &MonitorOptions,
+    json: bool,
+    state: &mut messages::MergedState,
+    pipeline: &mut MonitorPipeline,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut display = display::StatusDisplay::new(false);
+    let mut last_lines = Vec::new();
+
+    while let Some(publish) = source.next_publish().await? {
+        let Ok(message) = messages::DeviceMessage::parse_from(&publish.topic, &publish.payload)
+        else {
+            continue;
+        };
+        state.update(&message);
+        if let Some(energy) = pipeline.energy_kwh() {
+            state.energy_kwh = Some(energy);
+        }
+
+        let is_pushall = matches!(
+            message.get_message_type(),
+            messages::MessageType::PushingPushAll
+        );
+        if options.once && !state.is_complete() && !is_pushall {
+            continue;
+        }
+
+        if json {
+            let snapshot = daemon::PrinterSnapshot {
+                name: name.to_string(),
+                connected: true,
+                last_message_at: Some(chrono::Local::now()),
+                state: state.clone(),
+            };
+            println!("{}", serde_json::to_string(&snapshot)?);
+        } else {
+            let lines = state_summary_lines(state);
+            if lines != last_lines {
+                for line in &lines {
+                    println!("[{name}] {line}");
+                }
+                last_lines = lines;
+            }
+        }
+
+        if options.once {
+            return Ok(());
+        }
+        pipeline.observe(state, &mut display).await;
+        state.started_at = pipeline.job_started_at();
+        if options.until_done && state.status().state.is_terminal() {
+            return Ok(());
+        }
+    }
+
+    Ok(())
+}
+
+async fn record_printer(
+    config: config::PrinterConfig,
+    out: &std::path::Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    use mqtt::MessageSource;
+
+    let mut recorder = recording::SessionRecorder::create(out)?;
+
+    println!(
+        "Recording printer '{}' at {} to {}",
+        config.name,
+        config.ip,
+        out.display()
+    );
+
+    let mut mqtt_client = mqtt::MqttClient::new(std::sync::Arc::new(config)).await?;
+    mqtt_client.connect().await?;
+
+    let mut eventloop = mqtt_client.get_eventloop();
+
+    loop {
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => {
+                println!(
+                    "{} Recording stopped: {} messages saved to {}",
+                    Icon::Stop,
+                    recorder.message_count(),
+                    out.display()
+                );
+                return Ok(());
+            }
+            publish = eventloop.next_publish() => {
+                let Some(publish) = publish? else {
+                    return Ok(());
+                };
+
+                recorder.record(&publish.topic, &publish.payload)?;
+                println!(
+                    "{} Recorded message {} from {} ({} bytes)",
+                    Icon::Recording,
+                    recorder.message_count(),
+                    publish.topic,
+                    publish.payload.len()
+                );
+            }
+        }
+    }
+}
+
+async fn replay_session(
+    path: &std::path::Path,
+    speed: f64,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let messages = recording::read_session(path)?;
+    let payloads = messages
+        .iter()
+        .map(|message| message.payload_bytes().map(|payload| payload.into_owned()))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    println!(
+        "{} Replaying {} messages from {} at {speed}x",
+        Icon::Play,
+        messages.len(),
+        path.display()
+    );
+
+    let (sender, mut source) = mqtt::ChannelSource::new(16);
+    let producer = tokio::spawn(async move {
+        let mut previous: Option<&recording::RecordedMessage> = None;
+        for (message, payload) in messages.iter().zip(payloads) {
+            if let Some(previous) = previous {
+                let delay = recording::replay_delay(previous, message, speed);
+                if !delay.is_zero() {
+                    tokio::time::sleep(delay).await;
+                }
+            }
+
+            let publish = rumqttc::Publish::new(&message.topic, rumqttc::QoS::AtMostOnce, payload);
+            if sender.send(Ok(publish)).await.is_err() {
+                break;
+            }
+            previous = Some(message);
         }
-        println!();
-    }
+    });
+
+    // Replay output is usually compared or piped, so always print one line per update
+    let mut display = display::StatusDisplay::new(false);
+    let mut state = messages::MergedState::default();
+    // A recorded session only shows on the console; nothing is saved or sent from it
+    let mut pipeline = MonitorPipeline {
+        subscribers: vec![Subscriber::Console(ConsoleSubscriber {
+            printer_name: String::new(),
+            printer_label: String::new(),
+            tagged: false,
+        })],
+        ..MonitorPipeline::default()
+    };
+    process_messages(
+        &mut source,
+        &mut display,
+        &MonitorOptions::default(),
+        &mut state,
+        &mut pipeline,
+    )
+    .await?;
+    producer.await?;
 
+    println!("{} Replay finished", Icon::Stop);
     Ok(())
 }
 
-fn handle_remove_printer(name: &str) -> Result<(), Box<dyn std::error::Error>> {
-    let config_path = config::AppConfig::get_config_path();
-    let mut app_config = config::AppConfig::load_from_file(&config_path)?;
+async fn run_simulator(
+    broker_host: &str,
+    broker_port: u16,
+    device_id: &str,
+    interval_ms: u64,
+    script: simulator::PrintJobScript,
+) -> Result<(), Box<dyn std::error::Error>> {
+    use rumqttc::{AsyncClient, Event, MqttOptions, Outgoing, QoS};
 
-    // Remove printer
-    let removed_printer = app_config.remove_printer(name)?;
+    let mut mqtt_options = MqttOptions::new("pulseprint-simulator", broker_host, broker_port);
+    mqtt_options.set_keep_alive(std::time::Duration::from_secs(30));
 
-    // Save config
-    app_config.save_to_file(&config_path)?;
+    let (client, mut eventloop) = AsyncClient::new(mqtt_options, 10);
+    let poller = tokio::spawn(async move {
+        loop {
+            match eventloop.poll().await {
+                Ok(Event::Outgoing(Outgoing::Disconnect)) => return Ok(()),
+                Ok(_) => {}
+                Err(e) => return Err(format!("MQTT connection error: {e}")),
+            }
+        }
+    });
 
-    let removed_name = &removed_printer.name;
-    println!("🗑️  Printer '{removed_name}' removed successfully");
+    let topic = format!("device/{device_id}/report");
+    println!(
+        "{} Simulating printer {device_id} on {broker_host}:{broker_port} ({topic})",
+        Icon::Simulate
+    );
 
-    // Show message if this was the default printer
-    if app_config.default_printer.is_none() && !app_config.printers.is_empty() {
-        let first_printer = app_config.printers.keys().next().unwrap();
-        println!("💡 Consider setting a new default printer with: set-default {first_printer}");
+    let mut simulator = simulator::PrinterSimulator::new(device_id, script);
+    let mut published = 0;
+    loop {
+        let phase = simulator.phase();
+        let Some(frame) = simulator.next() else {
+            break;
+        };
+        if poller.is_finished() {
+            break;
+        }
+
+        client
+            .publish(&topic, QoS::AtMostOnce, false, frame.to_string())
+            .await?;
+        published += 1;
+        println!(
+            "{} Published simulated message {published} ({phase:?})",
+            Icon::Publish
+        );
+
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => break,
+            _ = tokio::time::sleep(tokio::time::Duration::from_millis(interval_ms)) => {}
+        }
     }
 
+    let _ = client.disconnect().await;
+    poller.await??;
+
+    println!("{} Simulation finished", Icon::Stop);
     Ok(())
 }
 
-fn handle_set_default_printer(name: &str) -> Result<(), Box<dyn std::error::Error>> {
-    let config_path = config::AppConfig::get_config_path();
-    let mut app_config = config::AppConfig::load_from_file(&config_path)?;
-
-    // Set default printer
-    app_config.set_default_printer(name)?;
+fn parse_mqtt_message(
+    publish: &rumqttc::Publish,
+    display: &mut display::StatusDisplay,
+) -> Option<messages::DeviceMessage> {
+    // Parse straight from the payload bytes; large pushall bursts skip a separate UTF-8 pass
+    match messages::DeviceMessage::parse_from(&publish.topic, &publish.payload) {
+        Ok(message) => Some(message),
+        Err(e) => {
+            display.finish_line();
+            eprintln!("Failed to parse MQTT message: {e}");
+            if publish.payload.len() < 1000 {
+                eprintln!("Raw message: {}", String::from_utf8_lossy(&publish.payload));
+            } else {
+                eprintln!(
+                    "Raw message (truncated): {}...",
+                    String::from_utf8_lossy(&publish.payload[..500])
+                );
+            }
+            None
+        }
+    }
+}
 
-    // Save config
-    app_config.save_to_file(&config_path)?;
+fn display_message(message: &messages::DeviceMessage, display: &mut display::StatusDisplay) {
+    let message_type = message.get_message_type();
+    let sequence_id = message.get_sequence_id().unwrap_or("none");
 
-    println!("🎯 Printer '{name}' set as default");
+    match message_type {
+        messages::MessageType::PrintPushStatus => {
+            let status = messages::PrinterStatus::from_device_message(message);
+            if let Some(status) = &status
+                && display.in_place()
+                && status.state == messages::PrintState::Printing
+                && let Some(info) = progress_info(status, message)
+            {
+                display.update_progress(&display::render_progress_bar(&info));
+                return;
+            }
 
-    Ok(())
+            display.finish_line();
+            if let Some(status) = status {
+                let reason = message.print.as_ref().and_then(jobs::pause_reason);
+                handle_print_status(status, reason.as_ref());
+            }
+            // Also show detailed Bambu-specific info
+            if let Some(print_info) = &message.print {
+                handle_bambu_print_status(print_info);
+            }
+        }
+        messages::MessageType::PushingPushAll => {
+            display.finish_line();
+            println!(
+                "{} Received complete printer status (pushall)",
+                Icon::Pushall
+            );
+            handle_pushall_message(message);
+        }
+        messages::MessageType::SystemPushAll => {
+            display.finish_line();
+            println!("{} Received system information", Icon::System);
+            handle_system_message(message);
+        }
+        messages::MessageType::Unknown(cmd) => {
+            display.finish_line();
+            println!(
+                "{} Unknown message type: {cmd} (seq: {sequence_id})",
+                Icon::Unknown
+            );
+        }
+    }
 }
 
-fn validate_ip_address(ip: &str) -> Result<(), Box<dyn std::error::Error>> {
-    use std::net::Ipv4Addr;
+/// Plate of the remembered project for job `name` that the printer is running
+fn project_plate(
+    dir: &std::path::Path,
+    name: &str,
+    state: &messages::MergedState,
+) -> Option<project::PlateInfo> {
+    let project = project::lookup(dir, name)?;
+    let plate = state
+        .print
+        .extra
+        .get("gcode_file")
+        .and_then(serde_json::Value::as_str)
+        .and_then(project::plate_index);
+    project.plate(plate).cloned()
+}
 
-    ip.parse::<Ipv4Addr>()
-        .map_err(|_| format!("Invalid IP address: {ip}"))?;
+/// One-line wrap-up printed when a monitored job finishes or fails
+fn job_summary_line(job: &jobs::JobRecord) -> String {
+    use messages::PrintState;
 
-    Ok(())
-}
+    let (icon, outcome) = match job.final_state {
+        PrintState::Failed => (Icon::Failure, "failed after"),
+        _ => (Icon::Finished, "finished in"),
+    };
+    let name = job.name.as_deref().unwrap_or("Print");
+    let mut line = format!(
+        "{icon} Job '{name}' {outcome} {}",
+        display::format_duration_short(job.elapsed_secs())
+    );
 
-fn validate_device_id(device_id: &str) -> Result<(), Box<dyn std::error::Error>> {
-    if device_id.is_empty() {
-        return Err("Device ID cannot be empty".into());
+    if let Some(rate) = job.progress_per_hour() {
+        line.push_str(&format!(" | avg {rate:.1}%/h"));
     }
 
-    if device_id.len() < 5 {
-        return Err("Device ID seems too short (should be like '01S00A000000000')".into());
+    let mut peaks = Vec::new();
+    if job.nozzle.average().is_some() {
+        peaks.push(format!(
+            "nozzle {:.1}{}",
+            display::temperature(job.nozzle.peak),
+            display::degrees()
+        ));
+    }
+    if job.bed.average().is_some() {
+        peaks.push(format!(
+            "bed {:.1}{}",
+            display::temperature(job.bed.peak),
+            display::degrees()
+        ));
+    }
+    if !peaks.is_empty() {
+        line.push_str(&format!(" | peak {}", peaks.join(", ")));
     }
 
-    Ok(())
-}
-
-fn validate_access_code(access_code: &str) -> Result<(), Box<dyn std::error::Error>> {
-    if access_code.is_empty() {
-        return Err("Access code cannot be empty".into());
+    if let Some(energy) = job.energy_kwh() {
+        line.push_str(&format!(" | {energy:.2} kWh"));
     }
 
-    if access_code.len() != 8 {
-        return Err("Access code should be exactly 8 characters".into());
+    let usage = job.filament_usage();
+    if !usage.is_empty() {
+        line.push_str(&format!(
+            " | {:.1} g filament",
+            filament::total_grams(&usage)
+        ));
     }
 
-    // Check if it's all digits
-    if !access_code.chars().all(|c| c.is_ascii_digit()) {
-        return Err("Access code should contain only digits".into());
+    if let Some(cost) = &job.cost {
+        line.push_str(&format!(" | est. {}", cost.format_amount(cost.total())));
     }
 
-    Ok(())
+    let plural = if job.pauses == 1 { "" } else { "s" };
+    line.push_str(&format!(" | {} pause{plural}", job.pauses));
+    line
 }
 
-fn load_printer_config(
-    name: &Option<String>,
-    ip: &Option<String>,
-    device_id: &Option<String>,
-    access_code: &Option<String>,
-) -> Result<config::PrinterConfig, Box<dyn std::error::Error>> {
-    // If all manual parameters are provided, use them directly
-    if let (Some(ip), Some(device_id), Some(access_code)) = (ip, device_id, access_code) {
-        validate_ip_address(ip)?;
-        validate_device_id(device_id)?;
-        validate_access_code(access_code)?;
+/// Number of refresh ticks kept for the temperature sparklines
+const SPARKLINE_SAMPLES: usize = 40;
 
-        return Ok(config::PrinterConfig::new(
-            name.clone().unwrap_or_else(|| "manual".to_string()),
-            ip.clone(),
-            device_id.clone(),
-            access_code.clone(),
-        ));
+/// Compact full-screen view of the merged state for `monitor --refresh`
+fn status_panel(state: &messages::MergedState, history: &messages::TemperatureHistory) -> String {
+    use display::{Field, field_enabled};
+
+    let updated = display::format_clock_time(&chrono::Local::now(), display::time_format());
+    let title = format!("PulsePrint - updated {updated}");
+    if state.message_count == 0 {
+        return display::render_panel(&title, &[("Status", "Waiting for data...".to_string())]);
     }
 
-    // Otherwise, load from config
-    let config_path = config::AppConfig::get_config_path();
-    let app_config = config::AppConfig::load_from_file(&config_path)?;
+    let status = state.status();
+    let print = &state.print;
+    let mut rows: Vec<(&str, String)> = Vec::new();
 
-    // Determine which printer to use
-    let printer_config = match name {
-        Some(printer_name) => {
-            // Use specified printer
-            app_config.get_printer(printer_name)?.clone()
+    if field_enabled(Field::State) {
+        let reason =
+            jobs::pause_reason(print).filter(|_| status.state == messages::PrintState::Paused);
+        match reason {
+            Some(reason) => rows.push((
+                "State",
+                format!("{:?} ({})", status.state, reason.describe()),
+            )),
+            None => rows.push(("State", format!("{:?}", status.state))),
         }
-        None => {
-            // Use default printer
-            if app_config.printers.is_empty() {
-                return Err("No printers configured. Use 'add' command to add a printer.".into());
+    }
+    if let Some(percent) = status.progress
+        && field_enabled(Field::Progress)
+    {
+        rows.push((
+            "Progress",
+            display::render_progress_bar(&display::ProgressInfo {
+                percent,
+                ..Default::default()
+            }),
+        ));
+    }
+    if let Some(layer) = print.layer_num
+        && field_enabled(Field::Layer)
+    {
+        let layer = match print.total_layer_num {
+            Some(total) if total > 0 => format!("{layer}/{total}"),
+            _ => layer.to_string(),
+        };
+        rows.push(("Layer", layer));
+    }
+    if let Some(elapsed) = status.elapsed_secs(chrono::Local::now())
+        && field_enabled(Field::Remaining)
+    {
+        rows.push(("Elapsed", display::format_duration_short(elapsed)));
+    }
+    if let Some(remaining) = status.remaining_time
+        && field_enabled(Field::Remaining)
+    {
+        rows.push(("Remaining", display::format_duration_short(remaining)));
+    }
+    if let Some(finish_at) = &status.finish_at
+        && field_enabled(Field::Eta)
+    {
+        rows.push(("ETA", display::format_finish_time(finish_at)));
+    }
+    let temperature =
+        |temp: f64, color: Option<display::Color>, samples: &std::collections::VecDeque<f64>| {
+            let mut value = format!(
+                "{:>5.1}{}",
+                display::paint(display::temperature(temp), color),
+                display::degrees()
+            );
+            if samples.len() > 1 {
+                value.push_str("  ");
+                value.push_str(&display::render_sparkline(samples));
             }
-            app_config.get_default_printer()?.clone()
+            value
+        };
+    if let Some(nozzle) = print.nozzle_temper
+        && field_enabled(Field::Nozzle)
+    {
+        rows.push((
+            "Nozzle",
+            temperature(nozzle, display::nozzle_temp_color(nozzle), history.nozzle()),
+        ));
+    }
+    if let Some(bed) = print.bed_temper
+        && field_enabled(Field::Bed)
+    {
+        rows.push((
+            "Bed",
+            temperature(bed, display::bed_temp_color(bed), history.bed()),
+        ));
+    }
+    if let Some(chamber) = print.chamber_temper
+        && field_enabled(Field::Chamber)
+    {
+        rows.push(("Chamber", temperature(chamber, None, history.chamber())));
+    }
+    if let Some(nozzle) = print.nozzle_summary()
+        && field_enabled(Field::Nozzle)
+    {
+        rows.push(("Hotend", nozzle));
+    }
+    if let Some(wifi) = display::format_wifi(print)
+        && field_enabled(Field::Wifi)
+    {
+        rows.push(("WiFi", wifi));
+    }
+    if let Some(fans) = print.fan_speeds()
+        && field_enabled(Field::Fans)
+    {
+        rows.push(("Fans", fans.summary()));
+    }
+    if let Some(update) = print.firmware_update()
+        && field_enabled(Field::Firmware)
+    {
+        rows.push((
+            "Firmware",
+            display::paint(
+                format!("update available: {}", update.summary()),
+                Some(display::Color::Yellow),
+            )
+            .to_string(),
+        ));
+    }
+    if field_enabled(Field::Ams) {
+        for unit in ams_units(print) {
+            rows.push(("AMS", ams_unit_summary(unit)));
+        }
+        if let Some(active) = active_filament(print) {
+            rows.push(("Filament", active));
         }
-    };
-
-    // Apply any overrides
-    let mut final_config = printer_config;
-    if let Some(ip) = ip {
-        validate_ip_address(ip)?;
-        final_config.ip = ip.clone();
     }
-    if let Some(device_id) = device_id {
-        validate_device_id(device_id)?;
-        final_config.device_id = device_id.clone();
+    if let Some(flags) = print.home_flags() {
+        if flags.door_open && field_enabled(Field::Door) {
+            rows.push(("Door", "open".to_string()));
+        }
+        if let Some(problem) = flags.sdcard_problem()
+            && field_enabled(Field::Sdcard)
+        {
+            rows.push(("SD card", problem.to_string()));
+        }
     }
-    if let Some(access_code) = access_code {
-        validate_access_code(access_code)?;
-        final_config.access_code = access_code.clone();
+    if let Some(reason) = &status.fail_reason
+        && field_enabled(Field::Failure)
+    {
+        rows.push((
+            "Failure",
+            display::paint(reason, Some(display::Color::Red)).to_string(),
+        ));
     }
+    rows.push(("Messages", state.message_count.to_string()));
 
-    Ok(final_config)
+    display::render_panel(&title, &rows)
 }
 
-async fn monitor_printer(config: config::PrinterConfig) -> Result<(), Box<dyn std::error::Error>> {
-    const MAX_RETRIES: u32 = 5;
-    const RETRY_DELAY_SECS: u64 = 5;
-
-    let mut retry_count = 0;
-
-    loop {
-        println!(
-            "Connecting to printer '{}' at {} with device ID {} (attempt {}/{})",
-            config.name,
-            config.ip,
-            config.device_id,
-            retry_count + 1,
-            MAX_RETRIES + 1
-        );
+fn ams_units(print: &messages::PrintInfo) -> &[messages::AmsUnit] {
+    print
+        .ams
+        .as_ref()
+        .and_then(|ams| ams.units.as_deref())
+        .unwrap_or_default()
+}
 
-        match attempt_connection(&config).await {
-            Ok(_) => {
-                println!("Connection successful! Monitoring stopped.");
-                return Ok(());
-            }
-            Err(e) => {
-                eprintln!("Connection attempt failed: {e}");
+/// `AMS A: 23% humidity, 27.5°C, drying (45m left)`
+fn ams_unit_summary(unit: &messages::AmsUnit) -> String {
+    let mut parts = Vec::new();
+    match (unit.humidity_percent, unit.humidity) {
+        (Some(percent), _) => parts.push(format!("{percent}% humidity")),
+        (None, Some(level)) => parts.push(format!("humidity level {level}")),
+        (None, None) => {}
+    }
+    if let Some(temperature) = unit.temperature {
+        parts.push(format!(
+            "{:.1}{}",
+            display::temperature(temperature),
+            display::degrees()
+        ));
+    }
+    if unit.is_drying() {
+        let minutes = unit.dry_time.unwrap_or_default();
+        parts.push(format!(
+            "drying ({} left)",
+            display::format_duration_short(minutes * 60)
+        ));
+    }
 
-                retry_count += 1;
-                if retry_count > MAX_RETRIES {
-                    return Err(
-                        format!("Failed to connect after {} attempts", MAX_RETRIES + 1).into(),
-                    );
-                }
+    if parts.is_empty() {
+        unit.label()
+    } else {
+        format!("{}: {}", unit.label(), parts.join(", "))
+    }
+}
 
-                println!("Retrying in {RETRY_DELAY_SECS} seconds...");
-                tokio::time::sleep(tokio::time::Duration::from_secs(RETRY_DELAY_SECS)).await;
-            }
-        }
+fn print_state_summary(state: &messages::MergedState) {
+    for line in state_summary_lines(state) {
+        println!("{line}");
     }
 }
 
-async fn attempt_connection(
-    config: &config::PrinterConfig,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let mqtt_client = mqtt::MqttClient::new(config.clone()).await?;
-    mqtt_client.connect().await?;
+/// The print status and printer status lines of a summary, leaving out empty ones
+fn state_summary_lines(state: &messages::MergedState) -> Vec<String> {
+    [
+        print_status_line(&state.status(), jobs::pause_reason(&state.print).as_ref()),
+        printer_status_line(&state.print),
+    ]
+    .into_iter()
+    .filter(|line| !line.is_empty())
+    .collect()
+}
 
-    let mut eventloop = mqtt_client.get_eventloop();
+fn progress_info(
+    status: &messages::PrinterStatus,
+    message: &messages::DeviceMessage,
+) -> Option<display::ProgressInfo> {
+    use display::{Field, field_enabled};
 
-    loop {
-        match eventloop.poll().await {
-            Ok(notification) => {
-                use rumqttc::{Event, Packet};
-                match notification {
-                    Event::Incoming(packet) => {
-                        match packet {
-                            Packet::Publish(publish) => {
-                                handle_mqtt_message(publish).await;
-                            }
-                            _ => {
-                                // Other packet types (Subscribe, Connect, etc.)
-                            }
-                        }
-                    }
-                    Event::Outgoing(packet) => {
-                        // Less verbose for outgoing packets
-                        if let rumqttc::Outgoing::Publish(_) = packet {
-                            // Only log actual publishes, not pings
-                        }
-                    }
-                }
-            }
-            Err(e) => {
-                return Err(format!("MQTT connection error: {e}").into());
-            }
-        }
+    let print = message.print.as_ref()?;
+    if !field_enabled(Field::Progress) {
+        return None;
     }
-}
-
-async fn handle_mqtt_message(publish: rumqttc::Publish) {
-    let payload_str = match std::str::from_utf8(&publish.payload) {
-        Ok(s) => s,
-        Err(e) => {
-            eprintln!("Failed to parse message as UTF-8: {e}");
-            return;
-        }
-    };
 
-    match messages::DeviceMessage::parse(payload_str) {
-        Ok(message) => {
-            let message_type = message.get_message_type();
-            let sequence_id = message.get_sequence_id().unwrap_or("none");
+    Some(display::ProgressInfo {
+        percent: status.progress?,
+        layer: print.layer_num.filter(|_| field_enabled(Field::Layer)),
+        total_layers: print.total_layer_num,
+        remaining_secs: status
+            .remaining_time
+            .filter(|_| field_enabled(Field::Remaining)),
+        finish_at: status
+            .finish_at
+            .filter(|_| field_enabled(Field::Eta))
+            .map(|finish_at| display::format_finish_time(&finish_at)),
+    })
+}
 
-            match message_type {
-                messages::MessageType::PrintPushStatus => {
-                    if let Some(status) = messages::PrinterStatus::from_device_message(&message) {
-                        handle_print_status(status);
-                    }
-                    // Also show detailed Bambu-specific info
-                    handle_bambu_print_status(&message);
-                }
-                messages::MessageType::PushingPushAll => {
-                    println!("📊 Received complete printer status (pushall)");
-                    handle_pushall_message(&message);
-                }
-                messages::MessageType::SystemPushAll => {
-                    println!("🔧 Received system information");
-                    handle_system_message(&message);
-                }
-                messages::MessageType::Unknown(cmd) => {
-                    println!("❓ Unknown message type: {cmd} (seq: {sequence_id})");
-                }
-            }
-        }
-        Err(e) => {
-            eprintln!("Failed to parse MQTT message: {e}");
-            if payload_str.len() < 1000 {
-                eprintln!("Raw message: {payload_str}");
-            } else {
-                eprintln!("Raw message (truncated): {}...", &payload_str[..500]);
-            }
-        }
+fn handle_print_status(status: messages::PrinterStatus, pause_reason: Option<&jobs::PauseReason>) {
+    let line = print_status_line(&status, pause_reason);
+    if !line.is_empty() {
+        println!("{line}");
     }
 }
 
-fn handle_print_status(status: messages::PrinterStatus) {
+/// State, progress, ETA, remaining time and failure reason, as far as the fields allow
+fn print_status_line(
+    status: &messages::PrinterStatus,
+    pause_reason: Option<&jobs::PauseReason>,
+) -> String {
+    use display::{Field, field_enabled};
     use messages::PrintState;
+    use std::fmt::Write;
 
     let state_icon = match &status.state {
-        PrintState::Idle => "💤",
-        PrintState::Printing => "🖨️",
-        PrintState::Paused => "⏸️",
-        PrintState::Failed => "❌",
-        PrintState::Finished => "✅",
-        PrintState::Unknown(_) => "❓",
+        PrintState::Idle => Icon::Idle,
+        PrintState::Printing => Icon::Printing,
+        PrintState::Paused => Icon::Paused,
+        PrintState::Failed => Icon::Failure,
+        PrintState::Finished => Icon::Finished,
+        PrintState::Unknown(_) => Icon::Unknown,
+    };
+
+    let state_color = match &status.state {
+        PrintState::Printing => Some(display::Color::Green),
+        PrintState::Paused => Some(display::Color::Yellow),
+        PrintState::Failed => Some(display::Color::Red),
+        PrintState::Finished => Some(display::Color::Cyan),
+        PrintState::Idle | PrintState::Unknown(_) => None,
+    };
+
+    let mut line = String::new();
+    let mut push_part = |part: std::fmt::Arguments| {
+        if !line.is_empty() {
+            line.push_str(" - ");
+        }
+        let _ = line.write_fmt(part);
     };
 
-    print!("{state_icon} Print Status: {:?}", status.state);
+    if field_enabled(Field::State) {
+        match pause_reason.filter(|_| status.state == PrintState::Paused) {
+            Some(reason) => push_part(format_args!(
+                "{state_icon} Print Status: {:?} ({})",
+                display::paint(&status.state, state_color),
+                reason.describe()
+            )),
+            None => push_part(format_args!(
+                "{state_icon} Print Status: {:?}",
+                display::paint(&status.state, state_color)
+            )),
+        }
+    }
+
+    if let Some(progress) = status.progress
+        && field_enabled(Field::Progress)
+    {
+        push_part(format_args!("Progress: {progress}%"));
+    }
 
-    if let Some(progress) = status.progress {
-        print!(" - Progress: {progress}%");
+    // Prefer the printer's own ETA string, otherwise derive a local finish time
+    if field_enabled(Field::Eta) {
+        if let Some(eta) = &status.eta {
+            push_part(format_args!("ETA: {eta}"));
+        } else if let Some(finish_at) = &status.finish_at {
+            push_part(format_args!(
+                "ETA: {}",
+                display::format_finish_time(finish_at)
+            ));
+        }
     }
 
-    if let Some(eta) = &status.eta {
-        print!(" - ETA: {eta}");
+    if let Some(elapsed) = status.elapsed_secs(chrono::Local::now())
+        && field_enabled(Field::Remaining)
+    {
+        push_part(format_args!(
+            "Elapsed: {}",
+            display::format_duration_short(elapsed)
+        ));
     }
 
-    if let Some(remaining) = status.remaining_time {
+    if let Some(remaining) = status.remaining_time
+        && field_enabled(Field::Remaining)
+    {
         let hours = remaining / 3600;
         let minutes = (remaining % 3600) / 60;
         let seconds = remaining % 60;
         if hours > 0 {
-            print!(" - Remaining: {hours}h {minutes}m {seconds}s");
+            push_part(format_args!("Remaining: {hours}h {minutes}m {seconds}s"));
         } else {
-            print!(" - Remaining: {minutes}m {seconds}s");
+            push_part(format_args!("Remaining: {minutes}m {seconds}s"));
         }
     }
 
-    if let Some(reason) = &status.fail_reason {
-        print!(" - Failure: {reason}");
+    if let Some(reason) = &status.fail_reason
+        && field_enabled(Field::Failure)
+    {
+        push_part(format_args!(
+            "Failure: {}",
+            display::paint(reason, Some(display::Color::Red))
+        ));
     }
 
-    println!();
+    line
 }
 
 // Enhanced function to show actual printer data from messages
-fn handle_bambu_print_status(message: &messages::DeviceMessage) {
-    if let Some(print_info) = &message.print {
-        let mut info_parts = Vec::new();
+fn handle_bambu_print_status(print_info: &messages::PrintInfo) {
+    let line = printer_status_line(print_info);
+    if !line.is_empty() {
+        println!("{line}");
+    }
+}
 
-        // Temperature info
-        if let Some(nozzle_temp) = print_info.nozzle_temper {
-            info_parts.push(format!("🌡️ Nozzle: {nozzle_temp:.1}°C"));
-        }
+/// Temperatures, hotend, layer, remaining time, WiFi, door, SD card and AMS details
+fn printer_status_line(print_info: &messages::PrintInfo) -> String {
+    use std::fmt::Write;
 
-        if let Some(bed_temp) = print_info.bed_temper {
-            info_parts.push(format!("🛏️ Bed: {bed_temp:.1}°C"));
+    // Build the status line in a single buffer rather than one String per part
+    let mut line = String::new();
+    let mut push_part = |part: std::fmt::Arguments| {
+        if !line.is_empty() {
+            line.push_str(" | ");
         }
+        let _ = line.write_fmt(part);
+    };
 
-        // Print progress info
-        if let Some(layer) = print_info.layer_num {
-            info_parts.push(format!("📄 Layer: {layer}"));
-        }
+    // Temperature info
+    if let Some(nozzle_temp) = print_info.nozzle_temper
+        && display::field_enabled(display::Field::Nozzle)
+    {
+        push_part(format_args!(
+            "{} Nozzle: {:.1}{}",
+            Icon::Temperature,
+            display::paint(
+                display::temperature(nozzle_temp),
+                display::nozzle_temp_color(nozzle_temp)
+            ),
+            display::degrees()
+        ));
+    }
 
-        if let Some(remaining) = print_info.mc_remaining_time {
-            let hours = remaining / 3600;
-            let minutes = (remaining % 3600) / 60;
-            if hours > 0 {
-                info_parts.push(format!("⏱️ Remaining: {hours}h {minutes}m"));
-            } else {
-                info_parts.push(format!("⏱️ Remaining: {minutes}m"));
-            }
+    if let Some(bed_temp) = print_info.bed_temper
+        && display::field_enabled(display::Field::Bed)
+    {
+        push_part(format_args!(
+            "{} Bed: {:.1}{}",
+            Icon::Bed,
+            display::paint(
+                display::temperature(bed_temp),
+                display::bed_temp_color(bed_temp)
+            ),
+            display::degrees()
+        ));
+    }
+
+    if let Some(chamber_temp) = print_info.chamber_temper
+        && display::field_enabled(display::Field::Chamber)
+    {
+        push_part(format_args!(
+            "{} Chamber: {:.1}{}",
+            Icon::Temperature,
+            display::temperature(chamber_temp),
+            display::degrees()
+        ));
+    }
+
+    if let Some(nozzle) = print_info.nozzle_summary()
+        && display::field_enabled(display::Field::Nozzle)
+    {
+        push_part(format_args!("{} Hotend: {nozzle}", Icon::System));
+    }
+
+    // Print progress info
+    if let Some(layer) = print_info.layer_num
+        && display::field_enabled(display::Field::Layer)
+    {
+        push_part(format_args!("{} Layer: {layer}", Icon::Layer));
+    }
+
+    if let Some(remaining) = print_info.mc_remaining_time
+        && display::field_enabled(display::Field::Remaining)
+    {
+        let hours = remaining / 3600;
+        let minutes = (remaining % 3600) / 60;
+        if hours > 0 {
+            push_part(format_args!(
+                "{} Remaining: {hours}h {minutes}m",
+                Icon::Timer
+            ));
+        } else {
+            push_part(format_args!("{} Remaining: {minutes}m", Icon::Timer));
         }
+    }
+
+    if let Some(wifi) = display::format_wifi(print_info)
+        && display::field_enabled(display::Field::Wifi)
+    {
+        push_part(format_args!("{} WiFi: {wifi}", Icon::Wifi));
+    }
+
+    if let Some(fans) = print_info.fan_speeds()
+        && display::field_enabled(display::Field::Fans)
+    {
+        push_part(format_args!("{} Fans: {}", Icon::Fan, fans.summary()));
+    }
+
+    if let Some(update) = print_info.firmware_update()
+        && display::field_enabled(display::Field::Firmware)
+    {
+        push_part(format_args!(
+            "{} Firmware update: {}",
+            Icon::Firmware,
+            update.summary()
+        ));
+    }
 
-        if let Some(wifi) = &print_info.wifi_signal {
-            info_parts.push(format!("📶 WiFi: {wifi}"));
+    if let Some(flags) = print_info.home_flags() {
+        if flags.door_open && display::field_enabled(display::Field::Door) {
+            push_part(format_args!("{} Door open", Icon::Alert));
         }
+        if let Some(problem) = flags.sdcard_problem()
+            && display::field_enabled(display::Field::Sdcard)
+        {
+            push_part(format_args!("{} SD card {problem}", Icon::Alert));
+        }
+    }
 
-        if !info_parts.is_empty() {
-            println!("🖨️ Printer Status: {}", info_parts.join(" | "));
+    if display::field_enabled(display::Field::Ams) {
+        for unit in ams_units(print_info) {
+            push_part(format_args!(
+                "{} {}",
+                Icon::Humidity,
+                ams_unit_summary(unit)
+            ));
         }
+        if let Some(active) = active_filament(print_info) {
+            push_part(format_args!("{} Filament: {active}", Icon::Filament));
+        }
+    }
+
+    if line.is_empty() {
+        line
+    } else {
+        format!("{} Printer Status: {line}", Icon::Printer)
     }
 }
 
@@ -575,51 +5228,57 @@ fn handle_system_message(message: &messages::DeviceMessage) {
 
 fn display_device_info(extra_fields: &std::collections::HashMap<String, serde_json::Value>) {
     // Look for common device information fields
-    if let Some(model) = extra_fields.get("model") {
-        if let Some(model_str) = model.as_str() {
-            println!("  🖨️  Model: {model_str}");
-        }
+    if let Some(model) = extra_fields.get("model")
+        && let Some(model_str) = model.as_str()
+    {
+        println!("  {}  Model: {model_str}", Icon::Printer);
     }
 
-    if let Some(sn) = extra_fields.get("sn") {
-        if let Some(sn_str) = sn.as_str() {
-            println!("  🏷️  Serial Number: {sn_str}");
-        }
+    if let Some(sn) = extra_fields.get("sn")
+        && let Some(sn_str) = sn.as_str()
+    {
+        println!("  {}  Serial Number: {sn_str}", Icon::Serial);
     }
 
-    if let Some(firmware) = extra_fields.get("ota") {
-        if let Some(firmware_obj) = firmware.as_object() {
-            if let Some(version) = firmware_obj.get("version") {
-                if let Some(version_str) = version.as_str() {
-                    println!("  📦 Firmware: {version_str}");
-                }
-            }
-        }
+    if let Some(firmware) = extra_fields.get("ota")
+        && let Some(firmware_obj) = firmware.as_object()
+        && let Some(version) = firmware_obj.get("version")
+        && let Some(version_str) = version.as_str()
+    {
+        println!("  {} Firmware: {version_str}", Icon::Firmware);
     }
 
-    if let Some(wifi) = extra_fields.get("wifi") {
-        if let Some(wifi_obj) = wifi.as_object() {
-            if let Some(ssid) = wifi_obj.get("ssid") {
-                if let Some(ssid_str) = ssid.as_str() {
-                    println!("  📶 WiFi: {ssid_str}");
-                }
-            }
-        }
+    if let Some(wifi) = extra_fields.get("wifi")
+        && let Some(wifi_obj) = wifi.as_object()
+        && let Some(ssid) = wifi_obj.get("ssid")
+        && let Some(ssid_str) = ssid.as_str()
+    {
+        println!("  {} WiFi: {ssid_str}", Icon::Wifi);
     }
 
     // Display temperature information if available
-    if let Some(temp) = extra_fields.get("temp") {
-        if let Some(temp_obj) = temp.as_object() {
-            if let Some(bed_temp) = temp_obj.get("bed_temp") {
-                if let Some(bed_current) = bed_temp.as_f64() {
-                    println!("  🌡️  Bed Temperature: {bed_current}°C");
-                }
-            }
-            if let Some(nozzle_temp) = temp_obj.get("nozzle_temp") {
-                if let Some(nozzle_current) = nozzle_temp.as_f64() {
-                    println!("  🌡️  Nozzle Temperature: {nozzle_current}°C");
-                }
-            }
+    if let Some(temp) = extra_fields.get("temp")
+        && let Some(temp_obj) = temp.as_object()
+    {
+        if let Some(bed_temp) = temp_obj.get("bed_temp")
+            && let Some(bed_current) = bed_temp.as_f64()
+        {
+            println!(
+                "  {}  Bed Temperature: {}{}",
+                Icon::Temperature,
+                display::temperature(bed_current),
+                display::degrees()
+            );
+        }
+        if let Some(nozzle_temp) = temp_obj.get("nozzle_temp")
+            && let Some(nozzle_current) = nozzle_temp.as_f64()
+        {
+            println!(
+                "  {}  Nozzle Temperature: {}{}",
+                Icon::Temperature,
+                display::temperature(nozzle_current),
+                display::degrees()
+            );
         }
     }
 }
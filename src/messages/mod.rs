@@ -1,6 +1,7 @@
-use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Deserializer, Serialize};
 use serde_json::Value;
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use thiserror::Error;
 
 #[cfg(test)]
@@ -41,16 +42,20 @@ pub struct DeviceMessage {
     #[serde(rename = "sequence_id")]
     pub sequence_id: Option<String>,
 
+    /// Topic the message arrived on, when parsed with [`DeviceMessage::parse_from`]
+    #[serde(skip)]
+    pub topic: Option<String>,
+
     #[serde(flatten)]
     pub extra: HashMap<String, Value>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct PrintInfo {
     #[serde(rename = "command")]
     pub command: Option<String>,
 
-    #[serde(rename = "msg")]
+    #[serde(rename = "msg", default, deserialize_with = "lenient_number")]
     pub msg: Option<u32>,
 
     #[serde(rename = "state")]
@@ -59,47 +64,303 @@ pub struct PrintInfo {
     #[serde(rename = "fail_reason")]
     pub fail_reason: Option<String>,
 
-    #[serde(rename = "utc_time")]
+    #[serde(rename = "utc_time", default, deserialize_with = "lenient_number")]
     pub utc_time: Option<u64>,
 
     #[serde(rename = "gcode_state")]
     pub gcode_state: Option<String>,
 
-    #[serde(rename = "percent")]
+    #[serde(rename = "percent", default, deserialize_with = "lenient_number")]
     pub percent: Option<u32>,
 
     #[serde(rename = "eta")]
     pub eta: Option<String>,
 
-    #[serde(rename = "total_time")]
+    #[serde(rename = "total_time", default, deserialize_with = "lenient_number")]
     pub total_time: Option<u32>,
 
-    #[serde(rename = "remaining_time")]
+    #[serde(
+        rename = "remaining_time",
+        default,
+        deserialize_with = "lenient_number"
+    )]
     pub remaining_time: Option<u32>,
 
     // Actual Bambu Labs printer fields
-    #[serde(rename = "nozzle_temper")]
+    #[serde(rename = "nozzle_temper", default, deserialize_with = "lenient_number")]
     pub nozzle_temper: Option<f64>,
 
-    #[serde(rename = "bed_temper")]
+    #[serde(rename = "bed_temper", default, deserialize_with = "lenient_number")]
     pub bed_temper: Option<f64>,
 
-    #[serde(rename = "mc_remaining_time")]
+    #[serde(
+        rename = "chamber_temper",
+        default,
+        deserialize_with = "lenient_number"
+    )]
+    pub chamber_temper: Option<f64>,
+
+    #[serde(
+        rename = "mc_remaining_time",
+        default,
+        deserialize_with = "lenient_number"
+    )]
     pub mc_remaining_time: Option<u32>,
 
-    #[serde(rename = "layer_num")]
+    #[serde(rename = "layer_num", default, deserialize_with = "lenient_number")]
     pub layer_num: Option<u32>,
 
-    #[serde(rename = "wifi_signal")]
+    #[serde(
+        rename = "total_layer_num",
+        default,
+        deserialize_with = "lenient_number"
+    )]
+    pub total_layer_num: Option<u32>,
+
+    #[serde(rename = "wifi_signal", default, deserialize_with = "lenient_string")]
     pub wifi_signal: Option<String>,
 
-    #[serde(rename = "fan_gear")]
+    #[serde(rename = "fan_gear", default, deserialize_with = "lenient_number")]
     pub fan_gear: Option<u32>,
 
+    /// Name of the current job as shown on the printer screen
+    #[serde(rename = "subtask_name")]
+    pub subtask_name: Option<String>,
+
+    /// Installed nozzle material, e.g. `hardened_steel`
+    #[serde(rename = "nozzle_type")]
+    pub nozzle_type: Option<String>,
+
+    /// Installed nozzle diameter in millimeters; firmware sends it as a string
+    #[serde(
+        rename = "nozzle_diameter",
+        default,
+        deserialize_with = "lenient_number"
+    )]
+    pub nozzle_diameter: Option<f64>,
+
+    #[serde(rename = "ams", default, deserialize_with = "lenient_ams")]
+    pub ams: Option<AmsInfo>,
+
+    /// External spool holder, reported with the same fields as an AMS tray
+    #[serde(rename = "vt_tray")]
+    pub vt_tray: Option<AmsTray>,
+
+    #[serde(flatten)]
+    pub extra: HashMap<String, Value>,
+}
+
+/// AMS report; partial updates may carry only `tray_now` without the unit list
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AmsInfo {
+    #[serde(rename = "ams", default)]
+    pub units: Option<Vec<AmsUnit>>,
+
+    #[serde(rename = "tray_now", default, deserialize_with = "lenient_id")]
+    pub tray_now: Option<String>,
+
+    /// Slot the AMS is switching to during a filament change
+    #[serde(rename = "tray_tar", default, deserialize_with = "lenient_id")]
+    pub tray_tar: Option<String>,
+
+    #[serde(flatten)]
+    pub extra: HashMap<String, Value>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AmsUnit {
+    #[serde(rename = "id", default, deserialize_with = "lenient_id")]
+    pub id: Option<String>,
+
+    #[serde(rename = "tray", default)]
+    pub trays: Vec<AmsTray>,
+
+    /// Humidity level from 1 to 5, reported by every AMS
+    #[serde(rename = "humidity", default, deserialize_with = "lenient_number")]
+    pub humidity: Option<u32>,
+
+    /// Relative humidity in percent, reported by newer AMS firmware
+    #[serde(rename = "humidity_raw", default, deserialize_with = "lenient_number")]
+    pub humidity_percent: Option<u32>,
+
+    #[serde(rename = "temp", default, deserialize_with = "lenient_number")]
+    pub temperature: Option<f64>,
+
+    /// Minutes of drying left; non-zero while the unit is drying its filament
+    #[serde(rename = "dry_time", default, deserialize_with = "lenient_number")]
+    pub dry_time: Option<u32>,
+
+    #[serde(flatten)]
+    pub extra: HashMap<String, Value>,
+}
+
+impl AmsUnit {
+    /// `AMS A` for unit 0, matching the tray labels shown on the printer
+    pub fn label(&self) -> String {
+        match self.id.as_deref().and_then(|id| id.parse::<u8>().ok()) {
+            Some(unit) if unit < 26 => format!("AMS {}", char::from(b'A' + unit)),
+            _ => format!("AMS {}", self.id.as_deref().unwrap_or("?")),
+        }
+    }
+
+    pub fn is_drying(&self) -> bool {
+        self.dry_time.is_some_and(|minutes| minutes > 0)
+    }
+}
+
+/// `tray_now` of the external spool holder, reported as `vt_tray`
+pub const EXTERNAL_SLOT: u32 = 254;
+
+/// One AMS tray, or the external spool holder
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AmsTray {
+    #[serde(rename = "id", default, deserialize_with = "lenient_id")]
+    pub id: Option<String>,
+
+    /// Remaining filament in percent; `-1` when the spool has no RFID tag
+    #[serde(rename = "remain", default, deserialize_with = "lenient_number")]
+    pub remain: Option<i32>,
+
+    #[serde(rename = "tray_type")]
+    pub tray_type: Option<String>,
+
+    #[serde(rename = "tray_color")]
+    pub tray_color: Option<String>,
+
+    /// Nominal spool weight in grams
+    #[serde(rename = "tray_weight", default, deserialize_with = "lenient_number")]
+    pub tray_weight: Option<f64>,
+
+    /// Pressure advance (K) from the tray's flow calibration
+    #[serde(rename = "k", default, deserialize_with = "lenient_number")]
+    pub k: Option<f64>,
+
+    /// Flow dynamics coefficient (n) from the tray's flow calibration
+    #[serde(rename = "n", default, deserialize_with = "lenient_number")]
+    pub n: Option<f64>,
+
+    /// Calibration profile in use; `-1` for the filament's default values
+    #[serde(rename = "cali_idx", default, deserialize_with = "lenient_number")]
+    pub cali_idx: Option<i32>,
+
     #[serde(flatten)]
     pub extra: HashMap<String, Value>,
 }
 
+impl AmsInfo {
+    fn merge_from(&mut self, update: &AmsInfo) {
+        if update.units.is_some() {
+            self.units = update.units.clone();
+        }
+        if update.tray_now.is_some() {
+            self.tray_now = update.tray_now.clone();
+        }
+        if update.tray_tar.is_some() {
+            self.tray_tar = update.tray_tar.clone();
+        }
+        self.extra
+            .extend(update.extra.iter().map(|(k, v)| (k.clone(), v.clone())));
+    }
+}
+
+/// Numeric types that firmware may also send as strings (e.g. `"percent": "45"`)
+trait LenientNumber: Sized {
+    fn from_value(value: &Value) -> Option<Self>;
+}
+
+impl LenientNumber for u32 {
+    fn from_value(value: &Value) -> Option<Self> {
+        // Whole-number floats such as 45.0 are accepted too; 45.5 is not a count
+        let from_f64 = |f: f64| (f >= 0.0 && f.fract() == 0.0).then_some(f as u64);
+        let number = match value {
+            Value::Number(n) => n.as_u64().or_else(|| n.as_f64().and_then(from_f64)),
+            Value::String(s) => {
+                let s = s.trim();
+                s.parse().ok().or_else(|| s.parse().ok().and_then(from_f64))
+            }
+            _ => None,
+        }?;
+        u32::try_from(number).ok()
+    }
+}
+
+impl LenientNumber for u64 {
+    fn from_value(value: &Value) -> Option<Self> {
+        match value {
+            Value::Number(n) => n.as_u64(),
+            Value::String(s) => s.trim().parse().ok(),
+            _ => None,
+        }
+    }
+}
+
+impl LenientNumber for i32 {
+    fn from_value(value: &Value) -> Option<Self> {
+        let number = match value {
+            Value::Number(n) => n.as_i64(),
+            Value::String(s) => s.trim().parse().ok(),
+            _ => None,
+        }?;
+        i32::try_from(number).ok()
+    }
+}
+
+impl LenientNumber for f64 {
+    fn from_value(value: &Value) -> Option<Self> {
+        match value {
+            Value::Number(n) => n.as_f64(),
+            Value::String(s) => s.trim().parse().ok(),
+            _ => None,
+        }
+    }
+}
+
+/// Accepts a number or a numeric string; values of any other shape become `None`
+/// rather than failing the whole message
+fn lenient_number<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: LenientNumber,
+{
+    let value = Option::<Value>::deserialize(deserializer)?;
+    Ok(value.as_ref().and_then(T::from_value))
+}
+
+/// Accepts a string or a bare number; numbers are treated as dBm like the string form
+fn lenient_string<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = Option::<Value>::deserialize(deserializer)?;
+    Ok(match value {
+        Some(Value::String(s)) => Some(s),
+        Some(Value::Number(n)) => Some(format!("{n}dBm")),
+        _ => None,
+    })
+}
+
+/// Accepts an id sent either as a string (`"0"`) or a number (`0`)
+fn lenient_id<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = Option::<Value>::deserialize(deserializer)?;
+    Ok(match value {
+        Some(Value::String(s)) => Some(s),
+        Some(Value::Number(n)) => Some(n.to_string()),
+        _ => None,
+    })
+}
+
+/// Accepts an AMS object; anything else is dropped rather than failing the whole message
+fn lenient_ams<'de, D>(deserializer: D) -> Result<Option<AmsInfo>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = Option::<Value>::deserialize(deserializer)?;
+    Ok(value.and_then(|value| serde_json::from_value(value).ok()))
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SystemInfo {
     #[serde(rename = "command")]
@@ -127,6 +388,80 @@ pub struct DeviceInfo {
     pub extra: HashMap<String, Value>,
 }
 
+/// Modules whose serial and hardware version stand for the printer as a whole, in order of
+/// preference: the application processor (`rv1126` on the X1 series) before the motion
+/// controller
+const MAIN_MODULES: [&str; 3] = ["rv1126", "ap", "mc"];
+
+impl DeviceInfo {
+    /// Device details from a `get_version` reply, or `None` for other info messages
+    pub fn version_report(&self) -> Option<VersionReport> {
+        if self.command.as_deref() != Some("get_version") {
+            return None;
+        }
+        let modules: Vec<ModuleVersion> = self
+            .extra
+            .get("module")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(|module| {
+                let text = |key: &str| {
+                    module
+                        .get(key)
+                        .and_then(Value::as_str)
+                        .filter(|text| !text.is_empty())
+                        .map(str::to_string)
+                };
+                Some(ModuleVersion {
+                    name: text("name")?,
+                    product_name: text("product_name"),
+                    firmware: text("sw_ver"),
+                    hardware: text("hw_ver"),
+                    serial: text("sn"),
+                })
+            })
+            .collect();
+
+        let main = MAIN_MODULES
+            .iter()
+            .find_map(|name| modules.iter().find(|module| module.name == *name));
+        Some(VersionReport {
+            serial: main.and_then(|module| module.serial.clone()),
+            model: modules
+                .iter()
+                .find_map(|module| module.product_name.clone()),
+            hardware_revision: main.and_then(|module| module.hardware.clone()),
+            firmware: modules
+                .iter()
+                .find(|module| module.name == "ota")
+                .and_then(|module| module.firmware.clone()),
+            modules,
+        })
+    }
+}
+
+/// Serial, model and firmware of a printer, as answered to `get_version`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct VersionReport {
+    pub serial: Option<String>,
+    pub model: Option<String>,
+    pub hardware_revision: Option<String>,
+    /// Version of the printer firmware as a whole (the `ota` module)
+    pub firmware: Option<String>,
+    pub modules: Vec<ModuleVersion>,
+}
+
+/// One entry of the `get_version` module list, e.g. `ota`, `mc` or `ams/0`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ModuleVersion {
+    pub name: String,
+    pub product_name: Option<String>,
+    pub firmware: Option<String>,
+    pub hardware: Option<String>,
+    pub serial: Option<String>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PushingInfo {
     #[serde(rename = "command")]
@@ -152,27 +487,39 @@ pub enum MessageType {
 
 impl DeviceMessage {
     pub fn parse(json_data: &str) -> Result<Self, MessageError> {
-        let message: DeviceMessage = serde_json::from_str(json_data)?;
+        Self::parse_bytes(json_data.as_bytes())
+    }
+
+    /// Parses a raw MQTT payload without first copying or validating it as a `str`
+    pub fn parse_bytes(payload: &[u8]) -> Result<Self, MessageError> {
+        let message: DeviceMessage = serde_json::from_slice(payload)?;
+        Ok(message)
+    }
+
+    /// Parses a payload received on `topic`, tagging the message with it
+    pub fn parse_from(topic: &str, payload: &[u8]) -> Result<Self, MessageError> {
+        let mut message = Self::parse_bytes(payload)?;
+        message.topic = Some(topic.to_string());
         Ok(message)
     }
 
     pub fn get_message_type(&self) -> MessageType {
-        if let Some(print) = &self.print {
-            if print.command.as_deref() == Some("push_status") {
-                return MessageType::PrintPushStatus;
-            }
+        if let Some(print) = &self.print
+            && print.command.as_deref() == Some("push_status")
+        {
+            return MessageType::PrintPushStatus;
         }
 
-        if let Some(pushing) = &self.pushing {
-            if pushing.command.as_deref() == Some("pushall") {
-                return MessageType::PushingPushAll;
-            }
+        if let Some(pushing) = &self.pushing
+            && pushing.command.as_deref() == Some("pushall")
+        {
+            return MessageType::PushingPushAll;
         }
 
-        if let Some(system) = &self.system {
-            if system.command.as_deref() == Some("pushall") {
-                return MessageType::SystemPushAll;
-            }
+        if let Some(system) = &self.system
+            && system.command.as_deref() == Some("pushall")
+        {
+            return MessageType::SystemPushAll;
         }
 
         let command = self
@@ -189,6 +536,26 @@ impl DeviceMessage {
         }
     }
 
+    /// Dotted paths of every field that fell through to an `extra` map, sorted
+    pub fn unknown_fields(&self) -> Vec<String> {
+        let mut fields: Vec<String> = self.extra.keys().cloned().collect();
+
+        let sections = [
+            ("print", self.print.as_ref().map(|p| &p.extra)),
+            ("system", self.system.as_ref().map(|s| &s.extra)),
+            ("info", self.info.as_ref().map(|i| &i.extra)),
+            ("pushing", self.pushing.as_ref().map(|p| &p.extra)),
+        ];
+        for (section, extra) in sections {
+            if let Some(extra) = extra {
+                fields.extend(extra.keys().map(|key| format!("{section}.{key}")));
+            }
+        }
+
+        fields.sort();
+        fields
+    }
+
     pub fn get_sequence_id(&self) -> Option<&str> {
         self.sequence_id
             .as_deref()
@@ -198,24 +565,32 @@ impl DeviceMessage {
     }
 }
 
-#[derive(Debug, Clone)]
+/// Printer status derived from the reported fields. Serialized field names are stable, for
+/// JSON output and the APIs.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct PrinterStatus {
     pub state: PrintState,
     pub progress: Option<u32>,
     pub eta: Option<String>,
+    /// Local time the print is expected to finish, from the remaining time
+    pub finish_at: Option<DateTime<Local>>,
+    /// Local time the job started, as tracked by the monitor; printers do not report it
+    pub started_at: Option<DateTime<Local>>,
     pub remaining_time: Option<u32>,
-    #[allow(dead_code)]
     pub total_time: Option<u32>,
     pub fail_reason: Option<String>,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+/// Serialized as its lowercase name, e.g. `printing`; unknown states as reported
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum PrintState {
     Idle,
     Printing,
     Paused,
     Failed,
     Finished,
+    #[serde(untagged)]
     Unknown(String),
 }
 
@@ -232,13 +607,48 @@ impl From<&str> for PrintState {
     }
 }
 
+impl PrintState {
+    /// Maps the firmware's `gcode_state` (e.g. `RUNNING`, `FINISH`) onto a print state
+    pub fn from_gcode_state(gcode_state: &str) -> Self {
+        match gcode_state {
+            "IDLE" => PrintState::Idle,
+            "PREPARE" | "SLICING" | "RUNNING" => PrintState::Printing,
+            "PAUSE" => PrintState::Paused,
+            "FAILED" => PrintState::Failed,
+            "FINISH" => PrintState::Finished,
+            other => PrintState::Unknown(other.to_string()),
+        }
+    }
+
+    /// Lowercase name, as serialized; unknown states as reported
+    pub fn name(&self) -> &str {
+        match self {
+            PrintState::Idle => "idle",
+            PrintState::Printing => "printing",
+            PrintState::Paused => "paused",
+            PrintState::Failed => "failed",
+            PrintState::Finished => "finished",
+            PrintState::Unknown(raw) => raw,
+        }
+    }
+
+    /// Finished or failed; nothing further will happen until a new job starts
+    pub fn is_terminal(&self) -> bool {
+        matches!(self, PrintState::Finished | PrintState::Failed)
+    }
+}
+
 impl PrinterStatus {
     pub fn from_device_message(msg: &DeviceMessage) -> Option<Self> {
-        let print = msg.print.as_ref()?;
+        Some(Self::from_print_info(msg.print.as_ref()?))
+    }
 
+    pub fn from_print_info(print: &PrintInfo) -> Self {
         // Try to get explicit state first, then infer from available data
         let state = if let Some(explicit_state) = &print.state {
             PrintState::from(explicit_state.as_str())
+        } else if let Some(gcode_state) = &print.gcode_state {
+            PrintState::from_gcode_state(gcode_state)
         } else {
             // Infer state from available data
             if print.mc_remaining_time.is_some() && print.mc_remaining_time.unwrap_or(0) > 0 {
@@ -254,13 +664,485 @@ impl PrinterStatus {
         // Use mc_remaining_time if available, fallback to remaining_time
         let remaining_time = print.mc_remaining_time.or(print.remaining_time);
 
-        Some(PrinterStatus {
+        // Some firmwares omit `percent` but still count layers
+        let progress = print
+            .percent
+            .or_else(|| match (print.layer_num, print.total_layer_num) {
+                (Some(layer), Some(total)) if total > 0 => Some((layer.min(total) * 100) / total),
+                _ => None,
+            });
+
+        PrinterStatus {
+            finish_at: finish_time(&state, remaining_time, Local::now()),
+            started_at: None,
             state,
-            progress: print.percent,
+            progress,
             eta: print.eta.clone(),
             remaining_time,
             total_time: print.total_time,
             fail_reason: print.fail_reason.clone(),
-        })
+        }
+    }
+
+    /// Seconds since the job started, when the start is known
+    pub fn elapsed_secs(&self, now: DateTime<Local>) -> Option<u32> {
+        let started_at = self.started_at?;
+        Some((now - started_at).num_seconds().max(0) as u32)
+    }
+}
+
+/// When a print with `remaining` time left at `now` will finish; only a running print has one
+fn finish_time(
+    state: &PrintState,
+    remaining: Option<u32>,
+    now: DateTime<Local>,
+) -> Option<DateTime<Local>> {
+    let remaining = remaining.filter(|remaining| *remaining > 0)?;
+    (*state == PrintState::Printing).then(|| now + chrono::Duration::seconds(i64::from(remaining)))
+}
+
+impl PrintInfo {
+    /// Overlays the fields present in `update`, keeping previous values for absent ones.
+    /// P1 printers send partial deltas, so a single message rarely has every field.
+    pub fn merge_from(&mut self, update: &PrintInfo) {
+        macro_rules! merge_fields {
+            ($($field:ident),* $(,)?) => {
+                $(
+                    if update.$field.is_some() {
+                        self.$field = update.$field.clone();
+                    }
+                )*
+            };
+        }
+
+        merge_fields!(
+            command,
+            msg,
+            state,
+            fail_reason,
+            utc_time,
+            gcode_state,
+            percent,
+            eta,
+            total_time,
+            remaining_time,
+            nozzle_temper,
+            bed_temper,
+            chamber_temper,
+            mc_remaining_time,
+            layer_num,
+            total_layer_num,
+            wifi_signal,
+            fan_gear,
+            subtask_name,
+            nozzle_type,
+            nozzle_diameter,
+            vt_tray,
+        );
+
+        if let Some(ams) = &update.ams {
+            self.ams
+                .get_or_insert_with(AmsInfo::default)
+                .merge_from(ams);
+        }
+
+        self.extra
+            .extend(update.extra.iter().map(|(k, v)| (k.clone(), v.clone())));
+    }
+
+    /// Installed nozzle as `0.4 mm hardened steel`
+    pub fn nozzle_summary(&self) -> Option<String> {
+        let kind = self
+            .nozzle_type
+            .as_deref()
+            .filter(|kind| !kind.is_empty())
+            .map(|kind| kind.replace('_', " "));
+        match (self.nozzle_diameter, kind) {
+            (Some(diameter), Some(kind)) => Some(format!("{diameter} mm {kind}")),
+            (Some(diameter), None) => Some(format!("{diameter} mm")),
+            (None, kind) => kind,
+        }
+    }
+
+    /// Signal strength in dBm from `wifi_signal`, e.g. `-45dBm`
+    pub fn wifi_dbm(&self) -> Option<i32> {
+        let signal = self.wifi_signal.as_deref()?.trim();
+        let number = signal
+            .strip_suffix("dBm")
+            .or_else(|| signal.strip_suffix("dbm"))
+            .unwrap_or(signal);
+        number.trim().parse().ok()
+    }
+
+    pub fn wifi_quality(&self) -> Option<WifiQuality> {
+        self.wifi_dbm().map(WifiQuality::from_dbm)
+    }
+
+    /// Identify IDs of the objects skipped so far in the current job (`s_obj`)
+    pub fn skipped_objects(&self) -> Vec<u32> {
+        self.extra
+            .get("s_obj")
+            .and_then(Value::as_array)
+            .map(|ids| {
+                ids.iter()
+                    .filter_map(|id| id.as_u64().and_then(|id| u32::try_from(id).ok()))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Timelapse setting from the `ipcam` report, if the printer has sent one
+    pub fn timelapse_enabled(&self) -> Option<bool> {
+        match self.extra.get("ipcam")?.get("timelapse")?.as_str()? {
+            "enable" => Some(true),
+            "disable" => Some(false),
+            _ => None,
+        }
+    }
+
+    /// Slot feeding the printer: AMS trays are numbered four per unit and the external spool
+    /// is [`EXTERNAL_SLOT`]. Printers without an AMS always feed from the external spool.
+    pub fn active_slot(&self) -> Option<u32> {
+        let has_ams = self
+            .ams
+            .as_ref()
+            .and_then(|ams| ams.units.as_ref())
+            .is_some_and(|units| !units.is_empty());
+        if !has_ams {
+            return self.vt_tray.is_some().then_some(EXTERNAL_SLOT);
+        }
+        // 255 means nothing is loaded
+        let slot: u32 = self.ams.as_ref()?.tray_now.as_deref()?.parse().ok()?;
+        (slot != 255).then_some(slot)
+    }
+
+    /// Slot the AMS is loading, numbered like [`active_slot`](Self::active_slot)
+    pub fn target_slot(&self) -> Option<u32> {
+        let slot: u32 = self.ams.as_ref()?.tray_tar.as_deref()?.parse().ok()?;
+        (slot != 255).then_some(slot)
+    }
+
+    /// Tray feeding the printer: an AMS tray, or the external spool
+    pub fn active_tray(&self) -> Option<&AmsTray> {
+        let slot = self.active_slot()?;
+        if slot == EXTERNAL_SLOT {
+            return self.vt_tray.as_ref();
+        }
+        let (unit, tray) = ((slot / 4).to_string(), (slot % 4).to_string());
+        self.ams
+            .as_ref()?
+            .units
+            .as_ref()?
+            .iter()
+            .find(|candidate| candidate.id.as_deref() == Some(unit.as_str()))?
+            .trays
+            .iter()
+            .find(|candidate| candidate.id.as_deref() == Some(tray.as_str()))
+    }
+
+    /// Fan speeds decoded from `fan_gear`, if the printer has sent it
+    pub fn fan_speeds(&self) -> Option<FanSpeeds> {
+        self.fan_gear.map(FanSpeeds::decode)
+    }
+
+    /// Pending firmware update from `upgrade_state`, if the printer offers one
+    pub fn firmware_update(&self) -> Option<FirmwareUpdate> {
+        let state = self.extra.get("upgrade_state")?;
+        // 1 means a new version is available, 2 that the firmware is current
+        let available = state.get("new_version_state").and_then(Value::as_i64) == Some(1);
+        let forced = state
+            .get("force_upgrade")
+            .and_then(Value::as_bool)
+            .unwrap_or(false);
+        let modules: Vec<ModuleUpdate> = state
+            .get("new_ver_list")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(|module| {
+                let text = |key: &str| module.get(key).and_then(Value::as_str).map(str::to_string);
+                let current = text("cur_ver");
+                let new = text("new_ver").filter(|new| current.as_ref() != Some(new))?;
+                Some(ModuleUpdate {
+                    name: text("name")?,
+                    current,
+                    new,
+                })
+            })
+            .collect();
+        (available || forced || !modules.is_empty()).then_some(FirmwareUpdate { modules, forced })
+    }
+
+    /// Decoded `home_flag` bitfield, if the printer has sent one
+    pub fn home_flags(&self) -> Option<HomeFlags> {
+        // Sent as a signed 32-bit integer, so the highest flag shows up as a negative number
+        let flag = self.extra.get("home_flag")?.as_i64()?;
+        Some(HomeFlags::decode(flag as u32))
+    }
+}
+
+/// Fan speeds in percent, packed into the `fan_gear` report field one byte per fan
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FanSpeeds {
+    /// Fan on the toolhead cooling the printed part
+    pub part_cooling: u32,
+    /// Auxiliary fan on the side of the enclosure
+    pub aux: u32,
+    pub chamber: u32,
+}
+
+impl FanSpeeds {
+    pub fn decode(fan_gear: u32) -> Self {
+        // Each fan runs at a gear of 0 to 255
+        let percent = |shift: u32| ((fan_gear >> shift) & 0xff) * 100 / 255;
+        Self {
+            part_cooling: percent(0),
+            aux: percent(8),
+            chamber: percent(16),
+        }
+    }
+
+    /// `part 100%, aux 40%, chamber 0%`
+    pub fn summary(&self) -> String {
+        format!(
+            "part {}%, aux {}%, chamber {}%",
+            self.part_cooling, self.aux, self.chamber
+        )
+    }
+}
+
+/// Newer firmware the printer offers to install
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FirmwareUpdate {
+    /// Modules with a newer version; may be empty when the printer only flags the update
+    pub modules: Vec<ModuleUpdate>,
+    /// The printer requires the update before it prints again
+    pub forced: bool,
+}
+
+impl FirmwareUpdate {
+    /// `ota 01.07.00.00 → 01.08.00.00, ams 00.00.06.40 → 00.00.06.49 (required)`
+    pub fn summary(&self) -> String {
+        let mut summary = if self.modules.is_empty() {
+            "new version".to_string()
+        } else {
+            self.modules
+                .iter()
+                .map(|module| match &module.current {
+                    Some(current) => format!("{} {current} → {}", module.name, module.new),
+                    None => format!("{} {}", module.name, module.new),
+                })
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        if self.forced {
+            summary.push_str(" (required)");
+        }
+        summary
+    }
+}
+
+/// One entry of `new_ver_list`, e.g. module `ota` (the printer itself) or `ams`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModuleUpdate {
+    pub name: String,
+    pub current: Option<String>,
+    pub new: String,
+}
+
+/// Printer hardware and settings state packed into the `home_flag` report field
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct HomeFlags {
+    pub x_homed: bool,
+    pub y_homed: bool,
+    pub z_homed: bool,
+    /// Power supply set to 220 V rather than 110 V
+    pub is_220v: bool,
+    /// Automatic recovery from step loss is enabled
+    pub step_loss_recovery: bool,
+    pub camera_recording: bool,
+    /// AMS estimates remaining filament on spools without RFID
+    pub ams_calibrate_remaining: bool,
+    pub sdcard_present: bool,
+    /// SD card inserted but unreadable
+    pub sdcard_abnormal: bool,
+    /// AMS switches to a matching spool when one runs out
+    pub ams_auto_switch: bool,
+    pub wired_network: bool,
+    pub filament_tangle_detected: bool,
+    /// Only printers with a door sensor (X1 series) ever report the door open
+    pub door_open: bool,
+}
+
+impl HomeFlags {
+    pub fn decode(flag: u32) -> Self {
+        let bit = |mask: u32| flag & mask != 0;
+        Self {
+            x_homed: bit(0x0000_0001),
+            y_homed: bit(0x0000_0002),
+            z_homed: bit(0x0000_0004),
+            is_220v: bit(0x0000_0008),
+            step_loss_recovery: bit(0x0000_0010),
+            camera_recording: bit(0x0000_0020),
+            ams_calibrate_remaining: bit(0x0000_0080),
+            sdcard_present: bit(0x0000_0100),
+            sdcard_abnormal: bit(0x0000_0200),
+            ams_auto_switch: bit(0x0000_0400),
+            wired_network: bit(0x0004_0000),
+            filament_tangle_detected: bit(0x0010_0000),
+            door_open: bit(0x0080_0000),
+        }
+    }
+
+    /// `missing`, `abnormal`, or `None` for a working card
+    pub fn sdcard_problem(&self) -> Option<&'static str> {
+        if self.sdcard_abnormal {
+            Some("abnormal")
+        } else if !self.sdcard_present {
+            Some("missing")
+        } else {
+            None
+        }
+    }
+}
+
+/// How well the printer receives its WiFi network
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WifiQuality {
+    Excellent,
+    Good,
+    Weak,
+}
+
+impl WifiQuality {
+    /// Signals of -55 dBm and up are excellent, down to -70 dBm good, and weaker ones weak
+    pub fn from_dbm(dbm: i32) -> Self {
+        if dbm >= -55 {
+            WifiQuality::Excellent
+        } else if dbm >= -70 {
+            WifiQuality::Good
+        } else {
+            WifiQuality::Weak
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            WifiQuality::Excellent => "excellent",
+            WifiQuality::Good => "good",
+            WifiQuality::Weak => "weak",
+        }
+    }
+}
+
+/// Printer state accumulated across every message received so far. Serialized with the
+/// reported fields under `print`, named as the printer names them.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MergedState {
+    pub print: PrintInfo,
+    pub message_count: u64,
+    /// Energy used since monitoring started, from the printer's smart plug if one is configured
+    pub energy_kwh: Option<f64>,
+    /// Finish time of the running print, fixed when the remaining time was reported
+    pub finish_at: Option<DateTime<Local>>,
+    /// Start of the running job, filled in by the monitor's job tracking
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub started_at: Option<DateTime<Local>>,
+    /// Topic of the latest message, which differs from the report topic for messages on a
+    /// printer's `extra_topics`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_topic: Option<String>,
+}
+
+impl MergedState {
+    pub fn update(&mut self, message: &DeviceMessage) {
+        self.update_at(message, Local::now());
+    }
+
+    /// Merges a message received at `now`
+    pub fn update_at(&mut self, message: &DeviceMessage, now: DateTime<Local>) {
+        self.message_count += 1;
+        if let Some(topic) = &message.topic {
+            self.source_topic = Some(topic.clone());
+        }
+        let Some(print) = &message.print else {
+            return;
+        };
+        let previous = self.status().state;
+        self.print.merge_from(print);
+
+        // P1 printers only resend the remaining time when it changes, so the finish time is
+        // kept from then rather than drifting with every other report or a reconnect
+        let status = PrinterStatus::from_print_info(&self.print);
+        let reported = print.mc_remaining_time.is_some() || print.remaining_time.is_some();
+        if reported || status.state != previous || status.state != PrintState::Printing {
+            self.finish_at = finish_time(&status.state, status.remaining_time, now);
+        }
+    }
+
+    /// True once the fields needed for a one-line summary have all been seen
+    pub fn is_complete(&self) -> bool {
+        (self.print.state.is_some() || self.print.gcode_state.is_some())
+            && self.status().progress.is_some()
+            && self.print.nozzle_temper.is_some()
+            && self.print.bed_temper.is_some()
+    }
+
+    pub fn status(&self) -> PrinterStatus {
+        PrinterStatus {
+            finish_at: self.finish_at,
+            started_at: self.started_at,
+            ..PrinterStatus::from_print_info(&self.print)
+        }
+    }
+}
+
+/// Rolling window of recent temperature readings per sensor, oldest first
+#[derive(Debug, Clone)]
+pub struct TemperatureHistory {
+    capacity: usize,
+    nozzle: VecDeque<f64>,
+    bed: VecDeque<f64>,
+    chamber: VecDeque<f64>,
+}
+
+impl TemperatureHistory {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            nozzle: VecDeque::with_capacity(capacity),
+            bed: VecDeque::with_capacity(capacity),
+            chamber: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    /// Appends every reading present in `print`, dropping the oldest beyond capacity
+    pub fn record(&mut self, print: &PrintInfo) {
+        let capacity = self.capacity;
+        let push = |samples: &mut VecDeque<f64>, reading: Option<f64>| {
+            if let Some(reading) = reading {
+                if samples.len() == capacity {
+                    samples.pop_front();
+                }
+                samples.push_back(reading);
+            }
+        };
+
+        push(&mut self.nozzle, print.nozzle_temper);
+        push(&mut self.bed, print.bed_temper);
+        push(&mut self.chamber, print.chamber_temper);
+    }
+
+    pub fn nozzle(&self) -> &VecDeque<f64> {
+        &self.nozzle
+    }
+
+    pub fn bed(&self) -> &VecDeque<f64> {
+        &self.bed
+    }
+
+    pub fn chamber(&self) -> &VecDeque<f64> {
+        &self.chamber
     }
 }
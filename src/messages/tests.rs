@@ -296,3 +296,650 @@ fn test_parse_bambu_print_status_message() {
     assert_eq!(status.state, PrintState::Printing); // Should infer printing from remaining time
     assert_eq!(status.remaining_time, Some(1015));
 }
+
+#[test]
+fn test_unknown_fields() {
+    let json_data = r#"{
+        "print": {
+            "command": "push_status",
+            "percent": 10,
+            "lights_report": [],
+            "hms": []
+        },
+        "info": {
+            "command": "get_version",
+            "module": []
+        },
+        "model": "X1C"
+    }"#;
+
+    let message = DeviceMessage::parse(json_data).unwrap();
+    assert_eq!(
+        message.unknown_fields(),
+        vec!["info.module", "model", "print.hms", "print.lights_report"]
+    );
+
+    let known_only = DeviceMessage::parse(r#"{"print": {"percent": 1}}"#).unwrap();
+    assert!(known_only.unknown_fields().is_empty());
+}
+
+#[test]
+fn test_numbers_sent_as_strings() {
+    let json_data = r#"{
+        "print": {
+            "command": "push_status",
+            "percent": "45",
+            "layer_num": " 12 ",
+            "mc_remaining_time": "600",
+            "nozzle_temper": "219.5",
+            "bed_temper": 55,
+            "utc_time": "1700000000",
+            "fan_gear": 15.0,
+            "wifi_signal": -45
+        }
+    }"#;
+
+    let message = DeviceMessage::parse(json_data).unwrap();
+    let print = message.print.unwrap();
+    assert_eq!(print.percent, Some(45));
+    assert_eq!(print.layer_num, Some(12));
+    assert_eq!(print.mc_remaining_time, Some(600));
+    assert_eq!(print.nozzle_temper, Some(219.5));
+    assert_eq!(print.bed_temper, Some(55.0));
+    assert_eq!(print.utc_time, Some(1_700_000_000));
+    assert_eq!(print.fan_gear, Some(15));
+    assert_eq!(print.wifi_signal, Some("-45dBm".to_string()));
+}
+
+#[test]
+fn test_mismatched_numeric_types_do_not_drop_message() {
+    let json_data = r#"{
+        "print": {
+            "command": "push_status",
+            "percent": "n/a",
+            "layer_num": -3,
+            "nozzle_temper": null,
+            "bed_temper": {"current": 55},
+            "wifi_signal": true
+        }
+    }"#;
+
+    let message = DeviceMessage::parse(json_data).unwrap();
+    let print = message.print.unwrap();
+    assert_eq!(print.command, Some("push_status".to_string()));
+    assert_eq!(print.percent, None);
+    assert_eq!(print.layer_num, None);
+    assert_eq!(print.nozzle_temper, None);
+    assert_eq!(print.bed_temper, None);
+    assert_eq!(print.wifi_signal, None);
+}
+
+#[test]
+fn test_fractional_counts_are_not_rounded() {
+    let parse = |percent: &str| {
+        DeviceMessage::parse(&format!(r#"{{"print": {{"percent": {percent}}}}}"#))
+            .unwrap()
+            .print
+            .unwrap()
+            .percent
+    };
+    assert_eq!(parse("45.0"), Some(45));
+    assert_eq!(parse(r#""45.0""#), Some(45));
+    assert_eq!(parse("45.5"), None);
+    assert_eq!(parse(r#""99.9""#), None);
+    assert_eq!(parse("-1.0"), None);
+}
+
+#[test]
+fn test_parse_bytes_matches_parse() {
+    let json_data = r#"{"print": {"command": "push_status", "percent": 45}, "sequence_id": "7"}"#;
+
+    let from_str = DeviceMessage::parse(json_data).unwrap();
+    let from_bytes = DeviceMessage::parse_bytes(json_data.as_bytes()).unwrap();
+
+    assert_eq!(
+        from_bytes.print.unwrap().percent,
+        from_str.print.unwrap().percent
+    );
+    assert_eq!(from_bytes.sequence_id, from_str.sequence_id);
+}
+
+#[test]
+fn test_parse_bytes_rejects_invalid_utf8() {
+    let result = DeviceMessage::parse_bytes(b"{\"print\": {\"state\": \"\xff\"}}");
+    assert!(matches!(result, Err(MessageError::JsonParseError(_))));
+}
+
+#[test]
+fn test_merged_state_accumulates_partial_updates() {
+    let mut state = MergedState::default();
+    assert!(!state.is_complete());
+
+    let first = DeviceMessage::parse(
+        r#"{"print": {"command": "push_status", "state": "printing", "percent": 10, "nozzle_temper": 220.0}}"#,
+    )
+    .unwrap();
+    state.update(&first);
+    assert!(!state.is_complete());
+
+    let second = DeviceMessage::parse(
+        r#"{"print": {"command": "push_status", "bed_temper": 55.0, "percent": 11}}"#,
+    )
+    .unwrap();
+    state.update(&second);
+
+    assert!(state.is_complete());
+    assert_eq!(state.message_count, 2);
+    assert_eq!(state.print.percent, Some(11));
+    assert_eq!(state.print.nozzle_temper, Some(220.0));
+
+    let status = state.status();
+    assert_eq!(status.state, PrintState::Printing);
+    assert_eq!(status.progress, Some(11));
+}
+
+#[test]
+fn test_timelapse_setting_from_ipcam_report() {
+    let mut state = MergedState::default();
+    assert_eq!(state.print.timelapse_enabled(), None);
+
+    let message = DeviceMessage::parse(
+        r#"{"print": {"ipcam": {"ipcam_dev": "1", "ipcam_record": "enable", "timelapse": "enable"}}}"#,
+    )
+    .unwrap();
+    state.update(&message);
+    assert_eq!(state.print.timelapse_enabled(), Some(true));
+
+    let message =
+        DeviceMessage::parse(r#"{"print": {"ipcam": {"timelapse": "disable"}}}"#).unwrap();
+    state.update(&message);
+    assert_eq!(state.print.timelapse_enabled(), Some(false));
+}
+
+#[test]
+fn test_ams_humidity_and_drying() {
+    let message = DeviceMessage::parse(
+        r#"{"print": {"ams": {"ams": [
+            {"id": "0", "humidity": "4", "humidity_raw": "23", "temp": "27.5", "dry_time": 0, "tray": []},
+            {"id": "1", "humidity": "2", "dry_time": 45, "tray": []}
+        ]}}}"#,
+    )
+    .unwrap();
+    let units = message.print.unwrap().ams.unwrap().units.unwrap();
+
+    assert_eq!(units[0].label(), "AMS A");
+    assert_eq!(units[0].humidity, Some(4));
+    assert_eq!(units[0].humidity_percent, Some(23));
+    assert_eq!(units[0].temperature, Some(27.5));
+    assert!(!units[0].is_drying());
+
+    assert_eq!(units[1].label(), "AMS B");
+    assert_eq!(units[1].humidity_percent, None);
+    assert!(units[1].is_drying());
+}
+
+#[test]
+fn test_active_tray() {
+    let mut state = MergedState::default();
+    state.update(
+        &DeviceMessage::parse(
+            r#"{"print": {
+                "ams": {"tray_now": "5", "ams": [
+                    {"id": "0", "tray": [{"id": "1", "tray_type": "PLA"}]},
+                    {"id": "1", "tray": [{"id": "0", "tray_type": "PETG"}, {"id": "1", "tray_type": "ABS"}]}
+                ]},
+                "vt_tray": {"id": "254", "tray_type": "TPU"}
+            }}"#,
+        )
+        .unwrap(),
+    );
+    let active = |state: &MergedState| {
+        state
+            .print
+            .active_tray()
+            .and_then(|tray| tray.tray_type.clone())
+    };
+    assert_eq!(active(&state).as_deref(), Some("ABS"));
+
+    state.update(&DeviceMessage::parse(r#"{"print": {"ams": {"tray_now": "254"}}}"#).unwrap());
+    assert_eq!(active(&state).as_deref(), Some("TPU"));
+
+    state.update(&DeviceMessage::parse(r#"{"print": {"ams": {"tray_now": "255"}}}"#).unwrap());
+    assert_eq!(active(&state), None);
+}
+
+#[test]
+fn test_external_spool_without_ams() {
+    let message = DeviceMessage::parse(
+        r#"{"print": {"vt_tray": {"id": "254", "tray_type": "PETG", "tray_color": "FF6A13FF", "remain": 60}}}"#,
+    )
+    .unwrap();
+    let print = message.print.unwrap();
+    assert_eq!(print.active_slot(), Some(EXTERNAL_SLOT));
+    let tray = print.active_tray().unwrap();
+    assert_eq!(tray.tray_type.as_deref(), Some("PETG"));
+    assert_eq!(tray.tray_color.as_deref(), Some("FF6A13FF"));
+    assert_eq!(tray.remain, Some(60));
+
+    assert_eq!(PrintInfo::default().active_slot(), None);
+}
+
+#[test]
+fn test_tray_flow_calibration() {
+    let message = DeviceMessage::parse(
+        r#"{"print": {
+            "ams": {"ams": [{"id": "0", "tray": [
+                {"id": "0", "tray_type": "PLA", "k": 0.02, "n": 1.4, "cali_idx": -1},
+                {"id": "1", "tray_type": "PETG", "k": "0.045", "n": "1.0", "cali_idx": 3}
+            ]}]},
+            "vt_tray": {"id": "254", "tray_type": "TPU", "k": 0.1, "n": 1.0}
+        }}"#,
+    )
+    .unwrap();
+    let print = message.print.unwrap();
+    let trays = &print.ams.as_ref().unwrap().units.as_ref().unwrap()[0].trays;
+
+    assert_eq!(trays[0].k, Some(0.02));
+    assert_eq!(trays[0].n, Some(1.4));
+    assert_eq!(trays[0].cali_idx, Some(-1));
+    assert_eq!(trays[1].k, Some(0.045));
+    assert_eq!(trays[1].cali_idx, Some(3));
+
+    let external = print.vt_tray.as_ref().unwrap();
+    assert_eq!(external.tray_type.as_deref(), Some("TPU"));
+    assert_eq!(external.k, Some(0.1));
+
+    // The external spool survives deltas that omit it
+    let mut state = MergedState::default();
+    state.update(&DeviceMessage::parse(r#"{"print": {"vt_tray": {"k": 0.1}}}"#).unwrap());
+    state.update(&DeviceMessage::parse(r#"{"print": {"percent": 5}}"#).unwrap());
+    assert_eq!(state.print.vt_tray.unwrap().k, Some(0.1));
+}
+
+#[test]
+fn test_nozzle_info() {
+    let message = DeviceMessage::parse(
+        r#"{"print": {"nozzle_type": "hardened_steel", "nozzle_diameter": "0.4"}}"#,
+    )
+    .unwrap();
+    let print = message.print.unwrap();
+    assert_eq!(print.nozzle_type.as_deref(), Some("hardened_steel"));
+    assert_eq!(print.nozzle_diameter, Some(0.4));
+    assert_eq!(
+        print.nozzle_summary().as_deref(),
+        Some("0.4 mm hardened steel")
+    );
+
+    assert_eq!(PrintInfo::default().nozzle_summary(), None);
+}
+
+#[test]
+fn test_home_flags() {
+    let flags = HomeFlags::decode(0x0080_0107);
+    assert!(flags.x_homed && flags.y_homed && flags.z_homed);
+    assert!(flags.sdcard_present);
+    assert!(flags.door_open);
+    assert!(!flags.sdcard_abnormal);
+    assert!(!flags.wired_network);
+    assert_eq!(flags.sdcard_problem(), None);
+
+    assert_eq!(HomeFlags::decode(0).sdcard_problem(), Some("missing"));
+    assert_eq!(HomeFlags::decode(0x300).sdcard_problem(), Some("abnormal"));
+
+    let mut state = MergedState::default();
+    assert_eq!(state.print.home_flags(), None);
+    // Values with the top bit set arrive as negative numbers
+    state.update(&DeviceMessage::parse(r#"{"print": {"home_flag": -2138570481}}"#).unwrap());
+    let flags = state.print.home_flags().unwrap();
+    assert!(flags.sdcard_present);
+    assert!(flags.door_open);
+}
+
+#[test]
+fn test_skipped_objects() {
+    let mut state = MergedState::default();
+    assert!(state.print.skipped_objects().is_empty());
+
+    let message = DeviceMessage::parse(r#"{"print": {"s_obj": [127, 296]}}"#).unwrap();
+    state.update(&message);
+    assert_eq!(state.print.skipped_objects(), vec![127, 296]);
+}
+
+#[test]
+fn test_merged_state_ignores_messages_without_print() {
+    let mut state = MergedState::default();
+    let message = DeviceMessage::parse(r#"{"pushing": {"command": "pushall"}}"#).unwrap();
+    state.update(&message);
+
+    assert_eq!(state.message_count, 1);
+    assert!(state.print.percent.is_none());
+}
+
+#[test]
+fn test_print_state_from_gcode_state() {
+    assert_eq!(
+        PrintState::from_gcode_state("RUNNING"),
+        PrintState::Printing
+    );
+    assert_eq!(
+        PrintState::from_gcode_state("PREPARE"),
+        PrintState::Printing
+    );
+    assert_eq!(PrintState::from_gcode_state("PAUSE"), PrintState::Paused);
+    assert_eq!(PrintState::from_gcode_state("FINISH"), PrintState::Finished);
+    assert_eq!(PrintState::from_gcode_state("FAILED"), PrintState::Failed);
+    assert_eq!(PrintState::from_gcode_state("IDLE"), PrintState::Idle);
+    assert_eq!(
+        PrintState::from_gcode_state("OTHER"),
+        PrintState::Unknown("OTHER".to_string())
+    );
+
+    assert!(PrintState::Finished.is_terminal());
+    assert!(PrintState::Failed.is_terminal());
+    assert!(!PrintState::Printing.is_terminal());
+}
+
+#[test]
+fn test_printer_status_uses_gcode_state_without_explicit_state() {
+    let message = DeviceMessage::parse(
+        r#"{"print": {"command": "push_status", "gcode_state": "FINISH", "nozzle_temper": 180.0}}"#,
+    )
+    .unwrap();
+
+    let status = PrinterStatus::from_device_message(&message).unwrap();
+    assert_eq!(status.state, PrintState::Finished);
+}
+
+#[test]
+fn test_temperature_history_rolls_over() {
+    let mut history = TemperatureHistory::new(3);
+    for temp in [200.0, 210.0, 220.0, 230.0] {
+        history.record(&PrintInfo {
+            nozzle_temper: Some(temp),
+            chamber_temper: Some(35.0),
+            ..Default::default()
+        });
+    }
+
+    assert_eq!(
+        history.nozzle().iter().copied().collect::<Vec<_>>(),
+        vec![210.0, 220.0, 230.0]
+    );
+    assert!(history.bed().is_empty());
+    assert_eq!(history.chamber().len(), 3);
+}
+
+#[test]
+fn test_status_serialization() {
+    let states = [
+        PrintState::Idle,
+        PrintState::Printing,
+        PrintState::Paused,
+        PrintState::Failed,
+        PrintState::Finished,
+        PrintState::Unknown("calibrating".to_string()),
+    ];
+    for state in states {
+        let value = serde_json::to_value(&state).unwrap();
+        assert_eq!(value, state.name());
+        assert_eq!(serde_json::from_value::<PrintState>(value).unwrap(), state);
+    }
+
+    let mut state = MergedState::default();
+    state.update(
+        &DeviceMessage::parse(
+            r#"{"print": {"gcode_state": "RUNNING", "percent": 40, "mc_remaining_time": 25}}"#,
+        )
+        .unwrap(),
+    );
+    let mut value = serde_json::to_value(state.status()).unwrap();
+    assert!(value["finish_at"].is_string());
+    value.as_object_mut().unwrap().remove("finish_at");
+    assert_eq!(
+        value,
+        serde_json::json!({
+            "state": "printing",
+            "progress": 40,
+            "eta": null,
+            "started_at": null,
+            "remaining_time": 25,
+            "total_time": null,
+            "fail_reason": null,
+        })
+    );
+    let status: PrinterStatus = serde_json::from_value(value).unwrap();
+    assert_eq!(
+        status,
+        PrinterStatus {
+            finish_at: None,
+            ..state.status()
+        }
+    );
+
+    let value = serde_json::to_value(&state).unwrap();
+    assert_eq!(value["message_count"], 1);
+    assert_eq!(value["print"]["gcode_state"], "RUNNING");
+    let restored: MergedState = serde_json::from_value(value).unwrap();
+    assert_eq!(restored.message_count, 1);
+    assert_eq!(restored.status(), state.status());
+}
+
+#[test]
+fn test_elapsed_from_tracked_start() {
+    let now = Local::now();
+    let mut state = MergedState::default();
+    assert_eq!(state.status().elapsed_secs(now), None);
+
+    state.started_at = Some(now - chrono::Duration::minutes(134));
+    assert_eq!(state.status().elapsed_secs(now), Some(134 * 60));
+}
+
+#[test]
+fn test_progress_from_layers_without_percent() {
+    let status = |payload: &str| {
+        PrinterStatus::from_device_message(&DeviceMessage::parse(payload).unwrap()).unwrap()
+    };
+
+    let layers = status(r#"{"print": {"layer_num": 30, "total_layer_num": 120}}"#);
+    assert_eq!(layers.progress, Some(25));
+
+    let reported = status(r#"{"print": {"percent": 28, "layer_num": 30, "total_layer_num": 120}}"#);
+    assert_eq!(reported.progress, Some(28));
+
+    assert_eq!(
+        status(r#"{"print": {"layer_num": 3, "total_layer_num": 0}}"#).progress,
+        None
+    );
+    assert_eq!(status(r#"{"print": {"layer_num": 3}}"#).progress, None);
+    assert_eq!(
+        status(r#"{"print": {"layer_num": 130, "total_layer_num": 120}}"#).progress,
+        Some(100)
+    );
+}
+
+#[test]
+fn test_finish_time_is_fixed_when_remaining_time_is_reported() {
+    let start = chrono::Local::now();
+    let later = start + chrono::Duration::seconds(30);
+    let parse = |payload: &str| DeviceMessage::parse(payload).unwrap();
+    let mut state = MergedState::default();
+
+    state.update_at(
+        &parse(r#"{"print": {"gcode_state": "RUNNING", "mc_remaining_time": 600}}"#),
+        start,
+    );
+    let finish = start + chrono::Duration::seconds(600);
+    assert_eq!(state.status().finish_at, Some(finish));
+
+    // Other reports, e.g. the first after a reconnect, keep the finish time
+    state.update_at(&parse(r#"{"print": {"nozzle_temper": 220}}"#), later);
+    assert_eq!(state.status().finish_at, Some(finish));
+
+    state.update_at(&parse(r#"{"print": {"mc_remaining_time": 500}}"#), later);
+    assert_eq!(
+        state.status().finish_at,
+        Some(later + chrono::Duration::seconds(500))
+    );
+
+    state.update_at(&parse(r#"{"print": {"gcode_state": "PAUSE"}}"#), later);
+    assert_eq!(state.status().finish_at, None);
+    state.update_at(&parse(r#"{"print": {"gcode_state": "RUNNING"}}"#), later);
+    assert_eq!(
+        state.status().finish_at,
+        Some(later + chrono::Duration::seconds(500))
+    );
+
+    state.update_at(
+        &parse(r#"{"print": {"gcode_state": "FINISH", "mc_remaining_time": 0}}"#),
+        later,
+    );
+    assert_eq!(state.status().finish_at, None);
+
+    let single = PrinterStatus::from_device_message(&parse(
+        r#"{"print": {"gcode_state": "IDLE", "mc_remaining_time": 600}}"#,
+    ))
+    .unwrap();
+    assert_eq!(single.finish_at, None);
+}
+
+#[test]
+fn test_wifi_signal_parsing() {
+    let print = |signal: &str| PrintInfo {
+        wifi_signal: Some(signal.to_string()),
+        ..Default::default()
+    };
+
+    assert_eq!(print("-45dBm").wifi_dbm(), Some(-45));
+    assert_eq!(print(" -62 dBm").wifi_dbm(), Some(-62));
+    assert_eq!(print("-80").wifi_dbm(), Some(-80));
+    assert_eq!(print("unknown").wifi_dbm(), None);
+    assert_eq!(PrintInfo::default().wifi_dbm(), None);
+
+    assert_eq!(print("-45dBm").wifi_quality(), Some(WifiQuality::Excellent));
+    assert_eq!(WifiQuality::from_dbm(-55), WifiQuality::Excellent);
+    assert_eq!(WifiQuality::from_dbm(-56), WifiQuality::Good);
+    assert_eq!(WifiQuality::from_dbm(-70), WifiQuality::Good);
+    assert_eq!(WifiQuality::from_dbm(-71), WifiQuality::Weak);
+    assert_eq!(WifiQuality::Weak.name(), "weak");
+}
+
+#[test]
+fn test_fan_gear_decoding() {
+    let fans = FanSpeeds::decode(0x0000_66ff);
+    assert_eq!(
+        fans,
+        FanSpeeds {
+            part_cooling: 100,
+            aux: 40,
+            chamber: 0,
+        }
+    );
+    assert_eq!(fans.summary(), "part 100%, aux 40%, chamber 0%");
+    assert_eq!(FanSpeeds::decode(0x00ff_0000).chamber, 100);
+    assert_eq!(FanSpeeds::decode(0), FanSpeeds::default());
+
+    let print = PrintInfo {
+        fan_gear: Some(0x80),
+        ..Default::default()
+    };
+    assert_eq!(print.fan_speeds().unwrap().part_cooling, 50);
+    assert_eq!(PrintInfo::default().fan_speeds(), None);
+}
+
+#[test]
+fn test_firmware_update() {
+    let update = |upgrade_state: &str| {
+        DeviceMessage::parse(&format!(
+            r#"{{"print": {{"upgrade_state": {upgrade_state}}}}}"#
+        ))
+        .unwrap()
+        .print
+        .unwrap()
+        .firmware_update()
+    };
+
+    assert_eq!(
+        update(
+            r#"{"new_version_state": 2, "force_upgrade": false, "new_ver_list": [
+                {"name": "ota", "cur_ver": "01.08.00.00", "new_ver": "01.08.00.00"}
+            ]}"#
+        ),
+        None
+    );
+
+    let offered = update(
+        r#"{"new_version_state": 1, "force_upgrade": false, "new_ver_list": [
+            {"name": "ota", "cur_ver": "01.07.00.00", "new_ver": "01.08.00.00"},
+            {"name": "ams/0", "cur_ver": "00.00.06.40", "new_ver": "00.00.06.40"}
+        ]}"#,
+    )
+    .unwrap();
+    assert_eq!(
+        offered.modules,
+        vec![ModuleUpdate {
+            name: "ota".to_string(),
+            current: Some("01.07.00.00".to_string()),
+            new: "01.08.00.00".to_string(),
+        }]
+    );
+    assert_eq!(offered.summary(), "ota 01.07.00.00 → 01.08.00.00");
+
+    let forced = update(r#"{"new_version_state": 1, "force_upgrade": true}"#).unwrap();
+    assert_eq!(forced.summary(), "new version (required)");
+}
+
+#[test]
+fn test_version_report() {
+    let message = DeviceMessage::parse(
+        r#"{"info": {"command": "get_version", "sequence_id": "0", "module": [
+            {"name": "ota", "product_name": "Bambu Lab P1S", "sw_ver": "01.07.00.00", "hw_ver": "", "sn": ""},
+            {"name": "mc", "sw_ver": "00.00.30.12", "hw_ver": "MC07", "sn": "00M00A000000001"},
+            {"name": "ap", "sw_ver": "00.00.29.44", "hw_ver": "AP05", "sn": "01P00A000000001"},
+            {"name": "ams/0", "sw_ver": "00.00.06.40", "hw_ver": "AMS08", "sn": "006000000000001"},
+            {"sw_ver": "unnamed"}
+        ]}}"#,
+    )
+    .unwrap();
+    let report = message.info.unwrap().version_report().unwrap();
+
+    assert_eq!(report.serial.as_deref(), Some("01P00A000000001"));
+    assert_eq!(report.model.as_deref(), Some("Bambu Lab P1S"));
+    assert_eq!(report.hardware_revision.as_deref(), Some("AP05"));
+    assert_eq!(report.firmware.as_deref(), Some("01.07.00.00"));
+    assert_eq!(report.modules.len(), 4);
+    assert_eq!(
+        report.modules[3],
+        ModuleVersion {
+            name: "ams/0".to_string(),
+            product_name: None,
+            firmware: Some("00.00.06.40".to_string()),
+            hardware: Some("AMS08".to_string()),
+            serial: Some("006000000000001".to_string()),
+        }
+    );
+    assert_eq!(report.modules[0].hardware, None);
+
+    let other = DeviceMessage::parse(r#"{"info": {"command": "get_access_code"}}"#).unwrap();
+    assert_eq!(other.info.unwrap().version_report(), None);
+}
+
+#[test]
+fn test_messages_are_tagged_with_their_topic() {
+    let payload = br#"{"print": {"gcode_state": "RUNNING"}}"#;
+    let message = DeviceMessage::parse_from("bridge/x1c/report", payload).unwrap();
+    assert_eq!(message.topic.as_deref(), Some("bridge/x1c/report"));
+    assert_eq!(DeviceMessage::parse_bytes(payload).unwrap().topic, None);
+
+    let mut state = MergedState::default();
+    state.update(&DeviceMessage::parse_bytes(payload).unwrap());
+    assert_eq!(state.source_topic, None);
+    state.update(&message);
+    assert_eq!(state.source_topic.as_deref(), Some("bridge/x1c/report"));
+    assert_eq!(
+        serde_json::to_value(&state).unwrap()["source_topic"],
+        "bridge/x1c/report"
+    );
+    // Messages without a topic, as from a corpus file, keep the last one seen
+    state.update(&DeviceMessage::parse_bytes(payload).unwrap());
+    assert_eq!(state.source_topic.as_deref(), Some("bridge/x1c/report"));
+}
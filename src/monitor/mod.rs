@@ -0,0 +1,391 @@
+use crate::config::PrinterConfig;
+use crate::jobs::{self, HmsEntry, PrintStateMachine, Transition};
+use crate::messages::{AmsInfo, DeviceMessage, MergedState};
+use crate::mqtt::{self, ExponentialBackoff, MessageSource, ReconnectPolicy};
+use crate::telemetry::{TelemetryBuffer, TelemetrySample};
+use chrono::Local;
+use std::collections::BTreeSet;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use thiserror::Error;
+use tokio::sync::mpsc;
+use tokio_stream::Stream;
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_util::sync::CancellationToken;
+
+#[cfg(test)]
+mod tests;
+
+/// Events a slow consumer may fall behind by before the connection waits for it
+const EVENT_BUFFER: usize = 64;
+/// Degrees Celsius a temperature moves before it is reported again
+const TEMPERATURE_STEP: f64 = 1.0;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum MonitorError {
+    #[error("No printer was given to monitor")]
+    MissingPrinter,
+
+    #[error("Keep-alive interval must be at least one second, or zero, not {0:?}")]
+    InvalidKeepAlive(Duration),
+}
+
+/// Something that happened on a monitored printer. Every report yields a `StatusUpdated`,
+/// followed by the more specific events it caused.
+#[derive(Debug, Clone)]
+pub enum PrinterEvent {
+    /// The broker accepted the connection, or the connection failed or dropped with
+    /// `error`. After a failure the monitor reconnects unless its reconnect policy gives up,
+    /// which ends the stream.
+    ConnectionChanged {
+        connected: bool,
+        error: Option<String>,
+    },
+    /// A report was merged into the printer state, which is carried over reconnects
+    StatusUpdated(Box<MergedState>),
+    /// The first temperature readings, or one of them moved by a degree or more
+    TemperatureChanged(Temperatures),
+    /// The printer started a new job
+    JobStarted { name: Option<String> },
+    /// The printer reported an HMS code that was not active before
+    HmsRaised(HmsEntry),
+    /// The first AMS report, or a unit, tray, or the active tray changed
+    AmsChanged(Box<AmsInfo>),
+}
+
+/// Temperatures in degrees Celsius, as last reported
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Temperatures {
+    pub nozzle: Option<f64>,
+    pub bed: Option<f64>,
+    pub chamber: Option<f64>,
+}
+
+impl Temperatures {
+    fn of(state: &MergedState) -> Self {
+        Self {
+            nozzle: state.print.nozzle_temper,
+            bed: state.print.bed_temper,
+            chamber: state.print.chamber_temper,
+        }
+    }
+
+    fn moved_from(&self, previous: &Temperatures) -> bool {
+        let moved = |now: Option<f64>, before: Option<f64>| match (now, before) {
+            (Some(now), Some(before)) => (now - before).abs() >= TEMPERATURE_STEP,
+            (now, before) => now.is_some() != before.is_some(),
+        };
+        moved(self.nozzle, previous.nozzle)
+            || moved(self.bed, previous.bed)
+            || moved(self.chamber, previous.chamber)
+    }
+}
+
+/// Turns printer reports into [`PrinterEvent`]s, keeping the merged state and what was
+/// last reported
+#[derive(Debug, Default)]
+pub struct EventDetector {
+    state: MergedState,
+    temperatures: Option<Temperatures>,
+    machine: PrintStateMachine,
+    hms_codes: BTreeSet<String>,
+    ams: Option<AmsInfo>,
+}
+
+impl EventDetector {
+    pub fn state(&self) -> &MergedState {
+        &self.state
+    }
+
+    pub fn update(&mut self, message: &DeviceMessage) -> Vec<PrinterEvent> {
+        self.state.update(message);
+        let state = &self.state;
+        let mut events = vec![PrinterEvent::StatusUpdated(Box::new(state.clone()))];
+
+        let temperatures = Temperatures::of(state);
+        if temperatures != Temperatures::default()
+            && self
+                .temperatures
+                .is_none_or(|previous| temperatures.moved_from(&previous))
+        {
+            self.temperatures = Some(temperatures);
+            events.push(PrinterEvent::TemperatureChanged(temperatures));
+        }
+
+        if self.machine.observe(state).contains(&Transition::Started) {
+            events.push(PrinterEvent::JobStarted {
+                name: state.print.subtask_name.clone(),
+            });
+        }
+
+        let entries = jobs::hms_entries(state.print.extra.get("hms"));
+        let active: BTreeSet<String> = entries.iter().map(|entry| entry.code.clone()).collect();
+        for entry in entries {
+            if !self.hms_codes.contains(&entry.code) {
+                events.push(PrinterEvent::HmsRaised(entry));
+            }
+        }
+        // Cleared codes are reported again if they come back
+        self.hms_codes = active;
+
+        if let Some(ams) = &state.print.ams
+            && self.ams.as_ref() != Some(ams)
+        {
+            self.ams = Some(ams.clone());
+            events.push(PrinterEvent::AmsChanged(Box::new(ams.clone())));
+        }
+
+        events
+    }
+}
+
+/// Watches one printer for programs using this crate as a library
+///
+/// ```no_run
+/// # async fn run(config: pulseprint_cli::config::PrinterConfig) {
+/// use pulseprint_cli::monitor::{MonitorBuilder, PrinterEvent};
+/// use pulseprint_cli::mqtt::FixedDelay;
+/// use std::time::Duration;
+/// use tokio_stream::StreamExt;
+///
+/// let monitor = MonitorBuilder::new()
+///     .printer(config)
+///     .keep_alive(Duration::from_secs(15))
+///     .reconnect(FixedDelay {
+///         delay: Duration::from_secs(10),
+///         max_retries: None,
+///     })
+///     .build()
+///     .unwrap();
+/// let mut events = std::pin::pin!(monitor.events());
+/// while let Some(event) = events.next().await {
+///     if let PrinterEvent::JobStarted { name } = event {
+///         println!("Started {}", name.unwrap_or_default());
+///     }
+/// }
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct PrinterMonitor {
+    config: Arc<PrinterConfig>,
+    keep_alive: Duration,
+    reconnect: Arc<dyn ReconnectPolicy>,
+    cancel: CancellationToken,
+    telemetry: Arc<Mutex<TelemetryBuffer>>,
+}
+
+impl PrinterMonitor {
+    /// Monitor with the default keep-alive, reconnecting with [`ExponentialBackoff`] forever
+    pub fn new(config: PrinterConfig) -> Self {
+        Self {
+            config: Arc::new(config),
+            keep_alive: mqtt::DEFAULT_KEEP_ALIVE,
+            reconnect: Arc::new(ExponentialBackoff::default()),
+            cancel: CancellationToken::new(),
+            telemetry: Arc::default(),
+        }
+    }
+
+    pub fn config(&self) -> &PrinterConfig {
+        &self.config
+    }
+
+    pub fn keep_alive(&self) -> Duration {
+        self.keep_alive
+    }
+
+    pub fn reconnect_policy(&self) -> &dyn ReconnectPolicy {
+        self.reconnect.as_ref()
+    }
+
+    /// Token that stops this monitor's streams; clones of the monitor share it
+    pub fn cancellation_token(&self) -> &CancellationToken {
+        &self.cancel
+    }
+
+    /// Stops every stream of this monitor: each closes its connection and ends after the
+    /// events received so far
+    pub fn cancel(&self) {
+        self.cancel.cancel();
+    }
+
+    /// Temperatures and progress sampled by this monitor's streams within `window`, oldest
+    /// first. Samples older than the retention set on the builder are not kept.
+    pub fn telemetry(&self, window: Duration) -> Vec<TelemetrySample> {
+        self.telemetry.lock().unwrap().window(window, Local::now())
+    }
+
+    /// Connects in a background task and yields what happens on the printer, reconnecting
+    /// after every failure. The task stops once the monitor is cancelled or the stream is
+    /// dropped.
+    pub fn events(&self) -> impl Stream<Item = PrinterEvent> + Send + 'static {
+        let (sender, receiver) = mpsc::channel(EVENT_BUFFER);
+        tokio::spawn(follow(self.clone(), sender));
+        ReceiverStream::new(receiver)
+    }
+}
+
+/// Sets up a [`PrinterMonitor`]; only the printer is required
+#[derive(Debug, Default)]
+pub struct MonitorBuilder {
+    printer: Option<PrinterConfig>,
+    keep_alive: Option<Duration>,
+    reconnect: Option<Arc<dyn ReconnectPolicy>>,
+    cancel: Option<CancellationToken>,
+    telemetry_retention: Option<Duration>,
+}
+
+impl MonitorBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn printer(mut self, config: PrinterConfig) -> Self {
+        self.printer = Some(config);
+        self
+    }
+
+    /// Interval of MQTT pings on a quiet connection, 30 seconds by default; zero turns
+    /// them off
+    pub fn keep_alive(mut self, keep_alive: Duration) -> Self {
+        self.keep_alive = Some(keep_alive);
+        self
+    }
+
+    /// When to reconnect after a failure, [`ExponentialBackoff::default`] by default. The
+    /// policy from a config file is [`mqtt::reconnect_policy`].
+    pub fn reconnect(self, policy: impl ReconnectPolicy + 'static) -> Self {
+        self.reconnect_policy(Arc::new(policy))
+    }
+
+    pub fn reconnect_policy(mut self, policy: Arc<dyn ReconnectPolicy>) -> Self {
+        self.reconnect = Some(policy);
+        self
+    }
+
+    /// Stops the monitor when `token` is cancelled, e.g. a child of the embedding
+    /// application's shutdown token
+    pub fn cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancel = Some(token);
+        self
+    }
+
+    /// How long [`PrinterMonitor::telemetry`] keeps samples, an hour by default
+    pub fn telemetry_retention(mut self, retention: Duration) -> Self {
+        self.telemetry_retention = Some(retention);
+        self
+    }
+
+    pub fn build(self) -> Result<PrinterMonitor, MonitorError> {
+        let config = self.printer.ok_or(MonitorError::MissingPrinter)?;
+        let mut monitor = PrinterMonitor::new(config);
+        if let Some(keep_alive) = self.keep_alive {
+            // rumqttc panics on intervals below a second
+            if !keep_alive.is_zero() && keep_alive < Duration::from_secs(1) {
+                return Err(MonitorError::InvalidKeepAlive(keep_alive));
+            }
+            monitor.keep_alive = keep_alive;
+        }
+        if let Some(policy) = self.reconnect {
+            monitor.reconnect = policy;
+        }
+        if let Some(cancel) = self.cancel {
+            monitor.cancel = cancel;
+        }
+        if let Some(retention) = self.telemetry_retention {
+            monitor.telemetry = Arc::new(Mutex::new(TelemetryBuffer::new(retention)));
+        }
+        Ok(monitor)
+    }
+}
+
+async fn follow(monitor: PrinterMonitor, sender: mpsc::Sender<PrinterEvent>) {
+    let mut detector = EventDetector::default();
+    let mut attempt = 0;
+    loop {
+        let error = tokio::select! {
+            result = follow_once(&monitor, &mut detector, &mut attempt, &sender) => match result {
+                Ok(()) => "Connection closed".to_string(),
+                Err(e) => e,
+            },
+            () = stopped(&monitor, &sender) => return,
+        };
+        let event = PrinterEvent::ConnectionChanged {
+            connected: false,
+            error: Some(error),
+        };
+        if sender.send(event).await.is_err() {
+            return;
+        }
+
+        attempt += 1;
+        if !monitor.reconnect.should_retry(attempt) {
+            return;
+        }
+        let delay = monitor.reconnect.next_delay(&monitor.config.name, attempt);
+        tokio::select! {
+            () = tokio::time::sleep(delay) => {}
+            () = stopped(&monitor, &sender) => return,
+        }
+    }
+}
+
+/// Completes once the monitor is cancelled or nobody reads its events anymore
+async fn stopped(monitor: &PrinterMonitor, sender: &mpsc::Sender<PrinterEvent>) {
+    tokio::select! {
+        () = monitor.cancel.cancelled() => {}
+        () = sender.closed() => {}
+    }
+}
+
+async fn follow_once(
+    monitor: &PrinterMonitor,
+    detector: &mut EventDetector,
+    attempt: &mut u32,
+    sender: &mpsc::Sender<PrinterEvent>,
+) -> Result<(), String> {
+    let mut client =
+        mqtt::MqttClient::with_keep_alive(Arc::clone(&monitor.config), monitor.keep_alive)
+            .await
+            .map_err(|e| e.to_string())?;
+    client.handshake().await.map_err(|e| e.to_string())?;
+    client.connect().await.map_err(|e| e.to_string())?;
+    client.request_pushall().await.map_err(|e| e.to_string())?;
+    let event = PrinterEvent::ConnectionChanged {
+        connected: true,
+        error: None,
+    };
+    if sender.send(event).await.is_err() {
+        return Ok(());
+    }
+    *attempt = 0;
+
+    let mut eventloop = client.get_eventloop();
+    forward_messages(&mut eventloop, detector, &monitor.telemetry, sender).await
+}
+
+/// Sends the events of every report from `source` and samples it into `telemetry`, until
+/// the source is exhausted or nobody listens anymore
+async fn forward_messages<S: MessageSource>(
+    source: &mut S,
+    detector: &mut EventDetector,
+    telemetry: &Mutex<TelemetryBuffer>,
+    sender: &mpsc::Sender<PrinterEvent>,
+) -> Result<(), String> {
+    while let Some(publish) = source.next_publish().await.map_err(|e| e.to_string())? {
+        let Ok(message) = DeviceMessage::parse_from(&publish.topic, &publish.payload) else {
+            continue;
+        };
+        let events = detector.update(&message);
+        telemetry
+            .lock()
+            .unwrap()
+            .record(detector.state(), Local::now());
+        for event in events {
+            if sender.send(event).await.is_err() {
+                return Ok(());
+            }
+        }
+    }
+    Ok(())
+}
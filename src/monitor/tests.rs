@@ -0,0 +1,268 @@
+use super::*;
+use crate::messages::PrintState;
+use crate::mqtt::ChannelSource;
+use rumqttc::{Publish, QoS};
+use tokio_stream::StreamExt;
+
+/// Printer on a local port that nothing listens on, since it was just released
+fn offline_printer() -> PrinterConfig {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    drop(listener);
+
+    let mut config = PrinterConfig::new(
+        "offline".to_string(),
+        "127.0.0.1".to_string(),
+        "01S00A000000000".to_string(),
+        "12345678".to_string(),
+    );
+    config.port = port;
+    config
+}
+
+#[tokio::test]
+async fn test_forward_messages_merges_reports() {
+    let (publishes, mut source) = ChannelSource::new(4);
+    for payload in [
+        r#"{"print": {"command": "push_status", "gcode_state": "RUNNING", "percent": 10}}"#,
+        "not json",
+        r#"{"print": {"command": "push_status", "percent": 20}}"#,
+    ] {
+        publishes
+            .send(Ok(Publish::new(
+                "device/x/report",
+                QoS::AtMostOnce,
+                payload,
+            )))
+            .await
+            .unwrap();
+    }
+    drop(publishes);
+
+    let (sender, receiver) = mpsc::channel(8);
+    let mut detector = EventDetector::default();
+    let telemetry = Mutex::new(TelemetryBuffer::default());
+    forward_messages(&mut source, &mut detector, &telemetry, &sender)
+        .await
+        .unwrap();
+    drop(sender);
+
+    let events: Vec<PrinterEvent> = ReceiverStream::new(receiver).collect().await;
+    assert_eq!(events.len(), 2);
+    let PrinterEvent::StatusUpdated(last) = &events[1] else {
+        panic!("expected a status update, got {:?}", events[1]);
+    };
+    // Fields from earlier reports are kept
+    assert_eq!(last.status().state, PrintState::Printing);
+    assert_eq!(last.status().progress, Some(20));
+    assert_eq!(detector.state().message_count, 2);
+    // Reports within a second share one sample
+    let samples = telemetry.lock().unwrap();
+    assert_eq!(samples.len(), 1);
+    assert_eq!(samples.samples()[0].progress, Some(20));
+}
+
+fn update(detector: &mut EventDetector, payload: &str) -> Vec<PrinterEvent> {
+    detector.update(&DeviceMessage::parse(payload).unwrap())
+}
+
+/// Event names without the status update that starts every batch
+fn kinds(events: &[PrinterEvent]) -> Vec<&'static str> {
+    assert!(matches!(events[0], PrinterEvent::StatusUpdated(_)));
+    events[1..]
+        .iter()
+        .map(|event| match event {
+            PrinterEvent::ConnectionChanged { .. } => "connection",
+            PrinterEvent::StatusUpdated(_) => "status",
+            PrinterEvent::TemperatureChanged(_) => "temperature",
+            PrinterEvent::JobStarted { .. } => "job_started",
+            PrinterEvent::HmsRaised(_) => "hms",
+            PrinterEvent::AmsChanged(_) => "ams",
+        })
+        .collect()
+}
+
+#[test]
+fn test_event_detector() {
+    let mut detector = EventDetector::default();
+
+    let events = update(
+        &mut detector,
+        r#"{"print": {"gcode_state": "IDLE", "nozzle_temper": 25.0, "bed_temper": 24.0,
+            "ams": {"ams": [{"id": "0", "tray": [{"id": "0", "tray_type": "PLA"}]}]}}}"#,
+    );
+    assert_eq!(kinds(&events), ["temperature", "ams"]);
+
+    // Less than a degree, and the same AMS contents
+    let events = update(
+        &mut detector,
+        r#"{"print": {"nozzle_temper": 25.6,
+            "ams": {"ams": [{"id": "0", "tray": [{"id": "0", "tray_type": "PLA"}]}]}}}"#,
+    );
+    assert!(kinds(&events).is_empty());
+
+    let events = update(
+        &mut detector,
+        r#"{"print": {"gcode_state": "RUNNING", "subtask_name": "benchy", "nozzle_temper": 180.0,
+            "hms": [{"attr": 50331904, "code": 65543}]}}"#,
+    );
+    assert_eq!(kinds(&events), ["temperature", "job_started", "hms"]);
+    assert!(matches!(
+        &events[2],
+        PrinterEvent::JobStarted { name: Some(name) } if name == "benchy"
+    ));
+    let PrinterEvent::TemperatureChanged(temperatures) = &events[1] else {
+        panic!("expected temperatures, got {:?}", events[1]);
+    };
+    assert_eq!(temperatures.nozzle, Some(180.0));
+    assert_eq!(temperatures.bed, Some(24.0));
+
+    // The same HMS code is not raised again while it stays active
+    let events = update(
+        &mut detector,
+        r#"{"print": {"hms": [{"attr": 50331904, "code": 65543}],
+            "ams": {"ams": [{"id": "0", "tray": [{"id": "0", "tray_type": "PETG"}]}]}}}"#,
+    );
+    assert_eq!(kinds(&events), ["ams"]);
+}
+
+#[tokio::test]
+async fn test_forward_messages_reports_connection_errors() {
+    let (publishes, mut source) = ChannelSource::new(1);
+    publishes
+        .send(Err("connection reset".to_string()))
+        .await
+        .unwrap();
+
+    let (sender, _receiver) = mpsc::channel(1);
+    let telemetry = Mutex::default();
+    let error = forward_messages(
+        &mut source,
+        &mut EventDetector::default(),
+        &telemetry,
+        &sender,
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(error, "connection reset");
+}
+
+#[tokio::test]
+async fn test_events_report_failed_connections() {
+    let config = offline_printer();
+    let monitor = MonitorBuilder::new()
+        .printer(config)
+        .reconnect(mqtt::FixedDelay {
+            delay: Duration::from_millis(10),
+            max_retries: Some(1),
+        })
+        .build()
+        .unwrap();
+    assert_eq!(monitor.config().name, "offline");
+
+    let mut events = std::pin::pin!(monitor.events());
+    for _ in 0..2 {
+        let event = tokio::time::timeout(Duration::from_secs(10), events.next())
+            .await
+            .unwrap();
+        assert!(matches!(
+            event,
+            Some(PrinterEvent::ConnectionChanged {
+                connected: false,
+                error: Some(_)
+            })
+        ));
+    }
+    // The policy gives up after one retry, which ends the stream
+    let last = tokio::time::timeout(Duration::from_secs(1), events.next())
+        .await
+        .unwrap();
+    assert!(last.is_none());
+}
+
+#[test]
+fn test_builder_settings() {
+    let printer = PrinterConfig::new(
+        "p1s".to_string(),
+        "192.168.1.100".to_string(),
+        "01P00A000000000".to_string(),
+        "12345678".to_string(),
+    );
+    let monitor = MonitorBuilder::new()
+        .printer(printer.clone())
+        .build()
+        .unwrap();
+    assert_eq!(monitor.keep_alive(), mqtt::DEFAULT_KEEP_ALIVE);
+    assert!(monitor.reconnect_policy().should_retry(1000));
+    assert!(monitor.telemetry(Duration::from_secs(600)).is_empty());
+
+    let monitor = MonitorBuilder::new()
+        .printer(printer.clone())
+        .keep_alive(Duration::ZERO)
+        .telemetry_retention(Duration::from_secs(300))
+        .reconnect(mqtt::FixedDelay {
+            delay: Duration::from_secs(30),
+            max_retries: Some(2),
+        })
+        .build()
+        .unwrap();
+    assert_eq!(monitor.keep_alive(), Duration::ZERO);
+    assert_eq!(
+        monitor.reconnect_policy().next_delay("p1s", 1),
+        Duration::from_secs(30)
+    );
+    assert!(!monitor.reconnect_policy().should_retry(3));
+    assert_eq!(
+        monitor.telemetry.lock().unwrap().retention(),
+        Duration::from_secs(300)
+    );
+
+    assert_eq!(
+        MonitorBuilder::new().build().unwrap_err(),
+        MonitorError::MissingPrinter
+    );
+    let keep_alive = Duration::from_millis(500);
+    assert_eq!(
+        MonitorBuilder::new()
+            .printer(printer)
+            .keep_alive(keep_alive)
+            .build()
+            .unwrap_err(),
+        MonitorError::InvalidKeepAlive(keep_alive)
+    );
+}
+
+#[tokio::test]
+async fn test_cancellation_ends_the_stream() {
+    let config = offline_printer();
+    let token = CancellationToken::new();
+    let monitor = MonitorBuilder::new()
+        .printer(config)
+        .reconnect(mqtt::FixedDelay {
+            delay: Duration::from_secs(3600),
+            max_retries: None,
+        })
+        .cancellation(token.child_token())
+        .build()
+        .unwrap();
+
+    let mut events = std::pin::pin!(monitor.events());
+    let first = tokio::time::timeout(Duration::from_secs(10), events.next())
+        .await
+        .unwrap();
+    assert!(matches!(
+        first,
+        Some(PrinterEvent::ConnectionChanged {
+            connected: false,
+            error: Some(_)
+        })
+    ));
+
+    // The monitor is waiting out the hour-long reconnect delay when it is cancelled
+    token.cancel();
+    let last = tokio::time::timeout(Duration::from_secs(1), events.next())
+        .await
+        .unwrap();
+    assert!(last.is_none());
+    assert!(monitor.cancellation_token().is_cancelled());
+}
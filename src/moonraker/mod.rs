@@ -0,0 +1,495 @@
+use crate::daemon::{self, PrinterEntry, SharedPrinters};
+use crate::jobs::JobRecord;
+use crate::messages::PrintState;
+use crate::mqtt::PrintAction;
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde::Deserialize;
+use serde_json::{Map, Value, json};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio::net::TcpListener;
+use tokio::time::Instant;
+
+#[cfg(test)]
+mod tests;
+
+/// Moonraker release whose API is emulated
+const MOONRAKER_VERSION: &str = "v0.9.3";
+const API_VERSION: [u32; 3] = [1, 5, 0];
+
+/// Requested printer objects; `None` or an empty list asks for every attribute
+pub type ObjectQuery = BTreeMap<String, Option<Vec<String>>>;
+
+/// Error answered as `{"error": {...}}` over HTTP and as a JSON-RPC error on the websocket
+#[derive(Debug, Clone, PartialEq)]
+pub struct ApiError {
+    pub code: i64,
+    pub message: String,
+}
+
+impl ApiError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let status = u16::try_from(self.code)
+            .ok()
+            .and_then(|code| StatusCode::from_u16(code).ok())
+            .unwrap_or(StatusCode::BAD_REQUEST);
+        let body = json!({ "error": { "code": self.code, "message": self.message } });
+        (status, Json(body)).into_response()
+    }
+}
+
+/// Klipper's `print_stats.state` for a printer state
+fn klipper_state(state: &PrintState) -> &'static str {
+    match state {
+        PrintState::Printing => "printing",
+        PrintState::Paused => "paused",
+        PrintState::Finished => "complete",
+        PrintState::Failed => "error",
+        PrintState::Idle | PrintState::Unknown(_) => "standby",
+    }
+}
+
+/// Klipper printer objects built from what the Bambu printer reported
+pub fn printer_objects(entry: &PrinterEntry) -> Map<String, Value> {
+    let print = &entry.state.print;
+    let status = entry.state.status();
+    let state = klipper_state(&status.state);
+    let progress = f64::from(status.progress.unwrap_or(0)) / 100.0;
+    let duration = entry.jobs.active().map_or(0, JobRecord::elapsed_secs);
+    let extra_f64 = |key: &str| print.extra.get(key).and_then(Value::as_f64);
+
+    let (webhooks_state, webhooks_message) = if entry.connected {
+        ("ready", "Printer is ready")
+    } else {
+        ("startup", "Waiting for the printer to connect")
+    };
+
+    let Value::Object(objects) = json!({
+        "webhooks": { "state": webhooks_state, "state_message": webhooks_message },
+        "print_stats": {
+            "state": state,
+            "filename": print.subtask_name.clone().unwrap_or_default(),
+            "print_duration": duration,
+            "total_duration": duration,
+            "filament_used": 0.0,
+            "message": status.fail_reason.unwrap_or_default(),
+            "info": { "current_layer": print.layer_num, "total_layer": print.total_layer_num }
+        },
+        "display_status": { "progress": progress, "message": "" },
+        "virtual_sdcard": {
+            "progress": progress,
+            "is_active": state == "printing",
+            "file_position": 0
+        },
+        "extruder": {
+            "temperature": print.nozzle_temper.unwrap_or(0.0),
+            "target": extra_f64("nozzle_target_temper").unwrap_or(0.0),
+            "power": 0.0
+        },
+        "heater_bed": {
+            "temperature": print.bed_temper.unwrap_or(0.0),
+            "target": extra_f64("bed_target_temper").unwrap_or(0.0),
+            "power": 0.0
+        },
+        "temperature_sensor chamber": { "temperature": print.chamber_temper.unwrap_or(0.0) }
+    }) else {
+        unreachable!()
+    };
+    objects
+}
+
+/// The requested objects and attributes; unknown objects are left out as Klipper does
+pub fn select(objects: &Map<String, Value>, query: &ObjectQuery) -> Map<String, Value> {
+    query
+        .iter()
+        .filter_map(|(name, attributes)| {
+            let Value::Object(object) = objects.get(name)? else {
+                return None;
+            };
+            let selected = match attributes {
+                Some(attributes) if !attributes.is_empty() => object
+                    .iter()
+                    .filter(|(key, _)| attributes.contains(key))
+                    .map(|(key, value)| (key.clone(), value.clone()))
+                    .collect(),
+                _ => object.clone(),
+            };
+            Some((name.clone(), Value::Object(selected)))
+        })
+        .collect()
+}
+
+/// Attributes of `current` that differ from `previous`, grouped by object
+pub fn diff(previous: &Map<String, Value>, current: &Map<String, Value>) -> Map<String, Value> {
+    current
+        .iter()
+        .filter_map(|(name, object)| {
+            let Value::Object(object) = object else {
+                return None;
+            };
+            let before = previous.get(name).and_then(Value::as_object);
+            let changed: Map<String, Value> = object
+                .iter()
+                .filter(|(key, value)| before.and_then(|before| before.get(*key)) != Some(*value))
+                .map(|(key, value)| (key.clone(), value.clone()))
+                .collect();
+            (!changed.is_empty()).then(|| (name.clone(), Value::Object(changed)))
+        })
+        .collect()
+}
+
+/// Parses `?print_stats&extruder=temperature,target` style query pairs
+pub fn parse_query(pairs: &[(String, String)]) -> ObjectQuery {
+    pairs
+        .iter()
+        .map(|(name, attributes)| {
+            let attributes = (!attributes.is_empty())
+                .then(|| attributes.split(',').map(str::to_string).collect());
+            (name.clone(), attributes)
+        })
+        .collect()
+}
+
+#[derive(Clone)]
+pub struct MoonrakerState {
+    printers: SharedPrinters,
+    printer: String,
+    started: Instant,
+    /// Open websocket connections
+    connections: Arc<AtomicU64>,
+    next_connection_id: Arc<AtomicU64>,
+}
+
+impl MoonrakerState {
+    pub fn new(printers: SharedPrinters, printer: &str) -> Self {
+        Self {
+            printers,
+            printer: printer.to_string(),
+            started: Instant::now(),
+            connections: Arc::new(AtomicU64::new(0)),
+            next_connection_id: Arc::new(AtomicU64::new(1)),
+        }
+    }
+
+    /// Seconds since the server started, standing in for Klipper's `eventtime`
+    fn eventtime(&self) -> f64 {
+        self.started.elapsed().as_secs_f64()
+    }
+
+    fn with_entry<T>(&self, f: impl FnOnce(&PrinterEntry) -> T) -> Result<T, ApiError> {
+        let printers = self.printers.lock().unwrap();
+        let entry = printers.get(&self.printer).ok_or_else(|| {
+            ApiError::new(404, format!("Printer '{}' is not monitored", self.printer))
+        })?;
+        Ok(f(entry))
+    }
+
+    fn server_info(&self) -> Result<Value, ApiError> {
+        let connected = self.with_entry(|entry| entry.connected)?;
+        Ok(json!({
+            "klippy_connected": connected,
+            "klippy_state": if connected { "ready" } else { "startup" },
+            "components": ["server", "websockets", "history"],
+            "failed_components": [],
+            "registered_directories": [],
+            "warnings": [],
+            "websocket_count": self.connections.load(Ordering::Relaxed),
+            "moonraker_version": MOONRAKER_VERSION,
+            "api_version": API_VERSION,
+            "api_version_string": API_VERSION.map(|part| part.to_string()).join(".")
+        }))
+    }
+
+    fn printer_info(&self) -> Result<Value, ApiError> {
+        let connected = self.with_entry(|entry| entry.connected)?;
+        Ok(json!({
+            "state": if connected { "ready" } else { "startup" },
+            "state_message": if connected { "Printer is ready" } else { "Waiting for the printer to connect" },
+            "hostname": self.printer,
+            "software_version": format!("pulseprint-cli {}", env!("CARGO_PKG_VERSION")),
+            "cpu_info": "",
+            "klipper_path": "",
+            "python_path": "",
+            "log_file": "",
+            "config_file": ""
+        }))
+    }
+
+    fn objects_list(&self) -> Result<Value, ApiError> {
+        let objects = self.with_entry(printer_objects)?;
+        Ok(json!({ "objects": objects.keys().collect::<Vec<_>>() }))
+    }
+
+    fn objects_query(&self, query: &ObjectQuery) -> Result<Value, ApiError> {
+        let objects = self.with_entry(printer_objects)?;
+        Ok(json!({ "eventtime": self.eventtime(), "status": select(&objects, query) }))
+    }
+
+    fn history_list(&self) -> Result<Value, ApiError> {
+        let mut jobs: Vec<Value> = self.with_entry(|entry| {
+            entry
+                .history
+                .iter()
+                .enumerate()
+                .map(|(index, job)| history_job(index, job))
+                .collect()
+        })?;
+        jobs.reverse();
+        Ok(json!({ "count": jobs.len(), "jobs": jobs }))
+    }
+
+    async fn print_action(&self, action: PrintAction) -> Result<Value, ApiError> {
+        let request = daemon::Request::Control {
+            printer: self.printer.clone(),
+            action,
+        };
+        match daemon::handle_request(&self.printers, request).await {
+            daemon::Response::Ok => Ok(json!("ok")),
+            daemon::Response::Error { message } => Err(ApiError::new(503, message)),
+            daemon::Response::Printers { .. } => {
+                Err(ApiError::new(500, "Unexpected daemon response"))
+            }
+        }
+    }
+}
+
+/// A finished job in the shape of Moonraker's job history
+fn history_job(index: usize, job: &JobRecord) -> Value {
+    let status = match job.final_state {
+        PrintState::Finished => "completed",
+        PrintState::Failed => "error",
+        _ => "cancelled",
+    };
+    json!({
+        "job_id": format!("{:06X}", index + 1),
+        "filename": job.name.clone().unwrap_or_default(),
+        "status": status,
+        "start_time": job.started_at.timestamp(),
+        "end_time": job.ended_at.timestamp(),
+        "print_duration": job.elapsed_secs(),
+        "total_duration": job.elapsed_secs(),
+        "filament_used": 0.0,
+        "exists": false,
+        "metadata": {}
+    })
+}
+
+/// Subscription of one websocket client and the status it has been sent
+#[derive(Debug, Default)]
+pub struct Subscription {
+    query: ObjectQuery,
+    sent: Map<String, Value>,
+}
+
+impl Subscription {
+    /// `notify_status_update` for attributes that changed since the last one, if any
+    pub fn update(&mut self, objects: &Map<String, Value>, eventtime: f64) -> Option<Value> {
+        let current = select(objects, &self.query);
+        let changed = diff(&self.sent, &current);
+        self.sent = current;
+        (!changed.is_empty()).then(|| {
+            json!({
+                "jsonrpc": "2.0",
+                "method": "notify_status_update",
+                "params": [changed, eventtime]
+            })
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct RpcRequest {
+    method: String,
+    #[serde(default)]
+    params: Value,
+    id: Option<Value>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct ObjectsParams {
+    #[serde(default)]
+    objects: ObjectQuery,
+}
+
+/// Answers one JSON-RPC message from a websocket client; notifications get no reply
+pub async fn handle_rpc(
+    state: &MoonrakerState,
+    connection_id: u64,
+    text: &str,
+    subscription: &mut Option<Subscription>,
+) -> Option<Value> {
+    let request: RpcRequest = match serde_json::from_str(text) {
+        Ok(request) => request,
+        Err(_) => {
+            return Some(json!({
+                "jsonrpc": "2.0",
+                "error": { "code": -32700, "message": "Parse error" },
+                "id": null
+            }));
+        }
+    };
+
+    let result = match request.method.as_str() {
+        "server.info" => state.server_info(),
+        "server.connection.identify" => Ok(json!({ "connection_id": connection_id })),
+        "server.websocket.id" => Ok(json!({ "websocket_id": connection_id })),
+        "server.history.list" => state.history_list(),
+        "printer.info" => state.printer_info(),
+        "printer.objects.list" => state.objects_list(),
+        "printer.objects.query" | "printer.objects.subscribe" => {
+            let params: ObjectsParams = serde_json::from_value(request.params).unwrap_or_default();
+            let result = state.objects_query(&params.objects);
+            if request.method == "printer.objects.subscribe" {
+                let mut new = Subscription {
+                    query: params.objects,
+                    sent: Map::new(),
+                };
+                if let Ok(objects) = state.with_entry(printer_objects) {
+                    new.sent = select(&objects, &new.query);
+                }
+                *subscription = Some(new);
+            }
+            result
+        }
+        "printer.print.pause" => state.print_action(PrintAction::Pause).await,
+        "printer.print.resume" => state.print_action(PrintAction::Resume).await,
+        "printer.print.cancel" => state.print_action(PrintAction::Stop).await,
+        _ => Err(ApiError::new(-32601, "Method not found")),
+    };
+
+    let id = request.id?;
+    Some(match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "result": result, "id": id }),
+        Err(error) => json!({
+            "jsonrpc": "2.0",
+            "error": { "code": error.code, "message": error.message },
+            "id": id
+        }),
+    })
+}
+
+pub fn router(state: MoonrakerState) -> Router {
+    Router::new()
+        .route("/server/info", get(server_info))
+        .route("/server/history/list", get(history_list))
+        .route("/printer/info", get(printer_info))
+        .route("/printer/objects/list", get(objects_list))
+        .route("/printer/objects/query", get(objects_query))
+        .route("/printer/print/pause", post(pause))
+        .route("/printer/print/resume", post(resume))
+        .route("/printer/print/cancel", post(cancel))
+        .route("/websocket", get(websocket))
+        .with_state(state)
+}
+
+/// Serves the emulated Moonraker API for one printer until the task is aborted
+pub async fn serve(
+    listener: TcpListener,
+    printers: SharedPrinters,
+    printer: String,
+) -> std::io::Result<()> {
+    axum::serve(listener, router(MoonrakerState::new(printers, &printer))).await
+}
+
+type ApiResult = Result<Json<Value>, ApiError>;
+
+fn wrap(result: Result<Value, ApiError>) -> ApiResult {
+    result.map(|result| Json(json!({ "result": result })))
+}
+
+async fn server_info(State(state): State<MoonrakerState>) -> ApiResult {
+    wrap(state.server_info())
+}
+
+async fn history_list(State(state): State<MoonrakerState>) -> ApiResult {
+    wrap(state.history_list())
+}
+
+async fn printer_info(State(state): State<MoonrakerState>) -> ApiResult {
+    wrap(state.printer_info())
+}
+
+async fn objects_list(State(state): State<MoonrakerState>) -> ApiResult {
+    wrap(state.objects_list())
+}
+
+async fn objects_query(
+    State(state): State<MoonrakerState>,
+    Query(pairs): Query<Vec<(String, String)>>,
+) -> ApiResult {
+    wrap(state.objects_query(&parse_query(&pairs)))
+}
+
+async fn pause(State(state): State<MoonrakerState>) -> ApiResult {
+    wrap(state.print_action(PrintAction::Pause).await)
+}
+
+async fn resume(State(state): State<MoonrakerState>) -> ApiResult {
+    wrap(state.print_action(PrintAction::Resume).await)
+}
+
+async fn cancel(State(state): State<MoonrakerState>) -> ApiResult {
+    wrap(state.print_action(PrintAction::Stop).await)
+}
+
+async fn websocket(upgrade: WebSocketUpgrade, State(state): State<MoonrakerState>) -> Response {
+    upgrade.on_upgrade(move |socket| serve_websocket(socket, state))
+}
+
+async fn serve_websocket(mut socket: WebSocket, state: MoonrakerState) {
+    let Ok(mut changes) = state.with_entry(|entry| entry.changes.subscribe()) else {
+        return;
+    };
+    let connection_id = state.next_connection_id.fetch_add(1, Ordering::Relaxed);
+    state.connections.fetch_add(1, Ordering::Relaxed);
+    let mut subscription = None;
+
+    loop {
+        let reply = tokio::select! {
+            message = socket.recv() => match message {
+                Some(Ok(Message::Text(text))) => {
+                    handle_rpc(&state, connection_id, &text, &mut subscription).await
+                }
+                Some(Ok(_)) => None,
+                Some(Err(_)) | None => break,
+            },
+            changed = changes.changed() => {
+                if changed.is_err() {
+                    break;
+                }
+                match (&mut subscription, state.with_entry(printer_objects)) {
+                    (Some(subscription), Ok(objects)) => {
+                        subscription.update(&objects, state.eventtime())
+                    }
+                    _ => None,
+                }
+            }
+        };
+
+        if let Some(reply) = reply
+            && socket
+                .send(Message::Text(reply.to_string().into()))
+                .await
+                .is_err()
+        {
+            break;
+        }
+    }
+
+    state.connections.fetch_sub(1, Ordering::Relaxed);
+}
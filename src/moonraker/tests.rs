@@ -0,0 +1,240 @@
+use super::*;
+use crate::daemon::test_support::{apply, shared};
+
+fn query(objects: &[(&str, Option<&[&str]>)]) -> ObjectQuery {
+    objects
+        .iter()
+        .map(|(name, attributes)| {
+            (
+                name.to_string(),
+                attributes.map(|attributes| attributes.iter().map(|a| a.to_string()).collect()),
+            )
+        })
+        .collect()
+}
+
+const PRINTING: &str = r#"{"print": {"gcode_state": "RUNNING", "percent": 42, "subtask_name": "benchy", "layer_num": 12, "total_layer_num": 200, "nozzle_temper": 219.5, "nozzle_target_temper": 220, "bed_temper": 55.0, "bed_target_temper": 55}}"#;
+
+#[test]
+fn test_printer_objects_map_bambu_state() {
+    let printers = shared(&["x1c"]);
+    apply(&printers, "x1c", PRINTING);
+
+    let objects = printer_objects(&printers.lock().unwrap()["x1c"]);
+    assert_eq!(objects["webhooks"]["state"], "ready");
+    assert_eq!(objects["print_stats"]["state"], "printing");
+    assert_eq!(objects["print_stats"]["filename"], "benchy");
+    assert_eq!(objects["print_stats"]["info"]["current_layer"], 12);
+    assert_eq!(objects["display_status"]["progress"], 0.42);
+    assert_eq!(objects["virtual_sdcard"]["is_active"], true);
+    assert_eq!(objects["extruder"]["temperature"], 219.5);
+    assert_eq!(objects["extruder"]["target"], 220.0);
+    assert_eq!(objects["heater_bed"]["target"], 55.0);
+
+    let idle = printer_objects(&PrinterEntry::default());
+    assert_eq!(idle["webhooks"]["state"], "startup");
+    assert_eq!(idle["print_stats"]["state"], "standby");
+}
+
+#[test]
+fn test_select_and_diff() {
+    let objects = json!({
+        "extruder": {"temperature": 200.0, "target": 220.0},
+        "heater_bed": {"temperature": 60.0, "target": 60.0}
+    });
+    let Value::Object(objects) = objects else {
+        unreachable!()
+    };
+
+    let selected = select(
+        &objects,
+        &query(&[
+            ("extruder", Some(&["temperature"])),
+            ("heater_bed", None),
+            ("fan", None),
+        ]),
+    );
+    assert_eq!(
+        Value::Object(selected.clone()),
+        json!({
+            "extruder": {"temperature": 200.0},
+            "heater_bed": {"temperature": 60.0, "target": 60.0}
+        })
+    );
+
+    let mut later = selected.clone();
+    later["extruder"]["temperature"] = json!(205.0);
+    assert_eq!(
+        Value::Object(diff(&selected, &later)),
+        json!({"extruder": {"temperature": 205.0}})
+    );
+    assert!(diff(&later, &later).is_empty());
+}
+
+#[test]
+fn test_parse_query_pairs() {
+    let pairs = vec![
+        ("print_stats".to_string(), String::new()),
+        ("extruder".to_string(), "temperature,target".to_string()),
+    ];
+    assert_eq!(
+        parse_query(&pairs),
+        query(&[
+            ("print_stats", None),
+            ("extruder", Some(&["temperature", "target"])),
+        ])
+    );
+}
+
+#[tokio::test]
+async fn test_rpc_dispatch() {
+    let printers = shared(&["x1c"]);
+    let state = MoonrakerState::new(Arc::clone(&printers), "x1c");
+    let mut subscription = None;
+
+    let reply = handle_rpc(
+        &state,
+        7,
+        r#"{"jsonrpc": "2.0", "method": "server.connection.identify", "params": {"client_name": "mainsail"}, "id": 1}"#,
+        &mut subscription,
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        reply,
+        json!({"jsonrpc": "2.0", "result": {"connection_id": 7}, "id": 1})
+    );
+
+    let reply = handle_rpc(
+        &state,
+        7,
+        r#"{"jsonrpc": "2.0", "method": "printer.print.cancel", "id": 2}"#,
+        &mut subscription,
+    )
+    .await
+    .unwrap();
+    assert_eq!(reply["error"]["message"], "Printer 'x1c' is not connected");
+
+    let reply = handle_rpc(
+        &state,
+        7,
+        r#"{"jsonrpc": "2.0", "method": "machine.reboot", "id": 3}"#,
+        &mut subscription,
+    )
+    .await
+    .unwrap();
+    assert_eq!(reply["error"]["code"], -32601);
+
+    let reply = handle_rpc(&state, 7, "not json", &mut subscription)
+        .await
+        .unwrap();
+    assert_eq!(reply["error"]["code"], -32700);
+
+    // Notifications without an id get no reply
+    assert!(
+        handle_rpc(
+            &state,
+            7,
+            r#"{"jsonrpc": "2.0", "method": "server.info"}"#,
+            &mut subscription
+        )
+        .await
+        .is_none()
+    );
+}
+
+#[tokio::test]
+async fn test_subscription_sends_changed_attributes() {
+    let printers = shared(&["x1c"]);
+    let state = MoonrakerState::new(Arc::clone(&printers), "x1c");
+    let mut subscription = None;
+
+    let reply = handle_rpc(
+        &state,
+        1,
+        r#"{"jsonrpc": "2.0", "method": "printer.objects.subscribe", "params": {"objects": {"print_stats": ["state"], "extruder": null}}, "id": 5}"#,
+        &mut subscription,
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        reply["result"]["status"]["print_stats"],
+        json!({"state": "standby"})
+    );
+    assert_eq!(reply["result"]["status"]["extruder"]["temperature"], 0.0);
+
+    apply(&printers, "x1c", PRINTING);
+    let objects = printer_objects(&printers.lock().unwrap()["x1c"]);
+    let subscription = subscription.as_mut().unwrap();
+    let update = subscription.update(&objects, 1.5).unwrap();
+    assert_eq!(update["method"], "notify_status_update");
+    assert_eq!(
+        update["params"],
+        json!([
+            {
+                "print_stats": {"state": "printing"},
+                "extruder": {"temperature": 219.5, "target": 220.0}
+            },
+            1.5
+        ])
+    );
+
+    assert!(subscription.update(&objects, 2.0).is_none());
+}
+
+#[tokio::test]
+async fn test_http_endpoints() {
+    let printers = shared(&["x1c"]);
+    for payload in [
+        PRINTING,
+        r#"{"print": {"gcode_state": "FINISH", "percent": 100}}"#,
+    ] {
+        apply(&printers, "x1c", payload);
+    }
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    tokio::spawn(serve(listener, Arc::clone(&printers), "x1c".to_string()));
+    let base = format!("http://{address}");
+
+    let info: Value = reqwest::get(format!("{base}/server/info"))
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(info["result"]["klippy_state"], "ready");
+
+    let query: Value = reqwest::get(format!(
+        "{base}/printer/objects/query?print_stats=state,filename&temperature_sensor%20chamber"
+    ))
+    .await
+    .unwrap()
+    .json()
+    .await
+    .unwrap();
+    assert_eq!(
+        query["result"]["status"],
+        json!({
+            "print_stats": {"state": "complete", "filename": "benchy"},
+            "temperature_sensor chamber": {"temperature": 0.0}
+        })
+    );
+
+    let history: Value = reqwest::get(format!("{base}/server/history/list"))
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(history["result"]["count"], 1);
+    assert_eq!(history["result"]["jobs"][0]["status"], "completed");
+    assert_eq!(history["result"]["jobs"][0]["filename"], "benchy");
+
+    let response = reqwest::Client::new()
+        .post(format!("{base}/printer/print/pause"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 503);
+}
@@ -1,9 +1,18 @@
-use crate::config::PrinterConfig;
-use rumqttc::{AsyncClient, EventLoop, MqttOptions, QoS, TlsConfiguration, Transport};
+use crate::config::{MqttSettings, MqttVersion, PrinterConfig, RetryBackoff};
+use crate::requests::{CommandResponse, RequestError, RequestManager};
+use rumqttc::{
+    AsyncClient, Event, EventLoop, MqttOptions, Outgoing, Packet, Publish, QoS, SubscribeFilter,
+    TlsConfiguration, Transport, v5,
+};
 use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
 use rustls::{DigitallySignedStruct, SignatureScheme};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
 use std::error::Error;
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
+use tokio::sync::{Semaphore, mpsc};
 
 #[cfg(test)]
 mod tests;
@@ -61,59 +70,766 @@ impl ServerCertVerifier for NoVerifyTls {
     }
 }
 
+/// TLS settings for the printer's LAN services, which use self-signed certificates
+pub fn printer_tls_config() -> rustls::ClientConfig {
+    rustls::ClientConfig::builder()
+        .dangerous()
+        .with_custom_certificate_verifier(Arc::new(NoVerifyTls {}))
+        .with_no_client_auth()
+}
+
 pub struct MqttClient {
-    client: AsyncClient,
-    eventloop: EventLoop,
-    config: PrinterConfig,
+    client: ClientHandle,
+    eventloop: Connection,
+    config: Arc<PrinterConfig>,
+    requests: RequestManager,
+    v5_refusal: Option<String>,
 }
 
-impl MqttClient {
-    pub async fn new(config: PrinterConfig) -> Result<Self, Box<dyn Error>> {
-        let mut mqtt_options = MqttOptions::new("pulseprint-cli", &config.ip, config.port);
+/// Interval of MQTT pings on an otherwise quiet connection
+pub const DEFAULT_KEEP_ALIVE: Duration = Duration::from_secs(30);
+/// How long an MQTT 5 broker gets to accept the connection before 3.1.1 is tried instead
+const V5_HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+/// Shortest keep-alive the MQTT 5 client allows
+const V5_MIN_KEEP_ALIVE: Duration = Duration::from_secs(5);
+
+/// Brokers (`ip:port`) that refused MQTT 5 before, so reconnects go straight to 3.1.1
+static V311_ONLY: Mutex<Option<HashSet<String>>> = Mutex::new(None);
+
+fn broker_key(config: &PrinterConfig) -> String {
+    format!("{}:{}", config.ip, config.port)
+}
+
+/// MQTT 5 counterpart of [`mqtt_options`]
+fn v5_options(config: &PrinterConfig, client_id: &str, keep_alive: Duration) -> v5::MqttOptions {
+    let mut options = v5::MqttOptions::new(client_id, &config.ip, config.port);
+    options.set_credentials("bblp", &config.access_code);
+    if config.use_tls {
+        let tls_config = TlsConfiguration::Rustls(Arc::new(printer_tls_config()));
+        options.set_transport(Transport::Tls(tls_config));
+    }
+    // The MQTT 5 client cannot turn pings off or ping more often than this
+    options.set_keep_alive(keep_alive.max(V5_MIN_KEEP_ALIVE));
+    options
+}
+
+/// Why an MQTT 5 handshake failed
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum V5Failure {
+    /// The broker does not speak MQTT 5: it refused the protocol version, or closed the
+    /// connection instead of answering, as 3.1.1 brokers do
+    Unsupported(String),
+    /// Anything else, from an unreachable broker to a wrong access code
+    Failed(String),
+}
+
+impl V5Failure {
+    fn of(error: &v5::ConnectionError) -> Self {
+        let unsupported = match error {
+            v5::ConnectionError::ConnectionRefused(code) => {
+                *code == v5::mqttbytes::v5::ConnectReturnCode::UnsupportedProtocolVersion
+            }
+            // Only once the TCP connection is up; a refused or unreachable port is not an
+            // answer from the broker
+            v5::ConnectionError::Io(e) => matches!(
+                e.kind(),
+                std::io::ErrorKind::ConnectionAborted
+                    | std::io::ErrorKind::ConnectionReset
+                    | std::io::ErrorKind::UnexpectedEof
+            ),
+            _ => false,
+        };
+        let reason = describe_v5_error(error);
+        if unsupported {
+            V5Failure::Unsupported(reason)
+        } else {
+            V5Failure::Failed(reason)
+        }
+    }
+}
+
+/// Opens an MQTT 5 session and waits for the broker to accept it
+async fn connect_v5(
+    config: &PrinterConfig,
+    keep_alive: Duration,
+) -> Result<(v5::AsyncClient, v5::EventLoop), V5Failure> {
+    let (client, mut eventloop) =
+        v5::AsyncClient::new(v5_options(config, "pulseprint-cli", keep_alive), 10);
+    let handshake = async {
+        loop {
+            match eventloop.poll().await {
+                Ok(v5::Event::Incoming(v5::Incoming::ConnAck(_))) => return Ok(()),
+                Ok(_) => {}
+                Err(e) => return Err(V5Failure::of(&e)),
+            }
+        }
+    };
+    match tokio::time::timeout(V5_HANDSHAKE_TIMEOUT, handshake).await {
+        Ok(Ok(())) => Ok((client, eventloop)),
+        Ok(Err(e)) => Err(e),
+        Err(_) => Err(V5Failure::Failed(
+            "timed out waiting for the broker to accept the connection".to_string(),
+        )),
+    }
+}
+
+/// Connection error of an MQTT 5 session, with the reason code and reason string of a
+/// DISCONNECT sent by the broker
+pub fn describe_v5_error(error: &v5::ConnectionError) -> String {
+    match error {
+        v5::ConnectionError::MqttState(v5::StateError::ServerDisconnect {
+            reason_code,
+            reason_string,
+        }) => match reason_string {
+            Some(reason) => format!("Broker disconnected ({reason_code:?}): {reason}"),
+            None => format!("Broker disconnected ({reason_code:?})"),
+        },
+        v5::ConnectionError::ConnectionRefused(code) => {
+            format!("Broker refused the connection ({code:?})")
+        }
+        other => other.to_string(),
+    }
+}
+
+/// Connection settings for the printer's broker: LAN credentials and TLS when enabled
+fn mqtt_options(config: &PrinterConfig, client_id: &str, keep_alive: Duration) -> MqttOptions {
+    let mut mqtt_options = MqttOptions::new(client_id, &config.ip, config.port);
+
+    // Set authentication
+    mqtt_options.set_credentials("bblp", &config.access_code);
+
+    // Configure TLS if enabled
+    if config.use_tls {
+        let tls_config = TlsConfiguration::Rustls(Arc::new(printer_tls_config()));
+        mqtt_options.set_transport(Transport::Tls(tls_config));
+    }
+
+    // Set connection parameters
+    mqtt_options.set_keep_alive(keep_alive);
+    mqtt_options
+}
+
+/// Result of [`probe`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Reachability {
+    /// The broker accepted the access code
+    Online,
+    /// The broker answered but refused the access code
+    Rejected,
+    /// No MQTT session could be opened, with the reason
+    Unreachable(String),
+}
+
+/// Opens an MQTT session and waits for the broker's answer, without subscribing to anything
+pub async fn probe(config: &PrinterConfig, timeout: Duration) -> Reachability {
+    // A separate client ID, so a running monitor keeps its session
+    let (_client, mut eventloop) = AsyncClient::new(
+        mqtt_options(config, "pulseprint-cli-probe", DEFAULT_KEEP_ALIVE),
+        1,
+    );
+    let handshake = async {
+        loop {
+            match eventloop.poll().await {
+                Ok(Event::Incoming(Packet::ConnAck(_))) => return Reachability::Online,
+                Ok(_) => {}
+                Err(rumqttc::ConnectionError::ConnectionRefused(
+                    rumqttc::ConnectReturnCode::BadUserNamePassword
+                    | rumqttc::ConnectReturnCode::NotAuthorized,
+                )) => return Reachability::Rejected,
+                Err(e) => return Reachability::Unreachable(e.to_string()),
+            }
+        }
+    };
+    tokio::time::timeout(timeout, handshake)
+        .await
+        .unwrap_or_else(|_| Reachability::Unreachable("timed out".to_string()))
+}
+
+/// Limits how many printer connections are opened at the same time, so a large fleet does
+/// not start dozens of TLS handshakes at once. Clones share the same limit.
+#[derive(Debug, Clone)]
+pub struct ConnectionPool {
+    permits: Arc<Semaphore>,
+}
+
+impl ConnectionPool {
+    /// A `max_concurrent` of 0 means no limit
+    pub fn new(max_concurrent: usize) -> Self {
+        let permits = match max_concurrent {
+            0 => Semaphore::MAX_PERMITS,
+            max => max,
+        };
+        Self {
+            permits: Arc::new(Semaphore::new(permits)),
+        }
+    }
+
+    /// Connects to the printer once a slot is free. The slot is held only until the broker
+    /// accepts or refuses the session, not for the life of the connection.
+    pub async fn open(&self, config: Arc<PrinterConfig>) -> Result<MqttClient, Box<dyn Error>> {
+        let _permit = self.permits.acquire().await?;
+        let mut client = MqttClient::new(config).await?;
+        client.handshake().await?;
+        Ok(client)
+    }
+
+    /// [`probe`] once a slot is free; `timeout` starts counting when the slot is taken
+    pub async fn probe(&self, config: &PrinterConfig, timeout: Duration) -> Reachability {
+        match self.permits.acquire().await {
+            Ok(_permit) => probe(config, timeout).await,
+            Err(e) => Reachability::Unreachable(e.to_string()),
+        }
+    }
+}
+
+/// How long to wait before reconnecting after `attempt` failed attempts: `base` plus up to
+/// `base` again, offset differently per printer and attempt, so printers that dropped off
+/// together (say after a network outage) do not all reconnect in the same instant
+pub fn reconnect_delay(printer: &str, attempt: u32, base: Duration) -> Duration {
+    base + base.mul_f64(spread(printer, attempt))
+}
+
+/// Fraction from 0 to 1 that differs per printer and attempt but is stable for both
+fn spread(printer: &str, attempt: u32) -> f64 {
+    let mut hasher = DefaultHasher::new();
+    (printer, attempt).hash(&mut hasher);
+    (hasher.finish() % 1000) as f64 / 1000.0
+}
+
+/// Decides whether and when to reconnect after a connection failed or dropped. `attempt`
+/// counts the failures in a row, starting at 1.
+pub trait ReconnectPolicy: std::fmt::Debug + Send + Sync {
+    fn should_retry(&self, attempt: u32) -> bool;
+
+    fn next_delay(&self, printer: &str, attempt: u32) -> Duration;
+}
+
+/// Waits the same time before every attempt
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FixedDelay {
+    pub delay: Duration,
+    /// Attempts after the first before giving up; `None` retries forever
+    pub max_retries: Option<u32>,
+}
+
+impl ReconnectPolicy for FixedDelay {
+    fn should_retry(&self, attempt: u32) -> bool {
+        self.max_retries.is_none_or(|max| attempt <= max)
+    }
 
-        // Set authentication
-        mqtt_options.set_credentials("bblp", &config.access_code);
+    fn next_delay(&self, _printer: &str, _attempt: u32) -> Duration {
+        self.delay
+    }
+}
 
-        // Configure TLS if enabled
-        if config.use_tls {
-            let tls_config = rustls::ClientConfig::builder()
-                .dangerous()
-                .with_custom_certificate_verifier(std::sync::Arc::new(NoVerifyTls {}))
-                .with_no_client_auth();
+/// Doubles the delay after every failure up to `max`. Each delay is between half and all
+/// of the doubled value, picked per printer and attempt so printers that dropped off
+/// together spread out.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExponentialBackoff {
+    pub base: Duration,
+    pub max: Duration,
+    /// Attempts after the first before giving up; `None` retries forever
+    pub max_retries: Option<u32>,
+}
 
-            let tls_config = TlsConfiguration::Rustls(std::sync::Arc::new(tls_config));
-            mqtt_options.set_transport(Transport::Tls(tls_config));
+impl Default for ExponentialBackoff {
+    fn default() -> Self {
+        Self {
+            base: Duration::from_secs(5),
+            max: Duration::from_secs(300),
+            max_retries: None,
         }
+    }
+}
+
+impl ReconnectPolicy for ExponentialBackoff {
+    fn should_retry(&self, attempt: u32) -> bool {
+        self.max_retries.is_none_or(|max| attempt <= max)
+    }
+
+    fn next_delay(&self, printer: &str, attempt: u32) -> Duration {
+        let doublings = attempt.saturating_sub(1).min(31);
+        let delay = self.base.saturating_mul(1 << doublings).min(self.max);
+        delay / 2 + (delay / 2).mul_f64(spread(printer, attempt))
+    }
+}
 
-        // Set connection parameters
-        mqtt_options.set_keep_alive(Duration::from_secs(30));
+/// The policy configured under `[mqtt_settings]`
+pub fn reconnect_policy(settings: &MqttSettings) -> Arc<dyn ReconnectPolicy> {
+    let delay = Duration::from_secs(settings.retry_delay_secs);
+    let max_retries = Some(settings.retry_attempts);
+    match settings.retry_backoff {
+        RetryBackoff::Fixed => Arc::new(FixedDelay { delay, max_retries }),
+        RetryBackoff::Exponential => Arc::new(ExponentialBackoff {
+            base: delay,
+            max: Duration::from_secs(settings.max_retry_delay_secs).max(delay),
+            max_retries,
+        }),
+    }
+}
+
+impl MqttClient {
+    pub async fn new(config: Arc<PrinterConfig>) -> Result<Self, Box<dyn Error>> {
+        Self::with_keep_alive(config, DEFAULT_KEEP_ALIVE).await
+    }
 
-        let (client, eventloop) = AsyncClient::new(mqtt_options, 10);
+    /// Client pinging the broker every `keep_alive`, which must be at least a second, or
+    /// zero to turn pings off. A printer configured for MQTT 5 is connected right away. If
+    /// the broker turns the protocol version down, the client falls back to 3.1.1 (see
+    /// [`v5_refusal`](Self::v5_refusal)); any other failure, such as a wrong access code,
+    /// is returned. 3.1.1 clients connect once the event loop is first polled.
+    pub async fn with_keep_alive(
+        config: Arc<PrinterConfig>,
+        keep_alive: Duration,
+    ) -> Result<Self, Box<dyn Error>> {
+        let key = broker_key(&config);
+        let refused_before = V311_ONLY
+            .lock()
+            .unwrap()
+            .as_ref()
+            .is_some_and(|brokers| brokers.contains(&key));
+        let mut v5_refusal = None;
+        if config.mqtt_version == MqttVersion::V5 && !refused_before {
+            match connect_v5(&config, keep_alive).await {
+                Ok((client, eventloop)) => {
+                    return Ok(MqttClient {
+                        client: ClientHandle::V5(client),
+                        eventloop: Connection::V5(Box::new(eventloop)),
+                        config,
+                        requests: RequestManager::default(),
+                        v5_refusal: None,
+                    });
+                }
+                Err(V5Failure::Unsupported(reason)) => {
+                    V311_ONLY
+                        .lock()
+                        .unwrap()
+                        .get_or_insert_with(HashSet::new)
+                        .insert(key);
+                    v5_refusal = Some(reason);
+                }
+                Err(V5Failure::Failed(reason)) => {
+                    return Err(format!("MQTT connection error: {reason}").into());
+                }
+            }
+        }
+
+        let options = mqtt_options(&config, "pulseprint-cli", keep_alive);
+        let (client, eventloop) = AsyncClient::new(options, 10);
 
         Ok(MqttClient {
-            client,
-            eventloop,
+            client: ClientHandle::V311(client),
+            eventloop: Connection::V311(Box::new(eventloop)),
             config,
+            requests: RequestManager::default(),
+            v5_refusal,
         })
     }
 
-    pub async fn connect(&self) -> Result<(), Box<dyn Error>> {
-        // Subscribe to the device report topic
-        let report_topic = self.config.report_topic();
+    /// How the broker turned MQTT 5 down, when this client fell back to 3.1.1 for a printer
+    /// configured for MQTT 5. `None` on later connections to the same broker, which go
+    /// straight to 3.1.1.
+    pub fn v5_refusal(&self) -> Option<&str> {
+        self.v5_refusal.as_deref()
+    }
+
+    /// Protocol the client talks to the broker
+    pub fn protocol(&self) -> MqttVersion {
+        match self.client {
+            ClientHandle::V311(_) => MqttVersion::V311,
+            ClientHandle::V5(_) => MqttVersion::V5,
+        }
+    }
+
+    pub async fn connect(&mut self) -> Result<(), Box<dyn Error>> {
+        // The device report topic and any extra topics, in one request so a long list does
+        // not fill the client's queue before the event loop runs
         self.client
-            .subscribe(&report_topic, QoS::AtMostOnce)
-            .await?;
+            .subscribe_many(self.config.subscribe_topics())
+            .await
+    }
 
-        println!(
-            "Connected to printer '{}' at {} and subscribed to {}",
-            self.config.name, self.config.ip, report_topic
-        );
-        println!("📡 Monitoring printer status - Press Ctrl+C to stop...");
+    /// Asks the printer to push its complete status on the report topic
+    pub async fn request_pushall(&mut self) -> Result<(), Box<dyn Error>> {
+        let payload = serde_json::json!({
+            "pushing": {
+                "sequence_id": "0",
+                "command": "pushall",
+                "version": 1,
+                "push_target": 1
+            }
+        });
 
-        Ok(())
+        self.client
+            .publish(&self.config.request_topic(), payload.to_string())
+            .await
+    }
+
+    /// Drives the event loop until the broker answers the connection request
+    pub async fn handshake(&mut self) -> Result<(), Box<dyn Error>> {
+        let Connection::V311(eventloop) = &mut self.eventloop else {
+            // MQTT 5 sessions are connected when the client is created
+            return Ok(());
+        };
+        loop {
+            match eventloop.poll().await {
+                Ok(Event::Incoming(Packet::ConnAck(_))) => return Ok(()),
+                Ok(_) => {}
+                Err(e) => return Err(format!("MQTT connection error: {e}").into()),
+            }
+        }
+    }
+
+    /// Handle for sending print commands while the event loop is being polled elsewhere
+    pub fn control(&self) -> PrinterControl {
+        PrinterControl {
+            client: self.client.clone(),
+            request_topic: self.config.request_topic(),
+            requests: self.requests.clone(),
+        }
+    }
+
+    /// Requests sent with [`PrinterControl::request`], to be resolved by whoever polls the
+    /// event loop
+    pub fn requests(&self) -> RequestManager {
+        self.requests.clone()
     }
 
-    pub fn get_eventloop(self) -> EventLoop {
+    pub fn get_eventloop(self) -> Connection {
         self.eventloop
     }
 }
+
+/// Sending half of a client under either protocol
+#[derive(Debug, Clone)]
+enum ClientHandle {
+    V311(AsyncClient),
+    V5(v5::AsyncClient),
+}
+
+impl ClientHandle {
+    async fn publish(&self, topic: &str, payload: String) -> Result<(), Box<dyn Error>> {
+        match self {
+            ClientHandle::V311(client) => {
+                client
+                    .publish(topic, QoS::AtMostOnce, false, payload)
+                    .await?
+            }
+            ClientHandle::V5(client) => {
+                client
+                    .publish(topic, v5::mqttbytes::QoS::AtMostOnce, false, payload)
+                    .await?
+            }
+        }
+        Ok(())
+    }
+
+    async fn subscribe_many(&self, topics: Vec<String>) -> Result<(), Box<dyn Error>> {
+        match self {
+            ClientHandle::V311(client) => {
+                let filters = topics
+                    .into_iter()
+                    .map(|topic| SubscribeFilter::new(topic, QoS::AtMostOnce));
+                client.subscribe_many(filters).await?
+            }
+            ClientHandle::V5(client) => {
+                let filters = topics.into_iter().map(|topic| {
+                    v5::mqttbytes::v5::Filter::new(topic, v5::mqttbytes::QoS::AtMostOnce)
+                });
+                client.subscribe_many(filters).await?
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Event loop of a printer connection under the negotiated protocol. MQTT 5 publishes are
+/// handed out as 3.1.1 ones, so the message pipeline does not care which was used.
+pub enum Connection {
+    V311(Box<EventLoop>),
+    V5(Box<v5::EventLoop>),
+}
+
+impl Connection {
+    /// Drives the connection until a queued publish has been written to the broker
+    pub async fn flush_publish(&mut self) -> Result<(), Box<dyn Error>> {
+        loop {
+            match self {
+                Connection::V311(eventloop) => match eventloop.poll().await {
+                    Ok(Event::Outgoing(Outgoing::Publish(_))) => return Ok(()),
+                    Ok(_) => {}
+                    Err(e) => return Err(format!("MQTT connection error: {e}").into()),
+                },
+                Connection::V5(eventloop) => match eventloop.poll().await {
+                    Ok(v5::Event::Outgoing(Outgoing::Publish(_))) => return Ok(()),
+                    Ok(_) => {}
+                    Err(e) => {
+                        return Err(
+                            format!("MQTT connection error: {}", describe_v5_error(&e)).into()
+                        );
+                    }
+                },
+            }
+        }
+    }
+}
+
+impl MessageSource for Connection {
+    async fn next_publish(&mut self) -> Result<Option<Publish>, Box<dyn Error>> {
+        match self {
+            Connection::V311(eventloop) => eventloop.next_publish().await,
+            Connection::V5(eventloop) => loop {
+                match eventloop.poll().await {
+                    Ok(v5::Event::Incoming(v5::Incoming::Publish(publish))) => {
+                        let topic = String::from_utf8_lossy(&publish.topic).into_owned();
+                        return Ok(Some(Publish::from_bytes(
+                            topic,
+                            QoS::AtMostOnce,
+                            publish.payload,
+                        )));
+                    }
+                    Ok(_) => {}
+                    Err(e) => {
+                        return Err(
+                            format!("MQTT connection error: {}", describe_v5_error(&e)).into()
+                        );
+                    }
+                }
+            },
+        }
+    }
+}
+
+/// Print job commands accepted on the printer request topic
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PrintAction {
+    Pause,
+    Resume,
+    /// Cancels the current print
+    Stop,
+}
+
+impl PrintAction {
+    pub fn payload(self) -> serde_json::Value {
+        let command = match self {
+            PrintAction::Pause => "pause",
+            PrintAction::Resume => "resume",
+            PrintAction::Stop => "stop",
+        };
+        serde_json::json!({
+            "print": {
+                "sequence_id": "0",
+                "command": command,
+                "param": ""
+            }
+        })
+    }
+}
+
+/// Print of a project file that is already on the printer's SD card
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ProjectFile {
+    /// Path relative to the SD card root, e.g. `cache/benchy.3mf`
+    pub path: String,
+    /// Plate of the project to print, starting at 1
+    pub plate: u32,
+    pub bed_leveling: bool,
+    pub flow_calibration: bool,
+    pub timelapse: bool,
+    /// AMS tray for each filament slot of the project; empty prints from the external spool
+    pub ams_mapping: Vec<i32>,
+}
+
+impl ProjectFile {
+    pub fn payload(&self) -> serde_json::Value {
+        let path = self.path.trim_start_matches('/');
+        serde_json::json!({
+            "print": {
+                "sequence_id": "0",
+                "command": "project_file",
+                "param": format!("Metadata/plate_{}.gcode", self.plate),
+                "url": format!("file:///sdcard/{path}"),
+                "subtask_name": crate::project::job_name(path),
+                "project_id": "0",
+                "profile_id": "0",
+                "task_id": "0",
+                "subtask_id": "0",
+                "md5": "",
+                "bed_type": "auto",
+                "bed_levelling": self.bed_leveling,
+                "flow_cali": self.flow_calibration,
+                "vibration_cali": true,
+                "layer_inspect": false,
+                "timelapse": self.timelapse,
+                "use_ams": !self.ams_mapping.is_empty(),
+                "ams_mapping": self.ams_mapping
+            }
+        })
+    }
+}
+
+/// Print command that stops printing the given objects for the rest of the job
+pub fn skip_objects_payload(ids: &[u32]) -> serde_json::Value {
+    serde_json::json!({
+        "print": {
+            "sequence_id": "0",
+            "command": "skip_objects",
+            "obj_list": ids
+        }
+    })
+}
+
+/// Info command asking the printer for the firmware and serial of each of its modules
+pub fn get_version_payload() -> serde_json::Value {
+    serde_json::json!({
+        "info": {
+            "sequence_id": "0",
+            "command": "get_version"
+        }
+    })
+}
+
+/// Camera command that turns timelapse recording on or off for upcoming prints
+pub fn timelapse_payload(enabled: bool) -> serde_json::Value {
+    serde_json::json!({
+        "camera": {
+            "sequence_id": "0",
+            "command": "ipcam_timelapse",
+            "control": if enabled { "enable" } else { "disable" }
+        }
+    })
+}
+
+#[derive(Debug, Clone)]
+pub struct PrinterControl {
+    client: ClientHandle,
+    request_topic: String,
+    requests: RequestManager,
+}
+
+impl PrinterControl {
+    /// Sends the command without waiting for an answer
+    pub async fn send(&self, action: PrintAction) -> Result<(), Box<dyn Error>> {
+        self.publish(action.payload()).await
+    }
+
+    /// Sends the command and waits until the printer confirms it
+    pub async fn execute(&self, action: PrintAction) -> Result<CommandResponse, RequestError> {
+        self.request(action.payload(), crate::requests::RESPONSE_TIMEOUT)
+            .await?
+            .into_result()
+    }
+
+    /// Sends a command with a fresh sequence ID and waits up to `timeout` for the printer's
+    /// answer, which may still report that the command failed. Only answered while the
+    /// client's [`requests`](MqttClient::requests) are resolved from the event loop.
+    pub async fn request(
+        &self,
+        mut payload: serde_json::Value,
+        timeout: Duration,
+    ) -> Result<CommandResponse, RequestError> {
+        let pending = self.requests.register(&mut payload)?;
+        self.publish(payload)
+            .await
+            .map_err(|e| RequestError::Send(e.to_string()))?;
+        pending.wait(timeout).await
+    }
+
+    pub async fn set_timelapse(&self, enabled: bool) -> Result<(), Box<dyn Error>> {
+        self.publish(timelapse_payload(enabled)).await
+    }
+
+    /// Publishes a raw command payload on the request topic
+    pub async fn publish(&self, payload: serde_json::Value) -> Result<(), Box<dyn Error>> {
+        self.client
+            .publish(&self.request_topic, payload.to_string())
+            .await
+    }
+}
+
+/// Source of incoming printer publishes, so the message pipeline can run without a broker
+// Callers always know the concrete source, so the futures keep their auto traits
+#[allow(async_fn_in_trait)]
+pub trait MessageSource {
+    /// Waits for the next publish; `Ok(None)` means the source is exhausted
+    async fn next_publish(&mut self) -> Result<Option<Publish>, Box<dyn Error>>;
+}
+
+impl MessageSource for EventLoop {
+    async fn next_publish(&mut self) -> Result<Option<Publish>, Box<dyn Error>> {
+        loop {
+            match self.poll().await {
+                Ok(Event::Incoming(Packet::Publish(publish))) => return Ok(Some(publish)),
+                Ok(_) => {
+                    // Other packet types (Subscribe, Connect, pings, etc.)
+                }
+                Err(e) => return Err(format!("MQTT connection error: {e}").into()),
+            }
+        }
+    }
+}
+
+/// Channel-backed message source fed by a replay task or a test
+pub struct ChannelSource {
+    receiver: mpsc::Receiver<Result<Publish, String>>,
+}
+
+impl ChannelSource {
+    pub fn new(buffer: usize) -> (mpsc::Sender<Result<Publish, String>>, Self) {
+        let (sender, receiver) = mpsc::channel(buffer);
+        (sender, Self { receiver })
+    }
+}
+
+impl MessageSource for ChannelSource {
+    async fn next_publish(&mut self) -> Result<Option<Publish>, Box<dyn Error>> {
+        match self.receiver.recv().await {
+            Some(Ok(publish)) => Ok(Some(publish)),
+            Some(Err(e)) => Err(e.into()),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Wraps a message source and drops payloads identical to the previous one on the same topic
+pub struct DedupeSource<S> {
+    inner: S,
+    last_hashes: HashMap<String, u64>,
+    skipped: u64,
+}
+
+impl<S: MessageSource> DedupeSource<S> {
+    pub fn new(inner: S) -> Self {
+        Self {
+            inner,
+            last_hashes: HashMap::new(),
+            skipped: 0,
+        }
+    }
+
+    pub fn skipped(&self) -> u64 {
+        self.skipped
+    }
+}
+
+impl<S: MessageSource> MessageSource for DedupeSource<S> {
+    async fn next_publish(&mut self) -> Result<Option<Publish>, Box<dyn Error>> {
+        while let Some(publish) = self.inner.next_publish().await? {
+            let mut hasher = DefaultHasher::new();
+            publish.payload.hash(&mut hasher);
+            let hash = hasher.finish();
+
+            if self.last_hashes.get(&publish.topic) == Some(&hash) {
+                self.skipped += 1;
+                continue;
+            }
+
+            self.last_hashes.insert(publish.topic.clone(), hash);
+            return Ok(Some(publish));
+        }
+
+        Ok(None)
+    }
+}
@@ -1,5 +1,6 @@
 use super::*;
 use crate::config::PrinterConfig;
+use std::sync::Arc;
 
 #[test]
 fn test_printer_config_creation() {
@@ -40,7 +41,7 @@ async fn test_mqtt_client_creation_with_valid_config() {
     );
 
     // This will create the client but won't actually connect
-    let result = MqttClient::new(config).await;
+    let result = MqttClient::new(Arc::new(config)).await;
     assert!(
         result.is_ok(),
         "MqttClient creation should succeed with valid config"
@@ -121,7 +122,451 @@ async fn test_mqtt_client_with_empty_config() {
     );
 
     // Should create client but fail when trying to connect
-    let result = MqttClient::new(config).await;
+    let result = MqttClient::new(Arc::new(config)).await;
     // Empty IP should still create the client object (connection will fail later)
     assert!(result.is_ok() || result.is_err()); // Either outcome is acceptable for empty config
 }
+
+#[tokio::test]
+async fn test_channel_source_yields_publishes_in_order() {
+    let (sender, mut source) = ChannelSource::new(4);
+
+    sender
+        .send(Ok(Publish::new(
+            "device/a/report",
+            QoS::AtMostOnce,
+            "first",
+        )))
+        .await
+        .unwrap();
+    sender
+        .send(Ok(Publish::new(
+            "device/a/report",
+            QoS::AtMostOnce,
+            "second",
+        )))
+        .await
+        .unwrap();
+    drop(sender);
+
+    let first = source.next_publish().await.unwrap().unwrap();
+    assert_eq!(&first.payload[..], b"first");
+    let second = source.next_publish().await.unwrap().unwrap();
+    assert_eq!(&second.payload[..], b"second");
+
+    // A closed channel means the source is exhausted
+    assert!(source.next_publish().await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn test_channel_source_propagates_errors() {
+    let (sender, mut source) = ChannelSource::new(1);
+
+    sender
+        .send(Err("connection reset".to_string()))
+        .await
+        .unwrap();
+
+    let error = source.next_publish().await.unwrap_err();
+    assert_eq!(error.to_string(), "connection reset");
+}
+
+#[tokio::test]
+async fn test_dedupe_source_skips_identical_consecutive_payloads() {
+    let (sender, source) = ChannelSource::new(8);
+    let mut source = DedupeSource::new(source);
+
+    for (topic, payload) in [
+        ("device/a/report", "one"),
+        ("device/a/report", "one"),
+        ("device/b/report", "one"),
+        ("device/a/report", "two"),
+        ("device/a/report", "one"),
+    ] {
+        sender
+            .send(Ok(Publish::new(topic, QoS::AtMostOnce, payload)))
+            .await
+            .unwrap();
+    }
+    drop(sender);
+
+    let mut received = Vec::new();
+    while let Some(publish) = source.next_publish().await.unwrap() {
+        received.push((publish.topic, publish.payload.to_vec()));
+    }
+
+    assert_eq!(
+        received,
+        vec![
+            ("device/a/report".to_string(), b"one".to_vec()),
+            ("device/b/report".to_string(), b"one".to_vec()),
+            ("device/a/report".to_string(), b"two".to_vec()),
+            ("device/a/report".to_string(), b"one".to_vec()),
+        ]
+    );
+    assert_eq!(source.skipped(), 1);
+}
+
+#[test]
+fn test_print_action_payload() {
+    let payload = PrintAction::Pause.payload();
+    assert_eq!(payload["print"]["command"], "pause");
+    assert_eq!(payload["print"]["sequence_id"], "0");
+    assert_eq!(PrintAction::Resume.payload()["print"]["command"], "resume");
+    assert_eq!(PrintAction::Stop.payload()["print"]["command"], "stop");
+}
+
+#[test]
+fn test_timelapse_payload() {
+    let payload = timelapse_payload(true);
+    assert_eq!(payload["camera"]["command"], "ipcam_timelapse");
+    assert_eq!(payload["camera"]["control"], "enable");
+    assert_eq!(timelapse_payload(false)["camera"]["control"], "disable");
+}
+
+#[test]
+fn test_get_version_payload() {
+    let payload = get_version_payload();
+    assert_eq!(payload["info"]["command"], "get_version");
+    assert_eq!(payload["info"]["sequence_id"], "0");
+}
+
+#[test]
+fn test_project_file_payload() {
+    let project = ProjectFile {
+        path: "/cache/benchy.gcode.3mf".to_string(),
+        plate: 2,
+        bed_leveling: true,
+        flow_calibration: false,
+        timelapse: true,
+        ams_mapping: vec![0, 3],
+    };
+    let payload = project.payload();
+    let print = &payload["print"];
+    assert_eq!(print["command"], "project_file");
+    assert_eq!(print["param"], "Metadata/plate_2.gcode");
+    assert_eq!(print["url"], "file:///sdcard/cache/benchy.gcode.3mf");
+    assert_eq!(print["subtask_name"], "benchy");
+    assert_eq!(print["bed_levelling"], true);
+    assert_eq!(print["flow_cali"], false);
+    assert_eq!(print["timelapse"], true);
+    assert_eq!(print["use_ams"], true);
+    assert_eq!(print["ams_mapping"], serde_json::json!([0, 3]));
+
+    let external = ProjectFile {
+        ams_mapping: Vec::new(),
+        ..project
+    };
+    assert_eq!(external.payload()["print"]["use_ams"], false);
+}
+
+#[test]
+fn test_skip_objects_payload() {
+    let payload = skip_objects_payload(&[3, 7]);
+    assert_eq!(payload["print"]["command"], "skip_objects");
+    assert_eq!(payload["print"]["obj_list"], serde_json::json!([3, 7]));
+}
+
+/// Broker on a local port that answers the first CONNECT with a CONNACK carrying `code`
+async fn fake_broker(code: u8) -> u16 {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let mut buffer = [0u8; 256];
+        let _ = stream.read(&mut buffer).await;
+        let _ = stream.write_all(&[0x20, 0x02, 0x00, code]).await;
+        tokio::time::sleep(Duration::from_secs(1)).await;
+    });
+    port
+}
+
+fn local_printer(port: u16) -> PrinterConfig {
+    let mut config = PrinterConfig::new(
+        "probe".to_string(),
+        "127.0.0.1".to_string(),
+        "01S00A000000000".to_string(),
+        "12345678".to_string(),
+    );
+    config.port = port;
+    config.use_tls = false;
+    config
+}
+
+#[tokio::test]
+async fn test_probe_reachability() {
+    let timeout = Duration::from_secs(5);
+
+    let port = fake_broker(0).await;
+    assert_eq!(
+        probe(&local_printer(port), timeout).await,
+        Reachability::Online
+    );
+
+    // 4: bad user name or password
+    let port = fake_broker(4).await;
+    assert_eq!(
+        probe(&local_printer(port), timeout).await,
+        Reachability::Rejected
+    );
+
+    // Nothing listens on a port that was just released
+    let port = tokio::net::TcpListener::bind("127.0.0.1:0")
+        .await
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    assert!(matches!(
+        probe(&local_printer(port), timeout).await,
+        Reachability::Unreachable(_)
+    ));
+}
+
+/// CONNACK accepting an MQTT 5 session
+const V5_ACCEPTED: &[u8] = &[0x20, 0x03, 0x00, 0x00, 0x00];
+/// What 3.1.1 firmware answers to MQTT 5: return code 1, unacceptable protocol version
+const V311_REFUSAL: &[u8] = &[0x20, 0x02, 0x00, 0x01];
+
+/// Broker on a local port that accepts every 3.1.1 connection and answers MQTT 5 ones with
+/// `v5_replies`
+async fn versioned_broker(v5_replies: &'static [&'static [u8]]) -> u16 {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        loop {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            tokio::spawn(async move {
+                let mut buffer = [0u8; 256];
+                let read = stream.read(&mut buffer).await.unwrap_or(0);
+                // Fixed header, then "MQTT" with its length, then the protocol level
+                let level = if read > 8 { buffer[8] } else { 0 };
+                let replies: &[&[u8]] = match level {
+                    5 => v5_replies,
+                    _ => &[&[0x20, 0x02, 0x00, 0x00]],
+                };
+                for reply in replies {
+                    let _ = stream.write_all(reply).await;
+                }
+                tokio::time::sleep(Duration::from_secs(1)).await;
+            });
+        }
+    });
+    port
+}
+
+fn v5_printer(port: u16) -> PrinterConfig {
+    let mut config = local_printer(port);
+    config.mqtt_version = MqttVersion::V5;
+    config
+}
+
+fn v311_only(config: &PrinterConfig) -> bool {
+    V311_ONLY
+        .lock()
+        .unwrap()
+        .as_ref()
+        .is_some_and(|brokers| brokers.contains(&broker_key(config)))
+}
+
+#[tokio::test]
+async fn test_mqtt_version_negotiation() {
+    let config = v5_printer(versioned_broker(&[V5_ACCEPTED]).await);
+    let mut client = MqttClient::new(Arc::new(config)).await.unwrap();
+    assert_eq!(client.protocol(), MqttVersion::V5);
+    assert_eq!(client.v5_refusal(), None);
+    assert!(client.handshake().await.is_ok());
+
+    // Older firmware only speaks 3.1.1 and answers MQTT 5 with a CONNACK refusing the
+    // protocol version
+    let config = v5_printer(versioned_broker(&[V311_REFUSAL]).await);
+    let mut client = MqttClient::new(Arc::new(config.clone())).await.unwrap();
+    assert_eq!(client.protocol(), MqttVersion::V311);
+    assert!(client.v5_refusal().is_some());
+    assert!(client.handshake().await.is_ok());
+    assert!(v311_only(&config));
+    // Reconnects go straight to 3.1.1
+    let client = MqttClient::new(Arc::new(config)).await.unwrap();
+    assert_eq!(client.protocol(), MqttVersion::V311);
+    assert_eq!(client.v5_refusal(), None);
+
+    let config = v5_printer(versioned_broker(&[&[0x20, 0x03, 0x00, 0x84, 0x00]]).await);
+    let client = MqttClient::new(Arc::new(config)).await.unwrap();
+    assert_eq!(client.protocol(), MqttVersion::V311);
+    assert_eq!(
+        client.v5_refusal(),
+        Some("Broker refused the connection (UnsupportedProtocolVersion)")
+    );
+
+    let config = local_printer(versioned_broker(&[V5_ACCEPTED]).await);
+    let client = MqttClient::new(Arc::new(config)).await.unwrap();
+    assert_eq!(client.protocol(), MqttVersion::V311);
+}
+
+#[tokio::test]
+async fn test_mqtt_5_failures_do_not_fall_back() {
+    // A wrong access code is an error, not a reason to try 3.1.1
+    let config = v5_printer(versioned_broker(&[&[0x20, 0x03, 0x00, 0x86, 0x00]]).await);
+    let error = MqttClient::new(Arc::new(config.clone()))
+        .await
+        .err()
+        .unwrap();
+    assert_eq!(
+        error.to_string(),
+        "MQTT connection error: Broker refused the connection (BadUserNamePassword)"
+    );
+    assert!(!v311_only(&config));
+
+    // Nothing listening yet, e.g. while the printer boots
+    let port = {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        listener.local_addr().unwrap().port()
+    };
+    let config = v5_printer(port);
+    assert!(MqttClient::new(Arc::new(config.clone())).await.is_err());
+    assert!(!v311_only(&config));
+}
+
+#[tokio::test]
+async fn test_mqtt_5_disconnect_reason_reaches_the_event_loop() {
+    // DISCONNECT with ServerBusy (0x89) and the reason string "too many clients"
+    const DISCONNECT: &[u8] = &[
+        0xe0, 0x15, 0x89, 0x13, 0x1f, 0x00, 0x10, b't', b'o', b'o', b' ', b'm', b'a', b'n', b'y',
+        b' ', b'c', b'l', b'i', b'e', b'n', b't', b's',
+    ];
+    let config = v5_printer(versioned_broker(&[V5_ACCEPTED, DISCONNECT]).await);
+    let client = MqttClient::new(Arc::new(config)).await.unwrap();
+    assert_eq!(client.protocol(), MqttVersion::V5);
+
+    let mut eventloop = client.get_eventloop();
+    let error = eventloop.next_publish().await.err().unwrap();
+    assert_eq!(
+        error.to_string(),
+        "MQTT connection error: Broker disconnected (ServerBusy): too many clients"
+    );
+}
+
+#[test]
+fn test_describe_v5_disconnect() {
+    let error = v5::ConnectionError::MqttState(v5::StateError::ServerDisconnect {
+        reason_code: v5::mqttbytes::v5::DisconnectReasonCode::ServerBusy,
+        reason_string: Some("too many clients".to_string()),
+    });
+    assert_eq!(
+        describe_v5_error(&error),
+        "Broker disconnected (ServerBusy): too many clients"
+    );
+}
+
+#[tokio::test]
+async fn test_connection_pool_opens_and_limits() {
+    let pool = ConnectionPool::new(1);
+
+    let port = fake_broker(0).await;
+    let client = pool.open(Arc::new(local_printer(port))).await;
+    assert!(client.is_ok());
+    // The slot is given back once the broker has answered
+    assert_eq!(pool.permits.available_permits(), 1);
+
+    // 5: not authorized
+    let port = fake_broker(5).await;
+    assert!(pool.open(Arc::new(local_printer(port))).await.is_err());
+    assert_eq!(pool.permits.available_permits(), 1);
+
+    // While the only slot is taken, probes wait for it
+    let held = pool.permits.acquire().await.unwrap();
+    let printer = local_printer(fake_broker(0).await);
+    let waiting = tokio::time::timeout(
+        Duration::from_millis(200),
+        pool.probe(&printer, Duration::from_secs(5)),
+    );
+    assert!(waiting.await.is_err());
+    drop(held);
+
+    assert_eq!(
+        ConnectionPool::new(0).permits.available_permits(),
+        tokio::sync::Semaphore::MAX_PERMITS
+    );
+}
+
+#[test]
+fn test_reconnect_delay_is_staggered() {
+    let base = Duration::from_secs(5);
+    let delays: Vec<Duration> = (1..=20)
+        .map(|n| reconnect_delay(&format!("farm-{n}"), 1, base))
+        .collect();
+
+    assert!(
+        delays
+            .iter()
+            .all(|delay| *delay >= base && *delay < base * 2)
+    );
+    let distinct: std::collections::BTreeSet<_> = delays.iter().collect();
+    assert!(distinct.len() > 10);
+
+    // Stable for the same printer and attempt, different for the next attempt
+    assert_eq!(
+        reconnect_delay("farm-1", 1, base),
+        reconnect_delay("farm-1", 1, base)
+    );
+    assert_ne!(
+        reconnect_delay("farm-1", 1, base),
+        reconnect_delay("farm-1", 2, base)
+    );
+}
+
+#[test]
+fn test_fixed_delay_policy() {
+    let policy = FixedDelay {
+        delay: Duration::from_secs(5),
+        max_retries: Some(2),
+    };
+    assert!(policy.should_retry(1));
+    assert!(policy.should_retry(2));
+    assert!(!policy.should_retry(3));
+    assert_eq!(policy.next_delay("farm-1", 1), Duration::from_secs(5));
+    assert_eq!(policy.next_delay("farm-2", 9), Duration::from_secs(5));
+}
+
+#[test]
+fn test_exponential_backoff_policy() {
+    let policy = ExponentialBackoff {
+        base: Duration::from_secs(2),
+        max: Duration::from_secs(60),
+        max_retries: None,
+    };
+    assert!(policy.should_retry(u32::MAX));
+
+    // Between half and all of 2, 4, 8, ... seconds, capped at a minute
+    for (attempt, full) in [(1, 2), (2, 4), (3, 8), (6, 60), (40, 60)] {
+        let delay = policy.next_delay("farm-1", attempt);
+        let full = Duration::from_secs(full);
+        assert!(
+            delay >= full / 2 && delay <= full,
+            "attempt {attempt}: {delay:?}"
+        );
+    }
+    assert_ne!(
+        policy.next_delay("farm-1", 3),
+        policy.next_delay("farm-2", 3)
+    );
+}
+
+#[test]
+fn test_reconnect_policy_from_settings() {
+    let mut settings = crate::config::MqttSettings::default();
+    let policy = reconnect_policy(&settings);
+    assert_eq!(policy.next_delay("farm-1", 4), Duration::from_secs(5));
+    assert!(policy.should_retry(5));
+    assert!(!policy.should_retry(6));
+
+    settings.retry_backoff = crate::config::RetryBackoff::Exponential;
+    settings.max_retry_delay_secs = 20;
+    let policy = reconnect_policy(&settings);
+    assert!(policy.next_delay("farm-1", 10) <= Duration::from_secs(20));
+    assert!(policy.next_delay("farm-1", 10) >= Duration::from_secs(10));
+}
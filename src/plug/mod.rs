@@ -0,0 +1,194 @@
+use crate::config::{PlugKind, SmartPlugSettings};
+use crate::display::Icon;
+use rumqttc::{AsyncClient, Event, MqttOptions, Packet, QoS};
+use serde_json::Value;
+use std::time::Duration;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+
+#[cfg(test)]
+mod tests;
+
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// One power/energy update from a smart plug
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PlugReading {
+    pub power_w: Option<f64>,
+    /// Cumulative energy counter of the plug
+    pub total_kwh: Option<f64>,
+}
+
+/// Topic filter to subscribe to for the configured plug
+pub fn subscription(kind: PlugKind, topic: &str) -> String {
+    match kind {
+        PlugKind::Tasmota => format!("tele/{topic}/SENSOR"),
+        PlugKind::Shelly => format!("{topic}/#"),
+    }
+}
+
+/// Extracts a reading from a plug publish; unrelated topics and payloads give `None`
+///
+/// Tasmota sends `{"ENERGY": {"Total": kWh, "Power": W}}` on `tele/<topic>/SENSOR`. Shelly
+/// Gen2+ sends `{"apower": W, "aenergy": {"total": Wh}}` on `<prefix>/status/switch:<n>`;
+/// Gen1 sends bare numbers on `<prefix>/relay/<n>/power` (W) and `.../energy` (watt-minutes).
+pub fn parse_reading(kind: PlugKind, topic: &str, payload: &[u8]) -> Option<PlugReading> {
+    match kind {
+        PlugKind::Tasmota => {
+            let value: Value = serde_json::from_slice(payload).ok()?;
+            let energy = value.get("ENERGY")?;
+            let reading = PlugReading {
+                power_w: energy.get("Power").and_then(Value::as_f64),
+                total_kwh: energy.get("Total").and_then(Value::as_f64),
+            };
+            (reading != PlugReading::default()).then_some(reading)
+        }
+        PlugKind::Shelly => {
+            let (_, suffix) = topic.rsplit_once('/')?;
+            if suffix.starts_with("switch:") {
+                let value: Value = serde_json::from_slice(payload).ok()?;
+                let reading = PlugReading {
+                    power_w: value.get("apower").and_then(Value::as_f64),
+                    total_kwh: value
+                        .pointer("/aenergy/total")
+                        .and_then(Value::as_f64)
+                        .map(|wh| wh / 1000.0),
+                };
+                return (reading != PlugReading::default()).then_some(reading);
+            }
+
+            let number: f64 = std::str::from_utf8(payload).ok()?.trim().parse().ok()?;
+            match suffix {
+                "power" => Some(PlugReading {
+                    power_w: Some(number),
+                    total_kwh: None,
+                }),
+                "energy" => Some(PlugReading {
+                    power_w: None,
+                    total_kwh: Some(number / 60.0 / 1000.0),
+                }),
+                _ => None,
+            }
+        }
+    }
+}
+
+/// Energy used since monitoring started, from the plug's counter or, failing that, from
+/// integrating power readings over time
+#[derive(Debug, Clone, Default)]
+pub struct EnergyMeter {
+    energy_kwh: Option<f64>,
+    last_total: Option<f64>,
+    last_power: Option<(Duration, f64)>,
+}
+
+impl EnergyMeter {
+    /// Adds a reading taken `at` since the meter started
+    pub fn record(&mut self, reading: PlugReading, at: Duration) {
+        if let Some(total) = reading.total_kwh {
+            let energy = self.energy_kwh.get_or_insert(0.0);
+            // A lower counter means the plug was reset; count from the new value
+            if let Some(last) = self.last_total
+                && total >= last
+            {
+                *energy += total - last;
+            }
+            self.last_total = Some(total);
+            return;
+        }
+
+        // Counters are more accurate than sampled power, so power is only used without them
+        if self.last_total.is_some() {
+            return;
+        }
+        if let Some(power) = reading.power_w {
+            let energy = self.energy_kwh.get_or_insert(0.0);
+            if let Some((last_at, last_power)) = self.last_power {
+                let hours = at.saturating_sub(last_at).as_secs_f64() / 3600.0;
+                *energy += last_power * hours / 1000.0;
+            }
+            self.last_power = Some((at, power));
+        }
+    }
+
+    pub fn energy_kwh(&self) -> Option<f64> {
+        self.energy_kwh
+    }
+}
+
+/// Background subscription to a smart plug, publishing the running energy total
+#[derive(Debug)]
+pub struct PlugMonitor {
+    energy: watch::Receiver<Option<f64>>,
+    task: JoinHandle<()>,
+}
+
+impl PlugMonitor {
+    pub fn start(settings: &SmartPlugSettings, printer_name: &str) -> Self {
+        let mut options = MqttOptions::new(
+            format!("pulseprint-cli-plug-{printer_name}"),
+            &settings.broker,
+            settings.port,
+        );
+        options.set_keep_alive(Duration::from_secs(30));
+        if let Some(username) = &settings.username {
+            options.set_credentials(username, settings.password.as_deref().unwrap_or_default());
+        }
+
+        let (client, mut eventloop) = AsyncClient::new(options, 10);
+        let (sender, energy) = watch::channel(None);
+        let kind = settings.kind;
+        let filter = subscription(kind, &settings.topic);
+        let broker = settings.broker.clone();
+
+        let task = tokio::spawn(async move {
+            let started = Instant::now();
+            let mut meter = EnergyMeter::default();
+            let mut reported_error = false;
+
+            loop {
+                match eventloop.poll().await {
+                    Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                        reported_error = false;
+                        // Subscriptions do not survive a reconnect with a clean session
+                        if let Err(e) = client.subscribe(&filter, QoS::AtMostOnce).await {
+                            eprintln!("{} Smart plug subscribe failed: {e}", Icon::Failure);
+                        }
+                    }
+                    Ok(Event::Incoming(Packet::Publish(publish))) => {
+                        if let Some(reading) = parse_reading(kind, &publish.topic, &publish.payload)
+                        {
+                            meter.record(reading, started.elapsed());
+                            sender.send_replace(meter.energy_kwh());
+                        }
+                    }
+                    Ok(_) => {}
+                    Err(e) => {
+                        if !reported_error {
+                            eprintln!(
+                                "{} Smart plug broker {broker} unreachable: {e}",
+                                Icon::Failure
+                            );
+                            reported_error = true;
+                        }
+                        tokio::time::sleep(RECONNECT_DELAY).await;
+                    }
+                }
+            }
+        });
+
+        Self { energy, task }
+    }
+
+    /// Energy used since the monitor started, once the plug has reported anything
+    pub fn energy_kwh(&self) -> Option<f64> {
+        *self.energy.borrow()
+    }
+}
+
+impl Drop for PlugMonitor {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
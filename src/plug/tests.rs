@@ -0,0 +1,102 @@
+use super::*;
+
+fn secs(secs: u64) -> Duration {
+    Duration::from_secs(secs)
+}
+
+#[test]
+fn test_subscription_per_kind() {
+    assert_eq!(
+        subscription(PlugKind::Tasmota, "printer_plug"),
+        "tele/printer_plug/SENSOR"
+    );
+    assert_eq!(
+        subscription(PlugKind::Shelly, "shellies/plug-1"),
+        "shellies/plug-1/#"
+    );
+}
+
+#[test]
+fn test_parse_tasmota_sensor() {
+    let payload =
+        br#"{"Time":"2025-08-01T09:00:00","ENERGY":{"Total":12.345,"Power":187,"Voltage":230}}"#;
+    assert_eq!(
+        parse_reading(PlugKind::Tasmota, "tele/plug/SENSOR", payload),
+        Some(PlugReading {
+            power_w: Some(187.0),
+            total_kwh: Some(12.345),
+        })
+    );
+    assert_eq!(
+        parse_reading(PlugKind::Tasmota, "tele/plug/SENSOR", br#"{"AM2301":{}}"#),
+        None
+    );
+}
+
+#[test]
+fn test_parse_shelly_gen2_and_gen1() {
+    let payload = br#"{"id":0,"output":true,"apower":95.5,"aenergy":{"total":2500.0}}"#;
+    assert_eq!(
+        parse_reading(PlugKind::Shelly, "shelly-plug/status/switch:0", payload),
+        Some(PlugReading {
+            power_w: Some(95.5),
+            total_kwh: Some(2.5),
+        })
+    );
+
+    assert_eq!(
+        parse_reading(PlugKind::Shelly, "shellies/plug-1/relay/0/power", b"120.5"),
+        Some(PlugReading {
+            power_w: Some(120.5),
+            total_kwh: None,
+        })
+    );
+    // Gen1 energy counters are in watt-minutes
+    assert_eq!(
+        parse_reading(PlugKind::Shelly, "shellies/plug-1/relay/0/energy", b"60000"),
+        Some(PlugReading {
+            power_w: None,
+            total_kwh: Some(1.0),
+        })
+    );
+    assert_eq!(
+        parse_reading(PlugKind::Shelly, "shellies/plug-1/relay/0", b"on"),
+        None
+    );
+}
+
+#[test]
+fn test_meter_uses_counter_deltas() {
+    let mut meter = EnergyMeter::default();
+    assert_eq!(meter.energy_kwh(), None);
+
+    for (at, total) in [(0, 10.0), (60, 10.2), (120, 0.1), (180, 0.3)] {
+        meter.record(
+            PlugReading {
+                power_w: Some(500.0),
+                total_kwh: Some(total),
+            },
+            secs(at),
+        );
+    }
+
+    // The reset between 10.2 and 0.1 is skipped rather than counted as negative usage
+    assert!((meter.energy_kwh().unwrap() - 0.4).abs() < 1e-9);
+}
+
+#[test]
+fn test_meter_integrates_power_without_counter() {
+    let mut meter = EnergyMeter::default();
+    for (at, power) in [(0, 100.0), (1800, 300.0), (3600, 0.0)] {
+        meter.record(
+            PlugReading {
+                power_w: Some(power),
+                total_kwh: None,
+            },
+            secs(at),
+        );
+    }
+
+    // 100 W for half an hour, then 300 W for half an hour
+    assert!((meter.energy_kwh().unwrap() - 0.2).abs() < 1e-9);
+}
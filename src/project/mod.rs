@@ -0,0 +1,258 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{Read, Seek};
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+#[cfg(test)]
+mod tests;
+
+/// Slicer output inside a Bambu Studio / Orca Slicer project
+const SLICE_INFO: &str = "Metadata/slice_info.config";
+
+#[derive(Debug, Error)]
+pub enum ProjectError {
+    #[error("IO error: {0}")]
+    IoError(#[from] std::io::Error),
+
+    #[error("Not a 3MF archive: {0}")]
+    ArchiveError(#[from] zip::result::ZipError),
+
+    #[error("Invalid slice metadata: {0}")]
+    XmlError(#[from] roxmltree::Error),
+
+    #[error("Project has not been sliced; export it as a plate-sliced 3MF first")]
+    NotSliced,
+
+    #[error("Project has no plate {0}")]
+    UnknownPlate(u32),
+}
+
+/// Slicer metadata of a project file, as stored in `Metadata/slice_info.config`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProjectInfo {
+    /// Job name the printer will report, i.e. the file name without its extension
+    pub name: String,
+    pub plates: Vec<PlateInfo>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlateInfo {
+    pub index: u32,
+    /// Print time predicted by the slicer
+    pub estimated_secs: Option<u32>,
+    /// Total filament weight in grams
+    pub weight_g: Option<f64>,
+    pub filaments: Vec<FilamentInfo>,
+    /// Objects the printer can identify, and skip, during the print
+    pub objects: Vec<PlateObject>,
+    /// Nozzle diameter in millimeters the plate was sliced for
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nozzle_diameter: Option<f64>,
+    /// Archive path of the plate preview image
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thumbnail: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FilamentInfo {
+    /// Filament slot of the project, starting at 1
+    pub id: u32,
+    pub material: Option<String>,
+    pub color: Option<String>,
+    pub used_m: Option<f64>,
+    pub used_g: Option<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlateObject {
+    /// Identify ID used by the skip-objects command
+    pub id: u32,
+    pub name: String,
+}
+
+impl ProjectInfo {
+    pub fn read(path: &Path) -> Result<Self, ProjectError> {
+        let file_name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        Self::from_archive(job_name(&file_name), fs::File::open(path)?)
+    }
+
+    pub fn from_archive<R: Read + Seek>(name: &str, reader: R) -> Result<Self, ProjectError> {
+        let mut archive = zip::ZipArchive::new(reader)?;
+        let mut xml = String::new();
+        match archive.by_name(SLICE_INFO) {
+            Ok(mut entry) => entry.read_to_string(&mut xml)?,
+            Err(zip::result::ZipError::FileNotFound) => return Err(ProjectError::NotSliced),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut plates = parse_slice_info(&xml)?;
+        for plate in &mut plates {
+            let thumbnail = format!("Metadata/plate_{}.png", plate.index);
+            if archive.index_for_name(&thumbnail).is_some() {
+                plate.thumbnail = Some(thumbnail);
+            }
+        }
+        Ok(Self {
+            name: name.to_string(),
+            plates,
+        })
+    }
+
+    /// The plate with `index`, or the only plate when no index is known
+    pub fn plate(&self, index: Option<u32>) -> Option<&PlateInfo> {
+        match index {
+            Some(index) => self.plates.iter().find(|plate| plate.index == index),
+            None if self.plates.len() == 1 => self.plates.first(),
+            None => None,
+        }
+    }
+}
+
+impl PlateInfo {
+    pub fn total_weight_g(&self) -> Option<f64> {
+        self.weight_g.or_else(|| {
+            let weights: Vec<f64> = self.filaments.iter().filter_map(|f| f.used_g).collect();
+            (!weights.is_empty()).then(|| weights.iter().sum())
+        })
+    }
+
+    /// Filament use as `12.3 g PLA, 4.0 g PETG`
+    pub fn filament_summary(&self) -> Option<String> {
+        let parts: Vec<String> = self
+            .filaments
+            .iter()
+            .filter_map(|filament| {
+                Some(format!(
+                    "{:.1} g {}",
+                    filament.used_g?,
+                    filament.material.as_deref().unwrap_or("filament")
+                ))
+            })
+            .collect();
+        (!parts.is_empty()).then(|| parts.join(", "))
+    }
+}
+
+pub fn parse_slice_info(xml: &str) -> Result<Vec<PlateInfo>, ProjectError> {
+    let document = roxmltree::Document::parse(xml)?;
+
+    let plates = document
+        .descendants()
+        .filter(|node| node.has_tag_name("plate"))
+        .enumerate()
+        .map(|(position, plate)| {
+            let metadata = |key: &str| {
+                plate
+                    .children()
+                    .find(|node| {
+                        node.has_tag_name("metadata") && node.attribute("key") == Some(key)
+                    })
+                    .and_then(|node| node.attribute("value"))
+            };
+
+            PlateInfo {
+                index: metadata("index")
+                    .and_then(|value| value.parse().ok())
+                    .unwrap_or(position as u32 + 1),
+                estimated_secs: metadata("prediction").and_then(|value| value.parse().ok()),
+                weight_g: metadata("weight").and_then(|value| value.parse().ok()),
+                // One entry per extruder, separated by spaces or commas
+                nozzle_diameter: metadata("nozzle_diameters").and_then(|value| {
+                    value
+                        .split([',', ' '])
+                        .find(|part| !part.is_empty())?
+                        .parse()
+                        .ok()
+                }),
+                filaments: plate
+                    .children()
+                    .filter(|node| node.has_tag_name("filament"))
+                    .map(|node| FilamentInfo {
+                        id: node
+                            .attribute("id")
+                            .and_then(|id| id.parse().ok())
+                            .unwrap_or_default(),
+                        material: node.attribute("type").map(str::to_string),
+                        color: node.attribute("color").map(str::to_string),
+                        used_m: node
+                            .attribute("used_m")
+                            .and_then(|value| value.parse().ok()),
+                        used_g: node
+                            .attribute("used_g")
+                            .and_then(|value| value.parse().ok()),
+                    })
+                    .collect(),
+                objects: plate
+                    .children()
+                    .filter(|node| node.has_tag_name("object"))
+                    .filter_map(|node| {
+                        Some(PlateObject {
+                            id: node.attribute("identify_id")?.parse().ok()?,
+                            name: node.attribute("name").unwrap_or_default().to_string(),
+                        })
+                    })
+                    .collect(),
+                thumbnail: None,
+            }
+        })
+        .collect();
+    Ok(plates)
+}
+
+/// Preview image of `plate` as PNG bytes
+pub fn read_thumbnail(path: &Path, plate: u32) -> Result<Vec<u8>, ProjectError> {
+    let mut archive = zip::ZipArchive::new(fs::File::open(path)?)?;
+    let mut entry = match archive.by_name(&format!("Metadata/plate_{plate}.png")) {
+        Ok(entry) => entry,
+        Err(zip::result::ZipError::FileNotFound) => return Err(ProjectError::UnknownPlate(plate)),
+        Err(e) => return Err(e.into()),
+    };
+    let mut image = Vec::new();
+    entry.read_to_end(&mut image)?;
+    Ok(image)
+}
+
+/// Job name the printer reports for a project file: the file name without `.gcode.3mf`/`.3mf`
+pub fn job_name(file_name: &str) -> &str {
+    let name = file_name.rsplit('/').next().unwrap_or(file_name);
+    name.strip_suffix(".gcode.3mf")
+        .or_else(|| name.strip_suffix(".3mf"))
+        .unwrap_or(name)
+}
+
+/// Plate number from the printer's `gcode_file` field, e.g. `/data/Metadata/plate_2.gcode`
+pub fn plate_index(gcode_file: &str) -> Option<u32> {
+    gcode_file
+        .rsplit('/')
+        .next()?
+        .strip_prefix("plate_")?
+        .strip_suffix(".gcode")?
+        .parse()
+        .ok()
+}
+
+fn catalog_path(dir: &Path, name: &str) -> PathBuf {
+    let file_name: String = name
+        .chars()
+        .map(|c| if matches!(c, '/' | '\\') { '_' } else { c })
+        .collect();
+    dir.join(format!("{file_name}.json"))
+}
+
+/// Keeps the metadata so jobs printed from this project can be matched to it later
+pub fn remember(dir: &Path, project: &ProjectInfo) -> std::io::Result<PathBuf> {
+    fs::create_dir_all(dir)?;
+    let path = catalog_path(dir, &project.name);
+    fs::write(&path, serde_json::to_string_pretty(project)?)?;
+    Ok(path)
+}
+
+/// Metadata remembered for the project with this job name
+pub fn lookup(dir: &Path, name: &str) -> Option<ProjectInfo> {
+    let contents = fs::read_to_string(catalog_path(dir, name)).ok()?;
+    serde_json::from_str(&contents).ok()
+}
@@ -0,0 +1,128 @@
+use super::*;
+use std::io::{Cursor, Write};
+use tempfile::tempdir;
+
+const SLICE_INFO_XML: &str = r##"<?xml version="1.0" encoding="UTF-8"?>
+<config>
+  <header>
+    <header_item key="X-BBL-Client-Type" value="slicer"/>
+  </header>
+  <plate>
+    <metadata key="index" value="1"/>
+    <metadata key="prediction" value="3723"/>
+    <metadata key="weight" value="16.40"/>
+    <metadata key="nozzle_diameters" value="0.4"/>
+    <object identify_id="127" name="Cube" skipped="false" />
+    <object identify_id="296" name="Cylinder" skipped="false" />
+    <filament id="1" tray_info_idx="GFA00" type="PLA" color="#FFFFFF" used_m="4.12" used_g="12.30" />
+    <filament id="3" tray_info_idx="GFG00" type="PETG" color="#000000" used_m="1.30" used_g="4.10" />
+  </plate>
+  <plate>
+    <metadata key="index" value="2"/>
+    <metadata key="prediction" value="600"/>
+  </plate>
+</config>"##;
+
+fn archive(entries: &[(&str, &[u8])]) -> Cursor<Vec<u8>> {
+    let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+    for (name, contents) in entries {
+        writer
+            .start_file(*name, zip::write::SimpleFileOptions::default())
+            .unwrap();
+        writer.write_all(contents).unwrap();
+    }
+    let mut cursor = writer.finish().unwrap();
+    cursor.set_position(0);
+    cursor
+}
+
+#[test]
+fn test_parse_slice_info() {
+    let plates = parse_slice_info(SLICE_INFO_XML).unwrap();
+    assert_eq!(plates.len(), 2);
+
+    let first = &plates[0];
+    assert_eq!(first.index, 1);
+    assert_eq!(first.estimated_secs, Some(3723));
+    assert_eq!(first.weight_g, Some(16.4));
+    assert_eq!(first.nozzle_diameter, Some(0.4));
+    assert_eq!(
+        first.objects,
+        vec![
+            PlateObject {
+                id: 127,
+                name: "Cube".to_string()
+            },
+            PlateObject {
+                id: 296,
+                name: "Cylinder".to_string()
+            },
+        ]
+    );
+    assert_eq!(first.filaments[1].id, 3);
+    assert_eq!(first.filaments[1].material.as_deref(), Some("PETG"));
+    assert_eq!(
+        first.filament_summary().as_deref(),
+        Some("12.3 g PLA, 4.1 g PETG")
+    );
+
+    assert_eq!(plates[1].index, 2);
+    assert!(plates[1].filaments.is_empty());
+    assert_eq!(plates[1].nozzle_diameter, None);
+    assert_eq!(plates[1].total_weight_g(), None);
+}
+
+#[test]
+fn test_from_archive_finds_thumbnails() {
+    let project = ProjectInfo::from_archive(
+        "benchy",
+        archive(&[
+            (SLICE_INFO, SLICE_INFO_XML.as_bytes()),
+            ("Metadata/plate_1.png", b"png"),
+        ]),
+    )
+    .unwrap();
+
+    assert_eq!(project.name, "benchy");
+    assert_eq!(
+        project.plates[0].thumbnail.as_deref(),
+        Some("Metadata/plate_1.png")
+    );
+    assert_eq!(project.plates[1].thumbnail, None);
+    assert_eq!(project.plate(Some(2)).unwrap().estimated_secs, Some(600));
+    // Ambiguous without a plate number
+    assert!(project.plate(None).is_none());
+}
+
+#[test]
+fn test_unsliced_project_is_rejected() {
+    let result = ProjectInfo::from_archive("model", archive(&[("3D/3dmodel.model", b"<model/>")]));
+    assert!(matches!(result, Err(ProjectError::NotSliced)));
+
+    let result = ProjectInfo::from_archive("model", Cursor::new(b"not a zip".to_vec()));
+    assert!(matches!(result, Err(ProjectError::ArchiveError(_))));
+}
+
+#[test]
+fn test_job_name_and_plate_index() {
+    assert_eq!(job_name("benchy.gcode.3mf"), "benchy");
+    assert_eq!(job_name("cache/bracket v2.3mf"), "bracket v2");
+    assert_eq!(job_name("notes.txt"), "notes.txt");
+
+    assert_eq!(plate_index("/data/Metadata/plate_2.gcode"), Some(2));
+    assert_eq!(plate_index("Metadata/plate_10.gcode"), Some(10));
+    assert_eq!(plate_index("benchy.gcode"), None);
+}
+
+#[test]
+fn test_remember_and_lookup() {
+    let temp_dir = tempdir().unwrap();
+    let project = ProjectInfo {
+        name: "benchy".to_string(),
+        plates: parse_slice_info(SLICE_INFO_XML).unwrap(),
+    };
+
+    remember(temp_dir.path(), &project).unwrap();
+    assert_eq!(lookup(temp_dir.path(), "benchy"), Some(project));
+    assert_eq!(lookup(temp_dir.path(), "other"), None);
+}
@@ -0,0 +1,185 @@
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use thiserror::Error;
+
+#[cfg(test)]
+mod tests;
+
+#[derive(Debug, Error)]
+pub enum RecordingError {
+    #[error("IO error: {0}")]
+    IoError(#[from] std::io::Error),
+
+    #[error("Failed to serialize recorded message: {0}")]
+    SerializeError(#[from] serde_json::Error),
+
+    #[error("Invalid session file at line {line}: {source}")]
+    InvalidLine {
+        line: usize,
+        source: serde_json::Error,
+    },
+
+    #[error("Invalid base64 payload: {0}")]
+    InvalidPayload(#[from] base64::DecodeError),
+}
+
+/// How a [`RecordedMessage`] holds the payload bytes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PayloadEncoding {
+    /// The payload as text, for every payload that is valid UTF-8
+    #[default]
+    Utf8,
+    /// Standard base64, for payloads that are not valid UTF-8
+    Base64,
+}
+
+impl PayloadEncoding {
+    fn is_utf8(&self) -> bool {
+        *self == PayloadEncoding::Utf8
+    }
+}
+
+/// A single raw MQTT payload captured from the printer, stored as one NDJSON line. Lines of
+/// text payloads carry no `encoding`, so sessions stay readable and older files still load.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordedMessage {
+    pub timestamp_ms: u64,
+    pub topic: String,
+    pub payload: String,
+    #[serde(default, skip_serializing_if = "PayloadEncoding::is_utf8")]
+    pub encoding: PayloadEncoding,
+}
+
+impl RecordedMessage {
+    pub fn new(topic: &str, payload: &[u8]) -> Self {
+        let (payload, encoding) = match std::str::from_utf8(payload) {
+            Ok(text) => (text.to_string(), PayloadEncoding::Utf8),
+            Err(_) => (BASE64.encode(payload), PayloadEncoding::Base64),
+        };
+        Self {
+            timestamp_ms: current_timestamp_ms(),
+            topic: topic.to_string(),
+            payload,
+            encoding,
+        }
+    }
+
+    /// The payload exactly as it was received
+    pub fn payload_bytes(&self) -> Result<Cow<'_, [u8]>, RecordingError> {
+        match self.encoding {
+            PayloadEncoding::Utf8 => Ok(Cow::Borrowed(self.payload.as_bytes())),
+            PayloadEncoding::Base64 => Ok(Cow::Owned(BASE64.decode(&self.payload)?)),
+        }
+    }
+}
+
+/// Appends recorded messages to an NDJSON session file
+pub struct SessionRecorder {
+    writer: BufWriter<File>,
+    message_count: usize,
+}
+
+impl SessionRecorder {
+    pub fn create(path: &Path) -> Result<Self, RecordingError> {
+        if let Some(parent) = path.parent()
+            && !parent.as_os_str().is_empty()
+        {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let file = File::create(path)?;
+
+        Ok(Self {
+            writer: BufWriter::new(file),
+            message_count: 0,
+        })
+    }
+
+    pub fn record(&mut self, topic: &str, payload: &[u8]) -> Result<(), RecordingError> {
+        self.write_message(&RecordedMessage::new(topic, payload))
+    }
+
+    pub fn write_message(&mut self, message: &RecordedMessage) -> Result<(), RecordingError> {
+        serde_json::to_writer(&mut self.writer, message)?;
+        self.writer.write_all(b"\n")?;
+        // Flush every line so an interrupted session still leaves a usable file
+        self.writer.flush()?;
+        self.message_count += 1;
+        Ok(())
+    }
+
+    pub fn message_count(&self) -> usize {
+        self.message_count
+    }
+}
+
+/// Reads every recorded message from an NDJSON session file, skipping blank lines
+pub fn read_session(path: &Path) -> Result<Vec<RecordedMessage>, RecordingError> {
+    let reader = BufReader::new(File::open(path)?);
+    let mut messages = Vec::new();
+
+    for (index, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let message =
+            serde_json::from_str(&line).map_err(|source| RecordingError::InvalidLine {
+                line: index + 1,
+                source,
+            })?;
+        messages.push(message);
+    }
+
+    Ok(messages)
+}
+
+/// Slowest and fastest replay speeds `parse_speed` accepts
+pub const SPEED_RANGE: std::ops::RangeInclusive<f64> = 0.01..=1000.0;
+
+/// Parses a replay speed multiplier such as `10x`, `0.5x` or `2`
+pub fn parse_speed(value: &str) -> Result<f64, String> {
+    let trimmed = value.trim();
+    let number = trimmed
+        .strip_suffix('x')
+        .or_else(|| trimmed.strip_suffix('X'))
+        .unwrap_or(trimmed);
+
+    let speed: f64 = number
+        .parse()
+        .map_err(|_| format!("Invalid speed '{value}' (expected e.g. '10x')"))?;
+
+    if !speed.is_finite() || speed <= 0.0 {
+        return Err(format!("Speed must be greater than zero, got '{value}'"));
+    }
+    if !SPEED_RANGE.contains(&speed) {
+        return Err(format!(
+            "Speed must be between {}x and {}x, got '{value}'",
+            SPEED_RANGE.start(),
+            SPEED_RANGE.end()
+        ));
+    }
+
+    Ok(speed)
+}
+
+/// Delay to wait before replaying `next`, scaled by the replay speed
+pub fn replay_delay(previous: &RecordedMessage, next: &RecordedMessage, speed: f64) -> Duration {
+    let gap_ms = next.timestamp_ms.saturating_sub(previous.timestamp_ms);
+    Duration::try_from_secs_f64(gap_ms as f64 / 1000.0 / speed).unwrap_or(Duration::MAX)
+}
+
+pub fn current_timestamp_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
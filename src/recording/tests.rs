@@ -0,0 +1,145 @@
+use super::*;
+use tempfile::tempdir;
+
+#[test]
+fn test_recorded_message_new() {
+    let message = RecordedMessage::new("device/abc/report", br#"{"print":{}}"#);
+
+    assert_eq!(message.topic, "device/abc/report");
+    assert_eq!(message.payload, r#"{"print":{}}"#);
+    assert!(message.timestamp_ms > 0);
+}
+
+#[test]
+fn test_recorded_message_keeps_binary_payload() {
+    let bytes = [0x7b, 0xff, 0x7d];
+    let message = RecordedMessage::new("device/abc/report", &bytes);
+    assert_eq!(message.encoding, PayloadEncoding::Base64);
+    assert_eq!(message.payload, "e/99");
+    assert_eq!(message.payload_bytes().unwrap().as_ref(), bytes);
+
+    let line = serde_json::to_string(&message).unwrap();
+    assert!(line.contains(r#""encoding":"base64""#));
+    let read: RecordedMessage = serde_json::from_str(&line).unwrap();
+    assert_eq!(read.payload_bytes().unwrap().as_ref(), bytes);
+}
+
+#[test]
+fn test_text_payload_lines_have_no_encoding() {
+    let message = RecordedMessage::new("device/abc/report", b"{}");
+    assert_eq!(message.encoding, PayloadEncoding::Utf8);
+    let line = serde_json::to_string(&message).unwrap();
+    assert!(!line.contains("encoding"));
+
+    // Sessions recorded before payloads had an encoding
+    let old: RecordedMessage =
+        serde_json::from_str(r#"{"timestamp_ms": 1, "topic": "t", "payload": "{}"}"#).unwrap();
+    assert_eq!(old.payload_bytes().unwrap().as_ref(), b"{}");
+
+    let broken = RecordedMessage {
+        encoding: PayloadEncoding::Base64,
+        ..old
+    };
+    assert!(matches!(
+        broken.payload_bytes(),
+        Err(RecordingError::InvalidPayload(_))
+    ));
+}
+
+#[test]
+fn test_session_recorder_writes_ndjson() {
+    let temp_dir = tempdir().unwrap();
+    let path = temp_dir.path().join("sessions").join("session.ndjson");
+
+    let mut recorder = SessionRecorder::create(&path).unwrap();
+    recorder
+        .record(
+            "device/abc/report",
+            br#"{"print":{"command":"push_status"}}"#,
+        )
+        .unwrap();
+    recorder
+        .record("device/abc/report", br#"{"pushing":{"command":"pushall"}}"#)
+        .unwrap();
+    assert_eq!(recorder.message_count(), 2);
+
+    let contents = std::fs::read_to_string(&path).unwrap();
+    let lines: Vec<&str> = contents.lines().collect();
+    assert_eq!(lines.len(), 2);
+
+    let first: RecordedMessage = serde_json::from_str(lines[0]).unwrap();
+    assert_eq!(first.topic, "device/abc/report");
+    assert_eq!(first.payload, r#"{"print":{"command":"push_status"}}"#);
+}
+
+#[test]
+fn test_read_session_round_trip() {
+    let temp_dir = tempdir().unwrap();
+    let path = temp_dir.path().join("session.ndjson");
+
+    let mut recorder = SessionRecorder::create(&path).unwrap();
+    recorder.record("device/abc/report", b"{}").unwrap();
+    recorder.record("device/abc/report", b"[]").unwrap();
+
+    let messages = read_session(&path).unwrap();
+    assert_eq!(messages.len(), 2);
+    assert_eq!(messages[0].payload, "{}");
+    assert_eq!(messages[1].payload, "[]");
+}
+
+#[test]
+fn test_read_session_reports_bad_line() {
+    let temp_dir = tempdir().unwrap();
+    let path = temp_dir.path().join("session.ndjson");
+    std::fs::write(
+        &path,
+        "{\"timestamp_ms\":1,\"topic\":\"t\",\"payload\":\"{}\"}\n\nnot json\n",
+    )
+    .unwrap();
+
+    match read_session(&path) {
+        Err(RecordingError::InvalidLine { line, .. }) => assert_eq!(line, 3),
+        other => panic!("Expected InvalidLine, got {other:?}"),
+    }
+}
+
+#[test]
+fn test_parse_speed() {
+    assert_eq!(parse_speed("10x").unwrap(), 10.0);
+    assert_eq!(parse_speed("0.5x").unwrap(), 0.5);
+    assert_eq!(parse_speed("2").unwrap(), 2.0);
+    assert!(parse_speed("0x").is_err());
+    assert!(parse_speed("-1").is_err());
+    assert!(parse_speed("fast").is_err());
+
+    // Bounds are inclusive
+    assert_eq!(parse_speed("0.01x").unwrap(), 0.01);
+    assert_eq!(parse_speed("1000x").unwrap(), 1000.0);
+    assert!(parse_speed("0.009x").is_err());
+    assert!(parse_speed("1e-300x").is_err());
+    assert!(parse_speed("1001x").is_err());
+}
+
+#[test]
+fn test_replay_delay_scales_with_speed() {
+    let first = RecordedMessage {
+        timestamp_ms: 1_000,
+        topic: "t".to_string(),
+        payload: "{}".to_string(),
+        encoding: PayloadEncoding::Utf8,
+    };
+    let second = RecordedMessage {
+        timestamp_ms: 3_000,
+        ..first.clone()
+    };
+
+    assert_eq!(replay_delay(&first, &second, 1.0), Duration::from_secs(2));
+    assert_eq!(
+        replay_delay(&first, &second, 10.0),
+        Duration::from_millis(200)
+    );
+    // Out-of-order timestamps never produce a negative delay
+    assert_eq!(replay_delay(&second, &first, 1.0), Duration::ZERO);
+    // Too long to represent
+    assert_eq!(replay_delay(&first, &second, 1e-300), Duration::MAX);
+}
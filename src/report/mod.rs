@@ -0,0 +1,490 @@
+use crate::config::{ReportFormat, ReportSettings};
+use crate::display::format_duration_short;
+use crate::jobs::{JobRecord, TemperatureStats};
+use crate::messages::PrintState;
+use crate::stats::JobStats;
+use chrono::{DateTime, Local, NaiveDate, TimeZone};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[cfg(test)]
+mod tests;
+
+const TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+/// Label/value rows shared by both report formats; the printer's `details` follow its name
+fn summary_rows<'a>(
+    printer: &str,
+    details: &'a [(String, String)],
+    job: &JobRecord,
+) -> Vec<(&'a str, String)> {
+    let mut rows = vec![("Printer", printer.to_string())];
+    rows.extend(
+        details
+            .iter()
+            .map(|(label, value)| (label.as_str(), value.clone())),
+    );
+    rows.extend([
+        ("Result", result_label(&job.final_state).to_string()),
+        ("Started", job.started_at.format(TIME_FORMAT).to_string()),
+        ("Ended", job.ended_at.format(TIME_FORMAT).to_string()),
+        ("Duration", format_duration_short(job.elapsed_secs())),
+    ]);
+
+    if job.start_progress > 0 {
+        rows.push((
+            "Note",
+            format!(
+                "Monitoring started at {}%, duration covers the observed part only",
+                job.start_progress
+            ),
+        ));
+    }
+    if let Some(estimate) = job.plate.as_ref().and_then(|plate| plate.estimated_secs) {
+        rows.push(("Slicer estimate", format_duration_short(estimate)));
+    }
+    if let Some(progress) = job.final_progress {
+        rows.push(("Progress", format!("{progress}%")));
+    }
+    match (job.last_layer, job.total_layers) {
+        (Some(layer), Some(total)) => rows.push(("Layers", format!("{layer}/{total}"))),
+        (None, Some(total)) => rows.push(("Layers", total.to_string())),
+        (Some(layer), None) => rows.push(("Layers", layer.to_string())),
+        (None, None) => {}
+    }
+    if let Some(summary) = temperature_summary(&job.nozzle) {
+        rows.push(("Nozzle", summary));
+    }
+    if let Some(summary) = temperature_summary(&job.bed) {
+        rows.push(("Bed", summary));
+    }
+    for usage in job.filament_usage() {
+        rows.push((
+            "Filament",
+            format!(
+                "{:.1} g {} from {}",
+                usage.grams,
+                usage.material.as_deref().unwrap_or("filament"),
+                usage.tray
+            ),
+        ));
+    }
+    if let Some(summary) = job
+        .plate
+        .as_ref()
+        .and_then(|plate| plate.filament_summary())
+    {
+        rows.push(("Sliced filament", summary));
+    }
+    if let Some(energy) = job.energy_kwh() {
+        rows.push(("Energy", format!("{energy:.2} kWh (smart plug)")));
+    }
+    if let Some(cost) = &job.cost {
+        let mut parts = Vec::new();
+        if let Some(filament) = cost.filament {
+            parts.push(format!("filament {}", cost.format_amount(filament)));
+        }
+        if let Some(electricity) = cost.electricity {
+            parts.push(format!("electricity {}", cost.format_amount(electricity)));
+        }
+        rows.push((
+            "Estimated cost",
+            format!(
+                "{} ({})",
+                cost.format_amount(cost.total()),
+                parts.join(", ")
+            ),
+        ));
+    }
+    if job.pauses > 0 {
+        rows.push(("Pauses", job.pauses.to_string()));
+    }
+    if let Some(reason) = &job.fail_reason {
+        rows.push(("Failure", reason.clone()));
+    }
+
+    rows
+}
+
+fn temperature_summary(stats: &TemperatureStats) -> Option<String> {
+    let average = stats.average()?;
+    Some(format!("peak {:.1}°C, average {average:.1}°C", stats.peak))
+}
+
+fn result_label(state: &PrintState) -> &'static str {
+    match state {
+        PrintState::Finished => "Finished",
+        PrintState::Failed => "Failed",
+        PrintState::Printing | PrintState::Paused => "Interrupted",
+        PrintState::Idle | PrintState::Unknown(_) => "Unknown",
+    }
+}
+
+fn job_title(job: &JobRecord) -> &str {
+    job.name.as_deref().unwrap_or("Untitled job")
+}
+
+pub fn render_markdown(printer: &str, details: &[(String, String)], job: &JobRecord) -> String {
+    let mut out = format!("# Print report: {}\n\n", job_title(job));
+    out.push_str("| | |\n|---|---|\n");
+    for (label, value) in summary_rows(printer, details, job) {
+        out.push_str(&format!("| {label} | {} |\n", value.replace('|', "\\|")));
+    }
+
+    out.push_str("\n## HMS events\n\n");
+    if job.hms_codes.is_empty() {
+        out.push_str("None\n");
+    } else {
+        for code in &job.hms_codes {
+            out.push_str(&format!("- `HMS_{code}`\n"));
+        }
+    }
+    out
+}
+
+pub fn render_html(printer: &str, details: &[(String, String)], job: &JobRecord) -> String {
+    let title = escape_html(job_title(job));
+    let mut out = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Print report: {title}</title>\n</head>\n<body>\n<h1>Print report: {title}</h1>\n<table>\n"
+    );
+    for (label, value) in summary_rows(printer, details, job) {
+        out.push_str(&format!(
+            "<tr><th>{label}</th><td>{}</td></tr>\n",
+            escape_html(&value)
+        ));
+    }
+    out.push_str("</table>\n<h2>HMS events</h2>\n");
+    if job.hms_codes.is_empty() {
+        out.push_str("<p>None</p>\n");
+    } else {
+        out.push_str("<ul>\n");
+        for code in &job.hms_codes {
+            out.push_str(&format!("<li><code>HMS_{code}</code></li>\n"));
+        }
+        out.push_str("</ul>\n");
+    }
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Writes the report for a completed job and returns its path
+///
+/// Files are named `<start time>-<printer>-<job>.<ext>` inside the configured directory, or
+/// `default_dir` when none is set.
+pub fn write_report(
+    settings: &ReportSettings,
+    default_dir: &Path,
+    printer: &str,
+    details: &[(String, String)],
+    job: &JobRecord,
+) -> std::io::Result<PathBuf> {
+    let directory = settings.directory.as_deref().unwrap_or(default_dir);
+    fs::create_dir_all(directory)?;
+
+    let (contents, extension) = match settings.format {
+        ReportFormat::Markdown => (render_markdown(printer, details, job), "md"),
+        ReportFormat::Html => (render_html(printer, details, job), "html"),
+    };
+
+    let file_name = format!(
+        "{}-{}-{}.{extension}",
+        job.started_at.format("%Y%m%d-%H%M%S"),
+        file_name_part(printer),
+        file_name_part(job_title(job))
+    );
+    let path = directory.join(file_name);
+    fs::write(&path, contents)?;
+    Ok(path)
+}
+
+/// Keeps letters, digits, `-` and `_`; everything else becomes `_`
+fn file_name_part(text: &str) -> String {
+    text.chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// First day of the month given as `YYYY-MM`, e.g. `2024-07`
+pub fn parse_month(value: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(&format!("{}-01", value.trim()), "%Y-%m-%d")
+        .map_err(|_| format!("Invalid month '{value}' (expected e.g. '2024-07')"))
+}
+
+/// One printer's jobs of a [`MonthlyReport`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct PrinterMonth {
+    pub printer: String,
+    pub stats: JobStats,
+    /// Time spent printing within the month
+    pub printing_secs: u32,
+    /// Smart plug energy of the jobs that had a plug reading
+    pub energy_kwh: Option<f64>,
+}
+
+/// Utilization, failures, filament and energy of every printer over one month, from the
+/// saved job history
+#[derive(Debug, Clone, PartialEq)]
+pub struct MonthlyReport {
+    /// First day of the month
+    pub month: NaiveDate,
+    /// Printers with jobs that ended in the month, by name
+    pub printers: Vec<PrinterMonth>,
+    /// Failed jobs of the month with their printer, in the order they ended
+    pub failures: Vec<(String, JobRecord)>,
+    /// Length of the month
+    month_secs: u32,
+}
+
+impl MonthlyReport {
+    /// Local midnight that begins `month`; jobs that ended before it are not in its report
+    pub fn start(month: NaiveDate) -> DateTime<Local> {
+        local_midnight(month)
+    }
+
+    /// Report over the jobs in `entries` that ended in `month`. Jobs that started in the
+    /// previous month only count their time within this one.
+    pub fn compile(month: NaiveDate, entries: &[(String, JobRecord)]) -> Self {
+        let start = local_midnight(month);
+        let end = local_midnight(next_month(month));
+        let in_month: Vec<&(String, JobRecord)> = entries
+            .iter()
+            .filter(|(_, job)| job.ended_at >= start && job.ended_at < end)
+            .collect();
+
+        let mut names: Vec<&str> = in_month.iter().map(|(name, _)| name.as_str()).collect();
+        names.sort_unstable();
+        names.dedup();
+        let printers = names
+            .into_iter()
+            .map(|printer| {
+                let jobs: Vec<&JobRecord> = in_month
+                    .iter()
+                    .filter(|(name, _)| name == printer)
+                    .map(|(_, job)| job)
+                    .collect();
+                let printing_secs = jobs
+                    .iter()
+                    .map(|job| {
+                        (job.ended_at - job.started_at.max(start))
+                            .num_seconds()
+                            .max(0)
+                    })
+                    .sum::<i64>();
+                let energy: Vec<f64> = jobs.iter().filter_map(|job| job.energy_kwh()).collect();
+                PrinterMonth {
+                    printer: printer.to_string(),
+                    stats: JobStats::compute(jobs.iter().copied()),
+                    printing_secs: printing_secs as u32,
+                    energy_kwh: (!energy.is_empty()).then(|| energy.iter().sum()),
+                }
+            })
+            .collect();
+
+        let mut failures: Vec<(String, JobRecord)> = in_month
+            .into_iter()
+            .filter(|(_, job)| job.final_state == PrintState::Failed)
+            .cloned()
+            .collect();
+        failures.sort_by_key(|(_, job)| job.ended_at);
+
+        Self {
+            month,
+            printers,
+            failures,
+            month_secs: (end - start).num_seconds().max(0) as u32,
+        }
+    }
+
+    /// Share of the month the printer spent printing, in percent
+    pub fn utilization(&self, printer: &PrinterMonth) -> f64 {
+        if self.month_secs == 0 {
+            return 0.0;
+        }
+        f64::from(printer.printing_secs) * 100.0 / f64::from(self.month_secs)
+    }
+
+    /// Month as `July 2024`
+    pub fn title(&self) -> String {
+        self.month.format("%B %Y").to_string()
+    }
+
+    /// Header and one row per printer, then the totals when there are several printers
+    fn table(&self) -> Vec<[String; 9]> {
+        let mut rows = vec![
+            [
+                "Printer",
+                "Jobs",
+                "Finished",
+                "Failed",
+                "Success rate",
+                "Printing time",
+                "Utilization",
+                "Filament",
+                "Energy",
+            ]
+            .map(str::to_string),
+        ];
+        for printer in &self.printers {
+            rows.push(self.table_row(printer));
+        }
+        if self.printers.len() > 1 {
+            let mut total = PrinterMonth {
+                printer: "All printers".to_string(),
+                stats: JobStats::default(),
+                printing_secs: 0,
+                energy_kwh: None,
+            };
+            for printer in &self.printers {
+                total.stats.jobs += printer.stats.jobs;
+                total.stats.finished += printer.stats.finished;
+                total.stats.failed += printer.stats.failed;
+                total.stats.filament_g += printer.stats.filament_g;
+                total.printing_secs += printer.printing_secs;
+                if let Some(energy) = printer.energy_kwh {
+                    total.energy_kwh = Some(total.energy_kwh.unwrap_or(0.0) + energy);
+                }
+            }
+            let mut row = self.table_row(&total);
+            // Shared by all printers, so the farm's utilization is the average
+            row[6] = format!(
+                "{:.1}%",
+                self.utilization(&total) / self.printers.len() as f64
+            );
+            rows.push(row);
+        }
+        rows
+    }
+
+    fn table_row(&self, printer: &PrinterMonth) -> [String; 9] {
+        [
+            printer.printer.clone(),
+            printer.stats.jobs.to_string(),
+            printer.stats.finished.to_string(),
+            printer.stats.failed.to_string(),
+            printer
+                .stats
+                .success_rate()
+                .map(|rate| format!("{rate:.1}%"))
+                .unwrap_or_else(|| "-".to_string()),
+            format_duration_short(printer.printing_secs),
+            format!("{:.1}%", self.utilization(printer)),
+            format!("{:.1} g", printer.stats.filament_g),
+            printer
+                .energy_kwh
+                .map(|energy| format!("{energy:.2} kWh"))
+                .unwrap_or_else(|| "-".to_string()),
+        ]
+    }
+
+    fn failure_row(printer: &str, job: &JobRecord) -> [String; 4] {
+        [
+            job.ended_at.format(TIME_FORMAT).to_string(),
+            printer.to_string(),
+            job_title(job).to_string(),
+            job.fail_reason
+                .clone()
+                .unwrap_or_else(|| "no reason reported".to_string()),
+        ]
+    }
+
+    pub fn render_markdown(&self) -> String {
+        let mut out = format!("# Monthly report: {}\n\n", self.title());
+        if self.printers.is_empty() {
+            out.push_str("No jobs ended this month.\n");
+            return out;
+        }
+        let cell = |value: &String| value.replace('|', "\\|");
+        for (index, row) in self.table().iter().enumerate() {
+            let cells: Vec<String> = row.iter().map(cell).collect();
+            out.push_str(&format!("| {} |\n", cells.join(" | ")));
+            if index == 0 {
+                out.push_str(&format!("|{}\n", "---|".repeat(row.len())));
+            }
+        }
+
+        out.push_str("\n## Failures\n\n");
+        if self.failures.is_empty() {
+            out.push_str("None\n");
+        } else {
+            out.push_str("| Ended | Printer | Job | Reason |\n|---|---|---|---|\n");
+            for (printer, job) in &self.failures {
+                let cells: Vec<String> = Self::failure_row(printer, job).iter().map(cell).collect();
+                out.push_str(&format!("| {} |\n", cells.join(" | ")));
+            }
+        }
+        out
+    }
+
+    pub fn render_html(&self) -> String {
+        let title = escape_html(&self.title());
+        let mut out = format!(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Monthly report: {title}</title>\n</head>\n<body>\n<h1>Monthly report: {title}</h1>\n"
+        );
+        if self.printers.is_empty() {
+            out.push_str("<p>No jobs ended this month.</p>\n</body>\n</html>\n");
+            return out;
+        }
+        out.push_str("<table>\n");
+        for (index, row) in self.table().iter().enumerate() {
+            let tag = if index == 0 { "th" } else { "td" };
+            out.push_str("<tr>");
+            for value in row {
+                out.push_str(&format!("<{tag}>{}</{tag}>", escape_html(value)));
+            }
+            out.push_str("</tr>\n");
+        }
+        out.push_str("</table>\n<h2>Failures</h2>\n");
+        if self.failures.is_empty() {
+            out.push_str("<p>None</p>\n");
+        } else {
+            out.push_str(
+                "<table>\n<tr><th>Ended</th><th>Printer</th><th>Job</th><th>Reason</th></tr>\n",
+            );
+            for (printer, job) in &self.failures {
+                out.push_str("<tr>");
+                for value in Self::failure_row(printer, job) {
+                    out.push_str(&format!("<td>{}</td>", escape_html(&value)));
+                }
+                out.push_str("</tr>\n");
+            }
+            out.push_str("</table>\n");
+        }
+        out.push_str("</body>\n</html>\n");
+        out
+    }
+
+    /// The report as `format`
+    pub fn render(&self, format: ReportFormat) -> String {
+        match format {
+            ReportFormat::Markdown => self.render_markdown(),
+            ReportFormat::Html => self.render_html(),
+        }
+    }
+}
+
+fn next_month(month: NaiveDate) -> NaiveDate {
+    month
+        .checked_add_months(chrono::Months::new(1))
+        .unwrap_or(NaiveDate::MAX)
+}
+
+fn local_midnight(day: NaiveDate) -> DateTime<Local> {
+    let midnight = day.and_hms_opt(0, 0, 0).unwrap_or_default();
+    Local
+        .from_local_datetime(&midnight)
+        .earliest()
+        .unwrap_or_else(|| Local.from_utc_datetime(&midnight))
+}
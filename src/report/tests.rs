@@ -0,0 +1,217 @@
+use super::*;
+use crate::jobs::JobTracker;
+use crate::messages::{DeviceMessage, MergedState};
+use chrono::{Duration, Local, TimeZone};
+use tempfile::tempdir;
+
+fn finished_job(name: &str) -> JobRecord {
+    let start = Local.with_ymd_and_hms(2025, 8, 1, 9, 0, 0).unwrap();
+    let mut tracker = JobTracker::default();
+    let mut state = MergedState::default();
+
+    for (minutes, payload) in [
+        (
+            0,
+            format!(
+                r#"{{"print": {{"command": "push_status", "gcode_state": "RUNNING", "subtask_name": "{name}", "percent": 0, "nozzle_temper": 220.0, "bed_temper": 60.0, "total_layer_num": 120}}}}"#
+            ),
+        ),
+        (
+            90,
+            r#"{"print": {"command": "push_status", "gcode_state": "FAILED", "fail_reason": "spaghetti", "percent": 40, "layer_num": 48, "hms": [{"attr": 50331904, "code": 65543}]}}"#
+                .to_string(),
+        ),
+    ] {
+        state.update(&DeviceMessage::parse(&payload).unwrap());
+        if let Some(job) = tracker.observe(&state, start + Duration::minutes(minutes)) {
+            return job;
+        }
+    }
+    panic!("job did not complete");
+}
+
+#[test]
+fn test_render_markdown() {
+    let details = [
+        ("Location".to_string(), "rack-B/slot-3".to_string()),
+        ("owner".to_string(), "lab".to_string()),
+    ];
+    let report = render_markdown("x1c", &details, &finished_job("benchy"));
+
+    assert!(report.starts_with("# Print report: benchy\n"));
+    assert!(report.contains("| Printer | x1c |\n| Location | rack-B/slot-3 |\n| owner | lab |\n"));
+    assert!(report.contains("| Result | Failed |"));
+    assert!(report.contains("| Duration | 1h 30m |"));
+    assert!(report.contains("| Layers | 48/120 |"));
+    assert!(report.contains("| Nozzle | peak 220.0°C, average 220.0°C |"));
+    assert!(report.contains("| Failure | spaghetti |"));
+    assert!(report.contains("- `HMS_0300_0100_0001_0007`"));
+}
+
+#[test]
+fn test_render_markdown_includes_cost() {
+    let mut job = finished_job("benchy");
+    job.cost = Some(crate::jobs::JobCost {
+        filament: None,
+        electricity: Some(0.054),
+        currency: Some("EUR".to_string()),
+    });
+
+    let report = render_markdown("x1c", &[], &job);
+    assert!(report.contains("| Estimated cost | 0.05 EUR (electricity 0.05 EUR) |"));
+}
+
+#[test]
+fn test_render_markdown_includes_slicer_metadata() {
+    let mut job = finished_job("benchy");
+    job.plate = Some(crate::project::PlateInfo {
+        index: 1,
+        estimated_secs: Some(3723),
+        weight_g: Some(12.3),
+        filaments: vec![crate::project::FilamentInfo {
+            id: 1,
+            material: Some("PLA".to_string()),
+            color: None,
+            used_m: Some(4.12),
+            used_g: Some(12.3),
+        }],
+        objects: Vec::new(),
+        nozzle_diameter: Some(0.4),
+        thumbnail: None,
+    });
+
+    let report = render_markdown("x1c", &[], &job);
+    assert!(report.contains("| Slicer estimate | 1h 2m |"));
+    assert!(report.contains("| Sliced filament | 12.3 g PLA |"));
+}
+
+#[test]
+fn test_render_html_escapes_job_name() {
+    let report = render_html("x1c", &[], &finished_job("<b>&co"));
+
+    assert!(report.contains("<h1>Print report: &lt;b&gt;&amp;co</h1>"));
+    assert!(report.contains("<tr><th>Result</th><td>Failed</td></tr>"));
+    assert!(report.contains("<li><code>HMS_0300_0100_0001_0007</code></li>"));
+}
+
+#[test]
+fn test_write_report_uses_format_and_directory() {
+    let temp_dir = tempdir().unwrap();
+    let settings = ReportSettings {
+        enabled: true,
+        format: ReportFormat::Html,
+        directory: Some(temp_dir.path().join("reports")),
+    };
+
+    let path = write_report(
+        &settings,
+        Path::new("/unused"),
+        "farm 1",
+        &[],
+        &finished_job("cube/v2"),
+    )
+    .unwrap();
+
+    assert_eq!(
+        path.file_name().unwrap().to_str().unwrap(),
+        "20250801-090000-farm_1-cube_v2.html"
+    );
+    assert!(
+        fs::read_to_string(&path)
+            .unwrap()
+            .contains("<!DOCTYPE html>")
+    );
+}
+
+/// The fixture job moved to end at `ended_at` after `minutes`, finished unless `failed`
+fn job_ending(ended_at: DateTime<Local>, minutes: i64, failed: bool) -> JobRecord {
+    let mut job = finished_job("benchy");
+    job.started_at = ended_at - Duration::minutes(minutes);
+    job.ended_at = ended_at;
+    if !failed {
+        job.final_state = PrintState::Finished;
+        job.fail_reason = None;
+    }
+    job
+}
+
+#[test]
+fn test_parse_month() {
+    assert_eq!(
+        parse_month("2024-07").unwrap(),
+        NaiveDate::from_ymd_opt(2024, 7, 1).unwrap()
+    );
+    assert!(parse_month("2024-13").is_err());
+    assert!(parse_month("July").is_err());
+}
+
+#[test]
+fn test_monthly_report_compiles_per_printer() {
+    let month = parse_month("2025-08").unwrap();
+    let day = |day, hour| Local.with_ymd_and_hms(2025, 8, day, hour, 0, 0).unwrap();
+    let mut metered = job_ending(day(3, 12), 60, false);
+    metered.energy_start_kwh = Some(10.0);
+    metered.energy_end_kwh = Some(10.25);
+    let entries = vec![
+        // Started in July, only its August hours count
+        ("x1c".to_string(), job_ending(day(1, 2), 240, false)),
+        ("x1c".to_string(), metered),
+        ("p1s".to_string(), job_ending(day(20, 9), 90, true)),
+        // Other months are left out
+        (
+            "p1s".to_string(),
+            job_ending(
+                Local.with_ymd_and_hms(2025, 9, 1, 1, 0, 0).unwrap(),
+                60,
+                false,
+            ),
+        ),
+    ];
+
+    let report = MonthlyReport::compile(month, &entries);
+    assert_eq!(report.title(), "August 2025");
+    let names: Vec<&str> = report.printers.iter().map(|p| p.printer.as_str()).collect();
+    assert_eq!(names, ["p1s", "x1c"]);
+
+    let x1c = &report.printers[1];
+    assert_eq!(x1c.stats.jobs, 2);
+    assert_eq!(x1c.stats.finished, 2);
+    assert_eq!(x1c.printing_secs, 3 * 3600);
+    assert_eq!(x1c.energy_kwh, Some(0.25));
+    let hours_in_august = 31.0 * 24.0;
+    assert!((report.utilization(x1c) - 3.0 * 100.0 / hours_in_august).abs() < 0.01);
+
+    let p1s = &report.printers[0];
+    assert_eq!(p1s.stats.failed, 1);
+    assert_eq!(p1s.energy_kwh, None);
+    assert_eq!(report.failures.len(), 1);
+    assert_eq!(report.failures[0].0, "p1s");
+}
+
+#[test]
+fn test_render_monthly_report() {
+    let month = parse_month("2025-08").unwrap();
+    let ended = Local.with_ymd_and_hms(2025, 8, 20, 9, 0, 0).unwrap();
+    let entries = vec![
+        ("x1c".to_string(), job_ending(ended, 60, false)),
+        ("p1s".to_string(), job_ending(ended, 90, true)),
+    ];
+    let report = MonthlyReport::compile(month, &entries);
+
+    let markdown = report.render(ReportFormat::Markdown);
+    assert!(markdown.starts_with("# Monthly report: August 2025\n"));
+    assert!(markdown.contains("| x1c | 1 | 1 | 0 | 100.0% | 1h 0m |"));
+    assert!(markdown.contains("| All printers | 2 | 1 | 1 | 50.0% | 2h 30m |"));
+    assert!(markdown.contains("| 2025-08-20 09:00:00 | p1s | benchy | spaghetti |"));
+
+    let html = report.render(ReportFormat::Html);
+    assert!(html.contains("<h1>Monthly report: August 2025</h1>"));
+    assert!(html.contains("<td>spaghetti</td>"));
+
+    let empty = MonthlyReport::compile(parse_month("2025-07").unwrap(), &entries);
+    assert!(
+        empty
+            .render_markdown()
+            .contains("No jobs ended this month.")
+    );
+}
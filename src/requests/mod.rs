@@ -0,0 +1,247 @@
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use thiserror::Error;
+use tokio::sync::oneshot;
+
+#[cfg(test)]
+mod tests;
+
+/// How long a command waits for the printer's answer unless told otherwise
+pub const RESPONSE_TIMEOUT: Duration = Duration::from_secs(15);
+
+#[derive(Debug, Error)]
+pub enum RequestError {
+    #[error("{0}")]
+    Invalid(String),
+
+    #[error("Cannot send the request: {0}")]
+    Send(String),
+
+    #[error(
+        "The printer did not answer '{command}' (sequence ID {sequence_id}) within {timeout:?}"
+    )]
+    Timeout {
+        command: String,
+        sequence_id: String,
+        timeout: Duration,
+    },
+
+    #[error("The printer rejected '{command}': {reason}")]
+    Rejected { command: String, reason: String },
+}
+
+/// Command group and command of a raw request such as `{"print": {"command": "pause"}}`,
+/// checking that the payload is one object keyed by the group
+pub fn request_command(payload: &Value) -> Result<(&str, &str), RequestError> {
+    let (group, body) = payload
+        .as_object()
+        .filter(|object| object.len() == 1)
+        .and_then(|object| object.iter().next())
+        .ok_or_else(|| {
+            RequestError::Invalid(
+                r#"The payload must be one object keyed by the command group, e.g. {"print": {...}}"#
+                    .to_string(),
+            )
+        })?;
+    let command = body.get("command").and_then(Value::as_str).ok_or_else(|| {
+        RequestError::Invalid(format!(r#"The "{group}" request has no "command""#))
+    })?;
+    Ok((group, command))
+}
+
+/// Assigns sequence IDs to outgoing commands and hands each the report that answers it.
+/// Whoever reads the report stream feeds it to [`resolve`](RequestManager::resolve); clones
+/// share the same pending requests.
+#[derive(Debug, Clone)]
+pub struct RequestManager {
+    next_id: Arc<AtomicU64>,
+    pending: Arc<Mutex<HashMap<String, Pending>>>,
+}
+
+#[derive(Debug)]
+struct Pending {
+    group: String,
+    command: String,
+    reply: oneshot::Sender<Value>,
+}
+
+impl Default for RequestManager {
+    fn default() -> Self {
+        // Starting from the clock keeps IDs apart from those of an earlier run, whose late
+        // answers could otherwise be taken for this one's
+        let millis = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|since| since.as_millis() as u64)
+            .unwrap_or_default();
+        Self::starting_at(millis % 1_000_000_000)
+    }
+}
+
+impl RequestManager {
+    /// Manager handing out sequence IDs counting up from `first`
+    pub fn starting_at(first: u64) -> Self {
+        Self {
+            next_id: Arc::new(AtomicU64::new(first)),
+            pending: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Sets a fresh sequence ID on the request, replacing any it has, and registers it so
+    /// the answer can be awaited once the request is sent
+    pub fn register(&self, payload: &mut Value) -> Result<PendingResponse, RequestError> {
+        let (group, command) = request_command(payload)?;
+        let (group, command) = (group.to_string(), command.to_string());
+        let sequence_id = self.next_id.fetch_add(1, Ordering::Relaxed).to_string();
+        payload[&group]["sequence_id"] = Value::String(sequence_id.clone());
+
+        let (reply, receiver) = oneshot::channel();
+        self.pending.lock().unwrap().insert(
+            sequence_id.clone(),
+            Pending {
+                group: group.clone(),
+                command: command.clone(),
+                reply,
+            },
+        );
+        Ok(PendingResponse {
+            group,
+            command,
+            sequence_id,
+            receiver,
+            manager: self.clone(),
+        })
+    }
+
+    /// Hands a report from the printer to the request it answers: the one with the same
+    /// group, command and sequence ID. Returns whether the report answered a request.
+    pub fn resolve(&self, payload: &[u8]) -> bool {
+        if self.pending.lock().unwrap().is_empty() {
+            return false;
+        }
+        let Ok(Value::Object(report)) = serde_json::from_slice::<Value>(payload) else {
+            return false;
+        };
+
+        let mut pending = self.pending.lock().unwrap();
+        for (group, body) in &report {
+            // Some firmware sends the ID back as a number
+            let sequence_id = match body.get("sequence_id") {
+                Some(Value::String(id)) => id.clone(),
+                Some(Value::Number(id)) => id.to_string(),
+                _ => continue,
+            };
+            let answers = pending.get(&sequence_id).is_some_and(|request| {
+                request.group == *group
+                    && body.get("command").and_then(Value::as_str) == Some(&request.command)
+            });
+            if answers && let Some(request) = pending.remove(&sequence_id) {
+                // The requester may have stopped waiting already
+                let _ = request.reply.send(Value::Object(report.clone()));
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Requests still waiting for an answer
+    pub fn pending(&self) -> usize {
+        self.pending.lock().unwrap().len()
+    }
+
+    fn forget(&self, sequence_id: &str) {
+        self.pending.lock().unwrap().remove(sequence_id);
+    }
+}
+
+/// A registered request whose answer has not been seen yet. Dropping it stops waiting.
+#[derive(Debug)]
+pub struct PendingResponse {
+    group: String,
+    command: String,
+    sequence_id: String,
+    receiver: oneshot::Receiver<Value>,
+    manager: RequestManager,
+}
+
+impl PendingResponse {
+    pub fn sequence_id(&self) -> &str {
+        &self.sequence_id
+    }
+
+    /// Waits up to `timeout` for the report answering the request
+    pub async fn wait(mut self, timeout: Duration) -> Result<CommandResponse, RequestError> {
+        match tokio::time::timeout(timeout, &mut self.receiver).await {
+            Ok(Ok(report)) => Ok(CommandResponse {
+                group: self.group.clone(),
+                command: self.command.clone(),
+                report,
+            }),
+            // The sender only goes away once the request is forgotten, which is after this
+            _ => Err(RequestError::Timeout {
+                command: self.command.clone(),
+                sequence_id: self.sequence_id.clone(),
+                timeout,
+            }),
+        }
+    }
+}
+
+impl Drop for PendingResponse {
+    fn drop(&mut self) {
+        self.manager.forget(&self.sequence_id);
+    }
+}
+
+/// The printer's answer to a command
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommandResponse {
+    pub group: String,
+    pub command: String,
+    /// The whole report payload
+    pub report: Value,
+}
+
+impl CommandResponse {
+    /// The answer's fields, e.g. `result` and `reason`
+    pub fn body(&self) -> &Value {
+        &self.report[&self.group]
+    }
+
+    /// Why the printer refused the command, if its `result` says so. Answers without a
+    /// `result`, such as `get_version`, count as success.
+    pub fn failure(&self) -> Option<String> {
+        let body = self.body();
+        let result = body.get("result").and_then(Value::as_str)?;
+        if !matches!(
+            result.to_ascii_lowercase().as_str(),
+            "fail" | "failed" | "failure" | "error"
+        ) {
+            return None;
+        }
+        let reason = body
+            .get("reason")
+            .and_then(Value::as_str)
+            .filter(|reason| !reason.is_empty())
+            .map(str::to_string)
+            .or_else(|| {
+                body.get("err_code")
+                    .filter(|code| !code.is_null())
+                    .map(|code| format!("error code {code}"))
+            });
+        Some(reason.unwrap_or_else(|| "no reason given".to_string()))
+    }
+
+    /// The answer, or [`RequestError::Rejected`] when the printer refused the command
+    pub fn into_result(self) -> Result<Self, RequestError> {
+        match self.failure() {
+            Some(reason) => Err(RequestError::Rejected {
+                command: self.command,
+                reason,
+            }),
+            None => Ok(self),
+        }
+    }
+}
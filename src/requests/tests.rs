@@ -0,0 +1,105 @@
+use super::*;
+use serde_json::json;
+
+fn answer(report: Value) -> Vec<u8> {
+    report.to_string().into_bytes()
+}
+
+#[test]
+fn test_request_command() {
+    let payload = json!({"print": {"command": "gcode_line", "param": "G28"}});
+    assert_eq!(request_command(&payload).unwrap(), ("print", "gcode_line"));
+
+    assert!(request_command(&json!([1, 2])).is_err());
+    assert!(request_command(&json!({"print": {}, "info": {}})).is_err());
+    assert_eq!(
+        request_command(&json!({"print": {"param": ""}}))
+            .unwrap_err()
+            .to_string(),
+        r#"The "print" request has no "command""#
+    );
+}
+
+#[tokio::test]
+async fn test_answer_is_matched_by_sequence_id() {
+    let manager = RequestManager::starting_at(100);
+    let mut pause = json!({"print": {"sequence_id": "0", "command": "pause", "param": ""}});
+    let pending = manager.register(&mut pause).unwrap();
+    assert_eq!(pending.sequence_id(), "100");
+    assert_eq!(pause["print"]["sequence_id"], "100");
+
+    let mut version = json!({"info": {"command": "get_version"}});
+    let other = manager.register(&mut version).unwrap();
+    assert_eq!(other.sequence_id(), "101");
+    assert_eq!(manager.pending(), 2);
+
+    // Status pushes and answers to other requests are not taken for this one's
+    assert!(!manager.resolve(&answer(
+        json!({"print": {"command": "push_status", "sequence_id": "100"}})
+    )));
+    assert!(!manager.resolve(&answer(
+        json!({"print": {"command": "pause", "sequence_id": "99", "result": "success"}})
+    )));
+    assert!(!manager.resolve(b"not json"));
+    assert!(manager.resolve(&answer(
+        json!({"print": {"command": "pause", "sequence_id": "100", "result": "success"}})
+    )));
+    assert_eq!(manager.pending(), 1);
+
+    let response = pending.wait(Duration::from_secs(1)).await.unwrap();
+    assert_eq!(response.command, "pause");
+    assert_eq!(response.body()["result"], "success");
+    assert_eq!(response.failure(), None);
+
+    // Some firmware answers with a numeric ID
+    assert!(manager.resolve(&answer(
+        json!({"info": {"command": "get_version", "sequence_id": 101, "module": []}})
+    )));
+    assert!(other.wait(Duration::from_secs(1)).await.is_ok());
+    assert_eq!(manager.pending(), 0);
+}
+
+#[tokio::test]
+async fn test_unanswered_request_times_out() {
+    let manager = RequestManager::starting_at(7);
+    let mut stop = json!({"print": {"command": "stop"}});
+    let pending = manager.register(&mut stop).unwrap();
+
+    let error = pending.wait(Duration::from_millis(10)).await.unwrap_err();
+    assert!(matches!(
+        &error,
+        RequestError::Timeout { command, sequence_id, .. } if command == "stop" && sequence_id == "7"
+    ));
+    assert_eq!(manager.pending(), 0);
+
+    // Giving up on a request forgets it as well
+    let mut resume = json!({"print": {"command": "resume"}});
+    drop(manager.register(&mut resume).unwrap());
+    assert_eq!(manager.pending(), 0);
+}
+
+#[test]
+fn test_command_failure() {
+    let response = |body: Value| CommandResponse {
+        group: "print".to_string(),
+        command: "resume".to_string(),
+        report: json!({ "print": body }),
+    };
+
+    assert!(response(json!({"result": "SUCCESS"})).into_result().is_ok());
+    assert_eq!(
+        response(json!({"result": "fail", "reason": "not paused"})).failure(),
+        Some("not paused".to_string())
+    );
+    assert_eq!(
+        response(json!({"result": "failed", "reason": "", "err_code": 84033543})).failure(),
+        Some("error code 84033543".to_string())
+    );
+    let error = response(json!({"result": "fail"}))
+        .into_result()
+        .unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "The printer rejected 'resume': no reason given"
+    );
+}
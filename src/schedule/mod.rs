@@ -0,0 +1,93 @@
+use crate::jobs;
+use crate::messages::{MergedState, PrintState};
+use crate::mqtt::ProjectFile;
+use chrono::{DateTime, Duration, Local, NaiveDateTime, NaiveTime, TimeZone};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+#[cfg(test)]
+mod tests;
+
+#[derive(Debug, Error, PartialEq)]
+pub enum ScheduleError {
+    #[error("Invalid start time '{0}'; use HH:MM or YYYY-MM-DD HH:MM")]
+    InvalidTime(String),
+
+    #[error("Start time {0} has already passed")]
+    InPast(String),
+}
+
+/// Reason a scheduled print was not started when its time came
+#[derive(Debug, Error, PartialEq)]
+pub enum PreflightError {
+    #[error("printer is not connected")]
+    NotConnected,
+
+    #[error("printer has not reported its status")]
+    NoStatus,
+
+    #[error("printer is not idle ({0})")]
+    Busy(String),
+
+    #[error("printer reports HMS errors: {}", .0.join(", "))]
+    HmsErrors(Vec<String>),
+}
+
+/// Print the daemon starts on `printer` at `at`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScheduledPrint {
+    pub printer: String,
+    pub at: DateTime<Local>,
+    pub job: ProjectFile,
+}
+
+/// Parses `06:30` as the next time the clock shows 06:30, or `2025-08-01 06:30` as that moment
+pub fn parse_start_time(
+    input: &str,
+    now: DateTime<Local>,
+) -> Result<DateTime<Local>, ScheduleError> {
+    let input = input.trim();
+    let invalid = || ScheduleError::InvalidTime(input.to_string());
+
+    if let Ok(time) = NaiveTime::parse_from_str(input, "%H:%M") {
+        let today = now.date_naive().and_time(time);
+        let start = local(today).ok_or_else(invalid)?;
+        if start > now {
+            return Ok(start);
+        }
+        return local(today + Duration::days(1)).ok_or_else(invalid);
+    }
+
+    let moment = NaiveDateTime::parse_from_str(input, "%Y-%m-%d %H:%M")
+        .or_else(|_| NaiveDateTime::parse_from_str(input, "%Y-%m-%dT%H:%M"))
+        .map_err(|_| invalid())?;
+    let start = local(moment).ok_or_else(invalid)?;
+    if start <= now {
+        return Err(ScheduleError::InPast(input.to_string()));
+    }
+    Ok(start)
+}
+
+/// Resolves a wall-clock time; times skipped by a DST change have no local equivalent
+fn local(moment: NaiveDateTime) -> Option<DateTime<Local>> {
+    Local.from_local_datetime(&moment).earliest()
+}
+
+/// Checks that the printer can take a new job: idle or done with the last one, and free of
+/// HMS errors
+pub fn preflight(state: &MergedState) -> Result<(), PreflightError> {
+    if state.message_count == 0 {
+        return Err(PreflightError::NoStatus);
+    }
+
+    match state.status().state {
+        PrintState::Idle | PrintState::Finished => {}
+        other => return Err(PreflightError::Busy(format!("{other:?}"))),
+    }
+
+    let hms = jobs::hms_codes(state.print.extra.get("hms"));
+    if !hms.is_empty() {
+        return Err(PreflightError::HmsErrors(hms));
+    }
+    Ok(())
+}
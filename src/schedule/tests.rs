@@ -0,0 +1,91 @@
+use super::*;
+use crate::messages::DeviceMessage;
+
+fn at(date: &str) -> DateTime<Local> {
+    local(NaiveDateTime::parse_from_str(date, "%Y-%m-%d %H:%M").unwrap()).unwrap()
+}
+
+fn state(payload: &str) -> MergedState {
+    let mut state = MergedState::default();
+    state.update(&DeviceMessage::parse(payload).unwrap());
+    state
+}
+
+#[test]
+fn test_parse_clock_time_picks_next_occurrence() {
+    let now = at("2025-08-01 05:00");
+    assert_eq!(
+        parse_start_time("06:30", now).unwrap(),
+        at("2025-08-01 06:30")
+    );
+    // Already past today, so tomorrow
+    assert_eq!(
+        parse_start_time("04:15", now).unwrap(),
+        at("2025-08-02 04:15")
+    );
+    assert_eq!(
+        parse_start_time("05:00", now).unwrap(),
+        at("2025-08-02 05:00")
+    );
+}
+
+#[test]
+fn test_parse_full_date() {
+    let now = at("2025-08-01 05:00");
+    assert_eq!(
+        parse_start_time("2025-08-03 22:00", now).unwrap(),
+        at("2025-08-03 22:00")
+    );
+    assert_eq!(
+        parse_start_time("2025-08-03T22:00", now).unwrap(),
+        at("2025-08-03 22:00")
+    );
+    assert_eq!(
+        parse_start_time("2025-07-31 22:00", now),
+        Err(ScheduleError::InPast("2025-07-31 22:00".to_string()))
+    );
+    assert!(matches!(
+        parse_start_time("6.30pm", now),
+        Err(ScheduleError::InvalidTime(_))
+    ));
+    assert!(matches!(
+        parse_start_time("25:00", now),
+        Err(ScheduleError::InvalidTime(_))
+    ));
+}
+
+#[test]
+fn test_preflight_accepts_idle_printer() {
+    assert_eq!(
+        preflight(&state(r#"{"print": {"gcode_state": "IDLE", "hms": []}}"#)),
+        Ok(())
+    );
+    assert_eq!(
+        preflight(&state(r#"{"print": {"gcode_state": "FINISH"}}"#)),
+        Ok(())
+    );
+}
+
+#[test]
+fn test_preflight_rejects_busy_or_faulty_printer() {
+    assert_eq!(
+        preflight(&MergedState::default()),
+        Err(PreflightError::NoStatus)
+    );
+    assert!(matches!(
+        preflight(&state(r#"{"print": {"gcode_state": "RUNNING"}}"#)),
+        Err(PreflightError::Busy(_))
+    ));
+    assert!(matches!(
+        preflight(&state(r#"{"print": {"gcode_state": "FAILED"}}"#)),
+        Err(PreflightError::Busy(_))
+    ));
+    assert_eq!(
+        preflight(&state(
+            r#"{"print": {"gcode_state": "IDLE", "hms": [{"attr": 50331904, "code": 65543}]}}"#
+        )),
+        Err(PreflightError::HmsErrors(vec![
+            "0300_0100_0001_0007".to_string()
+        ]))
+    );
+}
@@ -0,0 +1,132 @@
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+#[cfg(test)]
+mod tests;
+
+pub const UNIT_NAME: &str = "pulseprint-cli.service";
+
+/// Sends a state line such as `READY=1` to systemd
+///
+/// Returns `Ok(false)` when not started by systemd with `Type=notify` (no `NOTIFY_SOCKET`).
+pub fn notify(state: &str) -> std::io::Result<bool> {
+    let Some(socket) = std::env::var_os("NOTIFY_SOCKET") else {
+        return Ok(false);
+    };
+    send_notification(Path::new(&socket), state)?;
+    Ok(true)
+}
+
+#[cfg(unix)]
+fn send_notification(socket: &Path, state: &str) -> std::io::Result<()> {
+    use std::os::unix::ffi::OsStrExt;
+    use std::os::unix::net::UnixDatagram;
+
+    let datagram = UnixDatagram::unbound()?;
+    let name = socket.as_os_str().as_bytes();
+
+    // A leading '@' names a socket in the Linux abstract namespace
+    if let Some(abstract_name) = name.strip_prefix(b"@") {
+        #[cfg(target_os = "linux")]
+        {
+            use std::os::linux::net::SocketAddrExt;
+            let address = std::os::unix::net::SocketAddr::from_abstract_name(abstract_name)?;
+            datagram.send_to_addr(state.as_bytes(), &address)?;
+            return Ok(());
+        }
+        #[cfg(not(target_os = "linux"))]
+        {
+            let _ = abstract_name;
+            return Err(std::io::ErrorKind::Unsupported.into());
+        }
+    }
+
+    datagram.send_to(state.as_bytes(), socket)?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn send_notification(_socket: &Path, _state: &str) -> std::io::Result<()> {
+    Err(std::io::ErrorKind::Unsupported.into())
+}
+
+/// How often to send `WATCHDOG=1`: half the `WatchdogSec=` systemd passes in `WATCHDOG_USEC`
+pub fn watchdog_interval() -> Option<Duration> {
+    parse_watchdog(
+        std::env::var("WATCHDOG_USEC").ok().as_deref(),
+        std::env::var("WATCHDOG_PID").ok().as_deref(),
+        std::process::id(),
+    )
+}
+
+fn parse_watchdog(usec: Option<&str>, pid: Option<&str>, own_pid: u32) -> Option<Duration> {
+    let usec: u64 = usec?.parse().ok()?;
+    // The watchdog is meant for a specific process when WATCHDOG_PID is set
+    if let Some(pid) = pid
+        && pid.parse() != Ok(own_pid)
+    {
+        return None;
+    }
+    (usec > 0).then(|| Duration::from_micros(usec / 2))
+}
+
+/// Location of the user unit, `~/.config/systemd/user/pulseprint-cli.service`
+pub fn user_unit_path() -> Option<PathBuf> {
+    Some(
+        dirs::config_dir()?
+            .join("systemd")
+            .join("user")
+            .join(UNIT_NAME),
+    )
+}
+
+/// User unit running `daemon start` with the given executable, arguments and environment
+pub fn unit_file(exe: &Path, args: &[&str], environment: &[(&str, String)]) -> String {
+    // ExecStart expands $VARIABLES, so literal dollar signs are doubled
+    let mut exec_start = quote_arg(&exe.display().to_string()).replace('$', "$$");
+    for arg in args {
+        exec_start.push(' ');
+        exec_start.push_str(&quote_arg(arg).replace('$', "$$"));
+    }
+
+    let mut unit = String::from(
+        "[Unit]\n\
+         Description=PulsePrint printer monitoring daemon\n\
+         After=network-online.target\n\
+         Wants=network-online.target\n\
+         \n\
+         [Service]\n\
+         Type=notify\n",
+    );
+    unit.push_str(&format!("ExecStart={exec_start}\n"));
+    for (name, value) in environment {
+        unit.push_str(&format!(
+            "Environment={}\n",
+            quote_arg(&format!("{name}={value}"))
+        ));
+    }
+    unit.push_str(
+        "Restart=on-failure\n\
+         RestartSec=10\n\
+         WatchdogSec=60\n\
+         \n\
+         [Install]\n\
+         WantedBy=default.target\n",
+    );
+    unit
+}
+
+/// Double-quotes values containing whitespace, quotes or backslashes, as systemd expects,
+/// and escapes `%` specifiers
+fn quote_arg(arg: &str) -> String {
+    let arg = arg.replace('%', "%%");
+    if arg.is_empty()
+        || arg
+            .chars()
+            .any(|c| c.is_whitespace() || c == '"' || c == '\\' || c == '\'')
+    {
+        format!("\"{}\"", arg.replace('\\', "\\\\").replace('"', "\\\""))
+    } else {
+        arg.to_string()
+    }
+}
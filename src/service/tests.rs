@@ -0,0 +1,59 @@
+use super::*;
+
+#[test]
+fn test_parse_watchdog() {
+    assert_eq!(
+        parse_watchdog(Some("60000000"), None, 42),
+        Some(Duration::from_secs(30))
+    );
+    assert_eq!(
+        parse_watchdog(Some("60000000"), Some("42"), 42),
+        Some(Duration::from_secs(30))
+    );
+    assert_eq!(parse_watchdog(Some("60000000"), Some("7"), 42), None);
+    assert_eq!(parse_watchdog(Some("0"), None, 42), None);
+    assert_eq!(parse_watchdog(None, None, 42), None);
+}
+
+#[test]
+fn test_unit_file_runs_daemon_as_notify_service() {
+    let unit = unit_file(
+        Path::new("/usr/local/bin/pulseprint-cli"),
+        &["--no-emoji", "daemon", "start"],
+        &[],
+    );
+
+    assert!(unit.contains("\nType=notify\n"));
+    assert!(unit.contains("\nExecStart=/usr/local/bin/pulseprint-cli --no-emoji daemon start\n"));
+    assert!(unit.contains("\nWatchdogSec=60\n"));
+    assert!(unit.contains("\nWantedBy=default.target\n"));
+    assert!(!unit.contains("Environment="));
+}
+
+#[test]
+fn test_unit_file_quotes_and_escapes() {
+    let unit = unit_file(
+        Path::new("/home/me/My Tools/pulseprint-cli"),
+        &["daemon", "start"],
+        &[("PULSEPRINT_TEST_CONFIG_DIR", "/srv/100%/$conf".to_string())],
+    );
+
+    assert!(unit.contains("\nExecStart=\"/home/me/My Tools/pulseprint-cli\" daemon start\n"));
+    assert!(unit.contains("\nEnvironment=PULSEPRINT_TEST_CONFIG_DIR=/srv/100%%/$conf\n"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_send_notification_to_socket() {
+    use std::os::unix::net::UnixDatagram;
+
+    let temp_dir = tempfile::tempdir().unwrap();
+    let path = temp_dir.path().join("notify.sock");
+    let receiver = UnixDatagram::bind(&path).unwrap();
+
+    send_notification(&path, "READY=1").unwrap();
+
+    let mut buffer = [0u8; 64];
+    let len = receiver.recv(&mut buffer).unwrap();
+    assert_eq!(&buffer[..len], b"READY=1");
+}
@@ -0,0 +1,130 @@
+use crate::config::ConfigError;
+use crate::discovery::DiscoveredPrinter;
+use std::io::{self, BufRead, Write};
+
+#[cfg(test)]
+mod tests;
+
+/// Line-based questions for the setup wizard, asked on `output` and answered on `input`
+pub struct Prompter<R, W> {
+    input: R,
+    output: W,
+}
+
+impl<R: BufRead, W: Write> Prompter<R, W> {
+    pub fn new(input: R, output: W) -> Self {
+        Self { input, output }
+    }
+
+    fn read_answer(&mut self, question: &str) -> io::Result<String> {
+        write!(self.output, "{question}")?;
+        self.output.flush()?;
+        let mut line = String::new();
+        if self.input.read_line(&mut line)? == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "setup cancelled",
+            ));
+        }
+        Ok(line.trim().to_string())
+    }
+
+    /// Asks until the answer passes `validate`; an empty answer takes `default`
+    pub fn ask(
+        &mut self,
+        question: &str,
+        default: Option<&str>,
+        validate: impl Fn(&str) -> Result<(), ConfigError>,
+    ) -> io::Result<String> {
+        let question = match default {
+            Some(default) => format!("{question} [{default}]: "),
+            None => format!("{question}: "),
+        };
+        loop {
+            let mut answer = self.read_answer(&question)?;
+            if answer.is_empty()
+                && let Some(default) = default
+            {
+                answer = default.to_string();
+            }
+            match validate(&answer) {
+                Ok(()) => return Ok(answer),
+                Err(e) => writeln!(self.output, "  {e}")?,
+            }
+        }
+    }
+
+    /// Yes/no question; an empty answer takes `default`
+    pub fn confirm(&mut self, question: &str, default: bool) -> io::Result<bool> {
+        let hint = if default { "[Y/n]" } else { "[y/N]" };
+        loop {
+            let answer = self.read_answer(&format!("{question} {hint} "))?;
+            match answer.to_ascii_lowercase().as_str() {
+                "" => return Ok(default),
+                "y" | "yes" => return Ok(true),
+                "n" | "no" => return Ok(false),
+                _ => writeln!(self.output, "  Please answer y or n")?,
+            }
+        }
+    }
+
+    /// Numbered choice between `options`, or `None` for the extra "enter manually" entry 0
+    pub fn choose(&mut self, options: &[String]) -> io::Result<Option<usize>> {
+        for (index, option) in options.iter().enumerate() {
+            writeln!(self.output, "  {}) {option}", index + 1)?;
+        }
+        writeln!(self.output, "  0) Enter the details manually")?;
+        loop {
+            let answer = self.read_answer("Choose a printer [1]: ")?;
+            let choice = if answer.is_empty() {
+                Some(1)
+            } else {
+                answer.parse::<usize>().ok()
+            };
+            match choice {
+                Some(0) => return Ok(None),
+                Some(choice) if choice <= options.len() => return Ok(Some(choice - 1)),
+                _ => writeln!(self.output, "  Enter a number from 0 to {}", options.len())?,
+            }
+        }
+    }
+}
+
+/// One line per discovered printer, e.g. `Workshop P1S (P1S) at 192.168.1.100, serial 01P...`
+pub fn describe(printer: &DiscoveredPrinter) -> String {
+    let mut description = printer
+        .name
+        .clone()
+        .unwrap_or_else(|| "Printer".to_string());
+    if let Some(model) = &printer.model {
+        description.push_str(&format!(" ({model})"));
+    }
+    description.push_str(&format!(" at {}, serial {}", printer.ip, printer.device_id));
+    description
+}
+
+/// Config name for a printer: its announced name or model in lowercase with dashes, made
+/// unique among the names already `taken`
+pub fn suggest_name(name: Option<&str>, model: Option<&str>, taken: &[&str]) -> String {
+    let slug = |text: &str| {
+        text.to_lowercase()
+            .split(|c: char| !c.is_ascii_alphanumeric())
+            .filter(|part| !part.is_empty())
+            .collect::<Vec<_>>()
+            .join("-")
+    };
+    let base = [name, model]
+        .into_iter()
+        .flatten()
+        .map(slug)
+        .find(|slug| !slug.is_empty())
+        .unwrap_or_else(|| "printer".to_string());
+
+    let mut candidate = base.clone();
+    let mut suffix = 2;
+    while taken.contains(&candidate.as_str()) {
+        candidate = format!("{base}-{suffix}");
+        suffix += 1;
+    }
+    candidate
+}
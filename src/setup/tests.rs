@@ -0,0 +1,72 @@
+use super::*;
+use crate::config;
+
+fn prompter(input: &str) -> Prompter<&[u8], Vec<u8>> {
+    Prompter::new(input.as_bytes(), Vec::new())
+}
+
+#[test]
+fn test_ask_retries_until_valid() {
+    let mut prompter = prompter("1234\n12345678\n");
+    let answer = prompter
+        .ask("Access code", None, config::validate_access_code)
+        .unwrap();
+    assert_eq!(answer, "12345678");
+
+    let output = String::from_utf8(prompter.output).unwrap();
+    assert_eq!(
+        output,
+        "Access code:   Access code should be exactly 8 characters\nAccess code: "
+    );
+}
+
+#[test]
+fn test_ask_default_and_eof() {
+    let mut prompter = prompter("\n");
+    assert_eq!(
+        prompter.ask("Name", Some("p1s"), |_| Ok(())).unwrap(),
+        "p1s"
+    );
+    let error = prompter.ask("Name", Some("p1s"), |_| Ok(())).unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+}
+
+#[test]
+fn test_confirm_and_choose() {
+    let mut prompter = prompter("maybe\nY\n\n");
+    assert!(prompter.confirm("Save?", false).unwrap());
+    assert!(!prompter.confirm("Save?", false).unwrap());
+
+    let options = vec!["first".to_string(), "second".to_string()];
+    assert_eq!(prompter_choice("\n", &options), Some(0));
+    assert_eq!(prompter_choice("5\n2\n", &options), Some(1));
+    assert_eq!(prompter_choice("0\n", &options), None);
+}
+
+fn prompter_choice(input: &str, options: &[String]) -> Option<usize> {
+    prompter(input).choose(options).unwrap()
+}
+
+#[test]
+fn test_describe_and_suggest_name() {
+    let printer = DiscoveredPrinter {
+        ip: "192.168.1.100".to_string(),
+        device_id: "01P00A000000000".to_string(),
+        name: Some("Workshop P1S".to_string()),
+        model: Some("P1S".to_string()),
+    };
+    assert_eq!(
+        describe(&printer),
+        "Workshop P1S (P1S) at 192.168.1.100, serial 01P00A000000000"
+    );
+
+    assert_eq!(
+        suggest_name(Some("Workshop P1S"), Some("P1S"), &[]),
+        "workshop-p1s"
+    );
+    assert_eq!(
+        suggest_name(None, Some("A1 mini"), &["a1-mini"]),
+        "a1-mini-2"
+    );
+    assert_eq!(suggest_name(Some("🙂"), None, &["printer"]), "printer-2");
+}
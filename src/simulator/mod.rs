@@ -0,0 +1,191 @@
+use serde_json::{Value, json};
+
+#[cfg(test)]
+mod tests;
+
+/// Parameters of the scripted print job the simulator plays back
+#[derive(Debug, Clone)]
+pub struct PrintJobScript {
+    pub total_layers: u32,
+    pub total_time_secs: u32,
+    pub heating_steps: u32,
+    pub printing_steps: u32,
+}
+
+impl Default for PrintJobScript {
+    fn default() -> Self {
+        Self {
+            total_layers: 120,
+            total_time_secs: 3600,
+            heating_steps: 5,
+            printing_steps: 50,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SimulatorPhase {
+    Announce,
+    Heating,
+    Printing,
+    Finished,
+    Done,
+}
+
+/// Fake Bambu printer producing realistic pushall/push_status payloads for a scripted job
+pub struct PrinterSimulator {
+    /// Serial number announced in the pushall frame, matching the report topic
+    device_id: String,
+    script: PrintJobScript,
+    phase: SimulatorPhase,
+    step: u32,
+    sequence_id: u64,
+}
+
+impl PrinterSimulator {
+    pub fn new(device_id: &str, script: PrintJobScript) -> Self {
+        Self {
+            device_id: device_id.to_string(),
+            script,
+            phase: SimulatorPhase::Announce,
+            step: 0,
+            sequence_id: 0,
+        }
+    }
+
+    pub fn phase(&self) -> SimulatorPhase {
+        self.phase
+    }
+
+    fn next_sequence_id(&mut self) -> String {
+        self.sequence_id += 1;
+        self.sequence_id.to_string()
+    }
+
+    fn heating_frame(&mut self) -> Value {
+        let steps = self.script.heating_steps.max(1);
+        let fraction = f64::from(self.step + 1) / f64::from(steps);
+        let sequence_id = self.next_sequence_id();
+
+        json!({
+            "print": {
+                "command": "push_status",
+                "msg": 0,
+                "state": "printing",
+                "gcode_state": "PREPARE",
+                "percent": 0,
+                "layer_num": 0,
+                "mc_remaining_time": self.script.total_time_secs,
+                "nozzle_temper": 25.0 + (220.0 - 25.0) * fraction,
+                "bed_temper": 25.0 + (55.0 - 25.0) * fraction,
+                "wifi_signal": "-45dBm",
+                "fan_gear": 0,
+                "sequence_id": sequence_id
+            },
+            "sequence_id": sequence_id
+        })
+    }
+
+    fn printing_frame(&mut self) -> Value {
+        let steps = self.script.printing_steps.max(1);
+        let fraction = f64::from(self.step + 1) / f64::from(steps);
+        let percent = (fraction * 100.0).round() as u32;
+        let layer = (fraction * f64::from(self.script.total_layers)).round() as u32;
+        let remaining = (f64::from(self.script.total_time_secs) * (1.0 - fraction)).round() as u32;
+        let sequence_id = self.next_sequence_id();
+
+        json!({
+            "print": {
+                "command": "push_status",
+                "msg": 1,
+                "state": "printing",
+                "gcode_state": "RUNNING",
+                "percent": percent,
+                "layer_num": layer,
+                "mc_remaining_time": remaining,
+                "nozzle_temper": if self.step.is_multiple_of(2) { 219.8 } else { 220.2 },
+                "bed_temper": 55.0,
+                "wifi_signal": "-45dBm",
+                "fan_gear": 15,
+                "sequence_id": sequence_id
+            },
+            "sequence_id": sequence_id
+        })
+    }
+
+    fn finished_frame(&mut self) -> Value {
+        let sequence_id = self.next_sequence_id();
+
+        json!({
+            "print": {
+                "command": "push_status",
+                "msg": 1,
+                "state": "finished",
+                "gcode_state": "FINISH",
+                "percent": 100,
+                "layer_num": self.script.total_layers,
+                "mc_remaining_time": 0,
+                "nozzle_temper": 180.0,
+                "bed_temper": 50.0,
+                "wifi_signal": "-45dBm",
+                "fan_gear": 0,
+                "sequence_id": sequence_id
+            },
+            "sequence_id": sequence_id
+        })
+    }
+
+    fn announce_frame(&mut self) -> Value {
+        let sequence_id = self.next_sequence_id();
+
+        json!({
+            "pushing": {
+                "command": "pushall",
+                "version": 1,
+                "sequence_id": sequence_id
+            },
+            "model": "X1C",
+            "sn": self.device_id,
+            "ota": { "version": "01.07.00.00" },
+            "wifi": { "ssid": "pulseprint-sim" }
+        })
+    }
+}
+
+impl Iterator for PrinterSimulator {
+    type Item = Value;
+
+    fn next(&mut self) -> Option<Value> {
+        let frame = match self.phase {
+            SimulatorPhase::Announce => {
+                self.phase = SimulatorPhase::Heating;
+                self.announce_frame()
+            }
+            SimulatorPhase::Heating => {
+                let frame = self.heating_frame();
+                self.step += 1;
+                if self.step >= self.script.heating_steps.max(1) {
+                    self.phase = SimulatorPhase::Printing;
+                    self.step = 0;
+                }
+                frame
+            }
+            SimulatorPhase::Printing => {
+                let frame = self.printing_frame();
+                self.step += 1;
+                if self.step >= self.script.printing_steps.max(1) {
+                    self.phase = SimulatorPhase::Finished;
+                    self.step = 0;
+                }
+                frame
+            }
+            SimulatorPhase::Finished => {
+                self.phase = SimulatorPhase::Done;
+                self.finished_frame()
+            }
+            SimulatorPhase::Done => return None,
+        };
+
+        Some(frame)
+    }
+}
@@ -0,0 +1,68 @@
+use super::*;
+use crate::messages::{DeviceMessage, MessageType, PrintState, PrinterStatus};
+
+const DEVICE_ID: &str = "01S00A123456789";
+
+fn short_script() -> PrintJobScript {
+    PrintJobScript {
+        total_layers: 10,
+        total_time_secs: 600,
+        heating_steps: 2,
+        printing_steps: 4,
+    }
+}
+
+#[test]
+fn test_simulator_frame_sequence() {
+    let frames: Vec<Value> = PrinterSimulator::new(DEVICE_ID, short_script()).collect();
+
+    // announce + heating + printing + finished
+    assert_eq!(frames.len(), 1 + 2 + 4 + 1);
+    assert_eq!(frames[0]["pushing"]["command"], "pushall");
+    assert_eq!(frames[0]["sn"], DEVICE_ID);
+    assert_eq!(frames[1]["print"]["gcode_state"], "PREPARE");
+    assert_eq!(frames[3]["print"]["gcode_state"], "RUNNING");
+    assert_eq!(frames[7]["print"]["state"], "finished");
+}
+
+#[test]
+fn test_simulator_progress_is_monotonic() {
+    let percents: Vec<u64> = PrinterSimulator::new(DEVICE_ID, short_script())
+        .filter_map(|frame| frame["print"]["percent"].as_u64())
+        .collect();
+
+    assert!(percents.windows(2).all(|pair| pair[0] <= pair[1]));
+    assert_eq!(percents.last(), Some(&100));
+}
+
+#[test]
+fn test_simulator_sequence_ids_increase() {
+    let mut simulator = PrinterSimulator::new(DEVICE_ID, short_script());
+    let first = simulator.next().unwrap();
+    let second = simulator.next().unwrap();
+
+    assert_eq!(first["pushing"]["sequence_id"], "1");
+    assert_eq!(second["sequence_id"], "2");
+}
+
+#[test]
+fn test_simulator_frames_parse_as_device_messages() {
+    for frame in PrinterSimulator::new(DEVICE_ID, short_script()) {
+        let message = DeviceMessage::parse(&frame.to_string()).unwrap();
+        if let MessageType::PrintPushStatus = message.get_message_type() {
+            let status = PrinterStatus::from_device_message(&message).unwrap();
+            assert!(matches!(
+                status.state,
+                PrintState::Printing | PrintState::Finished
+            ));
+        }
+    }
+}
+
+#[test]
+fn test_simulator_ends_in_done_phase() {
+    let mut simulator = PrinterSimulator::new(DEVICE_ID, short_script());
+    assert_eq!(simulator.phase(), SimulatorPhase::Announce);
+    while simulator.next().is_some() {}
+    assert_eq!(simulator.phase(), SimulatorPhase::Done);
+}
@@ -0,0 +1,107 @@
+use crate::display::ascii_mode;
+use crate::filament;
+use crate::jobs::JobRecord;
+use crate::messages::PrintState;
+use chrono::{Datelike, Timelike};
+use std::collections::HashMap;
+
+#[cfg(test)]
+mod tests;
+
+/// Failure reasons listed by [`JobStats`]
+const TOP_FAILURE_REASONS: usize = 5;
+const WEEKDAYS: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+
+/// Summary of a set of finished and failed jobs
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct JobStats {
+    pub jobs: usize,
+    pub finished: usize,
+    pub failed: usize,
+    /// Most common failure reasons with how often they occurred, most common first
+    pub failure_reasons: Vec<(String, usize)>,
+    /// Filament used according to the AMS readings, in grams
+    pub filament_g: f64,
+    pub average_duration_secs: Option<u32>,
+    /// Jobs started per hour of each weekday, Monday first
+    pub starts: [[u32; 24]; 7],
+}
+
+impl JobStats {
+    pub fn compute<'a>(jobs: impl IntoIterator<Item = &'a JobRecord>) -> Self {
+        let mut stats = JobStats::default();
+        let mut reasons: HashMap<String, usize> = HashMap::new();
+        let mut total_secs = 0u64;
+
+        for job in jobs {
+            stats.jobs += 1;
+            match job.final_state {
+                PrintState::Finished => stats.finished += 1,
+                PrintState::Failed => {
+                    stats.failed += 1;
+                    let reason = job
+                        .fail_reason
+                        .clone()
+                        .filter(|reason| !reason.is_empty())
+                        .unwrap_or_else(|| "no reason reported".to_string());
+                    *reasons.entry(reason).or_default() += 1;
+                }
+                _ => {}
+            }
+            stats.filament_g += filament::total_grams(&job.filament_usage());
+            total_secs += u64::from(job.elapsed_secs());
+
+            let start = job.started_at;
+            stats.starts[start.weekday().num_days_from_monday() as usize][start.hour() as usize] +=
+                1;
+        }
+
+        if stats.jobs > 0 {
+            stats.average_duration_secs = Some((total_secs / stats.jobs as u64) as u32);
+        }
+        let mut reasons: Vec<_> = reasons.into_iter().collect();
+        // Ties in alphabetical order, so the list is stable
+        reasons.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        reasons.truncate(TOP_FAILURE_REASONS);
+        stats.failure_reasons = reasons;
+        stats
+    }
+
+    /// Share of jobs that finished among those that finished or failed, in percent
+    pub fn success_rate(&self) -> Option<f64> {
+        let ended = self.finished + self.failed;
+        (ended > 0).then(|| self.finished as f64 * 100.0 / ended as f64)
+    }
+
+    /// Job starts by weekday and hour, shaded by how many jobs started then
+    pub fn heatmap(&self) -> String {
+        let shades: [char; 5] = if ascii_mode() {
+            [' ', '.', ':', '*', '#']
+        } else {
+            [' ', '░', '▒', '▓', '█']
+        };
+        let busiest = self.starts.iter().flatten().copied().max().unwrap_or(0);
+
+        let mut out = String::from("    ");
+        for hour in (0..24).step_by(6) {
+            out.push_str(&format!("{hour:<6}"));
+        }
+        out = out.trim_end().to_string();
+        out.push('\n');
+        for (day, hours) in WEEKDAYS.iter().zip(&self.starts) {
+            out.push_str(day);
+            out.push(' ');
+            for &count in hours {
+                // Any start at all shows, the busiest hour is fully shaded
+                let level = if busiest == 0 {
+                    0
+                } else {
+                    (count as usize * (shades.len() - 1)).div_ceil(busiest as usize)
+                };
+                out.push(shades[level]);
+            }
+            out.push('\n');
+        }
+        out
+    }
+}
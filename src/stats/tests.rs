@@ -0,0 +1,102 @@
+use super::*;
+use crate::filament::TrayReading;
+use crate::jobs::JobTracker;
+use crate::messages::{DeviceMessage, MergedState};
+use chrono::{DateTime, Local, TimeZone};
+
+/// Job that ran for `minutes` from `start` and ended in `state`
+fn job(start: DateTime<Local>, minutes: i64, state: &str, reason: Option<&str>) -> JobRecord {
+    let mut tracker = JobTracker::default();
+    let mut merged = MergedState::default();
+    let end = match reason {
+        Some(reason) => {
+            format!(r#"{{"print": {{"gcode_state": "{state}", "fail_reason": "{reason}"}}}}"#)
+        }
+        None => format!(r#"{{"print": {{"gcode_state": "{state}"}}}}"#),
+    };
+
+    for (at, payload) in [
+        (
+            start,
+            r#"{"print": {"gcode_state": "RUNNING", "subtask_name": "part", "percent": 0}}"#
+                .to_string(),
+        ),
+        (start + chrono::Duration::minutes(minutes), end),
+    ] {
+        merged.update(&DeviceMessage::parse(&payload).unwrap());
+        if let Some(job) = tracker.observe(&merged, at) {
+            return job;
+        }
+    }
+    panic!("job did not complete");
+}
+
+fn tray(remain: u32) -> std::collections::BTreeMap<String, TrayReading> {
+    [(
+        "A1".to_string(),
+        TrayReading {
+            remain,
+            weight: 1000.0,
+            material: Some("PLA".to_string()),
+        },
+    )]
+    .into()
+}
+
+#[test]
+fn test_job_stats() {
+    // 2025-08-04 is a Monday
+    let monday = Local.with_ymd_and_hms(2025, 8, 4, 9, 0, 0).unwrap();
+    let mut finished = job(monday, 60, "FINISH", None);
+    finished.trays_at_start = tray(80);
+    finished.trays_at_end = tray(75);
+    let jobs = [
+        finished,
+        job(monday, 30, "FAILED", Some("spaghetti")),
+        job(
+            monday + chrono::Duration::days(2),
+            30,
+            "FAILED",
+            Some("spaghetti"),
+        ),
+        job(monday + chrono::Duration::hours(5), 120, "FAILED", None),
+    ];
+
+    let stats = JobStats::compute(&jobs);
+    assert_eq!(stats.jobs, 4);
+    assert_eq!(stats.finished, 1);
+    assert_eq!(stats.failed, 3);
+    assert_eq!(stats.success_rate(), Some(25.0));
+    assert_eq!(
+        stats.failure_reasons,
+        vec![
+            ("spaghetti".to_string(), 2),
+            ("no reason reported".to_string(), 1)
+        ]
+    );
+    assert_eq!(stats.filament_g, 50.0);
+    assert_eq!(stats.average_duration_secs, Some(60 * 60));
+    assert_eq!(stats.starts[0][9], 2);
+    assert_eq!(stats.starts[0][14], 1);
+    assert_eq!(stats.starts[2][9], 1);
+
+    let empty = JobStats::compute(&[]);
+    assert_eq!(empty.success_rate(), None);
+    assert_eq!(empty.average_duration_secs, None);
+}
+
+#[test]
+fn test_heatmap() {
+    let mut stats = JobStats::default();
+    stats.starts[0][9] = 4;
+    stats.starts[6][23] = 1;
+
+    let heatmap = stats.heatmap();
+    let lines: Vec<_> = heatmap.lines().collect();
+    assert_eq!(lines.len(), 8);
+    assert_eq!(lines[0], "    0     6     12    18");
+    assert_eq!(lines[1].chars().nth(4 + 9), Some('█'));
+    assert_eq!(lines[1].chars().nth(4 + 8), Some(' '));
+    // A single start still shows next to the busiest hour
+    assert_eq!(lines[7], format!("Sun {}░", " ".repeat(23)));
+}
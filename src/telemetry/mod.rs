@@ -0,0 +1,225 @@
+use crate::messages::{DeviceMessage, MergedState};
+use crate::recording::RecordedMessage;
+use chrono::{DateTime, Local, TimeZone};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::io::Write;
+use std::time::Duration;
+use utoipa::ToSchema;
+
+#[cfg(test)]
+mod tests;
+
+/// How far back telemetry is kept unless configured otherwise
+pub const DEFAULT_RETENTION: Duration = Duration::from_secs(60 * 60);
+/// Reports arriving closer together than this replace the previous sample, so a printer
+/// pushing several times a second does not crowd out older samples
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Temperatures and progress of one printer at one moment
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct TelemetrySample {
+    #[schema(value_type = String, format = DateTime)]
+    pub at: DateTime<Local>,
+    pub nozzle: Option<f64>,
+    pub bed: Option<f64>,
+    pub chamber: Option<f64>,
+    /// Job progress in percent
+    pub progress: Option<u32>,
+    pub layer: Option<u32>,
+}
+
+impl TelemetrySample {
+    pub fn of(state: &MergedState, at: DateTime<Local>) -> Self {
+        Self {
+            at,
+            nozzle: state.print.nozzle_temper,
+            bed: state.print.bed_temper,
+            chamber: state.print.chamber_temper,
+            progress: state.status().progress,
+            layer: state.print.layer_num,
+        }
+    }
+}
+
+/// Samples of the last [`retention`](TelemetryBuffer::retention), oldest first, kept in
+/// memory for charts
+#[derive(Debug, Clone)]
+pub struct TelemetryBuffer {
+    retention: Duration,
+    samples: VecDeque<TelemetrySample>,
+}
+
+impl Default for TelemetryBuffer {
+    fn default() -> Self {
+        Self::new(DEFAULT_RETENTION)
+    }
+}
+
+impl TelemetryBuffer {
+    pub fn new(retention: Duration) -> Self {
+        Self {
+            retention,
+            samples: VecDeque::new(),
+        }
+    }
+
+    pub fn retention(&self) -> Duration {
+        self.retention
+    }
+
+    /// Samples the merged state, dropping samples older than the retention
+    pub fn record(&mut self, state: &MergedState, at: DateTime<Local>) {
+        let sample = TelemetrySample::of(state, at);
+        if let Some(last) = self.samples.back_mut()
+            && (at - last.at)
+                .to_std()
+                .is_ok_and(|age| age < SAMPLE_INTERVAL)
+        {
+            // Keep the first timestamp so steady reporting still yields a sample a second
+            *last = TelemetrySample {
+                at: last.at,
+                ..sample
+            };
+        } else {
+            self.samples.push_back(sample);
+        }
+
+        let cutoff = at - self.retention;
+        while self
+            .samples
+            .front()
+            .is_some_and(|oldest| oldest.at < cutoff)
+        {
+            self.samples.pop_front();
+        }
+    }
+
+    /// Samples taken within `window` before `now`, oldest first
+    pub fn window(&self, window: Duration, now: DateTime<Local>) -> Vec<TelemetrySample> {
+        let since = now - window;
+        self.samples
+            .iter()
+            .filter(|sample| sample.at >= since)
+            .cloned()
+            .collect()
+    }
+
+    pub fn samples(&self) -> &VecDeque<TelemetrySample> {
+        &self.samples
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+}
+
+/// A sample of the merged state after each status message of a recorded session
+pub fn session_samples(messages: &[RecordedMessage]) -> Vec<TelemetrySample> {
+    let mut state = MergedState::default();
+    let mut samples = Vec::new();
+    for recorded in messages {
+        let Some(message) = recorded
+            .payload_bytes()
+            .ok()
+            .and_then(|payload| DeviceMessage::parse_bytes(&payload).ok())
+        else {
+            continue;
+        };
+        if message.print.is_none() {
+            continue;
+        }
+        state.update(&message);
+        if let Some(at) = Local
+            .timestamp_millis_opt(recorded.timestamp_ms as i64)
+            .single()
+        {
+            samples.push(TelemetrySample::of(&state, at));
+        }
+    }
+    samples
+}
+
+/// Writes samples as CSV with a header row; unknown values are left empty
+pub fn write_csv<W: Write>(samples: &[TelemetrySample], mut writer: W) -> std::io::Result<()> {
+    fn cell<T: ToString>(value: Option<T>) -> String {
+        value.map(|value| value.to_string()).unwrap_or_default()
+    }
+
+    writeln!(writer, "at,nozzle,bed,chamber,progress,layer")?;
+    for sample in samples {
+        writeln!(
+            writer,
+            "{},{},{},{},{},{}",
+            sample.at.to_rfc3339(),
+            cell(sample.nozzle),
+            cell(sample.bed),
+            cell(sample.chamber),
+            cell(sample.progress),
+            cell(sample.layer)
+        )?;
+    }
+    writer.flush()
+}
+
+/// Columns of [`write_parquet`]: the sample time in UTC, then one nullable column per value
+#[cfg(feature = "parquet")]
+pub fn parquet_schema() -> arrow_schema::Schema {
+    use arrow_schema::{DataType, Field, Schema, TimeUnit};
+
+    Schema::new(vec![
+        Field::new(
+            "at",
+            DataType::Timestamp(TimeUnit::Millisecond, Some("UTC".into())),
+            false,
+        ),
+        Field::new("nozzle", DataType::Float64, true),
+        Field::new("bed", DataType::Float64, true),
+        Field::new("chamber", DataType::Float64, true),
+        Field::new("progress", DataType::UInt32, true),
+        Field::new("layer", DataType::UInt32, true),
+    ])
+}
+
+/// Writes samples as one Parquet row group with the columns of [`parquet_schema`]
+#[cfg(feature = "parquet")]
+pub fn write_parquet<W: Write + Send>(
+    samples: &[TelemetrySample],
+    writer: W,
+) -> Result<(), parquet::errors::ParquetError> {
+    use arrow_array::{
+        ArrayRef, Float64Array, RecordBatch, TimestampMillisecondArray, UInt32Array,
+    };
+    use std::sync::Arc;
+
+    let schema = Arc::new(parquet_schema());
+    let float = |value: fn(&TelemetrySample) -> Option<f64>| -> ArrayRef {
+        Arc::new(samples.iter().map(value).collect::<Float64Array>())
+    };
+    let count = |value: fn(&TelemetrySample) -> Option<u32>| -> ArrayRef {
+        Arc::new(samples.iter().map(value).collect::<UInt32Array>())
+    };
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(
+            TimestampMillisecondArray::from_iter_values(
+                samples.iter().map(|sample| sample.at.timestamp_millis()),
+            )
+            .with_timezone("UTC"),
+        ),
+        float(|sample| sample.nozzle),
+        float(|sample| sample.bed),
+        float(|sample| sample.chamber),
+        count(|sample| sample.progress),
+        count(|sample| sample.layer),
+    ];
+    let batch = RecordBatch::try_new(Arc::clone(&schema), columns)?;
+
+    let mut writer = parquet::arrow::ArrowWriter::try_new(writer, schema, None)?;
+    writer.write(&batch)?;
+    writer.close()?;
+    Ok(())
+}
@@ -0,0 +1,157 @@
+use super::*;
+use crate::messages::DeviceMessage;
+use crate::recording::PayloadEncoding;
+
+fn state(nozzle: f64, percent: u32) -> MergedState {
+    let mut state = MergedState::default();
+    state.update(
+        &DeviceMessage::parse(&format!(
+            r#"{{"print": {{"nozzle_temper": {nozzle}, "bed_temper": 60, "percent": {percent}, "layer_num": 3}}}}"#
+        ))
+        .unwrap(),
+    );
+    state
+}
+
+fn at(seconds: i64) -> DateTime<Local> {
+    Local.with_ymd_and_hms(2025, 8, 1, 9, 0, 0).unwrap() + chrono::Duration::seconds(seconds)
+}
+
+#[test]
+fn test_record_samples_state() {
+    let mut buffer = TelemetryBuffer::default();
+    assert!(buffer.is_empty());
+    buffer.record(&state(220.0, 40), at(0));
+
+    assert_eq!(
+        buffer.samples()[0],
+        TelemetrySample {
+            at: at(0),
+            nozzle: Some(220.0),
+            bed: Some(60.0),
+            chamber: None,
+            progress: Some(40),
+            layer: Some(3),
+        }
+    );
+}
+
+#[test]
+fn test_close_reports_replace_the_last_sample() {
+    let mut buffer = TelemetryBuffer::default();
+    buffer.record(&state(200.0, 1), at(0));
+    buffer.record(&state(205.0, 1), at(0));
+    assert_eq!(buffer.len(), 1);
+    assert_eq!(buffer.samples()[0].nozzle, Some(205.0));
+    assert_eq!(buffer.samples()[0].at, at(0));
+
+    buffer.record(&state(210.0, 2), at(1));
+    assert_eq!(buffer.len(), 2);
+}
+
+#[test]
+fn test_old_samples_are_dropped_and_windowed() {
+    let mut buffer = TelemetryBuffer::new(Duration::from_secs(600));
+    for minute in 0..=20 {
+        buffer.record(
+            &state(200.0 + minute as f64, minute),
+            at(minute as i64 * 60),
+        );
+    }
+    assert_eq!(buffer.retention(), Duration::from_secs(600));
+    assert_eq!(buffer.len(), 11);
+    assert_eq!(buffer.samples()[0].at, at(10 * 60));
+
+    let recent = buffer.window(Duration::from_secs(120), at(20 * 60));
+    assert_eq!(
+        recent
+            .iter()
+            .map(|sample| sample.progress)
+            .collect::<Vec<_>>(),
+        vec![Some(18), Some(19), Some(20)]
+    );
+    assert!(
+        buffer
+            .window(Duration::from_secs(60), at(60 * 60))
+            .is_empty()
+    );
+}
+
+#[test]
+fn test_session_samples_to_csv() {
+    let recorded = |seconds: u64, payload: &str| RecordedMessage {
+        timestamp_ms: at(0).timestamp_millis() as u64 + seconds * 1000,
+        topic: "device/01S/report".to_string(),
+        payload: payload.to_string(),
+        encoding: PayloadEncoding::Utf8,
+    };
+    let samples = session_samples(&[
+        recorded(0, r#"{"print": {"nozzle_temper": 210.5, "percent": 0}}"#),
+        recorded(1, r#"{"info": {"command": "get_version"}}"#),
+        recorded(2, "not json"),
+        recorded(3, r#"{"print": {"bed_temper": 60, "percent": 1}}"#),
+    ]);
+    assert_eq!(samples.len(), 2);
+    assert_eq!(samples[1].at, at(3));
+    // Values carry over from earlier reports, as in the merged state
+    assert_eq!(samples[1].nozzle, Some(210.5));
+    assert_eq!(samples[1].bed, Some(60.0));
+
+    let mut csv = Vec::new();
+    write_csv(&samples, &mut csv).unwrap();
+    let csv = String::from_utf8(csv).unwrap();
+    let lines: Vec<_> = csv.lines().collect();
+    assert_eq!(lines[0], "at,nozzle,bed,chamber,progress,layer");
+    assert_eq!(lines[1], format!("{},210.5,,,0,", at(0).to_rfc3339()));
+    assert_eq!(lines[2], format!("{},210.5,60,,1,", at(3).to_rfc3339()));
+}
+
+#[cfg(feature = "parquet")]
+#[test]
+fn test_parquet_round_trip() {
+    use arrow_array::Array;
+    use arrow_array::cast::AsArray;
+    use arrow_array::types::{Float64Type, TimestampMillisecondType, UInt32Type};
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+    let samples = vec![
+        TelemetrySample {
+            at: at(0),
+            nozzle: Some(210.5),
+            bed: None,
+            chamber: Some(35.0),
+            progress: Some(0),
+            layer: None,
+        },
+        TelemetrySample {
+            at: at(3),
+            nozzle: Some(220.0),
+            bed: Some(60.0),
+            chamber: None,
+            progress: Some(1),
+            layer: Some(2),
+        },
+    ];
+    let temp_dir = tempfile::tempdir().unwrap();
+    let path = temp_dir.path().join("job.parquet");
+    write_parquet(&samples, std::fs::File::create(&path).unwrap()).unwrap();
+
+    let reader =
+        ParquetRecordBatchReaderBuilder::try_new(std::fs::File::open(&path).unwrap()).unwrap();
+    assert_eq!(reader.schema().as_ref(), &parquet_schema());
+    let batches: Vec<_> = reader.build().unwrap().map(Result::unwrap).collect();
+    assert_eq!(batches.len(), 1);
+    let batch = &batches[0];
+    assert_eq!(batch.num_rows(), 2);
+
+    let times = batch.column(0).as_primitive::<TimestampMillisecondType>();
+    assert_eq!(times.value(1), at(3).timestamp_millis());
+    let nozzle = batch.column(1).as_primitive::<Float64Type>();
+    assert_eq!(nozzle.value(0), 210.5);
+    let bed = batch.column(2).as_primitive::<Float64Type>();
+    assert!(bed.is_null(0));
+    assert_eq!(bed.value(1), 60.0);
+    let layer = batch.column(5).as_primitive::<UInt32Type>();
+    assert!(layer.is_null(0));
+    assert_eq!(layer.value(1), 2);
+}
@@ -0,0 +1,223 @@
+use super::*;
+use std::collections::VecDeque;
+use std::time::Duration;
+
+fn report(payload: &str) -> Result<rumqttc::Publish, String> {
+    Ok(rumqttc::Publish::new(
+        "device/01S00C123456789/report",
+        rumqttc::QoS::AtMostOnce,
+        payload.as_bytes().to_vec(),
+    ))
+}
+
+#[tokio::test]
+async fn test_monitor_reconnects_and_keeps_state() {
+    // The first connection delivers one report and then drops; the second one finishes
+    let (first, first_source) = mqtt::ChannelSource::new(4);
+    first
+        .send(report(
+            r#"{"print": {"command": "push_status", "gcode_state": "RUNNING", "subtask_name": "benchy", "percent": 10}}"#,
+        ))
+        .await
+        .unwrap();
+    first
+        .send(Err("MQTT connection error: connection reset".to_string()))
+        .await
+        .unwrap();
+    let (second, second_source) = mqtt::ChannelSource::new(4);
+    second
+        .send(report(
+            r#"{"print": {"command": "push_status", "percent": 42, "layer_num": 12}}"#,
+        ))
+        .await
+        .unwrap();
+    drop(second);
+
+    let config = config::PrinterConfig::new(
+        "x1c".to_string(),
+        "192.168.1.100".to_string(),
+        "01S00C123456789".to_string(),
+        "12345678".to_string(),
+    );
+    let options = MonitorOptions {
+        reconnect: Some(std::sync::Arc::new(mqtt::FixedDelay {
+            delay: Duration::ZERO,
+            max_retries: Some(1),
+        })),
+        ..MonitorOptions::default()
+    };
+    let mut state = messages::MergedState::default();
+    let mut pipeline = MonitorPipeline::default();
+    let mut display = display::StatusDisplay::new(false);
+    let mut sources = VecDeque::from([first_source, second_source]);
+    let mut attempts = 0;
+
+    retry_connections(
+        &config,
+        &options,
+        &mut state,
+        &mut pipeline,
+        async |state, pipeline| {
+            attempts += 1;
+            let mut source = sources.pop_front().ok_or("no connection left")?;
+            process_messages(&mut source, &mut display, &options, state, pipeline).await
+        },
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(attempts, 2);
+    assert_eq!(state.message_count, 2);
+    // Fields from before the reconnect are merged with the ones after it
+    assert_eq!(state.print.subtask_name.as_deref(), Some("benchy"));
+    assert_eq!(state.print.layer_num, Some(12));
+
+    let status = state.status();
+    assert_eq!(status.state, messages::PrintState::Printing);
+    assert_eq!(status.progress, Some(42));
+    assert!(print_status_line(&status, None).starts_with(&format!(
+        "{} Print Status: Printing - Progress: 42%",
+        Icon::Printing
+    )));
+}
+
+#[tokio::test]
+async fn test_monitor_gives_up_when_the_policy_does() {
+    let config = config::PrinterConfig::new(
+        "x1c".to_string(),
+        "192.168.1.100".to_string(),
+        "01S00C123456789".to_string(),
+        "12345678".to_string(),
+    );
+    let options = MonitorOptions {
+        reconnect: Some(std::sync::Arc::new(mqtt::FixedDelay {
+            delay: Duration::ZERO,
+            max_retries: Some(2),
+        })),
+        ..MonitorOptions::default()
+    };
+    let mut state = messages::MergedState::default();
+    let mut display = display::StatusDisplay::new(false);
+    let mut attempts = 0;
+
+    let result = retry_connections(
+        &config,
+        &options,
+        &mut state,
+        &mut MonitorPipeline::default(),
+        async |state, pipeline| {
+            attempts += 1;
+            let (errors, mut source) = mqtt::ChannelSource::new(1);
+            errors
+                .send(Err("MQTT connection error: refused".to_string()))
+                .await
+                .unwrap();
+            process_messages(&mut source, &mut display, &options, state, pipeline).await
+        },
+    )
+    .await;
+
+    assert_eq!(
+        result.unwrap_err().to_string(),
+        "Failed to connect after 3 attempts"
+    );
+    assert_eq!(attempts, 3);
+    assert_eq!(state.message_count, 0);
+}
+
+#[tokio::test]
+async fn test_monitor_waits_for_alerts_held_for_a_snapshot() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let events_path = temp_dir.path().join("events.jsonl");
+    let bus = events::EventBus::start(&[config::SinkSettings {
+        target: config::SinkTarget::JsonFile {
+            path: events_path.clone(),
+        },
+        events: Vec::new(),
+        alerts: Vec::new(),
+    }]);
+    // Nothing listens on the camera port, so the still fails right away
+    let printer = config::PrinterConfig::new(
+        "p1s".to_string(),
+        "127.0.0.1".to_string(),
+        "01P00A123456789".to_string(),
+        "12345678".to_string(),
+    );
+    let mut pipeline = MonitorPipeline {
+        printer_name: "p1s".to_string(),
+        snapshots: Some((printer, temp_dir.path().join("snapshots"))),
+        subscribers: vec![Subscriber::Sinks(SinkSubscriber {
+            printer_name: "p1s".to_string(),
+            bus: bus.clone(),
+        })],
+        ..MonitorPipeline::default()
+    };
+
+    let alert = alerts::Alert {
+        kind: "spaghetti",
+        message: "Spaghetti detected".to_string(),
+    };
+    let mut display = display::StatusDisplay::new(false);
+    pipeline
+        .raise_with_snapshot(alert, false, &mut display)
+        .await;
+    pipeline.finish().await;
+    bus.finish().await;
+
+    let events = std::fs::read_to_string(&events_path).unwrap();
+    let event: serde_json::Value = serde_json::from_str(events.trim()).unwrap();
+    assert_eq!(event["event"], "alert");
+    assert_eq!(event["rule"], "spaghetti");
+    assert!(event.get("snapshot").is_none());
+}
+
+#[tokio::test]
+async fn test_pipeline_fans_out_to_subscribers() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let events_path = temp_dir.path().join("events.jsonl");
+    let bus = events::EventBus::start(&[config::SinkSettings {
+        target: config::SinkTarget::JsonFile {
+            path: events_path.clone(),
+        },
+        events: vec![
+            config::EventType::PrintStarted,
+            config::EventType::JobFinished,
+        ],
+        alerts: Vec::new(),
+    }]);
+    let mut pipeline = MonitorPipeline {
+        printer_name: "x1c".to_string(),
+        subscribers: vec![Subscriber::Sinks(SinkSubscriber {
+            printer_name: "x1c".to_string(),
+            bus: bus.clone(),
+        })],
+        ..MonitorPipeline::default()
+    };
+    let mut display = display::StatusDisplay::new(false);
+    let mut state = messages::MergedState::default();
+
+    for payload in [
+        r#"{"print": {"command": "push_status", "gcode_state": "IDLE"}}"#,
+        r#"{"print": {"command": "push_status", "gcode_state": "RUNNING", "subtask_name": "benchy", "mc_percent": 0}}"#,
+        r#"{"print": {"command": "push_status", "gcode_state": "FINISH", "mc_percent": 100}}"#,
+    ] {
+        let publish = report(payload).unwrap();
+        let message =
+            messages::DeviceMessage::parse_from(&publish.topic, &publish.payload).unwrap();
+        state.update(&message);
+        pipeline.observe(&state, &mut display).await;
+    }
+    pipeline.finish().await;
+    bus.finish().await;
+
+    let events = std::fs::read_to_string(&events_path).unwrap();
+    let events = events
+        .lines()
+        .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[0]["event"], "print_started");
+    assert_eq!(events[1]["event"], "job_finished");
+    assert_eq!(events[1]["job"]["name"], "benchy");
+    assert_eq!(events[1]["job"]["result"], "finished");
+}
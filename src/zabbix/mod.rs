@@ -0,0 +1,198 @@
+use crate::config::ZabbixSettings;
+use crate::messages::MergedState;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+use thiserror::Error;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+#[cfg(test)]
+mod tests;
+
+/// Every sender protocol packet starts with this header, followed by a little-endian length
+const HEADER: &[u8; 5] = b"ZBXD\x01";
+const SEND_TIMEOUT: Duration = Duration::from_secs(5);
+/// Responses are a short JSON status line; anything bigger is not a Zabbix server
+const MAX_RESPONSE_LEN: u64 = 64 * 1024;
+
+#[derive(Debug, Error)]
+pub enum ZabbixError {
+    #[error("IO error: {0}")]
+    IoError(#[from] std::io::Error),
+
+    #[error("Invalid JSON: {0}")]
+    JsonError(#[from] serde_json::Error),
+
+    #[error("Invalid response from Zabbix server: {0}")]
+    InvalidResponse(String),
+
+    #[error("Zabbix server rejected the data: {0}")]
+    Rejected(String),
+
+    #[error("Timed out talking to Zabbix server")]
+    Timeout,
+}
+
+/// One trapper item value
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ZabbixItem {
+    pub host: String,
+    pub key: String,
+    pub value: String,
+}
+
+#[derive(Debug, Serialize)]
+struct SenderRequest<'a> {
+    request: &'static str,
+    data: &'a [ZabbixItem],
+}
+
+/// Server reply, e.g. `{"response":"success","info":"processed: 3; failed: 0; ..."}`
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ZabbixResponse {
+    pub response: String,
+    #[serde(default)]
+    pub info: String,
+}
+
+impl ZabbixResponse {
+    pub fn is_success(&self) -> bool {
+        self.response == "success"
+    }
+}
+
+/// Frames items as a `sender data` request packet
+pub fn encode_request(items: &[ZabbixItem]) -> Result<Vec<u8>, ZabbixError> {
+    let body = serde_json::to_vec(&SenderRequest {
+        request: "sender data",
+        data: items,
+    })?;
+
+    let mut packet = Vec::with_capacity(HEADER.len() + 8 + body.len());
+    packet.extend_from_slice(HEADER);
+    packet.extend_from_slice(&(body.len() as u64).to_le_bytes());
+    packet.extend_from_slice(&body);
+    Ok(packet)
+}
+
+/// Parses a complete response packet (header, length, and JSON body)
+pub fn decode_response(packet: &[u8]) -> Result<ZabbixResponse, ZabbixError> {
+    let body = packet
+        .strip_prefix(HEADER.as_slice())
+        .ok_or_else(|| ZabbixError::InvalidResponse("missing ZBXD header".to_string()))?;
+    if body.len() < 8 {
+        return Err(ZabbixError::InvalidResponse("truncated length".to_string()));
+    }
+
+    let (len, body) = body.split_at(8);
+    let len = u64::from_le_bytes(len.try_into().expect("split at 8 bytes")) as usize;
+    if body.len() < len {
+        return Err(ZabbixError::InvalidResponse(format!(
+            "expected {len} bytes, got {}",
+            body.len()
+        )));
+    }
+
+    Ok(serde_json::from_slice(&body[..len])?)
+}
+
+/// Item values for the current printer state, keyed `<prefix>.<metric>`
+pub fn state_items(host: &str, key_prefix: &str, state: &MergedState) -> Vec<ZabbixItem> {
+    let status = state.status();
+    let mut items = Vec::new();
+    let mut push = |metric: &str, value: String| {
+        items.push(ZabbixItem {
+            host: host.to_string(),
+            key: format!("{key_prefix}.{metric}"),
+            value,
+        });
+    };
+
+    push("state", status.state.name().to_string());
+
+    if let Some(progress) = status.progress {
+        push("progress", progress.to_string());
+    }
+    if let Some(nozzle) = state.print.nozzle_temper {
+        push("nozzle_temp", nozzle.to_string());
+    }
+    if let Some(bed) = state.print.bed_temper {
+        push("bed_temp", bed.to_string());
+    }
+    if let Some(layer) = state.print.layer_num {
+        push("layer", layer.to_string());
+    }
+    if let Some(remaining) = status.remaining_time {
+        push("remaining_time", remaining.to_string());
+    }
+    if let Some(dbm) = state.print.wifi_dbm() {
+        push("wifi_signal", dbm.to_string());
+    }
+
+    items
+}
+
+/// Pushes printer state to a Zabbix server, at most once per configured interval
+#[derive(Debug)]
+pub struct ZabbixSender {
+    settings: ZabbixSettings,
+    host: String,
+    last_sent: Option<Instant>,
+}
+
+impl ZabbixSender {
+    pub fn new(settings: ZabbixSettings, printer_name: &str) -> Self {
+        let host = settings
+            .host
+            .clone()
+            .unwrap_or_else(|| printer_name.to_string());
+        Self {
+            settings,
+            host,
+            last_sent: None,
+        }
+    }
+
+    /// Sends the state if the push interval has elapsed; returns `None` when skipped
+    pub async fn maybe_send(
+        &mut self,
+        state: &MergedState,
+    ) -> Option<Result<ZabbixResponse, ZabbixError>> {
+        let interval = Duration::from_secs(self.settings.interval_secs);
+        if self
+            .last_sent
+            .is_some_and(|last_sent| last_sent.elapsed() < interval)
+        {
+            return None;
+        }
+
+        self.last_sent = Some(Instant::now());
+        let items = state_items(&self.host, &self.settings.key_prefix, state);
+        Some(self.send(&items).await)
+    }
+
+    pub async fn send(&self, items: &[ZabbixItem]) -> Result<ZabbixResponse, ZabbixError> {
+        let packet = encode_request(items)?;
+        let address = (self.settings.server.as_str(), self.settings.port);
+
+        let response = tokio::time::timeout(SEND_TIMEOUT, async {
+            let mut stream = TcpStream::connect(address).await?;
+            stream.write_all(&packet).await?;
+
+            let mut response = Vec::new();
+            (&mut stream)
+                .take(MAX_RESPONSE_LEN)
+                .read_to_end(&mut response)
+                .await?;
+            decode_response(&response)
+        })
+        .await
+        .map_err(|_| ZabbixError::Timeout)??;
+
+        if response.is_success() {
+            Ok(response)
+        } else {
+            Err(ZabbixError::Rejected(response.info))
+        }
+    }
+}
@@ -0,0 +1,118 @@
+use super::*;
+use crate::messages::DeviceMessage;
+use tokio::net::TcpListener;
+
+fn settings(server: &str, port: u16) -> ZabbixSettings {
+    ZabbixSettings {
+        server: server.to_string(),
+        port,
+        host: None,
+        key_prefix: "pulseprint".to_string(),
+        interval_secs: 60,
+    }
+}
+
+fn printing_state() -> MergedState {
+    let mut state = MergedState::default();
+    state.update(
+        &DeviceMessage::parse(
+            r#"{"print": {"command": "push_status", "gcode_state": "RUNNING", "percent": 45, "nozzle_temper": 220.5, "bed_temper": 60.0, "wifi_signal": "-58dBm"}}"#,
+        )
+        .unwrap(),
+    );
+    state
+}
+
+fn response_packet(body: &str) -> Vec<u8> {
+    let mut packet = HEADER.to_vec();
+    packet.extend_from_slice(&(body.len() as u64).to_le_bytes());
+    packet.extend_from_slice(body.as_bytes());
+    packet
+}
+
+#[test]
+fn test_encode_request_framing() {
+    let items = vec![ZabbixItem {
+        host: "x1c".to_string(),
+        key: "pulseprint.progress".to_string(),
+        value: "45".to_string(),
+    }];
+    let packet = encode_request(&items).unwrap();
+
+    assert_eq!(&packet[..5], b"ZBXD\x01");
+    let len = u64::from_le_bytes(packet[5..13].try_into().unwrap()) as usize;
+    assert_eq!(len, packet.len() - 13);
+
+    let body: serde_json::Value = serde_json::from_slice(&packet[13..]).unwrap();
+    assert_eq!(body["request"], "sender data");
+    assert_eq!(body["data"][0]["host"], "x1c");
+    assert_eq!(body["data"][0]["key"], "pulseprint.progress");
+    assert_eq!(body["data"][0]["value"], "45");
+}
+
+#[test]
+fn test_decode_response() {
+    let response = decode_response(&response_packet(
+        r#"{"response":"success","info":"processed: 2; failed: 0; total: 2"}"#,
+    ))
+    .unwrap();
+    assert!(response.is_success());
+    assert!(response.info.starts_with("processed: 2"));
+
+    assert!(decode_response(b"HTTP/1.1 400").is_err());
+    assert!(decode_response(&response_packet("{}")[..15]).is_err());
+}
+
+#[test]
+fn test_state_items() {
+    let items = state_items("x1c", "farm", &printing_state());
+    let pairs: Vec<(&str, &str)> = items
+        .iter()
+        .map(|item| (item.key.as_str(), item.value.as_str()))
+        .collect();
+
+    assert_eq!(
+        pairs,
+        vec![
+            ("farm.state", "printing"),
+            ("farm.progress", "45"),
+            ("farm.nozzle_temp", "220.5"),
+            ("farm.bed_temp", "60"),
+            ("farm.wifi_signal", "-58"),
+        ]
+    );
+    assert!(items.iter().all(|item| item.host == "x1c"));
+}
+
+#[tokio::test]
+async fn test_sender_round_trip_and_interval() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+
+    let server = tokio::spawn(async move {
+        let (mut socket, _) = listener.accept().await.unwrap();
+        let mut header = [0u8; 13];
+        socket.read_exact(&mut header).await.unwrap();
+        let len = u64::from_le_bytes(header[5..].try_into().unwrap()) as usize;
+        let mut body = vec![0u8; len];
+        socket.read_exact(&mut body).await.unwrap();
+
+        socket
+            .write_all(&response_packet(
+                r#"{"response":"success","info":"processed: 4; failed: 0"}"#,
+            ))
+            .await
+            .unwrap();
+        serde_json::from_slice::<serde_json::Value>(&body).unwrap()
+    });
+
+    let mut sender = ZabbixSender::new(settings("127.0.0.1", port), "x1c");
+    let response = sender.maybe_send(&printing_state()).await.unwrap().unwrap();
+    assert!(response.is_success());
+
+    let request = server.await.unwrap();
+    assert_eq!(request["data"][0]["host"], "x1c");
+
+    // A second update inside the interval is not sent
+    assert!(sender.maybe_send(&printing_state()).await.is_none());
+}
@@ -4,7 +4,7 @@ use tempfile::tempdir;
 #[test]
 fn test_cli_help_command() {
     let output = Command::new("cargo")
-        .args(&["run", "--", "--help"])
+        .args(["run", "--", "--help"])
         .output()
         .expect("Failed to execute command");
 
@@ -18,7 +18,7 @@ fn test_cli_help_command() {
 #[test]
 fn test_cli_version_command() {
     let output = Command::new("cargo")
-        .args(&["run", "--", "--version"])
+        .args(["run", "--", "--version"])
         .output()
         .expect("Failed to execute command");
 
@@ -31,7 +31,7 @@ fn test_cli_version_command() {
 #[test]
 fn test_monitor_help_command() {
     let output = Command::new("cargo")
-        .args(&["run", "--", "monitor", "--help"])
+        .args(["run", "--", "monitor", "--help"])
         .output()
         .expect("Failed to execute command");
 
@@ -43,6 +43,9 @@ fn test_monitor_help_command() {
     assert!(stdout.contains("--ip"));
     assert!(stdout.contains("--device-id"));
     assert!(stdout.contains("--access-code"));
+    assert!(stdout.contains("--once"));
+    assert!(stdout.contains("--fields"));
+    assert!(stdout.contains("--refresh"));
 }
 
 #[test]
@@ -55,7 +58,7 @@ fn test_monitor_no_config() {
 
     // Spawn the monitor command with a custom config directory
     let mut child = Command::new("cargo")
-        .args(&["run", "--", "monitor"])
+        .args(["run", "--", "monitor"])
         .env("PULSEPRINT_TEST_CONFIG_DIR", temp_dir.path())
         .stderr(std::process::Stdio::piped())
         .spawn()
@@ -73,13 +76,12 @@ fn test_monitor_no_config() {
             break; // Timeout after 5 seconds
         }
 
-        if let Ok(line) = line {
-            if line.contains("No printers configured")
-                || line.contains("Error loading printer configuration")
-            {
-                found_error = true;
-                break;
-            }
+        if let Ok(line) = line
+            && (line.contains("No printers configured")
+                || line.contains("Error loading printer configuration"))
+        {
+            found_error = true;
+            break;
         }
     }
 
@@ -103,7 +105,7 @@ fn test_monitor_with_direct_params() {
 
     // Spawn monitor with direct parameters (will fail to connect but that's expected)
     let mut child = Command::new("cargo")
-        .args(&[
+        .args([
             "run",
             "--",
             "monitor",
@@ -136,14 +138,13 @@ fn test_monitor_with_direct_params() {
             break; // Timeout after 5 seconds
         }
 
-        if let Ok(line) = line {
-            if line.contains("Connecting to printer")
-                && line.contains("192.168.1.100")
-                && line.contains("01S00A000000000")
-            {
-                found_connection_attempt = true;
-                break;
-            }
+        if let Ok(line) = line
+            && line.contains("Connecting to printer")
+            && line.contains("192.168.1.100")
+            && line.contains("01S00A000000000")
+        {
+            found_connection_attempt = true;
+            break;
         }
     }
 
@@ -184,7 +185,7 @@ fn test_monitor_with_direct_params() {
 #[test]
 fn test_cli_help_shows_new_commands() {
     let output = Command::new("cargo")
-        .args(&["run", "--", "--help"])
+        .args(["run", "--", "--help"])
         .output()
         .expect("Failed to execute command");
 
@@ -195,12 +196,20 @@ fn test_cli_help_shows_new_commands() {
     assert!(stdout.contains("list"));
     assert!(stdout.contains("remove"));
     assert!(stdout.contains("set-default"));
+    assert!(stdout.contains("daemon"));
+    assert!(stdout.contains("pause"));
+    assert!(stdout.contains("snapshot"));
+    assert!(stdout.contains("timelapse"));
+    assert!(stdout.contains("files"));
+    assert!(stdout.contains("inspect"));
+    assert!(stdout.contains("ams"));
+    assert!(stdout.contains("schedule"));
 }
 
 #[test]
 fn test_add_command_help() {
     let output = Command::new("cargo")
-        .args(&["run", "--", "add", "--help"])
+        .args(["run", "--", "add", "--help"])
         .output()
         .expect("Failed to execute command");
 
@@ -218,7 +227,7 @@ fn test_add_command_help() {
 #[test]
 fn test_list_command_help() {
     let output = Command::new("cargo")
-        .args(&["run", "--", "list", "--help"])
+        .args(["run", "--", "list", "--help"])
         .output()
         .expect("Failed to execute command");
 
@@ -231,7 +240,7 @@ fn test_list_command_help() {
 #[test]
 fn test_remove_command_help() {
     let output = Command::new("cargo")
-        .args(&["run", "--", "remove", "--help"])
+        .args(["run", "--", "remove", "--help"])
         .output()
         .expect("Failed to execute command");
 
@@ -244,7 +253,7 @@ fn test_remove_command_help() {
 #[test]
 fn test_set_default_command_help() {
     let output = Command::new("cargo")
-        .args(&["run", "--", "set-default", "--help"])
+        .args(["run", "--", "set-default", "--help"])
         .output()
         .expect("Failed to execute command");
 
@@ -260,7 +269,7 @@ fn test_list_empty_printers() {
     let temp_dir = tempdir().expect("Failed to create temp dir");
 
     let output = Command::new("cargo")
-        .args(&["run", "--", "list"])
+        .args(["run", "--", "list"])
         .env("PULSEPRINT_TEST_CONFIG_DIR", temp_dir.path())
         .output()
         .expect("Failed to execute command");
@@ -271,10 +280,112 @@ fn test_list_empty_printers() {
     assert!(stdout.contains("No printers configured"));
 }
 
+#[test]
+fn test_profiles_keep_separate_printers() {
+    let temp_dir = tempdir().expect("Failed to create temp dir");
+    let run = |args: &[&str], profile_env: Option<&str>| {
+        let mut command = Command::new("cargo");
+        command
+            .args(["run", "--"])
+            .args(args)
+            .env("PULSEPRINT_TEST_CONFIG_DIR", temp_dir.path())
+            .env_remove("PULSEPRINT_PROFILE");
+        if let Some(profile) = profile_env {
+            command.env("PULSEPRINT_PROFILE", profile);
+        }
+        command.output().expect("Failed to execute command")
+    };
+
+    let output = run(
+        &[
+            "--profile",
+            "office",
+            "add",
+            "--name",
+            "office-x1c",
+            "--ip",
+            "192.168.1.50",
+            "--device-id",
+            "01S00A000000000",
+            "--access-code",
+            "12345678",
+        ],
+        None,
+    );
+    assert!(output.status.success());
+    assert!(temp_dir.path().join("profiles/office/config.toml").exists());
+
+    // The default profile is untouched
+    let stdout = String::from_utf8(run(&["list"], None).stdout).unwrap();
+    assert!(stdout.contains("No printers configured"));
+
+    let stdout = String::from_utf8(run(&["list"], Some("office")).stdout).unwrap();
+    assert!(stdout.contains("office-x1c"));
+
+    let stdout = String::from_utf8(run(&["--profile", "office", "profiles"], None).stdout).unwrap();
+    assert!(stdout.contains("office (active)"));
+
+    let output = run(&["--profile", "../etc", "list"], None);
+    assert_eq!(output.status.code(), Some(11));
+}
+
+#[test]
+fn test_config_flag_and_env() {
+    let temp_dir = tempdir().expect("Failed to create temp dir");
+    let config_path = temp_dir.path().join("ci").join("printers.toml");
+    let config_arg = config_path.to_str().unwrap();
+
+    let output = Command::new("cargo")
+        .args([
+            "run",
+            "--",
+            "--config",
+            config_arg,
+            "add",
+            "--name",
+            "ci-p1s",
+            "--ip",
+            "192.168.1.60",
+            "--device-id",
+            "01P00A000000000",
+            "--access-code",
+            "12345678",
+        ])
+        .env("PULSEPRINT_TEST_CONFIG_DIR", temp_dir.path())
+        .output()
+        .expect("Failed to execute command");
+    assert!(output.status.success());
+    assert!(config_path.exists());
+    assert!(!temp_dir.path().join("config.toml").exists());
+
+    let output = Command::new("cargo")
+        .args(["run", "--", "list"])
+        .env("PULSEPRINT_TEST_CONFIG_DIR", temp_dir.path())
+        .env("PULSEPRINT_CONFIG", &config_path)
+        .output()
+        .expect("Failed to execute command");
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("ci-p1s"));
+
+    let output = Command::new("cargo")
+        .args([
+            "run",
+            "--",
+            "--config",
+            config_arg,
+            "--profile",
+            "home",
+            "list",
+        ])
+        .output()
+        .expect("Failed to execute command");
+    assert_eq!(output.status.code(), Some(2));
+}
+
 #[test]
 fn test_add_command_validation_invalid_ip() {
     let output = Command::new("cargo")
-        .args(&[
+        .args([
             "run",
             "--",
             "add",
@@ -299,7 +410,7 @@ fn test_add_command_validation_invalid_ip() {
 #[test]
 fn test_add_command_validation_invalid_access_code() {
     let output = Command::new("cargo")
-        .args(&[
+        .args([
             "run",
             "--",
             "add",
@@ -324,7 +435,7 @@ fn test_add_command_validation_invalid_access_code() {
 #[test]
 fn test_add_command_missing_arguments() {
     let output = Command::new("cargo")
-        .args(&["run", "--", "add", "--name", "test"])
+        .args(["run", "--", "add", "--name", "test"])
         .output()
         .expect("Failed to execute command");
 
@@ -338,7 +449,7 @@ fn test_add_command_missing_arguments() {
 fn test_remove_nonexistent_printer() {
     let temp_dir = tempdir().expect("Failed to create temp dir");
     let output = Command::new("cargo")
-        .args(&["run", "--", "remove", "nonexistent"])
+        .args(["run", "--", "remove", "nonexistent"])
         .env("PULSEPRINT_TEST_CONFIG_DIR", temp_dir.path())
         .output()
         .expect("Failed to execute command");
@@ -353,7 +464,7 @@ fn test_remove_nonexistent_printer() {
 fn test_set_default_nonexistent_printer() {
     let temp_dir = tempdir().expect("Failed to create temp dir");
     let output = Command::new("cargo")
-        .args(&["run", "--", "set-default", "nonexistent"])
+        .args(["run", "--", "set-default", "nonexistent"])
         .env("PULSEPRINT_TEST_CONFIG_DIR", temp_dir.path())
         .output()
         .expect("Failed to execute command");
@@ -363,3 +474,916 @@ fn test_set_default_nonexistent_printer() {
     let stderr = String::from_utf8(output.stderr).expect("Invalid UTF-8");
     assert!(stderr.contains("Printer not found"));
 }
+
+#[test]
+fn test_record_command_help() {
+    let output = Command::new("cargo")
+        .args(["run", "--", "record", "--help"])
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).expect("Invalid UTF-8");
+    assert!(stdout.contains("Record raw MQTT traffic"));
+    assert!(stdout.contains("--name"));
+    assert!(stdout.contains("--out"));
+}
+
+#[test]
+fn test_replay_recorded_session() {
+    let temp_dir = tempdir().expect("Failed to create temp dir");
+    let session_path = temp_dir.path().join("session.ndjson");
+    std::fs::write(
+        &session_path,
+        concat!(
+            r#"{"timestamp_ms":1000,"topic":"device/01S00A000000000/report","payload":"{\"print\":{\"command\":\"push_status\",\"percent\":45,\"nozzle_temper\":220.0}}"}"#,
+            "\n",
+            r#"{"timestamp_ms":1500,"topic":"device/01S00A000000000/report","payload":"{\"pushing\":{\"command\":\"pushall\"}}"}"#,
+            "\n",
+        ),
+    )
+    .expect("Failed to write session file");
+
+    let output = Command::new("cargo")
+        .args(["run", "--", "replay"])
+        .arg(&session_path)
+        .args(["--speed", "100x"])
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).expect("Invalid UTF-8");
+    assert!(stdout.contains("Replaying 2 messages"));
+    assert!(stdout.contains("Progress: 45%"));
+    assert!(stdout.contains("Received complete printer status"));
+}
+
+#[test]
+fn test_simulate_command_help() {
+    let output = Command::new("cargo")
+        .args(["run", "--", "simulate", "--help"])
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).expect("Invalid UTF-8");
+    assert!(stdout.contains("Simulate a Bambu Labs printer"));
+    assert!(stdout.contains("--broker-host"));
+    assert!(stdout.contains("--broker-port"));
+    assert!(stdout.contains("--interval-ms"));
+}
+
+#[test]
+fn test_parse_check_reports_unknown_fields() {
+    let temp_dir = tempdir().expect("Failed to create temp dir");
+    std::fs::write(
+        temp_dir.path().join("status.json"),
+        r#"{"print": {"command": "push_status", "lights_report": [{"node": "chamber_light", "mode": "on"}]}}"#,
+    )
+    .expect("Failed to write payload");
+    std::fs::write(temp_dir.path().join("broken.json"), "{ not json")
+        .expect("Failed to write payload");
+
+    let output = Command::new("cargo")
+        .args(["run", "--", "parse-check"])
+        .arg(temp_dir.path())
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).expect("Invalid UTF-8");
+    assert!(stdout.contains("Payloads: 2"));
+    assert!(stdout.contains("Failed: 1"));
+    assert!(stdout.contains("print.lights_report: 1"));
+    assert!(stdout.contains("broken.json"));
+}
+
+#[test]
+fn test_replay_no_emoji_output_is_ascii() {
+    let temp_dir = tempdir().expect("Failed to create temp dir");
+    let session_path = temp_dir.path().join("session.ndjson");
+    std::fs::write(
+        &session_path,
+        concat!(
+            r#"{"timestamp_ms":1000,"topic":"device/01S00A000000000/report","payload":"{\"print\":{\"command\":\"push_status\",\"state\":\"printing\",\"percent\":45,\"nozzle_temper\":220.0,\"layer_num\":3}}"}"#,
+            "\n",
+        ),
+    )
+    .expect("Failed to write session file");
+
+    let output = Command::new("cargo")
+        .args(["run", "--", "--no-emoji", "replay"])
+        .arg(&session_path)
+        .env("PULSEPRINT_TEST_CONFIG_DIR", temp_dir.path())
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).expect("Invalid UTF-8");
+    assert!(
+        stdout.is_ascii(),
+        "Expected ASCII-only output, got: {stdout}"
+    );
+    assert!(stdout.contains("[PRINTING] Print Status: Printing"));
+    assert!(stdout.contains("Nozzle: 220.0C"));
+}
+
+#[test]
+fn test_monitor_duration_without_status_exits_with_connection_code() {
+    let temp_dir = tempdir().expect("Failed to create temp dir");
+
+    // TEST-NET-1 address: never reachable, so no status is ever received
+    let output = Command::new("cargo")
+        .args([
+            "run",
+            "--",
+            "monitor",
+            "--ip",
+            "192.0.2.1",
+            "--device-id",
+            "01S00A000000000",
+            "--access-code",
+            "12345678",
+            "--duration",
+            "2s",
+        ])
+        .env("PULSEPRINT_TEST_CONFIG_DIR", temp_dir.path())
+        .output()
+        .expect("Failed to execute command");
+
+    assert_eq!(output.status.code(), Some(10));
+
+    let stdout = String::from_utf8(output.stdout).expect("Invalid UTF-8");
+    assert!(stdout.contains("Monitoring duration elapsed"));
+}
+
+#[test]
+fn test_completions_include_printer_names() {
+    let temp_dir = tempdir().expect("Failed to create temp dir");
+
+    let add = Command::new("cargo")
+        .args([
+            "run",
+            "--",
+            "add",
+            "--name",
+            "workshop-x1c",
+            "--ip",
+            "192.0.2.10",
+            "--device-id",
+            "01S00A000000000",
+            "--access-code",
+            "12345678",
+        ])
+        .env("PULSEPRINT_TEST_CONFIG_DIR", temp_dir.path())
+        .output()
+        .expect("Failed to execute command");
+    assert!(add.status.success());
+
+    let output = Command::new("cargo")
+        .args(["run", "--", "completions", "bash"])
+        .env("PULSEPRINT_TEST_CONFIG_DIR", temp_dir.path())
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).expect("Invalid UTF-8");
+    assert!(stdout.contains("_pulseprint-cli"));
+    assert!(stdout.contains("workshop-x1c"));
+}
+
+#[test]
+fn test_status_no_config_exit_code() {
+    let temp_dir = tempdir().expect("Failed to create temp dir");
+
+    let output = Command::new("cargo")
+        .args(["run", "--", "status"])
+        .env("PULSEPRINT_TEST_CONFIG_DIR", temp_dir.path())
+        .output()
+        .expect("Failed to execute command");
+
+    assert_eq!(output.status.code(), Some(11));
+
+    let stderr = String::from_utf8(output.stderr).expect("Invalid UTF-8");
+    assert!(stderr.contains("No printers configured"));
+}
+
+#[test]
+fn test_check_unknown_printer() {
+    let temp_dir = tempdir().expect("Failed to create temp dir");
+
+    let output = Command::new("cargo")
+        .args(["run", "--", "check", "missing"])
+        .env("PULSEPRINT_TEST_CONFIG_DIR", temp_dir.path())
+        .output()
+        .expect("Failed to execute command");
+
+    assert_eq!(output.status.code(), Some(3));
+
+    let stdout = String::from_utf8(output.stdout).expect("Invalid UTF-8");
+    assert!(stdout.starts_with("PULSEPRINT UNKNOWN - missing:"));
+    assert_eq!(stdout.lines().count(), 1);
+}
+
+#[test]
+fn test_replay_prints_job_summary() {
+    let temp_dir = tempdir().expect("Failed to create temp dir");
+    let session_path = temp_dir.path().join("session.ndjson");
+    std::fs::write(
+        &session_path,
+        concat!(
+            r#"{"timestamp_ms":1000,"topic":"device/01S00A000000000/report","payload":"{\"print\":{\"command\":\"push_status\",\"gcode_state\":\"RUNNING\",\"subtask_name\":\"cube\",\"percent\":0,\"nozzle_temper\":220.0}}"}"#,
+            "\n",
+            r#"{"timestamp_ms":1100,"topic":"device/01S00A000000000/report","payload":"{\"print\":{\"command\":\"push_status\",\"gcode_state\":\"PAUSE\"}}"}"#,
+            "\n",
+            r#"{"timestamp_ms":1200,"topic":"device/01S00A000000000/report","payload":"{\"print\":{\"command\":\"push_status\",\"gcode_state\":\"FINISH\",\"percent\":100}}"}"#,
+            "\n",
+        ),
+    )
+    .expect("Failed to write session file");
+
+    let output = Command::new("cargo")
+        .args(["run", "--", "--no-emoji", "replay"])
+        .arg(&session_path)
+        .args(["--speed", "100x"])
+        .env("PULSEPRINT_TEST_CONFIG_DIR", temp_dir.path())
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).expect("Invalid UTF-8");
+    assert!(stdout.contains("Job 'cube' finished in 0m"));
+    assert!(stdout.contains("peak nozzle 220.0C"));
+    assert!(stdout.contains("| 1 pause"));
+}
+
+#[test]
+fn test_list_live_without_daemon() {
+    let temp_dir = tempdir().expect("Failed to create temp dir");
+
+    let output = Command::new("cargo")
+        .args(["run", "--", "list", "--live"])
+        .env("PULSEPRINT_TEST_CONFIG_DIR", temp_dir.path())
+        .output()
+        .expect("Failed to execute command");
+
+    assert_eq!(output.status.code(), Some(1));
+
+    let stderr = String::from_utf8(output.stderr).expect("Invalid UTF-8");
+    assert!(stderr.contains("Daemon is not running"));
+}
+
+#[test]
+fn test_pause_no_config_exit_code() {
+    let temp_dir = tempdir().expect("Failed to create temp dir");
+
+    let output = Command::new("cargo")
+        .args(["run", "--", "pause"])
+        .env("PULSEPRINT_TEST_CONFIG_DIR", temp_dir.path())
+        .output()
+        .expect("Failed to execute command");
+
+    assert_eq!(output.status.code(), Some(11));
+}
+
+#[test]
+fn test_snapshot_no_config_exit_code() {
+    let temp_dir = tempdir().expect("Failed to create temp dir");
+    let out = temp_dir.path().join("frame.jpg");
+
+    let output = Command::new("cargo")
+        .args(["run", "--", "snapshot", "--out"])
+        .arg(&out)
+        .env("PULSEPRINT_TEST_CONFIG_DIR", temp_dir.path())
+        .output()
+        .expect("Failed to execute command");
+
+    assert_eq!(output.status.code(), Some(11));
+    assert!(!out.exists());
+
+    // The printer name is positional
+    let output = Command::new("cargo")
+        .args(["run", "--", "snapshot", "x1c", "--out"])
+        .arg(&out)
+        .env("PULSEPRINT_TEST_CONFIG_DIR", temp_dir.path())
+        .output()
+        .expect("Failed to execute command");
+
+    assert_eq!(output.status.code(), Some(11));
+    assert!(!out.exists());
+}
+
+#[test]
+fn test_print_start_no_config_exit_code() {
+    let temp_dir = tempdir().expect("Failed to create temp dir");
+
+    let output = Command::new("cargo")
+        .args([
+            "run",
+            "--",
+            "print",
+            "start",
+            "benchy.gcode.3mf",
+            "--ams-mapping",
+            "0,2",
+        ])
+        .env("PULSEPRINT_TEST_CONFIG_DIR", temp_dir.path())
+        .output()
+        .expect("Failed to execute command");
+
+    assert_eq!(output.status.code(), Some(11));
+}
+
+#[test]
+fn test_files_no_config_exit_code() {
+    let temp_dir = tempdir().expect("Failed to create temp dir");
+
+    let output = Command::new("cargo")
+        .args(["run", "--", "files", "list"])
+        .env("PULSEPRINT_TEST_CONFIG_DIR", temp_dir.path())
+        .output()
+        .expect("Failed to execute command");
+
+    assert_eq!(output.status.code(), Some(11));
+}
+
+#[test]
+fn test_ams_info_no_config_exit_code() {
+    let temp_dir = tempdir().expect("Failed to create temp dir");
+
+    let output = Command::new("cargo")
+        .args(["run", "--", "ams", "info"])
+        .env("PULSEPRINT_TEST_CONFIG_DIR", temp_dir.path())
+        .output()
+        .expect("Failed to execute command");
+
+    assert_eq!(output.status.code(), Some(11));
+}
+
+#[test]
+fn test_info_no_config_exit_code() {
+    let temp_dir = tempdir().expect("Failed to create temp dir");
+
+    let output = Command::new("cargo")
+        .args(["run", "--", "info", "x1c", "--output", "json"])
+        .env("PULSEPRINT_TEST_CONFIG_DIR", temp_dir.path())
+        .output()
+        .expect("Failed to execute command");
+
+    assert_eq!(output.status.code(), Some(11));
+    assert!(output.stdout.is_empty());
+}
+
+#[test]
+fn test_publish_checks_payload() {
+    let temp_dir = tempdir().expect("Failed to create temp dir");
+
+    let output = Command::new("cargo")
+        .args(["run", "--", "publish", "x1c", "--json", "{not json"])
+        .env("PULSEPRINT_TEST_CONFIG_DIR", temp_dir.path())
+        .output()
+        .expect("Failed to execute command");
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8(output.stderr).expect("Invalid UTF-8");
+    assert!(stderr.contains("Invalid JSON payload"));
+
+    let payload = temp_dir.path().join("pause.json");
+    std::fs::write(&payload, r#"{"print": {"command": "pause"}}"#).unwrap();
+    let output = Command::new("cargo")
+        .args(["run", "--", "publish", "x1c", "--file"])
+        .arg(&payload)
+        .env("PULSEPRINT_TEST_CONFIG_DIR", temp_dir.path())
+        .output()
+        .expect("Failed to execute command");
+    assert_eq!(output.status.code(), Some(11));
+}
+
+#[test]
+fn test_schedule_exit_codes() {
+    let temp_dir = tempdir().expect("Failed to create temp dir");
+
+    let output = Command::new("cargo")
+        .args(["run", "--", "schedule", "--at", "06:30", "--file", "a.3mf"])
+        .env("PULSEPRINT_TEST_CONFIG_DIR", temp_dir.path())
+        .output()
+        .expect("Failed to execute command");
+    assert_eq!(output.status.code(), Some(11));
+
+    let output = Command::new("cargo")
+        .args([
+            "run", "--", "schedule", "x1c", "--at", "6pm", "--file", "a.3mf",
+        ])
+        .env("PULSEPRINT_TEST_CONFIG_DIR", temp_dir.path())
+        .output()
+        .expect("Failed to execute command");
+    assert_eq!(output.status.code(), Some(2));
+
+    // Scheduling needs a running daemon
+    let output = Command::new("cargo")
+        .args([
+            "run", "--", "schedule", "x1c", "--at", "06:30", "--file", "a.3mf",
+        ])
+        .env("PULSEPRINT_TEST_CONFIG_DIR", temp_dir.path())
+        .output()
+        .expect("Failed to execute command");
+    assert_eq!(output.status.code(), Some(10));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("daemon start"));
+}
+
+#[test]
+fn test_inspect_project() {
+    use std::io::Write;
+
+    let temp_dir = tempdir().expect("Failed to create temp dir");
+    let project = temp_dir.path().join("benchy.gcode.3mf");
+    let mut archive = zip::ZipWriter::new(std::fs::File::create(&project).unwrap());
+    archive
+        .start_file(
+            "Metadata/slice_info.config",
+            zip::write::SimpleFileOptions::default(),
+        )
+        .unwrap();
+    archive
+        .write_all(
+            br##"<config><plate><metadata key="index" value="1"/><metadata key="prediction" value="3723"/><object identify_id="127" name="Benchy"/><filament id="1" type="PLA" color="#FFFFFF" used_m="4.12" used_g="12.30"/></plate></config>"##,
+        )
+        .unwrap();
+    archive
+        .start_file(
+            "Metadata/plate_1.png",
+            zip::write::SimpleFileOptions::default(),
+        )
+        .unwrap();
+    archive.write_all(b"png").unwrap();
+    archive.finish().unwrap();
+
+    let thumbnails = temp_dir.path().join("thumbs");
+    let output = Command::new("cargo")
+        .args(["run", "--", "--no-emoji", "inspect"])
+        .arg(&project)
+        .arg("--thumbnails")
+        .arg(&thumbnails)
+        .env("PULSEPRINT_TEST_CONFIG_DIR", temp_dir.path())
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).expect("Invalid UTF-8");
+    assert!(stdout.contains("benchy - plate 1"));
+    assert!(stdout.contains("1h 2m"));
+    assert!(stdout.contains("#1  PLA  #FFFFFF  4.12 m  12.3 g"));
+    assert!(stdout.contains("1 (ids 127)"));
+    assert_eq!(
+        std::fs::read(thumbnails.join("benchy_plate_1.png")).unwrap(),
+        b"png"
+    );
+    assert!(
+        temp_dir
+            .path()
+            .join("projects")
+            .join("benchy.json")
+            .exists()
+    );
+}
+
+#[test]
+fn test_skip_objects_requires_ids_or_list() {
+    let output = Command::new("cargo")
+        .args(["run", "--", "skip-objects"])
+        .output()
+        .expect("Failed to execute command");
+    assert_eq!(output.status.code(), Some(2));
+
+    let temp_dir = tempdir().expect("Failed to create temp dir");
+    let output = Command::new("cargo")
+        .args(["run", "--", "skip-objects", "--ids", "3,7"])
+        .env("PULSEPRINT_TEST_CONFIG_DIR", temp_dir.path())
+        .output()
+        .expect("Failed to execute command");
+    assert_eq!(output.status.code(), Some(11));
+}
+
+#[test]
+fn test_timelapse_no_config_exit_code() {
+    let temp_dir = tempdir().expect("Failed to create temp dir");
+
+    let output = Command::new("cargo")
+        .args(["run", "--", "timelapse", "on"])
+        .env("PULSEPRINT_TEST_CONFIG_DIR", temp_dir.path())
+        .output()
+        .expect("Failed to execute command");
+
+    assert_eq!(output.status.code(), Some(11));
+}
+
+#[test]
+fn test_service_install_print() {
+    let temp_dir = tempdir().expect("Failed to create temp dir");
+
+    let output = Command::new("cargo")
+        .args(["run", "--", "--no-emoji", "service", "install", "--print"])
+        .env("PULSEPRINT_TEST_CONFIG_DIR", temp_dir.path())
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).expect("Invalid UTF-8");
+    assert!(stdout.contains("Type=notify"));
+    assert!(stdout.contains("--no-emoji daemon start"));
+    assert!(stdout.contains(&format!(
+        "Environment=PULSEPRINT_TEST_CONFIG_DIR={}",
+        temp_dir.path().display()
+    )));
+}
+
+#[test]
+fn test_config_validate() {
+    let temp_dir = tempdir().expect("Failed to create temp dir");
+    let config_path = temp_dir.path().join("config.toml");
+
+    std::fs::write(
+        &config_path,
+        r#"
+[printers.x1c]
+ip = "192.168.1.100"
+device_id = "01S00A000000000"
+access_code = "12345678"
+"#,
+    )
+    .unwrap();
+    let output = Command::new("cargo")
+        .args(["run", "--", "config", "validate"])
+        .env("PULSEPRINT_TEST_CONFIG_DIR", temp_dir.path())
+        .output()
+        .expect("Failed to execute command");
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("is valid (1 printers)"));
+
+    std::fs::write(
+        &config_path,
+        r#"
+default_printer = "p1s"
+
+[printers.x1c]
+ip = "printer.local"
+device_id = "01S00A000000000"
+access_code = "12345678"
+"#,
+    )
+    .unwrap();
+    let output = Command::new("cargo")
+        .args(["run", "--", "config", "validate"])
+        .env("PULSEPRINT_TEST_CONFIG_DIR", temp_dir.path())
+        .output()
+        .expect("Failed to execute command");
+    assert_eq!(output.status.code(), Some(11));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("printers.x1c.ip: Invalid IP address: printer.local"));
+    assert!(stdout.contains("default_printer"));
+}
+
+#[test]
+fn test_env_printer_without_config_file() {
+    let temp_dir = tempdir().expect("Failed to create temp dir");
+
+    let output = Command::new("cargo")
+        .args(["run", "--", "list"])
+        .env("PULSEPRINT_TEST_CONFIG_DIR", temp_dir.path())
+        .env("PULSEPRINT_PRINTER_NAME", "container")
+        .env("PULSEPRINT_PRINTER_IP", "192.168.1.90")
+        .env("PULSEPRINT_DEVICE_ID", "01P00A000000000")
+        .env("PULSEPRINT_ACCESS_CODE", "12345678")
+        .output()
+        .expect("Failed to execute command");
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("container (default)"));
+    assert!(stdout.contains("IP: 192.168.1.90"));
+
+    // Nothing from the environment is written to disk
+    assert!(!temp_dir.path().join("config.toml").exists());
+}
+
+#[test]
+fn test_setup_cancelled_without_input() {
+    let temp_dir = tempdir().expect("Failed to create temp dir");
+
+    let output = Command::new("cargo")
+        .args(["run", "--", "setup"])
+        .env("PULSEPRINT_TEST_CONFIG_DIR", temp_dir.path())
+        .stdin(std::process::Stdio::null())
+        .output()
+        .expect("Failed to execute command");
+    assert_eq!(output.status.code(), Some(11));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("setup cancelled"));
+    assert!(!temp_dir.path().join("config.toml").exists());
+}
+
+#[test]
+fn test_printer_name_suggestion() {
+    let temp_dir = tempdir().expect("Failed to create temp dir");
+
+    let output = Command::new("cargo")
+        .args([
+            "run",
+            "--",
+            "add",
+            "--name",
+            "voron",
+            "--ip",
+            "192.168.1.70",
+            "--device-id",
+            "01P00A000000000",
+            "--access-code",
+            "12345678",
+        ])
+        .env("PULSEPRINT_TEST_CONFIG_DIR", temp_dir.path())
+        .output()
+        .expect("Failed to execute command");
+    assert!(output.status.success());
+
+    let output = Command::new("cargo")
+        .args(["run", "--", "set-default", "voroon"])
+        .env("PULSEPRINT_TEST_CONFIG_DIR", temp_dir.path())
+        .output()
+        .expect("Failed to execute command");
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("did you mean 'voron'?"));
+}
+
+#[test]
+fn test_clone_printer() {
+    let temp_dir = tempdir().expect("Failed to create temp dir");
+    std::fs::write(
+        temp_dir.path().join("config.toml"),
+        r#"
+[printers.farm-1]
+ip = "192.168.1.61"
+device_id = "01P00A000000001"
+access_code = "12345678"
+port = 8884
+model = "P1S"
+"#,
+    )
+    .unwrap();
+
+    let output = Command::new("cargo")
+        .args([
+            "run",
+            "--",
+            "clone",
+            "farm-1",
+            "farm-2",
+            "--ip",
+            "192.168.1.62",
+            "--device-id",
+            "01P00A000000002",
+        ])
+        .env("PULSEPRINT_TEST_CONFIG_DIR", temp_dir.path())
+        .output()
+        .expect("Failed to execute command");
+    assert!(output.status.success());
+
+    let output = Command::new("cargo")
+        .args(["run", "--", "list"])
+        .env("PULSEPRINT_TEST_CONFIG_DIR", temp_dir.path())
+        .output()
+        .expect("Failed to execute command");
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let farm_2 = stdout.split("farm-2").nth(1).unwrap();
+    assert!(farm_2.contains("IP: 192.168.1.62"));
+    assert!(farm_2.contains("Port: 8884"));
+    assert!(farm_2.contains("Model: P1S"));
+
+    // The new name must be free
+    let output = Command::new("cargo")
+        .args([
+            "run",
+            "--",
+            "clone",
+            "farm-1",
+            "farm-2",
+            "--ip",
+            "192.168.1.63",
+            "--device-id",
+            "01P00A000000003",
+        ])
+        .env("PULSEPRINT_TEST_CONFIG_DIR", temp_dir.path())
+        .output()
+        .expect("Failed to execute command");
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_list_shows_printer_details() {
+    let temp_dir = tempdir().expect("Failed to create temp dir");
+    std::fs::write(
+        temp_dir.path().join("config.toml"),
+        r#"
+[printers.x1c]
+ip = "192.168.1.100"
+device_id = "01S00A000000000"
+access_code = "12345678"
+location = "rack-B/slot-3"
+notes = "CF filaments only"
+
+[printers.x1c.metadata]
+owner = "lab"
+"#,
+    )
+    .unwrap();
+
+    let output = Command::new("cargo")
+        .args(["run", "--", "list"])
+        .env("PULSEPRINT_TEST_CONFIG_DIR", temp_dir.path())
+        .output()
+        .expect("Failed to execute command");
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("   Location: rack-B/slot-3"));
+    assert!(stdout.contains("   Notes: CF filaments only"));
+    assert!(stdout.contains("   owner: lab"));
+}
+
+#[test]
+fn test_monitor_several_printers() {
+    let temp_dir = tempdir().expect("Failed to create temp dir");
+    // TEST-NET-1 addresses: never reachable, so no status is ever received
+    std::fs::write(
+        temp_dir.path().join("config.toml"),
+        r#"
+[printers.farm-1]
+ip = "192.0.2.1"
+device_id = "01P00A000000001"
+access_code = "12345678"
+
+[printers.farm-2]
+ip = "192.0.2.2"
+device_id = "01P00A000000002"
+access_code = "12345678"
+"#,
+    )
+    .unwrap();
+
+    let monitor = |args: &[&str]| {
+        Command::new("cargo")
+            .args(["run", "--", "monitor"])
+            .args(args)
+            .env("PULSEPRINT_TEST_CONFIG_DIR", temp_dir.path())
+            .output()
+            .expect("Failed to execute command")
+    };
+
+    let output = monitor(&["--name", "farm-1,farm-2", "--json", "--duration", "2s"]);
+    assert_eq!(output.status.code(), Some(10));
+    // Nothing but status objects goes to stdout in JSON mode
+    assert!(output.stdout.is_empty());
+
+    let output = monitor(&["--name", "farm-1", "--name", "farm-3", "--once"]);
+    assert_eq!(output.status.code(), Some(11));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("Printer 'farm-3' not found"));
+
+    let output = monitor(&["--name", "farm-1,farm-2", "--ip", "192.0.2.3"]);
+    assert_eq!(output.status.code(), Some(11));
+}
+
+/// Plain MQTT 3.1.1 broker on a local port that accepts every client, acknowledges its
+/// subscription and then sends `payload` on the printer's report topic
+fn status_broker(device_id: &str, payload: &str) -> u16 {
+    use std::io::{Read, Write};
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let topic = format!("device/{device_id}/report");
+    let payload = payload.to_string();
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(mut stream) = stream else {
+                return;
+            };
+            let (topic, payload) = (topic.clone(), payload.clone());
+            std::thread::spawn(move || {
+                loop {
+                    let mut header = [0u8; 1];
+                    if stream.read_exact(&mut header).is_err() {
+                        return;
+                    }
+                    let (mut length, mut shift) = (0usize, 0);
+                    loop {
+                        let mut byte = [0u8; 1];
+                        if stream.read_exact(&mut byte).is_err() {
+                            return;
+                        }
+                        length |= usize::from(byte[0] & 0x7f) << shift;
+                        shift += 7;
+                        if byte[0] & 0x80 == 0 {
+                            break;
+                        }
+                    }
+                    let mut body = vec![0u8; length];
+                    if stream.read_exact(&mut body).is_err() {
+                        return;
+                    }
+
+                    let reply = match header[0] >> 4 {
+                        // CONNECT
+                        1 => vec![0x20, 0x02, 0x00, 0x00],
+                        // SUBSCRIBE: granted QoS 0, then the status report
+                        8 => {
+                            let mut reply = vec![0x90, 0x03, body[0], body[1], 0x00];
+                            let mut publish = (topic.len() as u16).to_be_bytes().to_vec();
+                            publish.extend(topic.as_bytes());
+                            publish.extend(payload.as_bytes());
+                            reply.push(0x30);
+                            let mut remaining = publish.len();
+                            loop {
+                                let byte = (remaining % 128) as u8;
+                                remaining /= 128;
+                                if remaining == 0 {
+                                    reply.push(byte);
+                                    break;
+                                }
+                                reply.push(byte | 0x80);
+                            }
+                            reply.extend(publish);
+                            reply
+                        }
+                        // PINGREQ
+                        12 => vec![0xd0, 0x00],
+                        _ => continue,
+                    };
+                    if stream.write_all(&reply).is_err() {
+                        return;
+                    }
+                }
+            });
+        }
+    });
+    port
+}
+
+#[test]
+fn test_monitor_json_still_publishes_to_sinks() {
+    let temp_dir = tempdir().expect("Failed to create temp dir");
+    let port = status_broker(
+        "01P00A000000001",
+        r#"{"print": {"command": "push_status", "gcode_state": "IDLE", "percent": 0, "nozzle_temper": 25.0, "bed_temper": 24.0}}"#,
+    );
+    let events = temp_dir.path().join("events.jsonl");
+    std::fs::write(
+        temp_dir.path().join("config.toml"),
+        format!(
+            r#"
+[printers.farm-1]
+ip = "127.0.0.1"
+port = {port}
+use_tls = false
+device_id = "01P00A000000001"
+access_code = "12345678"
+
+[[sinks]]
+type = "json_file"
+path = "{}"
+events = ["status"]
+"#,
+            events.display().to_string().replace('\\', "\\\\")
+        ),
+    )
+    .unwrap();
+
+    let output = Command::new("cargo")
+        .args(["run", "--", "monitor", "--name", "farm-1", "--json"])
+        .args(["--duration", "3s"])
+        .env("PULSEPRINT_TEST_CONFIG_DIR", temp_dir.path())
+        .output()
+        .expect("Failed to execute command");
+    assert_eq!(output.status.code(), Some(0));
+
+    // stdout carries only the status objects
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(!stdout.is_empty());
+    for line in stdout.lines() {
+        let status: serde_json::Value = serde_json::from_str(line).unwrap();
+        assert_eq!(status["name"], "farm-1");
+    }
+
+    let events = std::fs::read_to_string(&events).expect("the sink received no events");
+    let first: serde_json::Value = serde_json::from_str(events.lines().next().unwrap()).unwrap();
+    assert_eq!(first["event"], "status");
+    assert_eq!(first["printer"], "farm-1");
+    assert_eq!(first["print"]["gcode_state"], "IDLE");
+}